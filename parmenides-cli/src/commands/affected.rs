@@ -0,0 +1,505 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::affected::{builtin_strategy, AffectedStrategy};
+use parmenides_lib::cache::LocalCacheStore;
+use parmenides_lib::ci::DiffRange;
+use parmenides_lib::context::Context;
+use parmenides_lib::declarations::TimeoutsDeclaration;
+use parmenides_lib::diff_engine::{
+    CompositeDiffEngine, DiffEngine, GitDiffEngine, ManifestDiffEngine,
+};
+use parmenides_lib::errors::BuildWorkspaceError;
+use parmenides_lib::last_green::{LastGreen, DEFAULT_PIPELINE};
+use parmenides_lib::path_roots::{PathRoots, RemappedDiffEngine};
+use parmenides_lib::process::SystemProcessRunner;
+use parmenides_lib::project::ProjectId;
+use parmenides_lib::sort::sort_key;
+use parmenides_lib::workspace::{DependencyKind, Workspace};
+use parmenides_lib::{compute_affected, compute_merge_affected};
+
+use crate::errors::CliError;
+use crate::load::{build_workspace, find_repository, LoadedDeclaration};
+
+/// The revisions to compute the affected projects between.
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The revision to diff from. Without it, the revisions are inferred from the variables
+    /// of GitHub Actions, GitLab CI, Buildkite or CircleCI, and locally default to the merge
+    /// base with `origin/main`. With `--manifest`, defaults to `main`.
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Diff from the commit of the last green run of this pipeline, recorded with
+    /// `parmenides run --record-green`, instead of a fixed branch. Without a recorded run, the
+    /// revisions are inferred as without `--from`.
+    #[arg(
+        long,
+        value_name = "PIPELINE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_PIPELINE,
+        conflicts_with_all = ["from", "manifest"]
+    )]
+    pub since_green: Option<String>,
+
+    /// The revision to diff to. Without it, uncommitted changes are included.
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Diff from the merge base of the revisions, like `git diff from...to`.
+    #[arg(long)]
+    pub merge_base: bool,
+
+    /// The git repository. Defaults to the one containing the workspace.
+    #[arg(long)]
+    pub repository: Option<PathBuf>,
+
+    /// In a shallow clone, such as the depth-1 checkout of a CI runner, fetch history from
+    /// this remote until the revisions and their merge base are found, instead of failing.
+    #[arg(
+        long,
+        value_name = "REMOTE",
+        num_args = 0..=1,
+        default_missing_value = "origin"
+    )]
+    pub deepen: Option<String>,
+
+    /// Diff the files against the manifests recorded with `parmenides manifest` instead of a
+    /// git repository. The revisions name manifests, and without `--to` the files as they are
+    /// now are compared.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "merge_base",
+            "repository",
+            "untracked",
+            "ignored",
+            "submodules",
+            "scoped",
+            "deepen"
+        ]
+    )]
+    pub manifest: bool,
+
+    /// Also treat the untracked files of the working directory as changed. Only applies
+    /// without `--to`.
+    #[arg(long, conflicts_with = "to")]
+    pub untracked: bool,
+
+    /// Also treat the files ignored by git as changed, e.g. generated sources. Only applies
+    /// without `--to`.
+    #[arg(long, conflicts_with = "to")]
+    pub ignored: bool,
+
+    /// Treat the files changed inside a submodule whose commit changed as changed, instead of
+    /// the submodule directory.
+    #[arg(long)]
+    pub submodules: bool,
+
+    /// Only diff the project directories and the triggers, skipping the rest of the
+    /// repository, e.g. vendored code or assets. Files moved into or out of the workspace are
+    /// seen as added or deleted.
+    #[arg(long)]
+    pub scoped: bool,
+
+    /// Also treat these files as changed, relative to the workspace root, e.g. generated
+    /// files the diff can't see.
+    #[arg(long, value_name = "PATH", value_delimiter = ',')]
+    pub changed: Vec<PathBuf>,
+
+    /// Also propagate changes through soft dependencies, e.g. to examples and dev tooling.
+    #[arg(long)]
+    pub soft: bool,
+
+    /// Only propagate changes through dependencies of these kinds, e.g. `runtime,dev` for
+    /// tests. Defaults to every kind.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub kind: Vec<KindArg>,
+
+    /// Mark the affected projects with this built-in strategy instead of the declared one,
+    /// e.g. `changed_only` to leave out the dependents.
+    #[arg(long)]
+    pub strategy: Option<String>,
+}
+
+/// The kinds of dependencies, as command line values.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KindArg {
+    Runtime,
+    Dev,
+    Build,
+    Optional,
+}
+
+impl DiffArgs {
+    /// Returns the revisions to diff, inferring the ones not given, see
+    /// [`DiffRange::detect`].
+    ///
+    /// # Returns
+    /// - `Ok(DiffRange)`: The revisions.
+    /// - `Err(CliError)`: If the last green run of the workspace at `root` could not be read.
+    pub fn range(&self, root: &Path) -> Result<DiffRange, CliError> {
+        if let Some(pipeline) = &self.since_green {
+            let store = LocalCacheStore::in_workspace(root);
+
+            match LastGreen::new(&store).get(pipeline)? {
+                // Diffed from the merge base, so a branch forked before the run isn't
+                // diffed against the changes merged since.
+                Some(commit) => {
+                    return Ok(DiffRange {
+                        from: commit,
+                        to: self.to.clone(),
+                        merge_base: true,
+                    })
+                }
+                None => eprintln!("warning: no green run of {pipeline} is recorded"),
+            }
+        }
+
+        Ok(match &self.from {
+            Some(from) => DiffRange {
+                from: from.clone(),
+                to: self.to.clone(),
+                merge_base: self.merge_base,
+            },
+            None if self.manifest => DiffRange {
+                from: "main".to_owned(),
+                to: self.to.clone(),
+                merge_base: false,
+            },
+            None => {
+                let detected = DiffRange::detect();
+
+                DiffRange {
+                    from: detected.from,
+                    to: self.to.clone().or(detected.to),
+                    merge_base: self.merge_base || detected.merge_base,
+                }
+            }
+        })
+    }
+
+    /// Returns the kinds of dependencies changes propagate through.
+    pub fn kinds(&self) -> Vec<DependencyKind> {
+        if self.kind.is_empty() {
+            return DependencyKind::ALL.to_vec();
+        }
+
+        self.kind
+            .iter()
+            .map(|kind| match kind {
+                KindArg::Runtime => DependencyKind::Runtime,
+                KindArg::Dev => DependencyKind::Dev,
+                KindArg::Build => DependencyKind::Build,
+                KindArg::Optional => DependencyKind::Optional,
+            })
+            .collect()
+    }
+
+    /// Returns the strategy given with `--strategy`, if any.
+    ///
+    /// # Returns
+    /// - `Ok(Option<Arc<dyn AffectedStrategy>>)`: The strategy, or `None` to keep the declared one.
+    /// - `Err(CliError)`: If no built-in strategy has the name.
+    pub fn strategy(&self) -> Result<Option<Arc<dyn AffectedStrategy>>, CliError> {
+        let Some(name) = &self.strategy else {
+            return Ok(None);
+        };
+
+        let strategy = builtin_strategy(name)
+            .ok_or_else(|| BuildWorkspaceError::UnknownAffectedStrategy(name.clone()))?;
+
+        Ok(Some(strategy))
+    }
+
+    /// Sets up how changes propagate in `workspace`, with the strategy of [`Self::strategy`].
+    fn configure(&self, workspace: &mut Workspace, strategy: Option<Arc<dyn AffectedStrategy>>) {
+        workspace.set_soft_propagation(self.soft);
+        workspace.set_propagated_kinds(self.kinds());
+
+        if let Some(strategy) = strategy {
+            workspace.set_affected_strategy(strategy);
+        }
+    }
+}
+
+/// Prints the projects affected by the changes between two revisions.
+#[derive(Args, Debug)]
+pub struct AffectedArgs {
+    #[command(flatten)]
+    pub diff: DiffArgs,
+
+    /// Print project paths, relative to the workspace root, instead of identifiers.
+    #[arg(long)]
+    pub paths: bool,
+
+    /// Print why each project is affected.
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Print the projects affected by a past commit instead, e.g. a merge on the main branch.
+    /// The workspace is read from the declaration at that commit, and diffed from its first
+    /// parent.
+    #[arg(
+        long,
+        value_name = "COMMIT",
+        conflicts_with_all = ["from", "since_green", "to", "merge_base", "manifest"]
+    )]
+    pub at: Option<String>,
+}
+
+/// Marks the projects of `workspace` affected by the changes between the revisions of `args`.
+///
+/// # Returns
+/// - `Ok((PathBuf, Vec<ProjectId>))`: The repository or directory diffed, and the affected
+///   projects.
+/// - `Err(CliError)`: If there is no repository or the diff failed.
+pub fn mark_affected(
+    args: &DiffArgs,
+    root: &Path,
+    timeouts: &TimeoutsDeclaration,
+    roots: &PathRoots,
+    workspace: &mut Workspace,
+    context: &Context,
+) -> Result<(PathBuf, Vec<ProjectId>), CliError> {
+    let range = args.range(root)?;
+
+    let (path, engine): (PathBuf, Box<dyn DiffEngine>) = if args.manifest {
+        (root.to_path_buf(), Box::new(ManifestDiffEngine::open(root)))
+    } else {
+        let mut engine = open_engine(args, root, timeouts)?.with_merge_base(range.merge_base);
+
+        if args.scoped {
+            engine = engine.with_workspace_scope(workspace);
+        }
+
+        match &args.deepen {
+            Some(remote) if context.is_offline() => {
+                eprintln!("warning: offline, not deepening the clone from {remote}");
+            }
+            Some(remote) => {
+                let runner = SystemProcessRunner::new();
+                engine.deepen(remote, &range.from, range.to.as_deref(), &runner)?;
+            }
+            None => {}
+        }
+
+        (engine.path().to_path_buf(), Box::new(engine))
+    };
+
+    // The repository may be mounted at another root than the declaration's, e.g. in a container.
+    let engine: Box<dyn DiffEngine> = if roots.is_empty() {
+        engine
+    } else {
+        Box::new(RemappedDiffEngine::new(engine, roots.clone()))
+    };
+
+    let engine: Box<dyn DiffEngine> = if args.changed.is_empty() {
+        engine
+    } else {
+        let paths = args.changed.iter().map(|path| root.join(path));
+        Box::new(
+            CompositeDiffEngine::new()
+                .with_engine(engine)
+                .with_paths(paths),
+        )
+    };
+
+    args.configure(workspace, args.strategy()?);
+
+    let affected = compute_affected(
+        workspace,
+        engine.as_ref(),
+        &range.from,
+        range.to.as_deref(),
+        context,
+    )?;
+
+    Ok((path, affected))
+}
+
+/// Opens the repository of `args`, or the one containing `root`.
+pub fn open_engine(
+    args: &DiffArgs,
+    root: &Path,
+    timeouts: &TimeoutsDeclaration,
+) -> Result<GitDiffEngine, CliError> {
+    let repository = match &args.repository {
+        Some(repository) => repository.clone(),
+        None => find_repository(root).ok_or_else(|| CliError::NoRepository(root.to_path_buf()))?,
+    };
+
+    let mut engine = GitDiffEngine::open(&repository)?
+        .with_untracked(args.untracked)
+        .with_ignored(args.ignored)
+        .with_submodules(args.submodules);
+
+    if let Some(timeout) = timeouts.diff() {
+        engine = engine.with_timeout(timeout);
+    }
+
+    Ok(engine)
+}
+
+pub fn run(
+    args: &AffectedArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+    let timeouts = declaration.timeouts;
+    let roots = PathRoots::from_declaration(&declaration.path_roots);
+
+    let (workspace, repository, mut affected) = match &args.at {
+        Some(commit) => {
+            let source = source.ok_or_else(|| CliError::NoDeclarationFile(root.clone()))?;
+            let engine = open_engine(&args.diff, &root, &timeouts)?;
+            let strategy = args.diff.strategy()?;
+
+            let configure = |workspace: &mut Workspace| args.diff.configure(workspace, strategy);
+
+            let merge = compute_merge_affected(&engine, commit, &source, configure, context)?;
+
+            (merge.workspace, engine.path().to_path_buf(), merge.affected)
+        }
+        None => {
+            let mut workspace = build_workspace(&root, source.as_deref(), declaration)?;
+            let (repository, affected) = mark_affected(
+                &args.diff,
+                &root,
+                &timeouts,
+                &roots,
+                &mut workspace,
+                context,
+            )?;
+
+            (workspace, repository, affected)
+        }
+    };
+
+    affected.sort_by_cached_key(|id| sort_key(&describe(&workspace, &root, *id, args.paths)));
+
+    if args.explain {
+        writeln!(out, "strategy: {}", workspace.affected_strategy().name())?;
+    }
+
+    for id in affected {
+        let line = describe(&workspace, &root, id, args.paths);
+
+        if !args.explain {
+            writeln!(out, "{line}")?;
+            continue;
+        }
+
+        let Some(reason) = workspace.affected_reason(id) else {
+            writeln!(out, "{line}")?;
+            continue;
+        };
+
+        // A project reached through an implicit dependency is marked, as no file shows it.
+        let chain: Vec<String> = reason
+            .chain
+            .iter()
+            .enumerate()
+            .map(|(index, id)| {
+                let project = describe(&workspace, &root, *id, args.paths);
+                let implicit = index
+                    .checked_sub(1)
+                    .and_then(|previous| workspace.edge(*id, reason.chain[previous]))
+                    .is_some_and(|edge| edge.implicit);
+
+                if implicit {
+                    format!("{project} (implicit)")
+                } else {
+                    project
+                }
+            })
+            .collect();
+
+        // The dependents of a project encapsulating the change weren't marked for it.
+        let stopped = if reason.stopped { " (stops here)" } else { "" };
+
+        writeln!(out, "{line}: {}{stopped}", chain.join(" -> "))?;
+
+        for file in reason.files {
+            writeln!(
+                out,
+                "  {}",
+                file.strip_prefix(&repository).unwrap_or(&file).display()
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn describe(workspace: &Workspace, root: &Path, id: ProjectId, paths: bool) -> String {
+    let Some(project) = workspace.get_project(id) else {
+        return id.to_string();
+    };
+
+    if paths {
+        project
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(project.path())
+            .display()
+            .to_string()
+    } else {
+        project.identifier().to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parmenides_lib::context::Context;
+    use parmenides_lib::diff_engine::ManifestDiffEngine;
+
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, AffectedArgs};
+
+    #[test]
+    pub fn when_a_dependency_changed_should_print_it_and_its_dependents() {
+        let root = TempDir::new("affected");
+
+        for (path, content) in [
+            (
+                "parmenides.toml",
+                "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+                 [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n\n\
+                 [projects.\"apps/docs\"]\nname = \"docs\"\n",
+            ),
+            ("libs/core/lib.rs", "1"),
+            ("apps/web/main.rs", "1"),
+            ("apps/docs/index.md", "1"),
+        ] {
+            root.write(path, content);
+        }
+
+        let recorded = ManifestDiffEngine::open(&root).record("main", &Context::new());
+        root.write("libs/core/lib.rs", "2");
+
+        let args: AffectedArgs = parse_args(&["affected", "--manifest", "--explain"]);
+
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &Context::new(), &mut out);
+
+        assert!(recorded.is_ok());
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "strategy: transitive_dependents\n\
+             core: core\n  libs/core/lib.rs\n\
+             web: core -> web\n  libs/core/lib.rs\n"
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use parmenides_lib::bisect::{affecting_commits, bisect, AffectingCommit};
+use parmenides_lib::context::Context;
+use parmenides_lib::declarations::{TimeoutsDeclaration, WorkspaceDeclaration};
+use parmenides_lib::diff_engine::GitDiffEngine;
+use parmenides_lib::file_system::OsFileSystem;
+use parmenides_lib::process::SystemProcessRunner;
+use parmenides_lib::tasks::TaskRunner;
+
+use crate::errors::CliError;
+use crate::load::{find_repository, LoadedDeclaration};
+
+/// Finds the commit that broke a project, going back through the commits that affected it and
+/// skipping the others.
+#[derive(Args, Debug)]
+pub struct BisectArgs {
+    /// The identifier of the failing project.
+    pub project: String,
+
+    /// The commit to walk the first-parent history back from.
+    #[arg(long, default_value = "HEAD")]
+    pub from: String,
+
+    /// How many commits to walk back at most, affecting the project or not.
+    #[arg(long, default_value_t = 100)]
+    pub limit: usize,
+
+    /// Run the target with this name in the project at the commits affecting it, to find the
+    /// first one it fails at. Without it, the commits affecting the project are printed, newest
+    /// first.
+    #[arg(long, short)]
+    pub target: Option<String>,
+
+    /// The git repository. Defaults to the one containing the workspace.
+    #[arg(long)]
+    pub repository: Option<PathBuf>,
+}
+
+pub fn run(
+    args: &BisectArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+    let timeouts = declaration.timeouts;
+    let source = source.ok_or_else(|| CliError::NoDeclarationFile(root.clone()))?;
+    let engine = open_engine(args, &root, &timeouts)?;
+
+    let commits = affecting_commits(
+        &engine,
+        &args.from,
+        &source,
+        &args.project,
+        args.limit,
+        context,
+    )?;
+
+    let Some(target) = &args.target else {
+        for commit in &commits {
+            writeln!(out, "{}", commit.commit)?;
+        }
+
+        return Ok(());
+    };
+
+    let declaration = source
+        .strip_prefix(engine.path())
+        .unwrap_or(&source)
+        .to_path_buf();
+    let mut runner = SystemProcessRunner::new();
+
+    if let Some(timeout) = timeouts.task() {
+        runner = runner.with_timeout(timeout);
+    }
+
+    let tasks = TaskRunner::new(&runner);
+    let checkout = std::env::temp_dir().join(format!("parmenides-bisect-{}", std::process::id()));
+
+    let result = bisect(&commits, |commit| {
+        let passed = check(
+            &engine,
+            &tasks,
+            commit,
+            &checkout,
+            &declaration,
+            target,
+            context,
+        );
+
+        // Each commit is checked out from scratch, so files of another commit don't leak in.
+        let _ = std::fs::remove_dir_all(&checkout);
+
+        let passed = passed?;
+        let outcome = if passed { "passed" } else { "failed" };
+
+        eprintln!("{}: {target} {outcome}", commit.commit);
+
+        Ok::<_, CliError>(passed)
+    })?;
+
+    match result {
+        Some(index) => {
+            writeln!(out, "{}", commits[index].commit)?;
+
+            if index + 1 == commits.len() {
+                eprintln!(
+                    "warning: {target} fails at the oldest commit affecting {} that was walked, \
+                     the failure may predate it",
+                    args.project
+                );
+            }
+        }
+        None => match commits.first() {
+            Some(newest) => eprintln!("{target} passes at {}", newest.commit),
+            None => eprintln!("No commit affected {}", args.project),
+        },
+    }
+
+    Ok(())
+}
+
+/// Opens the repository of `args`, or the one containing `root`.
+fn open_engine(
+    args: &BisectArgs,
+    root: &Path,
+    timeouts: &TimeoutsDeclaration,
+) -> Result<GitDiffEngine, CliError> {
+    let repository = match &args.repository {
+        Some(repository) => repository.clone(),
+        None => find_repository(root).ok_or_else(|| CliError::NoRepository(root.to_path_buf()))?,
+    };
+
+    let engine = GitDiffEngine::open(&repository)?;
+
+    Ok(match timeouts.diff() {
+        Some(timeout) => engine.with_timeout(timeout),
+        None => engine,
+    })
+}
+
+/// Runs `target` in the project at `commit`, checked out to `checkout`.
+///
+/// # Returns
+/// - `Ok(bool)`: Whether the target passed, or isn't defined by the project at the commit.
+/// - `Err(CliError)`: If the commit could not be checked out, or its workspace built.
+fn check(
+    engine: &GitDiffEngine,
+    tasks: &TaskRunner,
+    commit: &AffectingCommit,
+    checkout: &Path,
+    declaration: &Path,
+    target: &str,
+    context: &Context,
+) -> Result<bool, CliError> {
+    engine.checkout(&commit.commit, checkout)?;
+
+    let mut declaration = WorkspaceDeclaration::from_path(checkout.join(declaration))?;
+    declaration.apply_preset(&OsFileSystem, context)?;
+    let workspace = declaration.build_workspace()?;
+
+    // The workspace of the commit was built from the same declaration, so IDs match.
+    let project = HashSet::from([commit.project]);
+    let report = tasks.run(&workspace, target, project, context)?;
+
+    Ok(report.is_success())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use git2::{Repository, Signature};
+    use parmenides_lib::context::Context;
+
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, BisectArgs};
+
+    /// Writes the files and commits them, returning the id of the new commit.
+    fn commit(repository: &Repository, files: &[(&str, &str)]) -> String {
+        let root = repository.workdir().unwrap();
+        let mut index = repository.index().unwrap();
+
+        for (path, content) in files {
+            let file = root.join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, content).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+
+        index.write().unwrap();
+
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parent = repository
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "commit",
+                &tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_bisecting_with_target_should_print_the_commit_it_started_failing_at() {
+        let root = TempDir::new("bisect");
+        let repository = Repository::init(&root).unwrap();
+
+        commit(&repository, &[("README.md", "1")]);
+        commit(
+            &repository,
+            &[
+                (
+                    "parmenides.toml",
+                    "[projects.\"libs/core\"]\nname = \"core\"\n\
+                     targets = { check = { command = \"grep -q good lib.rs\" } }\n\n\
+                     [projects.\"apps/web\"]\nname = \"web\"\n",
+                ),
+                ("libs/core/lib.rs", "good"),
+                ("apps/web/main.rs", "1"),
+            ],
+        );
+        commit(&repository, &[("libs/core/lib.rs", "still good")]);
+        let broken = commit(&repository, &[("libs/core/lib.rs", "bad")]);
+        commit(&repository, &[("apps/web/main.rs", "2")]);
+        commit(&repository, &[("libs/core/lib.rs", "still bad")]);
+
+        let listed: BisectArgs = parse_args(&["bisect", "core"]);
+        let checked: BisectArgs = parse_args(&["bisect", "core", "--target", "check"]);
+
+        let mut commits = Vec::new();
+        let listed = run(&listed, root.load(), &Context::new(), &mut commits);
+
+        let mut out = Vec::new();
+        let checked = run(&checked, root.load(), &Context::new(), &mut out);
+
+        assert!(listed.is_ok());
+        // The commit changing only web is skipped.
+        assert_eq!(String::from_utf8(commits).unwrap().lines().count(), 4);
+        assert!(checked.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{broken}\n"));
+    }
+}
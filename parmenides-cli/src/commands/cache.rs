@@ -0,0 +1,235 @@
+use std::io::Write;
+
+use clap::{Args, Subcommand};
+#[cfg(feature = "signing")]
+use parmenides_lib::cache::signing::{EntrySigning, SIGNING_KEY_ENV};
+use parmenides_lib::cache::{CacheScope, CacheStore, LocalCacheStore, TieredCacheStore};
+use parmenides_lib::context::Context;
+use parmenides_lib::file_system::OsFileSystem;
+use parmenides_lib::path_roots::PathRoots;
+use parmenides_lib::process::SystemProcessRunner;
+use parmenides_lib::tasks::TaskRunner;
+
+use crate::commands::affected::describe;
+use crate::commands::run::task_parameters;
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Maintains the local cache of task results.
+#[derive(Args, Debug)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    Verify(VerifyArgs),
+}
+
+/// Recomputes the integrity hash of every entry of the local cache, printing the keys of the
+/// corrupted ones and failing if any is left. With `--target`, also runs the target again where
+/// it is cached, printing the projects whose run the cache doesn't reproduce.
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Delete the corrupted entries, and the ones not reproduced, so the tasks run again
+    /// instead.
+    #[arg(long)]
+    pub evict: bool,
+
+    /// Run this target again in each project with a cached run, and compare the exit code and
+    /// output with the cached ones, to find the tasks that aren't deterministic.
+    #[arg(long, short)]
+    pub target: Option<String>,
+
+    /// Set a parameter the commands reference as `{{ key }}`, like `parmenides run --arg`.
+    #[arg(long = "arg", value_name = "KEY=VALUE", requires = "target")]
+    pub arguments: Vec<String>,
+}
+
+pub fn run(
+    args: &CacheArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    match &args.command {
+        CacheCommand::Verify(args) => verify(args, loaded, context, out),
+    }
+}
+
+fn verify(
+    args: &VerifyArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let store = LocalCacheStore::in_workspace(&loaded.root);
+    let verification = store.verify()?;
+
+    for key in &verification.corrupted {
+        if args.evict {
+            store.remove(key)?;
+            writeln!(out, "{key}: corrupted, evicted")?;
+        } else {
+            writeln!(out, "{key}: corrupted")?;
+        }
+    }
+
+    writeln!(
+        out,
+        "checked {} entries, {} corrupted",
+        verification.checked,
+        verification.corrupted.len()
+    )?;
+
+    if !verification.corrupted.is_empty() && !args.evict {
+        return Err(CliError::CacheCorrupted(verification.corrupted.len()));
+    }
+
+    let Some(target) = &args.target else {
+        return Ok(());
+    };
+
+    let mismatches = rerun_cached(target, args, loaded, &store, context)?;
+
+    for (project, key) in &mismatches {
+        if args.evict {
+            store.remove(key)?;
+            writeln!(out, "{project}: not reproduced, evicted")?;
+        } else {
+            writeln!(out, "{project}: not reproduced")?;
+        }
+    }
+
+    writeln!(out, "{} cached runs not reproduced", mismatches.len())?;
+
+    if mismatches.is_empty() || args.evict {
+        Ok(())
+    } else {
+        Err(CliError::Nondeterministic(
+            mismatches.into_iter().map(|(project, _)| project).collect(),
+        ))
+    }
+}
+
+/// Runs `target` again where it is cached in `store`, see [`TaskRunner::verify_cached`], and
+/// returns the projects whose run isn't reproduced, with the key of their entry.
+fn rerun_cached(
+    target: &str,
+    args: &VerifyArgs,
+    loaded: LoadedDeclaration,
+    store: &LocalCacheStore,
+    context: &Context,
+) -> Result<Vec<(String, String)>, CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        redactor,
+        ..
+    } = loaded;
+    let roots = PathRoots::from_declaration(&declaration.path_roots);
+    let parameters = task_parameters(declaration.constants.clone(), &args.arguments)?;
+    let scope = CacheScope::from_declaration(&declaration.cache, &parameters)?;
+
+    // The local entries are sealed when signing, and can only be read through the keys.
+    #[cfg(feature = "signing")]
+    let signing = EntrySigning::from_declaration(
+        &declaration.cache,
+        std::env::var(SIGNING_KEY_ENV).ok().as_deref(),
+    )?;
+
+    #[cfg(not(feature = "signing"))]
+    if !declaration.cache.trusted_keys.is_empty() {
+        return Err(CliError::SigningUnsupported);
+    }
+
+    let mut runner = SystemProcessRunner::new();
+
+    if let Some(timeout) = declaration.timeouts.task() {
+        runner = runner.with_timeout(timeout);
+    }
+
+    let workspace = build_workspace(&root, source.as_deref(), declaration)?;
+    let tiered = TieredCacheStore::new(store);
+
+    #[cfg(feature = "signing")]
+    let tiered = match signing {
+        Some(signing) => tiered.with_signing(signing),
+        None => tiered,
+    };
+
+    let tasks = TaskRunner::new(&runner)
+        .with_parameters(parameters)
+        .with_redactor(redactor)
+        .with_path_roots(roots)
+        .with_cache(&tiered, scope, &OsFileSystem);
+
+    let mismatches = tasks.verify_cached(
+        &workspace,
+        target,
+        workspace.iter_with_ids().map(|(id, _)| id),
+        context,
+    )?;
+
+    #[cfg(feature = "signing")]
+    for err in tiered.rejected() {
+        eprintln!("warning: {err}, evicted");
+    }
+
+    for err in tasks.undecodable() {
+        eprintln!("warning: {err}, not verified");
+    }
+
+    Ok(mismatches
+        .into_iter()
+        .map(|mismatch| {
+            (
+                describe(&workspace, &root, mismatch.project, false),
+                mismatch.key,
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use parmenides_lib::context::Context;
+
+    use crate::commands::run::RunArgs;
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, CacheArgs};
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_verifying_with_target_should_report_the_runs_not_reproduced() {
+        let root = TempDir::with_declaration(
+            "verify",
+            "[projects.stamped]\nname = \"stamped\"\n\
+             targets = { build = { command = \"echo run >> ../log; cat ../log\" } }\n\n\
+             [projects.stable]\nname = \"stable\"\n\
+             targets = { build = { command = \"echo run\" } }\n",
+        );
+        std::fs::create_dir_all(root.join("stamped")).unwrap();
+        std::fs::create_dir_all(root.join("stable")).unwrap();
+
+        let cached: RunArgs = parse_args(&["run", "--target", "build", "--cache"]);
+        let ran = crate::commands::run::run(&cached, root.load(), &Context::new(), &mut Vec::new());
+
+        let mut out = Vec::new();
+        let args: CacheArgs = parse_args(&["cache", "verify", "--target", "build"]);
+        let result = run(&args, root.load(), &Context::new(), &mut out);
+
+        assert!(ran.is_ok());
+        assert!(
+            matches!(result, Err(CliError::Nondeterministic(projects)) if projects == ["stamped"])
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "checked 2 entries, 0 corrupted\nstamped: not reproduced\n1 cached runs not reproduced\n"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::edit::{edit_declaration_file, edited_declaration_file};
+use parmenides_lib::export::{
+    backstage_relations, to_backstage, BackstageOptions, Catalog, CatalogDrift, GraphView,
+};
+use parmenides_lib::file_system::OsFileSystem;
+use parmenides_lib::workspace::Workspace;
+
+use crate::commands::affected::describe;
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// The formats the catalog can be rendered in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    Markdown,
+    Json,
+    /// Backstage `catalog-info.yaml` entities, one `Component` per project.
+    Backstage,
+}
+
+/// Prints a catalog of the projects: their description, owners, tags, links and direct
+/// dependencies and dependents, e.g. to publish an internal directory derived from the graph.
+#[derive(Args, Debug)]
+pub struct CatalogArgs {
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = CatalogFormat::Markdown)]
+    pub format: CatalogFormat,
+
+    /// The owner of the Backstage components of projects without owners.
+    #[arg(long, default_value = "unknown")]
+    pub default_owner: String,
+
+    /// The Backstage system every component is part of.
+    #[arg(long)]
+    pub system: Option<String>,
+
+    /// Check the `dependsOn` relations of an existing Backstage catalog against the graph
+    /// instead, printing the relations it declares that are absent and the dependencies it
+    /// doesn't declare.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["format", "default_owner", "system"])]
+    pub check: Option<PathBuf>,
+
+    /// Print project paths, relative to the workspace root, instead of identifiers.
+    #[arg(long, requires = "check")]
+    pub paths: bool,
+
+    /// Update the dependencies of the declaration file to match the catalog instead of printing
+    /// the drift, keeping its formatting.
+    #[arg(long, requires = "check")]
+    pub fix: bool,
+
+    /// Print the declaration file as it would be after the fixes, without writing it.
+    #[arg(long, requires = "fix")]
+    pub dry_run: bool,
+}
+
+pub fn run(
+    args: &CatalogArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let app_tag = loaded.declaration.lint.app_tag.clone();
+    let workspace = build_workspace(&loaded.root, loaded.source.as_deref(), loaded.declaration)?;
+
+    if let Some(path) = &args.check {
+        if args.fix {
+            let source = loaded
+                .source
+                .ok_or_else(|| CliError::NoDeclarationFile(loaded.root.clone()))?;
+
+            return fix(&workspace, path, &source, args.dry_run, out);
+        }
+
+        return check(&workspace, &loaded.root, path, args.paths, out);
+    }
+
+    let catalog = Catalog::new(
+        &workspace,
+        &GraphView::full(&workspace),
+        &loaded.root,
+        &OsFileSystem,
+    );
+
+    match args.format {
+        CatalogFormat::Markdown => write!(out, "{}", catalog.to_markdown())?,
+        CatalogFormat::Json => writeln!(out, "{}", catalog.to_json())?,
+        CatalogFormat::Backstage => {
+            let options = BackstageOptions {
+                app_tag,
+                default_owner: args.default_owner.clone(),
+                system: args.system.clone(),
+            };
+
+            write!(out, "{}", to_backstage(&catalog, &options))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the catalog at `path` and compares it against the workspace.
+fn detect(workspace: &Workspace, path: &Path) -> Result<CatalogDrift, CliError> {
+    let content =
+        std::fs::read_to_string(path).map_err(|err| CliError::ReadCatalog(path.into(), err))?;
+
+    Ok(CatalogDrift::detect(
+        workspace,
+        &backstage_relations(&content)?,
+    ))
+}
+
+/// Prints the drift between the catalog at `path` and the workspace, failing if there is any.
+fn check(
+    workspace: &Workspace,
+    root: &Path,
+    path: &Path,
+    paths: bool,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let drift = detect(workspace, path)?;
+
+    for (project, dependency) in &drift.absent {
+        writeln!(
+            out,
+            "{} -> {}: declared by the catalog, absent from the workspace",
+            describe(workspace, root, *project, paths),
+            describe(workspace, root, *dependency, paths)
+        )?;
+    }
+
+    for (project, dependency) in &drift.undeclared {
+        writeln!(
+            out,
+            "{} -> {}: not declared by the catalog",
+            describe(workspace, root, *project, paths),
+            describe(workspace, root, *dependency, paths)
+        )?;
+    }
+
+    for name in &drift.unknown {
+        writeln!(out, "{name}: no project has this identifier")?;
+    }
+
+    if drift.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::CatalogDrifted(
+            drift.absent.len() + drift.undeclared.len() + drift.unknown.len(),
+        ))
+    }
+}
+
+/// Applies the fixes for the drift between the catalog at `path` and the workspace to the
+/// declaration file at `source`, or prints the fixed declaration when `dry_run` is set.
+///
+/// Components matching no project can't be fixed in the declaration, so they are still printed
+/// and fail the command.
+fn fix(
+    workspace: &Workspace,
+    path: &Path,
+    source: &Path,
+    dry_run: bool,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let drift = detect(workspace, path)?;
+    let edits = drift.fixes(workspace);
+
+    if dry_run {
+        write!(out, "{}", edited_declaration_file(source, &edits)?)?;
+    } else if !edits.is_empty() {
+        edit_declaration_file(source, &edits)?;
+    }
+
+    if drift.unknown.is_empty() {
+        return Ok(());
+    }
+
+    for name in &drift.unknown {
+        eprintln!("{name}: no project has this identifier");
+    }
+
+    Err(CliError::CatalogDrifted(drift.unknown.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, CatalogArgs};
+
+    /// Creates a workspace named after `name` where `web` depends on `core`.
+    fn workspace(name: &str) -> TempDir {
+        TempDir::with_declaration(
+            &format!("catalog-{name}"),
+            "[projects.\"libs/core\"]\nname = \"core\"\n\
+             description = \"Shared types.\"\nowners = [\"@platform\"]\n\n\
+             [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n",
+        )
+    }
+
+    /// Runs `parmenides catalog` with `args` in the workspace at `root`.
+    fn catalog(root: &TempDir, args: &[&str]) -> (Result<(), CliError>, String) {
+        let args: CatalogArgs = parse_args(&[&["catalog"], args].concat());
+
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &mut out);
+
+        (result, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    pub fn when_printing_catalog_should_list_each_project_with_its_relations() {
+        let root = workspace("markdown");
+
+        let (result, out) = catalog(&root, &[]);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            out,
+            "# Catalog\n\n\
+             ## core\n\n\
+             Shared types.\n\n\
+             - Identifier: `core`\n\
+             - Path: `libs/core`\n\
+             - Owners: @platform\n\
+             - Tags: none\n\
+             - Dependencies: none\n\
+             - Dependents: web\n\n\
+             ## web\n\n\
+             - Identifier: `web`\n\
+             - Path: `apps/web`\n\
+             - Owners: none\n\
+             - Tags: none\n\
+             - Dependencies: core\n\
+             - Dependents: none\n"
+        );
+    }
+
+    #[test]
+    pub fn when_checking_drifted_catalog_should_print_the_drift_and_fail() {
+        let root = workspace("check");
+        root.write(
+            "catalog-info.yaml",
+            "kind: Component\nmetadata:\n  name: core\nspec:\n  dependsOn:\n  - web\n\
+             ---\n\
+             kind: Component\nmetadata:\n  name: web\nspec: {}\n\
+             ---\n\
+             kind: Component\nmetadata:\n  name: api\nspec: {}\n",
+        );
+        let check = root.join("catalog-info.yaml");
+
+        let (result, out) = catalog(&root, &["--check", check.to_str().unwrap()]);
+
+        assert!(matches!(result, Err(CliError::CatalogDrifted(3))));
+        assert_eq!(
+            out,
+            "core -> web: declared by the catalog, absent from the workspace\n\
+             web -> core: not declared by the catalog\n\
+             api: no project has this identifier\n"
+        );
+    }
+}
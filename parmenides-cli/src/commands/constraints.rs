@@ -0,0 +1,59 @@
+use std::io::Write;
+
+use clap::Args;
+use parmenides_lib::constraints::{check, Constraint, ViolationKind};
+
+use crate::commands::affected::describe;
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Checks the dependencies of the workspace against its declared constraints, printing every
+/// dependency breaking one.
+#[derive(Args, Debug)]
+pub struct ConstraintsArgs {
+    /// Print project paths, relative to the workspace root, instead of identifiers.
+    #[arg(long)]
+    pub paths: bool,
+}
+
+pub fn run(
+    args: &ConstraintsArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        mut declaration,
+        ..
+    } = loaded;
+
+    let constraints = std::mem::take(&mut declaration.constraints)
+        .iter()
+        .map(Constraint::from_declaration)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let workspace = build_workspace(&root, source.as_deref(), declaration)?;
+    let violations = check(&workspace, &constraints);
+
+    for violation in &violations {
+        let reason = match violation.kind {
+            ViolationKind::Forbidden => "forbidden",
+            ViolationKind::NotAllowed => "not allowed",
+        };
+
+        writeln!(
+            out,
+            "{} -> {}: {reason} by {}",
+            describe(&workspace, &root, violation.dependent, args.paths),
+            describe(&workspace, &root, violation.dependency, args.paths),
+            violation.constraint
+        )?;
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ConstraintsViolated(violations.len()))
+    }
+}
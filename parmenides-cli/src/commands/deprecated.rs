@@ -0,0 +1,65 @@
+use std::io::Write;
+
+use clap::Args;
+use parmenides_lib::deprecation::migrations;
+
+use crate::commands::affected::describe;
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Lists the deprecated projects, soonest sunset first, each followed by the dependents that
+/// must migrate away from it.
+#[derive(Args, Debug)]
+pub struct DeprecatedArgs {
+    /// Print project paths, relative to the workspace root, instead of identifiers.
+    #[arg(long)]
+    pub paths: bool,
+}
+
+pub fn run(
+    args: &DeprecatedArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+
+    let workspace = build_workspace(&root, source.as_deref(), declaration)?;
+
+    for migration in migrations(&workspace) {
+        let mut details = Vec::new();
+
+        if let Some(sunset) = &migration.sunset {
+            details.push(format!("sunset {sunset}"));
+        }
+
+        if let Some(replacement) = migration.replacement {
+            details.push(format!(
+                "replaced by {}",
+                describe(&workspace, &root, replacement, args.paths)
+            ));
+        }
+
+        let project = describe(&workspace, &root, migration.project, args.paths);
+
+        if details.is_empty() {
+            writeln!(out, "{project}")?;
+        } else {
+            writeln!(out, "{project} ({})", details.join(", "))?;
+        }
+
+        for dependent in migration.dependents {
+            writeln!(
+                out,
+                "  {}",
+                describe(&workspace, &root, dependent, args.paths)
+            )?;
+        }
+    }
+
+    Ok(())
+}
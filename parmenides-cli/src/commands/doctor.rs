@@ -0,0 +1,371 @@
+use std::io::Write;
+use std::path::Path;
+
+use clap::Args;
+use parmenides_lib::cache::{RemoteCacheDeclaration, LOCAL_CACHE_DIRECTORY};
+use parmenides_lib::context::Context;
+use parmenides_lib::credentials::CredentialsDeclaration;
+use parmenides_lib::declarations::{TimeoutsDeclaration, WatchDeclaration};
+use parmenides_lib::diff_engine::GitDiffEngine;
+use parmenides_lib::process::SystemProcessRunner;
+use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+use parmenides_lib::watch::{resolve_backend, WatchBackend};
+
+use crate::errors::CliError;
+use crate::load::{find_repository, load_declaration};
+
+/// Checks the environment parmenides runs in, and how to fix what doesn't work.
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// The base revision affected projects are usually computed from.
+    #[arg(long, default_value = "main")]
+    pub from: String,
+}
+
+/// The outcome of a single check.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass(String),
+    /// What failed, and a hint on how to fix it.
+    Fail(String, String),
+    /// The check couldn't run, as an earlier one failed.
+    Skip,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+impl Check {
+    fn pass<S: Into<String>>(name: &'static str, message: S) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Pass(message.into()),
+        }
+    }
+
+    fn fail<S: Into<String>, H: Into<String>>(name: &'static str, message: S, hint: H) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Fail(message.into(), hint.into()),
+        }
+    }
+
+    fn skip(name: &'static str) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Skip,
+        }
+    }
+}
+
+/// Runs every check, in order. Checks depending on a failed one are skipped.
+pub fn checks(
+    args: &DoctorArgs,
+    declaration: Option<&Path>,
+    start: &Path,
+    policy: UnknownKeyPolicy,
+    context: &Context,
+) -> Vec<Check> {
+    let loaded = match load_declaration(declaration, start, policy, context) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            return vec![
+                Check::fail(
+                    "declaration",
+                    err.to_string(),
+                    "run from inside a workspace, or pass --declaration",
+                ),
+                Check::skip("projects"),
+                Check::skip("git"),
+                Check::skip("base revision"),
+                Check::skip("cache"),
+                Check::skip("watcher"),
+            ];
+        }
+    };
+
+    let root = loaded.root;
+    let timeouts = loaded.declaration.timeouts;
+    let watch = loaded.declaration.watch.clone();
+    let remote = loaded.declaration.cache.remote.clone();
+    let credentials = loaded.declaration.credentials.clone();
+
+    let mut checks = vec![Check::pass(
+        "declaration",
+        format!("loaded the workspace at {}", root.display()),
+    )];
+
+    checks.push(match loaded.declaration.build_workspace() {
+        Ok(workspace) => match workspace.iter().count() {
+            1 => Check::pass("projects", "found 1 project"),
+            count => Check::pass("projects", format!("found {count} projects")),
+        },
+        Err(err) => Check::fail(
+            "projects",
+            err.to_string(),
+            "fix the projects of the declaration",
+        ),
+    });
+
+    checks.extend(check_git(&args.from, &root));
+    // Offline, the remote cache isn't used, so it isn't reached either.
+    let remote = remote.filter(|_| !context.is_offline());
+    checks.push(check_cache(&root, remote.as_ref(), &credentials, &timeouts));
+    checks.push(check_watcher(&watch));
+
+    checks
+}
+
+fn check_git(from: &str, root: &Path) -> [Check; 2] {
+    let Some(repository) = find_repository(root) else {
+        return [
+            Check::fail(
+                "git",
+                format!("no git repository contains {}", root.display()),
+                "run `git init`, or pass --repository to the affected commands",
+            ),
+            Check::skip("base revision"),
+        ];
+    };
+
+    let engine = match GitDiffEngine::open(&repository) {
+        Ok(engine) => engine,
+        Err(err) => {
+            return [
+                Check::fail(
+                    "git",
+                    err.to_string(),
+                    "check the repository with `git status`",
+                ),
+                Check::skip("base revision"),
+            ];
+        }
+    };
+
+    let git = Check::pass(
+        "git",
+        format!("opened the repository at {}", repository.display()),
+    );
+
+    let base = match engine.resolve(from) {
+        Ok(commit) => Check::pass("base revision", format!("{from} is at {commit}")),
+        Err(err) => Check::fail(
+            "base revision",
+            err.to_string(),
+            format!("fetch it, e.g. `git fetch origin {from}`, or pass another --from"),
+        ),
+    };
+
+    [git, base]
+}
+
+fn check_cache(
+    root: &Path,
+    remote: Option<&RemoteCacheDeclaration>,
+    credentials: &CredentialsDeclaration,
+    timeouts: &TimeoutsDeclaration,
+) -> Check {
+    let local = root.join(LOCAL_CACHE_DIRECTORY);
+
+    let Some(remote) = remote else {
+        return Check::pass("cache", format!("local only, at {}", local.display()));
+    };
+
+    check_remote_cache(remote, credentials, timeouts)
+}
+
+#[cfg(feature = "http")]
+fn check_remote_cache(
+    remote: &RemoteCacheDeclaration,
+    credentials: &CredentialsDeclaration,
+    timeouts: &TimeoutsDeclaration,
+) -> Check {
+    use parmenides_lib::cache::{HttpRemoteCache, RemoteCache};
+    use parmenides_lib::errors::RemoteCacheError;
+
+    let runner = SystemProcessRunner::new();
+    let chain = credentials.chain(&remote.token_env, &runner);
+
+    let credential = match remote.credential(&chain) {
+        Ok(credential) => credential,
+        Err(err) => {
+            return Check::fail(
+                "cache",
+                err.to_string(),
+                "fix the credentials of the declaration",
+            )
+        }
+    };
+
+    let mut cache = HttpRemoteCache::new(&remote.url);
+
+    if let Some(credential) = &credential {
+        cache = cache.with_token(&credential.token);
+    }
+
+    if let Some(timeout) = timeouts.cache() {
+        cache = cache.with_timeout(timeout);
+    }
+
+    match cache.get("parmenides-doctor") {
+        Ok(_) => match credential {
+            Some(credential) => Check::pass(
+                "cache",
+                format!(
+                    "reached {}, with a token from {}",
+                    remote.url, credential.source
+                ),
+            ),
+            None => Check::pass(
+                "cache",
+                format!(
+                    "reached {}, without a token in {}",
+                    remote.url, remote.token_env
+                ),
+            ),
+        },
+        Err(err @ RemoteCacheError::Status(_, 401 | 403)) => Check::fail(
+            "cache",
+            err.to_string(),
+            format!(
+                "set a valid token in {}, the credentials file or the helper",
+                remote.token_env
+            ),
+        ),
+        Err(err) => Check::fail(
+            "cache",
+            err.to_string(),
+            "check cache.remote.url and the network, or run with --offline",
+        ),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn check_remote_cache(
+    remote: &RemoteCacheDeclaration,
+    _: &CredentialsDeclaration,
+    _: &TimeoutsDeclaration,
+) -> Check {
+    Check::fail(
+        "cache",
+        format!(
+            "{} is declared, but this build has no HTTP support",
+            remote.url
+        ),
+        "install a build with the http feature, or run with --offline",
+    )
+}
+
+fn check_watcher(watch: &WatchDeclaration) -> Check {
+    let resolved = resolve_backend(WatchBackend::Auto, &SystemProcessRunner::new());
+
+    match watch.backend {
+        WatchBackend::Watchman if resolved != WatchBackend::Watchman => Check::fail(
+            "watcher",
+            "watchman is configured, but not installed",
+            "install Watchman, or set watch.backend to polling",
+        ),
+        WatchBackend::Notify if !cfg!(feature = "notify") => Check::fail(
+            "watcher",
+            "notify is configured, but this build has no notify support",
+            "install a build with the notify feature, or set watch.backend to polling",
+        ),
+        WatchBackend::Auto => Check::pass("watcher", format!("auto, using {resolved}")),
+        backend => Check::pass("watcher", backend.to_string()),
+    }
+}
+
+pub fn run(
+    args: &DoctorArgs,
+    declaration: Option<&Path>,
+    start: &Path,
+    policy: UnknownKeyPolicy,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let checks = checks(args, declaration, start, policy, context);
+    let mut failed = 0;
+
+    for check in &checks {
+        match &check.outcome {
+            Outcome::Pass(message) => writeln!(out, "pass  {}: {message}", check.name)?,
+            Outcome::Fail(message, hint) => {
+                failed += 1;
+                writeln!(out, "fail  {}: {message}", check.name)?;
+                writeln!(out, "      hint: {hint}")?;
+            }
+            Outcome::Skip => writeln!(out, "skip  {}", check.name)?,
+        }
+    }
+
+    if failed > 0 {
+        return Err(CliError::ChecksFailed(failed));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use parmenides_lib::context::Context;
+    use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+
+    use crate::testing::TempDir;
+
+    use super::{checks, Check, DoctorArgs, Outcome};
+
+    fn names_and_passes(checks: &[Check]) -> Vec<(&str, Option<bool>)> {
+        checks
+            .iter()
+            .map(|check| {
+                let passed = match check.outcome {
+                    Outcome::Pass(_) => Some(true),
+                    Outcome::Fail(..) => Some(false),
+                    Outcome::Skip => None,
+                };
+
+                (check.name, passed)
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn when_workspace_has_no_repository_should_fail_git_and_skip_base() {
+        let root = TempDir::with_declaration("doctor", "[projects.\"apps/web\"]\nname = \"web\"\n");
+
+        let args = DoctorArgs {
+            from: "main".to_owned(),
+        };
+        let found = checks(&args, None, &root, UnknownKeyPolicy::Warn, &Context::new());
+        let missing = checks(
+            &args,
+            Some(&root.join("missing.toml")),
+            &root,
+            UnknownKeyPolicy::Warn,
+            &Context::new(),
+        );
+
+        assert_eq!(
+            names_and_passes(&found),
+            vec![
+                ("declaration", Some(true)),
+                ("projects", Some(true)),
+                ("git", Some(false)),
+                ("base revision", None),
+                ("cache", Some(true)),
+                ("watcher", Some(true)),
+            ]
+        );
+        assert_eq!(
+            found[1].outcome,
+            Outcome::Pass("found 1 project".to_owned())
+        );
+        assert_eq!(names_and_passes(&missing)[0], ("declaration", Some(false)));
+        assert!(missing[1..]
+            .iter()
+            .all(|check| check.outcome == Outcome::Skip));
+    }
+}
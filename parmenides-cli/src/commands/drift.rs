@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::Command;
+
+use clap::Args;
+use parmenides_lib::drift::DependencyDrift;
+use parmenides_lib::edit::{edit_declaration_file, edited_declaration_file};
+use parmenides_lib::errors::TraceError;
+use parmenides_lib::process::shell_command;
+use parmenides_lib::trace::{infer_dependencies, trace_command, TraceOutput};
+
+use crate::commands::affected::describe;
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Runs a target in every project declaring it under `strace`, and compares the projects whose
+/// files each run read against its declared dependencies. The dependencies read but not
+/// declared, and the declared ones never read, are printed.
+#[derive(Args, Debug)]
+pub struct DriftArgs {
+    /// The target to run, e.g. `build`.
+    pub target: String,
+
+    /// Print project paths, relative to the workspace root, instead of identifiers.
+    #[arg(long)]
+    pub paths: bool,
+
+    /// Update the dependencies of the declaration file to match the runs instead of printing
+    /// the drift, keeping its formatting.
+    #[arg(long)]
+    pub fix: bool,
+
+    /// Print the declaration file as it would be after the fixes, without writing it.
+    #[arg(long, requires = "fix")]
+    pub dry_run: bool,
+}
+
+pub fn run(
+    args: &DriftArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    drift(args, loaded, &trace_command, out)
+}
+
+/// Runs the drift check of `args`, tracing the targets with `trace`.
+fn drift(
+    args: &DriftArgs,
+    loaded: LoadedDeclaration,
+    trace: &dyn Fn(&Command) -> Result<TraceOutput, TraceError>,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let root = loaded.root.clone();
+    let source = loaded.source.clone();
+    let workspace = build_workspace(&loaded.root, loaded.source.as_deref(), loaded.declaration)?;
+
+    let mut inferred = BTreeMap::new();
+    let mut failed = Vec::new();
+
+    for (id, project) in workspace.iter_with_ids() {
+        let Some(target) = project.target(&args.target) else {
+            continue;
+        };
+
+        let mut command = shell_command(&target.command);
+        command.current_dir(project.path());
+
+        let output = trace(&command)?;
+
+        // A failed run may have stopped before reading its dependencies.
+        if !output.status.success() {
+            failed.push(describe(&workspace, &root, id, args.paths));
+            continue;
+        }
+
+        inferred.insert(
+            id,
+            infer_dependencies(&workspace, id, &output.accessed_paths),
+        );
+    }
+
+    if !failed.is_empty() {
+        return Err(CliError::CommandFailed(failed));
+    }
+
+    let drift = DependencyDrift::detect(&workspace, &inferred);
+
+    if args.fix {
+        let source = source.ok_or_else(|| CliError::NoDeclarationFile(root.clone()))?;
+        let edits = drift.fixes(&workspace);
+
+        if args.dry_run {
+            write!(out, "{}", edited_declaration_file(&source, &edits)?)?;
+        } else if !edits.is_empty() {
+            edit_declaration_file(&source, &edits)?;
+        }
+
+        return Ok(());
+    }
+
+    for (project, dependency) in &drift.missing {
+        writeln!(
+            out,
+            "{} -> {}: read by {}, not declared",
+            describe(&workspace, &root, *project, args.paths),
+            describe(&workspace, &root, *dependency, args.paths),
+            args.target
+        )?;
+    }
+
+    for (project, dependency) in &drift.unused {
+        writeln!(
+            out,
+            "{} -> {}: declared, not read by {}",
+            describe(&workspace, &root, *project, args.paths),
+            describe(&workspace, &root, *dependency, args.paths),
+            args.target
+        )?;
+    }
+
+    if drift.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::DependenciesDrifted(
+            drift.missing.len() + drift.unused.len(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{Command, ExitStatus};
+
+    use parmenides_lib::errors::TraceError;
+    use parmenides_lib::trace::TraceOutput;
+
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{drift, DriftArgs};
+
+    const DECLARATION: &str = "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+                               [projects.\"apps/web\"]\nname = \"web\"\n\n\
+                               [projects.\"apps/web\".targets.build]\ncommand = \"make\"\n";
+
+    /// Creates a workspace named after `name` where `web` declares no dependencies.
+    fn workspace(name: &str) -> TempDir {
+        TempDir::with_declaration(&format!("drift-{name}"), DECLARATION)
+    }
+
+    /// Runs `parmenides drift` with `args` in the workspace at `root`, where every traced
+    /// command reads a file of `core`.
+    fn run(root: &TempDir, args: &[&str]) -> (Result<(), CliError>, String) {
+        let args: DriftArgs = parse_args(&[&["drift"], args].concat());
+
+        let trace = |_: &Command| -> Result<TraceOutput, TraceError> {
+            Ok(TraceOutput {
+                status: ExitStatus::from_raw(0),
+                accessed_paths: [root.join("libs/core/src/lib.rs")].into(),
+            })
+        };
+
+        let mut out = Vec::new();
+        let result = drift(&args, root.load(), &trace, &mut out);
+
+        (result, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    pub fn when_target_reads_undeclared_project_should_print_the_drift_and_fail() {
+        let root = workspace("check");
+
+        let (result, out) = run(&root, &["build"]);
+
+        assert!(matches!(result, Err(CliError::DependenciesDrifted(1))));
+        assert_eq!(out, "web -> core: read by build, not declared\n");
+    }
+
+    #[test]
+    pub fn when_fixing_undeclared_dependency_should_add_it_to_the_declaration() {
+        let root = workspace("fix");
+
+        let (dry_run, printed) = run(&root, &["build", "--fix", "--dry-run"]);
+        let untouched = root.read("parmenides.toml");
+
+        let (fixed, _) = run(&root, &["build", "--fix"]);
+        let written = root.read("parmenides.toml");
+
+        let (check, out) = run(&root, &["build"]);
+
+        assert!(dry_run.is_ok());
+        assert!(printed
+            .contains("[projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n"));
+        assert_eq!(untouched, DECLARATION);
+        assert!(fixed.is_ok());
+        assert_eq!(written, printed);
+        assert!(check.is_ok());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use parmenides_lib::context::Context;
+use parmenides_lib::declarations::TimeoutsDeclaration;
+use parmenides_lib::diff_engine::GitDiffEngine;
+use parmenides_lib::export::{churn, to_features_csv, FeatureHistory, GraphView};
+use parmenides_lib::shard::{Durations, DURATIONS_FILE};
+
+use crate::errors::CliError;
+use crate::load::{build_workspace, find_repository, LoadedDeclaration};
+
+/// Prints a CSV table of features per project, e.g. to train test selection models: the
+/// dependency counts, tags, churn and failure rate of each project.
+#[derive(Args, Debug)]
+pub struct FeaturesArgs {
+    /// Fill the failure rates from the runs of the target with this name, as recorded by
+    /// `parmenides run --record-durations`.
+    #[arg(long, short)]
+    pub target: Option<String>,
+
+    /// Fill the churn by counting how many of this many commits changed each project. Without
+    /// it, the repository history isn't read.
+    #[arg(long)]
+    pub churn: Option<usize>,
+
+    /// The commit to count the churn back from.
+    #[arg(long, default_value = "HEAD", requires = "churn")]
+    pub from: String,
+
+    /// The git repository. Defaults to the one containing the workspace.
+    #[arg(long, requires = "churn")]
+    pub repository: Option<PathBuf>,
+}
+
+pub fn run(
+    args: &FeaturesArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+    let timeouts = declaration.timeouts;
+
+    let workspace = build_workspace(&root, source.as_deref(), declaration)?;
+    let mut history = FeatureHistory::default();
+
+    if let Some(limit) = args.churn {
+        let engine = open_engine(args, &root, &timeouts)?;
+
+        history.churn = churn(&workspace, &engine, &args.from, limit, context)?;
+    }
+
+    if let Some(target) = &args.target {
+        let durations = Durations::load(root.join(DURATIONS_FILE))?;
+
+        history.failure_rates = workspace
+            .iter_with_ids()
+            .filter_map(|(id, project)| {
+                Some((id, durations.failure_rate(target, project.identifier())?))
+            })
+            .collect();
+    }
+
+    write!(
+        out,
+        "{}",
+        to_features_csv(&workspace, &GraphView::full(&workspace), &history)
+    )?;
+
+    Ok(())
+}
+
+/// Opens the repository of `args`, or the one containing `root`.
+fn open_engine(
+    args: &FeaturesArgs,
+    root: &Path,
+    timeouts: &TimeoutsDeclaration,
+) -> Result<GitDiffEngine, CliError> {
+    let repository = match &args.repository {
+        Some(repository) => repository.clone(),
+        None => find_repository(root).ok_or_else(|| CliError::NoRepository(root.to_path_buf()))?,
+    };
+
+    let engine = GitDiffEngine::open(&repository)?;
+
+    Ok(match timeouts.diff() {
+        Some(timeout) => engine.with_timeout(timeout),
+        None => engine,
+    })
+}
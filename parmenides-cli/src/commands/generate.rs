@@ -0,0 +1,67 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Args;
+use parmenides_lib::edit::edit_declaration_file;
+use parmenides_lib::generate::generate_project;
+
+use crate::commands::run::task_parameters;
+use crate::errors::CliError;
+use crate::load::LoadedDeclaration;
+
+/// Creates a new project from one of the generators of the declaration, declares it, and prints
+/// its path.
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    /// The name of the generator.
+    pub generator: String,
+
+    /// Where to create the project, relative to the workspace root.
+    pub path: PathBuf,
+
+    /// The name of the new project. Defaults to the last component of its path.
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Set a parameter the template references as `{{ key }}`, overriding the constant of the
+    /// declaration with the same key.
+    #[arg(long = "arg", value_name = "KEY=VALUE")]
+    pub arguments: Vec<String>,
+}
+
+pub fn run(
+    args: &GenerateArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+
+    let source = source.ok_or_else(|| CliError::NoDeclarationFile(root.clone()))?;
+
+    let path = root.join(&args.path);
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+
+    let parameters = task_parameters(declaration.constants.clone(), &args.arguments)?;
+    let edit = generate_project(&declaration, &args.generator, &path, &name, &parameters)?;
+
+    edit_declaration_file(&source, &[edit])?;
+
+    writeln!(
+        out,
+        "{}",
+        path.strip_prefix(&root).unwrap_or(&path).display()
+    )?;
+
+    Ok(())
+}
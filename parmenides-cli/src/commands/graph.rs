@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::export::{to_dot, to_json, to_mermaid, DotOptions, GraphView};
+
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// The formats the graph can be rendered in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+    #[cfg(feature = "svg")]
+    Svg,
+}
+
+/// Renders the workspace graph.
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+    pub format: GraphFormat,
+
+    /// Restrict the graph to the project with this identifier and its neighborhood.
+    #[arg(long)]
+    pub focus: Option<String>,
+
+    /// How many levels of dependencies and dependents of the focused project to include.
+    #[arg(long, default_value_t = 1, requires = "focus")]
+    pub depth: usize,
+}
+
+pub fn run(
+    args: &GraphArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let workspace = build_workspace(&loaded.root, loaded.source.as_deref(), loaded.declaration)?;
+
+    let view = match &args.focus {
+        Some(identifier) => {
+            let id = workspace
+                .get_id_by_identifier(identifier)
+                .ok_or_else(|| CliError::UnknownProject(identifier.clone()))?;
+
+            GraphView::neighborhood(&workspace, id, args.depth)
+        }
+        None => GraphView::full(&workspace),
+    };
+
+    let options = DotOptions::default();
+
+    let rendered = match args.format {
+        GraphFormat::Dot => to_dot(&workspace, &view, &options),
+        GraphFormat::Mermaid => to_mermaid(&workspace, &view, &options),
+        GraphFormat::Json => to_json(&workspace, &view),
+        #[cfg(feature = "svg")]
+        GraphFormat::Svg => parmenides_lib::export::to_svg(&workspace, &view, &options)?,
+    };
+
+    write!(out, "{rendered}")?;
+
+    if !rendered.ends_with('\n') {
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
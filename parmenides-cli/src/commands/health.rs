@@ -0,0 +1,126 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::context::Context;
+use parmenides_lib::diff_engine::GitDiffEngine;
+use parmenides_lib::export::churn;
+use parmenides_lib::health::{
+    read, record, trend_to_text, HealthReport, HealthSignals, HEALTH_FILE,
+};
+use parmenides_lib::lint::Linter;
+use parmenides_lib::shard::{Durations, DURATIONS_FILE};
+
+use crate::errors::CliError;
+use crate::load::{build_workspace, find_repository, LoadedDeclaration};
+
+/// The formats the health can be printed in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthFormat {
+    Text,
+    Json,
+}
+
+/// Scores the health of the workspace from 0 to 100, with a breakdown per category: lint
+/// violations, cycles between tag layers, average blast radius, cache hit rate and stale
+/// projects.
+#[derive(Args, Debug)]
+pub struct HealthArgs {
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = HealthFormat::Text)]
+    pub format: HealthFormat,
+
+    /// Append the report to `.parmenides/health.jsonl`, to follow the trend.
+    #[arg(long)]
+    pub record: bool,
+
+    /// Print the recorded reports instead of computing one.
+    #[arg(long, conflicts_with_all = ["record", "stale_after"])]
+    pub trend: bool,
+
+    /// Count the projects no commit changed among this many as stale. Without it, the
+    /// repository history isn't read and stale projects aren't scored.
+    #[arg(long)]
+    pub stale_after: Option<usize>,
+
+    /// The commit to count the commits back from.
+    #[arg(long, default_value = "HEAD", requires = "stale_after")]
+    pub from: String,
+
+    /// The git repository. Defaults to the one containing the workspace.
+    #[arg(long, requires = "stale_after")]
+    pub repository: Option<PathBuf>,
+}
+
+pub fn run(
+    args: &HealthArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+    let path = root.join(HEALTH_FILE);
+
+    if args.trend {
+        let reports = read(&path)?;
+
+        match args.format {
+            HealthFormat::Text => write!(out, "{}", trend_to_text(&reports))?,
+            HealthFormat::Json => writeln!(
+                out,
+                "{}",
+                serde_json::to_string_pretty(&reports).expect("reports serialize")
+            )?,
+        }
+
+        return Ok(());
+    }
+
+    let linter = Linter::from_declaration(&declaration.lint)?;
+    let timeouts = declaration.timeouts;
+    let workspace = build_workspace(&root, source.as_deref(), declaration)?;
+
+    let mut signals = HealthSignals {
+        lint_violations: Some(linter.run(&workspace).diagnostics.len()),
+        cache_hit_rate: Durations::load(root.join(DURATIONS_FILE))?.cache_hit_rate(),
+        churn: None,
+    };
+
+    if let Some(limit) = args.stale_after {
+        let repository = match &args.repository {
+            Some(repository) => repository.clone(),
+            None => find_repository(&root).ok_or_else(|| CliError::NoRepository(root.clone()))?,
+        };
+
+        let engine = GitDiffEngine::open(&repository)?;
+        let engine = match timeouts.diff() {
+            Some(timeout) => engine.with_timeout(timeout),
+            None => engine,
+        };
+
+        signals.churn = Some(churn(&workspace, &engine, &args.from, limit, context)?);
+    }
+
+    let report = HealthReport::compute(&workspace, &signals, SystemTime::now());
+
+    if args.record {
+        record(&path, &report)?;
+    }
+
+    match args.format {
+        HealthFormat::Text => write!(out, "{}", report.to_text())?,
+        HealthFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&report).expect("reports serialize")
+        )?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::lint::{Baseline, Linter, Severity};
+
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// The formats the lint report can be printed in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintFormat {
+    Text,
+    Sarif,
+}
+
+/// Checks the workspace graph against the configured lint rules, failing if any rule with the
+/// error severity is broken.
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = LintFormat::Text)]
+    pub format: LintFormat,
+
+    /// A baseline file of tolerated violations, only violations that are not in it are reported.
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+}
+
+pub fn run(
+    args: &LintArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+
+    let linter = Linter::from_declaration(&declaration.lint)?;
+    let workspace = build_workspace(&root, source.as_deref(), declaration)?;
+
+    let mut report = linter.run(&workspace);
+
+    if let Some(path) = &args.baseline {
+        report = Baseline::load(path)?.filter_new(&report, &workspace);
+    }
+
+    match args.format {
+        LintFormat::Text => write!(out, "{}", report.to_text(&workspace))?,
+        LintFormat::Sarif => writeln!(out, "{:#}", report.to_sarif(&linter, &workspace))?,
+    }
+
+    let errors = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    if errors == 0 {
+        Ok(())
+    } else {
+        Err(CliError::LintFailed(errors))
+    }
+}
@@ -0,0 +1,37 @@
+use std::io::Write;
+
+use clap::Args;
+use parmenides_lib::context::Context;
+use parmenides_lib::diff_engine::ManifestDiffEngine;
+
+use crate::errors::CliError;
+use crate::load::LoadedDeclaration;
+
+/// Records the content hashes of the files of the workspace, for `--manifest` to diff against
+/// where there is no git history, such as tarball based CI.
+#[derive(Args, Debug)]
+pub struct ManifestArgs {
+    /// The name to record the manifest as, which `--from` and `--to` refer to.
+    #[arg(long, default_value = "main")]
+    pub name: String,
+}
+
+pub fn run(
+    args: &ManifestArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let engine = ManifestDiffEngine::open(&loaded.root);
+    let manifest = engine.record(&args.name, context)?;
+    let path = engine.manifest_path(&args.name);
+
+    writeln!(
+        out,
+        "recorded {} files to {}",
+        manifest.files().len(),
+        path.strip_prefix(&loaded.root).unwrap_or(&path).display()
+    )?;
+
+    Ok(())
+}
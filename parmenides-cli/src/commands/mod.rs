@@ -0,0 +1,22 @@
+pub mod affected;
+pub mod bisect;
+pub mod cache;
+pub mod catalog;
+pub mod constraints;
+pub mod deprecated;
+pub mod doctor;
+#[cfg(target_os = "linux")]
+pub mod drift;
+pub mod features;
+pub mod generate;
+pub mod graph;
+pub mod health;
+pub mod lint;
+pub mod manifest;
+pub mod move_project;
+pub mod rename;
+pub mod run;
+pub mod schema;
+pub mod shard;
+pub mod stats;
+pub mod watch;
@@ -0,0 +1,91 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Args;
+use parmenides_lib::refactor::move_project;
+use parmenides_lib::selector::Selector;
+
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Moves a project directory to a new path, rewriting its declaration and every dependency
+/// referencing it, and prints the new path.
+#[derive(Args, Debug)]
+pub struct MoveArgs {
+    /// The identifier of the project, or a selector matching only it, e.g. `name:core`.
+    pub project: String,
+
+    /// The new path of the project, relative to the workspace root. It must not exist.
+    pub path: PathBuf,
+}
+
+pub fn run(
+    args: &MoveArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+
+    let source = source.ok_or_else(|| CliError::NoDeclarationFile(root.clone()))?;
+    let workspace = build_workspace(&root, Some(&source), declaration)?;
+
+    let id = match workspace.get_id_by_identifier(&args.project) {
+        Some(id) => id,
+        None => match Selector::parse(&args.project)?.select(&workspace)[..] {
+            [id] => id,
+            [] => return Err(CliError::UnknownProject(args.project.clone())),
+            ref ids => return Err(CliError::AmbiguousProject(args.project.clone(), ids.len())),
+        },
+    };
+
+    let from = workspace
+        .get_project(id)
+        .map(|project| project.path().to_path_buf())
+        .ok_or_else(|| CliError::UnknownProject(args.project.clone()))?;
+
+    move_project(&source, from, root.join(&args.path))?;
+
+    writeln!(out, "{}", args.path.display())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, MoveArgs};
+
+    #[test]
+    pub fn when_moving_selected_project_should_move_directory_and_rewrite_references() {
+        let root = TempDir::with_declaration(
+            "move",
+            "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+             [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n",
+        );
+        root.write("libs/core/lib.rs", "");
+
+        let ambiguous: MoveArgs = parse_args(&["move", "path:*s/*", "shared"]);
+        let ambiguous = run(&ambiguous, root.load(), &mut Vec::new());
+
+        let args: MoveArgs = parse_args(&["move", "path:*/libs/*", "shared/core"]);
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &mut out);
+
+        assert!(matches!(ambiguous, Err(CliError::AmbiguousProject(_, 2))));
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "shared/core\n");
+        assert!(root.join("shared/core/lib.rs").exists());
+        assert_eq!(
+            root.read("parmenides.toml"),
+            "[projects.\"shared/core\"]\nname = \"core\"\n\n\
+             [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"shared/core\"]\n"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::Write;
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::refactor::{rename_tag, rename_target};
+
+use crate::errors::CliError;
+use crate::load::LoadedDeclaration;
+
+/// What a rename applies to.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameKind {
+    /// A tag, also renamed in the selectors of constraints and lint rules.
+    Tag,
+    /// A target, also renamed in the contracts naming it.
+    Target,
+}
+
+/// Renames a tag or a target across the declaration, keeping its formatting, and prints the
+/// files that changed.
+#[derive(Args, Debug)]
+pub struct RenameArgs {
+    /// Whether to rename a tag or a target.
+    #[arg(value_enum)]
+    pub kind: RenameKind,
+
+    /// The current name.
+    pub from: String,
+
+    /// The new name.
+    pub to: String,
+}
+
+pub fn run(
+    args: &RenameArgs,
+    loaded: LoadedDeclaration,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let source = loaded
+        .source
+        .ok_or_else(|| CliError::NoDeclarationFile(loaded.root.clone()))?;
+
+    let changed = match args.kind {
+        RenameKind::Tag => rename_tag([&source], &args.from, &args.to)?,
+        RenameKind::Target => rename_target([&source], &args.from, &args.to)?,
+    };
+
+    for path in changed {
+        writeln!(
+            out,
+            "{}",
+            path.strip_prefix(&loaded.root).unwrap_or(&path).display()
+        )?;
+    }
+
+    Ok(())
+}
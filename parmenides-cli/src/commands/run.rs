@@ -0,0 +1,607 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+#[cfg(feature = "signing")]
+use parmenides_lib::cache::signing::{EntrySigning, SIGNING_KEY_ENV};
+#[cfg(feature = "http")]
+use parmenides_lib::cache::HttpRemoteCache;
+use parmenides_lib::cache::{CacheScope, LocalCacheStore, TieredCacheStore};
+use parmenides_lib::context::Context;
+use parmenides_lib::determinism::{find_nondeterministic_projects, Difference};
+use parmenides_lib::file_system::OsFileSystem;
+use parmenides_lib::last_green::{LastGreen, DEFAULT_PIPELINE};
+use parmenides_lib::parameters::Parameters;
+use parmenides_lib::path_roots::PathRoots;
+use parmenides_lib::process::{ProcessOutput, SystemProcessRunner};
+use parmenides_lib::project::ProjectId;
+use parmenides_lib::redaction::{RedactingWriter, Redactor};
+use parmenides_lib::shard::{Durations, DURATIONS_FILE};
+use parmenides_lib::sort::natural_cmp;
+use parmenides_lib::tasks::{JsonTaskReport, TaskReport, TaskRunner, TaskStatus};
+use parmenides_lib::workspace::Workspace;
+
+use crate::commands::affected::{describe, mark_affected, open_engine, DiffArgs};
+use crate::commands::shard::ShardSelectionArgs;
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Runs a shell command, or a target, in each project directory, dependencies first.
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// The command to run, through the platform shell. Each argument is quoted, so it reaches
+    /// the command as it was given.
+    #[arg(
+        required_unless_present = "target",
+        conflicts_with = "target",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub command: Vec<String>,
+
+    /// Run the target with this name in the projects defining it, instead of a command.
+    #[arg(long, short)]
+    pub target: Option<String>,
+
+    /// Set a parameter the commands reference as `{{ key }}`, overriding the constant of the
+    /// declaration with the same key, e.g. `--arg tag=v1.2.3`.
+    #[arg(long = "arg", value_name = "KEY=VALUE")]
+    pub arguments: Vec<String>,
+
+    /// Only run in the projects affected by the changes between the revisions.
+    #[arg(long)]
+    pub affected: bool,
+
+    #[command(flatten)]
+    pub diff: DiffArgs,
+
+    #[command(flatten)]
+    pub shard: ShardSelectionArgs,
+
+    /// Replay the output of successful runs whose project inputs and command are unchanged,
+    /// from the cache under `.parmenides/cache` and the declared remote cache.
+    #[arg(long)]
+    pub cache: bool,
+
+    /// How many projects to run in at once. Projects only run once their dependencies are
+    /// done.
+    #[arg(long, short, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+
+    /// Record how long the target ran in each project, and whether it failed, to
+    /// `.parmenides/durations.json`, to balance the shards of `parmenides shard`.
+    #[arg(long, requires = "target")]
+    pub record_durations: bool,
+
+    /// Write how the command ended in each project to this file, as JSON. See
+    /// `parmenides schema task-report`.
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Once every task succeeded, record the commit run, `--to` or `HEAD`, as the last green
+    /// run of this pipeline, for `--since-green` to diff from.
+    #[arg(
+        long,
+        value_name = "PIPELINE",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_PIPELINE,
+        conflicts_with = "manifest"
+    )]
+    pub record_green: Option<String>,
+
+    /// Instead of running once, run twice in each project and report the projects whose runs
+    /// differ in exit code, output, or the files of `--output`, as caching them isn't safe.
+    #[arg(
+        long,
+        conflicts_with_all = ["cache", "record_durations", "report", "record_green"]
+    )]
+    pub check_determinism: bool,
+
+    /// A file the command writes, relative to the project directory, to compare between the
+    /// runs of `--check-determinism`.
+    #[arg(long = "output", value_name = "FILE", requires = "check_determinism")]
+    pub outputs: Vec<PathBuf>,
+}
+
+pub fn run(
+    args: &RunArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        redactor,
+        ..
+    } = loaded;
+    let timeouts = declaration.timeouts;
+    let roots = PathRoots::from_declaration(&declaration.path_roots);
+    let parameters = task_parameters(declaration.constants.clone(), &args.arguments)?;
+    let scope = CacheScope::from_declaration(&declaration.cache, &parameters)?;
+
+    #[cfg(feature = "signing")]
+    let signing = EntrySigning::from_declaration(
+        &declaration.cache,
+        std::env::var(SIGNING_KEY_ENV).ok().as_deref(),
+    )?;
+
+    // Reading entries without checking them would defeat the keys.
+    #[cfg(not(feature = "signing"))]
+    if args.cache && !declaration.cache.trusted_keys.is_empty() {
+        return Err(CliError::SigningUnsupported);
+    }
+
+    let remote = declaration.cache.remote.clone();
+    #[cfg(feature = "http")]
+    let credentials = declaration.credentials.clone();
+    let mut workspace = build_workspace(&root, source.as_deref(), declaration)?;
+
+    let mut runner = SystemProcessRunner::new();
+
+    if let Some(timeout) = timeouts.task() {
+        runner = runner.with_timeout(timeout);
+    }
+
+    if remote.is_some() && args.cache && context.is_offline() {
+        eprintln!("warning: offline, using the local cache only");
+    }
+
+    #[cfg(feature = "http")]
+    let remote = match &remote {
+        Some(remote) if !context.is_offline() => {
+            let chain = credentials.chain(&remote.token_env, &runner);
+            let cache = HttpRemoteCache::from_declaration(remote, &chain)?;
+
+            Some(match timeouts.cache() {
+                Some(timeout) => cache.with_timeout(timeout),
+                None => cache,
+            })
+        }
+        _ => None,
+    };
+
+    #[cfg(not(feature = "http"))]
+    if remote.is_some() && args.cache && !context.is_offline() {
+        eprintln!("warning: this build has no HTTP support, using the local cache only");
+    }
+
+    let local = LocalCacheStore::in_workspace(&root);
+    let store = TieredCacheStore::new(&local);
+
+    #[cfg(feature = "http")]
+    let store = match &remote {
+        Some(remote) => store.with_remote(remote),
+        None => store,
+    };
+
+    #[cfg(feature = "signing")]
+    let store = match signing {
+        Some(signing) => store.with_signing(signing),
+        None => store,
+    };
+
+    let mut tasks = TaskRunner::new(&runner)
+        .with_jobs(usize::from(args.jobs))
+        .with_parameters(parameters.clone())
+        .with_redactor(redactor.clone())
+        .with_path_roots(roots.clone());
+
+    if args.cache {
+        tasks = tasks.with_cache(&store, scope, &OsFileSystem);
+    }
+
+    let selected: HashSet<ProjectId> = if args.affected {
+        mark_affected(
+            &args.diff,
+            &root,
+            &timeouts,
+            &roots,
+            &mut workspace,
+            context,
+        )?;
+
+        // Changes only stop at a project with a contract if its contract passes in this run.
+        let contracts = tasks.gate_contracts(&mut workspace, context)?;
+
+        for result in &contracts.results {
+            let project = describe(&workspace, &root, result.project, false);
+
+            if result.status.is_success() {
+                eprintln!("The contract of {project} passed, its dependents aren't affected");
+            } else {
+                eprintln!("The contract of {project} failed, its dependents are affected");
+            }
+        }
+
+        workspace.affected_projects().collect()
+    } else {
+        workspace.iter_with_ids().map(|(id, _)| id).collect()
+    };
+
+    let selected = args.shard.select(&workspace, selected)?;
+
+    if args.check_determinism {
+        let commands = determinism_commands(args, &parameters, &workspace, selected)?;
+
+        return check_determinism(args, &runner, &workspace, &root, commands, context, out);
+    }
+
+    let report = match &args.target {
+        Some(target) => tasks.run(&workspace, target, selected, context)?,
+        None => tasks.run_command(
+            &workspace,
+            &shell_command_line(&args.command)?,
+            selected,
+            context,
+        )?,
+    };
+
+    if let Some(err) = store.fallback() {
+        eprintln!("warning: {err}, using the local cache only");
+    }
+
+    #[cfg(feature = "signing")]
+    for err in store.rejected() {
+        eprintln!("warning: {err}, ran its task instead");
+    }
+
+    for err in tasks.undecodable() {
+        eprintln!("warning: {err}, ran its task instead");
+    }
+
+    print_report(&report, &workspace, &root, &redactor, out)?;
+
+    for warning in &report.warnings {
+        eprintln!("warning: {warning}, its project ran without the cache");
+    }
+
+    if let Some(path) = &args.report {
+        // Only strings and numbers are serialized, which can't fail.
+        let content = serde_json::to_string_pretty(&JsonTaskReport::new(&workspace, &report))
+            .unwrap_or_default();
+        let content = redactor.redact(&content);
+
+        std::fs::write(path, content.as_bytes())
+            .map_err(|err| CliError::WriteReport(path.clone(), err))?;
+    }
+
+    // Durations only balance later shards, failing to record them doesn't fail the run.
+    if let (Some(target), true) = (&args.target, args.record_durations) {
+        let path = root.join(DURATIONS_FILE);
+
+        let recorded = Durations::load(&path).and_then(|mut durations| {
+            durations.record(target, &workspace, &report);
+            durations.save(&path)
+        });
+
+        if let Err(err) = recorded {
+            eprintln!("warning: {err}");
+        }
+    }
+
+    if !report.is_success() {
+        let mut failed: Vec<String> = report
+            .unsuccessful()
+            .map(|id| describe(&workspace, &root, id, false))
+            .collect();
+        failed.sort_by(|a, b| natural_cmp(a, b));
+
+        return Err(CliError::CommandFailed(failed));
+    }
+
+    if let Some(pipeline) = &args.record_green {
+        let engine = open_engine(&args.diff, &root, &timeouts)?;
+        let commit = engine.resolve(args.diff.to.as_deref().unwrap_or("HEAD"))?;
+
+        LastGreen::new(&local).record(pipeline, &commit)?;
+        eprintln!("Recorded {commit} as the last green run of {pipeline}");
+    }
+
+    Ok(())
+}
+
+/// Returns `command` as a shell command line, each argument quoted, so e.g.
+/// `grep "foo bar" file` still searches for `foo bar`.
+pub fn shell_command_line(command: &[String]) -> Result<String, CliError> {
+    Ok(shlex::try_join(command.iter().map(String::as_str))?)
+}
+
+/// Groups `selected` by the command they run, interpolated, since the commands of a target
+/// differ between projects. Projects not defining the target are left out.
+fn determinism_commands(
+    args: &RunArgs,
+    parameters: &Parameters,
+    workspace: &Workspace,
+    selected: HashSet<ProjectId>,
+) -> Result<BTreeMap<String, Vec<ProjectId>>, CliError> {
+    let command = shell_command_line(&args.command)?;
+    let mut commands: BTreeMap<String, Vec<ProjectId>> = BTreeMap::new();
+
+    for (id, project) in workspace.iter_with_ids() {
+        if !selected.contains(&id) {
+            continue;
+        }
+
+        let template = match &args.target {
+            Some(target) => match project.target(target) {
+                Some(target) => target.command.as_str(),
+                None => continue,
+            },
+            None => command.as_str(),
+        };
+
+        commands
+            .entry(parameters.interpolate(template)?)
+            .or_default()
+            .push(id);
+    }
+
+    Ok(commands)
+}
+
+/// Runs each command twice in its projects, and prints how the runs of each project whose runs
+/// differ did.
+///
+/// # Returns
+/// - `Ok(())`: If every project ran deterministically.
+/// - `Err(CliError::Nondeterministic)`: If the runs of some projects differ.
+fn check_determinism(
+    args: &RunArgs,
+    runner: &SystemProcessRunner,
+    workspace: &Workspace,
+    root: &Path,
+    commands: BTreeMap<String, Vec<ProjectId>>,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let mut found = Vec::new();
+    let mut checked = 0;
+
+    for (command, projects) in &commands {
+        checked += projects.len();
+        found.extend(find_nondeterministic_projects(
+            runner,
+            &OsFileSystem,
+            workspace,
+            projects,
+            command,
+            &args.outputs,
+            context,
+        )?);
+    }
+
+    let mut found: Vec<_> = found
+        .into_iter()
+        .map(|report| (describe(workspace, root, report.project, false), report))
+        .collect();
+    found.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+
+    for (project, report) in &found {
+        writeln!(out, "{project}")?;
+
+        for difference in &report.differences {
+            match difference {
+                Difference::ExitCode(first, second) => writeln!(
+                    out,
+                    "  exited with {} then {}",
+                    exit_code(*first),
+                    exit_code(*second)
+                )?,
+                Difference::Stdout => writeln!(out, "  printed different output")?,
+                Difference::Output(path) => {
+                    writeln!(out, "  wrote a different {}", path.display())?
+                }
+            }
+        }
+    }
+
+    if found.is_empty() {
+        eprintln!("The runs matched in the {checked} checked projects");
+
+        return Ok(());
+    }
+
+    Err(CliError::Nondeterministic(
+        found.into_iter().map(|(project, _)| project).collect(),
+    ))
+}
+
+fn exit_code(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("code {code}"),
+        None => "a signal".to_owned(),
+    }
+}
+
+/// Returns the constants of the declaration, overridden by the `key=value` `arguments`.
+pub fn task_parameters(
+    constants: HashMap<String, String>,
+    arguments: &[String],
+) -> Result<Parameters, CliError> {
+    let mut parameters = Parameters::new(constants);
+    parameters.apply_arguments(arguments)?;
+
+    Ok(parameters)
+}
+
+/// Prints the output and outcome of each task of `report`, in the order they ran, masking the
+/// secrets of `redactor`.
+pub fn print_report(
+    report: &TaskReport,
+    workspace: &Workspace,
+    root: &Path,
+    redactor: &Redactor,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let mut out = RedactingWriter::new(out, redactor.clone());
+    let mut err = RedactingWriter::new(std::io::stderr(), redactor.clone());
+
+    for result in &report.results {
+        writeln!(
+            out,
+            "> {}",
+            describe(workspace, root, result.project, false)
+        )?;
+
+        match &result.status {
+            TaskStatus::Succeeded(output) => print_output(output, &mut out, &mut err)?,
+            TaskStatus::Cached(output) => {
+                print_output(output, &mut out, &mut err)?;
+                writeln!(err, "Replayed from the cache")?;
+            }
+            TaskStatus::Failed(output) => {
+                print_output(output, &mut out, &mut err)?;
+
+                match output.code {
+                    Some(code) => writeln!(err, "The command exited with code {code}")?,
+                    None => writeln!(err, "The command was terminated by a signal")?,
+                }
+            }
+            TaskStatus::TimedOut => writeln!(err, "The command timed out")?,
+            TaskStatus::Skipped => writeln!(err, "Skipped, as a dependency failed")?,
+            _ => {}
+        }
+    }
+
+    out.into_inner()?;
+    err.into_inner()?;
+
+    Ok(())
+}
+
+fn print_output(
+    output: &ProcessOutput,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<(), CliError> {
+    out.write_all(&output.stdout)?;
+    err.write_all(&output.stderr)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use parmenides_lib::context::Context;
+    use parmenides_lib::redaction::MASK;
+
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, RunArgs};
+
+    /// Returns the content of every file under `directory`.
+    fn contents(directory: &std::path::Path) -> Vec<String> {
+        let mut found = vec![];
+
+        for entry in std::fs::read_dir(directory).into_iter().flatten().flatten() {
+            let path = entry.path();
+
+            if path.is_dir() {
+                found.extend(contents(&path));
+            } else {
+                found.push(String::from_utf8_lossy(&std::fs::read(path).unwrap()).into());
+            }
+        }
+
+        found
+    }
+
+    #[test]
+    pub fn when_output_has_secrets_should_mask_them_everywhere_it_is_written() {
+        let root = TempDir::with_declaration(
+            "redact",
+            "[redaction]\npatterns = [\"hunter[0-9]\"]\n\n\
+             [projects.app]\nname = \"app\"\n\
+             targets = { leak = { command = \"echo password=hunter2\" } }\n",
+        );
+        std::fs::create_dir_all(root.join("app")).unwrap();
+
+        let report = root.join("report.json");
+        let args: RunArgs = parse_args(&[
+            "run",
+            "--target",
+            "leak",
+            "--cache",
+            "--report",
+            report.to_str().unwrap(),
+        ]);
+
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &Context::new(), &mut out);
+
+        let report = std::fs::read_to_string(&report);
+        let cached = contents(&root.join(".parmenides/cache"));
+
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(out, format!("> app\npassword={MASK}\n"));
+        assert!(!report.unwrap().contains("hunter2"));
+        // Entries start with their integrity hash, and store the output as bytes.
+        let stdout: Vec<String> = cached
+            .iter()
+            .map(|entry| {
+                let (_, entry) = entry.split_once('\n').unwrap();
+                let entry: serde_json::Value = serde_json::from_str(entry).unwrap();
+                let bytes: Vec<u8> = serde_json::from_value(entry["stdout"].clone()).unwrap();
+
+                String::from_utf8(bytes).unwrap()
+            })
+            .collect();
+
+        assert_eq!(stdout, [format!("password={MASK}\n")]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_checking_determinism_should_report_the_projects_whose_runs_differ() {
+        let root = TempDir::with_declaration(
+            "determinism",
+            "[projects.stamped]\nname = \"stamped\"\n\
+             targets = { build = { command = \"echo run >> log; cat log > out\" } }\n\n\
+             [projects.stable]\nname = \"stable\"\n\
+             targets = { build = { command = \"echo run > out\" } }\n",
+        );
+        std::fs::create_dir_all(root.join("stamped")).unwrap();
+        std::fs::create_dir_all(root.join("stable")).unwrap();
+
+        let args: RunArgs = parse_args(&[
+            "run",
+            "--target",
+            "build",
+            "--check-determinism",
+            "--output",
+            "out",
+        ]);
+
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &Context::new(), &mut out);
+
+        assert!(
+            matches!(result, Err(CliError::Nondeterministic(projects)) if projects == ["stamped"])
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "stamped\n  wrote a different out\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_command_arguments_have_spaces_should_pass_them_unsplit() {
+        let root = TempDir::with_declaration("quote", "[projects.app]\nname = \"app\"\n");
+        std::fs::create_dir_all(root.join("app")).unwrap();
+
+        let args: RunArgs = parse_args(&["run", "--", "printf", "%s\\n", "foo bar", "a;b"]);
+
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &Context::new(), &mut out);
+
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "> app\nfoo bar\na;b\n");
+    }
+}
@@ -0,0 +1,38 @@
+use std::io::Write;
+
+use clap::{Args, ValueEnum};
+use parmenides_lib::schema::Schema;
+
+use crate::errors::CliError;
+
+/// Prints the JSON Schema of the declaration format or of a JSON output, e.g. for an editor
+/// to validate and complete `parmenides.yaml`.
+#[derive(Args, Debug)]
+pub struct SchemaArgs {
+    /// The schema to print.
+    #[arg(value_enum)]
+    pub schema: SchemaArg,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum SchemaArg {
+    /// The workspace declaration, `parmenides.toml` or `parmenides.yaml`.
+    Declaration,
+    /// The graph printed by `parmenides graph --format json`.
+    Graph,
+    /// The report written by `parmenides run --report`.
+    TaskReport,
+}
+
+/// Prints the schema. It doesn't need a workspace, so it runs before one is loaded.
+pub fn run(args: &SchemaArgs, out: &mut dyn Write) -> Result<(), CliError> {
+    let schema = match args.schema {
+        SchemaArg::Declaration => Schema::Declaration,
+        SchemaArg::Graph => Schema::Graph,
+        SchemaArg::TaskReport => Schema::TaskReport,
+    };
+
+    writeln!(out, "{}", schema.content().trim_end())?;
+
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use clap::Args;
+use parmenides_lib::context::Context;
+use parmenides_lib::path_roots::PathRoots;
+use parmenides_lib::project::ProjectId;
+use parmenides_lib::shard::{shard, stable_shard, Durations, ShardConstraint, DURATIONS_FILE};
+use parmenides_lib::workspace::Workspace;
+
+use crate::commands::affected::{describe, mark_affected, DiffArgs};
+use crate::errors::CliError;
+use crate::load::{build_workspace, LoadedDeclaration};
+
+/// Splits the projects into balanced shards for parallel CI jobs, printing the identifiers of
+/// each shard on its own line.
+///
+/// Projects are weighed by the durations recorded with `parmenides run --record-durations`.
+#[derive(Args, Debug)]
+pub struct ShardArgs {
+    /// How many shards to split into.
+    #[arg(long, short = 'n', value_parser = clap::value_parser!(u16).range(1..))]
+    pub count: u16,
+
+    /// Only shard the projects defining the target with this name, weighed by its durations.
+    #[arg(long, short)]
+    pub target: Option<String>,
+
+    /// Place projects in the same shard as their dependencies or a later one, instead of the
+    /// same one, for pipelines running the shards in order.
+    #[arg(long)]
+    pub ordered: bool,
+
+    /// Place each project by its stable ID instead of by durations, so the shards only change
+    /// with the projects and the count, as with `parmenides run --shard-index`.
+    #[arg(long, conflicts_with = "ordered")]
+    pub stable: bool,
+
+    /// Print only the shard with this index, from 0, e.g. in each job of a matrix.
+    #[arg(long)]
+    pub index: Option<usize>,
+
+    /// Print the shards as a JSON array of arrays of identifiers, e.g. for a matrix definition.
+    #[arg(long, conflicts_with = "index")]
+    pub json: bool,
+
+    /// Only shard the projects affected by the changes between the revisions.
+    #[arg(long)]
+    pub affected: bool,
+
+    #[command(flatten)]
+    pub diff: DiffArgs,
+}
+
+/// Selects the projects of one stable shard, so retried CI jobs run exactly the same ones.
+#[derive(Args, Debug)]
+pub struct ShardSelectionArgs {
+    /// Only run in the projects of the shard with this index, from 0.
+    #[arg(long, requires = "shard_count")]
+    pub shard_index: Option<usize>,
+
+    /// How many shards the projects are split into, by their stable IDs, see `parmenides shard
+    /// --stable`.
+    #[arg(long, requires = "shard_index", value_parser = clap::value_parser!(u16).range(1..))]
+    pub shard_count: Option<u16>,
+}
+
+impl ShardSelectionArgs {
+    /// Keeps the projects of the selected shard, or every project without a selection.
+    pub fn select(
+        &self,
+        workspace: &Workspace,
+        projects: HashSet<ProjectId>,
+    ) -> Result<HashSet<ProjectId>, CliError> {
+        let (Some(index), Some(count)) = (self.shard_index, self.shard_count) else {
+            return Ok(projects);
+        };
+
+        if index >= usize::from(count) {
+            return Err(CliError::ShardOutOfRange { index, count });
+        }
+
+        // Only the stable IDs place the projects, the durations are irrelevant.
+        let estimates = Durations::new().estimates(workspace, "", projects);
+        let mut shards = stable_shard(workspace, &estimates, usize::from(count))?;
+
+        Ok(shards.swap_remove(index).projects.into_iter().collect())
+    }
+}
+
+pub fn run(
+    args: &ShardArgs,
+    loaded: LoadedDeclaration,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    if let Some(index) = args.index.filter(|index| *index >= usize::from(args.count)) {
+        return Err(CliError::ShardOutOfRange {
+            index,
+            count: args.count,
+        });
+    }
+
+    let LoadedDeclaration {
+        root,
+        source,
+        declaration,
+        ..
+    } = loaded;
+    let timeouts = declaration.timeouts;
+    let roots = PathRoots::from_declaration(&declaration.path_roots);
+
+    let mut workspace = build_workspace(&root, source.as_deref(), declaration)?;
+
+    let projects: Vec<ProjectId> = if args.affected {
+        mark_affected(
+            &args.diff,
+            &root,
+            &timeouts,
+            &roots,
+            &mut workspace,
+            context,
+        )?
+        .1
+    } else {
+        workspace.iter_with_ids().map(|(id, _)| id).collect()
+    };
+
+    let projects = projects.into_iter().filter(|id| match &args.target {
+        Some(target) => workspace
+            .get_project(*id)
+            .is_some_and(|project| project.target(target).is_some()),
+        None => true,
+    });
+
+    let durations = Durations::load(root.join(DURATIONS_FILE))?;
+    let estimates = durations.estimates(
+        &workspace,
+        args.target.as_deref().unwrap_or_default(),
+        projects,
+    );
+
+    let constraint = if args.ordered {
+        ShardConstraint::Ordered
+    } else {
+        ShardConstraint::CoLocate
+    };
+
+    let count = usize::from(args.count);
+    let shards = if args.stable {
+        stable_shard(&workspace, &estimates, count)?
+    } else {
+        shard(&workspace, &estimates, count, constraint)?
+    };
+
+    let shards: Vec<Vec<String>> = shards
+        .into_iter()
+        .map(|shard| {
+            shard
+                .projects
+                .into_iter()
+                .map(|id| describe(&workspace, &root, id, false))
+                .collect()
+        })
+        .collect();
+
+    if args.json {
+        // Only strings are serialized, which can't fail.
+        writeln!(
+            out,
+            "{}",
+            serde_json::to_string(&shards).unwrap_or_default()
+        )?;
+        return Ok(());
+    }
+
+    for (index, shard) in shards.iter().enumerate() {
+        if args.index.is_none_or(|selected| selected == index) {
+            writeln!(out, "{}", shard.join(" "))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use parmenides_lib::context::Context;
+
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{run, ShardArgs};
+
+    /// Runs `parmenides shard` with `args` in a workspace where `web` depends on `core`.
+    fn shard(name: &str, args: &[&str]) -> (Result<(), CliError>, String) {
+        let root = TempDir::with_declaration(
+            &format!("shard-{name}"),
+            "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+             [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n\n\
+             [projects.\"apps/docs\"]\nname = \"docs\"\n",
+        );
+
+        let args: ShardArgs = parse_args(&[&["shard"], args].concat());
+
+        let mut out = Vec::new();
+        let result = run(&args, root.load(), &Context::new(), &mut out);
+
+        (result, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    pub fn when_sharding_should_place_projects_with_their_dependencies() {
+        let (result, out) = shard("json", &["--count", "2", "--json"]);
+
+        assert!(result.is_ok());
+        assert_eq!(out, "[[\"core\",\"web\"],[\"docs\"]]\n");
+    }
+
+    #[test]
+    pub fn when_index_is_not_a_shard_should_return_error() {
+        let (result, out) = shard("index", &["--count", "2", "--index", "2"]);
+
+        assert!(matches!(
+            result,
+            Err(CliError::ShardOutOfRange { index: 2, count: 2 })
+        ));
+        assert!(out.is_empty());
+    }
+}
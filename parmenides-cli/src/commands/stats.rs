@@ -0,0 +1,41 @@
+use std::io::Write;
+use std::path::Path;
+
+use clap::{Args, Subcommand};
+use parmenides_lib::stats::{read, StatsReport, STATS_FILE};
+
+use crate::errors::CliError;
+
+/// Reports the command runs recorded when `stats.enabled` is set in the declaration.
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[command(subcommand)]
+    pub command: StatsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StatsCommand {
+    /// Prints the runs, median duration and workspace size of each command, month by month.
+    Report,
+}
+
+pub fn run(args: &StatsArgs, root: &Path, out: &mut dyn Write) -> Result<(), CliError> {
+    match args.command {
+        StatsCommand::Report => {
+            let path = root.join(STATS_FILE);
+            let records = read(&path)?;
+
+            if records.is_empty() {
+                eprintln!(
+                    "No runs recorded in {}, set stats.enabled to record them",
+                    path.display()
+                );
+                return Ok(());
+            }
+
+            write!(out, "{}", StatsReport::new(&records).to_text())?;
+        }
+    }
+
+    Ok(())
+}
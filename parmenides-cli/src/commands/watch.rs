@@ -0,0 +1,470 @@
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use parmenides_lib::context::{CancellationToken, Context};
+use parmenides_lib::declarations::WorkspaceDeclaration;
+use parmenides_lib::discovery::Discovery;
+use parmenides_lib::errors::{TaskError, WatchError};
+use parmenides_lib::file_system::OsFileSystem;
+use parmenides_lib::parameters::Parameters;
+use parmenides_lib::path_roots::PathRoots;
+use parmenides_lib::process::SystemProcessRunner;
+use parmenides_lib::project::ProjectId;
+use parmenides_lib::redaction::Redactor;
+use parmenides_lib::sort::natural_cmp;
+use parmenides_lib::tasks::{TaskReport, TaskRunner};
+use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+use parmenides_lib::watch::{open_watcher, watch_affected, Watcher};
+use parmenides_lib::workspace::Workspace;
+
+use crate::commands::affected::describe;
+use crate::commands::run::{print_report, shell_command_line, task_parameters};
+use crate::errors::CliError;
+use crate::load::{build_workspace, parse_declaration, workspace_discovery, LoadedDeclaration};
+
+/// Watches the workspace, printing the projects affected by each change, or running a command
+/// or target in them.
+///
+/// When a declaration file changes, either the declaration or the manifest of a discovered
+/// project, only that file is parsed again and the workspace is validated with it. Watching
+/// goes on with the new workspace, or with the previous file if it is broken, and each changed
+/// file is reported.
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// The command to run in the affected projects, through the platform shell. Each argument
+    /// is quoted, like with `parmenides run`.
+    #[arg(
+        conflicts_with = "target",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub command: Vec<String>,
+
+    /// Run the target with this name in the affected projects defining it, instead of a
+    /// command.
+    #[arg(long, short)]
+    pub target: Option<String>,
+
+    /// Set a parameter the commands reference as `{{ key }}`, overriding the constant of the
+    /// declaration with the same key, e.g. `--arg tag=v1.2.3`.
+    #[arg(long = "arg", value_name = "KEY=VALUE")]
+    pub arguments: Vec<String>,
+
+    /// How many projects to run in at once.
+    #[arg(long, short, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+}
+
+pub fn run(
+    args: &WatchArgs,
+    loaded: LoadedDeclaration,
+    policy: UnknownKeyPolicy,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let declarations = Declarations::new(loaded);
+    let mut watcher = open_watcher(&declarations.root, &declarations.declaration.watch)?;
+
+    watch(args, declarations, watcher.as_mut(), policy, context, out)
+}
+
+/// Runs the watch loop of `args` with `watcher`, until it fails or `context` is cancelled.
+fn watch(
+    args: &WatchArgs,
+    mut declarations: Declarations,
+    watcher: &mut dyn Watcher,
+    policy: UnknownKeyPolicy,
+    context: &Context,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let root = declarations.root.clone();
+    let declaration = &declarations.declaration;
+    let timeouts = declaration.timeouts;
+    let roots = PathRoots::from_declaration(&declaration.path_roots);
+    let (mut workspace, mut parameters, mut redactor) = declarations.build(&args.arguments)?;
+
+    let mut runner = SystemProcessRunner::new();
+
+    if let Some(timeout) = timeouts.task() {
+        runner = runner.with_timeout(timeout);
+    }
+
+    let command = shell_command_line(&args.command)?;
+    let mut failure = None;
+
+    eprintln!("Watching {} for changes", root.display());
+
+    loop {
+        let tasks = TaskRunner::new(&runner)
+            .with_jobs(usize::from(args.jobs))
+            .with_parameters(parameters.clone())
+            .with_redactor(redactor.clone())
+            .with_path_roots(roots.clone());
+
+        let mut watching = ReloadingWatcher {
+            inner: &mut *watcher,
+            declarations: &declarations,
+            parent: context,
+            session: CancellationToken::new(),
+            reload: vec![],
+        };
+        let session = Context::new().with_cancellation(watching.session.clone());
+
+        watch_affected(&workspace, &mut watching, &session, |batch| {
+            let mut affected: Vec<String> = batch
+                .affected
+                .iter()
+                .map(|id| describe(&workspace, &root, *id, false))
+                .collect();
+            affected.sort_by(|a, b| natural_cmp(a, b));
+
+            let selected: HashSet<ProjectId> = batch.affected.iter().copied().collect();
+
+            let result = match &args.target {
+                Some(target) => run_tasks(&root, &workspace, &affected, &redactor, out, || {
+                    tasks.run(&workspace, target, selected, context)
+                }),
+                None if !command.is_empty() => {
+                    run_tasks(&root, &workspace, &affected, &redactor, out, || {
+                        tasks.run_command(&workspace, &command, selected, context)
+                    })
+                }
+                None => writeln!(out, "{}", affected.join(" ")).map_err(CliError::from),
+            };
+
+            match result {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(err) => {
+                    failure = Some(err);
+                    ControlFlow::Break(())
+                }
+            }
+        })?;
+
+        let reload = watching.reload;
+
+        if reload.is_empty() || failure.is_some() || context.is_cancelled() {
+            break;
+        }
+
+        // Only the workspace is rebuilt, the watcher keeps watching the same root.
+        for path in &reload {
+            let relative = path.strip_prefix(&root).unwrap_or(path).display();
+            let mut reloaded = declarations.clone();
+
+            match reloaded
+                .reload(path, policy, context)
+                .and_then(|()| reloaded.build(&args.arguments))
+            {
+                Ok(built) => {
+                    declarations = reloaded;
+                    (workspace, parameters, redactor) = built;
+                    eprintln!("Reloaded {relative}");
+                }
+                Err(err) => {
+                    eprintln!("error: {err}");
+                    eprintln!("Kept the previous {relative}");
+                }
+            }
+        }
+
+        eprintln!("Watching for changes");
+    }
+
+    failure.map_or(Ok(()), Err)
+}
+
+/// The declaration files of the workspace, parsed, so a change to one of them only parses it
+/// again and keeps what the others declare.
+#[derive(Clone)]
+struct Declarations {
+    root: PathBuf,
+    /// The declaration file, in the root where the watchers report it.
+    source: Option<PathBuf>,
+    /// The declaration file as parsed, before its preset adds the discovered projects, when it
+    /// has a preset.
+    declared: Option<WorkspaceDeclaration>,
+    /// The projects discovered from their manifests, by the preset or without a declaration
+    /// file.
+    discovered: Option<WorkspaceDeclaration>,
+    /// The backend the projects are discovered with, if any.
+    discovery: Option<&'static dyn Discovery>,
+    /// The declaration of the whole workspace.
+    declaration: WorkspaceDeclaration,
+}
+
+impl Declarations {
+    fn new(loaded: LoadedDeclaration) -> Self {
+        let LoadedDeclaration {
+            root,
+            source,
+            declaration,
+            declared,
+            discovered,
+            ..
+        } = loaded;
+
+        let discovery = match &source {
+            Some(_) => declaration.preset.map(|preset| preset.discovery()),
+            None => workspace_discovery(&root),
+        };
+
+        Self {
+            source: source.and_then(|source| Some(root.join(source.file_name()?))),
+            root,
+            declared,
+            discovered,
+            discovery,
+            declaration,
+        }
+    }
+
+    /// Returns `true` if a change to `path` can change the declaration.
+    fn is_declaration(&self, path: &Path) -> bool {
+        self.source.as_deref() == Some(path)
+            || self.discovery.is_some_and(|discovery| {
+                path.file_name().is_some_and(|name| {
+                    discovery
+                        .manifest_names()
+                        .iter()
+                        .any(|manifest| name == *manifest)
+                })
+            })
+    }
+
+    /// Parses `path` again, and updates the declaration with it. The projects discovered from
+    /// the other manifests are kept, unless the declaration file changes its preset.
+    fn reload(
+        &mut self,
+        path: &Path,
+        policy: UnknownKeyPolicy,
+        context: &Context,
+    ) -> Result<(), CliError> {
+        if self.source.as_deref() == Some(path) {
+            let declaration = parse_declaration(path, &self.root, policy)?;
+            self.discovery = declaration.preset.map(|preset| preset.discovery());
+
+            let Some(preset) = declaration.preset else {
+                self.declared = None;
+                self.discovered = None;
+                self.declaration = declaration;
+
+                return Ok(());
+            };
+
+            let discovered = match &self.discovered {
+                Some(discovered) if self.declaration.preset == Some(preset) => discovered.clone(),
+                _ => preset
+                    .discovery()
+                    .discover(&OsFileSystem, &self.root, context)?,
+            };
+
+            self.declared = Some(declaration);
+            self.discovered = Some(discovered);
+        } else if let (Some(discovery), Some(discovered)) = (self.discovery, &mut self.discovered) {
+            discovery.rediscover(&OsFileSystem, &self.root, path, discovered, context)?;
+        }
+
+        if let Some(discovered) = &self.discovered {
+            self.declaration = match &self.declared {
+                Some(declared) => {
+                    let mut declaration = declared.clone();
+
+                    if let Some(preset) = declared.preset {
+                        preset.apply_discovered(discovered.clone(), &mut declaration);
+                    }
+
+                    declaration
+                }
+                None => discovered.clone(),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Builds the workspace of the declaration, with the parameters and the redactor of its
+    /// tasks.
+    fn build(&self, arguments: &[String]) -> Result<(Workspace, Parameters, Redactor), CliError> {
+        let parameters = task_parameters(self.declaration.constants.clone(), arguments)?;
+        let redactor = Redactor::from_declaration(&self.declaration.redaction)?;
+        let workspace =
+            build_workspace(&self.root, self.source.as_deref(), self.declaration.clone())?;
+
+        Ok((workspace, parameters, redactor))
+    }
+}
+
+/// Reports the changes of `inner`, cancelling `session` when declaration files change, so the
+/// workspace is reloaded before watching on. The other changes of that batch are dropped.
+struct ReloadingWatcher<'a> {
+    inner: &'a mut dyn Watcher,
+    declarations: &'a Declarations,
+    parent: &'a Context,
+    session: CancellationToken,
+    reload: Vec<PathBuf>,
+}
+
+impl Watcher for ReloadingWatcher<'_> {
+    fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError> {
+        let changed = self.inner.wait()?;
+
+        self.reload.extend(
+            changed
+                .iter()
+                .filter(|path| self.declarations.is_declaration(path))
+                .cloned(),
+        );
+
+        if !self.reload.is_empty() {
+            self.session.cancel();
+        }
+
+        if self.parent.is_cancelled() {
+            self.session.cancel();
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Runs the tasks of a batch and prints their report. A failing task doesn't stop watching, as
+/// it is usually what is being fixed.
+fn run_tasks<F>(
+    root: &Path,
+    workspace: &Workspace,
+    affected: &[String],
+    redactor: &Redactor,
+    out: &mut dyn Write,
+    run: F,
+) -> Result<(), CliError>
+where
+    F: FnOnce() -> Result<TaskReport, TaskError>,
+{
+    eprintln!("Running in {}", affected.join(", "));
+
+    let report = run()?;
+    print_report(&report, workspace, root, redactor, out)?;
+
+    if report.is_success() {
+        eprintln!("Done, watching for changes");
+    } else {
+        eprintln!("Failed, watching for changes");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, VecDeque};
+    use std::path::PathBuf;
+
+    use parmenides_lib::context::Context;
+    use parmenides_lib::errors::WatchError;
+    use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+    use parmenides_lib::watch::Watcher;
+
+    use crate::errors::CliError;
+    use crate::testing::{parse_args, TempDir};
+
+    use super::{watch, Declarations, WatchArgs};
+
+    /// Reports each batch of changes in turn, writing its file first, then fails.
+    struct ScriptedWatcher(VecDeque<(PathBuf, &'static str)>);
+
+    impl Watcher for ScriptedWatcher {
+        fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError> {
+            let (path, content) = self
+                .0
+                .pop_front()
+                .ok_or_else(|| WatchError::Notify("no more changes".to_owned()))?;
+            std::fs::write(&path, content).unwrap();
+
+            Ok(BTreeSet::from([path]))
+        }
+    }
+
+    #[test]
+    pub fn when_watching_should_print_the_affected_projects_of_each_change() {
+        let root = TempDir::with_declaration(
+            "watch-affected",
+            "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+             [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n\n\
+             [projects.\"apps/docs\"]\nname = \"docs\"\n",
+        );
+        std::fs::create_dir_all(root.join("libs/core")).unwrap();
+        std::fs::create_dir_all(root.join("apps/web")).unwrap();
+        std::fs::create_dir_all(root.join("apps/docs")).unwrap();
+
+        let mut watcher = ScriptedWatcher(VecDeque::from([
+            (root.join("libs/core/lib.rs"), "1"),
+            (root.join("apps/web/main.rs"), "1"),
+            // docs now depends on core too.
+            (
+                root.join("parmenides.toml"),
+                "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+                 [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n\n\
+                 [projects.\"apps/docs\"]\nname = \"docs\"\ndependencies = [\"libs/core\"]\n",
+            ),
+            (root.join("libs/core/lib.rs"), "2"),
+        ]));
+
+        let args: WatchArgs = parse_args(&["watch"]);
+
+        let mut out = Vec::new();
+        let result = watch(
+            &args,
+            Declarations::new(root.load()),
+            &mut watcher,
+            UnknownKeyPolicy::Deny,
+            &Context::new(),
+            &mut out,
+        );
+
+        assert!(matches!(result, Err(CliError::Watch(..))));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "core web\nweb\ncore docs web\n"
+        );
+    }
+
+    #[test]
+    pub fn when_a_member_manifest_changes_should_parse_only_it_again() {
+        let root = TempDir::new("watch-reload");
+        root.write("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        root.write("crates/core/Cargo.toml", "[package]\nname = \"core\"\n");
+        root.write(
+            "crates/api/Cargo.toml",
+            "[package]\nname = \"api\"\n\n[dependencies]\ncore = { path = \"../core\" }\n",
+        );
+
+        let context = Context::new();
+        let mut declarations = Declarations::new(root.load());
+
+        // Broken after the load, so parsing it again would fail.
+        root.write("crates/core/Cargo.toml", "[package");
+        root.write("crates/api/Cargo.toml", "[package]\nname = \"api\"\n");
+
+        let api = root.join("crates/api/Cargo.toml");
+        let member = declarations.reload(&api, UnknownKeyPolicy::Deny, &context);
+        let dependencies = declarations.declaration.projects[&root.join("crates/api")]
+            .dependencies
+            .clone();
+        let built = declarations
+            .build(&[])
+            .map(|(workspace, _, _)| workspace.len());
+        let manifest = root.join("Cargo.toml");
+        let workspace = declarations.reload(&manifest, UnknownKeyPolicy::Deny, &context);
+
+        assert!(declarations.is_declaration(&api));
+        assert!(!declarations.is_declaration(&root.join("crates/api/src/lib.rs")));
+        assert!(member.is_ok());
+        assert_eq!(dependencies, None);
+        assert_eq!(built.unwrap(), 2);
+        assert!(workspace.is_err());
+    }
+}
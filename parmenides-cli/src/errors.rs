@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+
+use parmenides_lib::errors::{
+    BaselineError, BisectError, BuildWorkspaceError, CacheError, ComputeAffectedError,
+    ComputeMergeAffectedError, ConstraintError, CredentialError, DeterminismError, DiffEngineError,
+    DiscoveryError, DurationsError, EditDeclarationError, EncryptionError, GenerateError,
+    HealthError, ImportCatalogError, InterpolateError, LintConfigError, LoadDeclarationError,
+    MoveProjectError, ParseArgumentError, RedactionError, SelectorError, StatsError, TaskError,
+    TopologicalOrderError, WatchError,
+};
+use thiserror::Error;
+
+/// Errors reported by the command line, before exiting with a failure code.
+#[derive(Error, Debug)]
+pub enum CliError {
+    /// Indicates that no declaration file nor known workspace manifest was found.
+    #[error("Could not find a parmenides declaration or a Cargo or npm workspace in {0}")]
+    NoDeclaration(PathBuf),
+
+    /// Indicates that no git repository contains the workspace.
+    #[error("Could not find a git repository containing {0}")]
+    NoRepository(PathBuf),
+
+    /// Indicates that a command editing or reading the declaration file at a past commit ran in
+    /// a discovered workspace, which has none.
+    #[error("This command needs a declaration file, but the workspace in {0} was discovered")]
+    NoDeclarationFile(PathBuf),
+
+    /// Indicates that no project has the given identifier.
+    #[error("Could not find a project with the identifier {0}")]
+    UnknownProject(String),
+
+    /// Indicates that a selector naming a single project matches several.
+    #[error("The selector {0} matches {1} projects instead of one")]
+    AmbiguousProject(String, usize),
+
+    /// Indicates that a command run in projects failed in some of them.
+    #[error("The command failed in {}", .0.join(", "))]
+    CommandFailed(Vec<String>),
+
+    /// Indicates that running a command twice gave different results in some projects.
+    #[error("The runs differ in {}", .0.join(", "))]
+    Nondeterministic(Vec<String>),
+
+    /// Indicates that the shard to print is past the number of shards.
+    #[error("There is no shard {index}, only {count} shards numbered from 0")]
+    ShardOutOfRange { index: usize, count: u16 },
+
+    /// Indicates that some `doctor` checks failed.
+    #[error("{0} of the doctor checks failed")]
+    ChecksFailed(usize),
+
+    /// Indicates that dependencies of the workspace break its constraints.
+    #[error("{0} dependencies break the constraints of the workspace")]
+    ConstraintsViolated(usize),
+
+    /// Indicates that lint rules with the error severity are broken.
+    #[error("{0} lint violations with the error severity were found")]
+    LintFailed(usize),
+
+    /// Indicates that the cache declares trusted keys, but signing isn't supported by this
+    /// build.
+    #[cfg(not(feature = "signing"))]
+    #[error("The cache declares trusted keys, but this build has no signing support")]
+    SigningUnsupported,
+
+    /// Indicates that entries of the local cache don't match their integrity hash.
+    #[error("{0} entries of the cache are corrupted")]
+    CacheCorrupted(usize),
+
+    /// Indicates that the relations of a service catalog drifted from the workspace.
+    #[error("{0} relations of the catalog drifted from the workspace")]
+    CatalogDrifted(usize),
+
+    /// Indicates that the dependencies read by a traced target drifted from the declared ones.
+    #[error("{0} dependencies drifted from the declaration")]
+    DependenciesDrifted(usize),
+
+    /// Indicates that a service catalog to check could not be read.
+    #[error("Could not read the catalog {0}: {1}")]
+    ReadCatalog(PathBuf, std::io::Error),
+
+    /// Indicates that the report of a run could not be written.
+    #[error("Could not write the report {0}: {1}")]
+    WriteReport(PathBuf, std::io::Error),
+
+    /// Indicates that an argument of the command to run can't be quoted for the shell.
+    #[error("Could not quote the command for the shell: {0}")]
+    QuoteCommand(#[from] shlex::QuoteError),
+
+    /// Indicates that the output could not be written.
+    #[error("Could not write the output: {0}")]
+    Output(#[from] std::io::Error),
+
+    /// Indicates that the current directory could not be read.
+    #[error("Could not read the current directory: {0}")]
+    CurrentDirectory(std::io::Error),
+
+    #[error(transparent)]
+    LoadDeclaration(#[from] LoadDeclarationError),
+
+    #[error(transparent)]
+    Discovery(#[from] DiscoveryError),
+
+    #[error(transparent)]
+    BuildWorkspace(#[from] BuildWorkspaceError),
+
+    #[error(transparent)]
+    Selector(#[from] SelectorError),
+
+    #[error(transparent)]
+    Constraint(#[from] ConstraintError),
+
+    #[error(transparent)]
+    EditDeclaration(#[from] EditDeclarationError),
+
+    #[error(transparent)]
+    DiffEngine(#[from] DiffEngineError),
+
+    #[error(transparent)]
+    ImportCatalog(#[from] ImportCatalogError),
+
+    #[error(transparent)]
+    Credential(#[from] CredentialError),
+
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+
+    #[error(transparent)]
+    ComputeAffected(#[from] ComputeAffectedError),
+
+    #[error(transparent)]
+    Bisect(#[from] BisectError),
+
+    #[error(transparent)]
+    ComputeMergeAffected(#[from] ComputeMergeAffectedError),
+
+    #[error(transparent)]
+    Task(#[from] TaskError),
+
+    #[error(transparent)]
+    Determinism(#[from] DeterminismError),
+
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+
+    #[error(transparent)]
+    TopologicalOrder(#[from] TopologicalOrderError),
+
+    #[error(transparent)]
+    Durations(#[from] DurationsError),
+
+    #[error(transparent)]
+    Interpolate(#[from] InterpolateError),
+
+    #[error(transparent)]
+    ParseArgument(#[from] ParseArgumentError),
+
+    #[error(transparent)]
+    Redaction(#[from] RedactionError),
+
+    #[error(transparent)]
+    Stats(#[from] StatsError),
+
+    #[error(transparent)]
+    Generate(#[from] GenerateError),
+
+    #[error(transparent)]
+    Health(#[from] HealthError),
+
+    #[error(transparent)]
+    MoveProject(#[from] MoveProjectError),
+
+    #[error(transparent)]
+    LintConfig(#[from] LintConfigError),
+
+    #[error(transparent)]
+    Baseline(#[from] BaselineError),
+
+    #[error(transparent)]
+    Watch(#[from] WatchError),
+
+    #[cfg(target_os = "linux")]
+    #[error(transparent)]
+    Trace(#[from] parmenides_lib::errors::TraceError),
+
+    #[cfg(feature = "signing")]
+    #[error(transparent)]
+    CacheSignature(#[from] parmenides_lib::errors::CacheSignatureError),
+
+    #[cfg(feature = "svg")]
+    #[error(transparent)]
+    Render(#[from] parmenides_lib::errors::RenderError),
+}
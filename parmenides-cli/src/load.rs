@@ -0,0 +1,249 @@
+//! Finds and loads the workspace the command line operates on.
+use std::path::{Path, PathBuf};
+
+use parmenides_lib::context::Context;
+use parmenides_lib::declarations::WorkspaceDeclaration;
+use parmenides_lib::discovery::{CargoDiscovery, Discovery, NodeDiscovery};
+use parmenides_lib::file_system::OsFileSystem;
+use parmenides_lib::process::SystemProcessRunner;
+use parmenides_lib::redaction::Redactor;
+use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+use parmenides_lib::workspace::Workspace;
+
+use crate::errors::CliError;
+
+/// The declaration file names looked up, in order, in each directory.
+const DECLARATION_FILES: [&str; 3] = ["parmenides.toml", "parmenides.yaml", "parmenides.yml"];
+
+/// A loaded declaration and the directory it describes.
+pub struct LoadedDeclaration {
+    pub root: PathBuf,
+    /// The declaration file, or `None` when the workspace was discovered.
+    pub source: Option<PathBuf>,
+    pub declaration: WorkspaceDeclaration,
+    /// The declaration file as parsed, before its preset adds the discovered projects, when it
+    /// has a preset.
+    pub declared: Option<WorkspaceDeclaration>,
+    /// The projects discovered from the manifests, by the preset or without a declaration
+    /// file, before the declaration overrides them.
+    pub discovered: Option<WorkspaceDeclaration>,
+    /// Masks the secrets of the declaration, see [`parmenides_lib::redaction`].
+    pub redactor: Redactor,
+}
+
+/// Returns the first declaration file found in `start` or one of its ancestors.
+pub fn find_declaration(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(|directory| {
+        DECLARATION_FILES
+            .iter()
+            .map(|name| directory.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Returns the root of the git repository containing `start`.
+pub fn find_repository(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|directory| directory.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Loads the declaration at `path`, or the one found from `start`. Without one, the Cargo or
+/// npm workspace at `start` is discovered instead. The paths discovery skipped are printed to
+/// stderr.
+///
+/// Unknown keys in the declaration are handled by `policy`, and printed to stderr when warned
+/// about. Encrypted values are decrypted, see [`parmenides_lib::encryption`], and the projects
+/// of the preset are discovered, see [`parmenides_lib::preset`]. The [`Redactor`] of the
+/// declaration is built once here, for every command printing or storing task output.
+pub fn load_declaration(
+    path: Option<&Path>,
+    start: &Path,
+    policy: UnknownKeyPolicy,
+    context: &Context,
+) -> Result<LoadedDeclaration, CliError> {
+    if let Some(path) = path
+        .map(Path::to_path_buf)
+        .or_else(|| find_declaration(start))
+    {
+        let root = path.parent().unwrap_or(start).to_path_buf();
+        let mut declaration = parse_declaration(&path, &root, policy)?;
+
+        let (declared, discovered) = match declaration.preset {
+            Some(preset) => {
+                let declared = declaration.clone();
+                let discovered = preset.discovery().discover(&OsFileSystem, &root, context)?;
+                preset.apply_discovered(discovered.clone(), &mut declaration);
+
+                (Some(declared), Some(discovered))
+            }
+            None => (None, None),
+        };
+
+        for warning in &declaration.warnings {
+            eprintln!("warning: {warning}");
+        }
+
+        let redactor = Redactor::from_declaration(&declaration.redaction)?;
+
+        return Ok(LoadedDeclaration {
+            root,
+            source: Some(path),
+            declaration,
+            declared,
+            discovered,
+            redactor,
+        });
+    }
+
+    let discovery =
+        workspace_discovery(start).ok_or_else(|| CliError::NoDeclaration(start.to_path_buf()))?;
+
+    let declaration = discovery.discover(&OsFileSystem, start, context)?;
+
+    for warning in &declaration.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    let redactor = Redactor::from_declaration(&declaration.redaction)?;
+
+    Ok(LoadedDeclaration {
+        root: start.to_path_buf(),
+        source: None,
+        declared: None,
+        discovered: Some(declaration.clone()),
+        declaration,
+        redactor,
+    })
+}
+
+/// Parses the declaration file at `path`, in `root`, printing its unknown keys to stderr when
+/// `policy` warns about them, and decrypts its encrypted values. Its preset, if any, isn't
+/// applied.
+pub fn parse_declaration(
+    path: &Path,
+    root: &Path,
+    policy: UnknownKeyPolicy,
+) -> Result<WorkspaceDeclaration, CliError> {
+    let (mut declaration, unknown) = WorkspaceDeclaration::from_path_checked(path, policy)?;
+
+    for key in unknown {
+        eprintln!("warning: {}: {key}", path.display());
+    }
+
+    let runner = SystemProcessRunner::new();
+    let encryption = declaration.encryption.clone();
+    encryption
+        .decrypter(root, &runner)
+        .decrypt_declaration(&mut declaration)?;
+
+    Ok(declaration)
+}
+
+/// Returns the backend discovering the workspace at `start` without a declaration file, for
+/// the manifest found there.
+pub fn workspace_discovery(start: &Path) -> Option<&'static dyn Discovery> {
+    if start.join("Cargo.toml").is_file() {
+        Some(&CargoDiscovery)
+    } else if start.join("package.json").is_file() {
+        Some(&NodeDiscovery)
+    } else {
+        None
+    }
+}
+
+/// Builds the workspace of a declaration. With `cache.snapshot` enabled, the workspace is
+/// loaded from its snapshot instead while the declaration file at `source` is unchanged, and
+/// the snapshot is written again otherwise.
+///
+/// A snapshot that can't be read or written only costs the time saved, so it is warned about
+/// rather than failing the command.
+#[cfg(feature = "snapshot")]
+pub fn build_workspace(
+    root: &Path,
+    source: Option<&Path>,
+    declaration: WorkspaceDeclaration,
+) -> Result<Workspace, CliError> {
+    use parmenides_lib::snapshot::{self, SNAPSHOT_FILE};
+
+    // The path is hashed too, as projects are stored by absolute path, so a moved checkout
+    // isn't loaded with the paths of the old one.
+    let content = match source {
+        // The projects of a preset come from manifests the hash doesn't cover.
+        Some(source) if declaration.cache.snapshot && declaration.preset.is_none() => {
+            std::fs::read(source).ok().map(|content| {
+                let mut hashed = source.to_string_lossy().into_owned().into_bytes();
+                hashed.push(0);
+                hashed.extend(content);
+                hashed
+            })
+        }
+        _ => None,
+    };
+
+    let Some(content) = content else {
+        return Ok(declaration.build_workspace()?);
+    };
+
+    let path = root.join(SNAPSHOT_FILE);
+    let hash = snapshot::content_hash(&content);
+
+    match snapshot::load(&path, &hash) {
+        Ok(Some(workspace)) => return Ok(workspace),
+        Ok(None) => {}
+        Err(err) => eprintln!("warning: {err}, building the workspace instead"),
+    }
+
+    let workspace = declaration.build_workspace()?;
+
+    if let Err(err) = snapshot::save(&path, &workspace, &hash) {
+        eprintln!("warning: {err}");
+    }
+
+    Ok(workspace)
+}
+
+/// Builds the workspace of a declaration. This build has no snapshot support, so
+/// `cache.snapshot` is ignored.
+#[cfg(not(feature = "snapshot"))]
+pub fn build_workspace(
+    _: &Path,
+    _: Option<&Path>,
+    declaration: WorkspaceDeclaration,
+) -> Result<Workspace, CliError> {
+    Ok(declaration.build_workspace()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use parmenides_lib::context::Context;
+    use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+
+    use crate::testing::TempDir;
+
+    use super::{find_declaration, load_declaration};
+
+    #[test]
+    pub fn when_declaration_is_in_ancestor_should_find_and_load_it() {
+        let root = TempDir::with_declaration("load", "[projects.\"apps/web\"]\nname = \"web\"\n");
+        let nested = root.join("apps/web");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let found = find_declaration(&nested);
+        let loaded = load_declaration(None, &nested, UnknownKeyPolicy::Deny, &Context::new());
+        let missing = load_declaration(
+            None,
+            &std::env::temp_dir().join("missing"),
+            UnknownKeyPolicy::Warn,
+            &Context::new(),
+        );
+
+        assert_eq!(found, Some(root.join("parmenides.toml")));
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.root, *root);
+        assert!(loaded.declaration.projects.contains_key(&nested));
+        assert!(missing.is_err());
+    }
+}
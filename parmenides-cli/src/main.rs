@@ -1,3 +1,248 @@
-fn main() {
-    println!("Hello, world!");
+//! # Parmenides
+//!
+//! The command line of parmenides, for CI pipelines and people. Every command loads the
+//! workspace declaration found from the current directory, or discovers the Cargo or npm
+//! workspace there.
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+use clap::{Parser, Subcommand};
+use parmenides_lib::context::Context;
+use parmenides_lib::stats::{self, StatsRecord, STATS_FILE};
+use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+use parmenides_lib::warnings::IoPolicy;
+
+mod commands;
+mod errors;
+mod load;
+mod progress;
+#[cfg(test)]
+mod testing;
+
+use commands::affected::AffectedArgs;
+use commands::bisect::BisectArgs;
+use commands::cache::CacheArgs;
+use commands::catalog::CatalogArgs;
+use commands::constraints::ConstraintsArgs;
+use commands::deprecated::DeprecatedArgs;
+use commands::doctor::DoctorArgs;
+#[cfg(target_os = "linux")]
+use commands::drift::DriftArgs;
+use commands::features::FeaturesArgs;
+use commands::generate::GenerateArgs;
+use commands::graph::GraphArgs;
+use commands::health::HealthArgs;
+use commands::lint::LintArgs;
+use commands::manifest::ManifestArgs;
+use commands::move_project::MoveArgs;
+use commands::rename::RenameArgs;
+use commands::run::RunArgs;
+use commands::schema::SchemaArgs;
+use commands::shard::ShardArgs;
+use commands::stats::StatsArgs;
+use commands::watch::WatchArgs;
+use errors::CliError;
+use progress::BarProgress;
+
+#[derive(Parser, Debug)]
+#[command(name = "parmenides", version, about)]
+struct Cli {
+    /// The declaration file. Defaults to the first `parmenides.toml`, `.yaml` or `.yml` found
+    /// from the current directory upwards.
+    #[arg(long, global = true)]
+    declaration: Option<PathBuf>,
+
+    /// Fail instead of warning when the declaration has unknown keys.
+    #[arg(long, global = true)]
+    deny_unknown_keys: bool,
+
+    /// Fail instead of warning when a manifest, directory or input file can't be read, e.g.
+    /// without permission or through a broken symlink.
+    #[arg(long, global = true)]
+    strict_io: bool,
+
+    /// Never use the network: the remote cache and fetches are skipped with a warning, and
+    /// only what is available locally is used.
+    #[arg(long, global = true)]
+    offline: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    Affected(AffectedArgs),
+    Bisect(BisectArgs),
+    Cache(CacheArgs),
+    Catalog(CatalogArgs),
+    Constraints(ConstraintsArgs),
+    Deprecated(DeprecatedArgs),
+    Doctor(DoctorArgs),
+    #[cfg(target_os = "linux")]
+    Drift(DriftArgs),
+    Features(FeaturesArgs),
+    Generate(GenerateArgs),
+    Graph(GraphArgs),
+    Health(HealthArgs),
+    Lint(LintArgs),
+    Manifest(ManifestArgs),
+    Move(MoveArgs),
+    Rename(RenameArgs),
+    Run(RunArgs),
+    Schema(SchemaArgs),
+    Shard(ShardArgs),
+    Stats(StatsArgs),
+    Watch(WatchArgs),
+}
+
+impl Command {
+    /// The name runs are recorded under, see [`parmenides_lib::stats`].
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Affected(_) => "affected",
+            Command::Bisect(_) => "bisect",
+            Command::Cache(_) => "cache",
+            Command::Catalog(_) => "catalog",
+            Command::Constraints(_) => "constraints",
+            Command::Deprecated(_) => "deprecated",
+            Command::Doctor(_) => "doctor",
+            #[cfg(target_os = "linux")]
+            Command::Drift(_) => "drift",
+            Command::Features(_) => "features",
+            Command::Generate(_) => "generate",
+            Command::Graph(_) => "graph",
+            Command::Health(_) => "health",
+            Command::Lint(_) => "lint",
+            Command::Manifest(_) => "manifest",
+            Command::Move(_) => "move",
+            Command::Rename(_) => "rename",
+            Command::Run(_) => "run",
+            Command::Schema(_) => "schema",
+            Command::Shard(_) => "shard",
+            Command::Stats(_) => "stats",
+            Command::Watch(_) => "watch",
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    let current_directory = std::env::current_dir().map_err(CliError::CurrentDirectory)?;
+    let io_policy = if cli.strict_io {
+        IoPolicy::Strict
+    } else {
+        IoPolicy::Warn
+    };
+    let context = Context::new()
+        .with_progress(Arc::new(BarProgress::new()))
+        .with_io_policy(io_policy)
+        .with_offline(cli.offline);
+
+    let policy = if cli.deny_unknown_keys {
+        UnknownKeyPolicy::Deny
+    } else {
+        UnknownKeyPolicy::Warn
+    };
+
+    // Loading is one of the checks, so a broken declaration is reported rather than returned.
+    if let Command::Doctor(args) = &cli.command {
+        return commands::doctor::run(
+            args,
+            cli.declaration.as_deref(),
+            &current_directory,
+            policy,
+            &context,
+            &mut std::io::stdout().lock(),
+        );
+    }
+
+    if let Command::Schema(args) = &cli.command {
+        return commands::schema::run(args, &mut std::io::stdout().lock());
+    }
+
+    let loaded = load::load_declaration(
+        cli.declaration.as_deref(),
+        &current_directory,
+        policy,
+        &context,
+    )?;
+
+    let mut out = std::io::stdout().lock();
+
+    if let Command::Stats(args) = &cli.command {
+        return commands::stats::run(args, &loaded.root, &mut out);
+    }
+
+    // Measured before the commands consume the declaration.
+    let recording = loaded.declaration.stats.enabled.then(|| {
+        let projects = &loaded.declaration.projects;
+        let dependencies = projects
+            .values()
+            .map(|project| project.dependencies.as_ref().map_or(0, Vec::len))
+            .sum::<usize>();
+
+        (loaded.root.join(STATS_FILE), projects.len(), dependencies)
+    });
+
+    let started = SystemTime::now();
+    let timer = Instant::now();
+
+    let result = match &cli.command {
+        Command::Affected(args) => commands::affected::run(args, loaded, &context, &mut out),
+        Command::Bisect(args) => commands::bisect::run(args, loaded, &context, &mut out),
+        Command::Cache(args) => commands::cache::run(args, loaded, &context, &mut out),
+        Command::Catalog(args) => commands::catalog::run(args, loaded, &mut out),
+        Command::Constraints(args) => commands::constraints::run(args, loaded, &mut out),
+        Command::Deprecated(args) => commands::deprecated::run(args, loaded, &mut out),
+        #[cfg(target_os = "linux")]
+        Command::Drift(args) => commands::drift::run(args, loaded, &mut out),
+        Command::Features(args) => commands::features::run(args, loaded, &context, &mut out),
+        Command::Generate(args) => commands::generate::run(args, loaded, &mut out),
+        Command::Graph(args) => commands::graph::run(args, loaded, &mut out),
+        Command::Health(args) => commands::health::run(args, loaded, &context, &mut out),
+        Command::Lint(args) => commands::lint::run(args, loaded, &mut out),
+        Command::Manifest(args) => commands::manifest::run(args, loaded, &context, &mut out),
+        Command::Move(args) => commands::move_project::run(args, loaded, &mut out),
+        Command::Rename(args) => commands::rename::run(args, loaded, &mut out),
+        Command::Run(args) => commands::run::run(args, loaded, &context, &mut out),
+        Command::Shard(args) => commands::shard::run(args, loaded, &context, &mut out),
+        Command::Watch(args) => commands::watch::run(args, loaded, policy, &context, &mut out),
+        Command::Doctor(_) | Command::Schema(_) | Command::Stats(_) => {
+            unreachable!("handled above")
+        }
+    };
+
+    if let Some((path, projects, dependencies)) = recording {
+        let record = StatsRecord::new(
+            cli.command.name(),
+            started,
+            timer.elapsed(),
+            projects,
+            dependencies,
+            result.is_ok(),
+        );
+
+        // Statistics are a convenience, failing to record them doesn't fail the command.
+        if let Err(err) = stats::record(&path, &record) {
+            eprintln!("warning: {err}");
+        }
+    }
+
+    result
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        // The reader went away, e.g. `parmenides affected | head`, which isn't a failure.
+        Err(CliError::Output(err)) if err.kind() == std::io::ErrorKind::BrokenPipe => {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,56 @@
+use std::sync::Mutex;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use parmenides_lib::progress::{ProgressSink, Stage};
+
+/// A [`ProgressSink`] drawing an indicatif bar on stderr for the running stage.
+///
+/// Nothing is drawn when stderr isn't a terminal, so CI logs stay clean.
+#[derive(Default)]
+pub struct BarProgress {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl BarProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn label(stage: Stage) -> &'static str {
+    match stage {
+        Stage::Discovery => "Discovering",
+        Stage::Determinism => "Checking determinism",
+        Stage::Tasks => "Running",
+        _ => "Working",
+    }
+}
+
+impl ProgressSink for BarProgress {
+    fn start(&self, stage: Stage, total: Option<usize>) {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total as u64).with_style(
+                ProgressStyle::with_template("{prefix} [{bar:30}] {pos}/{len} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar())
+                    .progress_chars("=> "),
+            ),
+            None => ProgressBar::new_spinner(),
+        };
+
+        bar.set_prefix(label(stage));
+        *self.bar.lock().unwrap() = Some(bar);
+    }
+
+    fn advance(&self, _stage: Stage, item: &str) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_message(item.to_owned());
+            bar.inc(1);
+        }
+    }
+
+    fn finish(&self, _stage: Stage) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Helpers shared by the tests of the commands.
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use clap::{Args, Parser};
+use parmenides_lib::context::Context;
+use parmenides_lib::unknown_keys::UnknownKeyPolicy;
+
+use crate::load::{load_declaration, LoadedDeclaration};
+
+/// A directory under the temporary directory of the system, removed with its content when
+/// dropped, so a failing test doesn't leave it behind.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory named after `name`, unique to the test that creates it.
+    pub fn new(name: &str) -> Self {
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "parmenides-cli-{name}-{}-{}",
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        // Left behind by an earlier run with the same process ID that was killed.
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    /// Creates a directory like [`Self::new`], with a `parmenides.toml` declaring `declaration`.
+    pub fn with_declaration(name: &str, declaration: &str) -> Self {
+        let directory = Self::new(name);
+        directory.write("parmenides.toml", declaration);
+
+        directory
+    }
+
+    /// Writes a file at `path` in the directory, creating its parent directories.
+    pub fn write(&self, path: &str, content: &str) {
+        let path = self.0.join(path);
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// Reads the file at `path` in the directory.
+    pub fn read(&self, path: &str) -> String {
+        std::fs::read_to_string(self.0.join(path)).unwrap()
+    }
+
+    /// Loads the declaration of the directory, rejecting unknown keys.
+    pub fn load(&self) -> LoadedDeclaration {
+        load_declaration(None, &self.0, UnknownKeyPolicy::Deny, &Context::new()).unwrap()
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Parses the arguments of a command, starting with its name, e.g. `["run", "--cache"]`.
+pub fn parse_args<A: Args>(args: &[&str]) -> A {
+    #[derive(Parser)]
+    struct Cli<A: Args> {
+        #[command(flatten)]
+        args: A,
+    }
+
+    Cli::<A>::parse_from(args).args
+}
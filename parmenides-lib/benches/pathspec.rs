@@ -0,0 +1,122 @@
+//! Times diffs of a repository whose vendored files outnumber the files of the workspace,
+//! with and without [`GitDiffEngine::with_workspace_scope`].
+//!
+//! Run with `cargo bench -p parmenides-lib --bench pathspec`. The number of vendored files is
+//! read from `PARMENIDES_BENCH_FILES`, 20000 by default.
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use git2::{IndexAddOption, Repository, Signature};
+use parmenides_lib::context::Context;
+use parmenides_lib::declarations::WorkspaceDeclaration;
+use parmenides_lib::diff_engine::{DiffEngine, GitDiffEngine};
+
+/// How many times each diff is run, keeping the fastest.
+const RUNS: u32 = 5;
+
+/// How many projects the workspace has, each with a single file.
+const PROJECTS: usize = 10;
+
+/// Writes the projects and `vendored` files, with `content`, and commits every file.
+fn commit(repository: &Repository, vendored: usize, content: &str) -> String {
+    let root = repository.workdir().unwrap();
+
+    for project in 0..PROJECTS {
+        let directory = root.join(format!("libs/lib{project}"));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("lib.rs"), content).unwrap();
+    }
+
+    for file in 0..vendored {
+        let directory = root.join(format!("vendor/crate{}", file / 100));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join(format!("file{file}.rs")), content).unwrap();
+    }
+
+    let mut index = repository.index().unwrap();
+    index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("bench", "bench@example.com").unwrap();
+    let parent = repository
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+
+    repository
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            content,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+        .to_string()
+}
+
+/// Returns the fastest of [`RUNS`] diffs, and the number of files the diff reported.
+fn time(engine: &GitDiffEngine, from: &str, to: Option<&str>) -> (Duration, usize) {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            let changed = engine.get_changed_files(from, to, &Context::new()).unwrap();
+
+            (started.elapsed(), changed.len())
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, path: &Path, from: &str, to: Option<&str>, scoped: &GitDiffEngine) {
+    let whole = GitDiffEngine::open(path).unwrap();
+
+    let (whole_time, whole_files) = time(&whole, from, to);
+    let (scoped_time, scoped_files) = time(scoped, from, to);
+
+    println!("{name}:");
+    println!("  whole repository   {whole_time:>10.2?}  {whole_files} files");
+    println!("  workspace scope    {scoped_time:>10.2?}  {scoped_files} files");
+}
+
+fn main() {
+    let vendored = std::env::var("PARMENIDES_BENCH_FILES")
+        .ok()
+        .and_then(|files| files.parse().ok())
+        .unwrap_or(20_000);
+
+    let path =
+        std::env::temp_dir().join(format!("parmenides-bench-pathspec-{}", std::process::id()));
+    let repository = Repository::init(&path).unwrap();
+
+    let first = commit(&repository, vendored, "1");
+    let second = commit(&repository, vendored, "2");
+
+    let mut declaration = WorkspaceDeclaration::new();
+
+    for project in 0..PROJECTS {
+        declaration.add_project(format!("libs/lib{project}"), format!("lib{project}"), None);
+    }
+
+    declaration.resolve_paths(&path);
+    let workspace = declaration.build_workspace().unwrap();
+
+    let scoped = GitDiffEngine::open(&path)
+        .unwrap()
+        .with_workspace_scope(&workspace);
+
+    println!("{PROJECTS} projects, {vendored} vendored files\n");
+    report(
+        "revisions, vendored files changed",
+        &path,
+        &first,
+        Some(&second),
+        &scoped,
+    );
+    report("working directory", &path, &second, None, &scoped);
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
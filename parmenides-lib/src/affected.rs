@@ -0,0 +1,844 @@
+//! The affected pipeline ties a [`DiffEngine`] to a [`Workspace`]: it computes the changed
+//! files, maps each one to the project that owns it, and marks those projects (and their
+//! dependents) as affected.
+//!
+//! Which projects a change marks is decided by an [`AffectedStrategy`], by default
+//! [`TransitiveDependents`].
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::context::Context;
+#[cfg(feature = "git")]
+use crate::declarations::WorkspaceDeclaration;
+#[cfg(feature = "git")]
+use crate::diff_engine::GitDiffEngine;
+use crate::diff_engine::{ChangedFile, DiffEngine};
+#[cfg(feature = "git")]
+use crate::errors::ComputeMergeAffectedError;
+use crate::errors::{ComputeAffectedError, MarkProjectAsAffectedError};
+use crate::project::ProjectId;
+use crate::workspace::{explain, AffectedReason, Cause, DependencyKind, Workspace};
+
+/// Computes the projects affected by the changes between two revisions.
+///
+/// Each changed file is assigned to the project with the longest path prefix, so files in
+/// nested projects belong to the innermost one. Files outside every project, or outside the
+/// inputs of their project, see [`crate::project::Project::inputs`], are ignored, unless they
+/// are triggers affecting every project, see [`Workspace::is_trigger`].
+///
+/// # Parameters
+/// - `workspace`: The workspace whose projects are marked as affected.
+/// - `engine`: The diff engine computing the changed paths.
+/// - `from`: The revision to diff from.
+/// - `to`: The revision to diff to, or `None` to include the uncommitted changes of the working
+///   directory and the index.
+/// - `context`: Stops the computation when cancelled, leaving the workspace untouched.
+///
+/// # Returns
+/// - `Ok(Vec<ProjectId>)`: Every affected project of the workspace, in ID order.
+/// - `Err(ComputeAffectedError)`: If the diff failed or was cancelled.
+pub fn compute_affected<E>(
+    workspace: &mut Workspace,
+    engine: &E,
+    from: &str,
+    to: Option<&str>,
+    context: &Context,
+) -> Result<Vec<ProjectId>, ComputeAffectedError>
+where
+    E: DiffEngine + ?Sized,
+{
+    let changed_files = engine.get_changed_files(from, to, context)?;
+
+    if context.is_cancelled() {
+        return Err(ComputeAffectedError::Cancelled);
+    }
+
+    // A renamed file affects the project it left as well as the one it joined.
+    mark_changed_paths(workspace, changed_files.iter().flat_map(ChangedFile::paths))?;
+
+    Ok(workspace.affected_projects().collect())
+}
+
+/// The projects affected by a past commit, see [`compute_merge_affected`].
+#[cfg(feature = "git")]
+#[derive(Debug)]
+pub struct MergeAffected {
+    /// The id of the commit.
+    pub commit: String,
+    /// The id of its first parent, which the commit was diffed from.
+    pub parent: String,
+    /// The workspace as declared at the commit, with the affected projects marked.
+    pub workspace: Workspace,
+    /// Every affected project of the workspace, in ID order.
+    pub affected: Vec<ProjectId>,
+}
+
+/// Computes the projects affected by a past commit, as they would have been when it landed.
+///
+/// The workspace is built from the declaration as it was at the commit, not as it is now, so
+/// projects added, moved or removed since don't change the answer. The commit is diffed from
+/// its first parent, which for a merge commit is the branch it was merged into. This answers
+/// questions like why CI didn't run a project when a merge broke it.
+///
+/// # Parameters
+/// - `engine`: The repository the commit is read from.
+/// - `revision`: The commit, e.g. a merge commit on the main branch.
+/// - `declaration`: The declaration file, inside [`GitDiffEngine::path`].
+/// - `configure`: Sets up the workspace of the commit before it is marked, e.g. with
+///   [`Workspace::set_soft_propagation`].
+/// - `context`: Stops the computation when cancelled.
+///
+/// # Returns
+/// - `Ok(MergeAffected)`: The workspace at the commit, and its affected projects.
+/// - `Err(ComputeMergeAffectedError)`: If the commit has no parent, its declaration could not
+///   be loaded or built, or the diff failed.
+#[cfg(feature = "git")]
+pub fn compute_merge_affected<F>(
+    engine: &GitDiffEngine,
+    revision: &str,
+    declaration: &Path,
+    configure: F,
+    context: &Context,
+) -> Result<MergeAffected, ComputeMergeAffectedError>
+where
+    F: FnOnce(&mut Workspace),
+{
+    let commit = engine.resolve(revision)?;
+    let parent = engine
+        .first_parent(&commit)?
+        .ok_or_else(|| ComputeMergeAffectedError::NoParent(commit.clone()))?;
+
+    let file_system = engine.file_system(&commit)?;
+    let mut declaration = WorkspaceDeclaration::from_path_in(&file_system, declaration)
+        .map_err(|err| ComputeMergeAffectedError::Load(commit.clone(), err))?;
+
+    // The projects of a preset are discovered from the manifests at the commit too.
+    declaration
+        .apply_preset(&file_system, context)
+        .map_err(|err| ComputeMergeAffectedError::Discovery(commit.clone(), err))?;
+
+    let mut workspace = declaration
+        .build_workspace()
+        .map_err(|err| ComputeMergeAffectedError::BuildWorkspace(commit.clone(), err))?;
+
+    configure(&mut workspace);
+
+    let affected = compute_affected(&mut workspace, engine, &parent, Some(&commit), context)?;
+
+    Ok(MergeAffected {
+        commit,
+        parent,
+        workspace,
+        affected,
+    })
+}
+
+/// Marks the projects owning the changed paths, and their dependents, as affected.
+///
+/// The paths are recorded as the reason of their owners, see [`Workspace::affected_reason`].
+///
+/// This is the incremental step shared by [`compute_affected`] and the [`crate::watch`]
+/// backends, which feed it each batch of changes as it happens.
+///
+/// # Returns
+/// - `Ok(Vec<ProjectId>)`: The projects owning at least one of the paths, in ID order.
+/// - `Err(MarkProjectAsAffectedError)`: If marking an owner failed.
+pub fn mark_changed_paths<'a, I, P>(
+    workspace: &mut Workspace,
+    paths: I,
+) -> Result<Vec<ProjectId>, MarkProjectAsAffectedError>
+where
+    I: IntoIterator<Item = &'a P>,
+    P: AsRef<Path> + 'a + ?Sized,
+{
+    let changes = changes_by_owner(workspace, paths);
+    let owners = changes.keys().copied().collect();
+
+    workspace.mark_projects_as_changed(changes)?;
+
+    Ok(owners)
+}
+
+/// Decides which projects a change set marks as affected, e.g. to swap the transitive
+/// dependents for a prediction or a budget without forking the pipeline.
+///
+/// The strategy of a workspace is set with [`Workspace::set_affected_strategy`], or by name
+/// with the `affected_strategy` key of the declaration for the built-in ones, see
+/// [`builtin_strategy`]. Its name is recorded in the reports of the marking, e.g.
+/// [`crate::export::WorkspaceGraph::strategy`].
+pub trait AffectedStrategy: Debug + Send + Sync {
+    /// The unique name of the strategy, used in configuration and reports.
+    fn name(&self) -> &str;
+
+    /// Marks the projects affected by the changed projects.
+    ///
+    /// # Parameters
+    /// - `workspace`: The workspace the projects belong to.
+    /// - `changes`: The changed projects, which all exist, with the files that changed in each.
+    /// - `marking`: The marks to update, with the propagation settings of the caller.
+    fn mark(
+        &self,
+        workspace: &Workspace,
+        changes: Vec<(ProjectId, Vec<PathBuf>)>,
+        marking: &mut Marking,
+    );
+}
+
+/// Marks the changed projects and, breadth-first, their dependents, through the edges the
+/// propagation settings allow. The default strategy.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TransitiveDependents;
+
+impl AffectedStrategy for TransitiveDependents {
+    fn name(&self) -> &str {
+        "transitive_dependents"
+    }
+
+    fn mark(
+        &self,
+        workspace: &Workspace,
+        changes: Vec<(ProjectId, Vec<PathBuf>)>,
+        marking: &mut Marking,
+    ) {
+        workspace.propagate_to_dependents(changes, marking);
+    }
+}
+
+/// Only marks the changed projects, e.g. for linters whose results don't depend on the
+/// dependencies of a project.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ChangedOnly;
+
+impl AffectedStrategy for ChangedOnly {
+    fn name(&self) -> &str {
+        "changed_only"
+    }
+
+    fn mark(
+        &self,
+        _workspace: &Workspace,
+        changes: Vec<(ProjectId, Vec<PathBuf>)>,
+        marking: &mut Marking,
+    ) {
+        for (id, files) in changes {
+            marking.mark_changed(id, files);
+        }
+    }
+}
+
+/// Returns the built-in strategy with the given name, see [`AffectedStrategy::name`].
+pub fn builtin_strategy(name: &str) -> Option<Arc<dyn AffectedStrategy>> {
+    match name {
+        "transitive_dependents" => Some(Arc::new(TransitiveDependents)),
+        "changed_only" => Some(Arc::new(ChangedOnly)),
+        _ => None,
+    }
+}
+
+/// The marks an [`AffectedStrategy`] updates, with the reasons explaining them, see
+/// [`Workspace::affected_reason`].
+#[derive(Debug)]
+pub struct Marking<'m> {
+    pub(crate) affected: &'m mut [bool],
+    pub(crate) causes: &'m mut HashMap<ProjectId, Cause>,
+    pub(crate) soft: bool,
+    pub(crate) kinds: &'m [DependencyKind],
+}
+
+impl Marking<'_> {
+    /// Returns `true` if changes should propagate through soft edges, see
+    /// [`Workspace::set_soft_propagation`].
+    pub fn soft_propagation(&self) -> bool {
+        self.soft
+    }
+
+    /// Returns the kinds of the edges changes should propagate through, see
+    /// [`Workspace::set_propagated_kinds`].
+    pub fn propagated_kinds(&self) -> &[DependencyKind] {
+        self.kinds
+    }
+
+    /// Returns `true` if the project is marked as affected.
+    pub fn is_affected(&self, id: ProjectId) -> bool {
+        self.affected.get(id.into_inner()).copied().unwrap_or(false)
+    }
+
+    /// Marks a changed project as affected, adding the files to its reason.
+    ///
+    /// # Returns
+    /// `true` if its dependents still have to be walked: the project wasn't affected yet, or
+    /// an earlier change stopped at it.
+    pub fn mark_changed(&mut self, id: ProjectId, files: Vec<PathBuf>) -> bool {
+        let Some(affected) = self.affected.get_mut(id.into_inner()) else {
+            return false;
+        };
+
+        let stopped = matches!(self.causes.get(&id), Some(Cause::Stopped(_)));
+
+        match self.causes.get_mut(&id) {
+            Some(Cause::Changed(existing)) => existing.extend(files),
+            _ => {
+                self.causes.insert(id, Cause::Changed(files));
+            }
+        }
+
+        let walk = !*affected || stopped;
+        *affected = true;
+
+        walk
+    }
+
+    /// Marks a project as affected through one of its dependencies, unless it already is.
+    ///
+    /// # Returns
+    /// `true` if the project wasn't affected yet.
+    pub fn mark_dependent(&mut self, id: ProjectId, dependency: ProjectId) -> bool {
+        match self.affected.get_mut(id.into_inner()) {
+            Some(affected) if !*affected => {
+                *affected = true;
+                self.causes.insert(id, Cause::Dependency(dependency));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The affected marks of one change set, kept apart from the [`Workspace`] they refer to.
+///
+/// A long-lived process can build the workspace once and compute the affected projects of
+/// many commit ranges, each in its own state, without touching the graph. The marking
+/// follows the same rules as [`Workspace::mark_projects_as_changed`].
+#[derive(Debug, Clone)]
+pub struct AffectedState<'a> {
+    workspace: &'a Workspace,
+    affected: Vec<bool>,
+    causes: HashMap<ProjectId, Cause>,
+    soft: bool,
+    kinds: Vec<DependencyKind>,
+    strategy: Arc<dyn AffectedStrategy>,
+}
+
+impl<'a> AffectedState<'a> {
+    /// Creates a state with no project affected, marked with the strategy of the workspace and
+    /// propagating through soft edges and edges of each kind as it does, see
+    /// [`Workspace::set_affected_strategy`], [`Workspace::set_soft_propagation`] and
+    /// [`Workspace::set_propagated_kinds`].
+    pub fn new(workspace: &'a Workspace) -> Self {
+        Self {
+            workspace,
+            affected: vec![false; workspace.id_bound()],
+            causes: HashMap::new(),
+            soft: workspace.soft_propagation(),
+            kinds: workspace.propagated_kinds().to_vec(),
+            strategy: workspace.affected_strategy(),
+        }
+    }
+
+    /// Overrides the strategy marking the affected projects, e.g. to compare two strategies on
+    /// the same workspace.
+    pub fn with_strategy(mut self, strategy: Arc<dyn AffectedStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Returns the strategy marking the affected projects.
+    pub fn strategy(&self) -> &dyn AffectedStrategy {
+        self.strategy.as_ref()
+    }
+
+    /// Overrides whether changes propagate through soft edges, e.g. to compare both modes on
+    /// the same workspace.
+    pub fn with_soft_propagation(mut self, enabled: bool) -> Self {
+        self.soft = enabled;
+        self
+    }
+
+    /// Overrides the kinds of the edges changes propagate through.
+    pub fn with_propagated_kinds<I>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = DependencyKind>,
+    {
+        self.kinds = kinds.into_iter().collect();
+        self
+    }
+
+    /// Returns the workspace the marks refer to.
+    pub fn workspace(&self) -> &'a Workspace {
+        self.workspace
+    }
+
+    /// Marks the projects changed between two revisions, and their dependents, as affected.
+    /// See [`compute_affected`].
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ProjectId>)`: Every affected project, in ID order.
+    /// - `Err(ComputeAffectedError)`: If the diff failed or was cancelled.
+    pub fn compute<E>(
+        &mut self,
+        engine: &E,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ProjectId>, ComputeAffectedError>
+    where
+        E: DiffEngine + ?Sized,
+    {
+        let changed_files = engine.get_changed_files(from, to, context)?;
+
+        if context.is_cancelled() {
+            return Err(ComputeAffectedError::Cancelled);
+        }
+
+        self.mark_changed_paths(changed_files.iter().flat_map(ChangedFile::paths))?;
+
+        Ok(self.affected_projects().collect())
+    }
+
+    /// Marks the projects owning the changed paths, and their dependents, as affected.
+    /// See [`mark_changed_paths`].
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ProjectId>)`: The projects owning at least one of the paths, in ID order.
+    /// - `Err(MarkProjectAsAffectedError)`: If marking an owner failed.
+    pub fn mark_changed_paths<'p, I, P>(
+        &mut self,
+        paths: I,
+    ) -> Result<Vec<ProjectId>, MarkProjectAsAffectedError>
+    where
+        I: IntoIterator<Item = &'p P>,
+        P: AsRef<Path> + 'p + ?Sized,
+    {
+        let changes = changes_by_owner(self.workspace, paths);
+        let owners = changes.keys().copied().collect();
+
+        self.workspace.propagate(
+            changes,
+            self.strategy.as_ref(),
+            self.soft,
+            &self.kinds,
+            &mut self.affected,
+            &mut self.causes,
+        )?;
+
+        Ok(owners)
+    }
+
+    /// Returns `true` if the project is marked as affected.
+    pub fn is_affected(&self, id: ProjectId) -> bool {
+        self.affected.get(id.into_inner()).copied().unwrap_or(false)
+    }
+
+    /// Returns the IDs of the projects marked as affected, in ID order.
+    pub fn affected_projects(&self) -> impl Iterator<Item = ProjectId> + '_ {
+        self.workspace
+            .iter_with_ids()
+            .map(|(id, _)| id)
+            .filter(|id| self.is_affected(*id))
+    }
+
+    /// Explains why a project is affected. See [`Workspace::affected_reason`].
+    pub fn affected_reason(&self, id: ProjectId) -> Option<AffectedReason> {
+        explain(&self.causes, id)
+    }
+
+    /// Clears every mark, so the state can be reused for another change set.
+    pub fn reset(&mut self) {
+        self.affected.fill(false);
+        self.causes.clear();
+    }
+}
+
+fn changes_by_owner<'a, I, P>(workspace: &Workspace, paths: I) -> BTreeMap<ProjectId, Vec<PathBuf>>
+where
+    I: IntoIterator<Item = &'a P>,
+    P: AsRef<Path> + 'a + ?Sized,
+{
+    let mut changes: BTreeMap<ProjectId, Vec<PathBuf>> = BTreeMap::new();
+
+    for path in paths {
+        let path = path.as_ref();
+
+        if workspace.is_trigger(&path) {
+            for (id, _) in workspace.iter_with_ids() {
+                changes.entry(id).or_default().push(path.to_path_buf());
+            }
+
+            continue;
+        }
+
+        let Some(owner) = workspace.resolve_owner(&path) else {
+            continue;
+        };
+
+        // A file that isn't an input of its owner affects nothing, not even an outer project.
+        if workspace
+            .get_project(owner)
+            .is_some_and(|project| project.is_input(&path))
+        {
+            changes.entry(owner).or_default().push(path.to_path_buf());
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use crate::context::{CancellationToken, Context};
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::diff_engine::{ChangeKind, ChangedFile, DiffEngine};
+    use crate::errors::{BuildWorkspaceError, ComputeAffectedError, DiffEngineError};
+    use crate::project::ProjectId;
+    use crate::workspace::Workspace;
+
+    use super::{
+        compute_affected, mark_changed_paths, AffectedState, AffectedStrategy, Marking,
+        TransitiveDependents,
+    };
+
+    struct FakeDiffEngine {
+        root: PathBuf,
+    }
+
+    impl FakeDiffEngine {
+        fn new<P: AsRef<Path>>(root: P) -> Self {
+            Self {
+                root: root.as_ref().to_path_buf(),
+            }
+        }
+    }
+
+    impl DiffEngine for FakeDiffEngine {
+        fn get_changed_files(
+            &self,
+            from: &str,
+            _to: Option<&str>,
+            _context: &Context,
+        ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+            if from == "bad" {
+                return Err(DiffEngineError::Io(
+                    self.root.join(from),
+                    std::io::Error::from(std::io::ErrorKind::NotFound),
+                ));
+            }
+
+            Ok(vec![
+                ChangedFile::new(
+                    self.root.join("libs/core/nested/src/lib.rs"),
+                    ChangeKind::Modified,
+                ),
+                ChangedFile::new(self.root.join("README.md"), ChangeKind::Added),
+                ChangedFile::renamed(
+                    self.root.join("docs/guide.md"),
+                    self.root.join("tools/guide.md"),
+                ),
+            ])
+        }
+    }
+
+    #[test]
+    pub fn when_computing_affected_should_mark_owners_and_dependents() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration.add_project("/repo/libs/core/nested", "nested", None);
+        declaration.add_project(
+            "/repo/apps/web",
+            "web",
+            Some(vec!["/repo/libs/core/nested".into()]),
+        );
+        declaration.add_project("/repo/tools", "tools", None);
+
+        let mut workspace = declaration.build_workspace().unwrap();
+
+        let affected = compute_affected(
+            &mut workspace,
+            &FakeDiffEngine::new("/repo"),
+            "main",
+            Some("HEAD"),
+            &Context::new(),
+        )
+        .unwrap();
+
+        let mut names: Vec<_> = affected
+            .into_iter()
+            .map(|id| workspace.get_project(id).unwrap().name.clone())
+            .collect();
+        names.sort();
+
+        assert_eq!(names, vec!["nested", "tools", "web"]);
+
+        let web_id = workspace.get_id_by_path(&"/repo/apps/web").unwrap();
+        let reason = workspace.affected_reason(web_id).unwrap();
+
+        assert_eq!(reason.chain.len(), 2);
+        assert_eq!(
+            reason.files,
+            vec![PathBuf::from("/repo/libs/core/nested/src/lib.rs")]
+        );
+    }
+
+    #[test]
+    pub fn when_changed_files_are_not_inputs_should_not_mark_owner() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration
+            .add_project("/repo/libs/core/nested", "nested", None)
+            .inputs = vec!["src/**".to_owned(), "!**/lib.rs".to_owned()];
+        declaration.add_project("/repo/docs", "docs", None).inputs = vec!["!**/*.md".to_owned()];
+        declaration.add_project("/repo/tools", "tools", None).inputs = vec!["src".to_owned()];
+
+        let mut workspace = declaration.build_workspace().unwrap();
+
+        let affected = mark_changed_paths(
+            &mut workspace,
+            [
+                "/repo/libs/core/nested/src/lib.rs",
+                "/repo/libs/core/nested/src/main.rs",
+                "/repo/docs/guide.md",
+                "/repo/tools/README.md",
+            ],
+        )
+        .unwrap();
+
+        let names: Vec<_> = affected
+            .into_iter()
+            .map(|id| workspace.get_project(id).unwrap().name.clone())
+            .collect();
+
+        // The excluded lib.rs doesn't fall back to the outer core project.
+        assert_eq!(names, vec!["nested"]);
+        assert_eq!(
+            workspace
+                .affected_reason(workspace.get_id_by_path(&"/repo/libs/core/nested").unwrap())
+                .unwrap()
+                .files,
+            vec![PathBuf::from("/repo/libs/core/nested/src/main.rs")]
+        );
+
+        let mut invalid = WorkspaceDeclaration::new();
+        invalid.add_project("core", "core", None).inputs = vec!["[z-a]".to_owned()];
+
+        assert!(matches!(
+            invalid.build_workspace(),
+            Err(BuildWorkspaceError::InvalidInputs(..))
+        ));
+    }
+
+    #[test]
+    pub fn when_changed_files_are_triggers_should_mark_every_project() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("libs/core", "core", None);
+        declaration.add_project("apps/web", "web", None);
+        declaration.triggers = vec!["Cargo.lock".to_owned(), ".github/**".to_owned()];
+        declaration.resolve_paths("/repo");
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let mut lock = AffectedState::new(&workspace);
+        let mut readme = AffectedState::new(&workspace);
+
+        let owners = lock
+            .mark_changed_paths(["/repo/Cargo.lock", "/repo/.github/workflows/ci.yml"])
+            .unwrap();
+        readme.mark_changed_paths(["/repo/README.md"]).unwrap();
+
+        let web = workspace.get_id_by_path(&"/repo/apps/web").unwrap();
+
+        assert_eq!(owners.len(), 2);
+        assert_eq!(
+            lock.affected_reason(web).unwrap().files,
+            vec![
+                PathBuf::from("/repo/Cargo.lock"),
+                PathBuf::from("/repo/.github/workflows/ci.yml")
+            ]
+        );
+        assert_eq!(readme.affected_projects().count(), 0);
+    }
+
+    /// Marks the transitive dependents, but at most `budget` projects, changed ones first.
+    #[derive(Debug)]
+    struct Capped {
+        budget: usize,
+    }
+
+    impl AffectedStrategy for Capped {
+        fn name(&self) -> &str {
+            "capped"
+        }
+
+        fn mark(
+            &self,
+            workspace: &Workspace,
+            changes: Vec<(ProjectId, Vec<PathBuf>)>,
+            marking: &mut Marking,
+        ) {
+            let mut all = vec![false; workspace.id_bound()];
+            let mut causes = Default::default();
+            let mut transitive = Marking {
+                affected: &mut all,
+                causes: &mut causes,
+                soft: marking.soft_propagation(),
+                kinds: marking.propagated_kinds(),
+            };
+
+            TransitiveDependents.mark(workspace, changes.clone(), &mut transitive);
+
+            let changed: Vec<ProjectId> = changes.iter().map(|(id, _)| *id).collect();
+
+            for (id, files) in changes.into_iter().take(self.budget) {
+                marking.mark_changed(id, files);
+            }
+
+            let dependents = workspace
+                .iter_with_ids()
+                .map(|(id, _)| id)
+                .filter(|id| all[id.into_inner()] && !changed.contains(id));
+
+            for id in dependents.take(self.budget.saturating_sub(changed.len())) {
+                marking.mark_dependent(id, changed[0]);
+            }
+        }
+    }
+
+    #[test]
+    pub fn when_strategy_is_swapped_should_mark_with_it() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration.add_project(
+            "/repo/apps/web",
+            "web",
+            Some(vec!["/repo/libs/core".into()]),
+        );
+        declaration.add_project(
+            "/repo/apps/admin",
+            "admin",
+            Some(vec!["/repo/libs/core".into()]),
+        );
+        declaration.affected_strategy = Some("changed_only".to_owned());
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_identifier("core").unwrap();
+        let admin = workspace.get_id_by_identifier("admin").unwrap();
+
+        let mut changed_only = AffectedState::new(&workspace);
+        let mut capped =
+            AffectedState::new(&workspace).with_strategy(Arc::new(Capped { budget: 2 }));
+
+        changed_only
+            .mark_changed_paths(["/repo/libs/core/lib.rs"])
+            .unwrap();
+        capped
+            .mark_changed_paths(["/repo/libs/core/lib.rs"])
+            .unwrap();
+
+        assert_eq!(changed_only.strategy().name(), "changed_only");
+        assert_eq!(changed_only.affected_projects().collect::<Vec<_>>(), [core]);
+        assert_eq!(capped.strategy().name(), "capped");
+        assert_eq!(
+            capped.affected_projects().collect::<Vec<_>>(),
+            [core, admin]
+        );
+        assert_eq!(capped.affected_reason(admin).unwrap().chain, [core, admin]);
+
+        let mut unknown = WorkspaceDeclaration::new();
+        unknown.affected_strategy = Some("predicted".to_owned());
+
+        assert!(matches!(
+            unknown.build_workspace(),
+            Err(BuildWorkspaceError::UnknownAffectedStrategy(name)) if name == "predicted"
+        ));
+    }
+
+    #[test]
+    pub fn when_diff_fails_should_return_error() {
+        let mut workspace = WorkspaceDeclaration::new().build_workspace().unwrap();
+
+        let error = compute_affected(
+            &mut workspace,
+            &FakeDiffEngine::new("/repo"),
+            "bad",
+            Some("HEAD"),
+            &Context::new(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            error,
+            ComputeAffectedError::Diff(DiffEngineError::Io(path, _)) if path == Path::new("/repo/bad")
+        ));
+    }
+
+    #[test]
+    pub fn when_cancelled_should_leave_workspace_untouched() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/tools", "tools", None);
+
+        let mut workspace = declaration.build_workspace().unwrap();
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let error = compute_affected(
+            &mut workspace,
+            &FakeDiffEngine::new("/repo"),
+            "main",
+            Some("HEAD"),
+            &Context::new().with_cancellation(cancellation),
+        )
+        .unwrap_err();
+
+        assert!(matches!(error, ComputeAffectedError::Cancelled));
+        assert!(workspace.affected_projects().next().is_none());
+    }
+
+    #[test]
+    pub fn when_reusing_workspace_should_compute_each_range_independently() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration.add_project(
+            "/repo/apps/web",
+            "web",
+            Some(vec!["/repo/libs/core".into()]),
+        );
+        declaration.add_project("/repo/tools", "tools", None);
+
+        let mut workspace = declaration.build_workspace().unwrap();
+        let core_id = workspace.get_id_by_path(&"/repo/libs/core").unwrap();
+        let web_id = workspace.get_id_by_path(&"/repo/apps/web").unwrap();
+        let tools_id = workspace.get_id_by_path(&"/repo/tools").unwrap();
+
+        let mut state = AffectedState::new(&workspace);
+        state
+            .mark_changed_paths([Path::new("/repo/libs/core/lib.rs")])
+            .unwrap();
+        let first: Vec<_> = state.affected_projects().collect();
+
+        state.reset();
+        state
+            .mark_changed_paths([Path::new("/repo/tools/main.rs")])
+            .unwrap();
+        let second: Vec<_> = state.affected_projects().collect();
+
+        assert_eq!(first, vec![core_id, web_id]);
+        assert_eq!(second, vec![tools_id]);
+        assert_eq!(state.affected_reason(web_id), None);
+        assert!(workspace.affected_projects().next().is_none());
+
+        workspace.mark_project_as_affected(core_id).unwrap();
+        workspace.reset_affected();
+
+        assert!(workspace.affected_projects().next().is_none());
+        assert_eq!(workspace.affected_reason(core_id), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! # Bisect
+//!
+//! In a monorepo, most commits on the main branch don't touch a given project, so bisecting a
+//! failure with plain `git bisect` spends most of its steps on commits that can't have caused
+//! it. [`affecting_commits`] walks the first-parent history back and keeps only the commits
+//! that affected the project, as computed by [`compute_merge_affected`], and [`bisect`]
+//! searches those for the first one failing.
+use std::path::Path;
+
+use crate::affected::compute_merge_affected;
+use crate::context::Context;
+use crate::diff_engine::GitDiffEngine;
+use crate::errors::{BisectError, ComputeMergeAffectedError, LoadDeclarationError};
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+/// A commit that affected the project being bisected.
+#[derive(Debug)]
+pub struct AffectingCommit {
+    /// The id of the commit.
+    pub commit: String,
+    /// The workspace as declared at the commit, with the affected projects marked.
+    pub workspace: Workspace,
+    /// The ID of the project in [`Self::workspace`].
+    pub project: ProjectId,
+}
+
+/// Walks the first-parent history back from `revision`, and returns the commits that affected
+/// the project with `identifier`, newest first.
+///
+/// The walk stops at the root commit, at the first commit without the declaration or the
+/// project, as nothing before it could have broken the project, or after `limit` commits.
+///
+/// # Parameters
+/// - `engine`: The repository the commits are read from.
+/// - `revision`: The commit to start from, e.g. `main`.
+/// - `declaration`: The declaration file, inside [`GitDiffEngine::path`].
+/// - `identifier`: The identifier of the project.
+/// - `limit`: How many commits to walk at most, affecting or not.
+/// - `context`: Stops the walk when cancelled.
+///
+/// # Returns
+/// - `Ok(Vec<AffectingCommit>)`: The commits affecting the project, newest first.
+/// - `Err(BisectError)`: If the project isn't declared at `revision`, a commit could not be
+///   read, or the walk was cancelled.
+pub fn affecting_commits(
+    engine: &GitDiffEngine,
+    revision: &str,
+    declaration: &Path,
+    identifier: &str,
+    limit: usize,
+    context: &Context,
+) -> Result<Vec<AffectingCommit>, BisectError> {
+    let mut commits = Vec::new();
+    let start = engine
+        .resolve(revision)
+        .map_err(ComputeMergeAffectedError::from)?;
+    let mut next = Some(start.clone());
+
+    for _ in 0..limit {
+        let Some(commit) = next.take() else {
+            break;
+        };
+
+        if context.is_cancelled() {
+            return Err(BisectError::Cancelled);
+        }
+
+        let merge = match compute_merge_affected(engine, &commit, declaration, |_| {}, context) {
+            Ok(merge) => merge,
+            Err(ComputeMergeAffectedError::NoParent(_)) => break,
+            Err(ComputeMergeAffectedError::Load(_, LoadDeclarationError::Io(_, err)))
+                if err.kind() == std::io::ErrorKind::NotFound && commit != start =>
+            {
+                break
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let Some(project) = merge.workspace.get_id_by_identifier(identifier) else {
+            if commit == start {
+                return Err(BisectError::UnknownProject(identifier.to_owned(), commit));
+            }
+
+            break;
+        };
+
+        next = Some(merge.parent);
+
+        if merge.affected.contains(&project) {
+            commits.push(AffectingCommit {
+                commit: merge.commit,
+                workspace: merge.workspace,
+                project,
+            });
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Searches `commits`, newest first, for the oldest commit failing `check`, assuming that once
+/// a commit fails, every newer one fails too.
+///
+/// Only about the logarithm of the number of commits are checked. When every checked commit
+/// fails, the oldest of `commits` is returned, and the failure may predate it.
+///
+/// # Returns
+/// - `Ok(Some(usize))`: The index of the oldest failing commit.
+/// - `Ok(None)`: If there are no commits, or the newest passes.
+/// - `Err(E)`: If a check failed to run.
+pub fn bisect<T, F, E>(commits: &[T], mut check: F) -> Result<Option<usize>, E>
+where
+    F: FnMut(&T) -> Result<bool, E>,
+{
+    let Some(newest) = commits.first() else {
+        return Ok(None);
+    };
+
+    if check(newest)? {
+        return Ok(None);
+    }
+
+    // The commit at `failing` fails, and every commit from `passing` on is yet to fail.
+    let (mut failing, mut passing) = (0, commits.len());
+
+    while passing - failing > 1 {
+        let middle = failing + (passing - failing) / 2;
+
+        if check(&commits[middle])? {
+            passing = middle;
+        } else {
+            failing = middle;
+        }
+    }
+
+    Ok(Some(failing))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use git2::Repository;
+
+    use crate::context::Context;
+    use crate::diff_engine::suite::commit;
+    use crate::diff_engine::GitDiffEngine;
+    use crate::errors::BisectError;
+
+    use super::{affecting_commits, bisect};
+
+    #[test]
+    pub fn when_bisecting_should_find_oldest_failing_commit_checking_few() {
+        let commits: Vec<usize> = (0..100).rev().collect();
+        let mut checked = 0;
+
+        // Commits from 37 on fail.
+        let first = bisect(&commits, |commit| {
+            checked += 1;
+            Ok::<_, Infallible>(*commit < 37)
+        });
+        let passing = bisect(&commits, |_| Ok::<_, Infallible>(true));
+        let failing = bisect(&commits, |_| Ok::<_, Infallible>(false));
+
+        assert_eq!(first.unwrap().map(|index| commits[index]), Some(37));
+        assert!(checked <= 8);
+        assert_eq!(passing.unwrap(), None);
+        assert_eq!(failing.unwrap(), Some(99));
+    }
+
+    #[test]
+    pub fn when_walking_history_should_keep_only_commits_affecting_project() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-bisect-walk-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        let declaration = "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+                           [projects.\"apps/web\"]\nname = \"web\"\n";
+
+        commit(&repository, &[("README.md", "1")]);
+        let added = commit(
+            &repository,
+            &[
+                ("parmenides.toml", declaration),
+                ("libs/core/lib.rs", "1"),
+                ("apps/web/main.rs", "1"),
+            ],
+        );
+        commit(&repository, &[("apps/web/main.rs", "2")]);
+        let changed = commit(&repository, &[("libs/core/lib.rs", "2")]);
+        commit(&repository, &[("apps/web/main.rs", "3")]);
+
+        let engine = GitDiffEngine::open(&path).unwrap();
+        let declaration = path.join("parmenides.toml");
+
+        let core = affecting_commits(&engine, "HEAD", &declaration, "core", 100, &Context::new());
+        let limited = affecting_commits(&engine, "HEAD", &declaration, "core", 2, &Context::new());
+        let missing = affecting_commits(
+            &engine,
+            "HEAD",
+            &declaration,
+            "missing",
+            100,
+            &Context::new(),
+        );
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        let core: Vec<String> = core
+            .unwrap()
+            .into_iter()
+            .map(|affecting| affecting.commit)
+            .collect();
+
+        assert_eq!(core, [changed, added]);
+        assert_eq!(limited.unwrap().len(), 1);
+        assert!(matches!(missing, Err(BisectError::UnknownProject(..))));
+    }
+}
@@ -0,0 +1,227 @@
+//! A [`RemoteCache`] over HTTP. Entries are plain resources under a base URL: `GET` reads one,
+//! answering `404` on a miss, and `PUT` stores one. Any server or object store with that
+//! interface works, e.g. nginx with WebDAV or a presigned bucket proxy.
+use std::time::Duration;
+
+use crate::credentials::CredentialChain;
+use crate::errors::{CredentialError, HttpError, RemoteCacheError};
+use crate::http::HttpClient;
+
+#[cfg(feature = "signing")]
+use super::signing::EntrySigning;
+use super::{RemoteCache, RemoteCacheDeclaration};
+
+/// A [`RemoteCache`] storing entries at `{url}/{key}`, optionally authenticated with a bearer
+/// token. Calls are retried and rate limits waited out by its [`HttpClient`].
+pub struct HttpRemoteCache {
+    url: String,
+    client: HttpClient,
+    #[cfg(feature = "signing")]
+    signing: Option<EntrySigning>,
+}
+
+impl HttpRemoteCache {
+    /// A cache under the base `url`, without authentication nor timeout.
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_owned(),
+            client: HttpClient::new(),
+            #[cfg(feature = "signing")]
+            signing: None,
+        }
+    }
+
+    /// Builds the cache declared in the workspace, with the token `credentials` resolves for
+    /// its URL or else its declared token, if any. See
+    /// [`crate::credentials::CredentialsDeclaration::chain`], with
+    /// [`RemoteCacheDeclaration::token_env`] as the environment variable.
+    pub fn from_declaration(
+        declaration: &RemoteCacheDeclaration,
+        credentials: &CredentialChain,
+    ) -> Result<Self, CredentialError> {
+        let cache = Self::new(&declaration.url);
+
+        Ok(match declaration.credential(credentials)? {
+            Some(credential) => cache.with_token(credential.token),
+            None => cache,
+        })
+    }
+
+    pub fn with_token<S: Into<String>>(mut self, token: S) -> Self {
+        self.client = self.client.with_bearer_token(token);
+        self
+    }
+
+    /// Sets the timeout of each attempt of a call, after which the remote counts as
+    /// unreachable.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = self.client.with_timeout(timeout);
+        self
+    }
+
+    /// Calls the remote with `client` instead, e.g. to change its retries or proxy.
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Signs the entries uploaded and rejects the entries downloaded that fail the signature
+    /// check, see [`EntrySigning::open`].
+    #[cfg(feature = "signing")]
+    pub fn with_signing(mut self, signing: EntrySigning) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    fn entry_url(&self, key: &str) -> String {
+        format!("{}/{}", self.url, key)
+    }
+}
+
+/// Reports every failure of the client, rate limits included, as an unreachable remote, so
+/// the local cache is used instead.
+fn unreachable(err: HttpError) -> RemoteCacheError {
+    match err {
+        HttpError::Unreachable(url, message) => RemoteCacheError::Unreachable(url, message),
+        HttpError::RateLimited(ref url, _) | HttpError::InvalidProxy(ref url, _) => {
+            RemoteCacheError::Unreachable(url.clone(), err.to_string())
+        }
+    }
+}
+
+impl RemoteCache for HttpRemoteCache {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, RemoteCacheError> {
+        let url = self.entry_url(key);
+        let response = self.client.get(&url).map_err(unreachable)?;
+
+        let body = match response.status {
+            200..=299 => response.body,
+            404 => return Ok(None),
+            status => return Err(RemoteCacheError::Status(url, status)),
+        };
+
+        #[cfg(feature = "signing")]
+        if let Some(signing) = &self.signing {
+            return signing
+                .open(key, &body)
+                .map(Some)
+                .map_err(|err| RemoteCacheError::Untrusted(url, err));
+        }
+
+        Ok(Some(body))
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), RemoteCacheError> {
+        let url = self.entry_url(key);
+
+        #[cfg(feature = "signing")]
+        let signed = self
+            .signing
+            .as_ref()
+            .map(|signing| signing.seal(key, value));
+        #[cfg(feature = "signing")]
+        let value = signed.as_deref().unwrap_or(value);
+
+        let response = self.client.put(&url, value).map_err(unreachable)?;
+
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(RemoteCacheError::Status(url, response.status))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use crate::cache::RemoteCache;
+    use crate::errors::RemoteCacheError;
+
+    use super::HttpRemoteCache;
+
+    /// Answers a single request with `response`, returning the request head and body.
+    fn serve_once(response: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut length = 0;
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+
+                if line == "\r\n" {
+                    break;
+                }
+
+                request.push_str(&line);
+            }
+
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            request
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    pub fn when_getting_should_send_token_and_read_entry() {
+        let (url, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nentry");
+
+        let cache = HttpRemoteCache::new(format!("{url}/")).with_token("secret");
+        let entry = cache.get("main/abc").unwrap();
+        let request = server.join().unwrap();
+
+        assert_eq!(entry, Some(b"entry".to_vec()));
+        assert!(request.starts_with("GET /main/abc HTTP/1.1"));
+        assert!(request
+            .to_lowercase()
+            .contains("authorization: bearer secret"));
+    }
+
+    #[test]
+    pub fn when_putting_should_send_entry_and_report_refusals() {
+        let (url, server) = serve_once("HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+
+        let result = HttpRemoteCache::new(&url).put("main/abc", b"entry");
+        let request = server.join().unwrap();
+
+        assert_eq!(
+            result,
+            Err(RemoteCacheError::Status(format!("{url}/main/abc"), 403))
+        );
+        assert!(request.starts_with("PUT /main/abc HTTP/1.1"));
+        assert!(request.ends_with("entry"));
+    }
+
+    #[test]
+    pub fn when_remote_is_down_should_report_unreachable() {
+        // Bound then dropped, so nothing listens on the port.
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+
+        let result = HttpRemoteCache::new(url)
+            .with_timeout(Duration::from_secs(5))
+            .get("main/abc");
+
+        assert!(matches!(result, Err(RemoteCacheError::Unreachable(..))));
+    }
+}
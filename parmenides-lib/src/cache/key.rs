@@ -0,0 +1,617 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::context::Context;
+use crate::errors::CacheError;
+use crate::file_system::FileSystem;
+use crate::path_roots::PathRoots;
+use crate::project::ProjectId;
+use crate::warnings::{IoPolicy, PathWarning, PathWarnings};
+use crate::workspace::Workspace;
+
+use super::encode_hex;
+
+/// Directories never hashed as inputs, as they hold version control data, the cache itself,
+/// or build outputs.
+const IGNORED_DIRECTORIES: [&str; 4] = [".git", ".parmenides", "target", "node_modules"];
+
+/// Hashes the inputs of projects: their source files and the input hashes of their
+/// dependencies, so a change anywhere below a project changes its hash.
+///
+/// Files are hashed by their path relative to the project, so the hashes are the same on every
+/// machine, wherever the workspace is checked out. Files owned by a nested project belong to
+/// that project only, and files that aren't inputs of the project, see
+/// [`crate::project::Project::inputs`], are left out. The files matched by the triggers of the
+/// workspace, see [`Workspace::triggers`], are inputs of every project.
+///
+/// With [`InputHasher::with_path_roots`], paths are remapped before they are hashed or
+/// reported, so the hashes and warnings are the same in every environment the workspace is
+/// mounted in.
+///
+/// Symbolic links are never followed, see [`FileSystem::read_link`].
+///
+/// Files and directories that can't be read fail the hashing with [`IoPolicy::Strict`].
+/// Otherwise they are recorded as warnings, and the projects whose hash doesn't cover them are
+/// told by [`InputHasher::is_complete`].
+pub struct InputHasher<'a> {
+    fs: &'a dyn FileSystem,
+    workspace: &'a Workspace,
+    hashes: HashMap<ProjectId, String>,
+    warnings: PathWarnings,
+    incomplete: HashSet<ProjectId>,
+    roots: PathRoots,
+    triggers: Option<(String, bool)>,
+}
+
+impl<'a> InputHasher<'a> {
+    pub fn new(fs: &'a dyn FileSystem, workspace: &'a Workspace) -> Self {
+        Self {
+            fs,
+            workspace,
+            hashes: HashMap::new(),
+            warnings: PathWarnings::default(),
+            incomplete: HashSet::new(),
+            roots: PathRoots::new(),
+            triggers: None,
+        }
+    }
+
+    /// Remaps the paths of the inputs with `roots`, see [`crate::path_roots`].
+    pub fn with_path_roots(mut self, roots: PathRoots) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    /// Handles the paths that can't be read with `policy`. Defaults to [`IoPolicy::Warn`].
+    pub fn with_io_policy(mut self, policy: IoPolicy) -> Self {
+        self.warnings = PathWarnings::new(policy);
+        self
+    }
+
+    /// Returns `false` if an input of the project, or of one of its dependencies, could not be
+    /// read, so its hash doesn't cover every input.
+    pub fn is_complete(&self, id: ProjectId) -> bool {
+        !self.incomplete.contains(&id)
+    }
+
+    /// Returns the paths skipped so far, as they could not be read.
+    pub fn into_warnings(self) -> Vec<PathWarning> {
+        self.warnings.into_vec()
+    }
+
+    /// Returns the hex-encoded input hash of a project, computing the hashes of its
+    /// dependencies first if needed.
+    ///
+    /// The dependencies are walked depth first with an explicit stack, as generated graphs can
+    /// chain thousands of projects, more than the call stack fits. The cancellation of
+    /// `context` is checked before each directory is listed.
+    pub fn input_hash(&mut self, id: ProjectId, context: &Context) -> Result<String, CacheError> {
+        // The projects being hashed, each with the index of its next dependency to hash.
+        let mut stack: Vec<(ProjectId, usize)> = vec![(id, 0)];
+
+        while let Some(&(current, index)) = stack.last() {
+            if self.hashes.contains_key(&current) {
+                stack.pop();
+                continue;
+            }
+
+            let dependency = self
+                .workspace
+                .get_project(current)
+                .and_then(|project| project.dependencies().get(index).copied());
+
+            if let Some(dependency) = dependency {
+                if let Some((_, index)) = stack.last_mut() {
+                    *index += 1;
+                }
+
+                stack.push((dependency, 0));
+                continue;
+            }
+
+            stack.pop();
+
+            let hash = self.project_hash(current, context)?;
+            self.hashes.insert(current, hash);
+        }
+
+        Ok(self.hashes.get(&id).cloned().unwrap_or_default())
+    }
+
+    /// Hashes a project whose dependencies were all hashed.
+    fn project_hash(&mut self, id: ProjectId, context: &Context) -> Result<String, CacheError> {
+        let Some(project) = self.workspace.get_project(id) else {
+            return Ok(String::new());
+        };
+
+        let mut hasher = Sha256::new();
+        let mut files = Vec::new();
+
+        self.collect_files(id, &project.path, &mut files, context)?;
+
+        if !self.hash_files(&mut hasher, files, &project.path)? {
+            self.incomplete.insert(id);
+        }
+
+        let (triggers, triggers_complete) = self.triggers_hash(context)?;
+
+        if !triggers.is_empty() {
+            hasher.update(b"triggers\0");
+            hasher.update(triggers.as_bytes());
+        }
+
+        if !triggers_complete {
+            self.incomplete.insert(id);
+        }
+
+        let mut dependencies: Vec<&str> = Vec::new();
+
+        for dependency in project.dependencies() {
+            dependencies.extend(self.hashes.get(dependency).map(String::as_str));
+
+            if !self.is_complete(*dependency) {
+                self.incomplete.insert(id);
+            }
+        }
+
+        // Sorted, so the order dependencies are declared in doesn't matter.
+        dependencies.sort();
+
+        for dependency in dependencies {
+            hasher.update(b"dependency\0");
+            hasher.update(dependency.as_bytes());
+        }
+
+        Ok(encode_hex(&hasher.finalize()))
+    }
+
+    /// Returns the hash of the files matched by the triggers of the workspace, empty if it has
+    /// none, and whether every one of them could be read. Computed once and shared by every
+    /// project.
+    fn triggers_hash(&mut self, context: &Context) -> Result<(String, bool), CacheError> {
+        if let Some(triggers) = &self.triggers {
+            return Ok(triggers.clone());
+        }
+
+        let Some((root, _)) = self.workspace.triggers() else {
+            self.triggers = Some((String::new(), true));
+            return Ok((String::new(), true));
+        };
+
+        let mut hasher = Sha256::new();
+        let mut files = Vec::new();
+
+        let listed = self.collect_triggers(root, &mut files, context)?;
+        let read = self.hash_files(&mut hasher, files, root)?;
+
+        let triggers = (encode_hex(&hasher.finalize()), listed && read);
+        self.triggers = Some(triggers.clone());
+
+        Ok(triggers)
+    }
+
+    /// Hashes the paths, relative to `base`, and contents of `files`, returning `false` if one
+    /// of them could not be read. Symbolic links are hashed by their target instead, so a link
+    /// to a directory isn't walked and a link out of the project doesn't hash what it points
+    /// to.
+    fn hash_files(
+        &mut self,
+        hasher: &mut Sha256,
+        files: Vec<PathBuf>,
+        base: &Path,
+    ) -> Result<bool, CacheError> {
+        let base = self.roots.remap(base);
+        let mut complete = true;
+
+        for file in files {
+            let remapped = self.roots.remap(&file);
+            let relative = remapped.strip_prefix(&base).unwrap_or(&remapped);
+
+            if let Some(target) = self.fs.read_link(&file) {
+                hasher.update(b"link\0");
+                hasher.update(relative.to_string_lossy().as_bytes());
+                hasher.update(b"\0");
+                hasher.update(self.roots.remap(&target).to_string_lossy().as_bytes());
+                continue;
+            }
+
+            let content = match self.fs.read(&file) {
+                Ok(content) => content,
+                Err(err) => {
+                    self.warnings
+                        .recover(&remapped, err)
+                        .map_err(|err| CacheError::Io(remapped.to_path_buf(), err))?;
+                    complete = false;
+
+                    hasher.update(b"unreadable\0");
+                    hasher.update(relative.to_string_lossy().as_bytes());
+                    continue;
+                }
+            };
+
+            hasher.update(b"file\0");
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update(b"\0");
+            hasher.update(Sha256::digest(&content));
+        }
+
+        Ok(complete)
+    }
+
+    fn collect_files(
+        &mut self,
+        id: ProjectId,
+        directory: &Path,
+        files: &mut Vec<PathBuf>,
+        context: &Context,
+    ) -> Result<(), CacheError> {
+        let Some(project) = self.workspace.get_project(id) else {
+            return Ok(());
+        };
+
+        let Some(entries) = self.list(directory, context)? else {
+            self.incomplete.insert(id);
+            return Ok(());
+        };
+
+        for entry in entries {
+            if is_ignored(&entry) || self.workspace.resolve_owner(&entry) != Some(id) {
+                continue;
+            }
+
+            if self.fs.read_link(&entry).is_none() && self.fs.is_dir(&entry) {
+                self.collect_files(id, &entry, files, context)?;
+            } else if project.is_input(&entry) {
+                files.push(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collects the trigger files below `directory`, returning `false` if a directory could not
+    /// be listed.
+    fn collect_triggers(
+        &mut self,
+        directory: &Path,
+        files: &mut Vec<PathBuf>,
+        context: &Context,
+    ) -> Result<bool, CacheError> {
+        let Some(entries) = self.list(directory, context)? else {
+            return Ok(false);
+        };
+
+        let mut complete = true;
+
+        for entry in entries {
+            if is_ignored(&entry) {
+                continue;
+            }
+
+            if self.fs.read_link(&entry).is_none() && self.fs.is_dir(&entry) {
+                complete &= self.collect_triggers(&entry, files, context)?;
+            } else if self.workspace.is_trigger(&entry) {
+                files.push(entry);
+            }
+        }
+
+        Ok(complete)
+    }
+
+    /// Lists the entries of `directory`, empty if it isn't one, or `None` if it could not be
+    /// read. Checks the cancellation of `context` first.
+    fn list(
+        &mut self,
+        directory: &Path,
+        context: &Context,
+    ) -> Result<Option<Vec<PathBuf>>, CacheError> {
+        if context.is_cancelled() {
+            return Err(CacheError::Cancelled);
+        }
+
+        if !self.fs.is_dir(directory) {
+            return Ok(Some(Vec::new()));
+        }
+
+        match self.fs.read_dir(directory) {
+            Ok(entries) => Ok(Some(entries)),
+            Err(err) => {
+                let directory = self.roots.remap(directory);
+
+                self.warnings
+                    .recover(&directory, err)
+                    .map_err(|err| CacheError::Io(directory.to_path_buf(), err))?;
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn is_ignored(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| IGNORED_DIRECTORIES.contains(&name))
+}
+
+/// Returns the cache key of running `command` in a project with the given input hash.
+pub fn task_key(input_hash: &str, command: &str) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(b"inputs\0");
+    hasher.update(input_hash.as_bytes());
+    hasher.update(b"command\0");
+    hasher.update(command.as_bytes());
+
+    encode_hex(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::context::{CancellationToken, Context};
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::CacheError;
+    use crate::file_system::{MemoryFileSystem, OsFileSystem};
+    use crate::warnings::IoPolicy;
+
+    use super::{task_key, InputHasher};
+
+    #[test]
+    pub fn when_hashing_inputs_should_follow_files_and_dependencies_only() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None);
+        declaration.add_project("/repo/core/nested", "nested", None);
+        declaration.add_project("/repo/web", "web", Some(vec!["/repo/core".into()]));
+        let workspace = declaration.build_workspace().unwrap();
+
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let hashes = |fs: &MemoryFileSystem| {
+            let mut hasher = InputHasher::new(fs, &workspace);
+
+            ["/repo/core", "/repo/web"]
+                .map(|path| hasher.input_hash(id(path), &Context::new()).unwrap())
+        };
+
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/core/src/lib.rs", "fn core() {}")
+            .with_file("/repo/core/target/debug/out", "build output")
+            .with_file("/repo/web/index.html", "<html>");
+
+        let [core, web] = hashes(&fs);
+
+        let [nested_core, nested_web] = hashes(
+            &fs.clone()
+                .with_file("/repo/core/target/debug/out", "another output")
+                .with_file("/repo/core/nested/lib.rs", "fn nested() {}"),
+        );
+
+        let [changed_core, changed_web] = hashes(
+            &fs.clone()
+                .with_file("/repo/core/src/lib.rs", "fn changed() {}"),
+        );
+
+        assert_eq!(
+            (nested_core.as_str(), nested_web.as_str()),
+            (core.as_str(), web.as_str())
+        );
+        assert_ne!(changed_core, core);
+        assert_ne!(changed_web, web);
+        assert_ne!(
+            task_key(&core, "cargo test"),
+            task_key(&core, "cargo build")
+        );
+    }
+
+    #[test]
+    pub fn when_a_file_is_not_an_input_should_not_change_the_hash() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None).inputs =
+            vec!["src/**".to_owned(), "!**/*.md".to_owned()];
+        let workspace = declaration.build_workspace().unwrap();
+
+        let id = workspace.get_id_by_path(&"/repo/core").unwrap();
+
+        let hash = |fs: &MemoryFileSystem| {
+            InputHasher::new(fs, &workspace)
+                .input_hash(id, &Context::new())
+                .unwrap()
+        };
+
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/core/src/lib.rs", "fn core() {}")
+            .with_file("/repo/core/src/README.md", "# Core")
+            .with_file("/repo/core/fixtures/data.json", "{}");
+
+        let core = hash(&fs);
+
+        let excluded = hash(
+            &fs.clone()
+                .with_file("/repo/core/src/README.md", "# Changed")
+                .with_file("/repo/core/fixtures/data.json", "[]"),
+        );
+
+        let changed = hash(
+            &fs.clone()
+                .with_file("/repo/core/src/lib.rs", "fn changed() {}"),
+        );
+
+        assert_eq!(excluded, core);
+        assert_ne!(changed, core);
+    }
+
+    #[test]
+    pub fn when_a_trigger_changes_should_change_every_task_key() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("core", "core", None);
+        declaration.add_project("web", "web", None);
+        declaration.triggers = vec!["Cargo.lock".to_owned(), ".github/**".to_owned()];
+        declaration.resolve_paths("/repo");
+        let workspace = declaration.build_workspace().unwrap();
+
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let keys = |fs: &MemoryFileSystem| {
+            let mut hasher = InputHasher::new(fs, &workspace);
+
+            ["/repo/core", "/repo/web"].map(|path| {
+                task_key(
+                    &hasher.input_hash(id(path), &Context::new()).unwrap(),
+                    "cargo test",
+                )
+            })
+        };
+
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/Cargo.lock", "version = 3")
+            .with_file("/repo/.github/workflows/ci.yml", "on: push")
+            .with_file("/repo/README.md", "# Repo")
+            .with_file("/repo/core/src/lib.rs", "fn core() {}")
+            .with_file("/repo/web/index.html", "<html>");
+
+        let [core, web] = keys(&fs);
+
+        let [untriggered_core, untriggered_web] =
+            keys(&fs.clone().with_file("/repo/README.md", "# Changed"));
+
+        let [locked_core, locked_web] =
+            keys(&fs.clone().with_file("/repo/Cargo.lock", "version = 4"));
+
+        let [ci_core, ci_web] = keys(
+            &fs.clone()
+                .with_file("/repo/.github/workflows/ci.yml", "on: pull_request"),
+        );
+
+        assert_eq!(
+            (untriggered_core, untriggered_web),
+            (core.clone(), web.clone())
+        );
+        assert!(locked_core != core && locked_web != web);
+        assert!(ci_core != core && ci_web != web);
+    }
+
+    #[test]
+    pub fn when_an_input_is_unreadable_should_mark_the_hash_incomplete() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None);
+        declaration.add_project("/repo/web", "web", Some(vec!["/repo/core".into()]));
+        declaration.add_project("/repo/docs", "docs", None);
+        let workspace = declaration.build_workspace().unwrap();
+
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/core/src/lib.rs", "fn core() {}")
+            .with_file("/repo/core/.env", "SECRET=1")
+            .with_file("/repo/web/index.html", "<html>")
+            .with_file("/repo/docs/index.md", "# Docs")
+            .with_unreadable("/repo/core/.env");
+
+        let mut hasher = InputHasher::new(&fs, &workspace);
+        let hashed = ["/repo/core", "/repo/web", "/repo/docs"]
+            .map(|path| hasher.input_hash(id(path), &Context::new()));
+
+        let complete =
+            ["/repo/core", "/repo/web", "/repo/docs"].map(|path| hasher.is_complete(id(path)));
+        let warnings = hasher.into_warnings();
+
+        let strict = InputHasher::new(&fs, &workspace)
+            .with_io_policy(IoPolicy::Strict)
+            .input_hash(id("/repo/web"), &Context::new());
+
+        assert!(hashed.iter().all(Result::is_ok));
+        assert_eq!(complete, [false, false, true]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, Path::new("/repo/core/.env"));
+        assert!(
+            matches!(strict, Err(CacheError::Io(path, _)) if path == Path::new("/repo/core/.env"))
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_an_input_is_a_symlink_should_hash_its_target_without_following_it() {
+        let root =
+            std::env::temp_dir().join(format!("parmenides-hash-links-{}", std::process::id()));
+        let core = root.join("core");
+        let outside = root.join("outside");
+
+        std::fs::create_dir_all(core.join("src")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(core.join("src/lib.rs"), "fn core() {}").unwrap();
+        std::fs::write(outside.join("data"), "1").unwrap();
+        std::os::unix::fs::symlink("..", core.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink(&outside, core.join("outside")).unwrap();
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project(&core, "core", None);
+        let workspace = declaration.build_workspace().unwrap();
+        let id = workspace.get_id_by_path(&core).unwrap();
+
+        let hash = || {
+            let mut hasher = InputHasher::new(&OsFileSystem, &workspace);
+            let hash = hasher.input_hash(id, &Context::new()).unwrap();
+
+            (hash, hasher.is_complete(id))
+        };
+
+        let (linked, complete) = hash();
+
+        std::fs::write(outside.join("data"), "2").unwrap();
+        let (outside_changed, _) = hash();
+
+        std::fs::remove_file(core.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink(".", core.join("src/loop")).unwrap();
+        let (relinked, _) = hash();
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(complete);
+        assert_eq!(outside_changed, linked);
+        assert_ne!(relinked, linked);
+    }
+
+    #[test]
+    pub fn when_hashing_deep_dependency_chain_should_not_overflow_stack() {
+        const DEPTH: usize = 20_000;
+
+        let mut declaration = WorkspaceDeclaration::new();
+        let path = |level: usize| PathBuf::from(format!("/chain/{level:05}"));
+
+        for level in 0..DEPTH {
+            let dependencies = (level + 1 < DEPTH).then(|| vec![path(level + 1)]);
+
+            declaration.add_project(path(level), format!("p{level}"), dependencies);
+        }
+
+        let workspace = declaration.build_workspace().unwrap();
+        let fs = MemoryFileSystem::new();
+        let mut hasher = InputHasher::new(&fs, &workspace);
+
+        let top = hasher.input_hash(workspace.get_id_by_path(&path(0)).unwrap(), &Context::new());
+
+        assert!(top.is_ok_and(|hash| !hash.is_empty()));
+    }
+
+    #[test]
+    pub fn when_cancelled_should_stop_hashing() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None);
+        let workspace = declaration.build_workspace().unwrap();
+
+        let fs = MemoryFileSystem::new().with_file("/repo/core/src/lib.rs", "fn core() {}");
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let context = Context::new().with_cancellation(cancellation);
+
+        let id = workspace.get_id_by_path(&"/repo/core").unwrap();
+        let hashed = InputHasher::new(&fs, &workspace).input_hash(id, &context);
+
+        assert!(matches!(hashed, Err(CacheError::Cancelled)));
+    }
+}
@@ -0,0 +1,229 @@
+//! # Cache
+//!
+//! Task results are cached by key in namespaces. A [`CacheScope`] decides which namespaces a
+//! run reads from and which one it writes to, e.g. so pull request builds reuse the cache of
+//! `main` without being able to poison it.
+//!
+//! Keys are content addresses: [`InputHasher`] hashes the source files of a project and the
+//! hashes of its dependencies, and [`task_key`] combines that with the command. Entries live in
+//! a [`CacheStore`], by default the [`LocalCacheStore`] under [`LOCAL_CACHE_DIRECTORY`]. A
+//! [`TieredCacheStore`] adds a [`RemoteCache`] shared between machines, e.g. the
+//! `HttpRemoteCache` (feature `http`), and keeps working locally when it is unreachable.
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "http")]
+mod http;
+mod key;
+mod remote;
+#[cfg(feature = "signing")]
+pub mod signing;
+mod store;
+
+#[cfg(feature = "http")]
+pub use http::HttpRemoteCache;
+pub use key::{task_key, InputHasher};
+pub use remote::{RemoteCache, TieredCacheStore};
+pub use store::{
+    CacheStore, CacheVerification, LocalCacheStore, MemoryCacheStore, LOCAL_CACHE_DIRECTORY,
+};
+
+use crate::credentials::{Credential, CredentialChain, CredentialSource};
+use crate::errors::{CacheError, CredentialError, InterpolateError};
+use crate::parameters::Parameters;
+
+const DEFAULT_NAMESPACE: &str = "default";
+const DEFAULT_TOKEN_ENV: &str = "PARMENIDES_CACHE_TOKEN";
+
+/// Represents the cache settings of a workspace.
+///
+/// Namespaces may contain [`Parameters`] placeholders, e.g. `branch/{{ branch }}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheDeclaration {
+    /// The namespaces looked up on a read, in order.
+    #[serde(default = "default_read")]
+    pub read: Vec<String>,
+    /// The namespace results are written to.
+    #[serde(default = "default_write")]
+    pub write: String,
+    /// Disables writes entirely, e.g. for untrusted builds holding a read-only token.
+    #[serde(default)]
+    pub read_only: bool,
+    /// The hex-encoded ed25519 public keys whose signatures are accepted on download. When
+    /// empty, artifacts aren't verified. See the `signing` module (feature `signing`).
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+    /// The cache shared between machines, if any.
+    #[serde(default)]
+    pub remote: Option<RemoteCacheDeclaration>,
+    /// Keeps a snapshot of the built workspace, so large workspaces are only built again when
+    /// their declaration changes. See the `snapshot` module (feature `snapshot`).
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+/// Represents a remote cache over HTTP.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteCacheDeclaration {
+    /// The base URL entries are read from and written to, as `{url}/{namespace}/{key}`.
+    pub url: String,
+    /// The environment variable holding the bearer token, so the token stays out of the
+    /// declaration.
+    #[serde(default = "default_token_env")]
+    pub token_env: String,
+    /// A token written in the declaration, encrypted so it can be committed, see
+    /// [`crate::encryption`]. Used when the credentials have no token for the URL.
+    pub token: Option<String>,
+}
+
+impl RemoteCacheDeclaration {
+    /// Returns the token of the cache, from `credentials` or else the declared one.
+    pub fn credential(
+        &self,
+        credentials: &CredentialChain,
+    ) -> Result<Option<Credential>, CredentialError> {
+        Ok(credentials.resolve(&self.url)?.or_else(|| {
+            self.token.clone().map(|token| Credential {
+                token,
+                source: CredentialSource::Declaration,
+            })
+        }))
+    }
+}
+
+fn default_token_env() -> String {
+    DEFAULT_TOKEN_ENV.to_owned()
+}
+
+fn default_read() -> Vec<String> {
+    vec![DEFAULT_NAMESPACE.to_owned()]
+}
+
+fn default_write() -> String {
+    DEFAULT_NAMESPACE.to_owned()
+}
+
+impl Default for CacheDeclaration {
+    fn default() -> Self {
+        Self {
+            read: default_read(),
+            write: default_write(),
+            read_only: false,
+            trusted_keys: vec![],
+            remote: None,
+            snapshot: false,
+        }
+    }
+}
+
+/// The namespaces a run reads cache entries from and writes them to.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CacheScope {
+    read: Vec<String>,
+    write: Option<String>,
+}
+
+impl CacheScope {
+    /// A scope reading from `read`, in order, and writing to `write`. Use `None` for a
+    /// read-only scope.
+    pub fn new(read: Vec<String>, write: Option<String>) -> Self {
+        Self { read, write }
+    }
+
+    /// Builds the scope declared in the workspace, interpolating the namespaces.
+    pub fn from_declaration(
+        declaration: &CacheDeclaration,
+        parameters: &Parameters,
+    ) -> Result<Self, InterpolateError> {
+        let read = declaration
+            .read
+            .iter()
+            .map(|namespace| parameters.interpolate(namespace))
+            .collect::<Result<_, _>>()?;
+
+        let write = if declaration.read_only {
+            None
+        } else {
+            Some(parameters.interpolate(&declaration.write)?)
+        };
+
+        Ok(Self { read, write })
+    }
+
+    /// Returns `true` if entries can't be written.
+    pub fn is_read_only(&self) -> bool {
+        self.write.is_none()
+    }
+
+    /// Returns the namespaced keys to look up for `key`, in order.
+    pub fn read_keys<'a>(&'a self, key: &'a str) -> impl Iterator<Item = String> + 'a {
+        self.read
+            .iter()
+            .map(move |namespace| format!("{namespace}/{key}"))
+    }
+
+    /// Returns the namespaced key to write `key` to.
+    pub fn write_key(&self, key: &str) -> Result<String, CacheError> {
+        self.write
+            .as_ref()
+            .map(|namespace| format!("{namespace}/{key}"))
+            .ok_or(CacheError::ReadOnly)
+    }
+}
+
+impl Default for CacheScope {
+    fn default() -> Self {
+        Self::new(default_read(), Some(default_write()))
+    }
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::CacheError;
+    use crate::parameters::Parameters;
+
+    use super::{CacheDeclaration, CacheScope};
+
+    #[test]
+    pub fn when_scoping_by_branch_should_read_main_and_write_branch() {
+        let declaration = CacheDeclaration {
+            read: vec!["branch/{{ branch }}".to_owned(), "main".to_owned()],
+            write: "branch/{{ branch }}".to_owned(),
+            read_only: false,
+            trusted_keys: vec![],
+            remote: None,
+            snapshot: false,
+        };
+
+        let mut parameters = Parameters::default();
+        parameters.set("branch", "feature");
+
+        let scope = CacheScope::from_declaration(&declaration, &parameters).unwrap();
+
+        assert_eq!(
+            scope.read_keys("abc").collect::<Vec<_>>(),
+            vec!["branch/feature/abc", "main/abc"]
+        );
+        assert_eq!(scope.write_key("abc").unwrap(), "branch/feature/abc");
+    }
+
+    #[test]
+    pub fn when_scope_is_read_only_should_refuse_writes() {
+        let declaration = CacheDeclaration {
+            read_only: true,
+            ..CacheDeclaration::default()
+        };
+
+        let scope = CacheScope::from_declaration(&declaration, &Parameters::default()).unwrap();
+
+        assert!(scope.is_read_only());
+        assert_eq!(
+            scope.read_keys("abc").collect::<Vec<_>>(),
+            vec!["default/abc"]
+        );
+        assert!(matches!(scope.write_key("abc"), Err(CacheError::ReadOnly)));
+    }
+}
@@ -0,0 +1,349 @@
+use std::sync::Mutex;
+
+#[cfg(feature = "signing")]
+use crate::errors::CacheSignatureError;
+use crate::errors::{CacheError, RemoteCacheError};
+
+#[cfg(feature = "signing")]
+use super::signing::EntrySigning;
+use super::CacheStore;
+
+/// A cache shared between machines, e.g. so CI agents reuse each other's task results.
+///
+/// Keys are the namespaced keys of a [`CacheStore`].
+pub trait RemoteCache: Send + Sync {
+    /// Returns the entry stored under `key`, or `None` on a miss.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, RemoteCacheError>;
+
+    /// Stores `value` under `key`, replacing any existing entry.
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), RemoteCacheError>;
+}
+
+/// A [`CacheStore`] reading from a local store first and from a remote cache on a miss, and
+/// writing to both.
+///
+/// Entries found remotely are copied to the local store. Once the remote is unavailable, see
+/// [`RemoteCacheError::is_unavailable`], it isn't called again and the store keeps working
+/// locally; [`TieredCacheStore::fallback`] tells why.
+///
+/// With [`TieredCacheStore::with_signing`], entries are signed before being written to either
+/// store and copied locally as they were signed, so the stores themselves shouldn't sign. An
+/// entry failing the signature check is a miss, so its task runs again, and is evicted from the
+/// local store; [`TieredCacheStore::rejected`] tells which.
+pub struct TieredCacheStore<'a> {
+    local: &'a dyn CacheStore,
+    remote: Option<&'a dyn RemoteCache>,
+    fallback: Mutex<Option<RemoteCacheError>>,
+    #[cfg(feature = "signing")]
+    signing: Option<EntrySigning>,
+    #[cfg(feature = "signing")]
+    rejected: Mutex<Vec<(String, CacheSignatureError)>>,
+}
+
+impl<'a> TieredCacheStore<'a> {
+    /// A store using only `local`, until a remote is added with
+    /// [`TieredCacheStore::with_remote`].
+    pub fn new(local: &'a dyn CacheStore) -> Self {
+        Self {
+            local,
+            remote: None,
+            fallback: Mutex::new(None),
+            #[cfg(feature = "signing")]
+            signing: None,
+            #[cfg(feature = "signing")]
+            rejected: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn with_remote(mut self, remote: &'a dyn RemoteCache) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Signs the entries written and rejects the entries read, locally or remotely, that fail
+    /// the signature check, see [`EntrySigning::open`].
+    #[cfg(feature = "signing")]
+    pub fn with_signing(mut self, signing: EntrySigning) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    /// Returns the entries that failed the signature check, in the order they were read.
+    #[cfg(feature = "signing")]
+    pub fn rejected(&self) -> Vec<CacheError> {
+        self.rejected
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, err)| CacheError::Untrusted(key.clone(), err.clone()))
+            .collect()
+    }
+
+    /// Returns the error that made the store fall back to the local store, if any.
+    pub fn fallback(&self) -> Option<RemoteCacheError> {
+        self.fallback.lock().unwrap().clone()
+    }
+
+    fn remote(&self) -> Option<&'a dyn RemoteCache> {
+        if self.fallback.lock().unwrap().is_some() {
+            return None;
+        }
+
+        self.remote
+    }
+
+    /// Falls back to the local store on unavailability, and fails on other errors.
+    fn recover<T>(&self, result: Result<T, RemoteCacheError>) -> Result<Option<T>, CacheError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_unavailable() => {
+                self.fallback.lock().unwrap().get_or_insert(err);
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    fn seal(&self, key: &str, value: &[u8]) -> Vec<u8> {
+        match &self.signing {
+            Some(signing) => signing.seal(key, value),
+            None => value.to_vec(),
+        }
+    }
+
+    #[cfg(not(feature = "signing"))]
+    fn seal(&self, _key: &str, value: &[u8]) -> Vec<u8> {
+        value.to_vec()
+    }
+
+    /// Returns the value of an entry, or `None` if it fails the signature check.
+    #[cfg(feature = "signing")]
+    fn open(&self, key: &str, content: Vec<u8>) -> Option<Vec<u8>> {
+        let Some(signing) = &self.signing else {
+            return Some(content);
+        };
+
+        match signing.open(key, &content) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.rejected.lock().unwrap().push((key.to_owned(), err));
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "signing"))]
+    fn open(&self, _key: &str, content: Vec<u8>) -> Option<Vec<u8>> {
+        Some(content)
+    }
+}
+
+impl CacheStore for TieredCacheStore<'_> {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        if let Some(content) = self.local.get(key)? {
+            match self.open(key, content) {
+                Some(value) => return Ok(Some(value)),
+                // Evicted, so the run replaces it rather than it being rejected on every read.
+                None => self.local.remove(key)?,
+            }
+        }
+
+        let Some(remote) = self.remote() else {
+            return Ok(None);
+        };
+
+        let Some(content) = self.recover(remote.get(key))?.flatten() else {
+            return Ok(None);
+        };
+
+        // Checked before the copy, so a rejected entry doesn't reach the local store.
+        let Some(value) = self.open(key, content.clone()) else {
+            return Ok(None);
+        };
+
+        self.local.put(key, &content)?;
+
+        Ok(Some(value))
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), CacheError> {
+        let content = self.seal(key, value);
+
+        self.local.put(key, &content)?;
+
+        if let Some(remote) = self.remote() {
+            self.recover(remote.put(key, &content))?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the entry from the local store only, as the remote is shared.
+    fn remove(&self, key: &str) -> Result<(), CacheError> {
+        self.local.remove(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[cfg(feature = "signing")]
+    use crate::cache::signing::{ArtifactSigner, EntrySigning, TrustedKeys};
+    use crate::cache::{CacheStore, MemoryCacheStore};
+    #[cfg(feature = "signing")]
+    use crate::errors::CacheSignatureError;
+    use crate::errors::{CacheError, RemoteCacheError};
+
+    use super::{RemoteCache, TieredCacheStore};
+
+    #[derive(Default)]
+    struct FakeRemote {
+        entries: Mutex<HashMap<String, Vec<u8>>>,
+        status: Option<u16>,
+        calls: Mutex<usize>,
+    }
+
+    impl FakeRemote {
+        fn answer(&self) -> Result<(), RemoteCacheError> {
+            *self.calls.lock().unwrap() += 1;
+
+            match self.status {
+                Some(0) => Err(RemoteCacheError::Unreachable(
+                    "http://cache".to_owned(),
+                    "connection refused".to_owned(),
+                )),
+                Some(status) => Err(RemoteCacheError::Status("http://cache".to_owned(), status)),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl RemoteCache for FakeRemote {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, RemoteCacheError> {
+            self.answer()?;
+            Ok(self.entries.lock().unwrap().get(key).cloned())
+        }
+
+        fn put(&self, key: &str, value: &[u8]) -> Result<(), RemoteCacheError> {
+            self.answer()?;
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key.to_owned(), value.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn when_remote_has_entry_should_copy_it_locally() {
+        let local = MemoryCacheStore::new();
+        let remote = FakeRemote::default();
+        remote.put("main/abc", b"entry").unwrap();
+
+        let store = TieredCacheStore::new(&local).with_remote(&remote);
+
+        assert_eq!(store.get("main/abc").unwrap(), Some(b"entry".to_vec()));
+        assert_eq!(local.keys(), vec!["main/abc"]);
+
+        store.put("main/def", b"other").unwrap();
+
+        assert!(remote.entries.lock().unwrap().contains_key("main/def"));
+        assert_eq!(store.fallback(), None);
+    }
+
+    #[test]
+    pub fn when_remote_is_unreachable_should_fall_back_to_local() {
+        let local = MemoryCacheStore::new();
+        let remote = FakeRemote {
+            status: Some(0),
+            ..FakeRemote::default()
+        };
+
+        let store = TieredCacheStore::new(&local).with_remote(&remote);
+
+        assert_eq!(store.get("main/abc").unwrap(), None);
+        store.put("main/abc", b"entry").unwrap();
+        assert_eq!(store.get("main/abc").unwrap(), Some(b"entry".to_vec()));
+
+        assert_eq!(*remote.calls.lock().unwrap(), 1);
+        assert!(matches!(
+            store.fallback(),
+            Some(RemoteCacheError::Unreachable(..))
+        ));
+    }
+
+    #[test]
+    pub fn when_remote_refuses_should_return_error() {
+        let local = MemoryCacheStore::new();
+        let remote = FakeRemote {
+            status: Some(401),
+            ..FakeRemote::default()
+        };
+
+        let store = TieredCacheStore::new(&local).with_remote(&remote);
+
+        assert!(matches!(
+            store.get("main/abc"),
+            Err(CacheError::Remote(RemoteCacheError::Status(_, 401)))
+        ));
+        assert_eq!(store.fallback(), None);
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    pub fn when_remote_entry_is_tampered_should_reject_it_as_a_miss() {
+        let signer = ArtifactSigner::from_hex(&"07".repeat(32)).unwrap();
+        let trusted = TrustedKeys::from_hex([signer.public_key()]).unwrap();
+        let remote = FakeRemote::default();
+
+        let producer_local = MemoryCacheStore::new();
+        let producer = TieredCacheStore::new(&producer_local)
+            .with_remote(&remote)
+            .with_signing(EntrySigning::new(Some(signer), trusted.clone()));
+        producer.put("main/abc", b"entry").unwrap();
+
+        let mut tampered = remote.entries.lock().unwrap()["main/abc"].clone();
+        *tampered.last_mut().unwrap() = b'!';
+        remote.put("main/def", &tampered).unwrap();
+        remote.put("main/ghi", b"\nentry").unwrap();
+
+        let local = MemoryCacheStore::new();
+        let store = TieredCacheStore::new(&local)
+            .with_remote(&remote)
+            .with_signing(EntrySigning::new(None, trusted));
+
+        assert_eq!(store.get("main/abc").unwrap(), Some(b"entry".to_vec()));
+        assert_eq!(store.get("main/def").unwrap(), None);
+        assert_eq!(store.get("main/ghi").unwrap(), None);
+        assert!(matches!(
+            store.rejected().as_slice(),
+            [
+                CacheError::Untrusted(def, CacheSignatureError::Untrusted),
+                CacheError::Untrusted(ghi, CacheSignatureError::Unsigned),
+            ] if def == "main/def" && ghi == "main/ghi"
+        ));
+        assert_eq!(local.keys(), vec!["main/abc"]);
+    }
+
+    #[test]
+    #[cfg(feature = "signing")]
+    pub fn when_local_entry_is_tampered_should_evict_it_and_miss() {
+        let signer = ArtifactSigner::from_hex(&"07".repeat(32)).unwrap();
+        let trusted = TrustedKeys::from_hex([signer.public_key()]).unwrap();
+
+        let local = MemoryCacheStore::new();
+        local.put("main/abc", b"\nentry").unwrap();
+
+        let store = TieredCacheStore::new(&local).with_signing(EntrySigning::new(None, trusted));
+
+        assert_eq!(store.get("main/abc").unwrap(), None);
+        assert!(local.keys().is_empty());
+        assert!(matches!(
+            store.rejected().as_slice(),
+            [CacheError::Untrusted(key, CacheSignatureError::Unsigned)] if key == "main/abc"
+        ));
+    }
+}
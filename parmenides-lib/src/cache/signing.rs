@@ -0,0 +1,222 @@
+//! Signing of cache artifacts with ed25519, so that a consumer can tell a cached binary was
+//! uploaded by a trusted producer and wasn't tampered with since.
+//!
+//! Keys and signatures are exchanged as lowercase hex strings. Only the public keys belong in
+//! the declaration, see [`super::CacheDeclaration::trusted_keys`]; the secret key of a producer
+//! should come from its environment, see [`SIGNING_KEY_ENV`].
+//!
+//! The stores sign and verify their entries with an [`EntrySigning`], e.g.
+//! [`super::TieredCacheStore::with_signing`].
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::errors::CacheSignatureError;
+
+use super::{encode_hex, CacheDeclaration};
+
+/// The environment variable holding the hex-encoded secret key producers sign entries with.
+pub const SIGNING_KEY_ENV: &str = "PARMENIDES_CACHE_SIGNING_KEY";
+
+/// Signs artifacts before they are uploaded.
+#[derive(Debug, Clone)]
+pub struct ArtifactSigner {
+    key: SigningKey,
+}
+
+impl ArtifactSigner {
+    /// Creates a signer from a hex-encoded 32 bytes secret key.
+    pub fn from_hex(secret_key: &str) -> Result<Self, CacheSignatureError> {
+        let bytes = decode_hex(secret_key)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| CacheSignatureError::InvalidKey(secret_key.to_owned()))?;
+
+        Ok(Self {
+            key: SigningKey::from_bytes(&bytes),
+        })
+    }
+
+    /// Returns the hex-encoded public key, to be added to the trusted keys of consumers.
+    pub fn public_key(&self) -> String {
+        encode_hex(&self.key.verifying_key().to_bytes())
+    }
+
+    /// Returns the hex-encoded signature of `content`.
+    pub fn sign(&self, content: &[u8]) -> String {
+        encode_hex(&self.key.sign(content).to_bytes())
+    }
+}
+
+/// Verifies downloaded artifacts against a set of trusted public keys.
+#[derive(Debug, Clone)]
+pub struct TrustedKeys {
+    keys: Vec<VerifyingKey>,
+}
+
+impl TrustedKeys {
+    /// Parses hex-encoded public keys.
+    pub fn from_hex<I, S>(keys: I) -> Result<Self, CacheSignatureError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                let key = key.as_ref();
+
+                decode_hex(key)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+                    .ok_or_else(|| CacheSignatureError::InvalidKey(key.to_owned()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { keys })
+    }
+
+    /// Checks that `signature` was made over `content` by one of the trusted keys.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If a trusted key produced the signature.
+    /// - `Err(CacheSignatureError)`: If the signature is malformed, or no trusted key produced it.
+    pub fn verify(&self, content: &[u8], signature: &str) -> Result<(), CacheSignatureError> {
+        let parsed = decode_hex(signature)
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| CacheSignatureError::InvalidSignature(signature.to_owned()))?;
+
+        self.keys
+            .iter()
+            .any(|key| key.verify_strict(content, &parsed).is_ok())
+            .then_some(())
+            .ok_or(CacheSignatureError::Untrusted)
+    }
+}
+
+/// Signs the entries a store writes and verifies the entries it reads.
+///
+/// A signed entry holds the hex signature of its key and value on its first line, followed by
+/// the value. The key is signed too, so a trusted entry can't be replayed under another key.
+/// The line is empty for entries written without a signer, which are only accepted when there
+/// are no trusted keys.
+#[derive(Debug, Clone)]
+pub struct EntrySigning {
+    signer: Option<ArtifactSigner>,
+    trusted: TrustedKeys,
+}
+
+impl EntrySigning {
+    /// Signs entries with `signer`, if any, and accepts only the entries signed by `trusted`,
+    /// or any entry if it has no keys.
+    pub fn new(signer: Option<ArtifactSigner>, trusted: TrustedKeys) -> Self {
+        Self { signer, trusted }
+    }
+
+    /// Builds the signing of the workspace from its trusted keys and the secret key of the
+    /// producer, usually read from [`SIGNING_KEY_ENV`].
+    ///
+    /// # Returns
+    /// - `Ok(Some(EntrySigning))`: If there is a secret key or trusted keys.
+    /// - `Ok(None)`: If there are neither, so the entries aren't signed.
+    /// - `Err(CacheSignatureError)`: If a key is not valid.
+    pub fn from_declaration(
+        declaration: &CacheDeclaration,
+        secret_key: Option<&str>,
+    ) -> Result<Option<Self>, CacheSignatureError> {
+        if secret_key.is_none() && declaration.trusted_keys.is_empty() {
+            return Ok(None);
+        }
+
+        let signer = secret_key.map(ArtifactSigner::from_hex).transpose()?;
+        let trusted = TrustedKeys::from_hex(&declaration.trusted_keys)?;
+
+        Ok(Some(Self::new(signer, trusted)))
+    }
+
+    /// Returns the entry to store under `key` for `value`.
+    pub fn seal(&self, key: &str, value: &[u8]) -> Vec<u8> {
+        let mut content = match &self.signer {
+            Some(signer) => signer.sign(&message(key, value)).into_bytes(),
+            None => vec![],
+        };
+
+        content.push(b'\n');
+        content.extend_from_slice(value);
+        content
+    }
+
+    /// Returns the value of the entry stored under `key`, after checking its signature.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<u8>)`: The value, if the entry is signed by a trusted key or there are none.
+    /// - `Err(CacheSignatureError)`: If the entry is unsigned, malformed, or not signed by a
+    ///   trusted key.
+    pub fn open(&self, key: &str, content: &[u8]) -> Result<Vec<u8>, CacheSignatureError> {
+        let newline = content
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or(CacheSignatureError::Unsigned)?;
+        let (signature, value) = (&content[..newline], &content[newline + 1..]);
+
+        if !self.trusted.keys.is_empty() {
+            if signature.is_empty() {
+                return Err(CacheSignatureError::Unsigned);
+            }
+
+            let signature = String::from_utf8_lossy(signature);
+            self.trusted.verify(&message(key, value), &signature)?;
+        }
+
+        Ok(value.to_vec())
+    }
+}
+
+fn message(key: &str, value: &[u8]) -> Vec<u8> {
+    let mut message = key.as_bytes().to_vec();
+    message.push(0);
+    message.extend_from_slice(value);
+    message
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::CacheSignatureError;
+
+    use super::{ArtifactSigner, TrustedKeys};
+
+    #[test]
+    pub fn when_verifying_artifact_should_accept_only_trusted_untampered_content() {
+        let signer = ArtifactSigner::from_hex(&"07".repeat(32)).unwrap();
+        let other = ArtifactSigner::from_hex(&"08".repeat(32)).unwrap();
+
+        let trusted = TrustedKeys::from_hex([signer.public_key()]).unwrap();
+        let signature = signer.sign(b"binary");
+
+        assert_eq!(trusted.verify(b"binary", &signature), Ok(()));
+        assert_eq!(
+            trusted.verify(b"tampered", &signature),
+            Err(CacheSignatureError::Untrusted)
+        );
+        assert_eq!(
+            trusted.verify(b"binary", &other.sign(b"binary")),
+            Err(CacheSignatureError::Untrusted)
+        );
+        assert_eq!(
+            trusted.verify(b"binary", "zz"),
+            Err(CacheSignatureError::InvalidSignature("zz".to_owned()))
+        );
+        assert!(matches!(
+            TrustedKeys::from_hex(["abc"]),
+            Err(CacheSignatureError::InvalidKey(_))
+        ));
+    }
+}
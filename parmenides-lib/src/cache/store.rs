@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::CacheError;
+
+use super::encode_hex;
+#[cfg(feature = "signing")]
+use super::signing::EntrySigning;
+
+/// The directory of the [`LocalCacheStore`] of a workspace, relative to its root.
+pub const LOCAL_CACHE_DIRECTORY: &str = ".parmenides/cache";
+
+/// Stores cache entries by namespaced key, e.g. `main/3f2a…`, see
+/// [`super::CacheScope::read_keys`].
+pub trait CacheStore: Send + Sync {
+    /// Returns the entry stored under `key`, or `None` on a miss.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError>;
+
+    /// Stores `value` under `key`, replacing any existing entry.
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), CacheError>;
+
+    /// Deletes the entry stored under `key`, if any.
+    fn remove(&self, key: &str) -> Result<(), CacheError>;
+}
+
+/// A [`CacheStore`] keeping each entry in a file, under a directory of the machine.
+///
+/// Each file starts with a line holding the hex SHA-256 of the entry, so corrupted entries are
+/// read as misses and can be found with [`LocalCacheStore::verify`].
+#[derive(Debug, Clone)]
+pub struct LocalCacheStore {
+    root: PathBuf,
+    #[cfg(feature = "signing")]
+    signing: Option<EntrySigning>,
+}
+
+impl LocalCacheStore {
+    /// A store keeping its entries under `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            #[cfg(feature = "signing")]
+            signing: None,
+        }
+    }
+
+    /// The store of the workspace at `root`, under [`LOCAL_CACHE_DIRECTORY`].
+    pub fn in_workspace<P: AsRef<Path>>(root: P) -> Self {
+        Self::new(root.as_ref().join(LOCAL_CACHE_DIRECTORY))
+    }
+
+    /// Signs the entries written and rejects the entries read that fail the signature check,
+    /// see [`EntrySigning::open`].
+    #[cfg(feature = "signing")]
+    pub fn with_signing(mut self, signing: EntrySigning) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, CacheError> {
+        let relative = Path::new(key);
+
+        // Keys are namespaced with `/`, but must not escape the root.
+        let is_valid = !key.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+
+        if !is_valid {
+            return Err(CacheError::InvalidKey(key.to_owned()));
+        }
+
+        Ok(self.root.join(relative))
+    }
+
+    /// Recomputes the integrity hash of every stored entry.
+    ///
+    /// # Returns
+    /// - `Ok(CacheVerification)`: How many entries were checked and which are corrupted.
+    /// - `Err(CacheError)`: If the directory of the store could not be read.
+    pub fn verify(&self) -> Result<CacheVerification, CacheError> {
+        let mut verification = CacheVerification::default();
+
+        if self.root.exists() {
+            self.verify_directory(&self.root, &mut verification)?;
+        }
+
+        verification.corrupted.sort();
+
+        Ok(verification)
+    }
+
+    fn verify_directory(
+        &self,
+        directory: &Path,
+        verification: &mut CacheVerification,
+    ) -> Result<(), CacheError> {
+        let entries = std::fs::read_dir(directory)
+            .map_err(|err| CacheError::Io(directory.to_path_buf(), err))?;
+
+        for entry in entries {
+            let path = entry
+                .map_err(|err| CacheError::Io(directory.to_path_buf(), err))?
+                .path();
+
+            if path.is_dir() {
+                self.verify_directory(&path, verification)?;
+                continue;
+            }
+
+            // Entries still being written by a `put`.
+            if path.to_string_lossy().contains(".partial-") {
+                continue;
+            }
+
+            let content = std::fs::read(&path).map_err(|err| CacheError::Io(path.clone(), err))?;
+
+            verification.checked += 1;
+
+            if unseal(&content).is_none() {
+                let key = path.strip_prefix(&self.root).unwrap_or(&path);
+                let key: Vec<_> = key
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect();
+
+                verification.corrupted.push(key.join("/"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of [`LocalCacheStore::verify`].
+#[derive(Debug, PartialEq, Default)]
+pub struct CacheVerification {
+    /// How many entries were checked.
+    pub checked: usize,
+    /// The keys of the entries whose content doesn't match their integrity hash, sorted.
+    pub corrupted: Vec<String>,
+}
+
+/// Prefixes `value` with the line holding its integrity hash.
+fn seal(value: &[u8]) -> Vec<u8> {
+    let mut content = encode_hex(&Sha256::digest(value)).into_bytes();
+    content.push(b'\n');
+    content.extend_from_slice(value);
+    content
+}
+
+/// Returns the value of a sealed entry, or `None` if it doesn't match its integrity hash.
+fn unseal(content: &[u8]) -> Option<Vec<u8>> {
+    let newline = content.iter().position(|byte| *byte == b'\n')?;
+    let (hash, value) = (&content[..newline], &content[newline + 1..]);
+
+    (hash == encode_hex(&Sha256::digest(value)).as_bytes()).then(|| value.to_vec())
+}
+
+impl CacheStore for LocalCacheStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let path = self.path(key)?;
+
+        // A corrupted entry is a miss, so the task runs again and overwrites it.
+        let value = match std::fs::read(&path) {
+            Ok(content) => unseal(&content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(CacheError::Io(path, err)),
+        };
+
+        #[cfg(feature = "signing")]
+        if let (Some(signing), Some(value)) = (&self.signing, &value) {
+            return signing
+                .open(key, value)
+                .map(Some)
+                .map_err(|err| CacheError::Untrusted(key.to_owned(), err));
+        }
+
+        Ok(value)
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), CacheError> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| CacheError::Io(parent.to_path_buf(), err))?;
+        }
+
+        // Written aside and renamed, so concurrent readers never see a partial entry.
+        let partial = path.with_extension(format!("partial-{}", std::process::id()));
+
+        #[cfg(feature = "signing")]
+        let signed = self
+            .signing
+            .as_ref()
+            .map(|signing| signing.seal(key, value));
+        #[cfg(feature = "signing")]
+        let value = signed.as_deref().unwrap_or(value);
+
+        std::fs::write(&partial, seal(value))
+            .map_err(|err| CacheError::Io(partial.clone(), err))?;
+        std::fs::rename(&partial, &path).map_err(|err| CacheError::Io(path, err))
+    }
+
+    /// Deletes the entry stored under `key`, if any, e.g. a corrupted one.
+    fn remove(&self, key: &str) -> Result<(), CacheError> {
+        let path = self.path(key)?;
+
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(CacheError::Io(path, err))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An in-memory [`CacheStore`], e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemoryCacheStore {
+    entries: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the keys stored so far, sorted.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.entries.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), CacheError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), value.to_vec());
+
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), CacheError> {
+        self.entries.lock().unwrap().remove(key);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::CacheError;
+
+    use super::{CacheStore, CacheVerification, LocalCacheStore};
+
+    #[test]
+    pub fn when_storing_locally_should_read_back_and_reject_escaping_keys() {
+        let root = std::env::temp_dir().join(format!("parmenides-cache-{}", std::process::id()));
+        let store = LocalCacheStore::in_workspace(&root);
+
+        let missing = store.get("main/abc").unwrap();
+        store.put("main/abc", b"entry").unwrap();
+        let found = store.get("main/abc").unwrap();
+        let escaping = store.put("../abc", b"entry");
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(missing, None);
+        assert_eq!(found, Some(b"entry".to_vec()));
+        assert!(matches!(escaping, Err(CacheError::InvalidKey(_))));
+    }
+
+    #[test]
+    pub fn when_verifying_should_report_corrupted_entries_until_removed() {
+        let root =
+            std::env::temp_dir().join(format!("parmenides-cache-verify-{}", std::process::id()));
+        let store = LocalCacheStore::new(&root);
+
+        store.put("main/abc", b"entry").unwrap();
+        store.put("main/def", b"entry").unwrap();
+
+        let intact = store.verify().unwrap();
+
+        let path = root.join("main/def");
+        let mut content = std::fs::read(&path).unwrap();
+        *content.last_mut().unwrap() = b'!';
+        std::fs::write(&path, content).unwrap();
+
+        let tampered = store.verify().unwrap();
+        let found = store.get("main/def").unwrap();
+        store.remove("main/def").unwrap();
+        let removed = store.verify().unwrap();
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            intact,
+            CacheVerification {
+                checked: 2,
+                corrupted: vec![],
+            }
+        );
+        assert_eq!(tampered.corrupted, vec!["main/def".to_owned()]);
+        assert_eq!(found, None);
+        assert_eq!(
+            removed,
+            CacheVerification {
+                checked: 1,
+                corrupted: vec![],
+            }
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! # CI
+//!
+//! Infers the revisions to diff from the environment variables of CI providers, so the
+//! affected projects of a pipeline are computed without passing revisions, see
+//! [`DiffRange::detect`]. Pull requests are diffed from their merge base with the target
+//! branch, and pushes from the commit before them, so every commit of the push is covered.
+//!
+//! Outside a known provider, the range falls back to the merge base with
+//! [`DEFAULT_BASE`], up to the working directory.
+use std::path::Path;
+
+use crate::file_system::{FileSystem, OsFileSystem};
+
+/// The revision diffed from when no CI provider tells the base.
+pub const DEFAULT_BASE: &str = "origin/main";
+
+/// The revisions to diff between.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DiffRange {
+    /// The revision to diff from.
+    pub from: String,
+    /// The revision to diff to, or `None` for the working directory.
+    pub to: Option<String>,
+    /// Whether to diff from the merge base of the revisions, like `git diff from...to`.
+    pub merge_base: bool,
+}
+
+impl DiffRange {
+    /// The range of a local checkout: from the merge base with [`DEFAULT_BASE`], like
+    /// `origin/main...HEAD`, plus the uncommitted changes.
+    pub fn local() -> Self {
+        Self::merge_base(DEFAULT_BASE.to_owned(), None)
+    }
+
+    /// Infers the range from the environment of the process. See [`Self::detect_with`].
+    pub fn detect() -> Self {
+        Self::detect_with(|name| std::env::var(name).ok(), &OsFileSystem)
+    }
+
+    /// Infers the range from the variables of the CI provider `var` looks up, reading the
+    /// files they point to from `fs`:
+    ///
+    /// - GitHub Actions: from `GITHUB_BASE_REF` on pull requests, or on pushes the `before`
+    ///   commit of the event at `GITHUB_EVENT_PATH`, to `GITHUB_SHA`.
+    /// - GitLab CI: from `CI_MERGE_REQUEST_DIFF_BASE_SHA` on merge requests, or
+    ///   `CI_COMMIT_BEFORE_SHA` on pushes, to `CI_COMMIT_SHA`.
+    /// - Buildkite: from `BUILDKITE_PULL_REQUEST_BASE_BRANCH` on pull requests, to
+    ///   `BUILDKITE_COMMIT`.
+    /// - CircleCI, which doesn't tell the base: from [`DEFAULT_BASE`] to `CIRCLE_SHA1`.
+    ///
+    /// Branches are diffed from their merge base with the remote branch, e.g.
+    /// `origin/main...$GITHUB_SHA`. Without a base, e.g. on the first push of a branch, whose
+    /// commit before is all zeros, the branch defaults to the default branch of the project
+    /// where the provider tells it, and to [`DEFAULT_BASE`] otherwise.
+    ///
+    /// # Returns
+    /// The range of the first provider found, or [`Self::local`] outside CI.
+    pub fn detect_with<F>(var: F, fs: &dyn FileSystem) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        // Providers set some variables empty, e.g. `GITHUB_BASE_REF` outside pull requests.
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let branch = |name: &str| var(name).map(|branch| format!("origin/{branch}"));
+
+        if var("GITHUB_ACTIONS").is_some() {
+            let to = var("GITHUB_SHA");
+
+            if let Some(base) = branch("GITHUB_BASE_REF") {
+                return Self::merge_base(base, to);
+            }
+
+            let event: Option<serde_json::Value> = var("GITHUB_EVENT_PATH")
+                .and_then(|path| fs.read_to_string(Path::new(&path)).ok())
+                .and_then(|content| serde_json::from_str(&content).ok());
+            let event = event.as_ref();
+
+            let before = event
+                .and_then(|event| event["before"].as_str())
+                .filter(|sha| is_commit(sha));
+            let default_branch = event
+                .and_then(|event| event["repository"]["default_branch"].as_str())
+                .map(|branch| format!("origin/{branch}"));
+
+            return match before {
+                Some(before) => Self {
+                    from: before.to_owned(),
+                    to,
+                    merge_base: false,
+                },
+                None => Self::merge_base(Self::default_base(default_branch), to),
+            };
+        }
+
+        if var("GITLAB_CI").is_some() {
+            let to = var("CI_COMMIT_SHA");
+            let before = var("CI_COMMIT_BEFORE_SHA").filter(|sha| is_commit(sha));
+
+            return match var("CI_MERGE_REQUEST_DIFF_BASE_SHA").or(before) {
+                Some(from) => Self {
+                    from,
+                    to,
+                    merge_base: false,
+                },
+                None => Self::merge_base(Self::default_base(branch("CI_DEFAULT_BRANCH")), to),
+            };
+        }
+
+        if var("BUILDKITE").is_some() {
+            let base = branch("BUILDKITE_PULL_REQUEST_BASE_BRANCH")
+                .or_else(|| branch("BUILDKITE_PIPELINE_DEFAULT_BRANCH"));
+
+            return Self::merge_base(Self::default_base(base), var("BUILDKITE_COMMIT"));
+        }
+
+        if var("CIRCLECI").is_some() {
+            return Self::merge_base(DEFAULT_BASE.to_owned(), var("CIRCLE_SHA1"));
+        }
+
+        Self::local()
+    }
+
+    fn merge_base(from: String, to: Option<String>) -> Self {
+        Self {
+            from,
+            to,
+            merge_base: true,
+        }
+    }
+
+    fn default_base(base: Option<String>) -> String {
+        base.unwrap_or_else(|| DEFAULT_BASE.to_owned())
+    }
+}
+
+/// Returns `false` for the all zeros commit providers give as the commit before the first push
+/// of a branch.
+fn is_commit(sha: &str) -> bool {
+    sha.chars().any(|c| c != '0')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::file_system::MemoryFileSystem;
+
+    use super::DiffRange;
+
+    const ZEROS: &str = "0000000000000000000000000000000000000000";
+
+    fn detect(vars: &[(&str, &str)]) -> DiffRange {
+        detect_in(&MemoryFileSystem::new(), vars)
+    }
+
+    fn detect_in(fs: &MemoryFileSystem, vars: &[(&str, &str)]) -> DiffRange {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+
+        DiffRange::detect_with(|name| vars.get(name).map(|value| value.to_string()), fs)
+    }
+
+    fn range(from: &str, to: Option<&str>, merge_base: bool) -> DiffRange {
+        DiffRange {
+            from: from.to_owned(),
+            to: to.map(str::to_owned),
+            merge_base,
+        }
+    }
+
+    #[test]
+    pub fn when_running_github_pull_request_should_diff_from_merge_base_with_target() {
+        assert_eq!(
+            detect(&[
+                ("GITHUB_ACTIONS", "true"),
+                ("GITHUB_BASE_REF", "develop"),
+                ("GITHUB_SHA", "abc"),
+            ]),
+            range("origin/develop", Some("abc"), true)
+        );
+    }
+
+    #[test]
+    pub fn when_running_github_push_should_diff_from_commit_before_every_pushed_commit() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/github/event.json",
+            r#"{"before": "first", "after": "abc", "repository": {"default_branch": "trunk"}}"#,
+        );
+
+        assert_eq!(
+            detect_in(
+                &fs,
+                &[
+                    ("GITHUB_ACTIONS", "true"),
+                    ("GITHUB_BASE_REF", ""),
+                    ("GITHUB_EVENT_PATH", "/github/event.json"),
+                    ("GITHUB_SHA", "abc"),
+                ]
+            ),
+            range("first", Some("abc"), false)
+        );
+    }
+
+    #[test]
+    pub fn when_running_github_push_of_new_branch_should_diff_from_merge_base_with_default() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/github/event.json",
+            format!(r#"{{"before": "{ZEROS}", "repository": {{"default_branch": "trunk"}}}}"#),
+        );
+
+        let new_branch = detect_in(
+            &fs,
+            &[
+                ("GITHUB_ACTIONS", "true"),
+                ("GITHUB_EVENT_PATH", "/github/event.json"),
+                ("GITHUB_SHA", "abc"),
+            ],
+        );
+        let without_event = detect(&[("GITHUB_ACTIONS", "true"), ("GITHUB_SHA", "abc")]);
+
+        assert_eq!(new_branch, range("origin/trunk", Some("abc"), true));
+        assert_eq!(without_event, range("origin/main", Some("abc"), true));
+    }
+
+    #[test]
+    pub fn when_running_gitlab_should_diff_from_merge_request_base_or_commit_before() {
+        assert_eq!(
+            detect(&[
+                ("GITLAB_CI", "true"),
+                ("CI_MERGE_REQUEST_DIFF_BASE_SHA", "base"),
+                ("CI_COMMIT_BEFORE_SHA", "before"),
+                ("CI_COMMIT_SHA", "abc"),
+            ]),
+            range("base", Some("abc"), false)
+        );
+        assert_eq!(
+            detect(&[
+                ("GITLAB_CI", "true"),
+                ("CI_COMMIT_BEFORE_SHA", "before"),
+                ("CI_COMMIT_SHA", "abc"),
+            ]),
+            range("before", Some("abc"), false)
+        );
+    }
+
+    #[test]
+    pub fn when_running_gitlab_push_of_new_branch_should_diff_from_merge_base_with_default() {
+        assert_eq!(
+            detect(&[
+                ("GITLAB_CI", "true"),
+                ("CI_COMMIT_BEFORE_SHA", ZEROS),
+                ("CI_DEFAULT_BRANCH", "trunk"),
+                ("CI_COMMIT_SHA", "abc"),
+            ]),
+            range("origin/trunk", Some("abc"), true)
+        );
+    }
+
+    #[test]
+    pub fn when_running_buildkite_should_diff_from_merge_base_with_pull_request_base() {
+        assert_eq!(
+            detect(&[
+                ("BUILDKITE", "true"),
+                ("BUILDKITE_PULL_REQUEST_BASE_BRANCH", "release"),
+                ("BUILDKITE_COMMIT", "abc"),
+            ]),
+            range("origin/release", Some("abc"), true)
+        );
+    }
+
+    #[test]
+    pub fn when_running_circleci_should_diff_from_merge_base_with_default_base() {
+        assert_eq!(
+            detect(&[("CIRCLECI", "true"), ("CIRCLE_SHA1", "abc")]),
+            range("origin/main", Some("abc"), true)
+        );
+    }
+
+    #[test]
+    pub fn when_running_outside_ci_should_use_the_local_range() {
+        assert_eq!(detect(&[]), DiffRange::local());
+    }
+}
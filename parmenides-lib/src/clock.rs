@@ -0,0 +1,101 @@
+//! # Clock
+//!
+//! Time is read through the [`Clock`] trait, so code that measures durations, debounces, or
+//! waits between retries can be tested deterministically with a [`ManualClock`] instead of
+//! sleeping.
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A source of time.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Blocks for the given duration.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The [`Clock`] of the operating system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// A [`Clock`] that only moves when told to. Sleeping advances it instantly.
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Returns how far the clock moved since it was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// A borrowed clock reads the same time, so a caller can keep a [`ManualClock`] it lends.
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Clock, ManualClock};
+
+    #[test]
+    pub fn when_sleeping_on_manual_clock_should_advance_instantly() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+
+        clock.sleep(Duration::from_secs(60));
+        clock.advance(Duration::from_secs(1));
+
+        assert_eq!(clock.now() - start, Duration::from_secs(61));
+    }
+}
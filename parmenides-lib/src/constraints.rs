@@ -0,0 +1,265 @@
+//! # Constraints
+//!
+//! Constraints enforce the architecture of a workspace on its graph, like the module boundaries
+//! of Nx, e.g. that projects tagged `ui` must not depend on projects tagged `infra`. Each
+//! constraint applies to the projects matching a [`Selector`], and restricts the projects they
+//! may depend on directly. Checking a workspace returns every offending edge, not just the
+//! first, so a whole migration can be planned from one run.
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ConstraintError;
+use crate::project::ProjectId;
+use crate::selector::Selector;
+use crate::workspace::Workspace;
+
+/// Represents a declared constraint, see [`Constraint`].
+///
+/// Every field but the name is a [`crate::selector::Selector`] expression.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConstraintDeclaration {
+    /// The unique name of the constraint, used in reports.
+    pub name: String,
+    /// The selector for the projects the constraint applies to, e.g. `tag:ui`.
+    pub source: String,
+    /// The selector for the only projects the sources may depend on.
+    #[serde(default)]
+    pub allowed: Option<String>,
+    /// The selector for the projects the sources must not depend on, e.g. `tag:infra`.
+    #[serde(default)]
+    pub forbidden: Option<String>,
+}
+
+/// A rule restricting the direct dependencies of the projects matching a selector.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Constraint {
+    name: String,
+    source: Selector,
+    allowed: Option<Selector>,
+    forbidden: Option<Selector>,
+}
+
+impl Constraint {
+    /// Creates a constraint on the projects matching `source`, which doesn't restrict their
+    /// dependencies until [`Self::with_allowed`] or [`Self::with_forbidden`] is set.
+    pub fn new<N>(name: N, source: Selector) -> Self
+    where
+        N: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            source,
+            allowed: None,
+            forbidden: None,
+        }
+    }
+
+    /// Only allows the sources to depend on the projects matching `allowed`.
+    pub fn with_allowed(mut self, allowed: Selector) -> Self {
+        self.allowed = Some(allowed);
+        self
+    }
+
+    /// Forbids the sources from depending on the projects matching `forbidden`.
+    pub fn with_forbidden(mut self, forbidden: Selector) -> Self {
+        self.forbidden = Some(forbidden);
+        self
+    }
+
+    /// Creates the constraint from its declaration, parsing every selector.
+    ///
+    /// # Returns
+    /// - `Ok(Constraint)`: The constraint.
+    /// - `Err(ConstraintError)`: If a selector is invalid, or the declaration restricts
+    ///   nothing.
+    pub fn from_declaration(declaration: &ConstraintDeclaration) -> Result<Self, ConstraintError> {
+        let parse = |expression: &str| {
+            Selector::parse(expression)
+                .map_err(|err| ConstraintError::InvalidSelector(declaration.name.clone(), err))
+        };
+
+        if declaration.allowed.is_none() && declaration.forbidden.is_none() {
+            return Err(ConstraintError::Unrestricted(declaration.name.clone()));
+        }
+
+        let mut constraint = Self::new(declaration.name.clone(), parse(&declaration.source)?);
+
+        if let Some(allowed) = &declaration.allowed {
+            constraint = constraint.with_allowed(parse(allowed)?);
+        }
+
+        if let Some(forbidden) = &declaration.forbidden {
+            constraint = constraint.with_forbidden(parse(forbidden)?);
+        }
+
+        Ok(constraint)
+    }
+
+    /// Returns the name of the constraint.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Why a dependency breaks a constraint.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ViolationKind {
+    /// The dependency matches the forbidden selector.
+    Forbidden,
+    /// The dependency doesn't match the allowed selector.
+    NotAllowed,
+}
+
+/// A dependency edge breaking a constraint.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConstraintViolation {
+    /// The name of the broken constraint.
+    pub constraint: String,
+    /// The project the constraint applies to.
+    pub dependent: ProjectId,
+    /// The dependency it may not have.
+    pub dependency: ProjectId,
+    pub kind: ViolationKind,
+}
+
+/// Checks the direct dependencies of every project against the constraints.
+///
+/// Every kind of edge counts, soft and implicit ones included, as they are dependencies all
+/// the same. A dependency both forbidden and not allowed by a constraint is reported once, as
+/// forbidden.
+///
+/// # Returns
+/// Every violation, ordered by dependent, then dependency, then constraint, as declared.
+pub fn check(workspace: &Workspace, constraints: &[Constraint]) -> Vec<ConstraintViolation> {
+    let mut violations = Vec::new();
+
+    for (id, project) in workspace.iter_with_ids() {
+        let applying: Vec<&Constraint> = constraints
+            .iter()
+            .filter(|constraint| constraint.source.matches(project))
+            .collect();
+
+        if applying.is_empty() {
+            continue;
+        }
+
+        for dependency_id in project.dependencies() {
+            let Some(dependency) = workspace.get_project(*dependency_id) else {
+                continue;
+            };
+
+            for constraint in &applying {
+                let kind = if constraint
+                    .forbidden
+                    .as_ref()
+                    .is_some_and(|forbidden| forbidden.matches(dependency))
+                {
+                    ViolationKind::Forbidden
+                } else if constraint
+                    .allowed
+                    .as_ref()
+                    .is_some_and(|allowed| !allowed.matches(dependency))
+                {
+                    ViolationKind::NotAllowed
+                } else {
+                    continue;
+                };
+
+                violations.push(ConstraintViolation {
+                    constraint: constraint.name.clone(),
+                    dependent: id,
+                    dependency: *dependency_id,
+                    kind,
+                });
+            }
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::ConstraintError;
+
+    use super::{check, Constraint, ConstraintDeclaration, ConstraintViolation, ViolationKind};
+
+    #[test]
+    pub fn when_checking_constraints_should_report_every_offending_edge() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("libs/db", "db", None).tags = vec!["infra".to_owned()];
+        declaration.add_project("libs/design", "design", None).tags = vec!["ui".to_owned()];
+        declaration.add_project("libs/tools", "tools", None);
+        declaration
+            .add_project(
+                "apps/web",
+                "web",
+                Some(vec![
+                    "libs/db".into(),
+                    "libs/design".into(),
+                    "libs/tools".into(),
+                ]),
+            )
+            .tags = vec!["ui".to_owned()];
+
+        let workspace = declaration.build_workspace().unwrap();
+        let id = |identifier| workspace.get_id_by_identifier(identifier).unwrap();
+
+        let declarations = [
+            ConstraintDeclaration {
+                name: "ui-not-infra".to_owned(),
+                source: "tag:ui".to_owned(),
+                allowed: None,
+                forbidden: Some("tag:infra".to_owned()),
+            },
+            ConstraintDeclaration {
+                name: "ui-only-ui".to_owned(),
+                source: "tag:ui".to_owned(),
+                allowed: Some("tag:ui".to_owned()),
+                forbidden: None,
+            },
+        ];
+        let constraints: Vec<Constraint> = declarations
+            .iter()
+            .map(Constraint::from_declaration)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let violation = |constraint: &str, dependency, kind| ConstraintViolation {
+            constraint: constraint.to_owned(),
+            dependent: id("web"),
+            dependency: id(dependency),
+            kind,
+        };
+
+        assert_eq!(
+            check(&workspace, &constraints),
+            vec![
+                violation("ui-not-infra", "db", ViolationKind::Forbidden),
+                violation("ui-only-ui", "db", ViolationKind::NotAllowed),
+                violation("ui-only-ui", "tools", ViolationKind::NotAllowed),
+            ]
+        );
+
+        let unrestricted = ConstraintDeclaration {
+            name: "nothing".to_owned(),
+            source: "*".to_owned(),
+            allowed: None,
+            forbidden: None,
+        };
+        let invalid = ConstraintDeclaration {
+            forbidden: Some("team:infra".to_owned()),
+            ..unrestricted.clone()
+        };
+
+        assert!(matches!(
+            Constraint::from_declaration(&unrestricted),
+            Err(ConstraintError::Unrestricted(name)) if name == "nothing"
+        ));
+        assert!(matches!(
+            Constraint::from_declaration(&invalid),
+            Err(ConstraintError::InvalidSelector(..))
+        ));
+    }
+}
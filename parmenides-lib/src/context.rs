@@ -0,0 +1,109 @@
+//! # Context
+//!
+//! Long operations take a [`Context`] holding what the caller controls about their execution:
+//! where progress is reported, whether the operation should stop early because a daemon or
+//! language server superseded the request, whether paths that can't be read fail it, and
+//! whether it may use the network.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::progress::{NoProgress, ProgressSink};
+use crate::warnings::IoPolicy;
+
+/// A flag shared between the caller and an operation, used to abort the operation promptly.
+///
+/// Clones share the flag, so the caller keeps a clone and cancels it from another thread.
+/// Operations check it between steps and return their `Cancelled` error variant.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation holding the token to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The execution context of a long operation.
+///
+/// The default context reports nothing and is never cancelled.
+#[derive(Clone)]
+pub struct Context {
+    progress: Arc<dyn ProgressSink>,
+    cancellation: CancellationToken,
+    io_policy: IoPolicy,
+    offline: bool,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            progress: Arc::new(NoProgress),
+            cancellation: CancellationToken::new(),
+            io_policy: IoPolicy::default(),
+            offline: false,
+        }
+    }
+
+    /// Reports progress to `progress`.
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressSink>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Stops when `cancellation` is cancelled.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Handles the paths that can't be read with `policy`, see [`crate::warnings`].
+    pub fn with_io_policy(mut self, policy: IoPolicy) -> Self {
+        self.io_policy = policy;
+        self
+    }
+
+    /// Keeps the operation off the network when `offline` is set, so remote caches and fetches
+    /// are skipped and only what is available locally is used.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Returns where progress is reported.
+    pub fn progress(&self) -> &dyn ProgressSink {
+        self.progress.as_ref()
+    }
+
+    /// Returns how the paths that can't be read are handled.
+    pub fn io_policy(&self) -> IoPolicy {
+        self.io_policy
+    }
+
+    /// Returns `true` if the operation must not use the network.
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Returns `true` if the operation should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,320 @@
+//! # Credentials
+//!
+//! The tokens of remote backends, such as the remote cache, are resolved by a
+//! [`CredentialChain`] rather than written in the declaration. The chain tries each
+//! [`CredentialSource`] in order: an environment variable, the credentials file of the user,
+//! the keychain of the operating system and a helper command, e.g. one reading a secret
+//! manager. Only where to look is declared, see [`CredentialsDeclaration`].
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::CredentialError;
+use crate::process::{shell_command, ProcessRunner};
+
+/// The environment variable holding the service of a credential, for helper commands.
+pub const SERVICE_ENV: &str = "PARMENIDES_CREDENTIAL_SERVICE";
+
+/// The keychain service the tokens are stored under.
+const KEYCHAIN_SERVICE: &str = "parmenides";
+
+/// Represents where the credentials of remote backends are looked for, after the environment
+/// variable of each backend.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CredentialsDeclaration {
+    /// The credentials file, a TOML table of tokens by service. Defaults to
+    /// `parmenides/credentials.toml` in the configuration directory of the user.
+    pub file: Option<PathBuf>,
+    /// Whether the keychain of the operating system is looked up: `security` on macOS and
+    /// `secret-tool` elsewhere.
+    #[serde(default)]
+    pub keychain: bool,
+    /// A shell command printing the token of the service in [`SERVICE_ENV`], tried last.
+    pub helper: Option<String>,
+}
+
+impl CredentialsDeclaration {
+    /// Builds the chain of a backend reading its token from the environment variable `env`
+    /// first.
+    pub fn chain<'r>(&self, env: &str, runner: &'r dyn ProcessRunner) -> CredentialChain<'r> {
+        let mut chain = CredentialChain::new(runner).with_source(CredentialSource::Env(env.into()));
+
+        if let Some(file) = self.file.clone().or_else(default_file) {
+            chain = chain.with_source(CredentialSource::File(file));
+        }
+
+        if self.keychain {
+            chain = chain.with_source(CredentialSource::Keychain);
+        }
+
+        if let Some(helper) = &self.helper {
+            chain = chain.with_source(CredentialSource::Helper(helper.clone()));
+        }
+
+        chain
+    }
+}
+
+/// Returns the default credentials file, in `XDG_CONFIG_HOME`, `~/.config` or `APPDATA`.
+fn default_file() -> Option<PathBuf> {
+    let directory = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+
+    Some(directory.join("parmenides").join("credentials.toml"))
+}
+
+/// A place a token can be found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CredentialSource {
+    /// The environment variable with this name.
+    Env(String),
+    /// A TOML file of tokens by service, e.g. `"https://cache.example.com" = "token"`.
+    File(PathBuf),
+    /// The keychain of the operating system, under the `parmenides` service with the service
+    /// of the credential as account.
+    Keychain,
+    /// A shell command printing the token, run with the service in [`SERVICE_ENV`].
+    Helper(String),
+    /// The token written in the declaration of the backend, usually encrypted.
+    Declaration,
+}
+
+impl std::fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env(name) => write!(f, "the environment variable {name}"),
+            Self::File(path) => write!(f, "the credentials file {}", path.display()),
+            Self::Keychain => write!(f, "the keychain"),
+            Self::Helper(command) => write!(f, "the helper `{command}`"),
+            Self::Declaration => write!(f, "the declaration"),
+        }
+    }
+}
+
+/// A token and where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Credential {
+    pub token: String,
+    pub source: CredentialSource,
+}
+
+/// Resolves tokens from [`CredentialSource`]s, in the order they were added.
+///
+/// Sources without a token for the service are skipped, such as a missing file or keychain
+/// entry, or a keychain tool that isn't installed. Sources that are there but fail, such as an
+/// invalid file or a failing helper, fail the resolution rather than silently falling through.
+pub struct CredentialChain<'r> {
+    sources: Vec<CredentialSource>,
+    runner: &'r dyn ProcessRunner,
+}
+
+impl<'r> CredentialChain<'r> {
+    /// An empty chain, running the keychain tools and helpers with `runner`.
+    pub fn new(runner: &'r dyn ProcessRunner) -> Self {
+        Self {
+            sources: vec![],
+            runner,
+        }
+    }
+
+    pub fn with_source(mut self, source: CredentialSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Returns the sources, in the order they are tried.
+    pub fn sources(&self) -> &[CredentialSource] {
+        &self.sources
+    }
+
+    /// Returns the token of `service`, e.g. the URL of a remote cache, from the first source
+    /// having one.
+    ///
+    /// # Returns
+    /// - `Ok(Some(Credential))`: The token, and the source it was found in.
+    /// - `Ok(None)`: If no source has a token.
+    /// - `Err(CredentialError)`: If a source could not be read.
+    pub fn resolve(&self, service: &str) -> Result<Option<Credential>, CredentialError> {
+        for source in &self.sources {
+            let token = match source {
+                CredentialSource::Env(name) => std::env::var(name).ok(),
+                CredentialSource::File(path) => read_file(path, service)?,
+                CredentialSource::Keychain => self.keychain(service),
+                CredentialSource::Helper(command) => self.helper(command, service)?,
+                // Declared tokens belong to a backend, not a service.
+                CredentialSource::Declaration => None,
+            };
+
+            if let Some(token) = token.filter(|token| !token.trim().is_empty()) {
+                return Ok(Some(Credential {
+                    token: token.trim().to_owned(),
+                    source: source.clone(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn keychain(&self, service: &str) -> Option<String> {
+        let mut command = if cfg!(target_os = "macos") {
+            let mut command = Command::new("security");
+            command.args([
+                "find-generic-password",
+                "-s",
+                KEYCHAIN_SERVICE,
+                "-a",
+                service,
+                "-w",
+            ]);
+            command
+        } else {
+            let mut command = Command::new("secret-tool");
+            command.args(["lookup", "service", KEYCHAIN_SERVICE, "account", service]);
+            command
+        };
+
+        // Missing tools and entries alike mean the keychain has no token.
+        let output = self.runner.run(&mut command).ok()?;
+
+        output
+            .is_success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn helper(&self, helper: &str, service: &str) -> Result<Option<String>, CredentialError> {
+        let mut command = shell_command(helper);
+        command.env(SERVICE_ENV, service);
+
+        let output = self
+            .runner
+            .run(&mut command)
+            .map_err(|err| CredentialError::Helper(helper.to_owned(), err.to_string()))?;
+
+        if !output.is_success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            return Err(CredentialError::Helper(helper.to_owned(), stderr));
+        }
+
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+}
+
+fn read_file(path: &std::path::Path, service: &str) -> Result<Option<String>, CredentialError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(CredentialError::Io(path.to_path_buf(), err)),
+    };
+
+    let tokens: HashMap<String, String> = toml::from_str(&content)
+        .map_err(|err| CredentialError::Parse(path.to_path_buf(), err.to_string()))?;
+
+    Ok(tokens.get(service).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::CredentialError;
+    use crate::process::{ProcessOutput, ScriptedProcessRunner};
+
+    use super::{Credential, CredentialSource, CredentialsDeclaration};
+
+    /// Writes a credentials file with `content` in a fresh directory named after `name`.
+    fn credentials_file(name: &str, content: &str) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "parmenides-credentials-{name}-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let file = directory.join("credentials.toml");
+        std::fs::write(&file, content).unwrap();
+
+        file
+    }
+
+    /// Answers every helper command with a token. The keychain tools aren't scripted, so they
+    /// look missing.
+    fn runner() -> ScriptedProcessRunner {
+        ScriptedProcessRunner::new().with_output(
+            if cfg!(windows) { "cmd" } else { "sh" },
+            ProcessOutput::success("from-helper\n"),
+        )
+    }
+
+    #[test]
+    pub fn when_file_has_the_token_should_use_it_before_later_sources() {
+        let file = credentials_file("file", "\"https://cache.example.com\" = \"from-file\"\n");
+        let runner = runner();
+
+        let declaration = CredentialsDeclaration {
+            file: Some(file.clone()),
+            keychain: true,
+            helper: Some("vault read token".to_owned()),
+        };
+        let credential = declaration
+            .chain("PARMENIDES_TEST_MISSING_TOKEN", &runner)
+            .resolve("https://cache.example.com");
+
+        std::fs::remove_dir_all(file.parent().unwrap()).unwrap();
+
+        assert_eq!(
+            credential.unwrap(),
+            Some(Credential {
+                token: "from-file".to_owned(),
+                source: CredentialSource::File(file),
+            })
+        );
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    pub fn when_earlier_sources_have_no_token_should_use_the_helper() {
+        let file = credentials_file("helper", "\"https://cache.example.com\" = \"from-file\"\n");
+        let runner = runner();
+
+        let declaration = CredentialsDeclaration {
+            file: Some(file.clone()),
+            keychain: true,
+            helper: Some("vault read token".to_owned()),
+        };
+        let credential = declaration
+            .chain("PARMENIDES_TEST_MISSING_TOKEN", &runner)
+            .resolve("https://other.example.com");
+
+        std::fs::remove_dir_all(file.parent().unwrap()).unwrap();
+
+        assert_eq!(
+            credential.unwrap(),
+            Some(Credential {
+                token: "from-helper".to_owned(),
+                source: CredentialSource::Helper("vault read token".to_owned()),
+            })
+        );
+        // The keychain, then the helper.
+        assert_eq!(runner.commands().len(), 2);
+    }
+
+    #[test]
+    pub fn when_credentials_file_is_invalid_should_return_parse_error() {
+        let file = credentials_file("invalid", "not toml");
+        let runner = runner();
+
+        let declaration = CredentialsDeclaration {
+            file: Some(file.clone()),
+            ..CredentialsDeclaration::default()
+        };
+        let credential = declaration
+            .chain("PARMENIDES_TEST_MISSING_TOKEN", &runner)
+            .resolve("https://cache.example.com");
+
+        std::fs::remove_dir_all(file.parent().unwrap()).unwrap();
+
+        assert!(matches!(credential, Err(CredentialError::Parse(path, _)) if path == file));
+    }
+}
@@ -1,23 +1,109 @@
 //! Declarations are the serializable forms of the parmenides's objects.
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-use crate::errors::BuildWorkspaceError;
-use crate::project::{Project, ProjectId};
-use crate::workspace::Workspace;
+use crate::affected::builtin_strategy;
+use crate::cache::CacheDeclaration;
+use crate::constraints::ConstraintDeclaration;
+use crate::context::Context;
+use crate::credentials::CredentialsDeclaration;
+use crate::encryption::EncryptionDeclaration;
+use crate::errors::{BuildWorkspaceError, DiscoveryError, LoadDeclarationError, SourceLocation};
+use crate::file_system::{FileSystem, OsFileSystem};
+use crate::inputs::Inputs;
+use crate::lint::Severity;
+use crate::preset::Preset;
+use crate::project::{
+    identifier_from_path, identifier_from_relative_path, is_valid_date, is_valid_identifier,
+    Deprecation, Project, ProjectId, StableId,
+};
+use crate::stats::StatsDeclaration;
+use crate::tasks::Target;
+use crate::unknown_keys::{self, UnknownKey, UnknownKeyPolicy};
+use crate::warnings::PathWarning;
+use crate::watch::WatchBackend;
+use crate::workspace::{DependencyKind, Edge, Workspace};
 
 /// Represents a declaration of a project that can be used with `serde` for serialization and
 /// deserialization.
 ///
 /// ### Fields
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProjectDeclaration {
-    /// The human-readable name of the project.
+    /// The machine identifier of the project, unique in the workspace. Derived from the last
+    /// component of the path when missing, see [`crate::project::identifier_from_path`], or
+    /// from the path relative to the root when that clashes with another project.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The human-readable name of the project. It may contain spaces or emoji.
     pub name: String,
     /// An optional list of paths representing the project's dependencies.
     pub dependencies: Option<Vec<PathBuf>>,
+    /// Dependencies whose changes only propagate with soft propagation enabled, e.g. dev tooling
+    /// or examples. See [`crate::workspace::EdgeStrength::Soft`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub soft_dependencies: Vec<PathBuf>,
+    /// Dependencies not expressed in the files of the project, e.g. on a protobuf schema
+    /// project. They are hard dependencies, only marked for reports, see
+    /// [`crate::workspace::Edge::implicit`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implicit_dependencies: Vec<PathBuf>,
+    /// Dependencies the project fully encapsulates, e.g. behind a facade with its own contract
+    /// tests. Their changes affect the project, but not its dependents. See
+    /// [`crate::workspace::Edge::stop_propagation`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encapsulates: Vec<PathBuf>,
+    /// What some of the dependencies are needed for, by path, e.g. `dev` for test utilities.
+    /// The others are runtime dependencies. See [`crate::workspace::DependencyKind`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependency_kinds: BTreeMap<PathBuf, DependencyKind>,
+    /// The target running the contract tests of the project. When set, changes of the
+    /// encapsulated dependencies only stop at the project if the target passes, see
+    /// [`crate::tasks::TaskRunner::gate_contracts`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+    /// The globs of the files whose changes affect the project, relative to its directory, e.g.
+    /// `["src/**", "!**/*.md"]`. Every file of the project when empty. See
+    /// [`crate::inputs::Inputs`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+    /// Free-form labels used to group projects, e.g. by type (`app`, `lib`) or layer.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The targets the project can run, by name, e.g. `build` or `test`.
+    #[serde(default)]
+    pub targets: BTreeMap<String, Target>,
+    /// A short description of what the project is for, shown in the catalog. Defaults to the
+    /// first paragraph of its README there, see [`crate::export::Catalog`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The people or teams owning the project, e.g. `@web-team`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+    /// Links about the project by label, e.g. `docs` or `runbook`, to their URLs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, String>,
+    /// Marks the project as deprecated, see [`crate::project::Deprecation`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<DeprecationDeclaration>,
+}
+
+/// Represents the lifecycle of a deprecated project, see [`crate::project::Deprecation`].
+///
+/// An empty declaration, e.g. `deprecated = {}`, deprecates the project without a sunset date
+/// or replacement.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DeprecationDeclaration {
+    /// The date the project stops being maintained, as `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+    /// The path of the project the dependents should migrate to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<PathBuf>,
 }
 
 /// Represents a declaration of a workspace.
@@ -25,88 +111,897 @@ pub struct ProjectDeclaration {
 /// A workspace declaration contains multiple project declarations, each indexed by its path.
 /// This allows the workspace to be serialized, deserialized, and rebuilt into a functional
 /// [`Workspace`] object.
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkspaceDeclaration {
+    /// The built-in setup the projects are discovered with, e.g. `cargo-workspace`. The
+    /// declared projects override the discovered ones, see [`Preset::apply`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+    #[serde(default)]
     pub projects: HashMap<PathBuf, ProjectDeclaration>,
+    /// Workspace-level constants (e.g. a registry URL or an image prefix) that can be referenced
+    /// from task commands, cache namespaces and generator templates. See
+    /// [`crate::parameters::Parameters`].
+    #[serde(default)]
+    pub constants: HashMap<String, String>,
+    /// How secrets are masked in task output, logs, and reports.
+    #[serde(default)]
+    pub redaction: RedactionDeclaration,
+    /// The configuration of the graph lint rules.
+    #[serde(default)]
+    pub lint: LintDeclaration,
+    /// The project templates available to [`crate::generate`], indexed by name.
+    #[serde(default)]
+    pub generators: HashMap<String, GeneratorDeclaration>,
+    /// How file changes are watched. See [`crate::watch`].
+    #[serde(default)]
+    pub watch: WatchDeclaration,
+    /// Which cache namespaces are read and written. See [`crate::cache::CacheScope`].
+    #[serde(default)]
+    pub cache: CacheDeclaration,
+    /// Where the tokens of remote backends are looked for. See [`crate::credentials`].
+    #[serde(default)]
+    pub credentials: CredentialsDeclaration,
+    /// How the encrypted values of the declaration are decrypted. See [`crate::encryption`].
+    #[serde(default)]
+    pub encryption: EncryptionDeclaration,
+    /// How long each subsystem may run before it fails with a timeout error.
+    #[serde(default)]
+    pub timeouts: TimeoutsDeclaration,
+    /// The roots remapped between environments. See [`crate::path_roots::PathRoots`].
+    #[serde(default)]
+    pub path_roots: Vec<PathRootDeclaration>,
+    /// Whether command runs are recorded locally. See [`crate::stats`].
+    #[serde(default)]
+    pub stats: StatsDeclaration,
+    /// The globs of the files whose changes affect every project, relative to the workspace
+    /// root, e.g. `["Cargo.lock", ".github/**"]`. See [`Workspace::is_trigger`].
+    #[serde(default)]
+    pub triggers: Vec<String>,
+    /// The name of the built-in strategy marking the affected projects, e.g. `changed_only`.
+    /// Defaults to `transitive_dependents`. See [`crate::affected::builtin_strategy`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affected_strategy: Option<String>,
+    /// The rules restricting which projects may depend on which. See
+    /// [`crate::constraints::check`].
+    #[serde(default)]
+    pub constraints: Vec<ConstraintDeclaration>,
+    /// The root the triggers are relative to, set by [`Self::resolve_paths`].
+    #[serde(skip)]
+    root: PathBuf,
+    /// The paths skipped while discovering the projects, as they could not be read. See
+    /// [`crate::warnings`].
+    #[serde(skip)]
+    pub warnings: Vec<PathWarning>,
+}
+
+/// Represents a project template that can be instantiated to create a new project.
+///
+/// See [`crate::generate::generate_project`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeneratorDeclaration {
+    /// The directory holding the template files. File names and contents may contain
+    /// [`crate::parameters::Parameters`] placeholders.
+    pub template: PathBuf,
+    /// The tags given to every generated project.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The dependencies given to every generated project.
+    #[serde(default)]
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Represents the redaction settings of a workspace.
+///
+/// See [`crate::redaction::Redactor`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RedactionDeclaration {
+    /// Regular expressions whose matches are masked.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Names of the environment variables whose values are secrets and must be masked.
+    #[serde(default)]
+    pub secret_env: Vec<String>,
+}
+
+/// Represents the file watching settings of a workspace.
+///
+/// See [`crate::watch::open_watcher`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchDeclaration {
+    /// The watcher backend. Defaults to [`WatchBackend::Auto`].
+    #[serde(default)]
+    pub backend: WatchBackend,
+    /// How often the polling backend scans the workspace, in milliseconds.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// How long the notify backend waits for more events after one, in milliseconds, so a
+    /// burst of saves is reported as a single change.
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+/// Represents a root the repository has in one environment, and its counterpart in the
+/// environment the declaration is written for.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PathRootDeclaration {
+    /// The root in the other environment, e.g. `/workspace` in a CI container.
+    pub from: PathBuf,
+    /// The root the declaration uses.
+    pub to: PathBuf,
+    /// The environment variable detecting the other environment. The root is always remapped
+    /// when it is missing.
+    #[serde(default)]
+    pub when_env: Option<String>,
+}
+
+/// Represents the timeouts of the subsystems, in milliseconds. A missing timeout waits
+/// forever.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy)]
+pub struct TimeoutsDeclaration {
+    /// The timeout of a diff, see [`crate::diff_engine::GitDiffEngine::with_timeout`].
+    pub diff_ms: Option<u64>,
+    /// The timeout of a single remote cache call.
+    pub cache_ms: Option<u64>,
+    /// The timeout of a command run in a project, see
+    /// [`crate::process::SystemProcessRunner::with_timeout`].
+    pub task_ms: Option<u64>,
+}
+
+impl TimeoutsDeclaration {
+    /// Returns the diff timeout.
+    pub fn diff(&self) -> Option<Duration> {
+        self.diff_ms.map(Duration::from_millis)
+    }
+
+    /// Returns the remote cache call timeout.
+    pub fn cache(&self) -> Option<Duration> {
+        self.cache_ms.map(Duration::from_millis)
+    }
+
+    /// Returns the task timeout.
+    pub fn task(&self) -> Option<Duration> {
+        self.task_ms.map(Duration::from_millis)
+    }
+}
+
+/// Represents the configuration of the graph lint rules.
+///
+/// See [`crate::lint::Linter`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintDeclaration {
+    /// Severity overrides indexed by rule name. Use [`Severity::Off`] to disable a rule.
+    #[serde(default)]
+    pub rules: HashMap<String, Severity>,
+    /// The longest dependency chain allowed by the `max-dependency-depth` rule.
+    #[serde(default = "default_max_dependency_depth")]
+    pub max_dependency_depth: usize,
+    /// The tag that marks a project as an application for the `no-app-to-app` rule.
+    #[serde(default = "default_app_tag")]
+    pub app_tag: String,
+    /// User-defined rules forbidding dependencies between sets of projects.
+    #[serde(default)]
+    pub custom: Vec<CustomRuleDeclaration>,
+}
+
+/// Represents a user-defined lint rule that forbids projects matching `source` from depending
+/// on projects matching `forbidden`.
+///
+/// Both sets are [`crate::selector::Selector`] expressions. The message may reference the
+/// offending projects with `{source}` and `{target}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomRuleDeclaration {
+    /// The unique name of the rule, used in configuration and reports.
+    pub name: String,
+    /// The selector for the projects the rule applies to.
+    pub source: String,
+    /// The selector for the projects the sources must not depend on.
+    pub forbidden: String,
+    /// The message reported for each forbidden dependency.
+    pub message: String,
+    /// The severity of the rule. Defaults to [`Severity::Error`].
+    #[serde(default)]
+    pub severity: Option<Severity>,
+}
+
+fn default_max_dependency_depth() -> usize {
+    8
+}
+
+fn default_app_tag() -> String {
+    "app".to_owned()
+}
+
+fn default_poll_interval_ms() -> u64 {
+    500
+}
+
+fn default_debounce_ms() -> u64 {
+    100
+}
+
+impl Default for WatchDeclaration {
+    fn default() -> Self {
+        Self {
+            backend: WatchBackend::default(),
+            poll_interval_ms: default_poll_interval_ms(),
+            debounce_ms: default_debounce_ms(),
+        }
+    }
+}
+
+impl Default for LintDeclaration {
+    fn default() -> Self {
+        Self {
+            rules: HashMap::new(),
+            max_dependency_depth: default_max_dependency_depth(),
+            app_tag: default_app_tag(),
+            custom: vec![],
+        }
+    }
 }
 
 impl WorkspaceDeclaration {
     pub fn new() -> Self {
         Self {
+            preset: None,
             projects: HashMap::new(),
+            constants: HashMap::new(),
+            redaction: RedactionDeclaration::default(),
+            lint: LintDeclaration::default(),
+            generators: HashMap::new(),
+            watch: WatchDeclaration::default(),
+            cache: CacheDeclaration::default(),
+            credentials: CredentialsDeclaration::default(),
+            encryption: EncryptionDeclaration::default(),
+            timeouts: TimeoutsDeclaration::default(),
+            path_roots: vec![],
+            stats: StatsDeclaration::default(),
+            triggers: vec![],
+            affected_strategy: None,
+            constraints: vec![],
+            root: PathBuf::new(),
+            warnings: vec![],
+        }
+    }
+
+    pub fn add_constant<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.constants.insert(key.into(), value.into());
+    }
+
+    /// Parses a declaration from a TOML string.
+    ///
+    /// Project paths are kept as written. See [`Self::from_toml_path`] to resolve them against
+    /// the location of the file.
+    pub fn from_toml_str(content: &str) -> Result<Self, LoadDeclarationError> {
+        toml::from_str(content).map_err(|err| LoadDeclarationError::Parse {
+            message: err.message().to_owned(),
+            location: err
+                .span()
+                .map(|span| SourceLocation::from_offset(content, span.start)),
+        })
+    }
+
+    /// Reads a declaration from a TOML file, e.g. a `parmenides.toml` at the repository root.
+    ///
+    /// Relative project and dependency paths are resolved against the directory of the file.
+    pub fn from_toml_path<P>(path: P) -> Result<Self, LoadDeclarationError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_path_with(&OsFileSystem, path.as_ref(), Self::from_toml_str)
+    }
+
+    /// Parses a declaration from a YAML string.
+    ///
+    /// Project paths are kept as written. See [`Self::from_yaml_path`] to resolve them against
+    /// the location of the file.
+    pub fn from_yaml_str(content: &str) -> Result<Self, LoadDeclarationError> {
+        serde_yaml::from_str(content).map_err(|err| LoadDeclarationError::Parse {
+            location: err
+                .location()
+                .map(|location| SourceLocation::from_offset(content, location.index())),
+            message: err.to_string(),
+        })
+    }
+
+    /// Reads a declaration from a YAML file, e.g. a `parmenides.yaml` at the repository root.
+    ///
+    /// Relative project and dependency paths are resolved against the directory of the file.
+    pub fn from_yaml_path<P>(path: P) -> Result<Self, LoadDeclarationError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_path_with(&OsFileSystem, path.as_ref(), Self::from_yaml_str)
+    }
+
+    /// Reads a declaration from a file, choosing the format by its extension: `.toml`, or
+    /// `.yaml` and `.yml`.
+    pub fn from_path<P>(path: P) -> Result<Self, LoadDeclarationError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_path_in(&OsFileSystem, path)
+    }
+
+    /// Reads a declaration from a file of the given [`FileSystem`], like [`Self::from_path`].
+    pub fn from_path_in<P>(fs: &dyn FileSystem, path: P) -> Result<Self, LoadDeclarationError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_path_with(fs, path, Self::from_toml_str),
+            Some("yaml" | "yml") => Self::from_path_with(fs, path, Self::from_yaml_str),
+            _ => Err(LoadDeclarationError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Reads a declaration from a file, like [`Self::from_path`], checking its keys against the
+    /// schema first.
+    ///
+    /// # Parameters
+    /// - `path`: The declaration file.
+    /// - `policy`: What to do with unknown keys.
+    ///
+    /// # Returns
+    /// - `Ok((WorkspaceDeclaration, Vec<UnknownKey>))`: The declaration, and its unknown keys
+    ///   unless they are allowed.
+    /// - `Err(LoadDeclarationError)`: If the declaration could not be loaded, or has unknown keys
+    ///   and they are denied.
+    pub fn from_path_checked<P>(
+        path: P,
+        policy: UnknownKeyPolicy,
+    ) -> Result<(Self, Vec<UnknownKey>), LoadDeclarationError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_path_checked_in(&OsFileSystem, path, policy)
+    }
+
+    /// Reads a declaration from a file of the given [`FileSystem`], like
+    /// [`Self::from_path_checked`].
+    pub fn from_path_checked_in<P>(
+        fs: &dyn FileSystem,
+        path: P,
+        policy: UnknownKeyPolicy,
+    ) -> Result<(Self, Vec<UnknownKey>), LoadDeclarationError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let toml = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => true,
+            Some("yaml" | "yml") => false,
+            _ => return Err(LoadDeclarationError::UnsupportedFormat(path.to_path_buf())),
+        };
+
+        let mut unknown = Vec::new();
+
+        let declaration = Self::from_path_with(fs, path, |content| {
+            if policy != UnknownKeyPolicy::Allow {
+                unknown = if toml {
+                    unknown_keys::find_unknown_toml_keys(content)
+                } else {
+                    unknown_keys::find_unknown_yaml_keys(content)
+                };
+            }
+
+            if policy == UnknownKeyPolicy::Deny && !unknown.is_empty() {
+                return Err(LoadDeclarationError::UnknownKeys(std::mem::take(
+                    &mut unknown,
+                )));
+            }
+
+            if toml {
+                Self::from_toml_str(content)
+            } else {
+                Self::from_yaml_str(content)
+            }
+        })?;
+
+        Ok((declaration, unknown))
+    }
+
+    fn from_path_with<F>(
+        fs: &dyn FileSystem,
+        path: &Path,
+        parse: F,
+    ) -> Result<Self, LoadDeclarationError>
+    where
+        F: FnOnce(&str) -> Result<Self, LoadDeclarationError>,
+    {
+        let content = fs
+            .read_to_string(path)
+            .map_err(|err| LoadDeclarationError::Io(path.to_path_buf(), err))?;
+
+        let mut declaration = parse(&content)?;
+
+        if let Some(root) = path.parent() {
+            declaration.resolve_paths(root);
+        }
+
+        Ok(declaration)
+    }
+
+    /// Adds the projects of the declared [`Preset`], if any, discovering them in `fs` under
+    /// the root set by [`Self::resolve_paths`]. See [`Preset::apply`].
+    pub fn apply_preset(
+        &mut self,
+        fs: &dyn FileSystem,
+        context: &Context,
+    ) -> Result<(), DiscoveryError> {
+        let Some(preset) = self.preset else {
+            return Ok(());
+        };
+
+        let root = self.root.clone();
+        preset.apply(fs, &root, self, context)
+    }
+
+    /// Resolves every relative project, dependency, and template path against `root`.
+    pub fn resolve_paths<P>(&mut self, root: P)
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+
+        self.root = root.to_path_buf();
+        self.projects = std::mem::take(&mut self.projects)
+            .into_iter()
+            .map(|(path, mut project)| {
+                if let Some(dependencies) = &mut project.dependencies {
+                    for dependency in dependencies {
+                        *dependency = root.join(&*dependency);
+                    }
+                }
+
+                for dependency in project
+                    .soft_dependencies
+                    .iter_mut()
+                    .chain(&mut project.implicit_dependencies)
+                    .chain(&mut project.encapsulates)
+                {
+                    *dependency = root.join(&*dependency);
+                }
+
+                if let Some(replacement) = project
+                    .deprecated
+                    .as_mut()
+                    .and_then(|deprecated| deprecated.replacement.as_mut())
+                {
+                    *replacement = root.join(&*replacement);
+                }
+
+                project.dependency_kinds = std::mem::take(&mut project.dependency_kinds)
+                    .into_iter()
+                    .map(|(dependency, kind)| (root.join(dependency), kind))
+                    .collect();
+
+                (root.join(path), project)
+            })
+            .collect();
+
+        for generator in self.generators.values_mut() {
+            generator.template = root.join(&generator.template);
+
+            for dependency in &mut generator.dependencies {
+                *dependency = root.join(&*dependency);
+            }
         }
     }
 
-    pub fn add_project<P, S>(&mut self, path: P, name: S, dependencies: Option<Vec<PathBuf>>)
+    /// Adds a project declaration, replacing any declaration with the same path.
+    ///
+    /// Returns the new declaration so the remaining fields can be filled in.
+    pub fn add_project<P, S>(
+        &mut self,
+        path: P,
+        name: S,
+        dependencies: Option<Vec<PathBuf>>,
+    ) -> &mut ProjectDeclaration
     where
         P: Into<PathBuf>,
         S: Into<String>,
     {
-        self.projects.insert(
-            path.into(),
-            ProjectDeclaration {
-                name: name.into(),
-                dependencies,
-            },
-        );
+        let declaration = ProjectDeclaration {
+            id: None,
+            name: name.into(),
+            dependencies,
+            soft_dependencies: vec![],
+            implicit_dependencies: vec![],
+            encapsulates: vec![],
+            dependency_kinds: BTreeMap::new(),
+            contract: None,
+            inputs: vec![],
+            tags: vec![],
+            targets: BTreeMap::new(),
+            description: None,
+            owners: vec![],
+            links: BTreeMap::new(),
+            deprecated: None,
+        };
+
+        match self.projects.entry(path.into()) {
+            Entry::Occupied(mut entry) => {
+                entry.insert(declaration);
+                entry.into_mut()
+            }
+            Entry::Vacant(entry) => entry.insert(declaration),
+        }
     }
 
+    /// Builds the workspace the declaration describes.
+    ///
+    /// Projects are added in path order, each after its dependencies, so building the same
+    /// declaration always assigns the same IDs and reports the same errors, whatever the order
+    /// the projects were declared or discovered in.
+    ///
+    /// # Returns
+    /// - `Ok(Workspace)`: The workspace, with every declared project.
+    /// - `Err(BuildWorkspaceError)`: If a dependency is missing or cyclic, or a project is
+    ///   invalid.
     pub fn build_workspace(self) -> Result<Workspace, BuildWorkspaceError> {
         let mut workspace = Workspace::new();
 
-        for path in self.projects.keys() {
-            let mut stack = Vec::new();
+        // Sorted, as the declaration is a hash map.
+        let mut paths: Vec<&PathBuf> = self.projects.keys().collect();
+        paths.sort();
+
+        let identifiers = self.derived_identifiers(&paths);
+
+        for path in &paths {
+            self.add_project_to_workspace(path, &identifiers, &mut workspace)?;
+        }
+
+        // After every project, as replacements may come later in the order.
+        for path in paths {
+            if let Some(deprecated) = &self.projects[path].deprecated {
+                self.add_deprecation(path, deprecated, &mut workspace)?;
+            }
+        }
 
-            self.add_project_to_workspace(path, &mut workspace, &mut stack)?;
+        if !self.triggers.is_empty() {
+            let triggers =
+                Inputs::new(self.triggers).map_err(BuildWorkspaceError::InvalidTriggers)?;
+
+            workspace.set_triggers(self.root, triggers);
         }
 
+        if let Some(name) = self.affected_strategy {
+            let strategy = builtin_strategy(&name)
+                .ok_or(BuildWorkspaceError::UnknownAffectedStrategy(name))?;
+
+            workspace.set_affected_strategy(strategy);
+        }
+
+        workspace.set_constants(self.constants);
+
         Ok(workspace)
     }
 
+    /// Deprecates the project at `path`, which was added to the workspace.
+    fn add_deprecation(
+        &self,
+        path: &Path,
+        declaration: &DeprecationDeclaration,
+        workspace: &mut Workspace,
+    ) -> Result<(), BuildWorkspaceError> {
+        if let Some(sunset) = &declaration.sunset {
+            if !is_valid_date(sunset) {
+                return Err(BuildWorkspaceError::InvalidSunset(
+                    path.to_path_buf(),
+                    sunset.clone(),
+                ));
+            }
+        }
+
+        let replacement = declaration
+            .replacement
+            .as_ref()
+            .map(|replacement| {
+                workspace
+                    .get_id_by_path(replacement)
+                    .filter(|_| replacement != path)
+                    .ok_or_else(|| {
+                        BuildWorkspaceError::InvalidReplacement(
+                            path.to_path_buf(),
+                            replacement.clone(),
+                        )
+                    })
+            })
+            .transpose()?;
+
+        let id = workspace
+            .get_id_by_path(&path)
+            .ok_or_else(|| BuildWorkspaceError::ProjectDeclarationNotFound(path.to_path_buf()))?;
+
+        workspace.set_deprecation(
+            id,
+            Deprecation {
+                sunset: declaration.sunset.clone(),
+                replacement,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Derives the identifiers of the projects of `paths` without a declared one, from the last
+    /// component of their path. Projects for which it clashes with another project are
+    /// identified by their path relative to the root instead, e.g. `apps-ui` and `libs-ui`, so
+    /// only declared identifiers can clash.
+    fn derived_identifiers(&self, paths: &[&PathBuf]) -> HashMap<PathBuf, String> {
+        let mut taken: HashSet<String> = self
+            .projects
+            .values()
+            .filter_map(|project| project.id.clone())
+            .collect();
+
+        let derived: Vec<(&PathBuf, String)> = paths
+            .iter()
+            .filter(|path| self.projects[**path].id.is_none())
+            .map(|path| (*path, identifier_from_path(path)))
+            .collect();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        for (_, identifier) in &derived {
+            *counts.entry(identifier.clone()).or_default() += 1;
+        }
+
+        let (unique, clashing): (Vec<_>, Vec<_>) = derived
+            .into_iter()
+            .partition(|(_, identifier)| counts[identifier] == 1 && !taken.contains(identifier));
+
+        let mut identifiers = HashMap::new();
+
+        for (path, identifier) in unique {
+            taken.insert(identifier.clone());
+            identifiers.insert(path.clone(), identifier);
+        }
+
+        for (path, _) in clashing {
+            let relative = path.strip_prefix(&self.root).unwrap_or(path);
+            let qualified = identifier_from_relative_path(relative);
+            let mut identifier = qualified.clone();
+
+            // A project could be named like the qualified path of another one.
+            for suffix in 2.. {
+                if taken.insert(identifier.clone()) {
+                    break;
+                }
+
+                identifier = format!("{qualified}-{suffix}");
+            }
+
+            identifiers.insert(path.clone(), identifier);
+        }
+
+        identifiers
+    }
+
+    /// Adds the project at `path` to the workspace, after its dependencies.
+    ///
+    /// The dependencies are walked depth first with an explicit stack, as generated graphs
+    /// can chain thousands of projects, more than the call stack fits.
     fn add_project_to_workspace(
         &self,
         path: &PathBuf,
+        identifiers: &HashMap<PathBuf, String>,
         workspace: &mut Workspace,
-        stack: &mut Vec<PathBuf>,
-    ) -> Result<ProjectId, BuildWorkspaceError> {
-        if let Some(id) = workspace.get_id_by_path(path) {
-            return Ok(id);
-        }
+    ) -> Result<(), BuildWorkspaceError> {
+        // The projects being added, each with the index of its next dependency to add.
+        let mut stack: Vec<(&PathBuf, &ProjectDeclaration, usize)> = Vec::new();
+        let mut on_stack: HashSet<&PathBuf> = HashSet::new();
+        let mut next = Some(path);
 
-        if stack.contains(path) {
-            stack.push(path.clone());
+        loop {
+            if let Some(path) = next.take() {
+                if workspace.get_id_by_path(path).is_none() {
+                    if on_stack.contains(path) {
+                        let mut cycle: Vec<PathBuf> =
+                            stack.iter().map(|(path, ..)| (*path).clone()).collect();
+                        cycle.push(path.clone());
 
-            return Err(BuildWorkspaceError::CyclicDependencyFound(stack.clone()));
+                        return Err(BuildWorkspaceError::CyclicDependencyFound(cycle));
+                    }
+
+                    let declaration = self.projects.get(path).ok_or(
+                        BuildWorkspaceError::ProjectDeclarationNotFound(path.clone()),
+                    )?;
+
+                    on_stack.insert(path);
+                    stack.push((path, declaration, 0));
+                }
+            }
+
+            let Some((_, declaration, index)) = stack.last_mut() else {
+                return Ok(());
+            };
+
+            let dependency = declaration
+                .dependencies
+                .iter()
+                .flatten()
+                .chain(&declaration.soft_dependencies)
+                .chain(&declaration.implicit_dependencies)
+                .nth(*index);
+
+            if let Some(dependency) = dependency {
+                *index += 1;
+                next = Some(dependency);
+                continue;
+            }
+
+            if let Some((path, declaration, _)) = stack.pop() {
+                on_stack.remove(path);
+                self.add_declared_project(path, declaration, identifiers, workspace)?;
+            }
         }
+    }
 
-        stack.push(path.clone());
+    /// Adds a project whose dependencies were all added to the workspace.
+    fn add_declared_project(
+        &self,
+        path: &Path,
+        declaration: &ProjectDeclaration,
+        identifiers: &HashMap<PathBuf, String>,
+        workspace: &mut Workspace,
+    ) -> Result<ProjectId, BuildWorkspaceError> {
+        let id_of = |dependency: &PathBuf| {
+            workspace
+                .get_id_by_path(dependency)
+                .ok_or_else(|| BuildWorkspaceError::ProjectDeclarationNotFound(dependency.clone()))
+        };
 
-        let declaration =
-            self.projects
-                .get(path)
-                .ok_or(BuildWorkspaceError::ProjectDeclarationNotFound(
-                    path.clone(),
-                ))?;
+        let dependencies = declaration
+            .dependencies
+            .as_ref()
+            .map(|dependencies| {
+                dependencies
+                    .iter()
+                    .map(id_of)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
 
-        let dependencies = if let Some(dependencies) = &declaration.dependencies {
-            let mut ids = Vec::with_capacity(dependencies.len());
+        // Soft dependencies are edges like any other, only their propagation differs. A
+        // dependency declared both ways stays hard.
+        let mut soft = Vec::with_capacity(declaration.soft_dependencies.len());
 
-            for dep in dependencies {
-                let id = self.add_project_to_workspace(dep, workspace, stack)?;
+        for dep in &declaration.soft_dependencies {
+            let id = id_of(dep)?;
 
-                ids.push(id)
+            if !dependencies.iter().flatten().any(|hard| *hard == id) && !soft.contains(&id) {
+                soft.push(id);
             }
+        }
+
+        // Implicit dependencies are hard ones, only reported apart. A dependency also declared
+        // explicitly stays explicit.
+        let mut implicit = Vec::with_capacity(declaration.implicit_dependencies.len());
+
+        for dep in &declaration.implicit_dependencies {
+            let id = id_of(dep)?;
+
+            if !dependencies.iter().flatten().any(|hard| *hard == id)
+                && !soft.contains(&id)
+                && !implicit.contains(&id)
+            {
+                implicit.push(id);
+            }
+        }
 
-            Some(ids)
+        let dependencies = if soft.is_empty() && implicit.is_empty() {
+            dependencies
         } else {
+            Some(
+                dependencies
+                    .into_iter()
+                    .flatten()
+                    .chain(soft.iter().copied())
+                    .chain(implicit.iter().copied())
+                    .collect(),
+            )
+        };
+
+        if let Some(contract) = &declaration.contract {
+            if !declaration.targets.contains_key(contract) {
+                return Err(BuildWorkspaceError::UnknownContractTarget(
+                    path.to_path_buf(),
+                    contract.clone(),
+                ));
+            }
+        }
+
+        let inputs = if declaration.inputs.is_empty() {
             None
+        } else {
+            let inputs = Inputs::new(declaration.inputs.iter().cloned())
+                .map_err(|err| BuildWorkspaceError::InvalidInputs(path.to_path_buf(), err))?;
+
+            Some(inputs)
         };
 
-        let project = Project::new(path.clone(), declaration.name.clone(), dependencies);
+        let mut project = Project::new(path.to_path_buf(), declaration.name.clone(), dependencies)
+            .with_tags(declaration.tags.clone())
+            .with_targets(declaration.targets.clone())
+            .with_contract(declaration.contract.clone())
+            .with_inputs(inputs)
+            .with_description(declaration.description.clone())
+            .with_owners(declaration.owners.clone())
+            .with_links(declaration.links.clone());
+
+        if let Some(identifier) = &declaration.id {
+            if !is_valid_identifier(identifier) {
+                return Err(BuildWorkspaceError::InvalidIdentifier(
+                    path.to_path_buf(),
+                    identifier.clone(),
+                ));
+            }
+
+            project = project
+                .with_identifier(identifier.clone())
+                .with_stable_id(StableId::from_identifier(identifier));
+        } else {
+            let relative = path.strip_prefix(&self.root).unwrap_or(path);
+            project = project.with_stable_id(StableId::from_path(relative));
+
+            if let Some(identifier) = identifiers.get(path) {
+                project = project.with_identifier(identifier.clone());
+            }
+        }
 
         let id = workspace
             .add_project(project)
-            .map_err(|err| BuildWorkspaceError::ErrorWhileAddingProject(path.clone(), err))?;
+            .map_err(|err| BuildWorkspaceError::ErrorWhileAddingProject(path.to_path_buf(), err))?;
+
+        for dependency in soft {
+            workspace.set_edge(id, dependency, Edge::soft());
+        }
+
+        for dependency in implicit {
+            workspace.set_edge(id, dependency, Edge::default().with_implicit(true));
+        }
+
+        for (dependency, kind) in &declaration.dependency_kinds {
+            let edge = workspace
+                .get_id_by_path(dependency)
+                .and_then(|id_of_dependency| {
+                    Some((id_of_dependency, workspace.edge(id, id_of_dependency)?))
+                });
+
+            let Some((id_of_dependency, edge)) = edge else {
+                return Err(BuildWorkspaceError::KindOfNonDependency(
+                    path.to_path_buf(),
+                    dependency.clone(),
+                ));
+            };
+
+            workspace.set_edge(id, id_of_dependency, edge.with_kind(*kind));
+        }
+
+        for encapsulated in &declaration.encapsulates {
+            let edge = workspace
+                .get_id_by_path(encapsulated)
+                .and_then(|dependency| Some((dependency, workspace.edge(id, dependency)?)));
+
+            let Some((dependency, edge)) = edge else {
+                return Err(BuildWorkspaceError::EncapsulatesNonDependency(
+                    path.to_path_buf(),
+                    encapsulated.clone(),
+                ));
+            };
+
+            workspace.set_edge(id, dependency, edge.with_stop_propagation(true));
+        }
 
         Ok(id)
     }
@@ -120,9 +1015,14 @@ impl Default for WorkspaceDeclaration {
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
+
+    use crate::errors::{AddProjectError, BuildWorkspaceError, LoadDeclarationError};
+    use crate::file_system::MemoryFileSystem;
+    use crate::project::StableId;
 
-    use crate::errors::BuildWorkspaceError;
+    use crate::unknown_keys::UnknownKeyPolicy;
+    use crate::workspace::{DependencyKind, Edge, EdgeStrength};
 
     use super::WorkspaceDeclaration;
 
@@ -153,6 +1053,166 @@ mod tests {
         assert_eq!(dependent_project.name, "dependent");
     }
 
+    #[test]
+    pub fn when_building_should_derive_missing_identifiers_and_reject_invalid_ones() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/apps/Web App", "🌐 Web app", None);
+        declaration.add_project("/repo/libs/core", "Core", None).id = Some("engine".to_owned());
+
+        let workspace = declaration.build_workspace().unwrap();
+        let web = workspace.get_id_by_identifier("web-app").unwrap();
+
+        assert_eq!(workspace.get_project(web).unwrap().name(), "🌐 Web app");
+        assert_eq!(
+            workspace.get_id_by_identifier("engine"),
+            workspace.get_id_by_path(&"/repo/libs/core")
+        );
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None).id = Some("my core".to_owned());
+
+        assert_eq!(
+            declaration.build_workspace().unwrap_err(),
+            BuildWorkspaceError::InvalidIdentifier("/repo/core".into(), "my core".to_owned())
+        );
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/apps/ui", "app ui", None);
+        declaration.add_project("/repo/libs/ui", "lib ui", None);
+        declaration.add_project("/repo/libs/web", "lib web", None);
+        declaration.resolve_paths("/repo");
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        assert_eq!(
+            workspace.get_id_by_identifier("apps-ui"),
+            workspace.get_id_by_path(&"/repo/apps/ui")
+        );
+        assert_eq!(
+            workspace.get_id_by_identifier("libs-ui"),
+            workspace.get_id_by_path(&"/repo/libs/ui")
+        );
+        assert_eq!(
+            workspace.get_id_by_identifier("web"),
+            workspace.get_id_by_path(&"/repo/libs/web")
+        );
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/apps/web", "app web", None);
+        declaration.add_project("/repo/libs/core", "core", None).id = Some("web".to_owned());
+        declaration.resolve_paths("/repo");
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        assert_eq!(
+            workspace.get_id_by_identifier("apps-web"),
+            workspace.get_id_by_path(&"/repo/apps/web")
+        );
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/apps/ui", "app ui", None).id = Some("ui".to_owned());
+        declaration.add_project("/repo/libs/ui", "lib ui", None).id = Some("ui".to_owned());
+
+        assert!(matches!(
+            declaration.build_workspace().unwrap_err(),
+            BuildWorkspaceError::ErrorWhileAddingProject(
+                _,
+                AddProjectError::IdentifierAlreadyAdded(identifier, _)
+            ) if identifier == "ui"
+        ));
+    }
+
+    #[test]
+    pub fn when_rebuilding_should_keep_stable_ids_of_projects() {
+        let build = |paths: &[&str]| {
+            let mut declaration = WorkspaceDeclaration::new();
+
+            for path in paths {
+                declaration.add_project(*path, *path, None);
+            }
+
+            declaration.add_project("/repo/libs/core", "core", None).id = Some("engine".to_owned());
+            declaration.resolve_paths("/repo");
+            declaration.build_workspace().unwrap()
+        };
+
+        let before = build(&["/repo/apps/web", "/repo/apps/docs"]);
+        let after = build(&["/repo/apps/tools", "/repo/apps/docs", "/repo/apps/web"]);
+
+        for (_, project) in before.iter_with_ids() {
+            let id = after.get_id_by_stable_id(project.stable_id()).unwrap();
+
+            assert_eq!(after.get_project(id).unwrap().path(), project.path());
+        }
+
+        let core = before.get_project_by_path(&"/repo/libs/core").unwrap();
+        let web = before.get_project_by_path(&"/repo/apps/web").unwrap();
+
+        assert_eq!(core.stable_id(), StableId::from_identifier("engine"));
+        assert_eq!(
+            web.stable_id(),
+            StableId::from_path(Path::new("./apps/cli/../web"))
+        );
+        assert_eq!(
+            StableId::parse(&web.stable_id().to_string()),
+            Some(web.stable_id())
+        );
+    }
+
+    #[test]
+    pub fn when_checkout_moves_should_keep_stable_ids_of_projects() {
+        let build = |root: &str| {
+            let mut declaration = WorkspaceDeclaration::from_toml_str(
+                "[projects.\"apps/web\"]\nname = \"web\"\n\n[projects.\"libs/core\"]\nname = \"core\"\n",
+            )
+            .unwrap();
+            declaration.resolve_paths(root);
+            declaration.build_workspace().unwrap()
+        };
+
+        let first = build("/builds/a");
+        let second = build("/builds/b");
+
+        for (_, project) in first.iter_with_ids() {
+            let relative = project.path().strip_prefix("/builds/a").unwrap();
+            let moved = second
+                .get_project_by_path(&Path::new("/builds/b").join(relative))
+                .unwrap();
+
+            assert_eq!(moved.stable_id(), project.stable_id());
+        }
+    }
+
+    #[test]
+    pub fn when_building_same_declaration_should_assign_same_ids() {
+        let build = |paths: &[&str]| {
+            let mut declaration = WorkspaceDeclaration::new();
+
+            for path in paths {
+                declaration.add_project(*path, *path, None);
+            }
+
+            let workspace = declaration.build_workspace().unwrap();
+
+            workspace
+                .iter_with_ids()
+                .map(|(id, project)| (id, project.path().to_path_buf()))
+                .collect::<Vec<_>>()
+        };
+
+        let paths = [
+            "/repo/e", "/repo/a", "/repo/d", "/repo/b", "/repo/c", "/repo/f",
+        ];
+        let mut reversed = paths;
+        reversed.reverse();
+
+        let first = build(&paths);
+
+        assert_eq!(first[0].1, Path::new("/repo/a"));
+        assert_eq!(build(&reversed), first);
+        assert_eq!(build(&paths), first);
+    }
+
     #[test]
     pub fn when_creating_with_cyclic_dependency_should_return_error() {
         let mut workspace_declaration = WorkspaceDeclaration::new();
@@ -185,4 +1245,383 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    pub fn when_building_deep_dependency_chain_should_not_overflow_stack() {
+        const DEPTH: usize = 20_000;
+
+        let mut workspace_declaration = WorkspaceDeclaration::new();
+        let path = |level: usize| PathBuf::from(format!("/chain/{level:05}"));
+
+        // The first project in path order depends, through every other, on the last.
+        for level in 0..DEPTH {
+            let dependencies = (level + 1 < DEPTH).then(|| vec![path(level + 1)]);
+
+            workspace_declaration.add_project(path(level), format!("p{level}"), dependencies);
+        }
+
+        let mut workspace = workspace_declaration.build_workspace().unwrap();
+        let top = workspace.get_id_by_path(&path(0)).unwrap();
+        let bottom = workspace.get_id_by_path(&path(DEPTH - 1)).unwrap();
+
+        workspace.mark_project_as_affected(bottom).unwrap();
+
+        assert_eq!(workspace.len(), DEPTH);
+        assert_eq!(bottom.into_inner(), 0);
+        assert!(workspace.get_project(top).unwrap().affected);
+        assert_eq!(workspace.affected_projects().count(), DEPTH);
+    }
+
+    #[test]
+    pub fn when_building_workspace_should_keep_constants() {
+        let mut workspace_declaration = WorkspaceDeclaration::new();
+
+        workspace_declaration.add_constant("registry", "registry.example.com");
+
+        let workspace = workspace_declaration.build_workspace().unwrap();
+
+        assert_eq!(
+            workspace.constants().get("registry").map(String::as_str),
+            Some("registry.example.com")
+        );
+    }
+
+    #[test]
+    pub fn when_parsing_toml_should_build_declaration() {
+        let content = r#"
+[constants]
+registry = "registry.example.com"
+
+[projects."libs/core"]
+name = "core"
+tags = ["lib"]
+
+[projects."apps/web"]
+name = "web"
+dependencies = ["libs/core"]
+"#;
+
+        let declaration = WorkspaceDeclaration::from_toml_str(content).unwrap();
+
+        assert_eq!(declaration.projects.len(), 2);
+        assert_eq!(declaration.constants["registry"], "registry.example.com");
+
+        let workspace = declaration.build_workspace().unwrap();
+        let web = workspace.get_project_by_path(&"apps/web").unwrap();
+        let core_id = workspace.get_id_by_path(&"libs/core").unwrap();
+
+        assert_eq!(web.dependencies, Some(vec![core_id]));
+    }
+
+    #[test]
+    pub fn when_declaring_soft_dependencies_should_mark_edges_soft_unless_also_hard() {
+        let content = r#"
+[projects."libs/core"]
+name = "core"
+
+[projects."tools/lint"]
+name = "lint"
+
+[projects."apps/web"]
+name = "web"
+dependencies = ["libs/core"]
+soft_dependencies = ["tools/lint", "libs/core"]
+"#;
+
+        let workspace = WorkspaceDeclaration::from_toml_str(content)
+            .unwrap()
+            .build_workspace()
+            .unwrap();
+        let web = workspace.get_id_by_path(&"apps/web").unwrap();
+        let core = workspace.get_id_by_path(&"libs/core").unwrap();
+        let lint = workspace.get_id_by_path(&"tools/lint").unwrap();
+
+        assert_eq!(
+            workspace.get_project(web).unwrap().dependencies,
+            Some(vec![core, lint])
+        );
+        assert_eq!(
+            workspace.edge(web, lint).map(|edge| edge.strength),
+            Some(EdgeStrength::Soft)
+        );
+        assert_eq!(
+            workspace.edge(web, core).map(|edge| edge.strength),
+            Some(EdgeStrength::Hard)
+        );
+    }
+
+    #[test]
+    pub fn when_declaring_implicit_dependencies_should_propagate_like_hard_ones() {
+        let content = r#"
+[projects."libs/core"]
+name = "core"
+
+[projects."schemas/orders"]
+name = "orders"
+
+[projects."services/billing"]
+name = "billing"
+dependencies = ["libs/core"]
+implicit_dependencies = ["schemas/orders", "libs/core"]
+"#;
+
+        let mut workspace = WorkspaceDeclaration::from_toml_str(content)
+            .unwrap()
+            .build_workspace()
+            .unwrap();
+        let billing = workspace.get_id_by_path(&"services/billing").unwrap();
+        let core = workspace.get_id_by_path(&"libs/core").unwrap();
+        let orders = workspace.get_id_by_path(&"schemas/orders").unwrap();
+
+        assert_eq!(
+            workspace.get_project(billing).unwrap().dependencies,
+            Some(vec![core, orders])
+        );
+        assert_eq!(
+            workspace.edge(billing, orders),
+            Some(Edge::default().with_implicit(true))
+        );
+        assert_eq!(workspace.edge(billing, core), Some(Edge::default()));
+
+        workspace.mark_project_as_affected(orders).unwrap();
+
+        assert!(workspace.get_project(billing).unwrap().affected);
+    }
+
+    #[test]
+    pub fn when_encapsulating_should_stop_propagation_of_dependencies_only() {
+        let content = r#"
+[projects."libs/core"]
+name = "core"
+
+[projects."libs/facade"]
+name = "facade"
+dependencies = ["libs/core"]
+encapsulates = ["libs/core"]
+"#;
+
+        let workspace = WorkspaceDeclaration::from_toml_str(content)
+            .unwrap()
+            .build_workspace()
+            .unwrap();
+        let facade = workspace.get_id_by_path(&"libs/facade").unwrap();
+        let core = workspace.get_id_by_path(&"libs/core").unwrap();
+
+        assert!(workspace.edge(facade, core).unwrap().stop_propagation);
+
+        let content = r#"
+[projects."libs/core"]
+name = "core"
+
+[projects."libs/facade"]
+name = "facade"
+encapsulates = ["libs/core"]
+"#;
+
+        assert_eq!(
+            WorkspaceDeclaration::from_toml_str(content)
+                .unwrap()
+                .build_workspace()
+                .unwrap_err(),
+            BuildWorkspaceError::EncapsulatesNonDependency(
+                "libs/facade".into(),
+                "libs/core".into()
+            )
+        );
+    }
+
+    #[test]
+    pub fn when_declaring_dependency_kinds_should_propagate_only_through_chosen_kinds() {
+        let content = r#"
+[projects."libs/core"]
+name = "core"
+
+[projects."libs/testing"]
+name = "testing"
+
+[projects."apps/web"]
+name = "web"
+dependencies = ["libs/core", "libs/testing"]
+dependency_kinds = { "libs/testing" = "dev" }
+"#;
+
+        let mut workspace = WorkspaceDeclaration::from_toml_str(content)
+            .unwrap()
+            .build_workspace()
+            .unwrap();
+        let web = workspace.get_id_by_path(&"apps/web").unwrap();
+        let core = workspace.get_id_by_path(&"libs/core").unwrap();
+        let testing = workspace.get_id_by_path(&"libs/testing").unwrap();
+
+        workspace.mark_project_as_affected(testing).unwrap();
+        let with_every_kind = workspace.get_project(web).unwrap().affected;
+
+        workspace.reset_affected();
+        workspace.set_propagated_kinds([DependencyKind::Runtime]);
+        workspace.mark_project_as_affected(testing).unwrap();
+        let runtime_only = workspace.get_project(web).unwrap().affected;
+        workspace.mark_project_as_affected(core).unwrap();
+
+        assert_eq!(
+            workspace.edge(web, testing).map(|edge| edge.kind),
+            Some(DependencyKind::Dev)
+        );
+        assert_eq!(
+            workspace.edge(web, core).map(|edge| edge.kind),
+            Some(DependencyKind::Runtime)
+        );
+        assert!(with_every_kind);
+        assert!(!runtime_only);
+        assert!(workspace.get_project(web).unwrap().affected);
+
+        let content = r#"
+[projects."libs/testing"]
+name = "testing"
+
+[projects."apps/web"]
+name = "web"
+dependency_kinds = { "libs/testing" = "dev" }
+"#;
+
+        assert_eq!(
+            WorkspaceDeclaration::from_toml_str(content)
+                .unwrap()
+                .build_workspace()
+                .unwrap_err(),
+            BuildWorkspaceError::KindOfNonDependency("apps/web".into(), "libs/testing".into())
+        );
+    }
+
+    #[test]
+    pub fn when_toml_is_invalid_should_report_line_context() {
+        let content = "[projects.core]\nname = 42\n";
+
+        let error = WorkspaceDeclaration::from_toml_str(content).unwrap_err();
+
+        let LoadDeclarationError::Parse { location, .. } = &error else {
+            panic!("expected a parse error, got {error:?}");
+        };
+
+        let location = location.as_ref().unwrap();
+
+        assert_eq!(location.line, 2);
+        assert_eq!(location.column, 8);
+        assert_eq!(location.text, "name = 42");
+        assert!(error.to_string().contains("at line 2, column 8"));
+    }
+
+    #[test]
+    pub fn when_loading_toml_file_should_resolve_relative_paths() {
+        let root =
+            std::env::temp_dir().join(format!("parmenides-declaration-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        let path = root.join("parmenides.toml");
+        std::fs::write(
+            &path,
+            "[projects.core]\nname = \"core\"\n\n[projects.app]\nname = \"app\"\ndependencies = [\"core\"]\n",
+        )
+        .unwrap();
+
+        let declaration = WorkspaceDeclaration::from_toml_path(&path).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let app = &declaration.projects[&root.join("app")];
+        assert_eq!(app.dependencies, Some(vec![root.join("core")]));
+    }
+
+    #[test]
+    pub fn when_toml_file_is_missing_should_return_io_error() {
+        let error = WorkspaceDeclaration::from_toml_path("/definitely/not/here/parmenides.toml")
+            .unwrap_err();
+
+        assert!(matches!(error, LoadDeclarationError::Io(_, _)));
+    }
+
+    #[test]
+    pub fn when_parsing_yaml_should_build_declaration() {
+        let content = r#"
+constants:
+  registry: registry.example.com
+projects:
+  libs/core:
+    name: core
+    tags: [lib]
+  apps/web:
+    name: web
+    dependencies:
+      - libs/core
+"#;
+
+        let declaration = WorkspaceDeclaration::from_yaml_str(content).unwrap();
+
+        assert_eq!(declaration.constants["registry"], "registry.example.com");
+
+        let workspace = declaration.build_workspace().unwrap();
+        let web = workspace.get_project_by_path(&"apps/web").unwrap();
+        let core = workspace.get_project_by_path(&"libs/core").unwrap();
+        let core_id = workspace.get_id_by_path(&"libs/core").unwrap();
+
+        assert_eq!(web.dependencies, Some(vec![core_id]));
+        assert_eq!(core.tags, vec!["lib"]);
+    }
+
+    #[test]
+    pub fn when_yaml_is_invalid_should_report_line_context() {
+        let content = "projects:\n  core:\n    name: [core\n";
+
+        let error = WorkspaceDeclaration::from_yaml_str(content).unwrap_err();
+
+        let LoadDeclarationError::Parse { location, .. } = &error else {
+            panic!("expected a parse error, got {error:?}");
+        };
+
+        assert!(location.is_some());
+    }
+
+    #[test]
+    pub fn when_loading_unknown_extension_should_return_error() {
+        let error = WorkspaceDeclaration::from_path("parmenides.ini").unwrap_err();
+
+        assert!(matches!(error, LoadDeclarationError::UnsupportedFormat(_)));
+    }
+
+    #[test]
+    pub fn when_loading_from_memory_file_system_should_resolve_relative_paths() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/repo/parmenides.yaml",
+            "projects:\n  libs/core:\n    name: core\n",
+        );
+
+        let declaration = WorkspaceDeclaration::from_path_in(&fs, "/repo/parmenides.yaml").unwrap();
+
+        assert_eq!(
+            declaration.projects[Path::new("/repo/libs/core")].name,
+            "core"
+        );
+    }
+
+    #[test]
+    pub fn when_loading_checked_declaration_should_apply_unknown_key_policy() {
+        let fs = MemoryFileSystem::new().with_file(
+            "/repo/parmenides.toml",
+            "[projects.core]\nname = \"core\"\ntagz = [\"lib\"]\n",
+        );
+        let path = "/repo/parmenides.toml";
+
+        let (_, allowed) =
+            WorkspaceDeclaration::from_path_checked_in(&fs, path, UnknownKeyPolicy::Allow).unwrap();
+        let (declaration, warned) =
+            WorkspaceDeclaration::from_path_checked_in(&fs, path, UnknownKeyPolicy::Warn).unwrap();
+        let denied = WorkspaceDeclaration::from_path_checked_in(&fs, path, UnknownKeyPolicy::Deny);
+
+        assert!(allowed.is_empty());
+        assert_eq!(declaration.projects[Path::new("/repo/core")].name, "core");
+        assert_eq!(warned.len(), 1);
+        assert_eq!(warned[0].suggestion.as_deref(), Some("tags"));
+        assert!(matches!(
+            denied,
+            Err(LoadDeclarationError::UnknownKeys(keys)) if keys == warned
+        ));
+    }
 }
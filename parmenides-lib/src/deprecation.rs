@@ -0,0 +1,138 @@
+//! # Deprecation
+//!
+//! Projects can be deprecated with an optional sunset date and replacement, see
+//! [`crate::project::Deprecation`]. The migration report lists, for every deprecated project,
+//! the projects still depending on it, so the remaining work to retire it is tracked from the
+//! graph instead of by hand. New dependencies on deprecated projects are reported by the
+//! [`crate::lint::rules::DeprecatedDependency`] rule.
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+/// A deprecated project and the dependents that must migrate away from it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Migration {
+    /// The deprecated project.
+    pub project: ProjectId,
+    /// The date it stops being maintained, as `YYYY-MM-DD`, if one was announced.
+    pub sunset: Option<String>,
+    /// The project its dependents should migrate to, if any.
+    pub replacement: Option<ProjectId>,
+    /// The projects depending on it directly, in ID order. Deprecated dependents are listed
+    /// too, as they keep the project alive until they are retired themselves.
+    pub dependents: Vec<ProjectId>,
+}
+
+/// Lists the deprecated projects of the workspace with their dependents.
+///
+/// # Returns
+/// The migrations, soonest sunset first, then without a sunset, then by ID.
+pub fn migrations(workspace: &Workspace) -> Vec<Migration> {
+    let mut migrations: Vec<Migration> = workspace
+        .iter_with_ids()
+        .filter_map(|(id, project)| {
+            let deprecation = project.deprecation()?;
+            let mut dependents = project.dependents().to_vec();
+            dependents.sort();
+
+            Some(Migration {
+                project: id,
+                sunset: deprecation.sunset().map(str::to_owned),
+                replacement: deprecation.replacement(),
+                dependents,
+            })
+        })
+        .collect();
+
+    migrations.sort_by(|a, b| {
+        (a.sunset.is_none(), &a.sunset, a.project).cmp(&(b.sunset.is_none(), &b.sunset, b.project))
+    });
+
+    migrations
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::{DeprecationDeclaration, WorkspaceDeclaration};
+    use crate::errors::BuildWorkspaceError;
+
+    use super::{migrations, Migration};
+
+    #[test]
+    pub fn when_listing_migrations_should_order_by_sunset_with_dependents() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("libs/http", "http", None);
+        declaration
+            .add_project("libs/legacy", "legacy", None)
+            .deprecated = Some(DeprecationDeclaration::default());
+        declaration
+            .add_project("libs/old-http", "old-http", None)
+            .deprecated = Some(DeprecationDeclaration {
+            sunset: Some("2027-01-31".to_owned()),
+            replacement: Some("libs/http".into()),
+        });
+        declaration.add_project(
+            "apps/web",
+            "web",
+            Some(vec!["libs/old-http".into(), "libs/legacy".into()]),
+        );
+        declaration.add_project("apps/admin", "admin", Some(vec!["libs/old-http".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+        let id = |identifier| workspace.get_id_by_identifier(identifier).unwrap();
+
+        assert_eq!(
+            migrations(&workspace),
+            vec![
+                Migration {
+                    project: id("old-http"),
+                    sunset: Some("2027-01-31".to_owned()),
+                    replacement: Some(id("http")),
+                    dependents: vec![id("admin"), id("web")],
+                },
+                Migration {
+                    project: id("legacy"),
+                    sunset: None,
+                    replacement: None,
+                    dependents: vec![id("web")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn when_deprecation_is_invalid_should_return_error() {
+        let deprecated = |deprecation| {
+            let mut declaration = WorkspaceDeclaration::new();
+            declaration.add_project("libs/old", "old", None).deprecated = Some(deprecation);
+
+            declaration.build_workspace()
+        };
+
+        for sunset in ["2027-02-29", "2027-13-01", "27-01-31", "2027/01/31"] {
+            assert!(matches!(
+                deprecated(DeprecationDeclaration {
+                    sunset: Some(sunset.to_owned()),
+                    replacement: None,
+                }),
+                Err(BuildWorkspaceError::InvalidSunset(_, date)) if date == sunset
+            ));
+        }
+
+        assert!(deprecated(DeprecationDeclaration {
+            sunset: Some("2028-02-29".to_owned()),
+            replacement: None,
+        })
+        .is_ok());
+
+        for replacement in ["libs/missing", "libs/old"] {
+            assert!(matches!(
+                deprecated(DeprecationDeclaration {
+                    sunset: None,
+                    replacement: Some(replacement.into()),
+                }),
+                Err(BuildWorkspaceError::InvalidReplacement(..))
+            ));
+        }
+    }
+}
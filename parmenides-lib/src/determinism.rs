@@ -0,0 +1,212 @@
+//! # Determinism
+//!
+//! Remote caching is only safe when running a task twice produces the same results. The
+//! detector runs a command twice in each project and compares the exit codes, the standard
+//! output, and the declared output files, flagging the projects whose builds embed timestamps,
+//! random ordering, or other run-specific data.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use crate::context::Context;
+use crate::errors::DeterminismError;
+use crate::file_system::FileSystem;
+use crate::process::{shell_command, ProcessRunner};
+use crate::progress::Stage;
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+/// A difference between two runs of the same command.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Difference {
+    /// The runs exited with different codes.
+    ExitCode(Option<i32>, Option<i32>),
+    /// The runs printed different standard output.
+    Stdout,
+    /// The output file differs between the runs, or exists after only one of them.
+    Output(PathBuf),
+}
+
+/// A project whose command is not deterministic.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Nondeterminism {
+    pub project: ProjectId,
+    pub differences: Vec<Difference>,
+}
+
+/// What a single run produced, with the output files reduced to hashes.
+#[derive(PartialEq)]
+struct RunResult {
+    code: Option<i32>,
+    stdout: u64,
+    outputs: Vec<Option<u64>>,
+}
+
+/// Runs `command` twice in each project and reports the projects whose runs differ.
+///
+/// # Parameters
+/// - `runner`: Spawns the command, through the platform shell, in the project directory.
+/// - `fs`: Reads the output files after each run.
+/// - `workspace`: The workspace the projects belong to.
+/// - `projects`: The projects to check, e.g. the affected ones.
+/// - `command`: The shell command to run.
+/// - `outputs`: The files the command produces, relative to the project directory.
+/// - `context`: Receives each checked project under [`Stage::Determinism`] and stops the check
+///   when cancelled.
+///
+/// # Returns
+/// - `Ok(Vec<Nondeterminism>)`: The projects whose runs differ, in the order given.
+/// - `Err(DeterminismError)`: If the command could not be run.
+pub fn find_nondeterministic_projects(
+    runner: &dyn ProcessRunner,
+    fs: &dyn FileSystem,
+    workspace: &Workspace,
+    projects: &[ProjectId],
+    command: &str,
+    outputs: &[PathBuf],
+    context: &Context,
+) -> Result<Vec<Nondeterminism>, DeterminismError> {
+    let mut reports = Vec::new();
+    let progress = context.progress();
+
+    progress.start(Stage::Determinism, Some(projects.len()));
+
+    for id in projects {
+        let Some(project) = workspace.get_project(*id) else {
+            continue;
+        };
+
+        if context.is_cancelled() {
+            return Err(DeterminismError::Cancelled);
+        }
+
+        progress.advance(Stage::Determinism, &project.path.to_string_lossy());
+
+        let run = || -> Result<RunResult, DeterminismError> {
+            let output = runner
+                .run(shell_command(command).current_dir(&project.path))
+                .map_err(|err| match err.kind() {
+                    std::io::ErrorKind::TimedOut => DeterminismError::Timeout(project.path.clone()),
+                    _ => DeterminismError::Run(project.path.clone(), err),
+                })?;
+
+            Ok(RunResult {
+                code: output.code,
+                stdout: hash(&output.stdout),
+                outputs: outputs
+                    .iter()
+                    .map(|output| fs.read(&project.path.join(output)).ok().map(|c| hash(&c)))
+                    .collect(),
+            })
+        };
+
+        let first = run()?;
+        let second = run()?;
+
+        if first == second {
+            continue;
+        }
+
+        let mut differences = Vec::new();
+
+        if first.code != second.code {
+            differences.push(Difference::ExitCode(first.code, second.code));
+        }
+
+        if first.stdout != second.stdout {
+            differences.push(Difference::Stdout);
+        }
+
+        differences.extend(
+            outputs
+                .iter()
+                .zip(first.outputs.iter().zip(&second.outputs))
+                .filter(|(_, (first, second))| first != second)
+                .map(|(output, _)| Difference::Output(output.clone())),
+        );
+
+        reports.push(Nondeterminism {
+            project: *id,
+            differences,
+        });
+    }
+
+    progress.finish(Stage::Determinism);
+
+    Ok(reports)
+}
+
+fn hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::context::Context;
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::file_system::MemoryFileSystem;
+    use crate::process::{ProcessOutput, ProcessRunner, ScriptedProcessRunner};
+
+    use super::{find_nondeterministic_projects, Difference, Nondeterminism};
+
+    /// Prints the number of the run, like a build embedding a timestamp.
+    #[derive(Default)]
+    struct CountingRunner {
+        runs: AtomicUsize,
+    }
+
+    impl ProcessRunner for CountingRunner {
+        fn run(&self, _command: &mut Command) -> std::io::Result<ProcessOutput> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst);
+
+            Ok(ProcessOutput::success(format!("built at {run}")))
+        }
+    }
+
+    #[test]
+    pub fn when_output_changes_between_runs_should_flag_project() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_path(&"/repo/core").unwrap();
+
+        let fs = MemoryFileSystem::new().with_file("/repo/core/dist/out.js", "");
+
+        let deterministic = find_nondeterministic_projects(
+            &ScriptedProcessRunner::new().with_output("sh", ProcessOutput::success("done")),
+            &fs,
+            &workspace,
+            &[core],
+            "make",
+            &["dist/out.js".into()],
+            &Context::new(),
+        )
+        .unwrap();
+
+        let nondeterministic = find_nondeterministic_projects(
+            &CountingRunner::default(),
+            &fs,
+            &workspace,
+            &[core],
+            "make",
+            &["dist/out.js".into()],
+            &Context::new(),
+        )
+        .unwrap();
+
+        assert!(deterministic.is_empty());
+        assert_eq!(
+            nondeterministic,
+            vec![Nondeterminism {
+                project: core,
+                differences: vec![Difference::Stdout],
+            }]
+        );
+    }
+}
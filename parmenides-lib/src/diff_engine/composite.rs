@@ -0,0 +1,174 @@
+use std::path::PathBuf;
+
+use crate::context::Context;
+use crate::errors::DiffEngineError;
+
+use super::{ChangeKind, ChangedFile, DiffEngine};
+
+/// Where a [`CompositeDiffEngine`] gets changed files from.
+enum Source<'e> {
+    /// An engine diffing the revisions of the call, or its own.
+    Engine {
+        engine: Box<dyn DiffEngine + 'e>,
+        revisions: Option<(String, Option<String>)>,
+    },
+    /// Files reported as changed whatever the revisions.
+    Files(Vec<ChangedFile>),
+}
+
+/// A [`DiffEngine`] reporting the union of the files changed according to several sources,
+/// e.g. the commits since `main`, the uncommitted changes and paths given by hand, so they are
+/// marked as affected in one call.
+///
+/// A file reported by several sources is reported once, with the kind of the first source
+/// reporting it.
+#[derive(Default)]
+pub struct CompositeDiffEngine<'e> {
+    sources: Vec<Source<'e>>,
+}
+
+impl<'e> CompositeDiffEngine<'e> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an engine diffing the revisions each call is given. Engines can be borrowed, so
+    /// one engine can also be added with revisions of its own.
+    pub fn with_engine<E>(mut self, engine: E) -> Self
+    where
+        E: DiffEngine + 'e,
+    {
+        self.sources.push(Source::Engine {
+            engine: Box::new(engine),
+            revisions: None,
+        });
+        self
+    }
+
+    /// Adds an engine always diffing between `from` and `to`, whatever the revisions of the
+    /// call, e.g. `HEAD` and `None` for the uncommitted changes.
+    pub fn with_engine_between<E>(mut self, engine: E, from: &str, to: Option<&str>) -> Self
+    where
+        E: DiffEngine + 'e,
+    {
+        self.sources.push(Source::Engine {
+            engine: Box::new(engine),
+            revisions: Some((from.to_owned(), to.map(str::to_owned))),
+        });
+        self
+    }
+
+    /// Adds files reported as modified by every call. The paths must be absolute, like the
+    /// ones engines report.
+    pub fn with_paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.sources.push(Source::Files(
+            paths
+                .into_iter()
+                .map(|path| ChangedFile::new(path, ChangeKind::Modified))
+                .collect(),
+        ));
+        self
+    }
+}
+
+impl DiffEngine for CompositeDiffEngine<'_> {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        let mut changed_files: Vec<ChangedFile> = Vec::new();
+
+        for source in &self.sources {
+            if context.is_cancelled() {
+                return Err(DiffEngineError::Cancelled);
+            }
+
+            let mut files = match source {
+                Source::Engine {
+                    engine,
+                    revisions: None,
+                } => engine.get_changed_files(from, to, context)?,
+                Source::Engine {
+                    engine,
+                    revisions: Some((from, to)),
+                } => engine.get_changed_files(from, to.as_deref(), context)?,
+                Source::Files(files) => files.clone(),
+            };
+
+            files.retain(|file| {
+                !changed_files
+                    .iter()
+                    .any(|changed| changed.path == file.path && changed.old_path == file.old_path)
+            });
+            changed_files.extend(files);
+        }
+
+        changed_files.sort();
+
+        Ok(changed_files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{CancellationToken, Context};
+    use crate::diff_engine::{ChangeKind, ChangedFile, DiffEngine};
+    use crate::errors::DiffEngineError;
+
+    use super::CompositeDiffEngine;
+
+    /// Reports a file named after the revisions it diffs.
+    struct RevisionsDiffEngine;
+
+    impl DiffEngine for RevisionsDiffEngine {
+        fn get_changed_files(
+            &self,
+            from: &str,
+            to: Option<&str>,
+            _context: &Context,
+        ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+            Ok(vec![ChangedFile::new(
+                format!("/repo/{from}..{}", to.unwrap_or("working")),
+                ChangeKind::Added,
+            )])
+        }
+    }
+
+    #[test]
+    pub fn when_combining_engines_should_report_union_of_changed_files() {
+        let engine = RevisionsDiffEngine;
+        let composite = CompositeDiffEngine::new()
+            .with_engine(&engine)
+            .with_engine_between(&engine, "HEAD", None)
+            .with_paths(["/repo/HEAD..working", "/repo/libs/core/lib.rs"]);
+
+        assert_eq!(
+            composite
+                .get_changed_files("main", Some("HEAD"), &Context::new())
+                .unwrap(),
+            vec![
+                ChangedFile::new("/repo/HEAD..working", ChangeKind::Added),
+                ChangedFile::new("/repo/libs/core/lib.rs", ChangeKind::Modified),
+                ChangedFile::new("/repo/main..HEAD", ChangeKind::Added),
+            ]
+        );
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        assert!(matches!(
+            composite.get_changed_files(
+                "main",
+                None,
+                &Context::new().with_cancellation(cancellation)
+            ),
+            Err(DiffEngineError::Cancelled)
+        ));
+    }
+}
@@ -1,50 +1,1031 @@
-use std::{
-    collections::HashSet,
-    path::{Path, PathBuf},
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use git2::{
+    Delta, DiffDelta, DiffFindOptions, DiffOptions, FileMode, ObjectType, Repository, Tree,
 };
 
-use git2::Repository;
+use crate::context::Context;
+use crate::errors::DiffEngineError;
+use crate::file_system::FileSystem;
+use crate::process::ProcessRunner;
+use crate::workspace::Workspace;
 
-use super::DiffEngine;
+use super::{ChangeKind, ChangedFile, DiffEngine};
 
-pub struct GitDiffEngine;
+/// The number of commits the first attempt of [`GitDiffEngine::deepen`] fetches.
+pub const DEEPEN_STEP: u32 = 50;
 
-impl DiffEngine for GitDiffEngine {
-    fn get_affected_paths<P>(path: P, from: String, to: String) -> Result<HashSet<PathBuf>, String>
+/// The number of times [`GitDiffEngine::deepen`] deepens the history before fetching all of it.
+pub const DEEPEN_ATTEMPTS: u32 = 4;
+
+/// A [`DiffEngine`] backed by a git repository.
+///
+/// The repository is opened once by [`GitDiffEngine::open`] and reused by every diff.
+pub struct GitDiffEngine {
+    path: PathBuf,
+    repository: Repository,
+    merge_base: bool,
+    untracked: bool,
+    ignored: bool,
+    submodules: bool,
+    pathspecs: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl GitDiffEngine {
+    /// Opens the repository at `path`. Changed paths are reported joined to `path`.
+    pub fn open<P>(path: P) -> Result<Self, DiffEngineError>
     where
-        P: AsRef<std::path::Path>,
+        P: AsRef<Path>,
     {
-        get_affected_files_git(path.as_ref(), &from, &to).map_err(|err| err.to_string())
+        let path = path.as_ref().to_path_buf();
+        let repository = Repository::open(&path)
+            .map_err(|err| DiffEngineError::Repository(path.clone(), err.to_string()))?;
+
+        Ok(Self {
+            path,
+            repository,
+            merge_base: false,
+            untracked: false,
+            ignored: false,
+            submodules: false,
+            pathspecs: vec![],
+            timeout: None,
+        })
+    }
+
+    /// Diffs from the merge base of the two revisions instead of from `from`, like
+    /// `git diff from...to`.
+    ///
+    /// A feature branch is then compared against the point it forked from, so commits that
+    /// landed on the base branch afterwards don't mark projects as affected. Without a `to`
+    /// revision, the merge base with `HEAD` is used.
+    pub fn with_merge_base(mut self, merge_base: bool) -> Self {
+        self.merge_base = merge_base;
+        self
+    }
+
+    /// Also reports the untracked files of the working directory as added, when diffing
+    /// against it, so a new source file marks its project as affected before it is committed.
+    pub fn with_untracked(mut self, untracked: bool) -> Self {
+        self.untracked = untracked;
+        self
+    }
+
+    /// Also reports the files ignored by `.gitignore` as added, when diffing against the
+    /// working directory, e.g. generated sources. Build outputs are usually ignored too, so
+    /// this tends to mark many projects as affected.
+    pub fn with_ignored(mut self, ignored: bool) -> Self {
+        self.ignored = ignored;
+        self
+    }
+
+    /// Reports the files changed inside a submodule whose commit changed, joined to its path,
+    /// instead of the submodule directory, recursing into nested submodules.
+    ///
+    /// The submodule must be checked out, with both commits fetched. Otherwise, and for added
+    /// or removed submodules, the directory is reported, which still marks its owner.
+    pub fn with_submodules(mut self, submodules: bool) -> Self {
+        self.submodules = submodules;
+        self
+    }
+
+    /// Only diffs the directories of the projects of `workspace` and its triggers, with
+    /// libgit2 pathspecs, so the trees of large directories outside the workspace, such as
+    /// vendored code or assets, aren't walked. Project paths are matched literally, so a
+    /// directory named with glob characters doesn't match its siblings, while the triggers are
+    /// matched as globs.
+    ///
+    /// Files moved into or out of the workspace are then reported as added or deleted rather
+    /// than renamed. A project at the root of the repository leaves nothing to skip, so the
+    /// whole repository is diffed.
+    pub fn with_workspace_scope(mut self, workspace: &Workspace) -> Self {
+        let relative = |path: &Path| {
+            path.strip_prefix(&self.path).ok().map(|relative| {
+                relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+        };
+
+        let projects: Vec<String> = workspace
+            .iter()
+            .filter_map(|project| relative(project.path()))
+            .collect();
+
+        if projects.iter().any(String::is_empty) {
+            self.pathspecs = vec![];
+            return self;
+        }
+
+        let mut pathspecs: Vec<String> = projects
+            .into_iter()
+            .map(|path| literal_pathspec(&path))
+            .collect();
+
+        if let Some((root, triggers)) = workspace.triggers() {
+            if let Some(root) = relative(root) {
+                pathspecs.extend(triggers.iter().filter(|glob| !glob.starts_with('!')).map(
+                    |glob| match root.as_str() {
+                        "" => glob.clone(),
+                        root => format!("{root}/{glob}"),
+                    },
+                ));
+            }
+        }
+
+        if pathspecs.iter().any(String::is_empty) {
+            pathspecs.clear();
+        }
+
+        pathspecs.sort();
+        pathspecs.dedup();
+
+        self.pathspecs = pathspecs;
+        self
+    }
+
+    /// Fails diffs running longer than `timeout` with [`DiffEngineError::Timeout`].
+    ///
+    /// The time is checked between the steps of a diff, such as resolving the revisions and
+    /// detecting renames, as a single libgit2 call can't be interrupted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns `true` if the repository is a shallow clone, such as the depth-1 checkouts of
+    /// most CI runners, whose history stops after a few commits.
+    pub fn is_shallow(&self) -> bool {
+        self.repository.is_shallow()
+    }
+
+    /// Fetches history from `remote` until a diff between `from` and `to` can be computed in
+    /// a shallow clone, doing nothing otherwise.
+    ///
+    /// A remote branch that wasn't fetched, e.g. `origin/main` in a single-branch clone, is
+    /// fetched first. The history is then deepened by [`DEEPEN_STEP`] commits, doubling on
+    /// every attempt, until the revisions and their merge base with
+    /// [`Self::with_merge_base`] resolve, and is fetched whole after [`DEEPEN_ATTEMPTS`].
+    /// Commands are run with `runner`, so git's own credentials are used.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the repository has the history, or all of it was fetched.
+    /// - `Err(DiffEngineError)`: If a fetch failed, e.g. as the remote doesn't exist.
+    pub fn deepen(
+        &mut self,
+        remote: &str,
+        from: &str,
+        to: Option<&str>,
+        runner: &dyn ProcessRunner,
+    ) -> Result<(), DiffEngineError> {
+        if !self.is_shallow() || self.has_history(from, to) {
+            return Ok(());
+        }
+
+        let prefix = format!("{remote}/");
+
+        for revision in [Some(from), to].into_iter().flatten() {
+            let Some(branch) = revision.strip_prefix(&prefix) else {
+                continue;
+            };
+
+            if self.commit(revision).is_err() {
+                let refspec = format!("+refs/heads/{branch}:refs/remotes/{remote}/{branch}");
+                let args = [format!("--depth={DEEPEN_STEP}"), remote.to_owned(), refspec];
+                self.fetch(remote, &args, runner)?;
+            }
+        }
+
+        let mut depth = DEEPEN_STEP;
+
+        for _ in 0..DEEPEN_ATTEMPTS {
+            if !self.is_shallow() || self.has_history(from, to) {
+                return Ok(());
+            }
+
+            self.fetch(
+                remote,
+                &[format!("--deepen={depth}"), remote.to_owned()],
+                runner,
+            )?;
+            depth *= 2;
+        }
+
+        if self.is_shallow() && !self.has_history(from, to) {
+            self.fetch(
+                remote,
+                &["--unshallow".to_owned(), remote.to_owned()],
+                runner,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path the repository was opened from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the id of the commit `revision` resolves to, e.g. to check that a base branch
+    /// was fetched before diffing against it.
+    pub fn resolve(&self, revision: &str) -> Result<String, DiffEngineError> {
+        self.commit(revision).map(|commit| commit.id().to_string())
+    }
+
+    /// Returns the id of the first parent of the commit `revision` resolves to, which for a
+    /// merge commit is the branch merged into.
+    ///
+    /// # Returns
+    /// - `Ok(Some(String))`: The id of the parent commit.
+    /// - `Ok(None)`: If the commit is the root of the history.
+    /// - `Err(DiffEngineError)`: If the revision could not be resolved.
+    pub fn first_parent(&self, revision: &str) -> Result<Option<String>, DiffEngineError> {
+        let commit = self.commit(revision)?;
+
+        if commit.parent_count() == 0 {
+            return Ok(None);
+        }
+
+        commit
+            .parent_id(0)
+            .map(|id| Some(id.to_string()))
+            .map_err(|err| DiffEngineError::Git(err.to_string()))
+    }
+
+    /// Returns the files of the repository as they were at `revision`.
+    ///
+    /// Paths are read relative to [`Self::path`], like the changed paths of a diff, so a
+    /// declaration can be loaded from a past commit with
+    /// [`crate::declarations::WorkspaceDeclaration::from_path_in`].
+    pub fn file_system(&self, revision: &str) -> Result<RevisionFileSystem<'_>, DiffEngineError> {
+        Ok(RevisionFileSystem {
+            root: &self.path,
+            repository: &self.repository,
+            tree: self.tree(revision)?,
+        })
+    }
+
+    /// Writes the files of `revision` to `directory`, e.g. to run a target as of a past commit.
+    ///
+    /// The working directory, index and `HEAD` of the repository are left untouched.
+    pub fn checkout(&self, revision: &str, directory: &Path) -> Result<(), DiffEngineError> {
+        let tree = self.tree(revision)?;
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout
+            .target_dir(directory)
+            .update_index(false)
+            .recreate_missing(true)
+            .force();
+
+        self.repository
+            .checkout_tree(tree.as_object(), Some(&mut checkout))
+            .map_err(|err| DiffEngineError::Git(err.to_string()))
+    }
+
+    /// Returns the change a delta of a diff stands for, if it changed anything.
+    fn changed_file(&self, delta: &DiffDelta) -> Option<ChangedFile> {
+        let new_path = delta.new_file().path().map(|path| self.path.join(path));
+        let old_path = delta.old_file().path().map(|path| self.path.join(path));
+
+        let changed_file = match delta.status() {
+            Delta::Added | Delta::Copied | Delta::Untracked | Delta::Ignored => {
+                ChangedFile::new(new_path?, ChangeKind::Added)
+            }
+            Delta::Deleted => ChangedFile::new(old_path?, ChangeKind::Deleted),
+            Delta::Renamed => ChangedFile::renamed(new_path?, old_path?),
+            Delta::Modified | Delta::Typechange | Delta::Conflicted => {
+                ChangedFile::new(new_path?, ChangeKind::Modified)
+            }
+            Delta::Unmodified | Delta::Unreadable => return None,
+        };
+
+        Some(changed_file)
+    }
+
+    /// Returns the files changed inside the submodule of a delta moving it between commits,
+    /// or `None` to report the delta as is, see [`Self::with_submodules`].
+    fn submodule_changes(
+        &self,
+        delta: &DiffDelta,
+        context: &Context,
+    ) -> Result<Option<Vec<ChangedFile>>, DiffEngineError> {
+        let (old, new) = (delta.old_file(), delta.new_file());
+
+        let moved = delta.status() == Delta::Modified
+            && old.mode() == FileMode::Commit
+            && new.mode() == FileMode::Commit
+            && !old.id().is_zero()
+            && !new.id().is_zero();
+
+        let Some(path) = new.path().filter(|_| moved) else {
+            return Ok(None);
+        };
+
+        // A submodule that isn't checked out is reported as its directory.
+        let Ok(engine) = GitDiffEngine::open(self.path.join(path)) else {
+            return Ok(None);
+        };
+
+        let engine = engine.with_submodules(true);
+
+        match engine.get_changed_files(&old.id().to_string(), Some(&new.id().to_string()), context)
+        {
+            Ok(files) if !files.is_empty() => Ok(Some(files)),
+            Err(DiffEngineError::Cancelled) => Err(DiffEngineError::Cancelled),
+            // The commits may not be fetched, or the changes are only in the working directory.
+            Ok(_) | Err(_) => Ok(None),
+        }
+    }
+
+    /// Fails if the diff was cancelled or ran out of time.
+    fn check(&self, context: &Context, started: Instant) -> Result<(), DiffEngineError> {
+        if context.is_cancelled() {
+            return Err(DiffEngineError::Cancelled);
+        }
+
+        match self.timeout {
+            Some(timeout) if started.elapsed() >= timeout => Err(DiffEngineError::Timeout(timeout)),
+            _ => Ok(()),
+        }
+    }
+
+    fn commit(&self, revision: &str) -> Result<git2::Commit<'_>, DiffEngineError> {
+        self.repository
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|err| self.revision_error(revision, err))
+    }
+
+    /// Reports a revision that could not be resolved, as missing from the history of a
+    /// shallow clone if the repository is one.
+    fn revision_error(&self, revision: &str, err: git2::Error) -> DiffEngineError {
+        if self.is_shallow() {
+            DiffEngineError::Shallow(revision.to_owned())
+        } else {
+            DiffEngineError::Revision(revision.to_owned(), err.to_string())
+        }
+    }
+
+    /// Returns `true` if the revisions of a diff resolve, and so does their merge base with
+    /// [`Self::with_merge_base`].
+    fn has_history(&self, from: &str, to: Option<&str>) -> bool {
+        if self.merge_base {
+            self.base_tree(from, to).is_ok()
+        } else {
+            self.commit(from).is_ok() && to.is_none_or(|to| self.commit(to).is_ok())
+        }
+    }
+
+    /// Runs `git fetch` in the repository with `args`, then reopens it, as libgit2 doesn't
+    /// see the history fetched by another process.
+    fn fetch(
+        &mut self,
+        remote: &str,
+        args: &[String],
+        runner: &dyn ProcessRunner,
+    ) -> Result<(), DiffEngineError> {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.path).arg("fetch").args(args);
+
+        let output = runner
+            .run(&mut command)
+            .map_err(|err| DiffEngineError::Fetch(remote.to_owned(), err.to_string()))?;
+
+        if !output.is_success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            return Err(DiffEngineError::Fetch(remote.to_owned(), stderr));
+        }
+
+        self.repository = Repository::open(&self.path)
+            .map_err(|err| DiffEngineError::Repository(self.path.clone(), err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns the tree to diff from, honoring [`Self::with_merge_base`].
+    fn base_tree(&self, from: &str, to: Option<&str>) -> Result<git2::Tree<'_>, DiffEngineError> {
+        if !self.merge_base {
+            return self.tree(from);
+        }
+
+        let to = to.unwrap_or("HEAD");
+
+        let base = self
+            .repository
+            .merge_base(self.commit(from)?.id(), self.commit(to)?.id())
+            .map_err(|err| {
+                // The fork point may be past the depth of the clone.
+                if self.is_shallow() {
+                    DiffEngineError::Shallow(format!("the merge base of {from} and {to}"))
+                } else {
+                    DiffEngineError::MergeBase(from.to_owned(), to.to_owned(), err.to_string())
+                }
+            })?;
+
+        self.repository
+            .find_commit(base)
+            .and_then(|commit| commit.tree())
+            .map_err(|err| DiffEngineError::Git(err.to_string()))
+    }
+
+    fn tree(&self, revision: &str) -> Result<git2::Tree<'_>, DiffEngineError> {
+        self.repository
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|err| self.revision_error(revision, err))
+    }
+}
+
+/// The read-only [`FileSystem`] of the tree of a commit, see [`GitDiffEngine::file_system`].
+pub struct RevisionFileSystem<'a> {
+    root: &'a Path,
+    repository: &'a Repository,
+    tree: Tree<'a>,
+}
+
+impl RevisionFileSystem<'_> {
+    /// Returns the object at `path`, or `None` when the path isn't in the tree.
+    fn object(&self, path: &Path) -> Option<git2::Object<'_>> {
+        let relative = path.strip_prefix(self.root).ok()?;
+
+        if relative.as_os_str().is_empty() {
+            return Some(self.tree.as_object().clone());
+        }
+
+        self.tree
+            .get_path(relative)
+            .and_then(|entry| entry.to_object(self.repository))
+            .ok()
+    }
+
+    fn not_found(path: &Path) -> Error {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("{} is not in the revision", path.display()),
+        )
+    }
+}
+
+impl FileSystem for RevisionFileSystem<'_> {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        let object = self.object(path).ok_or_else(|| Self::not_found(path))?;
+        let blob = object
+            .into_blob()
+            .map_err(|_| Error::new(ErrorKind::IsADirectory, path.display().to_string()))?;
+
+        Ok(blob.content().to_vec())
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let object = self.object(path).ok_or_else(|| Self::not_found(path))?;
+        let tree = object
+            .into_tree()
+            .map_err(|_| Error::new(ErrorKind::NotADirectory, path.display().to_string()))?;
+
+        let mut entries: Vec<PathBuf> = tree
+            .iter()
+            .filter_map(|entry| entry.name().map(|name| path.join(name)))
+            .collect();
+
+        entries.sort();
+
+        Ok(entries)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.object(path)
+            .is_some_and(|object| object.kind() == Some(ObjectType::Blob))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.object(path)
+            .is_some_and(|object| object.kind() == Some(ObjectType::Tree))
     }
 }
 
-fn get_affected_files_git(
-    repo_path: &Path,
-    from: &str,
-    to: &str,
-) -> Result<HashSet<PathBuf>, git2::Error> {
-    let repo = Repository::open(repo_path)?;
+impl DiffEngine for GitDiffEngine {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        let started = Instant::now();
+        let tree_from = self.base_tree(from, to)?;
+
+        self.check(context, started)?;
+
+        let mut options = DiffOptions::new();
+
+        for pathspec in &self.pathspecs {
+            options.pathspec(pathspec);
+        }
+
+        let diff = match to {
+            Some(to) => {
+                let tree_to = self.tree(to)?;
+
+                self.repository.diff_tree_to_tree(
+                    Some(&tree_from),
+                    Some(&tree_to),
+                    Some(&mut options),
+                )
+            }
+            None => {
+                options
+                    .include_untracked(self.untracked)
+                    .recurse_untracked_dirs(self.untracked)
+                    .include_ignored(self.ignored)
+                    .recurse_ignored_dirs(self.ignored);
+
+                self.repository
+                    .diff_tree_to_workdir_with_index(Some(&tree_from), Some(&mut options))
+            }
+        }
+        .map_err(|err| DiffEngineError::Git(err.to_string()))?;
+
+        self.check(context, started)?;
 
-    let commit_from = repo.revparse_single(from)?;
-    let tree_from = commit_from.peel_to_tree()?;
+        // Without rename detection, a moved file shows up as an unrelated deletion and addition.
+        let mut diff = diff;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+            .map_err(|err| DiffEngineError::Git(err.to_string()))?;
 
-    let commit_to = repo.revparse_single(to)?;
-    let tree_to = commit_to.peel_to_tree()?;
+        self.check(context, started)?;
 
-    let diff = repo.diff_tree_to_tree(Some(&tree_from), Some(&tree_to), None)?;
+        let mut changed_files = Vec::new();
 
-    let mut affected_paths = HashSet::new();
-    diff.foreach(
-        &mut |delta, _| {
-            if let Some(path) = delta.new_file().path() {
-                affected_paths.insert(repo_path.join(path));
+        for delta in diff.deltas() {
+            if self.submodules {
+                if let Some(submodule_files) = self.submodule_changes(&delta, context)? {
+                    changed_files.extend(submodule_files);
+                    continue;
+                }
             }
-            true
-        },
-        None,
-        None,
-        None,
-    )?;
 
-    Ok(affected_paths)
+            changed_files.extend(self.changed_file(&delta));
+        }
+
+        changed_files.sort();
+
+        Ok(changed_files)
+    }
+}
+
+/// Returns a pathspec matching the files below `path` literally. libgit2 doesn't support the
+/// `:(literal)` magic of git, so the glob characters are escaped instead, and as an escaped
+/// pathspec is matched as a glob rather than as a directory prefix, `/*` is appended to it.
+fn literal_pathspec(path: &str) -> String {
+    let mut pathspec = String::with_capacity(path.len());
+
+    for character in path.chars() {
+        if matches!(character, '*' | '?' | '[' | ']' | '\\') {
+            pathspec.push('\\');
+        }
+
+        pathspec.push(character);
+    }
+
+    if pathspec.len() != path.len() {
+        pathspec.push_str("/*");
+    }
+
+    pathspec
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use git2::Repository;
+
+    use crate::affected::compute_merge_affected;
+    use crate::context::Context;
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::diff_engine::suite::{self, commit};
+    use crate::diff_engine::{ChangedFile, DiffEngine};
+    use crate::errors::{ComputeMergeAffectedError, DiffEngineError};
+    use crate::file_system::FileSystem;
+    use crate::process::{ProcessOutput, ProcessRunner};
+
+    use super::GitDiffEngine;
+
+    fn paths(changed_files: Vec<ChangedFile>) -> Vec<PathBuf> {
+        changed_files
+            .into_iter()
+            .map(|changed_file| changed_file.path)
+            .collect()
+    }
+
+    #[test]
+    pub fn when_repository_is_missing_should_return_repository_error() {
+        suite::fails_without_repository("no-repo", |path| GitDiffEngine::open(path));
+    }
+
+    #[test]
+    pub fn when_revision_is_unknown_should_return_revision_error() {
+        suite::fails_on_unknown_revision("git-unknown", |path| GitDiffEngine::open(path).unwrap());
+    }
+
+    #[test]
+    pub fn when_resolving_unknown_revision_should_return_revision_error() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-empty-repo-{}", std::process::id()));
+        Repository::init(&path).unwrap();
+
+        let resolved = GitDiffEngine::open(&path).unwrap().resolve("missing");
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert!(matches!(
+            resolved,
+            Err(DiffEngineError::Revision(revision, _)) if revision == "missing"
+        ));
+    }
+
+    #[test]
+    pub fn when_clone_is_shallow_should_deepen_until_diff_has_history() {
+        /// Fakes the fetches, writing the remote branch and dropping the shallow boundary.
+        struct Remote {
+            git: PathBuf,
+            base: String,
+            commands: Mutex<Vec<Vec<String>>>,
+        }
+
+        impl ProcessRunner for Remote {
+            fn run(&self, command: &mut Command) -> std::io::Result<ProcessOutput> {
+                let args: Vec<String> = command
+                    .get_args()
+                    .skip(3)
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .collect();
+
+                if args.iter().any(|arg| arg.starts_with("+refs/heads/main")) {
+                    let branch = self.git.join("refs/remotes/origin/main");
+                    std::fs::create_dir_all(branch.parent().unwrap())?;
+                    std::fs::write(branch, format!("{}\n", self.base))?;
+                }
+
+                if args.iter().any(|arg| arg.starts_with("--deepen")) {
+                    std::fs::remove_file(self.git.join("shallow"))?;
+                }
+
+                self.commands.lock().unwrap().push(args);
+
+                Ok(ProcessOutput::success(""))
+            }
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("parmenides-git-shallow-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        let base = commit(&repository, &[("libs/core/lib.rs", "1")]);
+        commit(&repository, &[("libs/core/lib.rs", "2")]);
+        let boundary = commit(&repository, &[("apps/web/main.rs", "1")]);
+        commit(&repository, &[("libs/core/lib.rs", "3")]);
+
+        // Like a clone of depth 2, the history stops at the boundary.
+        std::fs::write(path.join(".git/shallow"), format!("{boundary}\n")).unwrap();
+
+        let remote = Remote {
+            git: path.join(".git"),
+            base,
+            commands: Mutex::default(),
+        };
+
+        let mut engine = GitDiffEngine::open(&path).unwrap().with_merge_base(true);
+        let shallow = engine.is_shallow();
+        let ancestor = engine.resolve("HEAD~2");
+        let before = engine.get_changed_files("origin/main", Some("HEAD"), &Context::new());
+
+        let deepened = engine.deepen("origin", "origin/main", Some("HEAD"), &remote);
+        let after = engine.get_changed_files("origin/main", Some("HEAD"), &Context::new());
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert!(shallow);
+        assert!(matches!(
+            ancestor,
+            Err(DiffEngineError::Shallow(revision)) if revision == "HEAD~2"
+        ));
+        assert!(matches!(before, Err(DiffEngineError::Shallow(_))));
+        assert!(deepened.is_ok());
+        assert!(!engine.is_shallow());
+        assert_eq!(
+            remote.commands.into_inner().unwrap(),
+            [
+                vec![
+                    "--depth=50",
+                    "origin",
+                    "+refs/heads/main:refs/remotes/origin/main"
+                ],
+                vec!["--deepen=50", "origin"]
+            ]
+        );
+        assert_eq!(
+            paths(after.unwrap()),
+            vec![path.join("apps/web/main.rs"), path.join("libs/core/lib.rs")]
+        );
+    }
+
+    #[test]
+    pub fn when_cancelled_should_return_cancelled_error() {
+        suite::stops_when_cancelled("git-cancel", |path| GitDiffEngine::open(path).unwrap());
+    }
+
+    #[test]
+    pub fn when_diff_exceeds_timeout_should_return_timeout_error() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-git-timeout-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        let first = commit(&repository, &[("libs/core/lib.rs", "1")]);
+        let second = commit(&repository, &[("libs/core/lib.rs", "2")]);
+
+        let result = GitDiffEngine::open(&path)
+            .unwrap()
+            .with_timeout(Duration::ZERO)
+            .get_changed_files(&first, Some(&second), &Context::new());
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(DiffEngineError::Timeout(timeout)) if timeout == Duration::ZERO
+        ));
+    }
+
+    #[test]
+    pub fn when_diffing_revisions_should_reuse_repository_across_calls() {
+        let path = std::env::temp_dir().join(format!("parmenides-git-diff-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        let first = commit(&repository, &[("libs/core/lib.rs", "1")]);
+        let second = commit(&repository, &[("apps/web/main.rs", "1")]);
+        let third = commit(&repository, &[("libs/core/lib.rs", "2")]);
+
+        let engine = GitDiffEngine::open(&path).unwrap();
+
+        let first_diff = engine.get_changed_files(&first, Some(&second), &Context::new());
+        let second_diff = engine.get_changed_files(&second, Some(&third), &Context::new());
+        let head = engine.resolve("HEAD");
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(
+            paths(first_diff.unwrap()),
+            vec![path.join("apps/web/main.rs")]
+        );
+        assert_eq!(
+            paths(second_diff.unwrap()),
+            vec![path.join("libs/core/lib.rs")]
+        );
+        assert_eq!(head.unwrap(), third);
+    }
+
+    #[test]
+    pub fn when_diffing_against_working_directory_should_include_uncommitted_changes() {
+        suite::includes_uncommitted_changes("git-workdir", |path| {
+            GitDiffEngine::open(path).unwrap()
+        });
+    }
+
+    #[test]
+    pub fn when_including_untracked_files_should_report_them_as_added() {
+        suite::reports_untracked_files("git-untracked", |path, untracked| {
+            GitDiffEngine::open(path).unwrap().with_untracked(untracked)
+        });
+    }
+
+    #[test]
+    pub fn when_including_ignored_files_should_report_them_as_added() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-git-ignored-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        commit(
+            &repository,
+            &[("libs/core/lib.rs", "1"), (".gitignore", "generated/\n")],
+        );
+
+        std::fs::create_dir_all(path.join("libs/core/src")).unwrap();
+        std::fs::create_dir_all(path.join("generated")).unwrap();
+        std::fs::write(path.join("libs/core/src/new.rs"), "1").unwrap();
+        std::fs::write(path.join("generated/api.rs"), "1").unwrap();
+
+        let ignored = GitDiffEngine::open(&path)
+            .unwrap()
+            .with_untracked(true)
+            .with_ignored(true)
+            .get_changed_files("HEAD", None, &Context::new());
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(
+            paths(ignored.unwrap()),
+            vec![
+                path.join("generated/api.rs"),
+                path.join("libs/core/src/new.rs")
+            ]
+        );
+    }
+
+    #[test]
+    pub fn when_submodule_commit_changes_should_report_files_inside_it() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-git-submodule-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+        let submodule = Repository::init(path.join("vendor/lib")).unwrap();
+
+        // Stages the commit the submodule is at, then commits the rest.
+        let bump = |files: &[(&str, &str)]| {
+            commit(&submodule, files);
+
+            let mut index = repository.index().unwrap();
+            index.add_path(Path::new("vendor/lib")).unwrap();
+            index.write().unwrap();
+
+            commit(&repository, &[("libs/core/lib.rs", files[0].1)])
+        };
+
+        let first = bump(&[("a.rs", "1")]);
+        let second = bump(&[("a.rs", "2"), ("b.rs", "1")]);
+
+        let diff = |engine: GitDiffEngine| {
+            paths(
+                engine
+                    .get_changed_files(&first, Some(&second), &Context::new())
+                    .unwrap(),
+            )
+        };
+
+        let engine = || GitDiffEngine::open(&path).unwrap();
+        let pointer = diff(engine());
+        let recursed = diff(engine().with_submodules(true));
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(
+            pointer,
+            vec![path.join("libs/core/lib.rs"), path.join("vendor/lib")]
+        );
+        assert_eq!(
+            recursed,
+            vec![
+                path.join("libs/core/lib.rs"),
+                path.join("vendor/lib/a.rs"),
+                path.join("vendor/lib/b.rs")
+            ]
+        );
+    }
+
+    #[test]
+    pub fn when_scoped_to_workspace_should_only_diff_projects_and_triggers() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-git-scope-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        let files = |content| {
+            [
+                ("libs/core/lib.rs", content),
+                ("libs/core-extra/lib.rs", content),
+                ("libs/[ab]/lib.rs", content),
+                ("libs/a/lib.rs", content),
+                ("vendor/big/lib.rs", content),
+                ("Cargo.lock", content),
+                ("README.md", content),
+            ]
+        };
+
+        let first = commit(&repository, &files("1"));
+        let second = commit(&repository, &files("2"));
+        std::fs::write(path.join("vendor/big/lib.rs"), "3").unwrap();
+        std::fs::write(path.join("libs/core/lib.rs"), "3").unwrap();
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("libs/core", "core", None);
+        declaration.add_project("libs/[ab]", "bracketed", None);
+        declaration.triggers = vec!["Cargo.lock".to_owned(), "!README.md".to_owned()];
+        declaration.resolve_paths(&path);
+        let workspace = declaration.build_workspace().unwrap();
+
+        let engine = GitDiffEngine::open(&path)
+            .unwrap()
+            .with_workspace_scope(&workspace);
+        let revisions = engine.get_changed_files(&first, Some(&second), &Context::new());
+        let working_directory = engine.get_changed_files(&second, None, &Context::new());
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(
+            paths(revisions.unwrap()),
+            vec![
+                path.join("Cargo.lock"),
+                path.join("libs/[ab]/lib.rs"),
+                path.join("libs/core/lib.rs")
+            ]
+        );
+        assert_eq!(
+            paths(working_directory.unwrap()),
+            vec![path.join("libs/core/lib.rs")]
+        );
+    }
+
+    #[test]
+    pub fn when_diffing_with_merge_base_should_ignore_changes_on_base_branch() {
+        suite::diffs_from_merge_base("git-merge-base", |path, merge_base| {
+            GitDiffEngine::open(path)
+                .unwrap()
+                .with_merge_base(merge_base)
+        });
+    }
+
+    #[test]
+    pub fn when_files_are_renamed_or_deleted_should_report_change_kinds() {
+        suite::reports_change_kinds("git-kinds", |path| GitDiffEngine::open(path).unwrap());
+    }
+
+    #[test]
+    pub fn when_computing_merge_affected_should_use_declaration_of_commit() {
+        let path =
+            std::env::temp_dir().join(format!("parmenides-git-history-{}", std::process::id()));
+        let repository = Repository::init(&path).unwrap();
+
+        let root = commit(
+            &repository,
+            &[
+                (
+                    "parmenides.toml",
+                    "[projects.\"libs/core\"]\nname = \"core\"\n",
+                ),
+                ("libs/core/lib.rs", "1"),
+            ],
+        );
+        let merge = commit(
+            &repository,
+            &[
+                (
+                    "parmenides.toml",
+                    "[projects.\"libs/core\"]\nname = \"core\"\n\n\
+                     [projects.\"apps/web\"]\nname = \"web\"\ndependencies = [\"libs/core\"]\n",
+                ),
+                ("libs/core/lib.rs", "2"),
+                ("apps/web/main.rs", "1"),
+            ],
+        );
+        // The web project is gone from the current declaration, but not from the merge.
+        commit(
+            &repository,
+            &[(
+                "parmenides.toml",
+                "[projects.\"libs/core\"]\nname = \"core\"\n",
+            )],
+        );
+
+        let engine = GitDiffEngine::open(&path).unwrap();
+        let declaration = path.join("parmenides.toml");
+
+        let affected =
+            compute_merge_affected(&engine, &merge, &declaration, |_| {}, &Context::new()).unwrap();
+        let first = compute_merge_affected(&engine, &root, &declaration, |_| {}, &Context::new());
+        let file_system = engine.file_system(&root).unwrap();
+        let listed = file_system.read_dir(&path);
+        let is_dir = file_system.is_dir(&path.join("libs/core"));
+        let missing = file_system.read(&path.join("apps/web/main.rs"));
+
+        std::fs::remove_dir_all(&path).unwrap();
+
+        let mut identifiers: Vec<&str> = affected
+            .affected
+            .iter()
+            .map(|id| affected.workspace.get_project(*id).unwrap().identifier())
+            .collect();
+        identifiers.sort();
+
+        assert_eq!(affected.commit, merge);
+        assert_eq!(affected.parent, root);
+        assert_eq!(identifiers, ["core", "web"]);
+        assert!(matches!(
+            first,
+            Err(ComputeMergeAffectedError::NoParent(commit)) if commit == root
+        ));
+        assert_eq!(
+            listed.unwrap(),
+            vec![path.join("libs"), path.join("parmenides.toml")]
+        );
+        assert!(is_dir);
+        assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
 }
@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use gix::bstr::{BStr, ByteSlice};
+use gix::diff::Rewrites;
+use gix::hash::ObjectId;
+use gix::status::index_worktree::iter::Summary;
+use gix::status::tree_index::TrackRenames;
+use gix::status::UntrackedFiles;
+
+use crate::context::Context;
+use crate::errors::DiffEngineError;
+
+use super::{ChangeKind, ChangedFile, DiffEngine};
+
+/// A [`DiffEngine`] backed by a git repository read with gitoxide, a git implementation in pure
+/// Rust, so it builds where libgit2 can't be linked, such as static musl or WASM targets.
+///
+/// It diffs like [`super::GitDiffEngine`], which remains the default, but has none of its
+/// options beyond [`Self::with_merge_base`] and [`Self::with_untracked`].
+pub struct GixDiffEngine {
+    path: PathBuf,
+    repository: gix::Repository,
+    merge_base: bool,
+    untracked: bool,
+}
+
+impl GixDiffEngine {
+    /// Opens the repository at `path`. Changed paths are reported joined to `path`.
+    pub fn open<P>(path: P) -> Result<Self, DiffEngineError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let repository = gix::open(&path)
+            .map_err(|err| DiffEngineError::Repository(path.clone(), err.to_string()))?;
+
+        Ok(Self {
+            path,
+            repository,
+            merge_base: false,
+            untracked: false,
+        })
+    }
+
+    /// Diffs from the merge base of the two revisions instead of from `from`, like
+    /// `git diff from...to`. Without a `to` revision, the merge base with `HEAD` is used.
+    pub fn with_merge_base(mut self, merge_base: bool) -> Self {
+        self.merge_base = merge_base;
+        self
+    }
+
+    /// Also reports the untracked files of the working directory as added, when diffing
+    /// against it. Files ignored by `.gitignore` are left out.
+    pub fn with_untracked(mut self, untracked: bool) -> Self {
+        self.untracked = untracked;
+        self
+    }
+
+    /// Returns the path the repository was opened from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the changes between two trees, with renames detected.
+    fn tree_changes(
+        &self,
+        from: ObjectId,
+        to: ObjectId,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        let git = |err: gix::Error| DiffEngineError::Git(err.to_string());
+
+        let from = self.repository.find_tree(from).map_err(git)?;
+        let to = self.repository.find_tree(to).map_err(git)?;
+        let options = gix::diff::Options::default().with_rewrites(Some(Rewrites::default()));
+
+        let changes = self
+            .repository
+            .diff_tree_to_tree(&from, &to, options)
+            .map_err(git)?;
+
+        let mut changed_files = Vec::new();
+
+        for change in changes {
+            use gix::object::tree::diff::ChangeDetached as Change;
+
+            let changed_file = match change {
+                Change::Addition {
+                    location,
+                    entry_mode,
+                    ..
+                } if !entry_mode.is_tree() => {
+                    self.changed_file(location.as_bstr(), ChangeKind::Added)
+                }
+                Change::Deletion {
+                    location,
+                    entry_mode,
+                    ..
+                } if !entry_mode.is_tree() => {
+                    self.changed_file(location.as_bstr(), ChangeKind::Deleted)
+                }
+                Change::Modification {
+                    location,
+                    entry_mode,
+                    ..
+                } if !entry_mode.is_tree() => {
+                    self.changed_file(location.as_bstr(), ChangeKind::Modified)
+                }
+                // Directories show up as renamed when all their files moved.
+                Change::Rewrite { entry_mode, .. } if entry_mode.is_tree() => continue,
+                Change::Rewrite {
+                    location,
+                    copy: true,
+                    ..
+                } => self.changed_file(location.as_bstr(), ChangeKind::Added),
+                Change::Rewrite {
+                    location,
+                    source_location,
+                    ..
+                } => ChangedFile::renamed(
+                    self.join(location.as_bstr()),
+                    self.join(source_location.as_bstr()),
+                ),
+                _ => continue,
+            };
+
+            changed_files.push(changed_file);
+        }
+
+        Ok(changed_files)
+    }
+
+    /// Returns the changes between a tree and the working directory, combining the changes
+    /// staged in the index with the ones only in the working directory, like
+    /// `git diff <tree>` does.
+    fn working_directory_changes(
+        &self,
+        from: ObjectId,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        let git = |err: gix::Error| DiffEngineError::Git(err.to_string());
+
+        let untracked = match self.untracked {
+            true => UntrackedFiles::Files,
+            false => UntrackedFiles::None,
+        };
+
+        let items = self
+            .repository
+            .status(gix::progress::Discard)
+            .map_err(git)?
+            .head_tree(from)
+            .tree_index_track_renames(TrackRenames::Given(Rewrites::default()))
+            .untracked_files(untracked)
+            .into_iter(None)
+            .map_err(git)?;
+
+        // The staged changes come first, then the working directory changes of the same paths
+        // adjust them, e.g. a staged file deleted since is no longer reported as added.
+        let mut staged = BTreeMap::new();
+        let mut unstaged = Vec::new();
+
+        for item in items {
+            if context.is_cancelled() {
+                return Err(DiffEngineError::Cancelled);
+            }
+
+            match item.map_err(git)? {
+                gix::status::Item::TreeIndex(change) => {
+                    use gix::diff::index::ChangeRef as Change;
+
+                    let changed_file = match &change {
+                        Change::Addition { location, .. } => {
+                            self.changed_file(location, ChangeKind::Added)
+                        }
+                        Change::Deletion { location, .. } => {
+                            self.changed_file(location, ChangeKind::Deleted)
+                        }
+                        Change::Modification { location, .. } => {
+                            self.changed_file(location, ChangeKind::Modified)
+                        }
+                        Change::Rewrite {
+                            location,
+                            copy: true,
+                            ..
+                        } => self.changed_file(location, ChangeKind::Added),
+                        Change::Rewrite {
+                            location,
+                            source_location,
+                            ..
+                        } => ChangedFile::renamed(self.join(location), self.join(source_location)),
+                    };
+
+                    staged.insert(changed_file.path.clone(), changed_file);
+                }
+                gix::status::Item::IndexWorktree(item) => {
+                    let kind = match item.summary() {
+                        Some(Summary::Removed) => ChangeKind::Deleted,
+                        Some(Summary::Added | Summary::IntentToAdd | Summary::Copied) => {
+                            ChangeKind::Added
+                        }
+                        Some(
+                            Summary::Modified
+                            | Summary::TypeChange
+                            | Summary::Conflict
+                            | Summary::Renamed,
+                        ) => ChangeKind::Modified,
+                        None => continue,
+                    };
+
+                    unstaged.push(self.changed_file(item.rela_path(), kind));
+                }
+            }
+        }
+
+        for changed_file in unstaged {
+            let Some(previous) = staged.remove(&changed_file.path) else {
+                staged.insert(changed_file.path.clone(), changed_file);
+                continue;
+            };
+
+            let combined = match (previous.kind, changed_file.kind) {
+                // A file added or renamed since the tree, then deleted, is gone from both.
+                (ChangeKind::Added, ChangeKind::Deleted) => continue,
+                (ChangeKind::Renamed, ChangeKind::Deleted) => ChangedFile::new(
+                    previous.old_path.unwrap_or(previous.path),
+                    ChangeKind::Deleted,
+                ),
+                (_, ChangeKind::Deleted) => changed_file,
+                _ => previous,
+            };
+
+            staged.insert(combined.path.clone(), combined);
+        }
+
+        Ok(staged.into_values().collect())
+    }
+
+    /// Returns the tree to diff from, honoring [`Self::with_merge_base`].
+    fn base_tree(&self, from: &str, to: Option<&str>) -> Result<ObjectId, DiffEngineError> {
+        if !self.merge_base {
+            return self.tree(from);
+        }
+
+        let to = to.unwrap_or("HEAD");
+        let merge_base_error =
+            |message: String| DiffEngineError::MergeBase(from.to_owned(), to.to_owned(), message);
+
+        let base = self
+            .repository
+            .merge_base(self.commit(from)?, self.commit(to)?)
+            .map_err(|err| merge_base_error(err.to_string()))?
+            .ok_or_else(|| merge_base_error("the revisions have no common ancestor".to_owned()))?;
+
+        self.tree(&base.to_string())
+    }
+
+    fn commit(&self, revision: &str) -> Result<ObjectId, DiffEngineError> {
+        self.repository
+            .rev_parse_single(revision)
+            .and_then(|id| id.object())
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id)
+            .map_err(|err| DiffEngineError::Revision(revision.to_owned(), err.to_string()))
+    }
+
+    fn tree(&self, revision: &str) -> Result<ObjectId, DiffEngineError> {
+        self.repository
+            .rev_parse_single(revision)
+            .and_then(|id| id.object())
+            .and_then(|object| object.peel_to_tree())
+            .map(|tree| tree.id)
+            .map_err(|err| DiffEngineError::Revision(revision.to_owned(), err.to_string()))
+    }
+
+    fn changed_file(&self, location: &BStr, kind: ChangeKind) -> ChangedFile {
+        ChangedFile::new(self.join(location), kind)
+    }
+
+    /// Joins a path of the repository, always separated with `/`, to [`Self::path`].
+    fn join(&self, location: &BStr) -> PathBuf {
+        location
+            .to_str_lossy()
+            .split('/')
+            .fold(self.path.clone(), |path, component| path.join(component))
+    }
+}
+
+impl DiffEngine for GixDiffEngine {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        if context.is_cancelled() {
+            return Err(DiffEngineError::Cancelled);
+        }
+
+        let tree_from = self.base_tree(from, to)?;
+
+        let mut changed_files = match to {
+            Some(to) => self.tree_changes(tree_from, self.tree(to)?)?,
+            None => self.working_directory_changes(tree_from, context)?,
+        };
+
+        if context.is_cancelled() {
+            return Err(DiffEngineError::Cancelled);
+        }
+
+        changed_files.sort();
+
+        Ok(changed_files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff_engine::suite;
+
+    use super::GixDiffEngine;
+
+    #[test]
+    pub fn when_repository_is_missing_should_return_repository_error() {
+        suite::fails_without_repository("gix-no-repo", |path| GixDiffEngine::open(path));
+    }
+
+    #[test]
+    pub fn when_revision_is_unknown_should_return_revision_error() {
+        suite::fails_on_unknown_revision("gix-unknown", |path| GixDiffEngine::open(path).unwrap());
+    }
+
+    #[test]
+    pub fn when_cancelled_should_return_cancelled_error() {
+        suite::stops_when_cancelled("gix-cancel", |path| GixDiffEngine::open(path).unwrap());
+    }
+
+    #[test]
+    pub fn when_diffing_against_working_directory_should_include_uncommitted_changes() {
+        suite::includes_uncommitted_changes("gix-workdir", |path| {
+            GixDiffEngine::open(path).unwrap()
+        });
+    }
+
+    #[test]
+    pub fn when_including_untracked_files_should_report_them_as_added() {
+        suite::reports_untracked_files("gix-untracked", |path, untracked| {
+            GixDiffEngine::open(path).unwrap().with_untracked(untracked)
+        });
+    }
+
+    #[test]
+    pub fn when_diffing_with_merge_base_should_ignore_changes_on_base_branch() {
+        suite::diffs_from_merge_base("gix-merge-base", |path, merge_base| {
+            GixDiffEngine::open(path)
+                .unwrap()
+                .with_merge_base(merge_base)
+        });
+    }
+
+    #[test]
+    pub fn when_files_are_renamed_or_deleted_should_report_change_kinds() {
+        suite::reports_change_kinds("gix-kinds", |path| GixDiffEngine::open(path).unwrap());
+    }
+}
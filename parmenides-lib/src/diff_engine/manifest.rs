@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::cache::encode_hex;
+use crate::context::Context;
+use crate::errors::DiffEngineError;
+use crate::watch::IGNORED_DIRECTORIES;
+
+use super::{ChangeKind, ChangedFile, DiffEngine};
+
+/// The directory the manifests of a workspace are recorded in, relative to its root.
+pub const MANIFESTS_DIRECTORY: &str = ".parmenides/manifests";
+
+/// The content hashes of the files under a directory, keyed by their path relative to it.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl Manifest {
+    /// Hashes every file under `root`, skipping the directories a watcher ignores, such as
+    /// `.git` and `target`. The scan stops early if `context` is cancelled.
+    pub fn scan(root: &Path, context: &Context) -> Result<Self, DiffEngineError> {
+        let mut files = BTreeMap::new();
+        let mut stack = vec![root.to_path_buf()];
+
+        while let Some(directory) = stack.pop() {
+            if context.is_cancelled() {
+                return Err(DiffEngineError::Cancelled);
+            }
+
+            let entries = std::fs::read_dir(&directory)
+                .map_err(|err| DiffEngineError::Io(directory.clone(), err))?;
+
+            for entry in entries {
+                let entry = entry.map_err(|err| DiffEngineError::Io(directory.clone(), err))?;
+                let path = entry.path();
+                let file_type = entry
+                    .file_type()
+                    .map_err(|err| DiffEngineError::Io(path.clone(), err))?;
+
+                if file_type.is_dir() {
+                    if !IGNORED_DIRECTORIES.contains(&entry.file_name().to_string_lossy().as_ref())
+                    {
+                        stack.push(path);
+                    }
+                } else if file_type.is_file() {
+                    let content = std::fs::read(&path)
+                        .map_err(|err| DiffEngineError::Io(path.clone(), err))?;
+                    let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+
+                    files.insert(relative, encode_hex(&Sha256::digest(content)));
+                }
+            }
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Reads a manifest written by [`Self::save`].
+    pub fn load(path: &Path) -> Result<Self, DiffEngineError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|err| DiffEngineError::Io(path.into(), err))?;
+
+        serde_json::from_str(&content)
+            .map_err(|err| DiffEngineError::Manifest(path.to_path_buf(), err.to_string()))
+    }
+
+    /// Writes the manifest to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<(), DiffEngineError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| DiffEngineError::Io(parent.to_path_buf(), err))?;
+        }
+
+        // Only paths and strings are serialized, which can't fail.
+        let content = serde_json::to_string_pretty(self).unwrap_or_default();
+
+        std::fs::write(path, content).map_err(|err| DiffEngineError::Io(path.to_path_buf(), err))
+    }
+
+    /// Returns the hashes of the files, keyed by their relative path.
+    pub fn files(&self) -> &BTreeMap<PathBuf, String> {
+        &self.files
+    }
+
+    /// Returns the files whose hash differs between the manifests, joined to `root`. Files
+    /// only in `self` are deleted, and files only in `other` added.
+    fn diff(&self, other: &Self, root: &Path) -> Vec<ChangedFile> {
+        let mut changed: Vec<ChangedFile> = self
+            .files
+            .iter()
+            .filter_map(|(path, hash)| match other.files.get(path) {
+                None => Some(ChangedFile::new(root.join(path), ChangeKind::Deleted)),
+                Some(other) if other != hash => {
+                    Some(ChangedFile::new(root.join(path), ChangeKind::Modified))
+                }
+                Some(_) => None,
+            })
+            .collect();
+
+        changed.extend(
+            other
+                .files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .map(|path| ChangedFile::new(root.join(path), ChangeKind::Added)),
+        );
+
+        changed.sort();
+        changed
+    }
+}
+
+/// A [`DiffEngine`] comparing the files of a directory against recorded [`Manifest`]s, for
+/// tarball based CI and exported source trees without a repository.
+///
+/// Revisions name the manifests recorded with [`Self::record`]. Diffing to no revision
+/// compares against the files as they are now. Renames are reported as a deletion and an
+/// addition.
+pub struct ManifestDiffEngine {
+    root: PathBuf,
+    directory: PathBuf,
+}
+
+impl ManifestDiffEngine {
+    /// Creates an engine for the files under `root`, with the manifests recorded in its
+    /// [`MANIFESTS_DIRECTORY`]. Changed paths are reported joined to `root`.
+    pub fn open<P>(root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref().to_path_buf();
+
+        Self {
+            directory: root.join(MANIFESTS_DIRECTORY),
+            root,
+        }
+    }
+
+    /// Returns the path the files are read from.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path of the manifest recorded as `revision`.
+    pub fn manifest_path(&self, revision: &str) -> PathBuf {
+        self.directory.join(format!("{revision}.json"))
+    }
+
+    /// Hashes the files as they are now and records them as `revision`, replacing any
+    /// manifest recorded under the same name.
+    pub fn record(&self, revision: &str, context: &Context) -> Result<Manifest, DiffEngineError> {
+        let manifest = Manifest::scan(&self.root, context)?;
+        manifest.save(&self.manifest_path(revision))?;
+
+        Ok(manifest)
+    }
+}
+
+impl DiffEngine for ManifestDiffEngine {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        let from = Manifest::load(&self.manifest_path(from))?;
+        let to = match to {
+            Some(to) => Manifest::load(&self.manifest_path(to))?,
+            None => Manifest::scan(&self.root, context)?,
+        };
+
+        Ok(from.diff(&to, &self.root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{CancellationToken, Context};
+    use crate::diff_engine::{ChangeKind, ChangedFile, DiffEngine};
+    use crate::errors::DiffEngineError;
+
+    use super::ManifestDiffEngine;
+
+    #[test]
+    pub fn when_diffing_manifests_should_report_files_whose_hash_changed() {
+        let root = std::env::temp_dir().join(format!("parmenides-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("libs/core")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("libs/core/lib.rs"), "1").unwrap();
+        std::fs::write(root.join("libs/core/same.rs"), "1").unwrap();
+        std::fs::write(root.join("libs/core/removed.rs"), "1").unwrap();
+
+        let engine = ManifestDiffEngine::open(&root);
+        let context = Context::new();
+        engine.record("main", &context).unwrap();
+
+        std::fs::write(root.join("libs/core/lib.rs"), "2").unwrap();
+        std::fs::write(root.join("libs/core/added.rs"), "1").unwrap();
+        std::fs::write(root.join("target/build.o"), "1").unwrap();
+        std::fs::remove_file(root.join("libs/core/removed.rs")).unwrap();
+
+        let uncommitted = engine.get_changed_files("main", None, &context);
+        engine.record("feature", &context).unwrap();
+        let recorded = engine.get_changed_files("main", Some("feature"), &context);
+        let missing = engine.get_changed_files("missing", None, &context);
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let cancelled = engine.get_changed_files(
+            "main",
+            None,
+            &Context::new().with_cancellation(cancellation),
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let expected = vec![
+            ChangedFile::new(root.join("libs/core/added.rs"), ChangeKind::Added),
+            ChangedFile::new(root.join("libs/core/lib.rs"), ChangeKind::Modified),
+            ChangedFile::new(root.join("libs/core/removed.rs"), ChangeKind::Deleted),
+        ];
+
+        assert_eq!(uncommitted.unwrap(), expected);
+        assert_eq!(recorded.unwrap(), expected);
+        assert!(matches!(missing, Err(DiffEngineError::Io(..))));
+        assert!(matches!(cancelled, Err(DiffEngineError::Cancelled)));
+    }
+}
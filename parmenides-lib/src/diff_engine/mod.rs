@@ -1,12 +1,108 @@
-use std::{
-    collections::HashSet,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
-mod git;
+use crate::context::Context;
+use crate::errors::DiffEngineError;
 
+mod composite;
+#[cfg(feature = "git")]
+pub(crate) mod git;
+#[cfg(feature = "gix")]
+mod gitoxide;
+mod manifest;
+#[cfg(all(test, any(feature = "git", feature = "gix")))]
+pub(crate) mod suite;
+
+pub use composite::CompositeDiffEngine;
+#[cfg(feature = "git")]
+pub use git::{GitDiffEngine, RevisionFileSystem, DEEPEN_ATTEMPTS, DEEPEN_STEP};
+#[cfg(feature = "gix")]
+pub use gitoxide::GixDiffEngine;
+pub use manifest::{Manifest, ManifestDiffEngine, MANIFESTS_DIRECTORY};
+
+/// How a file changed.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[non_exhaustive]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    /// The file was moved from [`ChangedFile::old_path`], possibly with modifications.
+    Renamed,
+}
+
+/// A file changed between two revisions.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[non_exhaustive]
+pub struct ChangedFile {
+    /// The absolute path of the file. For deleted files, the path it had before the deletion.
+    pub path: PathBuf,
+    /// The absolute path the file had before being renamed.
+    pub old_path: Option<PathBuf>,
+    pub kind: ChangeKind,
+}
+
+impl ChangedFile {
+    pub fn new<P: Into<PathBuf>>(path: P, kind: ChangeKind) -> Self {
+        Self {
+            path: path.into(),
+            old_path: None,
+            kind,
+        }
+    }
+
+    /// A file renamed from `old_path` to `path`.
+    pub fn renamed<P: Into<PathBuf>, O: Into<PathBuf>>(path: P, old_path: O) -> Self {
+        Self {
+            path: path.into(),
+            old_path: Some(old_path.into()),
+            kind: ChangeKind::Renamed,
+        }
+    }
+
+    /// Returns every path touched by the change: the path and, for renames, the old path.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.path.as_path()).chain(self.old_path.as_deref())
+    }
+}
+
+/// Computes the files changed between two revisions of a repository.
+///
+/// Engines are instances so they can hold their configuration and any state worth reusing, such
+/// as an open repository handle, across calls.
 pub trait DiffEngine {
-    fn get_affected_paths<P>(path: P, from: String, to: String) -> Result<HashSet<PathBuf>, String>
-    where
-        P: AsRef<Path>;
+    /// Returns the files changed between the `from` and `to` revisions, sorted by path.
+    ///
+    /// When `to` is `None`, `from` is compared against the working directory and the index, so
+    /// uncommitted changes are included. The diff stops early if `context` is cancelled.
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError>;
+}
+
+/// A borrowed engine diffs like the engine, so one engine can be added to a
+/// [`CompositeDiffEngine`] twice.
+impl<E: DiffEngine + ?Sized> DiffEngine for &E {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        (**self).get_changed_files(from, to, context)
+    }
+}
+
+/// A boxed engine diffs like the engine, so engines picked at runtime can be combined.
+impl<E: DiffEngine + ?Sized> DiffEngine for Box<E> {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        (**self).get_changed_files(from, to, context)
+    }
 }
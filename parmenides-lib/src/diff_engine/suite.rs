@@ -0,0 +1,295 @@
+//! The behaviors every git-backed [`DiffEngine`] shares, run by the tests of each backend with
+//! its own constructor, so the backends can't drift apart. The repositories are built with
+//! libgit2, whichever backend reads them.
+use std::path::{Path, PathBuf};
+
+use git2::{Repository, Signature};
+
+use crate::context::{CancellationToken, Context};
+use crate::diff_engine::{ChangeKind, ChangedFile, DiffEngine};
+use crate::errors::DiffEngineError;
+
+/// Creates an empty repository in a directory unique to the test and process.
+fn repository(name: &str) -> (PathBuf, Repository) {
+    let path = std::env::temp_dir().join(format!("parmenides-{name}-{}", std::process::id()));
+    let repository = Repository::init(&path).unwrap();
+
+    (path, repository)
+}
+
+fn paths(changed_files: Vec<ChangedFile>) -> Vec<PathBuf> {
+    changed_files
+        .into_iter()
+        .map(|changed_file| changed_file.path)
+        .collect()
+}
+
+/// Writes the files and commits them, returning the id of the new commit.
+pub(crate) fn commit(repository: &Repository, files: &[(&str, &str)]) -> String {
+    let root = repository.workdir().unwrap();
+    let mut index = repository.index().unwrap();
+
+    for (path, content) in files {
+        let file = root.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, content).unwrap();
+        index.add_path(Path::new(path)).unwrap();
+    }
+
+    index.write().unwrap();
+
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("test", "test@example.com").unwrap();
+    let parent = repository
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok());
+
+    repository
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "commit",
+            &tree,
+            &parent.iter().collect::<Vec<_>>(),
+        )
+        .unwrap()
+        .to_string()
+}
+
+/// Checks that a directory without a repository fails to open with
+/// [`DiffEngineError::Repository`].
+pub(crate) fn fails_without_repository<E, O>(name: &str, open: O)
+where
+    O: Fn(&Path) -> Result<E, DiffEngineError>,
+{
+    let path = std::env::temp_dir().join(format!("parmenides-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&path).unwrap();
+
+    let result = open(&path);
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert!(matches!(result, Err(DiffEngineError::Repository(..))));
+}
+
+/// Checks that a revision missing from the repository fails with [`DiffEngineError::Revision`].
+pub(crate) fn fails_on_unknown_revision<E, O>(name: &str, open: O)
+where
+    E: DiffEngine,
+    O: Fn(&Path) -> E,
+{
+    let (path, repository) = repository(name);
+
+    commit(&repository, &[("libs/core/lib.rs", "1")]);
+
+    let result = open(&path).get_changed_files("missing", Some("HEAD"), &Context::new());
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(DiffEngineError::Revision(revision, _)) if revision == "missing"
+    ));
+}
+
+/// Checks that diffing against the working directory includes the staged and the unstaged
+/// changes.
+pub(crate) fn includes_uncommitted_changes<E, O>(name: &str, open: O)
+where
+    E: DiffEngine,
+    O: Fn(&Path) -> E,
+{
+    let (path, repository) = repository(name);
+
+    commit(
+        &repository,
+        &[
+            ("libs/core/lib.rs", "1"),
+            ("apps/web/main.rs", "1"),
+            ("docs/README.md", "1"),
+        ],
+    );
+
+    // One change is only in the working directory, the other is staged.
+    std::fs::write(path.join("libs/core/lib.rs"), "2").unwrap();
+    std::fs::write(path.join("apps/web/main.rs"), "2").unwrap();
+
+    let mut index = repository.index().unwrap();
+    index.add_path(Path::new("apps/web/main.rs")).unwrap();
+    index.write().unwrap();
+
+    let changed = open(&path).get_changed_files("HEAD", None, &Context::new());
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        paths(changed.unwrap()),
+        vec![path.join("apps/web/main.rs"), path.join("libs/core/lib.rs")]
+    );
+}
+
+/// Checks that the untracked files of the working directory are reported as added only when
+/// `open` is asked for them, leaving out the files ignored by `.gitignore`.
+pub(crate) fn reports_untracked_files<E, O>(name: &str, open: O)
+where
+    E: DiffEngine,
+    O: Fn(&Path, bool) -> E,
+{
+    let (path, repository) = repository(name);
+
+    commit(
+        &repository,
+        &[("libs/core/lib.rs", "1"), (".gitignore", "generated/\n")],
+    );
+
+    std::fs::create_dir_all(path.join("libs/core/src")).unwrap();
+    std::fs::create_dir_all(path.join("generated")).unwrap();
+    std::fs::write(path.join("libs/core/src/new.rs"), "1").unwrap();
+    std::fs::write(path.join("generated/api.rs"), "1").unwrap();
+
+    let tracked = open(&path, false).get_changed_files("HEAD", None, &Context::new());
+    let untracked = open(&path, true).get_changed_files("HEAD", None, &Context::new());
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert!(tracked.unwrap().is_empty());
+    assert_eq!(
+        untracked.unwrap(),
+        vec![ChangedFile::new(
+            path.join("libs/core/src/new.rs"),
+            ChangeKind::Added
+        )]
+    );
+}
+
+/// Checks that with the merge base asked of `open`, a branch is diffed from the commit it
+/// forked from, so the changes that landed on the base branch afterwards are left out.
+pub(crate) fn diffs_from_merge_base<E, O>(name: &str, open: O)
+where
+    E: DiffEngine,
+    O: Fn(&Path, bool) -> E,
+{
+    let (path, repository) = repository(name);
+
+    let fork = commit(
+        &repository,
+        &[("libs/core/lib.rs", "1"), ("docs/README.md", "1")],
+    );
+    let fork_commit = repository
+        .find_commit(git2::Oid::from_str(&fork).unwrap())
+        .unwrap();
+    repository.branch("feature", &fork_commit, false).unwrap();
+
+    let main = commit(&repository, &[("docs/README.md", "2")]);
+    repository
+        .reference(
+            "refs/heads/main",
+            git2::Oid::from_str(&main).unwrap(),
+            true,
+            "main",
+        )
+        .unwrap();
+
+    repository.set_head("refs/heads/feature").unwrap();
+    repository
+        .reset(fork_commit.as_object(), git2::ResetType::Hard, None)
+        .unwrap();
+    commit(&repository, &[("libs/core/lib.rs", "2")]);
+
+    let two_dot = open(&path, false).get_changed_files("main", Some("HEAD"), &Context::new());
+
+    let engine = open(&path, true);
+    let three_dot = engine.get_changed_files("main", Some("HEAD"), &Context::new());
+    let working_directory = engine.get_changed_files("main", None, &Context::new());
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        paths(two_dot.unwrap()),
+        vec![path.join("docs/README.md"), path.join("libs/core/lib.rs")]
+    );
+    assert_eq!(
+        paths(three_dot.unwrap()),
+        vec![path.join("libs/core/lib.rs")]
+    );
+    assert_eq!(
+        paths(working_directory.unwrap()),
+        vec![path.join("libs/core/lib.rs")]
+    );
+}
+
+/// Checks that renamed and deleted files are reported with their kind, and renames with the
+/// path they had before.
+pub(crate) fn reports_change_kinds<E, O>(name: &str, open: O)
+where
+    E: DiffEngine,
+    O: Fn(&Path) -> E,
+{
+    let (path, repository) = repository(name);
+
+    let content = "a file with enough content for git to detect the rename\n".repeat(8);
+
+    let first = commit(
+        &repository,
+        &[
+            ("libs/core/moved.rs", &content),
+            ("libs/core/removed.rs", "1"),
+        ],
+    );
+
+    std::fs::create_dir_all(path.join("libs/util")).unwrap();
+    std::fs::rename(
+        path.join("libs/core/moved.rs"),
+        path.join("libs/util/moved.rs"),
+    )
+    .unwrap();
+    std::fs::remove_file(path.join("libs/core/removed.rs")).unwrap();
+
+    let mut index = repository.index().unwrap();
+    index.remove_path(Path::new("libs/core/moved.rs")).unwrap();
+    index
+        .remove_path(Path::new("libs/core/removed.rs"))
+        .unwrap();
+    index.write().unwrap();
+
+    let second = commit(&repository, &[("libs/util/moved.rs", &content)]);
+
+    let changed = open(&path).get_changed_files(&first, Some(&second), &Context::new());
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        changed.unwrap(),
+        vec![
+            ChangedFile::new(path.join("libs/core/removed.rs"), ChangeKind::Deleted),
+            ChangedFile::renamed(
+                path.join("libs/util/moved.rs"),
+                path.join("libs/core/moved.rs")
+            ),
+        ]
+    );
+}
+
+/// Checks that a cancelled context stops the diff.
+pub(crate) fn stops_when_cancelled<E, O>(name: &str, open: O)
+where
+    E: DiffEngine,
+    O: Fn(&Path) -> E,
+{
+    let (path, repository) = repository(name);
+
+    let first = commit(&repository, &[("libs/core/lib.rs", "1")]);
+    let second = commit(&repository, &[("libs/core/lib.rs", "2")]);
+
+    let cancellation = CancellationToken::new();
+    let context = Context::new().with_cancellation(cancellation.clone());
+    cancellation.cancel();
+
+    let result = open(&path).get_changed_files(&first, Some(&second), &context);
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert!(matches!(result, Err(DiffEngineError::Cancelled)));
+}
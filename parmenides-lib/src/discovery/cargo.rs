@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::context::Context;
+use crate::declarations::WorkspaceDeclaration;
+use crate::errors::DiscoveryError;
+use crate::file_system::FileSystem;
+use crate::progress::Stage;
+use crate::warnings::PathWarnings;
+
+use super::{discovered_member, expand_pattern, normalize, Discovery};
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Discovers the members of a Cargo workspace.
+///
+/// The root `Cargo.toml` lists the members (glob patterns are supported, as are `exclude`d
+/// paths). Each member becomes a project named after its package, and its `path` dependencies
+/// on other members, including the ones inherited with `workspace = true`, become project
+/// dependencies. A root manifest without a `[workspace]` table is a single-project workspace.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CargoDiscovery;
+
+impl CargoDiscovery {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Discovery for CargoDiscovery {
+    fn discover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        context: &Context,
+    ) -> Result<WorkspaceDeclaration, DiscoveryError> {
+        let root = normalize(root);
+        let root_manifest_path = root.join("Cargo.toml");
+        let root_manifest = read_manifest(fs, &root_manifest_path)?;
+
+        let workspace = root_manifest.get("workspace").and_then(Value::as_table);
+
+        let mut warnings = PathWarnings::new(context.io_policy());
+        let mut members = Vec::new();
+
+        if root_manifest.contains_key("package") {
+            members.push(root.clone());
+        }
+
+        if let Some(workspace) = workspace {
+            let excluded: Vec<PathBuf> = string_array(workspace, "exclude")
+                .map(|path| normalize(&root.join(path)))
+                .collect();
+
+            for pattern in string_array(workspace, "members") {
+                for member in expand_pattern(fs, &root, pattern, &mut warnings)? {
+                    if !excluded.contains(&member)
+                        && !members.contains(&member)
+                        && fs.is_file(&member.join("Cargo.toml"))
+                    {
+                        members.push(member);
+                    }
+                }
+            }
+        }
+
+        let workspace_paths = workspace_paths(&root, &root_manifest);
+
+        let progress = context.progress();
+
+        progress.start(Stage::Discovery, Some(members.len()));
+
+        let mut manifests = Vec::with_capacity(members.len());
+
+        for member in members {
+            if context.is_cancelled() {
+                return Err(DiscoveryError::Cancelled);
+            }
+
+            progress.advance(Stage::Discovery, &member.to_string_lossy());
+
+            let manifest_path = member.join("Cargo.toml");
+
+            if member == root {
+                manifests.push((member, root_manifest.clone()));
+                continue;
+            }
+
+            match fs.read_to_string(&manifest_path) {
+                Ok(content) => manifests.push((member, parse_manifest(&manifest_path, &content)?)),
+                Err(err) => warnings
+                    .recover(&manifest_path, err)
+                    .map_err(|err| DiscoveryError::Io(manifest_path.clone(), err))?,
+            }
+        }
+
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for (member, manifest) in &manifests {
+            let name = package_name(&member.join("Cargo.toml"), manifest)?;
+            let dependencies = member_dependencies(member, manifest, &workspace_paths, |path| {
+                manifests.iter().any(|(other, _)| other == path)
+            });
+
+            // Package names are unique in a Cargo workspace, so they make better identifiers than
+            // the directories.
+            declaration
+                .add_project(member.clone(), name, dependencies)
+                .id = Some(name.to_owned());
+        }
+
+        progress.finish(Stage::Discovery);
+
+        // The members are already under the root, this records it for the stable IDs.
+        declaration.resolve_paths(&root);
+        declaration.warnings = warnings.into_vec();
+
+        Ok(declaration)
+    }
+
+    fn manifest_names(&self) -> &'static [&'static str] {
+        &["Cargo.toml"]
+    }
+
+    fn rediscover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        manifest: &Path,
+        discovered: &mut WorkspaceDeclaration,
+        context: &Context,
+    ) -> Result<(), DiscoveryError> {
+        let root = normalize(root);
+
+        // Only the members are listed by the root manifest, so a member changing its package
+        // or its dependencies leaves the other projects as they are.
+        let Some(member) = discovered_member(&root, manifest, discovered) else {
+            *discovered = self.discover(fs, &root, context)?;
+            return Ok(());
+        };
+
+        let manifest_path = member.join("Cargo.toml");
+
+        let Ok(content) = fs.read_to_string(&manifest_path) else {
+            *discovered = self.discover(fs, &root, context)?;
+            return Ok(());
+        };
+
+        let manifest = parse_manifest(&manifest_path, &content)?;
+        // Read for the dependencies the members inherit from it.
+        let root_manifest = read_manifest(fs, &root.join("Cargo.toml"))?;
+        let workspace_paths = workspace_paths(&root, &root_manifest);
+
+        let name = package_name(&manifest_path, &manifest)?;
+        let dependencies = member_dependencies(&member, &manifest, &workspace_paths, |path| {
+            discovered.projects.contains_key(path)
+        });
+
+        discovered.add_project(member, name, dependencies).id = Some(name.to_owned());
+
+        Ok(())
+    }
+}
+
+/// Returns the paths of the dependencies declared in the root manifest, which members inherit
+/// with `workspace = true`, resolved against the root.
+fn workspace_paths<'a>(root: &Path, root_manifest: &'a Table) -> HashMap<&'a str, PathBuf> {
+    root_manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Value::as_table)
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, dependency)| {
+            let path = dependency.get("path")?.as_str()?;
+
+            Some((name.as_str(), normalize(&root.join(path))))
+        })
+        .collect()
+}
+
+fn package_name<'a>(manifest_path: &Path, manifest: &'a Table) -> Result<&'a str, DiscoveryError> {
+    manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            DiscoveryError::InvalidManifest(
+                manifest_path.to_path_buf(),
+                "missing package.name".to_owned(),
+            )
+        })
+}
+
+/// Returns the members `member` depends on, sorted, or `None` if it depends on none.
+fn member_dependencies<F>(
+    member: &Path,
+    manifest: &Table,
+    workspace_paths: &HashMap<&str, PathBuf>,
+    is_member: F,
+) -> Option<Vec<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut dependencies = Vec::new();
+
+    for dependency_table in dependency_tables(manifest) {
+        for (dependency_name, dependency) in dependency_table {
+            let path = if let Some(path) = dependency.get("path").and_then(Value::as_str) {
+                Some(normalize(&member.join(path)))
+            } else if dependency.get("workspace").and_then(Value::as_bool) == Some(true) {
+                let package = dependency
+                    .get("package")
+                    .and_then(Value::as_str)
+                    .unwrap_or(dependency_name);
+
+                workspace_paths.get(package).cloned()
+            } else {
+                None
+            };
+
+            // Members whose manifest couldn't be read are left out, with the dependencies on
+            // them.
+            if let Some(path) = path {
+                if is_member(&path) && !dependencies.contains(&path) {
+                    dependencies.push(path);
+                }
+            }
+        }
+    }
+
+    dependencies.sort();
+
+    (!dependencies.is_empty()).then_some(dependencies)
+}
+
+fn read_manifest(fs: &dyn FileSystem, path: &Path) -> Result<Table, DiscoveryError> {
+    let content = fs
+        .read_to_string(path)
+        .map_err(|err| DiscoveryError::Io(path.to_path_buf(), err))?;
+
+    parse_manifest(path, &content)
+}
+
+fn parse_manifest(path: &Path, content: &str) -> Result<Table, DiscoveryError> {
+    content
+        .parse::<Table>()
+        .map_err(|err| DiscoveryError::InvalidManifest(path.to_path_buf(), err.to_string()))
+}
+
+fn string_array<'a>(table: &'a Table, key: &str) -> impl Iterator<Item = &'a str> {
+    table
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Returns every dependency table of a manifest, including the target-specific ones.
+fn dependency_tables(manifest: &Table) -> Vec<&Table> {
+    let mut tables: Vec<&Table> = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|key| manifest.get(*key).and_then(Value::as_table))
+        .collect();
+
+    for target in manifest
+        .get("target")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values())
+    {
+        tables.extend(
+            DEPENDENCY_TABLES
+                .iter()
+                .filter_map(|key| target.get(*key).and_then(Value::as_table)),
+        );
+    }
+
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use crate::context::Context;
+    use crate::discovery::Discovery;
+    use crate::errors::DiscoveryError;
+    use crate::file_system::MemoryFileSystem;
+    use crate::progress::{ProgressEvent, RecordingProgress, Stage};
+    use crate::warnings::{IoPolicy, PathProblem};
+
+    use super::CargoDiscovery;
+
+    #[test]
+    pub fn when_discovering_cargo_workspace_should_wire_path_dependencies() {
+        let root = Path::new("/repo");
+        let mut fs = MemoryFileSystem::new();
+
+        fs.insert(
+            root.join("Cargo.toml"),
+            r#"[workspace]
+members = ["crates/*", "tools/cli"]
+exclude = ["crates/ignored"]
+
+[workspace.dependencies]
+core = { path = "crates/core" }
+serde = "1"
+"#,
+        );
+        fs.insert(
+            root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\n\n[dependencies]\nserde = { workspace = true }\n",
+        );
+        fs.insert(
+            root.join("crates/api/Cargo.toml"),
+            "[package]\nname = \"api\"\n\n[dependencies]\ncore = { path = \"../core\" }\n",
+        );
+        fs.insert(
+            root.join("crates/ignored/Cargo.toml"),
+            "[package]\nname = \"ignored\"\n",
+        );
+        fs.insert(
+            root.join("tools/cli/Cargo.toml"),
+            "[package]\nname = \"cli\"\n\n[dev-dependencies]\ncore.workspace = true\n",
+        );
+
+        let progress = Arc::new(RecordingProgress::new());
+        let context = Context::new().with_progress(progress.clone());
+        let result = CargoDiscovery::new().discover(&fs, root, &context);
+
+        let workspace = result.unwrap().build_workspace().unwrap();
+
+        assert_eq!(workspace.len(), 3);
+        assert_eq!(
+            progress.events().first(),
+            Some(&ProgressEvent::Started(Stage::Discovery, Some(3)))
+        );
+        assert_eq!(
+            progress.events().last(),
+            Some(&ProgressEvent::Finished(Stage::Discovery))
+        );
+
+        let core_id = workspace.get_id_by_path(&root.join("crates/core")).unwrap();
+        let api = workspace
+            .get_project_by_path(&root.join("crates/api"))
+            .unwrap();
+        let cli = workspace
+            .get_project_by_path(&root.join("tools/cli"))
+            .unwrap();
+
+        assert_eq!(api.name, "api");
+        assert_eq!(api.dependencies, Some(vec![core_id]));
+        assert_eq!(cli.dependencies, Some(vec![core_id]));
+    }
+
+    #[test]
+    pub fn when_member_manifest_is_unreadable_should_skip_it_unless_strict() {
+        let root = Path::new("/repo");
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                root.join("Cargo.toml"),
+                "[workspace]\nmembers = [\"crates/*\", \"vendor/*\"]\n",
+            )
+            .with_file(
+                root.join("crates/core/Cargo.toml"),
+                "[package]\nname = \"core\"\n",
+            )
+            .with_file(
+                root.join("crates/web/Cargo.toml"),
+                "[package]\nname = \"web\"\n\n[dependencies]\ncore = { path = \"../core\" }\n",
+            )
+            .with_file(root.join("vendor/private/Cargo.toml"), "")
+            .with_unreadable(root.join("crates/core/Cargo.toml"))
+            .with_unreadable(root.join("vendor"));
+
+        let discovered = CargoDiscovery.discover(&fs, root, &Context::new()).unwrap();
+
+        let strict =
+            CargoDiscovery.discover(&fs, root, &Context::new().with_io_policy(IoPolicy::Strict));
+
+        let paths: Vec<_> = discovered.warnings.iter().map(|w| &w.path).collect();
+        assert_eq!(
+            paths,
+            [&root.join("vendor"), &root.join("crates/core/Cargo.toml")]
+        );
+        assert!(discovered
+            .warnings
+            .iter()
+            .all(|warning| warning.problem == PathProblem::PermissionDenied));
+
+        let web = &discovered.projects[&root.join("crates/web")];
+        assert_eq!(discovered.projects.len(), 1);
+        assert_eq!(web.dependencies, None);
+        assert!(discovered.build_workspace().is_ok());
+
+        assert!(matches!(strict, Err(DiscoveryError::Io(path, _)) if path == root.join("vendor")));
+    }
+
+    #[test]
+    pub fn when_rediscovering_member_should_read_only_its_manifest() {
+        let root = Path::new("/repo");
+        let mut fs = MemoryFileSystem::new();
+
+        fs.insert(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        fs.insert(
+            root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        );
+        fs.insert(
+            root.join("crates/api/Cargo.toml"),
+            "[package]\nname = \"api\"\n\n[dependencies]\ncore = { path = \"../core\" }\n",
+        );
+
+        let discovery = CargoDiscovery::new();
+        let mut discovered = discovery.discover(&fs, root, &Context::new()).unwrap();
+
+        // Broken after the discovery, so reading it again would fail.
+        fs.insert(root.join("crates/core/Cargo.toml"), "[package");
+        fs.insert(
+            root.join("crates/api/Cargo.toml"),
+            "[package]\nname = \"server\"\n",
+        );
+
+        let member = discovery.rediscover(
+            &fs,
+            root,
+            &root.join("crates/api/Cargo.toml"),
+            &mut discovered,
+            &Context::new(),
+        );
+        let api = discovered.projects[&root.join("crates/api")].clone();
+        let workspace = discovery.rediscover(
+            &fs,
+            root,
+            &root.join("Cargo.toml"),
+            &mut discovered,
+            &Context::new(),
+        );
+
+        assert!(member.is_ok());
+        assert_eq!(api.name, "server");
+        assert_eq!(api.id.as_deref(), Some("server"));
+        assert_eq!(api.dependencies, None);
+        assert!(
+            matches!(workspace, Err(DiscoveryError::InvalidManifest(path, _))
+            if path == root.join("crates/core/Cargo.toml"))
+        );
+    }
+}
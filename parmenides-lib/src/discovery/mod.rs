@@ -0,0 +1,225 @@
+//! # Discovery
+//!
+//! Discovery backends build a [`WorkspaceDeclaration`] from the manifests a repository already
+//! has, such as a Cargo or npm workspace, so the declaration doesn't have to be maintained by hand.
+use std::path::{Component, Path, PathBuf};
+
+use crate::context::Context;
+use crate::declarations::WorkspaceDeclaration;
+use crate::errors::DiscoveryError;
+use crate::file_system::FileSystem;
+use crate::selector::wildcard_match;
+use crate::warnings::PathWarnings;
+
+mod cargo;
+mod node;
+
+pub use cargo::CargoDiscovery;
+pub use node::NodeDiscovery;
+
+/// A backend that discovers the projects of a repository.
+pub trait Discovery {
+    /// Discovers the projects under `root`, returning their declaration with absolute paths.
+    ///
+    /// Each manifest read is reported to the progress of `context` under
+    /// [`crate::progress::Stage::Discovery`], and discovery stops early if it is cancelled.
+    /// Members whose manifest or directory can't be read are skipped and recorded in
+    /// [`WorkspaceDeclaration::warnings`], unless the I/O policy of `context` is strict.
+    fn discover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        context: &Context,
+    ) -> Result<WorkspaceDeclaration, DiscoveryError>;
+
+    /// Returns the file names of the manifests the backend reads, e.g. `Cargo.toml`.
+    fn manifest_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Updates `discovered`, the declaration this backend discovered under `root`, after the
+    /// manifest at `manifest` changed.
+    ///
+    /// Backends read only that manifest again when it belongs to a discovered project and the
+    /// change can't affect the others. By default, and for any other change, such as one to the
+    /// root manifest, every project is discovered again.
+    fn rediscover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        manifest: &Path,
+        discovered: &mut WorkspaceDeclaration,
+        context: &Context,
+    ) -> Result<(), DiscoveryError> {
+        let _ = manifest;
+        *discovered = self.discover(fs, root, context)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the directory of `manifest`, if it is the manifest of a project of `discovered`
+/// other than the root.
+pub(crate) fn discovered_member(
+    root: &Path,
+    manifest: &Path,
+    discovered: &WorkspaceDeclaration,
+) -> Option<PathBuf> {
+    manifest
+        .parent()
+        .map(normalize)
+        .filter(|member| member != root && discovered.projects.contains_key(member))
+}
+
+/// Lexically normalizes a path, resolving `.` and `..` components without touching the file
+/// system.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
+
+/// Expands a member pattern such as `crates/*` into the matching directories under `root`.
+///
+/// Each component of the pattern may contain `*` wildcards, and a `**` component matches any
+/// number of directories, including none. The directories are returned sorted, so discovery is
+/// deterministic. Directories that can't be listed are skipped and recorded in `warnings`.
+pub(crate) fn expand_pattern(
+    fs: &dyn FileSystem,
+    root: &Path,
+    pattern: &str,
+    warnings: &mut PathWarnings,
+) -> Result<Vec<PathBuf>, DiscoveryError> {
+    let mut current = vec![root.to_path_buf()];
+
+    for component in Path::new(pattern).components() {
+        let component = component.as_os_str().to_string_lossy();
+
+        if component == "**" {
+            let mut matched = Vec::new();
+
+            while let Some(directory) = current.pop() {
+                // Links aren't followed, as they may point back up, and installed packages
+                // are never members.
+                current.extend(subdirectories(fs, &directory, warnings)?.filter(|entry| {
+                    fs.read_link(entry).is_none()
+                        && entry.file_name().is_some_and(|name| name != "node_modules")
+                }));
+                matched.push(directory);
+            }
+
+            current = matched;
+            continue;
+        }
+
+        if !component.contains('*') {
+            current = current
+                .into_iter()
+                .map(|directory| directory.join(component.as_ref()))
+                .collect();
+            continue;
+        }
+
+        let mut matched = Vec::new();
+
+        for directory in current {
+            for entry in subdirectories(fs, &directory, warnings)? {
+                let name = entry
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                if wildcard_match(&component, &name) {
+                    matched.push(entry);
+                }
+            }
+        }
+
+        current = matched;
+    }
+
+    let mut directories: Vec<_> = current
+        .into_iter()
+        .filter(|directory| fs.is_dir(directory))
+        .map(|directory| normalize(&directory))
+        .collect();
+
+    directories.sort();
+    directories.dedup();
+
+    Ok(directories)
+}
+
+/// Returns the directories in `directory`, or none if it doesn't exist.
+fn subdirectories<'a>(
+    fs: &'a dyn FileSystem,
+    directory: &Path,
+    warnings: &mut PathWarnings,
+) -> Result<impl Iterator<Item = PathBuf> + 'a, DiscoveryError> {
+    let entries = match fs.read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(err) => {
+            warnings
+                .recover(directory, err)
+                .map_err(|err| DiscoveryError::Io(directory.to_path_buf(), err))?;
+            vec![]
+        }
+    };
+
+    Ok(entries.into_iter().filter(|entry| fs.is_dir(entry)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::warnings::PathWarnings;
+
+    use super::{expand_pattern, normalize};
+
+    #[test]
+    pub fn when_normalizing_should_resolve_parent_components() {
+        assert_eq!(
+            normalize(Path::new("/repo/crates/b/../a/./src")),
+            PathBuf::from("/repo/crates/a/src")
+        );
+        assert_eq!(normalize(Path::new("../a")), PathBuf::from("../a"));
+    }
+
+    #[test]
+    pub fn when_expanding_double_wildcard_should_match_any_depth() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/libs/plugin/Cargo.toml", "")
+            .with_file("/repo/libs/core/plugin/Cargo.toml", "")
+            .with_file("/repo/libs/ui/forms/plugin/Cargo.toml", "")
+            .with_file("/repo/libs/ui/node_modules/plugin/Cargo.toml", "")
+            .with_file("/repo/libs/ui/forms/Cargo.toml", "");
+        let mut warnings = PathWarnings::default();
+
+        let directories =
+            expand_pattern(&fs, Path::new("/repo"), "libs/**/plugin", &mut warnings).unwrap();
+
+        assert_eq!(
+            directories,
+            [
+                PathBuf::from("/repo/libs/core/plugin"),
+                PathBuf::from("/repo/libs/plugin"),
+                PathBuf::from("/repo/libs/ui/forms/plugin"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::declarations::WorkspaceDeclaration;
+use crate::errors::DiscoveryError;
+use crate::file_system::FileSystem;
+use crate::progress::Stage;
+use crate::warnings::PathWarnings;
+
+use super::{discovered_member, expand_pattern, normalize, Discovery};
+
+const DEPENDENCY_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Discovers the packages of an npm, yarn, or pnpm workspace.
+///
+/// The packages are listed by the `workspaces` field of the root `package.json` (either an
+/// array or an object with a `packages` array), or by the `packages` of a
+/// `pnpm-workspace.yaml`. Patterns starting with `!` exclude packages. Each package becomes a
+/// project named after the package, and its dependencies on other packages become project
+/// dependencies, whether they use the `workspace:` protocol, a `file:` or `link:` path, or a
+/// version range that the package manager links locally.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NodeDiscovery;
+
+impl NodeDiscovery {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Discovery for NodeDiscovery {
+    fn discover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        context: &Context,
+    ) -> Result<WorkspaceDeclaration, DiscoveryError> {
+        let root = normalize(root);
+        let patterns = workspace_patterns(fs, &root)?;
+        let mut warnings = PathWarnings::new(context.io_policy());
+
+        let mut excluded = Vec::new();
+        let mut members = Vec::new();
+
+        for pattern in patterns
+            .iter()
+            .filter_map(|pattern| pattern.strip_prefix('!'))
+        {
+            excluded.extend(expand_pattern(fs, &root, pattern, &mut warnings)?);
+        }
+
+        for pattern in patterns.iter().filter(|pattern| !pattern.starts_with('!')) {
+            for member in expand_pattern(fs, &root, pattern, &mut warnings)? {
+                if !excluded.contains(&member)
+                    && !members.contains(&member)
+                    && !member.components().any(|c| c.as_os_str() == "node_modules")
+                    && fs.is_file(&member.join("package.json"))
+                {
+                    members.push(member);
+                }
+            }
+        }
+
+        let mut manifests = Vec::new();
+
+        let progress = context.progress();
+
+        progress.start(Stage::Discovery, Some(members.len()));
+
+        for member in members {
+            if context.is_cancelled() {
+                return Err(DiscoveryError::Cancelled);
+            }
+
+            progress.advance(Stage::Discovery, &member.to_string_lossy());
+
+            let manifest_path = member.join("package.json");
+
+            // Packages whose manifest couldn't be read are left out, with the dependencies on
+            // them.
+            let content = match fs.read_to_string(&manifest_path) {
+                Ok(content) => content,
+                Err(err) => {
+                    warnings
+                        .recover(&manifest_path, err)
+                        .map_err(|err| DiscoveryError::Io(manifest_path.clone(), err))?;
+                    continue;
+                }
+            };
+            let manifest = parse_package_json(&manifest_path, &content)?;
+            let name = package_name(&manifest_path, &manifest)?.to_owned();
+
+            manifests.push((member, name, manifest));
+        }
+
+        let packages: HashMap<&str, &PathBuf> = manifests
+            .iter()
+            .map(|(member, name, _)| (name.as_str(), member))
+            .collect();
+
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for (member, name, manifest) in &manifests {
+            let dependencies = package_dependencies(member, manifest, &packages, |path| {
+                manifests.iter().any(|(other, _, _)| other == path)
+            });
+
+            declaration.add_project(member.clone(), name, dependencies);
+        }
+
+        progress.finish(Stage::Discovery);
+
+        // The members are already under the root, this records it for the stable IDs.
+        declaration.resolve_paths(&root);
+        declaration.warnings = warnings.into_vec();
+
+        Ok(declaration)
+    }
+
+    fn manifest_names(&self) -> &'static [&'static str] {
+        &["package.json", "pnpm-workspace.yaml"]
+    }
+
+    fn rediscover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        manifest: &Path,
+        discovered: &mut WorkspaceDeclaration,
+        context: &Context,
+    ) -> Result<(), DiscoveryError> {
+        let root = normalize(root);
+
+        let Some(member) = discovered_member(&root, manifest, discovered) else {
+            *discovered = self.discover(fs, &root, context)?;
+            return Ok(());
+        };
+
+        let manifest_path = member.join("package.json");
+
+        let Ok(content) = fs.read_to_string(&manifest_path) else {
+            *discovered = self.discover(fs, &root, context)?;
+            return Ok(());
+        };
+
+        let manifest = parse_package_json(&manifest_path, &content)?;
+        let name = package_name(&manifest_path, &manifest)?;
+
+        // The other packages depend on this one by name, so renaming it can change them too.
+        if discovered
+            .projects
+            .get(&member)
+            .map(|project| project.name.as_str())
+            != Some(name)
+        {
+            *discovered = self.discover(fs, &root, context)?;
+            return Ok(());
+        }
+
+        let packages: HashMap<&str, &PathBuf> = discovered
+            .projects
+            .iter()
+            .map(|(path, project)| (project.name.as_str(), path))
+            .collect();
+
+        let dependencies = package_dependencies(&member, &manifest, &packages, |path| {
+            discovered.projects.contains_key(path)
+        });
+        let name = name.to_owned();
+
+        discovered.add_project(member, name, dependencies);
+
+        Ok(())
+    }
+}
+
+fn package_name<'a>(manifest_path: &Path, manifest: &'a Value) -> Result<&'a str, DiscoveryError> {
+    manifest.get("name").and_then(Value::as_str).ok_or_else(|| {
+        DiscoveryError::InvalidManifest(manifest_path.to_path_buf(), "missing name".to_owned())
+    })
+}
+
+/// Returns the packages `member` depends on, sorted, or `None` if it depends on none.
+fn package_dependencies<F>(
+    member: &Path,
+    manifest: &Value,
+    packages: &HashMap<&str, &PathBuf>,
+    is_member: F,
+) -> Option<Vec<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut dependencies = Vec::new();
+
+    for (dependency_name, specifier) in DEPENDENCY_FIELDS
+        .iter()
+        .filter_map(|field| manifest.get(*field).and_then(Value::as_object))
+        .flatten()
+    {
+        let specifier = specifier.as_str().unwrap_or_default();
+
+        let path = match specifier
+            .strip_prefix("file:")
+            .or_else(|| specifier.strip_prefix("link:"))
+        {
+            Some(path) => Some(normalize(&member.join(path))),
+            None => packages
+                .get(dependency_name.as_str())
+                .map(|path| (*path).clone()),
+        };
+
+        if let Some(path) = path {
+            if is_member(&path) && path != member && !dependencies.contains(&path) {
+                dependencies.push(path);
+            }
+        }
+    }
+
+    dependencies.sort();
+
+    (!dependencies.is_empty()).then_some(dependencies)
+}
+
+/// Reads the package patterns, preferring `pnpm-workspace.yaml` over `package.json`.
+fn workspace_patterns(fs: &dyn FileSystem, root: &Path) -> Result<Vec<String>, DiscoveryError> {
+    let pnpm_workspace_path = root.join("pnpm-workspace.yaml");
+
+    if fs.is_file(&pnpm_workspace_path) {
+        let content = fs
+            .read_to_string(&pnpm_workspace_path)
+            .map_err(|err| DiscoveryError::Io(pnpm_workspace_path.clone(), err))?;
+
+        let pnpm_workspace: serde_yaml::Value = serde_yaml::from_str(&content).map_err(|err| {
+            DiscoveryError::InvalidManifest(pnpm_workspace_path.clone(), err.to_string())
+        })?;
+
+        return Ok(pnpm_workspace
+            .get("packages")
+            .and_then(serde_yaml::Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(serde_yaml::Value::as_str)
+            .map(str::to_owned)
+            .collect());
+    }
+
+    let manifest = read_package_json(fs, &root.join("package.json"))?;
+
+    let workspaces = match manifest.get("workspaces") {
+        Some(Value::Object(workspaces)) => workspaces.get("packages"),
+        workspaces => workspaces,
+    };
+
+    Ok(workspaces
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_owned)
+        .collect())
+}
+
+fn read_package_json(fs: &dyn FileSystem, path: &Path) -> Result<Value, DiscoveryError> {
+    let content = fs
+        .read_to_string(path)
+        .map_err(|err| DiscoveryError::Io(path.to_path_buf(), err))?;
+
+    parse_package_json(path, &content)
+}
+
+fn parse_package_json(path: &Path, content: &str) -> Result<Value, DiscoveryError> {
+    serde_json::from_str(content)
+        .map_err(|err| DiscoveryError::InvalidManifest(path.to_path_buf(), err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::context::Context;
+    use crate::discovery::Discovery;
+    use crate::errors::DiscoveryError;
+    use crate::file_system::MemoryFileSystem;
+
+    use super::NodeDiscovery;
+
+    #[test]
+    pub fn when_discovering_npm_workspace_should_resolve_local_dependencies() {
+        let root = Path::new("/repo");
+        let mut fs = MemoryFileSystem::new();
+
+        fs.insert(
+            root.join("package.json"),
+            r#"{ "private": true, "workspaces": ["packages/*", "!packages/private"] }"#,
+        );
+        fs.insert(
+            root.join("packages/core/package.json"),
+            r#"{ "name": "@acme/core", "dependencies": { "lodash": "^4.0.0" } }"#,
+        );
+        fs.insert(
+            root.join("packages/ui/package.json"),
+            r#"{ "name": "@acme/ui", "dependencies": { "@acme/core": "workspace:*" } }"#,
+        );
+        fs.insert(
+            root.join("packages/web/package.json"),
+            r#"{ "name": "web", "devDependencies": { "ui": "file:../ui", "@acme/core": "^1.0.0" } }"#,
+        );
+        fs.insert(
+            root.join("packages/private/package.json"),
+            r#"{ "name": "private" }"#,
+        );
+
+        let result = NodeDiscovery::new().discover(&fs, root, &Context::new());
+
+        let workspace = result.unwrap().build_workspace().unwrap();
+
+        let core = workspace
+            .get_id_by_path(&root.join("packages/core"))
+            .unwrap();
+        let ui = workspace.get_id_by_path(&root.join("packages/ui")).unwrap();
+        let web = workspace
+            .get_project_by_path(&root.join("packages/web"))
+            .unwrap();
+
+        assert_eq!(workspace.len(), 3);
+        assert_eq!(
+            workspace.get_project(ui).unwrap().dependencies,
+            Some(vec![core])
+        );
+        assert_eq!(web.dependencies, Some(vec![core, ui]));
+    }
+
+    #[test]
+    pub fn when_discovering_pnpm_workspace_should_read_pnpm_workspace_yaml() {
+        let root = Path::new("/repo");
+        let mut fs = MemoryFileSystem::new();
+
+        fs.insert(root.join("package.json"), r#"{ "private": true }"#);
+        fs.insert(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  - 'apps/*'\n  - 'libs/*'\n",
+        );
+        fs.insert(root.join("libs/core/package.json"), r#"{ "name": "core" }"#);
+        fs.insert(
+            root.join("apps/web/package.json"),
+            r#"{ "name": "web", "dependencies": { "core": "workspace:^" } }"#,
+        );
+
+        let result = NodeDiscovery::new().discover(&fs, root, &Context::new());
+
+        let workspace = result.unwrap().build_workspace().unwrap();
+
+        let core = workspace.get_id_by_path(&root.join("libs/core")).unwrap();
+
+        assert_eq!(
+            workspace
+                .get_project_by_path(&root.join("apps/web"))
+                .unwrap()
+                .dependencies,
+            Some(vec![core])
+        );
+    }
+
+    #[test]
+    pub fn when_discovering_pnpm_workspace_with_double_wildcard_should_find_nested_packages() {
+        let root = Path::new("/repo");
+        let mut fs = MemoryFileSystem::new();
+
+        fs.insert(root.join("package.json"), r#"{ "private": true }"#);
+        fs.insert(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  - 'packages/**'\n",
+        );
+        fs.insert(
+            root.join("packages/core/package.json"),
+            r#"{ "name": "core" }"#,
+        );
+        fs.insert(
+            root.join("packages/ui/button/package.json"),
+            r#"{ "name": "button", "dependencies": { "core": "workspace:^" } }"#,
+        );
+
+        let result = NodeDiscovery::new().discover(&fs, root, &Context::new());
+
+        let workspace = result.unwrap().build_workspace().unwrap();
+
+        let core = workspace
+            .get_id_by_path(&root.join("packages/core"))
+            .unwrap();
+
+        assert_eq!(
+            workspace
+                .get_project_by_path(&root.join("packages/ui/button"))
+                .unwrap()
+                .dependencies,
+            Some(vec![core])
+        );
+    }
+
+    #[test]
+    pub fn when_rediscovering_package_should_read_all_again_only_when_renamed() {
+        let root = Path::new("/repo");
+        let mut fs = MemoryFileSystem::new();
+
+        fs.insert(
+            root.join("package.json"),
+            r#"{ "workspaces": ["packages/*"] }"#,
+        );
+        fs.insert(
+            root.join("packages/core/package.json"),
+            r#"{ "name": "core" }"#,
+        );
+        fs.insert(root.join("packages/ui/package.json"), r#"{ "name": "ui" }"#);
+
+        let discovery = NodeDiscovery::new();
+        let mut discovered = discovery.discover(&fs, root, &Context::new()).unwrap();
+
+        // Broken after the discovery, so reading it again would fail.
+        fs.insert(root.join("packages/core/package.json"), "{");
+        fs.insert(
+            root.join("packages/ui/package.json"),
+            r#"{ "name": "ui", "dependencies": { "core": "workspace:*" } }"#,
+        );
+
+        let manifest = root.join("packages/ui/package.json");
+        let context = Context::new();
+        let changed = discovery.rediscover(&fs, root, &manifest, &mut discovered, &context);
+        let ui = discovered.projects[&root.join("packages/ui")].clone();
+
+        fs.insert(&manifest, r#"{ "name": "web" }"#);
+
+        let renamed = discovery.rediscover(&fs, root, &manifest, &mut discovered, &context);
+
+        assert!(changed.is_ok());
+        assert_eq!(ui.dependencies, Some(vec![root.join("packages/core")]));
+        assert!(
+            matches!(renamed, Err(DiscoveryError::InvalidManifest(path, _))
+            if path == root.join("packages/core/package.json"))
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Drift detection compares the declared dependencies against the dependencies inferred from
+//! the code (e.g. by an import scanner or by [`crate::trace`]) and produces the fixes that bring
+//! the declaration back in sync.
+//!
+//! The fixes are [`DeclarationEdit`]s, applied with the [`crate::edit`] module.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::edit::DeclarationEdit;
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+/// The differences between the declared and the inferred dependencies.
+#[derive(Debug, PartialEq, Default)]
+pub struct DependencyDrift {
+    /// Inferred edges `(project, dependency)` that are not declared.
+    pub missing: Vec<(ProjectId, ProjectId)>,
+    /// Declared edges `(project, dependency)` with no detected usage.
+    pub unused: Vec<(ProjectId, ProjectId)>,
+}
+
+impl DependencyDrift {
+    /// Detects the drift between the workspace and the inferred dependencies.
+    ///
+    /// Only the projects present in `inferred` are considered scanned, so declared edges of
+    /// projects that were not analyzed are never reported as unused. Self-edges are ignored.
+    pub fn detect(
+        workspace: &Workspace,
+        inferred: &BTreeMap<ProjectId, BTreeSet<ProjectId>>,
+    ) -> Self {
+        let mut drift = Self::default();
+
+        for (project_id, dependencies) in inferred {
+            let Some(project) = workspace.get_project(*project_id) else {
+                continue;
+            };
+
+            let declared: BTreeSet<ProjectId> =
+                project.dependencies.iter().flatten().copied().collect();
+
+            drift.missing.extend(
+                dependencies
+                    .difference(&declared)
+                    .filter(|dependency| *dependency != project_id)
+                    .map(|dependency| (*project_id, *dependency)),
+            );
+
+            drift.unused.extend(
+                declared
+                    .difference(dependencies)
+                    .map(|dependency| (*project_id, *dependency)),
+            );
+        }
+
+        drift
+    }
+
+    /// Returns `true` if the declared and inferred dependencies match.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unused.is_empty()
+    }
+
+    /// Returns the fixes that resolve the drift, referencing projects by their declared path.
+    pub fn fixes(&self, workspace: &Workspace) -> Vec<DeclarationEdit> {
+        let path = |id: ProjectId| {
+            workspace
+                .get_project(id)
+                .map(|project| project.path.clone())
+        };
+
+        let added = self.missing.iter().filter_map(|(project, dependency)| {
+            Some(DeclarationEdit::AddDependency {
+                project: path(*project)?,
+                dependency: path(*dependency)?,
+            })
+        });
+
+        let removed = self.unused.iter().filter_map(|(project, dependency)| {
+            Some(DeclarationEdit::RemoveDependency {
+                project: path(*project)?,
+                dependency: path(*dependency)?,
+            })
+        });
+
+        added.chain(removed).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::path::PathBuf;
+
+    use crate::declarations::WorkspaceDeclaration;
+
+    use crate::edit::DeclarationEdit;
+
+    use super::DependencyDrift;
+
+    #[test]
+    pub fn when_detecting_drift_should_report_missing_and_unused_edges() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("core", "core", None);
+        declaration.add_project("utils", "utils", None);
+        declaration.add_project("app", "app", Some(vec!["core".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let core = workspace.get_id_by_path(&"core").unwrap();
+        let utils = workspace.get_id_by_path(&"utils").unwrap();
+        let app = workspace.get_id_by_path(&"app").unwrap();
+
+        let inferred = BTreeMap::from([(app, BTreeSet::from([utils]))]);
+
+        let drift = DependencyDrift::detect(&workspace, &inferred);
+
+        assert_eq!(drift.missing, vec![(app, utils)]);
+        assert_eq!(drift.unused, vec![(app, core)]);
+        assert_eq!(
+            drift.fixes(&workspace),
+            vec![
+                DeclarationEdit::AddDependency {
+                    project: PathBuf::from("app"),
+                    dependency: PathBuf::from("utils"),
+                },
+                DeclarationEdit::RemoveDependency {
+                    project: PathBuf::from("app"),
+                    dependency: PathBuf::from("core"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,2108 @@
+//! Edits are the changes tooling makes to declarations: fixing drift, registering generated
+//! projects, and so on. They can be applied to an in-memory [`WorkspaceDeclaration`] or to the
+//! declaration file itself, preserving its formatting where the format allows it.
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use saphyr_parser::{Event, Parser, ScalarStyle, StrInput};
+use toml_edit::{Array, DocumentMut, Item, Table, TableLike, Value};
+
+use crate::declarations::WorkspaceDeclaration;
+use crate::errors::EditDeclarationError;
+use crate::selector::rename_tag;
+
+/// A change to a workspace declaration.
+///
+/// Projects are referenced by their declared path.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DeclarationEdit {
+    /// Declares `dependency` as a dependency of `project`.
+    AddDependency {
+        project: PathBuf,
+        dependency: PathBuf,
+    },
+    /// Removes `dependency` from the dependencies of `project`.
+    RemoveDependency {
+        project: PathBuf,
+        dependency: PathBuf,
+    },
+    /// Declares a new project.
+    AddProject {
+        path: PathBuf,
+        name: String,
+        dependencies: Vec<PathBuf>,
+        tags: Vec<String>,
+    },
+    /// Moves the project at `from` to `to`, together with any project nested under it, and
+    /// rewrites every dependency referencing them.
+    MoveProject { from: PathBuf, to: PathBuf },
+    /// Renames the tag `from` to `to` in every project and generator, and in the selectors of
+    /// the constraints and lint rules, see [`crate::selector::rename_tag`].
+    RenameTag { from: String, to: String },
+    /// Renames the target `from` to `to` in every project, and in the contracts naming it.
+    RenameTarget { from: String, to: String },
+}
+
+impl DeclarationEdit {
+    /// Returns the edit with every absolute path under `root` made relative to it.
+    ///
+    /// Declaration files usually hold paths relative to their directory, while a loaded
+    /// declaration holds resolved paths, so edits computed from a workspace need this before
+    /// being written to the file.
+    pub fn relative_to(&self, root: &Path) -> Self {
+        let relative = |path: &PathBuf| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+        match self {
+            Self::AddDependency {
+                project,
+                dependency,
+            } => Self::AddDependency {
+                project: relative(project),
+                dependency: relative(dependency),
+            },
+            Self::RemoveDependency {
+                project,
+                dependency,
+            } => Self::RemoveDependency {
+                project: relative(project),
+                dependency: relative(dependency),
+            },
+            Self::AddProject {
+                path,
+                name,
+                dependencies,
+                tags,
+            } => Self::AddProject {
+                path: relative(path),
+                name: name.clone(),
+                dependencies: dependencies.iter().map(relative).collect(),
+                tags: tags.clone(),
+            },
+            Self::MoveProject { from, to } => Self::MoveProject {
+                from: relative(from),
+                to: relative(to),
+            },
+            Self::RenameTag { .. } | Self::RenameTarget { .. } => self.clone(),
+        }
+    }
+}
+
+/// Returns the new location of `path` if it is `from` or nested under it.
+fn moved(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from).ok()?;
+
+    if rest.as_os_str().is_empty() {
+        Some(to.to_path_buf())
+    } else {
+        Some(to.join(rest))
+    }
+}
+
+/// Renames `from` to `to` in a list of tags, dropping it instead if `to` is already there.
+fn rename_in_list(tags: &mut Vec<String>, from: &str, to: &str) {
+    if tags.iter().any(|tag| tag == to) {
+        tags.retain(|tag| tag != from);
+        return;
+    }
+
+    for tag in tags.iter_mut().filter(|tag| *tag == from) {
+        *tag = to.to_owned();
+    }
+}
+
+/// Applies the edits to an in-memory declaration. Edits for undeclared projects are skipped,
+/// as are target renames in projects that already have a target with the new name.
+pub fn apply_edits(declaration: &mut WorkspaceDeclaration, edits: &[DeclarationEdit]) {
+    for edit in edits {
+        match edit {
+            DeclarationEdit::AddDependency {
+                project,
+                dependency,
+            } => {
+                if let Some(project) = declaration.projects.get_mut(project) {
+                    let dependencies = project.dependencies.get_or_insert_with(Vec::new);
+
+                    if !dependencies.contains(dependency) {
+                        dependencies.push(dependency.clone());
+                    }
+                }
+            }
+            DeclarationEdit::RemoveDependency {
+                project,
+                dependency,
+            } => {
+                if let Some(dependencies) = declaration
+                    .projects
+                    .get_mut(project)
+                    .and_then(|project| project.dependencies.as_mut())
+                {
+                    dependencies.retain(|existing| existing != dependency);
+                }
+            }
+            DeclarationEdit::AddProject {
+                path,
+                name,
+                dependencies,
+                tags,
+            } => {
+                let dependencies = (!dependencies.is_empty()).then(|| dependencies.clone());
+
+                declaration
+                    .add_project(path.clone(), name.clone(), dependencies)
+                    .tags = tags.clone();
+            }
+            DeclarationEdit::MoveProject { from, to } => {
+                declaration.projects = std::mem::take(&mut declaration.projects)
+                    .into_iter()
+                    .map(|(path, mut project)| {
+                        for dependency in project
+                            .dependencies
+                            .iter_mut()
+                            .flatten()
+                            .chain(&mut project.soft_dependencies)
+                            .chain(&mut project.implicit_dependencies)
+                            .chain(&mut project.encapsulates)
+                        {
+                            if let Some(new) = moved(dependency, from, to) {
+                                *dependency = new;
+                            }
+                        }
+
+                        if let Some(replacement) = project
+                            .deprecated
+                            .as_mut()
+                            .and_then(|deprecated| deprecated.replacement.as_mut())
+                        {
+                            if let Some(new) = moved(replacement, from, to) {
+                                *replacement = new;
+                            }
+                        }
+
+                        project.dependency_kinds = std::mem::take(&mut project.dependency_kinds)
+                            .into_iter()
+                            .map(|(dependency, kind)| {
+                                (moved(&dependency, from, to).unwrap_or(dependency), kind)
+                            })
+                            .collect();
+
+                        (moved(&path, from, to).unwrap_or(path), project)
+                    })
+                    .collect();
+
+                for dependency in declaration
+                    .generators
+                    .values_mut()
+                    .flat_map(|generator| &mut generator.dependencies)
+                {
+                    if let Some(new) = moved(dependency, from, to) {
+                        *dependency = new;
+                    }
+                }
+            }
+            DeclarationEdit::RenameTag { from, to } => {
+                let projects = declaration
+                    .projects
+                    .values_mut()
+                    .map(|project| &mut project.tags);
+                let generators = declaration
+                    .generators
+                    .values_mut()
+                    .map(|generator| &mut generator.tags);
+
+                for tags in projects.chain(generators) {
+                    rename_in_list(tags, from, to);
+                }
+
+                let constraints = declaration.constraints.iter_mut().flat_map(|constraint| {
+                    [Some(&mut constraint.source), constraint.allowed.as_mut()]
+                        .into_iter()
+                        .chain([constraint.forbidden.as_mut()])
+                        .flatten()
+                });
+                let rules = declaration
+                    .lint
+                    .custom
+                    .iter_mut()
+                    .flat_map(|rule| [&mut rule.source, &mut rule.forbidden]);
+
+                for selector in constraints.chain(rules) {
+                    *selector = rename_tag(selector, from, to);
+                }
+
+                if declaration.lint.app_tag == *from {
+                    declaration.lint.app_tag = to.clone();
+                }
+            }
+            DeclarationEdit::RenameTarget { from, to } => {
+                for project in declaration.projects.values_mut() {
+                    if project.targets.contains_key(from) && project.targets.contains_key(to) {
+                        continue;
+                    }
+
+                    if let Some(target) = project.targets.remove(from) {
+                        project.targets.insert(to.clone(), target);
+                    }
+
+                    if project.contract.as_ref() == Some(from) {
+                        project.contract = Some(to.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applies the edits to a declaration file in place, choosing the format by its extension.
+///
+/// Paths in the edits are made relative to the directory of the file first, see
+/// [`DeclarationEdit::relative_to`].
+pub fn edit_declaration_file<P>(
+    path: P,
+    edits: &[DeclarationEdit],
+) -> Result<(), EditDeclarationError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+
+    let edited = edited_declaration_file(path, edits)?;
+
+    std::fs::write(path, edited).map_err(|err| EditDeclarationError::Io(path.to_path_buf(), err))
+}
+
+/// Computes the content of a declaration file after the edits, without writing it.
+pub fn edited_declaration_file(
+    path: &Path,
+    edits: &[DeclarationEdit],
+) -> Result<String, EditDeclarationError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| EditDeclarationError::Io(path.to_path_buf(), err))?;
+
+    let root = path.parent().unwrap_or(Path::new(""));
+    let edits: Vec<_> = edits.iter().map(|edit| edit.relative_to(root)).collect();
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => edit_toml(&content, &edits),
+        Some("yaml" | "yml") => edit_yaml(&content, &edits),
+        _ => Err(EditDeclarationError::UnsupportedFormat(path.to_path_buf())),
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Applies the edits to a TOML declaration, preserving its comments and formatting.
+pub fn edit_toml(content: &str, edits: &[DeclarationEdit]) -> Result<String, EditDeclarationError> {
+    let mut document: DocumentMut = content.parse()?;
+
+    for edit in edits {
+        match edit {
+            DeclarationEdit::AddDependency {
+                project,
+                dependency,
+            } => {
+                let table = toml_project(&mut document, project)?;
+
+                if table.get("dependencies").is_none() {
+                    table.insert("dependencies", Item::Value(Value::Array(Array::new())));
+                }
+
+                if let Some(array) = table.get_mut("dependencies").and_then(Item::as_array_mut) {
+                    let dependency = key(dependency);
+
+                    if !array.iter().any(|item| item.as_str() == Some(&dependency)) {
+                        array.push(dependency);
+                    }
+                }
+            }
+            DeclarationEdit::RemoveDependency {
+                project,
+                dependency,
+            } => {
+                let table = toml_project(&mut document, project)?;
+
+                if let Some(array) = table.get_mut("dependencies").and_then(Item::as_array_mut) {
+                    let dependency = key(dependency);
+
+                    array.retain(|item| item.as_str() != Some(&dependency));
+                }
+            }
+            DeclarationEdit::AddProject {
+                path,
+                name,
+                dependencies,
+                tags,
+            } => {
+                if document.get("projects").is_none() {
+                    let mut projects = Table::new();
+                    projects.set_implicit(true);
+                    document.insert("projects", Item::Table(projects));
+                }
+
+                // A `projects = { ... }` inline table can only hold inline tables.
+                let inline = document.get("projects").is_some_and(Item::is_inline_table);
+
+                let projects = document
+                    .get_mut("projects")
+                    .and_then(Item::as_table_like_mut)
+                    .ok_or_else(|| EditDeclarationError::ProjectNotFound(path.clone()))?;
+
+                let mut table = Table::new();
+                table.insert("name", toml_edit::value(name.as_str()));
+
+                if !dependencies.is_empty() {
+                    let array: Array = dependencies.iter().map(|path| key(path)).collect();
+                    table.insert("dependencies", toml_edit::value(array));
+                }
+
+                if !tags.is_empty() {
+                    let array: Array = tags.iter().map(String::as_str).collect();
+                    table.insert("tags", toml_edit::value(array));
+                }
+
+                let item = if inline {
+                    toml_edit::value(table.into_inline_table())
+                } else {
+                    Item::Table(table)
+                };
+
+                projects.insert(&key(path), item);
+            }
+            DeclarationEdit::MoveProject { from, to } => {
+                let projects = document
+                    .get_mut("projects")
+                    .and_then(Item::as_table_like_mut)
+                    .ok_or_else(|| EditDeclarationError::ProjectNotFound(from.clone()))?;
+
+                if !projects.contains_key(&key(from)) {
+                    return Err(EditDeclarationError::ProjectNotFound(from.clone()));
+                }
+
+                move_toml_keys(projects, from, to);
+
+                for table in toml_children(&mut document, "projects") {
+                    for field in [
+                        "dependencies",
+                        "soft_dependencies",
+                        "implicit_dependencies",
+                        "encapsulates",
+                    ] {
+                        move_in_toml_array(table, field, from, to);
+                    }
+
+                    if let Some(kinds) = table
+                        .get_mut("dependency_kinds")
+                        .and_then(Item::as_table_like_mut)
+                    {
+                        move_toml_keys(kinds, from, to);
+                    }
+
+                    if let Some(deprecated) = table
+                        .get_mut("deprecated")
+                        .and_then(Item::as_table_like_mut)
+                    {
+                        rewrite_toml_string(deprecated, "replacement", |replacement| {
+                            moved(Path::new(replacement), from, to)
+                                .map_or_else(|| replacement.to_owned(), |new| key(&new))
+                        });
+                    }
+                }
+
+                for table in toml_children(&mut document, "generators") {
+                    move_in_toml_array(table, "dependencies", from, to);
+                }
+            }
+            DeclarationEdit::RenameTag { from, to } => {
+                for section in ["projects", "generators"] {
+                    for table in toml_children(&mut document, section) {
+                        if let Some(array) = table.get_mut("tags").and_then(Item::as_array_mut) {
+                            rename_in_toml_array(array, from, to);
+                        }
+                    }
+                }
+
+                for table in toml_list(document.get_mut("constraints")) {
+                    for selector in ["source", "allowed", "forbidden"] {
+                        rewrite_toml_string(table, selector, |value| rename_tag(value, from, to));
+                    }
+                }
+
+                if let Some(lint) = document.get_mut("lint").and_then(Item::as_table_like_mut) {
+                    rewrite_toml_string(lint, "app_tag", |tag| {
+                        if tag == from { to } else { tag }.to_owned()
+                    });
+
+                    for table in toml_list(lint.get_mut("custom")) {
+                        for selector in ["source", "forbidden"] {
+                            rewrite_toml_string(table, selector, |value| {
+                                rename_tag(value, from, to)
+                            });
+                        }
+                    }
+                }
+            }
+            DeclarationEdit::RenameTarget { from, to } => {
+                let Some(projects) = document
+                    .get_mut("projects")
+                    .and_then(Item::as_table_like_mut)
+                else {
+                    continue;
+                };
+
+                for (path, project) in projects.iter_mut() {
+                    let Some(project) = project.as_table_like_mut() else {
+                        continue;
+                    };
+
+                    if let Some(targets) =
+                        project.get_mut("targets").and_then(Item::as_table_like_mut)
+                    {
+                        if targets.contains_key(from) && targets.contains_key(to) {
+                            return Err(EditDeclarationError::TargetExists(
+                                PathBuf::from(path.get()),
+                                to.clone(),
+                            ));
+                        }
+
+                        if let Some(target) = targets.remove(from) {
+                            targets.insert(to, target);
+                        }
+                    }
+
+                    rewrite_toml_string(project, "contract", |contract| {
+                        if contract == from { to } else { contract }.to_owned()
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Returns the tables under the table at `key` of the document, e.g. every project.
+fn toml_children<'a>(document: &'a mut DocumentMut, key: &str) -> Vec<&'a mut dyn TableLike> {
+    document
+        .get_mut(key)
+        .and_then(Item::as_table_like_mut)
+        .map(|table| {
+            table
+                .iter_mut()
+                .filter_map(|(_, item)| item.as_table_like_mut())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the tables of a list, written either as `[[key]]` tables or as an array of inline
+/// tables.
+fn toml_list(item: Option<&mut Item>) -> Vec<&mut dyn TableLike> {
+    match item {
+        Some(Item::ArrayOfTables(tables)) => tables
+            .iter_mut()
+            .map(|table| table as &mut dyn TableLike)
+            .collect(),
+        Some(Item::Value(Value::Array(array))) => array
+            .iter_mut()
+            .filter_map(Value::as_inline_table_mut)
+            .map(|table| table as &mut dyn TableLike)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Rewrites the string at `key` of the table with `rewrite`, keeping its comments.
+fn rewrite_toml_string<F>(table: &mut dyn TableLike, key: &str, rewrite: F)
+where
+    F: Fn(&str) -> String,
+{
+    let Some(value) = table.get_mut(key).and_then(Item::as_value_mut) else {
+        return;
+    };
+
+    let Some(rewritten) = value.as_str().map(&rewrite) else {
+        return;
+    };
+
+    if value.as_str() != Some(&rewritten) {
+        let decor = value.decor().clone();
+        *value = Value::from(rewritten);
+        *value.decor_mut() = decor;
+    }
+}
+
+/// Renames the keys of the table that are paths at or under `from` to be under `to`.
+fn move_toml_keys(table: &mut dyn TableLike, from: &Path, to: &Path) {
+    let renamed: Vec<(String, PathBuf)> = table
+        .iter()
+        .filter_map(|(name, _)| moved(Path::new(name), from, to).map(|new| (name.to_owned(), new)))
+        .collect();
+
+    for (old, new) in renamed {
+        if let Some(item) = table.remove(&old) {
+            table.insert(&key(&new), item);
+        }
+    }
+}
+
+/// Rewrites the paths at or under `from` in the array at `field` of the table to be under `to`,
+/// keeping their comments.
+fn move_in_toml_array(table: &mut dyn TableLike, field: &str, from: &Path, to: &Path) {
+    let Some(array) = table.get_mut(field).and_then(Item::as_array_mut) else {
+        return;
+    };
+
+    for item in array.iter_mut() {
+        let Some(new) = item
+            .as_str()
+            .and_then(|path| moved(Path::new(path), from, to))
+        else {
+            continue;
+        };
+
+        let decor = item.decor().clone();
+        *item = Value::from(key(&new));
+        *item.decor_mut() = decor;
+    }
+}
+
+/// Renames `from` to `to` in a TOML array of tags, see [`rename_in_list`].
+fn rename_in_toml_array(array: &mut Array, from: &str, to: &str) {
+    if array.iter().any(|item| item.as_str() == Some(to)) {
+        let prefix = array.get(0).and_then(|item| item.decor().prefix().cloned());
+
+        array.retain(|item| item.as_str() != Some(from));
+
+        // The new first tag takes the spacing of the old one, e.g. none after the bracket.
+        if let (Some(first), Some(prefix)) = (array.get_mut(0), prefix) {
+            first.decor_mut().set_prefix(prefix);
+        }
+
+        return;
+    }
+
+    for item in array.iter_mut() {
+        if item.as_str() == Some(from) {
+            let decor = item.decor().clone();
+            *item = Value::from(to);
+            *item.decor_mut() = decor;
+        }
+    }
+}
+
+fn toml_project<'a>(
+    document: &'a mut DocumentMut,
+    project: &Path,
+) -> Result<&'a mut dyn toml_edit::TableLike, EditDeclarationError> {
+    document
+        .get_mut("projects")
+        .and_then(|projects| projects.get_mut(key(project)))
+        .and_then(Item::as_table_like_mut)
+        .ok_or_else(|| EditDeclarationError::ProjectNotFound(project.to_path_buf()))
+}
+
+/// Applies the edits to a YAML declaration, preserving its comments and formatting.
+///
+/// The edits are made to the parsed document, then only the parts of the source that changed
+/// are rewritten. Changes that can't be written that way, e.g. to a block scalar, fail with
+/// [`EditDeclarationError::UnsupportedYaml`] instead of rewriting the whole file.
+pub fn edit_yaml(content: &str, edits: &[DeclarationEdit]) -> Result<String, EditDeclarationError> {
+    let mut document: serde_yaml::Value = serde_yaml::from_str(content)?;
+
+    apply_yaml_edits(&mut document, edits)?;
+
+    let Some(root) = YamlParser::parse(content)? else {
+        return match document.is_null() {
+            true => Ok(content.to_owned()),
+            false => Err(unsupported_yaml("the file has no document")),
+        };
+    };
+
+    let mut rewriter = YamlRewriter::new(content);
+    rewriter.rewrite(&root, &document)?;
+    let edited = rewriter.apply(0..content.len())?;
+
+    // A rewrite that reads back differently would silently change the declaration.
+    if serde_yaml::from_str::<serde_yaml::Value>(&edited).ok() != Some(document) {
+        return Err(unsupported_yaml("the edited file doesn't match the edits"));
+    }
+
+    Ok(edited)
+}
+
+/// Applies the edits to a parsed YAML declaration.
+fn apply_yaml_edits(
+    document: &mut serde_yaml::Value,
+    edits: &[DeclarationEdit],
+) -> Result<(), EditDeclarationError> {
+    use serde_yaml::{Mapping, Value};
+
+    for edit in edits {
+        match edit {
+            DeclarationEdit::AddDependency {
+                project,
+                dependency,
+            }
+            | DeclarationEdit::RemoveDependency {
+                project,
+                dependency,
+            } => {
+                let mapping = document
+                    .get_mut("projects")
+                    .and_then(|projects| projects.get_mut(key(project)))
+                    .and_then(Value::as_mapping_mut)
+                    .ok_or_else(|| EditDeclarationError::ProjectNotFound(project.clone()))?;
+
+                let dependency = Value::String(key(dependency));
+
+                let dependencies = mapping
+                    .entry(Value::from("dependencies"))
+                    .or_insert_with(|| Value::Sequence(vec![]));
+
+                if dependencies.is_null() {
+                    *dependencies = Value::Sequence(vec![]);
+                }
+
+                if let Some(sequence) = dependencies.as_sequence_mut() {
+                    if matches!(edit, DeclarationEdit::AddDependency { .. }) {
+                        if !sequence.contains(&dependency) {
+                            sequence.push(dependency);
+                        }
+                    } else {
+                        sequence.retain(|item| *item != dependency);
+                    }
+                }
+            }
+            DeclarationEdit::AddProject {
+                path,
+                name,
+                dependencies,
+                tags,
+            } => {
+                let mut project = Mapping::new();
+                project.insert(Value::from("name"), Value::from(name.as_str()));
+
+                if !dependencies.is_empty() {
+                    let dependencies = dependencies.iter().map(|path| Value::from(key(path)));
+                    project.insert(Value::from("dependencies"), dependencies.collect());
+                }
+
+                if !tags.is_empty() {
+                    let tags = tags.iter().map(|tag| Value::from(tag.as_str()));
+                    project.insert(Value::from("tags"), tags.collect());
+                }
+
+                let Some(root) = document.as_mapping_mut() else {
+                    return Err(EditDeclarationError::ProjectNotFound(path.clone()));
+                };
+
+                let projects = root
+                    .entry(Value::from("projects"))
+                    .or_insert_with(|| Value::Mapping(Mapping::new()));
+
+                if let Some(projects) = projects.as_mapping_mut() {
+                    projects.insert(Value::from(key(path)), Value::Mapping(project));
+                }
+            }
+            DeclarationEdit::MoveProject { from, to } => {
+                let projects = document
+                    .get_mut("projects")
+                    .and_then(Value::as_mapping_mut)
+                    .filter(|projects| projects.contains_key(key(from)))
+                    .ok_or_else(|| EditDeclarationError::ProjectNotFound(from.clone()))?;
+
+                move_yaml_keys(projects, from, to);
+
+                for project in projects.values_mut() {
+                    for field in [
+                        "dependencies",
+                        "soft_dependencies",
+                        "implicit_dependencies",
+                        "encapsulates",
+                    ] {
+                        let paths = project
+                            .get_mut(field)
+                            .and_then(Value::as_sequence_mut)
+                            .into_iter()
+                            .flatten();
+
+                        for path in paths {
+                            move_yaml_path(path, from, to);
+                        }
+                    }
+
+                    if let Some(kinds) = project
+                        .get_mut("dependency_kinds")
+                        .and_then(Value::as_mapping_mut)
+                    {
+                        move_yaml_keys(kinds, from, to);
+                    }
+
+                    if let Some(replacement) = project
+                        .get_mut("deprecated")
+                        .and_then(|deprecated| deprecated.get_mut("replacement"))
+                    {
+                        move_yaml_path(replacement, from, to);
+                    }
+                }
+
+                let generators = document
+                    .get_mut("generators")
+                    .and_then(Value::as_mapping_mut)
+                    .into_iter()
+                    .flat_map(Mapping::values_mut)
+                    .filter_map(|generator| generator.get_mut("dependencies"))
+                    .filter_map(Value::as_sequence_mut)
+                    .flatten();
+
+                for path in generators {
+                    move_yaml_path(path, from, to);
+                }
+            }
+            DeclarationEdit::RenameTag { from, to } => {
+                for section in ["projects", "generators"] {
+                    let tables = document
+                        .get_mut(section)
+                        .and_then(Value::as_mapping_mut)
+                        .into_iter()
+                        .flat_map(Mapping::values_mut);
+
+                    for table in tables {
+                        let Some(sequence) = table.get_mut("tags").and_then(Value::as_sequence_mut)
+                        else {
+                            continue;
+                        };
+
+                        if sequence.iter().any(|tag| tag.as_str() == Some(to)) {
+                            sequence.retain(|tag| tag.as_str() != Some(from));
+                        } else {
+                            for tag in sequence.iter_mut().filter(|tag| tag.as_str() == Some(from))
+                            {
+                                *tag = Value::from(to.as_str());
+                            }
+                        }
+                    }
+                }
+
+                let constraints = document
+                    .get_mut("constraints")
+                    .and_then(Value::as_sequence_mut)
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|constraint| {
+                        yaml_strings(constraint, &["source", "allowed", "forbidden"])
+                    });
+
+                for selector in constraints {
+                    *selector = rename_tag(selector, from, to);
+                }
+
+                if let Some(lint) = document.get_mut("lint") {
+                    if let Some(tag) = lint
+                        .get_mut("app_tag")
+                        .filter(|tag| tag.as_str() == Some(from))
+                    {
+                        *tag = Value::from(to.as_str());
+                    }
+
+                    let rules = lint
+                        .get_mut("custom")
+                        .and_then(Value::as_sequence_mut)
+                        .into_iter()
+                        .flatten()
+                        .flat_map(|rule| yaml_strings(rule, &["source", "forbidden"]));
+
+                    for selector in rules {
+                        *selector = rename_tag(selector, from, to);
+                    }
+                }
+            }
+            DeclarationEdit::RenameTarget { from, to } => {
+                let projects = document
+                    .get_mut("projects")
+                    .and_then(Value::as_mapping_mut)
+                    .into_iter()
+                    .flat_map(|projects| projects.iter_mut());
+
+                for (path, project) in projects {
+                    if let Some(targets) =
+                        project.get_mut("targets").and_then(Value::as_mapping_mut)
+                    {
+                        if targets.contains_key(from.as_str()) && targets.contains_key(to.as_str())
+                        {
+                            return Err(EditDeclarationError::TargetExists(
+                                PathBuf::from(path.as_str().unwrap_or_default()),
+                                to.clone(),
+                            ));
+                        }
+
+                        // Rebuilt rather than removed and inserted, to keep the target in place.
+                        *targets = std::mem::take(targets)
+                            .into_iter()
+                            .map(|(name, target)| {
+                                if name.as_str() == Some(from) {
+                                    (Value::from(to.as_str()), target)
+                                } else {
+                                    (name, target)
+                                }
+                            })
+                            .collect();
+                    }
+
+                    if let Some(contract) = project
+                        .get_mut("contract")
+                        .filter(|contract| contract.as_str() == Some(from))
+                    {
+                        *contract = Value::from(to.as_str());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites a YAML path string at or under `from` to be under `to`.
+fn move_yaml_path(value: &mut serde_yaml::Value, from: &Path, to: &Path) {
+    if let Some(new) = value
+        .as_str()
+        .and_then(|path| moved(Path::new(path), from, to))
+    {
+        *value = serde_yaml::Value::String(key(&new));
+    }
+}
+
+/// Renames the keys of a YAML mapping that are paths at or under `from` to be under `to`,
+/// keeping their order.
+fn move_yaml_keys(mapping: &mut serde_yaml::Mapping, from: &Path, to: &Path) {
+    *mapping = std::mem::take(mapping)
+        .into_iter()
+        .map(|(mut name, value)| {
+            move_yaml_path(&mut name, from, to);
+            (name, value)
+        })
+        .collect();
+}
+
+/// Returns the strings at `keys` of a YAML mapping, skipping the missing ones.
+fn yaml_strings<'a>(mapping: &'a mut serde_yaml::Value, keys: &[&str]) -> Vec<&'a mut String> {
+    mapping
+        .as_mapping_mut()
+        .into_iter()
+        .flat_map(|mapping| mapping.iter_mut())
+        .filter(|(key, _)| key.as_str().is_some_and(|key| keys.contains(&key)))
+        .filter_map(|(_, value)| match value {
+            serde_yaml::Value::String(string) => Some(string),
+            _ => None,
+        })
+        .collect()
+}
+
+fn unsupported_yaml(reason: &str) -> EditDeclarationError {
+    EditDeclarationError::UnsupportedYaml(reason.to_owned())
+}
+
+/// A node of a YAML source, with the byte range it spans. The range of a block collection
+/// ends with its last child, as the source doesn't mark its end.
+enum YamlNode {
+    Scalar {
+        value: String,
+        style: ScalarStyle,
+        span: Range<usize>,
+    },
+    Sequence {
+        items: Vec<YamlNode>,
+        flow: bool,
+        span: Range<usize>,
+    },
+    Mapping {
+        entries: Vec<(YamlNode, YamlNode)>,
+        flow: bool,
+        span: Range<usize>,
+    },
+    /// An alias, with the value of the node it refers to.
+    Alias {
+        value: serde_yaml::Value,
+        span: Range<usize>,
+    },
+}
+
+impl YamlNode {
+    fn span(&self) -> Range<usize> {
+        match self {
+            Self::Scalar { span, .. }
+            | Self::Sequence { span, .. }
+            | Self::Mapping { span, .. }
+            | Self::Alias { span, .. } => span.clone(),
+        }
+    }
+
+    /// Returns the value of the node, as serde_yaml reads it.
+    fn value(&self) -> serde_yaml::Value {
+        use serde_yaml::Value;
+
+        match self {
+            Self::Scalar {
+                value,
+                style: ScalarStyle::Plain,
+                ..
+            } => match serde_yaml::from_str(value) {
+                Ok(resolved @ (Value::Null | Value::Bool(_) | Value::Number(_))) => resolved,
+                _ => Value::String(value.clone()),
+            },
+            Self::Scalar { value, .. } => Value::String(value.clone()),
+            Self::Sequence { items, .. } => items.iter().map(Self::value).collect(),
+            Self::Mapping { entries, .. } => Value::Mapping(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.value(), value.value()))
+                    .collect(),
+            ),
+            Self::Alias { value, .. } => value.clone(),
+        }
+    }
+
+    /// Returns the style of the first scalar of the node, to write new scalars alike.
+    fn style(&self) -> ScalarStyle {
+        match self {
+            Self::Scalar { style, .. } => *style,
+            Self::Sequence { items, .. } => items.first().map_or(ScalarStyle::Plain, Self::style),
+            Self::Mapping { entries, .. } => entries
+                .first()
+                .map_or(ScalarStyle::Plain, |(_, value)| value.style()),
+            Self::Alias { .. } => ScalarStyle::Plain,
+        }
+    }
+}
+
+/// Reads the nodes of a YAML source from the events of its parser.
+struct YamlParser<'a> {
+    parser: Parser<'a, StrInput<'a>>,
+    /// The byte offset of every character of the source, as the parser counts characters.
+    offsets: Vec<usize>,
+    anchors: HashMap<usize, serde_yaml::Value>,
+}
+
+impl<'a> YamlParser<'a> {
+    /// Parses the first document of the source, if it has one.
+    fn parse(content: &'a str) -> Result<Option<YamlNode>, EditDeclarationError> {
+        let offsets = content
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([content.len()])
+            .collect();
+
+        let mut parser = YamlParser {
+            parser: Parser::new_from_str(content),
+            offsets,
+            anchors: HashMap::new(),
+        };
+
+        loop {
+            match parser.next()? {
+                (Event::StreamStart | Event::DocumentStart(_), _) => continue,
+                (Event::StreamEnd, _) => return Ok(None),
+                (event, span) => return parser.node(event, span).map(Some),
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<(Event<'a>, Range<usize>), EditDeclarationError> {
+        let (event, span) = self
+            .parser
+            .next()
+            .ok_or_else(|| unsupported_yaml("the file ends unexpectedly"))?
+            .map_err(|err| EditDeclarationError::UnsupportedYaml(err.to_string()))?;
+
+        Ok((
+            event,
+            self.offsets[span.start.index()]..self.offsets[span.end.index()],
+        ))
+    }
+
+    fn node(
+        &mut self,
+        event: Event<'a>,
+        span: Range<usize>,
+    ) -> Result<YamlNode, EditDeclarationError> {
+        let (node, anchor) = match event {
+            Event::Scalar(value, style, anchor, _) => {
+                let value = value.into_owned();
+                (YamlNode::Scalar { value, style, span }, anchor)
+            }
+            Event::SequenceStart(anchor, _) => {
+                let mut items = Vec::new();
+
+                let end = loop {
+                    match self.next()? {
+                        (Event::SequenceEnd, end) => break end,
+                        (event, span) => items.push(self.node(event, span)?),
+                    }
+                };
+
+                let flow = !span.is_empty();
+                let end = collection_end(flow, end, items.last());
+
+                (
+                    YamlNode::Sequence {
+                        items,
+                        flow,
+                        span: span.start..end,
+                    },
+                    anchor,
+                )
+            }
+            Event::MappingStart(anchor, _) => {
+                let mut entries = Vec::new();
+
+                let end = loop {
+                    match self.next()? {
+                        (Event::MappingEnd, end) => break end,
+                        (event, span) => {
+                            let key = self.node(event, span)?;
+                            let (event, span) = self.next()?;
+                            entries.push((key, self.node(event, span)?));
+                        }
+                    }
+                };
+
+                let flow = !span.is_empty();
+                let end = collection_end(flow, end, entries.last().map(|(_, value)| value));
+
+                (
+                    YamlNode::Mapping {
+                        entries,
+                        flow,
+                        span: span.start..end,
+                    },
+                    anchor,
+                )
+            }
+            Event::Alias(anchor) => {
+                let value = self.anchors.get(&anchor).cloned().unwrap_or_default();
+                (YamlNode::Alias { value, span }, 0)
+            }
+            _ => return Err(unsupported_yaml("the file has an unexpected structure")),
+        };
+
+        if anchor != 0 {
+            self.anchors.insert(anchor, node.value());
+        }
+
+        Ok(node)
+    }
+}
+
+/// Returns where a collection ends: at its closing bracket when in flow style, or with its
+/// last child otherwise.
+fn collection_end(flow: bool, end: Range<usize>, last: Option<&YamlNode>) -> usize {
+    match (flow, last) {
+        (true, _) | (false, None) => end.end,
+        (false, Some(last)) => last.span().end,
+    }
+}
+
+/// Collects the replacements that turn a YAML source into an edited value, leaving the
+/// unchanged parts of the source as they are.
+struct YamlRewriter<'a> {
+    content: &'a str,
+    patches: Vec<(Range<usize>, String)>,
+}
+
+impl<'a> YamlRewriter<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            patches: Vec::new(),
+        }
+    }
+
+    /// Returns the source in `range` with the replacements applied. Insertions at the same
+    /// offset are applied in the order they were made, so children come before their parents.
+    fn apply(mut self, range: Range<usize>) -> Result<String, EditDeclarationError> {
+        self.patches.sort_by_key(|(span, _)| span.start);
+
+        let mut edited = String::new();
+        let mut cursor = range.start;
+
+        for (span, text) in &self.patches {
+            if span.start < cursor {
+                return Err(unsupported_yaml("the edits overlap"));
+            }
+
+            edited.push_str(&self.content[cursor..span.start]);
+            edited.push_str(text);
+            cursor = span.end;
+        }
+
+        edited.push_str(&self.content[cursor..range.end]);
+
+        Ok(edited)
+    }
+
+    fn replace(&mut self, span: Range<usize>, text: String) {
+        self.patches.push((span, text));
+    }
+
+    fn insert(&mut self, offset: usize, text: String) {
+        self.patches.push((offset..offset, text));
+    }
+
+    /// Rewrites `node` to read as `value`.
+    fn rewrite(
+        &mut self,
+        node: &YamlNode,
+        value: &serde_yaml::Value,
+    ) -> Result<(), EditDeclarationError> {
+        use serde_yaml::Value;
+
+        if node.value() == *value {
+            return Ok(());
+        }
+
+        match (node, value) {
+            (YamlNode::Scalar { style, span, .. }, Value::Sequence(_) | Value::Mapping(_)) => {
+                let text = yaml_flow(value, *style);
+                self.replace_scalar(span.clone(), text)
+            }
+            (YamlNode::Scalar { style, span, .. }, value) => match style {
+                ScalarStyle::Literal | ScalarStyle::Folded => {
+                    Err(unsupported_yaml("a block scalar would change"))
+                }
+                _ => {
+                    let text = yaml_scalar(value, *style);
+                    self.replace_scalar(span.clone(), text)
+                }
+            },
+            (YamlNode::Sequence { items, .. }, Value::Sequence(values))
+                if items.len() == values.len() =>
+            {
+                for (item, value) in items.iter().zip(values) {
+                    self.rewrite(item, value)?;
+                }
+
+                Ok(())
+            }
+            (YamlNode::Sequence { items, flow, span }, Value::Sequence(values)) => {
+                self.rewrite_sequence(items, *flow, span.clone(), values, node.style())
+            }
+            (
+                YamlNode::Mapping {
+                    entries,
+                    flow,
+                    span,
+                },
+                Value::Mapping(values),
+            ) if entries.len() <= values.len() => {
+                // The edits keep the order of the keys and only add new ones at the end.
+                for ((key, value), (new_key, new_value)) in entries.iter().zip(values) {
+                    self.rewrite(key, new_key)?;
+                    self.rewrite(value, new_value)?;
+                }
+
+                let added: Vec<_> = values.iter().skip(entries.len()).collect();
+
+                if !added.is_empty() {
+                    self.add_entries(node, entries, *flow, span.clone(), &added);
+                }
+
+                Ok(())
+            }
+            (
+                YamlNode::Sequence {
+                    flow: true, span, ..
+                }
+                | YamlNode::Mapping {
+                    flow: true, span, ..
+                },
+                value,
+            ) => {
+                self.replace(span.clone(), yaml_flow(value, node.style()));
+                Ok(())
+            }
+            _ => Err(unsupported_yaml(
+                "a block collection would change its shape",
+            )),
+        }
+    }
+
+    /// Replaces a scalar, which when empty spans nothing right after its key.
+    fn replace_scalar(
+        &mut self,
+        span: Range<usize>,
+        text: String,
+    ) -> Result<(), EditDeclarationError> {
+        if !span.is_empty() {
+            self.replace(span, text);
+            return Ok(());
+        }
+
+        let colon = self.content[span.start..]
+            .find(':')
+            .ok_or_else(|| unsupported_yaml("an empty value would change"))?;
+
+        self.insert(span.start + colon + 1, format!(" {text}"));
+
+        Ok(())
+    }
+
+    /// Rewrites a sequence that has items removed or added, keeping the items left in place
+    /// and adding the new ones at its end.
+    fn rewrite_sequence(
+        &mut self,
+        items: &[YamlNode],
+        flow: bool,
+        span: Range<usize>,
+        values: &[serde_yaml::Value],
+        style: ScalarStyle,
+    ) -> Result<(), EditDeclarationError> {
+        let mut remaining = values.iter().peekable();
+        let mut kept = Vec::new();
+
+        for item in items {
+            let keep = remaining.next_if(|value| item.value() == **value).is_some();
+            kept.push(keep);
+        }
+
+        let added: Vec<_> = remaining.collect();
+
+        for (index, item) in items.iter().enumerate() {
+            if kept[index] {
+                continue;
+            }
+
+            let item_span = item.span();
+
+            let removed = if !flow {
+                // The whole lines of the item, but the newline after them.
+                let start = line_start(self.content, item_span.start).saturating_sub(1);
+                start..line_end(self.content, item_span.end)
+            } else if kept[..index].iter().any(|kept| *kept) {
+                // With the separator before it, so the items after a kept one go in order.
+                items[index - 1].span().end..item_span.end
+            } else {
+                let next = items
+                    .get(index + 1)
+                    .map_or(item_span.end, |next| next.span().start);
+                item_span.start..next
+            };
+
+            self.replace(removed, String::new());
+        }
+
+        let any_kept = kept.iter().any(|kept| *kept);
+
+        match (items.last(), flow) {
+            (_, true) => {
+                let offset = items.last().map_or(span.start + 1, |last| last.span().end);
+                let separator = if any_kept { ", " } else { "" };
+
+                let text: Vec<_> = added.iter().map(|value| yaml_flow(value, style)).collect();
+
+                if !text.is_empty() {
+                    self.insert(offset, format!("{separator}{}", text.join(", ")));
+                }
+            }
+            (Some(last), false) if !added.is_empty() => {
+                let indentation = indentation(self.content, span.start);
+                let text: String = added
+                    .iter()
+                    .map(|value| format!("\n{indentation}- {}", yaml_flow(value, style)))
+                    .collect();
+
+                self.insert(line_end(self.content, last.span().end), text);
+            }
+            (_, false) if !any_kept => {
+                // Every item is gone, so the key is left with an empty flow sequence.
+                let before = self.content[..span.start].trim_end();
+
+                if !before.ends_with(':') {
+                    return Err(unsupported_yaml("a block sequence would be emptied"));
+                }
+
+                self.insert(before.len(), " []".to_owned());
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Adds entries at the end of a mapping.
+    fn add_entries(
+        &mut self,
+        node: &YamlNode,
+        entries: &[(YamlNode, YamlNode)],
+        flow: bool,
+        span: Range<usize>,
+        added: &[(&serde_yaml::Value, &serde_yaml::Value)],
+    ) {
+        let key_style = entries
+            .first()
+            .map_or(ScalarStyle::Plain, |(key, _)| key.style());
+        let style = node.style();
+        let last = entries.last().map(|(_, value)| value.span().end);
+
+        if flow {
+            let text: Vec<_> = added
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}: {}",
+                        yaml_scalar(key, key_style),
+                        yaml_flow(value, style)
+                    )
+                })
+                .collect();
+
+            let (offset, separator) = match last {
+                Some(last) => (last, ", "),
+                None => (span.start + 1, ""),
+            };
+
+            self.insert(offset, format!("{separator}{}", text.join(", ")));
+            return;
+        }
+
+        let indentation = indentation(self.content, span.start);
+        let text: String = added
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "\n{indentation}{}:{}",
+                    yaml_scalar(key, key_style),
+                    yaml_block(value, &indentation, style)
+                )
+            })
+            .collect();
+
+        self.insert(line_end(self.content, last.unwrap_or(span.end)), text);
+    }
+}
+
+/// Returns the offset of the start of the line of `offset`.
+fn line_start(content: &str, offset: usize) -> usize {
+    content[..offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1)
+}
+
+/// Returns the offset of the newline ending the line of `offset`, or of the end of the file.
+fn line_end(content: &str, offset: usize) -> usize {
+    content[offset..]
+        .find('\n')
+        .map_or(content.len(), |newline| offset + newline)
+}
+
+/// Returns the indentation of the node at `offset`, counting the `- ` of a sequence item it's
+/// the first entry of as spaces.
+fn indentation(content: &str, offset: usize) -> String {
+    content[line_start(content, offset)..offset]
+        .chars()
+        .map(|char| if char == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+/// Writes a scalar in the style of `style`, quoting plain ones when YAML needs it.
+fn yaml_scalar(value: &serde_yaml::Value, style: ScalarStyle) -> String {
+    match (value, style) {
+        (serde_yaml::Value::String(string), ScalarStyle::SingleQuoted) => {
+            format!("'{}'", string.replace('\'', "''"))
+        }
+        (serde_yaml::Value::String(string), ScalarStyle::DoubleQuoted) => {
+            serde_json::Value::from(string.as_str()).to_string()
+        }
+        _ => match serde_yaml::to_string(value) {
+            Ok(text) if !text.trim_end().contains('\n') => text.trim_end().to_owned(),
+            _ => serde_json::Value::from(value.as_str().unwrap_or_default()).to_string(),
+        },
+    }
+}
+
+/// Writes a value in flow style, e.g. `[a, b]`.
+fn yaml_flow(value: &serde_yaml::Value, style: ScalarStyle) -> String {
+    match value {
+        serde_yaml::Value::Sequence(items) => {
+            let items: Vec<_> = items.iter().map(|item| yaml_flow(item, style)).collect();
+            format!("[{}]", items.join(", "))
+        }
+        serde_yaml::Value::Mapping(entries) => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}: {}", yaml_flow(key, style), yaml_flow(value, style))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        value => yaml_scalar(value, style),
+    }
+}
+
+/// Writes the value of a key of a block mapping indented by `indentation`, from right after
+/// its colon. Mappings stay in block style, nested one level deeper.
+fn yaml_block(value: &serde_yaml::Value, indentation: &str, style: ScalarStyle) -> String {
+    match value {
+        serde_yaml::Value::Mapping(entries) if !entries.is_empty() => entries
+            .iter()
+            .map(|(key, value)| {
+                let nested = format!("{indentation}  ");
+                format!(
+                    "\n{nested}{}:{}",
+                    yaml_scalar(key, ScalarStyle::Plain),
+                    yaml_block(value, &nested, style)
+                )
+            })
+            .collect(),
+        value => format!(" {}", yaml_flow(value, style)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::declarations::{GeneratorDeclaration, WorkspaceDeclaration};
+    use crate::errors::EditDeclarationError;
+    use crate::workspace::DependencyKind;
+
+    use super::{apply_edits, edit_toml, edit_yaml, DeclarationEdit};
+
+    #[test]
+    pub fn when_editing_toml_should_preserve_comments() {
+        let content = r#"# The workspace
+[projects.core]
+name = "core"
+
+[projects.app]
+name = "app" # the main application
+dependencies = ["core"]
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[
+                DeclarationEdit::RemoveDependency {
+                    project: PathBuf::from("app"),
+                    dependency: PathBuf::from("core"),
+                },
+                DeclarationEdit::AddDependency {
+                    project: PathBuf::from("core"),
+                    dependency: PathBuf::from("utils"),
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"# The workspace
+[projects.core]
+name = "core"
+dependencies = ["utils"]
+
+[projects.app]
+name = "app" # the main application
+dependencies = []
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_adding_project_to_toml_should_append_table() {
+        let content = "[projects.core]\nname = \"core\"\n";
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::AddProject {
+                path: PathBuf::from("libs/new"),
+                name: "new".to_owned(),
+                dependencies: vec![PathBuf::from("core")],
+                tags: vec!["lib".to_owned()],
+            }],
+        )
+        .unwrap();
+
+        let declaration = WorkspaceDeclaration::from_toml_str(&edited).unwrap();
+        let project = &declaration.projects[Path::new("libs/new")];
+
+        assert!(edited.starts_with(content));
+        assert_eq!(project.name, "new");
+        assert_eq!(project.dependencies, Some(vec![PathBuf::from("core")]));
+        assert_eq!(project.tags, vec!["lib"]);
+    }
+
+    #[test]
+    pub fn when_editing_yaml_should_update_dependencies() {
+        let content = "projects:\n  app:\n    name: app\n    dependencies: null\n";
+
+        let edited = edit_yaml(
+            content,
+            &[
+                DeclarationEdit::AddDependency {
+                    project: PathBuf::from("app"),
+                    dependency: PathBuf::from("core"),
+                },
+                DeclarationEdit::AddProject {
+                    path: PathBuf::from("core"),
+                    name: "core".to_owned(),
+                    dependencies: vec![],
+                    tags: vec![],
+                },
+            ],
+        )
+        .unwrap();
+
+        let declaration = WorkspaceDeclaration::from_yaml_str(&edited).unwrap();
+
+        assert_eq!(
+            declaration.projects[Path::new("app")].dependencies,
+            Some(vec![PathBuf::from("core")])
+        );
+        assert_eq!(declaration.projects[Path::new("core")].name, "core");
+    }
+
+    #[test]
+    pub fn when_editing_yaml_should_preserve_comments_and_formatting() {
+        let content = r#"# The workspace.
+projects:
+  apps/web:
+    name: web # The storefront.
+    dependencies:
+      - "libs/old"   # Going away.
+      - "libs/core"
+    tags: [ui, 'app']
+  libs/core:
+    name: core
+"#;
+        let edits = [
+            DeclarationEdit::RemoveDependency {
+                project: PathBuf::from("apps/web"),
+                dependency: PathBuf::from("libs/old"),
+            },
+            DeclarationEdit::AddDependency {
+                project: PathBuf::from("apps/web"),
+                dependency: PathBuf::from("libs/ui"),
+            },
+            DeclarationEdit::AddDependency {
+                project: PathBuf::from("libs/core"),
+                dependency: PathBuf::from("libs/util"),
+            },
+            DeclarationEdit::RenameTag {
+                from: "app".to_owned(),
+                to: "frontend".to_owned(),
+            },
+            DeclarationEdit::AddProject {
+                path: PathBuf::from("libs/ui"),
+                name: "ui".to_owned(),
+                dependencies: vec![PathBuf::from("libs/core")],
+                tags: vec![],
+            },
+        ];
+
+        let edited = edit_yaml(content, &edits).unwrap();
+
+        assert_eq!(
+            edited,
+            r#"# The workspace.
+projects:
+  apps/web:
+    name: web # The storefront.
+    dependencies:
+      - "libs/core"
+      - "libs/ui"
+    tags: [ui, 'frontend']
+  libs/core:
+    name: core
+    dependencies: [libs/util]
+  libs/ui:
+    name: ui
+    dependencies: [libs/core]
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_editing_yaml_block_scalar_should_return_error() {
+        let content = "projects:\n  a:\n    name: a\n  legacy:\n    name: legacy\n    \
+                       deprecated:\n      replacement: >-\n        a\n";
+
+        let result = edit_yaml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("a"),
+                to: PathBuf::from("b"),
+            }],
+        );
+
+        assert!(matches!(
+            result,
+            Err(EditDeclarationError::UnsupportedYaml(_))
+        ));
+    }
+
+    #[test]
+    pub fn when_editing_undeclared_project_should_return_error() {
+        let result = edit_toml(
+            "[projects.core]\nname = \"core\"\n",
+            &[DeclarationEdit::AddDependency {
+                project: PathBuf::from("missing"),
+                dependency: PathBuf::from("core"),
+            }],
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn when_applying_edits_in_memory_should_update_declaration() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("core", "core", None);
+
+        apply_edits(
+            &mut declaration,
+            &[
+                DeclarationEdit::AddProject {
+                    path: PathBuf::from("app"),
+                    name: "app".to_owned(),
+                    dependencies: vec![],
+                    tags: vec![],
+                },
+                DeclarationEdit::AddDependency {
+                    project: PathBuf::from("app"),
+                    dependency: PathBuf::from("core"),
+                },
+            ],
+        );
+
+        assert_eq!(
+            declaration.projects[Path::new("app")].dependencies,
+            Some(vec![PathBuf::from("core")])
+        );
+    }
+
+    #[test]
+    pub fn when_moving_project_in_toml_should_rewrite_keys_and_references() {
+        let content = r#"[projects."libs/a"]
+name = "a"
+
+[projects."libs/a/sub"]
+name = "sub"
+
+[projects."apps/web"]
+name = "web"
+dependencies = ["libs/a", "libs/a/sub"] # both
+
+[generators.lib]
+template = "templates/lib"
+dependencies = ["libs/a"]
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("libs/a"),
+                to: PathBuf::from("libs/core"),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"[projects."libs/core"]
+name = "a"
+
+[projects."libs/core/sub"]
+name = "sub"
+
+[projects."apps/web"]
+name = "web"
+dependencies = ["libs/core", "libs/core/sub"] # both
+
+[generators.lib]
+template = "templates/lib"
+dependencies = ["libs/core"]
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_moving_project_in_toml_should_rewrite_soft_dependencies() {
+        let content = r#"[projects.a]
+name = "a"
+
+[projects.web]
+name = "web"
+soft_dependencies = ["a"] # docs only
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("a"),
+                to: PathBuf::from("b"),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"[projects.b]
+name = "a"
+
+[projects.web]
+name = "web"
+soft_dependencies = ["b"] # docs only
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_moving_project_in_toml_should_rewrite_encapsulated_dependencies() {
+        let content = r#"[projects.a]
+name = "a"
+
+[projects.api]
+name = "api"
+dependencies = ["a"]
+encapsulates = ["a"]
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("a"),
+                to: PathBuf::from("b"),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"[projects.b]
+name = "a"
+
+[projects.api]
+name = "api"
+dependencies = ["b"]
+encapsulates = ["b"]
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_moving_project_in_toml_should_rewrite_dependency_kinds() {
+        let content = r#"[projects.a]
+name = "a"
+
+[projects.web]
+name = "web"
+dependencies = ["a"]
+dependency_kinds = { a = "dev" }
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("a"),
+                to: PathBuf::from("b"),
+            }],
+        )
+        .unwrap();
+
+        let declaration = WorkspaceDeclaration::from_toml_str(&edited).unwrap();
+        let web = &declaration.projects[Path::new("web")];
+
+        assert_eq!(
+            web.dependency_kinds.keys().collect::<Vec<_>>(),
+            [Path::new("b")]
+        );
+        assert_eq!(web.dependency_kinds[Path::new("b")], DependencyKind::Dev);
+    }
+
+    #[test]
+    pub fn when_moving_project_in_toml_should_rewrite_implicit_dependencies() {
+        let content = r#"[projects.schema]
+name = "schema"
+
+[projects.client]
+name = "client"
+implicit_dependencies = ["schema"] # generated from it
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("schema"),
+                to: PathBuf::from("libs/schema"),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"[projects."libs/schema"]
+name = "schema"
+
+[projects.client]
+name = "client"
+implicit_dependencies = ["libs/schema"] # generated from it
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_moving_project_in_toml_should_rewrite_deprecation_replacement() {
+        let content = r#"[projects.core]
+name = "core"
+
+[projects.legacy]
+name = "legacy"
+deprecated = { sunset = "2027-01-31", replacement = "core" }
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[DeclarationEdit::MoveProject {
+                from: PathBuf::from("core"),
+                to: PathBuf::from("libs/core"),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"[projects."libs/core"]
+name = "core"
+
+[projects.legacy]
+name = "legacy"
+deprecated = { sunset = "2027-01-31", replacement = "libs/core" }
+"#
+        );
+    }
+
+    #[test]
+    pub fn when_moving_project_in_yaml_and_memory_should_rewrite_references() {
+        let content = r#"projects:
+  a:
+    name: a
+  web:
+    name: web
+    dependencies: [a]
+    soft_dependencies: [a]
+    implicit_dependencies: [a]
+    encapsulates: [a]
+    dependency_kinds:
+      a: dev
+  legacy:
+    name: legacy
+    deprecated:
+      replacement: a
+generators:
+  lib:
+    template: templates/lib
+    dependencies: [a]
+"#;
+        let edit = DeclarationEdit::MoveProject {
+            from: PathBuf::from("a"),
+            to: PathBuf::from("b"),
+        };
+
+        let edited = edit_yaml(content, std::slice::from_ref(&edit)).unwrap();
+        let moved = WorkspaceDeclaration::from_yaml_str(&edited).unwrap();
+        let web = &moved.projects[Path::new("web")];
+        let b = [PathBuf::from("b")];
+
+        assert_eq!(moved.projects[Path::new("b")].name, "a");
+        assert_eq!(web.dependencies.as_deref(), Some(&b[..]));
+        assert_eq!(web.soft_dependencies, b);
+        assert_eq!(web.implicit_dependencies, b);
+        assert_eq!(web.encapsulates, b);
+        assert_eq!(
+            web.dependency_kinds.keys().collect::<Vec<_>>(),
+            [Path::new("b")]
+        );
+        assert_eq!(
+            moved.projects[Path::new("legacy")]
+                .deprecated
+                .as_ref()
+                .and_then(|deprecated| deprecated.replacement.as_deref()),
+            Some(Path::new("b"))
+        );
+        assert_eq!(moved.generators["lib"].dependencies, b);
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("a", "a", None);
+        declaration.add_project("web", "web", Some(vec![PathBuf::from("a")]));
+        declaration.generators.insert(
+            "lib".to_owned(),
+            GeneratorDeclaration {
+                template: PathBuf::from("templates/lib"),
+                tags: vec![],
+                dependencies: vec![PathBuf::from("a")],
+            },
+        );
+
+        apply_edits(&mut declaration, &[edit]);
+
+        assert!(declaration.projects.contains_key(Path::new("b")));
+        assert_eq!(
+            declaration.projects[Path::new("web")].dependencies,
+            Some(vec![PathBuf::from("b")])
+        );
+        assert_eq!(
+            declaration.generators["lib"].dependencies,
+            [PathBuf::from("b")]
+        );
+    }
+
+    #[test]
+    pub fn when_renaming_tag_and_target_in_toml_should_preserve_formatting() {
+        let content = r#"[projects.web]
+name = "web"
+tags = ["ui", "app"] # layers
+contract = "test"
+
+[projects.web.targets.test]
+command = "npm test"
+
+[projects.web.targets.lint]
+command = "npm run lint"
+
+[projects.kit]
+name = "kit"
+tags = ["ui", "frontend"]
+
+[lint]
+app_tag = "app"
+custom = [{ name = "layers", source = "tag:ui", forbidden = "tag:infra", message = "no" }]
+
+# UI stays away from infrastructure.
+[[constraints]]
+name = "ui-not-infra"
+source = "tag:ui & !tag:legacy"
+forbidden = "tag:infra"
+"#;
+
+        let edited = edit_toml(
+            content,
+            &[
+                DeclarationEdit::RenameTag {
+                    from: "ui".to_owned(),
+                    to: "frontend".to_owned(),
+                },
+                DeclarationEdit::RenameTarget {
+                    from: "test".to_owned(),
+                    to: "check".to_owned(),
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            r#"[projects.web]
+name = "web"
+tags = ["frontend", "app"] # layers
+contract = "check"
+
+[projects.web.targets.check]
+command = "npm test"
+
+[projects.web.targets.lint]
+command = "npm run lint"
+
+[projects.kit]
+name = "kit"
+tags = ["frontend"]
+
+[lint]
+app_tag = "app"
+custom = [{ name = "layers", source = "tag:frontend", forbidden = "tag:infra", message = "no" }]
+
+# UI stays away from infrastructure.
+[[constraints]]
+name = "ui-not-infra"
+source = "tag:frontend & !tag:legacy"
+forbidden = "tag:infra"
+"#
+        );
+
+        let conflict = edit_toml(
+            &edited,
+            &[DeclarationEdit::RenameTarget {
+                from: "check".to_owned(),
+                to: "lint".to_owned(),
+            }],
+        );
+
+        assert!(matches!(
+            conflict,
+            Err(EditDeclarationError::TargetExists(project, target))
+                if project == Path::new("web") && target == "lint"
+        ));
+    }
+
+    #[test]
+    pub fn when_renaming_in_yaml_and_memory_should_rewrite_references() {
+        let content = r#"{"projects": {"web": {"name": "web", "tags": ["ui"], "contract": "test",
+            "targets": {"test": {"command": "npm test"}, "lint": {"command": "eslint"}}}},
+            "constraints": [{"name": "c", "source": "tag:ui", "forbidden": "tag:infra"}]}"#;
+        let edits = [
+            DeclarationEdit::RenameTag {
+                from: "ui".to_owned(),
+                to: "frontend".to_owned(),
+            },
+            DeclarationEdit::RenameTarget {
+                from: "test".to_owned(),
+                to: "check".to_owned(),
+            },
+        ];
+
+        let edited = edit_yaml(content, &edits).unwrap();
+        let value: serde_yaml::Value = serde_yaml::from_str(&edited).unwrap();
+        let web = &value["projects"]["web"];
+        let targets: Vec<&str> = web["targets"]
+            .as_mapping()
+            .unwrap()
+            .keys()
+            .filter_map(serde_yaml::Value::as_str)
+            .collect();
+
+        assert_eq!(web["tags"][0], "frontend");
+        assert_eq!(web["contract"], "check");
+        assert_eq!(targets, ["check", "lint"]);
+        assert_eq!(value["constraints"][0]["source"], "tag:frontend");
+
+        let mut declaration = WorkspaceDeclaration::from_yaml_str(content).unwrap();
+
+        apply_edits(&mut declaration, &edits);
+
+        let web = &declaration.projects[Path::new("web")];
+
+        assert_eq!(web.tags, ["frontend"]);
+        assert_eq!(web.contract.as_deref(), Some("check"));
+        assert!(web.targets.contains_key("check") && !web.targets.contains_key("test"));
+        assert_eq!(declaration.constraints[0].source, "tag:frontend");
+    }
+
+    #[test]
+    pub fn when_making_edit_relative_should_strip_root() {
+        let edit = DeclarationEdit::AddDependency {
+            project: PathBuf::from("/repo/app"),
+            dependency: PathBuf::from("/elsewhere/core"),
+        };
+
+        assert_eq!(
+            edit.relative_to(Path::new("/repo")),
+            DeclarationEdit::AddDependency {
+                project: PathBuf::from("app"),
+                dependency: PathBuf::from("/elsewhere/core"),
+            }
+        );
+    }
+}
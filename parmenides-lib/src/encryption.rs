@@ -0,0 +1,364 @@
+//! # Encryption
+//!
+//! Values of the declaration can be committed encrypted, sops style, so a workspace
+//! declaration holding a webhook URL in its constants or the token of its remote cache stays
+//! safe to share. An encrypted value is written as `ENC[<ciphertext>]` and decrypted when the
+//! declaration is loaded, see [`Decrypter::decrypt_declaration`].
+//!
+//! By default the ciphertext is the body of an ASCII armored [age](https://age-encryption.org)
+//! file, without its header, footer and line breaks:
+//!
+//! ```text
+//! printf '%s' "$SECRET" | age -r "$RECIPIENT" -a | sed '1d;$d' | tr -d '\n'
+//! ```
+//!
+//! It is decrypted with the `age` command and the identity of the [`EncryptionDeclaration`],
+//! reading the ciphertext from its standard input. Other tools, such as sops or a secret
+//! manager, can decrypt instead with a command, reading the ciphertext from a file only the
+//! current user can access.
+use std::collections::hash_map::RandomState;
+use std::fs::{DirBuilder, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::declarations::WorkspaceDeclaration;
+use crate::errors::EncryptionError;
+use crate::process::{shell_command, ProcessRunner};
+
+/// The environment variable overriding the identity file of the declaration, e.g. for a key
+/// provisioned by the CI.
+pub const IDENTITY_ENV: &str = "PARMENIDES_AGE_IDENTITY";
+
+/// The environment variable holding the path of the file with the ciphertext, for decryption
+/// commands.
+pub const CIPHERTEXT_ENV: &str = "PARMENIDES_CIPHERTEXT_FILE";
+
+const PREFIX: &str = "ENC[";
+const SUFFIX: &str = "]";
+
+/// The width of the lines of an armored age file.
+const ARMOR_WIDTH: usize = 64;
+
+/// How many random names are tried for the directory of a ciphertext file before giving up.
+const CIPHERTEXT_ATTEMPTS: usize = 16;
+
+/// Represents how the encrypted values of the declaration are decrypted.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct EncryptionDeclaration {
+    /// The age identity file, relative to the workspace root. [`IDENTITY_ENV`] takes
+    /// precedence when set.
+    pub identity: Option<PathBuf>,
+    /// A shell command printing the plaintext of the ciphertext in the file at
+    /// [`CIPHERTEXT_ENV`], used instead of `age`.
+    pub command: Option<String>,
+}
+
+impl EncryptionDeclaration {
+    /// Builds the decrypter of a workspace rooted at `root`, running its tools with `runner`.
+    pub fn decrypter<'r>(&self, root: &Path, runner: &'r dyn ProcessRunner) -> Decrypter<'r> {
+        let identity = std::env::var_os(IDENTITY_ENV)
+            .map(PathBuf::from)
+            .or_else(|| self.identity.as_ref().map(|identity| root.join(identity)));
+
+        let key = match (&self.command, identity) {
+            (Some(command), _) => Some(KeySource::Command(command.clone())),
+            (None, Some(identity)) => Some(KeySource::Identity(identity)),
+            (None, None) => None,
+        };
+
+        Decrypter { key, runner }
+    }
+}
+
+/// Returns `true` if `value` is written as an encrypted value.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX) && value.ends_with(SUFFIX)
+}
+
+/// Where the key decrypting the values comes from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum KeySource {
+    /// An age identity file.
+    Identity(PathBuf),
+    /// A shell command doing the decryption.
+    Command(String),
+}
+
+/// Decrypts the `ENC[...]` values of a declaration.
+///
+/// A decrypter without a key source only fails once it meets an encrypted value, so
+/// declarations without any don't need one.
+pub struct Decrypter<'r> {
+    key: Option<KeySource>,
+    runner: &'r dyn ProcessRunner,
+}
+
+impl Decrypter<'_> {
+    /// Returns the plaintext of `value`, or `value` itself if it isn't encrypted.
+    ///
+    /// # Parameters
+    /// - `name`: The key of the value in the declaration, e.g. `constants.webhook`, for the
+    ///   errors.
+    /// - `value`: The value, encrypted or not.
+    ///
+    /// # Returns
+    /// - `Ok(String)`: The plaintext, without its trailing line break.
+    /// - `Err(EncryptionError)`: If the value is encrypted and there is no key source, or it
+    ///   could not be decrypted.
+    pub fn decrypt(&self, name: &str, value: &str) -> Result<String, EncryptionError> {
+        if !is_encrypted(value) {
+            return Ok(value.to_owned());
+        }
+
+        let ciphertext = &value[PREFIX.len()..value.len() - SUFFIX.len()];
+
+        let output = match &self.key {
+            None => return Err(EncryptionError::NoKey(name.to_owned())),
+            Some(KeySource::Identity(identity)) => {
+                let mut command = Command::new("age");
+                command
+                    .arg("--decrypt")
+                    .arg("--identity")
+                    .arg(identity)
+                    .arg("-");
+
+                self.runner
+                    .run_with_input(&mut command, armor(ciphertext).as_bytes())
+            }
+            Some(KeySource::Command(command)) => {
+                let file = PrivateFile::create(ciphertext.as_bytes())?;
+
+                let mut command = shell_command(command);
+                command.env(CIPHERTEXT_ENV, &file.path);
+
+                self.runner.run(&mut command)
+            }
+        };
+
+        let output =
+            output.map_err(|err| EncryptionError::Decrypt(name.to_owned(), err.to_string()))?;
+
+        if !output.is_success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            return Err(EncryptionError::Decrypt(name.to_owned(), stderr));
+        }
+
+        let plaintext = String::from_utf8(output.stdout)
+            .map_err(|_| EncryptionError::Decrypt(name.to_owned(), "not UTF-8".to_owned()))?;
+
+        Ok(plaintext.trim_end_matches(['\r', '\n']).to_owned())
+    }
+
+    /// Decrypts the values of `declaration` that may be encrypted: the constants, and the URL
+    /// and token of the remote cache.
+    pub fn decrypt_declaration(
+        &self,
+        declaration: &mut WorkspaceDeclaration,
+    ) -> Result<(), EncryptionError> {
+        for (name, value) in &mut declaration.constants {
+            *value = self.decrypt(&format!("constants.{name}"), value)?;
+        }
+
+        if let Some(remote) = &mut declaration.cache.remote {
+            remote.url = self.decrypt("cache.remote.url", &remote.url)?;
+
+            if let Some(token) = &mut remote.token {
+                *token = self.decrypt("cache.remote.token", token)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A file only the current user can access, in a directory of its own with a random name,
+/// removed with its directory when dropped.
+///
+/// The directory is created rather than reused, so a file or link planted at its path makes
+/// the creation fail instead of being written through.
+struct PrivateFile {
+    directory: PathBuf,
+    path: PathBuf,
+}
+
+impl PrivateFile {
+    fn create(content: &[u8]) -> Result<Self, EncryptionError> {
+        let root = std::env::temp_dir();
+        let mut attempts = 0;
+
+        let directory = loop {
+            let directory = root.join(format!("parmenides-ciphertext-{:016x}", random()));
+
+            let mut builder = DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+            match builder.create(&directory) {
+                Ok(()) => break directory,
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    attempts += 1;
+
+                    if attempts == CIPHERTEXT_ATTEMPTS {
+                        return Err(EncryptionError::Io(directory, err));
+                    }
+                }
+                Err(err) => return Err(EncryptionError::Io(directory, err)),
+            }
+        };
+
+        let file = Self {
+            path: directory.join("ciphertext"),
+            directory,
+        };
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        options
+            .open(&file.path)
+            .and_then(|mut opened| opened.write_all(content))
+            .map_err(|err| EncryptionError::Io(file.path.clone(), err))?;
+
+        Ok(file)
+    }
+}
+
+impl Drop for PrivateFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// Returns a random number, from the keys the standard library seeds its hash maps with, which
+/// differ for every call.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Wraps the body of an armored age file back in its header and footer.
+fn armor(body: &str) -> String {
+    let body: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut armored = String::from("-----BEGIN AGE ENCRYPTED FILE-----\n");
+
+    for line in body.as_bytes().chunks(ARMOR_WIDTH) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+
+    armored.push_str("-----END AGE ENCRYPTED FILE-----\n");
+    armored
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::EncryptionError;
+    use crate::process::{ProcessOutput, ScriptedProcessRunner};
+
+    use super::{armor, EncryptionDeclaration};
+
+    #[test]
+    pub fn when_loading_encrypted_values_should_decrypt_them_with_the_identity() {
+        let runner = ScriptedProcessRunner::new().with_output(
+            "age",
+            ProcessOutput::success("https://hooks.example.com/1\n"),
+        );
+
+        let encryption = EncryptionDeclaration {
+            identity: Some("keys/age.txt".into()),
+            command: None,
+        };
+        let decrypter = encryption.decrypter(Path::new("/workspace"), &runner);
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.constants = HashMap::from([
+            ("webhook".to_owned(), "ENC[YWdl]".to_owned()),
+            ("registry".to_owned(), "registry.example.com".to_owned()),
+        ]);
+
+        let decrypted = decrypter.decrypt_declaration(&mut declaration);
+
+        assert!(decrypted.is_ok());
+        assert_eq!(
+            declaration.constants["webhook"],
+            "https://hooks.example.com/1"
+        );
+        assert_eq!(declaration.constants["registry"], "registry.example.com");
+
+        let identity = Path::new("/workspace").join("keys/age.txt");
+        assert_eq!(
+            runner.commands(),
+            [[
+                "age",
+                "--decrypt",
+                "--identity",
+                &identity.to_string_lossy(),
+                "-"
+            ]]
+        );
+        assert_eq!(runner.inputs(), [armor("YWdl").into_bytes()]);
+    }
+
+    #[test]
+    pub fn when_decrypting_without_key_should_return_no_key_error() {
+        let runner = ScriptedProcessRunner::new();
+
+        let decrypter =
+            EncryptionDeclaration::default().decrypter(Path::new("/workspace"), &runner);
+
+        let plain = decrypter.decrypt("constants.registry", "registry.example.com");
+        let encrypted = decrypter.decrypt("constants.webhook", "ENC[YWdl]");
+
+        assert_eq!(plain.unwrap(), "registry.example.com");
+        assert!(
+            matches!(encrypted, Err(EncryptionError::NoKey(name)) if name == "constants.webhook")
+        );
+        assert!(runner.commands().is_empty());
+    }
+
+    #[test]
+    pub fn when_armoring_ciphertext_should_wrap_it_in_header_and_lines() {
+        assert_eq!(
+            armor(&"a".repeat(70)),
+            format!(
+                "-----BEGIN AGE ENCRYPTED FILE-----\n{}\naaaaaa\n-----END AGE ENCRYPTED FILE-----\n",
+                "a".repeat(64)
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_decrypting_with_command_should_hand_it_a_private_file() {
+        let runner = crate::process::SystemProcessRunner::new();
+
+        let encryption = EncryptionDeclaration {
+            identity: None,
+            command: Some(
+                r#"ls -l "$PARMENIDES_CIPHERTEXT_FILE" | cut -c1-10; cat "$PARMENIDES_CIPHERTEXT_FILE"; echo " $(dirname "$PARMENIDES_CIPHERTEXT_FILE")""#
+                    .to_owned(),
+            ),
+        };
+
+        let decrypted = encryption
+            .decrypter(Path::new("/workspace"), &runner)
+            .decrypt("constants.webhook", "ENC[YWdl]")
+            .unwrap();
+
+        let (mode, rest) = decrypted.split_once('\n').unwrap();
+        let (ciphertext, directory) = rest.split_once(' ').unwrap();
+
+        assert_eq!(mode, "-rw-------");
+        assert_eq!(ciphertext, "YWdl");
+        assert!(!Path::new(directory).exists());
+    }
+}
@@ -3,14 +3,17 @@
 //! This module defines the error types used throughout `parmenides-lib` to handle various failure
 //! scenarios gracefully. Each error type is specific to a part of the library and is designed to
 //! provide detailed and actionable error messages.
+use std::fmt::Display;
 use std::path::PathBuf;
 
 use thiserror::Error;
 
-use crate::project::ProjectId;
+use crate::project::{ProjectId, StableId};
+use crate::unknown_keys::UnknownKey;
 
 /// Errors that can occur while adding a project to the [`crate::workspace::Workspace`].
 #[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum AddProjectError {
     /// Indicates that a project with the same path has already been added to the workspace.
     #[error("Path already added with id {0}")]
@@ -18,19 +21,52 @@ pub enum AddProjectError {
     /// Indicates that a dependency specified for a project could not be found in the workspace.
     #[error("The dependency {0} was not found in the workspace")]
     DepedencyNotFound(ProjectId),
+    /// Indicates that another project already has the same identifier.
+    #[error("The identifier {0} is already used by project {1}")]
+    IdentifierAlreadyAdded(String, ProjectId),
+    /// Indicates that another project already has the same stable ID, e.g. as its path only
+    /// differs in `.` or `..` components.
+    #[error("The stable id {0} is already used by project {1}")]
+    StableIdAlreadyAdded(StableId, ProjectId),
 }
 
 /// Errors that can occur while marking a project as affected in the [`crate::workspace::Workspace`].
 #[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum MarkProjectAsAffectedError {
     /// Indicates that the specified project could not be found in the workspace.
     #[error("Project {0} not found")]
     ProjectNotFound(ProjectId),
 }
 
+/// Errors that can occur while removing a project from the [`crate::workspace::Workspace`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum RemoveProjectError {
+    /// Indicates that the specified project could not be found in the workspace.
+    #[error("Project {0} not found")]
+    ProjectNotFound(ProjectId),
+    /// Indicates that other projects still depend on the project, given with its direct
+    /// dependents.
+    #[error("Project {0} is still a dependency of {1:?}")]
+    HasDependents(ProjectId, Vec<ProjectId>),
+}
+
+/// Errors that can occur while sorting the projects of a [`crate::workspace::Workspace`] in
+/// dependency order.
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum TopologicalOrderError {
+    /// Indicates that the workspace contains a dependency cycle. The paths start and end with
+    /// the same project.
+    #[error("A cyclic dependency with the path {0:?} was found")]
+    CyclicDependencyFound(Vec<PathBuf>),
+}
+
 /// Errors that can occur while building a [`crate::workspace::Workspace`] from a
 /// [`crate::declarations::WorkspaceDeclaration`].
 #[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum BuildWorkspaceError {
     /// Indicates that adding a project to the workspace failed.
     #[error("Error while adding project {0}: {1}")]
@@ -41,4 +77,715 @@ pub enum BuildWorkspaceError {
     /// Indicates that a cyclic dependency was detected while resolving project dependencies.
     #[error("A cyclic dependency with the path {0:?} was found")]
     CyclicDependencyFound(Vec<PathBuf>),
+    /// Indicates that the declared identifier of a project has characters not allowed in
+    /// identifiers, see [`crate::project::is_valid_identifier`].
+    #[error("The identifier {1} of the project {0} may only contain letters, digits, -, _ and .")]
+    InvalidIdentifier(PathBuf, String),
+    /// Indicates that a project encapsulates a path that isn't one of its dependencies.
+    #[error("The project {0} encapsulates {1}, which is not one of its dependencies")]
+    EncapsulatesNonDependency(PathBuf, PathBuf),
+    /// Indicates that a project declares the kind of a path that isn't one of its dependencies.
+    #[error("The project {0} declares the kind of {1}, which is not one of its dependencies")]
+    KindOfNonDependency(PathBuf, PathBuf),
+    /// Indicates that the contract of a project names a target it doesn't define.
+    #[error("The contract of the project {0} is the target {1}, which it doesn't define")]
+    UnknownContractTarget(PathBuf, String),
+    /// Indicates that an input glob of a project is invalid.
+    #[error("The inputs of the project {0} are invalid: {1}")]
+    InvalidInputs(PathBuf, InputsError),
+    /// Indicates that a trigger glob of the workspace is invalid.
+    #[error("The triggers of the workspace are invalid: {0}")]
+    InvalidTriggers(InputsError),
+    /// Indicates that the declared affected strategy isn't a built-in one, see
+    /// [`crate::affected::builtin_strategy`].
+    #[error(
+        "The affected strategy {0} is unknown, expected transitive_dependents or changed_only"
+    )]
+    UnknownAffectedStrategy(String),
+    /// Indicates that the sunset date of a deprecated project isn't formatted as `YYYY-MM-DD`.
+    #[error("The sunset date {1} of the project {0} is not a YYYY-MM-DD date")]
+    InvalidSunset(PathBuf, String),
+    /// Indicates that a deprecated project is replaced by itself, or by an undeclared project.
+    #[error("The project {0} is replaced by {1}, which is not another declared project")]
+    InvalidReplacement(PathBuf, PathBuf),
+}
+
+/// Errors that can occur while parsing a `key=value` argument into the
+/// [`crate::parameters::Parameters`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ParseArgumentError {
+    /// Indicates that the argument does not contain a `=` separator.
+    #[error("The argument {0:?} is not in the key=value format")]
+    MissingSeparator(String),
+    /// Indicates that the argument has an empty key.
+    #[error("The argument {0:?} has an empty key")]
+    EmptyKey(String),
+}
+
+/// Errors that can occur while interpolating a template with the
+/// [`crate::parameters::Parameters`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum InterpolateError {
+    /// Indicates that the template references a parameter that was not defined.
+    #[error("The parameter {0:?} is not defined")]
+    UnknownParameter(String),
+    /// Indicates that a `{{` placeholder was opened but never closed.
+    #[error("The placeholder starting at byte {0} is not closed")]
+    UnclosedPlaceholder(usize),
+}
+
+/// Errors that can occur while compiling the [`crate::inputs::Inputs`] of a project.
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum InputsError {
+    /// Indicates that a glob has an invalid character class, e.g. `[z-a]`.
+    #[error("The input glob {0:?} is invalid: {1}")]
+    InvalidGlob(String, regex::Error),
+}
+
+/// Errors that can occur while configuring a [`crate::redaction::Redactor`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum RedactionError {
+    /// Indicates that a redaction pattern is not a valid regular expression.
+    #[error("The redaction pattern {0:?} is invalid: {1}")]
+    InvalidPattern(String, regex::Error),
+}
+
+/// Errors that can occur while tracing the files accessed by a command with
+/// [`crate::trace::trace_command`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum TraceError {
+    /// Indicates that `strace` could not be started, usually because it is not installed.
+    #[error("Could not start strace: {0}")]
+    TracerNotAvailable(std::io::Error),
+    /// Indicates that reading the trace log failed.
+    #[error("Could not read the trace log {0}: {1}")]
+    ReadLog(PathBuf, std::io::Error),
+}
+
+/// Errors that can occur while parsing a [`crate::selector::Selector`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SelectorError {
+    /// Indicates that a term of the selector is empty, e.g. `tag:ui &`.
+    #[error("The selector {0:?} contains an empty term")]
+    EmptyTerm(String),
+    /// Indicates that a term doesn't have the `kind:pattern` form.
+    #[error("The selector term {0:?} is missing a kind, e.g. tag:{0}")]
+    MissingKind(String),
+    /// Indicates that a term uses a kind other than `tag`, `name`, or `path`.
+    #[error("Unknown selector kind {0:?}, expected tag, name, or path")]
+    UnknownKind(String),
+}
+
+/// Errors that can occur while configuring a [`crate::lint::Linter`] from a
+/// [`crate::declarations::LintDeclaration`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum LintConfigError {
+    /// Indicates that a custom rule uses an invalid selector.
+    #[error("The custom rule {0} has an invalid selector: {1}")]
+    InvalidSelector(String, SelectorError),
+}
+
+/// Errors that can occur while creating a [`crate::constraints::Constraint`] from its
+/// declaration.
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ConstraintError {
+    /// Indicates that a constraint uses an invalid selector.
+    #[error("The constraint {0} has an invalid selector: {1}")]
+    InvalidSelector(String, SelectorError),
+    /// Indicates that a constraint declares neither allowed nor forbidden projects.
+    #[error("The constraint {0} declares neither allowed nor forbidden projects")]
+    Unrestricted(String),
+}
+
+/// Errors that can occur while reading or writing a [`crate::lint::Baseline`] file.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum BaselineError {
+    /// Indicates that the baseline file could not be read or written.
+    #[error("Could not access the baseline file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the baseline file is not valid.
+    #[error("The baseline file {0} is invalid: {1}")]
+    Parse(PathBuf, serde_json::Error),
+}
+
+/// Errors that can occur while reading or writing a [`crate::shard::Durations`] file.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DurationsError {
+    /// Indicates that the durations file could not be read or written.
+    #[error("Could not access the durations file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the durations file is not valid.
+    #[error("The durations file {0} is invalid: {1}")]
+    Parse(PathBuf, serde_json::Error),
+}
+
+/// Errors that can occur while computing the affected projects with
+/// [`crate::affected::compute_affected`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ComputeAffectedError {
+    /// Indicates that the diff engine failed to compute the changed paths.
+    #[error("Could not compute the changed paths: {0}")]
+    Diff(#[from] DiffEngineError),
+    /// Indicates that marking an owning project as affected failed.
+    #[error("Could not mark a project as affected: {0}")]
+    MarkProjectAsAffected(#[from] MarkProjectAsAffectedError),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+}
+
+/// Errors that can occur while computing the projects affected by a past commit with
+/// [`crate::affected::compute_merge_affected`].
+#[cfg(feature = "git")]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ComputeMergeAffectedError {
+    /// Indicates that the commit is the root of the history, so it has nothing to diff from.
+    #[error("The commit {0} has no parent")]
+    NoParent(String),
+    /// Indicates that the declaration could not be loaded from the commit.
+    #[error("Could not load the declaration of {0}: {1}")]
+    Load(String, LoadDeclarationError),
+    /// Indicates that the projects of the preset of the declaration could not be discovered at
+    /// the commit.
+    #[error("Could not discover the projects of {0}: {1}")]
+    Discovery(String, DiscoveryError),
+    /// Indicates that the workspace could not be built from the declaration of the commit.
+    #[error("Could not build the workspace of {0}: {1}")]
+    BuildWorkspace(String, BuildWorkspaceError),
+    /// Indicates that resolving the commit or diffing it failed.
+    #[error(transparent)]
+    Diff(#[from] DiffEngineError),
+    #[error(transparent)]
+    ComputeAffected(#[from] ComputeAffectedError),
+}
+
+/// Errors that can occur while walking the commits affecting a project with
+/// [`crate::bisect::affecting_commits`].
+#[cfg(feature = "git")]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum BisectError {
+    /// Indicates that no project has the given identifier at the commit the walk starts from.
+    #[error("Could not find a project with the identifier {0} at {1}")]
+    UnknownProject(String, String),
+    #[error(transparent)]
+    ComputeMergeAffected(#[from] ComputeMergeAffectedError),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+}
+
+/// Errors that can occur while applying [`crate::edit::DeclarationEdit`]s to a declaration
+/// file.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum EditDeclarationError {
+    /// Indicates that the declaration file could not be read or written.
+    #[error("Could not access the declaration file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the declaration file is not valid TOML.
+    #[error("The declaration is not valid TOML: {0}")]
+    ParseToml(#[from] toml_edit::TomlError),
+    /// Indicates that the declaration file is not valid YAML.
+    #[error("The declaration is not valid YAML: {0}")]
+    ParseYaml(#[from] serde_yaml::Error),
+    /// Indicates that an edit references a project that is not declared in the file.
+    #[error("The project {0} is not declared in the file")]
+    ProjectNotFound(PathBuf),
+    /// Indicates that a target can't be renamed, as the project already has one with the new
+    /// name.
+    #[error("The project {0} already has a target named {1}")]
+    TargetExists(PathBuf, String),
+    /// Indicates that a YAML declaration can't be edited without rewriting more of it than the
+    /// edits change, e.g. a block scalar, so it is left as is.
+    #[error("The YAML declaration can't be edited in place: {0}")]
+    UnsupportedYaml(String),
+    /// Indicates that the file extension is neither `toml` nor `yaml` or `yml`.
+    #[error("The declaration file {0} has an unsupported format")]
+    UnsupportedFormat(PathBuf),
+}
+
+/// A position in a declaration file, e.g. of a parse error.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct SourceLocation {
+    /// The 1-based line of the error.
+    pub line: usize,
+    /// The 1-based column of the error.
+    pub column: usize,
+    /// The content of the offending line.
+    pub text: String,
+}
+
+impl SourceLocation {
+    /// Computes the location of a byte offset in the source.
+    pub fn from_offset(source: &str, offset: usize) -> Self {
+        let offset = offset.min(source.len());
+        let before = &source[..offset];
+
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |index| offset + index);
+
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            text: source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+        }
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+fn display_location(location: &Option<SourceLocation>) -> String {
+    match location {
+        Some(location) => format!(" at {location}"),
+        None => String::new(),
+    }
+}
+
+fn display_unknown_keys(keys: &[UnknownKey]) -> String {
+    keys.iter().map(|key| format!("\n  {key}")).collect()
+}
+
+fn display_snippet(location: &Option<SourceLocation>) -> String {
+    match location {
+        Some(location) => format!("\n{:>5} | {}", location.line, location.text),
+        None => String::new(),
+    }
+}
+
+/// Errors that can occur while loading a [`crate::declarations::WorkspaceDeclaration`] from a
+/// file or a string.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum LoadDeclarationError {
+    /// Indicates that the declaration file could not be read.
+    #[error("Could not read the declaration file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the file extension doesn't match any supported format.
+    #[error("The declaration file {0} has an unsupported format")]
+    UnsupportedFormat(PathBuf),
+    /// Indicates that the declaration is not valid. The location points at the offending line
+    /// when the parser reports one.
+    #[error(
+        "The declaration is invalid{}: {message}{}",
+        display_location(.location),
+        display_snippet(.location)
+    )]
+    Parse {
+        message: String,
+        location: Option<SourceLocation>,
+    },
+    /// Indicates that the declaration has keys outside its schema, and unknown keys are denied.
+    #[error("The declaration has unknown keys:{}", display_unknown_keys(.0))]
+    UnknownKeys(Vec<UnknownKey>),
+}
+
+/// Errors that can occur while generating a project with
+/// [`crate::generate::generate_project`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum GenerateError {
+    /// Indicates that no generator with the given name is declared.
+    #[error("The generator {0} is not declared")]
+    UnknownGenerator(String),
+    /// Indicates that the destination already exists and is not an empty directory.
+    #[error("The destination {0} already exists and is not empty")]
+    DestinationNotEmpty(PathBuf),
+    /// Indicates that reading the template or writing the project failed.
+    #[error("Could not generate {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that a template file name or content references an unknown parameter.
+    #[error("Could not interpolate the template {0}: {1}")]
+    Interpolate(PathBuf, InterpolateError),
+}
+
+/// Errors that can occur while moving a project with [`crate::refactor::move_project`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum MoveProjectError {
+    /// Indicates that the destination already exists.
+    #[error("The destination {0} already exists")]
+    DestinationExists(PathBuf),
+    /// Indicates that the declaration could not be updated.
+    #[error("Could not update the declaration: {0}")]
+    Edit(#[from] EditDeclarationError),
+    /// Indicates that moving the project directory failed.
+    #[error("Could not move {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+}
+
+/// Errors that can occur while discovering the projects of a repository with a discovery
+/// backend, see the `discovery` module.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DiscoveryError {
+    /// Indicates that a manifest or directory could not be read.
+    #[error("Could not read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that a manifest is not valid.
+    #[error("The manifest {0} is invalid: {1}")]
+    InvalidManifest(PathBuf, String),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+    /// Indicates that the declaration has a preset, but the crate was built without the
+    /// `discovery` feature.
+    #[error("Presets need the discovery feature")]
+    Disabled,
+}
+
+/// Errors that can occur while extracting a project with
+/// [`crate::refactor::extract_project`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ExtractProjectError {
+    /// Indicates that the project is not declared.
+    #[error("The project {0} is not declared")]
+    ProjectNotFound(PathBuf),
+    /// Indicates that the destination already exists and is not an empty directory.
+    #[error("The destination {0} already exists and is not empty")]
+    DestinationNotEmpty(PathBuf),
+    /// Indicates that the declaration file could not be loaded.
+    #[error("Could not load the declaration: {0}")]
+    Load(#[from] LoadDeclarationError),
+    /// Indicates that the extracted declaration could not be serialized.
+    #[error("Could not serialize the extracted declaration: {0}")]
+    Serialize(String),
+    /// Indicates that copying the project files or writing the declaration failed.
+    #[error("Could not extract {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+}
+
+/// Errors that can occur while a [`crate::diff_engine::DiffEngine`] computes the changed paths.
+///
+/// The variants describe failure modes shared by every engine. Engines add a variant here for
+/// failures of their own rather than flattening them into strings. The git variants carry the
+/// message of the library, so the libgit2 and gitoxide engines report the same variants.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DiffEngineError {
+    /// Indicates that the repository could not be found or opened. The message comes from the
+    /// backend of the engine, so every backend reports it the same way.
+    #[error("Could not open the repository {0}: {1}")]
+    Repository(PathBuf, String),
+    /// Indicates that a revision could not be resolved to a tree.
+    #[error("Could not resolve the revision {0}: {1}")]
+    Revision(String, String),
+    /// Indicates that the two revisions have no common ancestor.
+    #[error("Could not find the merge base of {0} and {1}: {2}")]
+    MergeBase(String, String, String),
+    /// Indicates that a revision, or the merge base of a range, could not be found in a
+    /// shallow clone, whose history may not reach it. See
+    /// [`crate::diff_engine::GitDiffEngine::deepen`].
+    #[cfg(feature = "git")]
+    #[error(
+        "Could not find {0} in the history of this shallow clone. Fetch more of it, e.g. with \
+         `git fetch --deepen=100` or `git fetch --unshallow`, or clone with a larger depth"
+    )]
+    Shallow(String),
+    /// Indicates that fetching more history from a remote failed.
+    #[cfg(feature = "git")]
+    #[error("Could not fetch from the remote {0}: {1}")]
+    Fetch(String, String),
+    /// Indicates that git failed while computing the diff.
+    #[error("Could not compute the diff: {0}")]
+    Git(String),
+    /// Indicates that reading from or writing to the file system failed.
+    #[error("Could not access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that a recorded [`crate::diff_engine::Manifest`] is not valid.
+    #[error("The manifest {0} is not valid: {1}")]
+    Manifest(PathBuf, String),
+
+    /// Indicates that the diff ran longer than its timeout.
+    #[error("The diff timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+}
+
+/// Errors that can occur while reading the relations of a service catalog with
+/// [`crate::export::backstage_relations`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ImportCatalogError {
+    /// Indicates that the catalog is not valid YAML, or an entity has no name.
+    #[error("The catalog is not valid: {0}")]
+    Parse(String),
+}
+
+/// Errors that can occur while rendering a graph with [`crate::export::to_svg`].
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum RenderError {
+    /// Indicates that the layout engine could not process the graph.
+    #[error("Could not lay out the graph: {0}")]
+    Layout(String),
+}
+
+/// Errors that can occur while recording or reading [`crate::stats`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum StatsError {
+    /// Indicates that the statistics file could not be accessed.
+    #[error("Could not access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    /// Indicates that a line of the statistics file is not a record.
+    #[error("Could not parse line {1} of {0}: {2}")]
+    Parse(PathBuf, usize, String),
+}
+
+/// Errors that can occur while recording or reading the [`crate::health`] reports.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum HealthError {
+    /// Indicates that the health file could not be accessed.
+    #[error("Could not access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    /// Indicates that a line of the health file is not a report.
+    #[error("Could not parse line {1} of {0}: {2}")]
+    Parse(PathBuf, usize, String),
+}
+
+/// Errors that can occur while saving or loading a workspace snapshot, see
+/// `crate::snapshot` (feature `snapshot`).
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    /// Indicates that the snapshot file could not be accessed.
+    #[error("Could not access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    /// Indicates that the snapshot file is corrupted.
+    #[error("Could not load the snapshot {0}: {1}")]
+    Invalid(PathBuf, String),
+}
+
+/// Errors that can occur while watching the workspace with a [`crate::watch::Watcher`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum WatchError {
+    /// Indicates that the `watchman` executable could not be run.
+    #[error("Watchman is not available: {0}")]
+    WatchmanUnavailable(std::io::Error),
+    /// Indicates that Watchman reported an error or sent an unexpected response.
+    #[error("Watchman failed: {0}")]
+    Watchman(String),
+    /// Indicates that scanning the workspace failed.
+    #[error("Could not watch {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the file system notifications failed, or aren't built in.
+    #[error("File system notifications failed: {0}")]
+    Notify(String),
+    #[error(transparent)]
+    MarkProjectAsAffected(#[from] MarkProjectAsAffectedError),
+}
+
+/// Errors that can occur while checking determinism with
+/// [`crate::determinism::find_nondeterministic_projects`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum DeterminismError {
+    /// Indicates that the command could not be run in the project directory.
+    #[error("Could not run the command in {0}: {1}")]
+    Run(PathBuf, std::io::Error),
+
+    /// Indicates that the command was killed in the project directory after running longer
+    /// than its timeout.
+    #[error("The command timed out in {0}")]
+    Timeout(PathBuf),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+}
+
+/// Errors that can occur while running a target with [`crate::tasks::TaskRunner::run`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum TaskError {
+    /// Indicates that the command could not be spawned in the project directory.
+    #[error("Could not run the command in {0}: {1}")]
+    Run(PathBuf, std::io::Error),
+
+    /// Indicates that the command of a project references an undefined parameter, or has an
+    /// unclosed placeholder.
+    #[error("Could not interpolate the command of {0}: {1}")]
+    Interpolate(PathBuf, InterpolateError),
+
+    #[error(transparent)]
+    TopologicalOrder(#[from] TopologicalOrderError),
+
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+}
+
+/// Errors that can occur while accessing the task cache.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum CacheError {
+    /// Indicates that a write was attempted through a read-only [`crate::cache::CacheScope`].
+    #[error("The cache is read-only")]
+    ReadOnly,
+
+    /// Indicates that a key would be stored outside of the cache.
+    #[error("Invalid cache key {0}")]
+    InvalidKey(String),
+
+    /// Indicates that an entry could not be decoded.
+    #[error("Could not decode the cache entry {0}: {1}")]
+    Corrupt(String, String),
+
+    /// Indicates that reading an input or accessing the store failed.
+    #[error("Could not access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    /// Indicates that an entry failed the signature check, see
+    /// [`crate::cache::signing::EntrySigning`].
+    #[cfg(feature = "signing")]
+    #[error("Rejected the cache entry {0}: {1}")]
+    Untrusted(String, CacheSignatureError),
+
+    /// Indicates that the operation was cancelled through its [`crate::context::Context`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+
+    #[error(transparent)]
+    Remote(#[from] RemoteCacheError),
+}
+
+/// Errors that can occur while calling a remote with a [`crate::http::HttpClient`].
+#[cfg(feature = "http")]
+#[derive(Error, Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum HttpError {
+    /// Indicates that the remote could not be reached on any attempt, e.g. it is down or timed
+    /// out.
+    #[error("Could not reach {0}: {1}")]
+    Unreachable(String, String),
+
+    /// Indicates that the remote kept answering `429 Too Many Requests`, or asked to wait for
+    /// longer than the retry policy allows.
+    #[error("{0} is rate limited")]
+    RateLimited(String, Option<std::time::Duration>),
+
+    /// Indicates that the proxy URL is not valid.
+    #[error("Invalid proxy {0}: {1}")]
+    InvalidProxy(String, String),
+}
+
+/// Errors that can occur while resolving a token with a
+/// [`crate::credentials::CredentialChain`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum CredentialError {
+    /// Indicates that the credentials file exists but could not be read.
+    #[error("Could not read the credentials file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the credentials file is not a TOML table of tokens.
+    #[error("The credentials file {0} is not valid: {1}")]
+    Parse(PathBuf, String),
+    /// Indicates that the helper command could not run or failed.
+    #[error("The credential helper `{0}` failed: {1}")]
+    Helper(String, String),
+}
+
+/// Errors that can occur while decrypting the values of a declaration with a
+/// [`crate::encryption::Decrypter`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum EncryptionError {
+    /// Indicates that a value is encrypted but the declaration has no key source.
+    #[error("{0} is encrypted, but no identity or command is declared in `encryption`")]
+    NoKey(String),
+    /// Indicates that the ciphertext could not be handed to the decryption tool.
+    #[error("Could not write the ciphertext to {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    /// Indicates that the decryption tool could not run or failed.
+    #[error("Could not decrypt {0}: {1}")]
+    Decrypt(String, String),
+}
+
+/// Errors that can occur while calling a [`crate::cache::RemoteCache`].
+#[derive(Error, Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum RemoteCacheError {
+    /// Indicates that the remote could not be reached, e.g. it is down or timed out.
+    #[error("Could not reach the remote cache at {0}: {1}")]
+    Unreachable(String, String),
+
+    /// Indicates that the remote answered with an unexpected HTTP status.
+    #[error("The remote cache answered {1} for {0}")]
+    Status(String, u16),
+
+    /// Indicates that an entry of the remote failed the signature check.
+    #[cfg(feature = "signing")]
+    #[error("Rejected the remote cache entry {0}: {1}")]
+    Untrusted(String, CacheSignatureError),
+}
+
+impl RemoteCacheError {
+    /// Returns `true` if the remote is unavailable rather than refusing the call, so that
+    /// falling back to the local cache is safe.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            RemoteCacheError::Unreachable(..) => true,
+            RemoteCacheError::Status(_, status) => *status >= 500,
+            #[cfg(feature = "signing")]
+            RemoteCacheError::Untrusted(..) => false,
+        }
+    }
+}
+
+/// Errors that can occur while signing or verifying cache artifacts.
+#[derive(Error, Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub enum CacheSignatureError {
+    /// Indicates that a key isn't a valid hex-encoded ed25519 key.
+    #[error("Invalid signing key {0}")]
+    InvalidKey(String),
+
+    /// Indicates that a signature isn't a valid hex-encoded ed25519 signature.
+    #[error("Invalid signature {0}")]
+    InvalidSignature(String),
+
+    /// Indicates that none of the trusted keys produced the signature.
+    #[error("The artifact is not signed by a trusted key")]
+    Untrusted,
+
+    /// Indicates that an entry has no signature, while trusted keys are declared.
+    #[error("The artifact is not signed")]
+    Unsigned,
 }
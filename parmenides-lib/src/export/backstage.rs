@@ -0,0 +1,431 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::drift::DependencyDrift;
+use crate::edit::DeclarationEdit;
+use crate::errors::ImportCatalogError;
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+use super::{Catalog, CatalogEntry};
+
+/// How the projects of a [`Catalog`] map to Backstage components, see [`to_backstage`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct BackstageOptions {
+    /// The tag marking the projects that are services rather than libraries, like the
+    /// `lint.app_tag` of the declaration.
+    pub app_tag: String,
+    /// The owner of the components of projects without owners. Backstage requires one.
+    pub default_owner: String,
+    /// The system every component is part of, if any.
+    pub system: Option<String>,
+}
+
+impl Default for BackstageOptions {
+    fn default() -> Self {
+        Self {
+            app_tag: "app".to_owned(),
+            default_owner: "unknown".to_owned(),
+            system: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entity<'c> {
+    api_version: &'static str,
+    kind: &'static str,
+    metadata: Metadata<'c>,
+    spec: Spec<'c>,
+}
+
+#[derive(Serialize)]
+struct Metadata<'c> {
+    name: &'c str,
+    title: &'c str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'c str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    links: Vec<Link<'c>>,
+    annotations: BTreeMap<&'static str, String>,
+}
+
+#[derive(Serialize)]
+struct Link<'c> {
+    url: &'c str,
+    title: &'c str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Spec<'c> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    lifecycle: &'static str,
+    owner: &'c str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'c str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<String>,
+}
+
+/// Renders a catalog as Backstage `catalog-info.yaml` entities, one `Component` per project
+/// in a multi-document YAML stream, with a `dependsOn` relation per direct dependency.
+///
+/// Projects tagged with [`BackstageOptions::app_tag`] are services, the others libraries, and
+/// deprecated projects have the `deprecated` lifecycle. The first owner of a project owns its
+/// component, without the leading `@` of team handles, and the tags are normalized to the
+/// lowercase words Backstage accepts.
+pub fn to_backstage(catalog: &Catalog, options: &BackstageOptions) -> String {
+    catalog
+        .entries
+        .iter()
+        .map(|entry| {
+            let entity = entity(entry, options);
+
+            // Only strings, lists and maps are serialized, which can't fail.
+            let yaml = serde_yaml::to_string(&entity).unwrap_or_default();
+
+            format!("---\n{yaml}")
+        })
+        .collect()
+}
+
+fn entity<'c>(entry: &'c CatalogEntry, options: &'c BackstageOptions) -> Entity<'c> {
+    let owner = entry
+        .owners
+        .first()
+        .map_or(options.default_owner.as_str(), |owner| {
+            owner.trim_start_matches('@')
+        });
+
+    let mut tags: Vec<String> = entry
+        .tags
+        .iter()
+        .filter_map(|tag| backstage_tag(tag))
+        .collect();
+    tags.dedup();
+
+    Entity {
+        api_version: "backstage.io/v1alpha1",
+        kind: "Component",
+        metadata: Metadata {
+            name: &entry.identifier,
+            title: &entry.name,
+            description: entry.description.as_deref(),
+            tags,
+            links: entry
+                .links
+                .iter()
+                .map(|(title, url)| Link { url, title })
+                .collect(),
+            annotations: BTreeMap::from([(
+                "parmenides.dev/path",
+                entry.path.display().to_string(),
+            )]),
+        },
+        spec: Spec {
+            kind: if entry.tags.contains(&options.app_tag) {
+                "service"
+            } else {
+                "library"
+            },
+            lifecycle: if entry.deprecated {
+                "deprecated"
+            } else {
+                "production"
+            },
+            owner,
+            system: options.system.as_deref(),
+            depends_on: entry
+                .dependencies
+                .iter()
+                .map(|dependency| format!("component:{dependency}"))
+                .collect(),
+        },
+    }
+}
+
+/// The parts of a Backstage entity its relations are read from.
+#[derive(Deserialize)]
+struct ImportedEntity {
+    kind: String,
+    metadata: ImportedMetadata,
+    #[serde(default)]
+    spec: ImportedSpec,
+}
+
+#[derive(Deserialize)]
+struct ImportedMetadata {
+    name: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ImportedSpec {
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+/// Reads the `dependsOn` relations between the components of Backstage entities, such as a
+/// `catalog-info.yaml` maintained by hand, keyed by component name.
+///
+/// Other kinds of entities, and relations to them, are skipped. References may have a
+/// namespace, like `component:default/core`, which is dropped.
+///
+/// # Returns
+/// - `Ok(BTreeMap<String, BTreeSet<String>>)`: The names of the components each one depends on.
+/// - `Err(ImportCatalogError)`: If the entities are not valid YAML or have no name.
+pub fn backstage_relations(
+    yaml: &str,
+) -> Result<BTreeMap<String, BTreeSet<String>>, ImportCatalogError> {
+    let mut relations = BTreeMap::new();
+
+    for document in serde_yaml::Deserializer::from_str(yaml) {
+        let value = serde_yaml::Value::deserialize(document)
+            .map_err(|err| ImportCatalogError::Parse(err.to_string()))?;
+
+        if value.is_null() {
+            continue;
+        }
+
+        let entity: ImportedEntity = serde_yaml::from_value(value)
+            .map_err(|err| ImportCatalogError::Parse(err.to_string()))?;
+
+        if !entity.kind.eq_ignore_ascii_case("component") {
+            continue;
+        }
+
+        let dependencies = entity
+            .spec
+            .depends_on
+            .iter()
+            .filter_map(|reference| {
+                let (kind, name) = reference
+                    .split_once(':')
+                    .unwrap_or(("component", reference));
+                let name = name.rsplit('/').next().unwrap_or(name);
+
+                kind.eq_ignore_ascii_case("component")
+                    .then(|| name.to_owned())
+            })
+            .collect();
+
+        relations.insert(entity.metadata.name, dependencies);
+    }
+
+    Ok(relations)
+}
+
+/// The differences between the relations a service catalog declares and the dependencies of
+/// the workspace, see [`CatalogDrift::detect`].
+#[derive(Debug, PartialEq, Default)]
+pub struct CatalogDrift {
+    /// Relations `(project, dependency)` of the catalog the workspace doesn't have.
+    pub absent: Vec<(ProjectId, ProjectId)>,
+    /// Dependencies `(project, dependency)` of the workspace the catalog doesn't declare.
+    pub undeclared: Vec<(ProjectId, ProjectId)>,
+    /// The components of the catalog matching no project identifier, sorted.
+    pub unknown: Vec<String>,
+}
+
+impl CatalogDrift {
+    /// Compares the relations read with [`backstage_relations`] against the workspace,
+    /// matching components to projects by identifier.
+    ///
+    /// Only the projects with a component are checked, so a catalog covering part of the
+    /// workspace doesn't report the dependencies of the others as undeclared.
+    pub fn detect(workspace: &Workspace, relations: &BTreeMap<String, BTreeSet<String>>) -> Self {
+        let mut unknown = BTreeSet::new();
+        let mut resolve = |name: &String| {
+            let id = workspace.get_id_by_identifier(name);
+
+            if id.is_none() {
+                unknown.insert(name.clone());
+            }
+
+            id
+        };
+
+        let mut declared: BTreeMap<ProjectId, BTreeSet<ProjectId>> = BTreeMap::new();
+
+        for (name, dependencies) in relations {
+            let Some(id) = resolve(name) else {
+                continue;
+            };
+
+            let dependencies = dependencies.iter().filter_map(&mut resolve).collect();
+            declared.insert(id, dependencies);
+        }
+
+        let drift = DependencyDrift::detect(workspace, &declared);
+
+        Self {
+            absent: drift.missing,
+            undeclared: drift.unused,
+            unknown: unknown.into_iter().collect(),
+        }
+    }
+
+    /// Returns `true` if the catalog matches the workspace.
+    pub fn is_empty(&self) -> bool {
+        self.absent.is_empty() && self.undeclared.is_empty() && self.unknown.is_empty()
+    }
+
+    /// Returns the fixes that bring the declaration in line with the catalog, adding the absent
+    /// dependencies and removing the undeclared ones, see [`DependencyDrift::fixes`].
+    pub fn fixes(&self, workspace: &Workspace) -> Vec<DeclarationEdit> {
+        let drift = DependencyDrift {
+            missing: self.absent.clone(),
+            unused: self.undeclared.clone(),
+        };
+
+        drift.fixes(workspace)
+    }
+}
+
+/// Normalizes a tag to the lowercase words separated by `-` Backstage accepts, or `None` if
+/// nothing is left of it.
+fn backstage_tag(tag: &str) -> Option<String> {
+    let words: Vec<String> = tag
+        .split(|char: char| !char.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+
+    (!words.is_empty()).then(|| words.join("-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::declarations::{DeprecationDeclaration, WorkspaceDeclaration};
+    use crate::edit::DeclarationEdit;
+    use crate::export::{Catalog, GraphView};
+    use crate::file_system::MemoryFileSystem;
+
+    use super::{backstage_relations, to_backstage, BackstageOptions, CatalogDrift};
+
+    #[test]
+    pub fn when_exporting_backstage_should_emit_a_component_per_project() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        let core = declaration.add_project("libs/core", "Core", None);
+        core.tags = vec!["Shared Code".to_owned()];
+        core.deprecated = Some(DeprecationDeclaration::default());
+        let web = declaration.add_project("apps/web", "Web", Some(vec!["libs/core".into()]));
+        web.tags = vec!["app".to_owned()];
+        web.owners = vec!["@web-team".to_owned()];
+        web.description = Some("The web application".to_owned());
+        web.links
+            .insert("docs".to_owned(), "https://docs.example.com".to_owned());
+        declaration.resolve_paths("/repo");
+
+        let workspace = declaration.build_workspace().unwrap();
+        let catalog = Catalog::new(
+            &workspace,
+            &GraphView::full(&workspace),
+            Path::new("/repo"),
+            &MemoryFileSystem::new(),
+        );
+
+        assert_eq!(
+            to_backstage(&catalog, &BackstageOptions::default()),
+            "---\n\
+             apiVersion: backstage.io/v1alpha1\n\
+             kind: Component\n\
+             metadata:\n  \
+               name: core\n  \
+               title: Core\n  \
+               tags:\n  \
+               - shared-code\n  \
+               annotations:\n    \
+                 parmenides.dev/path: libs/core\n\
+             spec:\n  \
+               type: library\n  \
+               lifecycle: deprecated\n  \
+               owner: unknown\n\
+             ---\n\
+             apiVersion: backstage.io/v1alpha1\n\
+             kind: Component\n\
+             metadata:\n  \
+               name: web\n  \
+               title: Web\n  \
+               description: The web application\n  \
+               tags:\n  \
+               - app\n  \
+               links:\n  \
+               - url: https://docs.example.com\n    \
+                 title: docs\n  \
+               annotations:\n    \
+                 parmenides.dev/path: apps/web\n\
+             spec:\n  \
+               type: service\n  \
+               lifecycle: production\n  \
+               owner: web-team\n  \
+               dependsOn:\n  \
+               - component:core\n"
+        );
+    }
+
+    #[test]
+    pub fn when_importing_backstage_relations_should_report_drift_from_graph() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("libs/core", "core", None);
+        declaration.add_project("libs/ui", "ui", Some(vec!["libs/core".into()]));
+        declaration.add_project("apps/web", "web", Some(vec!["libs/ui".into()]));
+        declaration.add_project("apps/docs", "docs", Some(vec!["libs/ui".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+        let id = |identifier| workspace.get_id_by_identifier(identifier).unwrap();
+
+        let relations = backstage_relations(
+            "apiVersion: backstage.io/v1alpha1\n\
+             kind: Component\n\
+             metadata:\n  name: web\n\
+             spec:\n  dependsOn:\n  - component:default/core\n  - resource:database\n\
+             ---\n\
+             kind: Component\n\
+             metadata:\n  name: ui\n\
+             spec:\n  dependsOn:\n  - core\n\
+             ---\n\
+             kind: Component\n\
+             metadata:\n  name: legacy\n\
+             ---\n\
+             kind: System\n\
+             metadata:\n  name: shop\n",
+        )
+        .unwrap();
+
+        let drift = CatalogDrift::detect(&workspace, &relations);
+
+        assert_eq!(
+            drift,
+            CatalogDrift {
+                absent: vec![(id("web"), id("core"))],
+                undeclared: vec![(id("web"), id("ui"))],
+                unknown: vec!["legacy".to_owned()],
+            }
+        );
+        assert_eq!(
+            drift.fixes(&workspace),
+            vec![
+                DeclarationEdit::AddDependency {
+                    project: "apps/web".into(),
+                    dependency: "libs/core".into(),
+                },
+                DeclarationEdit::RemoveDependency {
+                    project: "apps/web".into(),
+                    dependency: "libs/ui".into(),
+                },
+            ]
+        );
+        assert!(backstage_relations("kind: Component\nspec: {}\n").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_system::FileSystem;
+use crate::project::ProjectId;
+use crate::sort::natural_cmp;
+use crate::workspace::Workspace;
+
+use super::GraphView;
+
+/// The version of the [`Catalog`] format, bumped on incompatible changes.
+pub const CATALOG_FORMAT_VERSION: u32 = 1;
+
+/// The files the description of a project is read from when it doesn't declare one, in order.
+const READMES: [&str; 3] = ["README.md", "readme.md", "README"];
+
+/// A directory of the projects of a view, combining their metadata with the graph, meant to
+/// publish an internal service catalog that stays up to date with the workspace.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Catalog {
+    pub version: u32,
+    /// The focused projects of the view, sorted naturally by identifier.
+    pub entries: Vec<CatalogEntry>,
+}
+
+/// A project of a [`Catalog`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub identifier: String,
+    pub name: String,
+    /// The path of the project, relative to the workspace root.
+    pub path: PathBuf,
+    /// The declared description, or the first paragraph of the README of the project.
+    pub description: Option<String>,
+    pub owners: Vec<String>,
+    pub tags: Vec<String>,
+    pub links: BTreeMap<String, String>,
+    /// The identifiers of the direct dependencies, sorted naturally.
+    pub dependencies: Vec<String>,
+    /// The identifiers of the direct dependents, sorted naturally.
+    pub dependents: Vec<String>,
+    /// Whether the project is deprecated, see [`crate::project::Deprecation`].
+    pub deprecated: bool,
+}
+
+impl Catalog {
+    /// Builds the catalog of the focused projects of a view.
+    ///
+    /// # Parameters
+    /// - `root`: The workspace root, which paths are made relative to.
+    /// - `fs`: The file system the READMEs are read from, for the projects without a declared
+    ///   description. Unreadable READMEs are skipped.
+    pub fn new(workspace: &Workspace, view: &GraphView, root: &Path, fs: &dyn FileSystem) -> Self {
+        let identifiers = |ids: &[ProjectId]| {
+            let mut identifiers: Vec<String> = ids
+                .iter()
+                .filter_map(|id| workspace.get_project(*id))
+                .map(|project| project.identifier.clone())
+                .collect();
+            identifiers.sort_by(|a, b| natural_cmp(a, b));
+            identifiers
+        };
+
+        let mut entries: Vec<CatalogEntry> = view
+            .focused()
+            .filter_map(|id| workspace.get_project(id))
+            .map(|project| {
+                let description = project.description.clone().or_else(|| {
+                    READMES
+                        .iter()
+                        .find_map(|readme| fs.read_to_string(&project.path.join(readme)).ok())
+                        .and_then(|content| readme_summary(&content))
+                });
+
+                CatalogEntry {
+                    identifier: project.identifier.clone(),
+                    name: project.name.clone(),
+                    path: project
+                        .path
+                        .strip_prefix(root)
+                        .unwrap_or(&project.path)
+                        .to_path_buf(),
+                    description,
+                    owners: project.owners.clone(),
+                    tags: project.tags.clone(),
+                    links: project.links.clone(),
+                    dependencies: identifiers(project.dependencies()),
+                    dependents: identifiers(project.dependents()),
+                    deprecated: project.is_deprecated(),
+                }
+            })
+            .collect();
+
+        entries.sort_by(|a, b| natural_cmp(&a.identifier, &b.identifier));
+
+        Self {
+            version: CATALOG_FORMAT_VERSION,
+            entries,
+        }
+    }
+
+    /// Renders the catalog as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        // Only strings, booleans and numbers are serialized, which can't fail.
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Renders the catalog as a Markdown document, with a section per project.
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::from("# Catalog\n");
+
+        for entry in &self.entries {
+            let _ = write!(output, "\n## {}\n\n", entry.name);
+
+            if let Some(description) = &entry.description {
+                let _ = write!(output, "{description}\n\n");
+            }
+
+            if entry.deprecated {
+                output.push_str("**Deprecated.**\n\n");
+            }
+
+            let list = |items: &[String]| {
+                if items.is_empty() {
+                    "none".to_owned()
+                } else {
+                    items.join(", ")
+                }
+            };
+            let links: Vec<String> = entry
+                .links
+                .iter()
+                .map(|(label, url)| format!("[{label}]({url})"))
+                .collect();
+
+            let _ = writeln!(output, "- Identifier: `{}`", entry.identifier);
+            let _ = writeln!(output, "- Path: `{}`", entry.path.display());
+            let _ = writeln!(output, "- Owners: {}", list(&entry.owners));
+            let _ = writeln!(output, "- Tags: {}", list(&entry.tags));
+
+            if !links.is_empty() {
+                let _ = writeln!(output, "- Links: {}", links.join(", "));
+            }
+
+            let _ = writeln!(output, "- Dependencies: {}", list(&entry.dependencies));
+            let _ = writeln!(output, "- Dependents: {}", list(&entry.dependents));
+        }
+
+        output
+    }
+}
+
+/// Returns the first paragraph of a README, joined into one line, skipping the headings,
+/// badges and HTML before it.
+pub fn readme_summary(content: &str) -> Option<String> {
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        let skipped = line.is_empty()
+            || line.starts_with(['#', '<', '=', '-'])
+            || line.starts_with("![")
+            || line.starts_with("[![");
+
+        if !skipped {
+            paragraph.push(line);
+        } else if !paragraph.is_empty() {
+            break;
+        }
+    }
+
+    (!paragraph.is_empty()).then(|| paragraph.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::GraphView;
+    use crate::file_system::MemoryFileSystem;
+
+    use super::{readme_summary, Catalog};
+
+    #[test]
+    pub fn when_building_catalog_should_combine_metadata_readmes_and_graph() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        let core = declaration.add_project("libs/core", "Core", None);
+        core.description = Some("Shared domain types.".to_owned());
+        core.owners = vec!["@platform".to_owned()];
+        core.links.insert(
+            "docs".to_owned(),
+            "https://docs.example.com/core".to_owned(),
+        );
+        declaration
+            .add_project("apps/web", "Web", Some(vec!["libs/core".into()]))
+            .tags = vec!["app".to_owned()];
+        declaration.resolve_paths("/repo");
+
+        let workspace = declaration.build_workspace().unwrap();
+        let fs = MemoryFileSystem::new().with_file(
+            "/repo/apps/web/README.md",
+            "# Web\n\n[![CI](badge.svg)](ci)\n\nThe customer facing\nweb application.\n\nMore.\n",
+        );
+
+        let catalog = Catalog::new(
+            &workspace,
+            &GraphView::full(&workspace),
+            Path::new("/repo"),
+            &fs,
+        );
+
+        assert_eq!(
+            catalog.to_markdown(),
+            "# Catalog\n\
+             \n\
+             ## Core\n\
+             \n\
+             Shared domain types.\n\
+             \n\
+             - Identifier: `core`\n\
+             - Path: `libs/core`\n\
+             - Owners: @platform\n\
+             - Tags: none\n\
+             - Links: [docs](https://docs.example.com/core)\n\
+             - Dependencies: none\n\
+             - Dependents: web\n\
+             \n\
+             ## Web\n\
+             \n\
+             The customer facing web application.\n\
+             \n\
+             - Identifier: `web`\n\
+             - Path: `apps/web`\n\
+             - Owners: none\n\
+             - Tags: app\n\
+             - Dependencies: core\n\
+             - Dependents: none\n"
+        );
+
+        let json: Catalog = serde_json::from_str(&catalog.to_json()).unwrap();
+
+        assert_eq!(json, catalog);
+        assert_eq!(readme_summary("# Title\n\n"), None);
+    }
+}
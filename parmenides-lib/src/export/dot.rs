@@ -0,0 +1,134 @@
+use std::fmt::Write;
+
+use crate::workspace::Workspace;
+
+use super::{GraphView, NodeRole};
+
+/// The styling of a DOT export.
+#[derive(Debug, Clone)]
+pub struct DotOptions {
+    /// The direction of the layout, e.g. `LR` (left to right) or `TB` (top to bottom).
+    pub rank_direction: String,
+    /// The fill color of affected projects, or `None` to not highlight them.
+    pub affected_color: Option<String>,
+    /// The color of context projects and of the edges between them and the focused ones.
+    pub context_color: String,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self {
+            rank_direction: "LR".to_owned(),
+            affected_color: Some("#f4a261".to_owned()),
+            context_color: "#bbbbbb".to_owned(),
+        }
+    }
+}
+
+/// Renders a view of the workspace in the Graphviz DOT format.
+///
+/// Projects are identified by their path and labeled with their name. Edges point from a
+/// project to its dependencies. Context projects, and the edges reaching them, are dashed and
+/// dimmed.
+pub fn to_dot(workspace: &Workspace, view: &GraphView, options: &DotOptions) -> String {
+    let mut dot = String::new();
+
+    dot.push_str("digraph workspace {\n");
+    let _ = writeln!(dot, "  rankdir={};", options.rank_direction);
+    dot.push_str("  node [shape=box];\n");
+
+    for (id, role) in view.nodes() {
+        let Some(project) = workspace.get_project(id) else {
+            continue;
+        };
+
+        let mut attributes = vec![format!("label={}", quote(&project.name))];
+
+        match role {
+            NodeRole::Context => {
+                attributes.push("style=dashed".to_owned());
+                attributes.push(format!("color={}", quote(&options.context_color)));
+                attributes.push(format!("fontcolor={}", quote(&options.context_color)));
+            }
+            NodeRole::Focus => {
+                if let (true, Some(color)) = (project.affected, &options.affected_color) {
+                    attributes.push("style=filled".to_owned());
+                    attributes.push(format!("fillcolor={}", quote(color)));
+                }
+            }
+        }
+
+        let _ = writeln!(
+            dot,
+            "  {} [{}];",
+            quote(&project.path.to_string_lossy()),
+            attributes.join(", ")
+        );
+    }
+
+    for (from, to) in view.edges(workspace) {
+        let (Some(from_project), Some(to_project)) =
+            (workspace.get_project(from), workspace.get_project(to))
+        else {
+            continue;
+        };
+
+        let dimmed =
+            view.role(from) == Some(NodeRole::Context) || view.role(to) == Some(NodeRole::Context);
+
+        let _ = write!(
+            dot,
+            "  {} -> {}",
+            quote(&from_project.path.to_string_lossy()),
+            quote(&to_project.path.to_string_lossy())
+        );
+
+        if dimmed {
+            let _ = write!(
+                dot,
+                " [style=dashed, color={}]",
+                quote(&options.context_color)
+            );
+        }
+
+        dot.push_str(";\n");
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::GraphView;
+
+    use super::{to_dot, DotOptions};
+
+    #[test]
+    pub fn when_exporting_affected_view_should_dim_context() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("core", "core", None);
+        declaration.add_project("web", "web", Some(vec!["core".into()]));
+
+        let mut workspace = declaration.build_workspace().unwrap();
+        let web = workspace.get_id_by_path(&"web").unwrap();
+
+        workspace.mark_project_as_affected(web).unwrap();
+
+        let view = GraphView::affected_with_context(&workspace);
+        let dot = to_dot(&workspace, &view, &DotOptions::default());
+
+        assert!(dot.starts_with("digraph workspace {\n  rankdir=LR;\n"));
+        assert!(dot.contains(
+            "\"core\" [label=\"core\", style=dashed, color=\"#bbbbbb\", fontcolor=\"#bbbbbb\"];"
+        ));
+        assert!(dot.contains("\"web\" [label=\"web\", style=filled, fillcolor=\"#f4a261\"];"));
+        assert!(dot.contains("\"web\" -> \"core\" [style=dashed, color=\"#bbbbbb\"];"));
+    }
+}
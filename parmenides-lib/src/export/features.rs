@@ -0,0 +1,203 @@
+use std::collections::{BTreeSet, HashMap};
+
+#[cfg(feature = "git")]
+use crate::context::Context;
+#[cfg(feature = "git")]
+use crate::diff_engine::{ChangedFile, DiffEngine, GitDiffEngine};
+#[cfg(feature = "git")]
+use crate::errors::DiffEngineError;
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+use super::GraphView;
+
+/// The signals of each project that don't come from the graph, e.g. from the repository history
+/// and earlier runs. Projects without a value get an empty cell.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FeatureHistory {
+    /// How many of the walked commits changed an input of the project, see [`churn`].
+    pub churn: HashMap<ProjectId, usize>,
+    /// The share of the recorded runs of a target that failed in the project, from 0 to 1, see
+    /// [`crate::shard::Durations::failure_rate`].
+    pub failure_rates: HashMap<ProjectId, f64>,
+}
+
+/// Renders one row of features per focused project of a view as CSV, e.g. to train models
+/// predicting which tests a change needs to run.
+///
+/// The columns are the ID and identifier of the project, its direct and transitive
+/// dependency and dependent counts, one `tag:<name>` column per tag of the workspace, set to
+/// `1` or `0`, then its churn and failure rate from `history`. Rows are in ID order, and the
+/// tag columns sorted, so the same workspace always gives the same table.
+pub fn to_features_csv(
+    workspace: &Workspace,
+    view: &GraphView,
+    history: &FeatureHistory,
+) -> String {
+    let tags: BTreeSet<&str> = workspace
+        .iter()
+        .flat_map(|project| project.tags())
+        .map(String::as_str)
+        .collect();
+
+    let mut header: Vec<String> = [
+        "id",
+        "identifier",
+        "dependencies",
+        "dependents",
+        "transitive_dependencies",
+        "transitive_dependents",
+    ]
+    .map(str::to_owned)
+    .to_vec();
+    header.extend(tags.iter().map(|tag| format!("tag:{tag}")));
+    header.extend(["churn".to_owned(), "failure_rate".to_owned()]);
+
+    let mut csv = String::new();
+    push_row(&mut csv, &header);
+
+    for id in view.focused() {
+        let Some(project) = workspace.get_project(id) else {
+            continue;
+        };
+
+        let count = |ids: Option<Vec<ProjectId>>| ids.map_or(0, |ids| ids.len()).to_string();
+
+        let mut row = vec![
+            id.into_inner().to_string(),
+            project.identifier().to_owned(),
+            project.dependencies().len().to_string(),
+            project.dependents().len().to_string(),
+            count(workspace.transitive_dependencies(id)),
+            count(workspace.transitive_dependents(id)),
+        ];
+        row.extend(
+            tags.iter()
+                .map(|tag| u8::from(project.has_tag(tag)).to_string()),
+        );
+        row.push(
+            history
+                .churn
+                .get(&id)
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        );
+        row.push(
+            history
+                .failure_rates
+                .get(&id)
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        );
+
+        push_row(&mut csv, &row);
+    }
+
+    csv
+}
+
+/// Appends a CSV row, quoting the cells that need it.
+fn push_row(csv: &mut String, cells: &[String]) {
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect();
+
+    csv.push_str(&cells.join(","));
+    csv.push('\n');
+}
+
+/// Counts, for each project, how many commits changed one of its inputs, walking the
+/// first-parent history back from `revision` for at most `limit` commits.
+///
+/// Files are mapped to the projects of the current workspace, so projects added or moved
+/// since are counted by where their files are now. The root commit isn't counted, as it has
+/// no parent to diff from.
+///
+/// # Returns
+/// - `Ok(HashMap<ProjectId, usize>)`: The commit counts, without the projects no commit
+///   changed.
+/// - `Err(DiffEngineError)`: If a commit could not be read or diffed.
+#[cfg(feature = "git")]
+pub fn churn(
+    workspace: &Workspace,
+    engine: &GitDiffEngine,
+    revision: &str,
+    limit: usize,
+    context: &Context,
+) -> Result<HashMap<ProjectId, usize>, DiffEngineError> {
+    let mut churn: HashMap<ProjectId, usize> = HashMap::new();
+    let mut commit = engine.resolve(revision)?;
+
+    for _ in 0..limit {
+        let Some(parent) = engine.first_parent(&commit)? else {
+            break;
+        };
+
+        let changed = engine.get_changed_files(&parent, Some(&commit), context)?;
+        let owners: BTreeSet<ProjectId> = changed
+            .iter()
+            .flat_map(ChangedFile::paths)
+            .filter_map(|path| {
+                let owner = workspace.resolve_owner(&path)?;
+
+                workspace
+                    .get_project(owner)?
+                    .is_input(&path)
+                    .then_some(owner)
+            })
+            .collect();
+
+        for owner in owners {
+            *churn.entry(owner).or_default() += 1;
+        }
+
+        commit = parent;
+    }
+
+    Ok(churn)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::GraphView;
+
+    use super::{to_features_csv, FeatureHistory};
+
+    #[test]
+    pub fn when_exporting_features_should_write_one_row_per_project() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("core", "core", None).tags = vec!["lib".to_owned()];
+        declaration
+            .add_project("web", "web", Some(vec!["core".into()]))
+            .tags = vec!["app".to_owned(), "lib".to_owned()];
+        declaration.add_project("e2e", "e2e", Some(vec!["web".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_identifier("core").unwrap();
+        let web = workspace.get_id_by_identifier("web").unwrap();
+
+        let mut history = FeatureHistory::default();
+        history.churn.insert(core, 12);
+        history.failure_rates.insert(web, 0.25);
+
+        let csv = to_features_csv(&workspace, &GraphView::full(&workspace), &history);
+
+        assert_eq!(
+            csv,
+            "id,identifier,dependencies,dependents,transitive_dependencies,\
+             transitive_dependents,tag:app,tag:lib,churn,failure_rate\n\
+             0,core,0,1,0,2,0,1,12,\n\
+             1,web,1,1,1,1,1,1,,0.25\n\
+             2,e2e,1,0,2,0,0,0,,\n"
+        );
+    }
+}
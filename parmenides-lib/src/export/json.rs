@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::workspace::{DependencyKind, EdgeStrength, Workspace};
+
+use super::{GraphView, NodeRole};
+
+/// The version of the [`WorkspaceGraph`] format, bumped on incompatible changes.
+pub const GRAPH_FORMAT_VERSION: u32 = 1;
+
+/// A serializable snapshot of a view of the workspace, meant for CI systems and dashboards.
+///
+/// Projects are identified by their [`crate::project::ProjectId`], which edges refer to. The
+/// fields only change between versions of the format, see [`GRAPH_FORMAT_VERSION`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceGraph {
+    pub version: u32,
+    /// The name of the strategy the affected projects were marked with, see
+    /// [`crate::affected::AffectedStrategy`].
+    #[serde(default)]
+    pub strategy: String,
+    pub projects: Vec<GraphProject>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A project of a [`WorkspaceGraph`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphProject {
+    pub id: usize,
+    pub identifier: String,
+    /// The ID of the project across rebuilds, see [`crate::project::StableId`].
+    #[serde(default)]
+    pub stable_id: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub tags: Vec<String>,
+    pub affected: bool,
+    pub role: NodeRole,
+    /// Whether the project is deprecated, see [`crate::project::Deprecation`].
+    #[serde(default)]
+    pub deprecated: bool,
+}
+
+/// A dependency of a [`WorkspaceGraph`], from a project to one of its dependencies.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    /// Whether changes only propagate through the edge with soft propagation enabled.
+    #[serde(default)]
+    pub soft: bool,
+    /// Whether changes coming through the edge stop at the dependent.
+    #[serde(default)]
+    pub stop_propagation: bool,
+    /// What the dependency is needed for.
+    #[serde(default)]
+    pub kind: DependencyKind,
+    /// Whether the dependency isn't expressed in the files of the dependent.
+    #[serde(default)]
+    pub implicit: bool,
+}
+
+impl WorkspaceGraph {
+    /// Takes a snapshot of the projects and edges of a view.
+    pub fn new(workspace: &Workspace, view: &GraphView) -> Self {
+        let projects = view
+            .nodes()
+            .filter_map(|(id, role)| {
+                let project = workspace.get_project(id)?;
+
+                Some(GraphProject {
+                    id: id.into_inner(),
+                    identifier: project.identifier.clone(),
+                    stable_id: project.stable_id.to_string(),
+                    name: project.name.clone(),
+                    path: project.path.clone(),
+                    tags: project.tags.clone(),
+                    affected: project.affected,
+                    role,
+                    deprecated: project.is_deprecated(),
+                })
+            })
+            .collect();
+
+        let edges = view
+            .edges(workspace)
+            .map(|(from, to)| {
+                let edge = workspace.edge(from, to).unwrap_or_default();
+
+                GraphEdge {
+                    from: from.into_inner(),
+                    to: to.into_inner(),
+                    soft: edge.strength == EdgeStrength::Soft,
+                    stop_propagation: edge.stop_propagation,
+                    kind: edge.kind,
+                    implicit: edge.implicit,
+                }
+            })
+            .collect();
+
+        Self {
+            version: GRAPH_FORMAT_VERSION,
+            strategy: workspace.affected_strategy().name().to_owned(),
+            projects,
+            edges,
+        }
+    }
+}
+
+/// Renders a view of the workspace as a pretty-printed JSON [`WorkspaceGraph`].
+pub fn to_json(workspace: &Workspace, view: &GraphView) -> String {
+    // Only strings, numbers and booleans are serialized, which can't fail.
+    serde_json::to_string_pretty(&WorkspaceGraph::new(workspace, view)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::GraphView;
+
+    use super::{to_json, WorkspaceGraph};
+
+    #[test]
+    pub fn when_exporting_json_should_round_trip_projects_and_edges() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("core", "core", None);
+        declaration.add_project("web", "web", Some(vec!["core".into()]));
+
+        let mut workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_path(&"core").unwrap();
+        let web = workspace.get_id_by_path(&"web").unwrap();
+
+        workspace.mark_project_as_affected(web).unwrap();
+
+        let json = to_json(&workspace, &GraphView::full(&workspace));
+        let graph: WorkspaceGraph = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            graph,
+            WorkspaceGraph::new(&workspace, &GraphView::full(&workspace))
+        );
+        assert_eq!(graph.projects.len(), 2);
+        assert!(graph.projects[web.into_inner()].affected);
+        assert!(!graph.projects[core.into_inner()].affected);
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].from, web.into_inner());
+        assert_eq!(graph.edges[0].to, core.into_inner());
+        assert!(json.contains("\"role\": \"focus\""));
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt::Write;
+
+use crate::workspace::Workspace;
+
+use super::{DotOptions, GraphView, NodeRole};
+
+/// Renders a view of the workspace as a Mermaid flowchart, which GitHub and most wikis render
+/// inline in Markdown.
+///
+/// It takes the same styling as [`super::to_dot`]. Projects are identified by their id and
+/// labeled with their name. Edges point from a project to its dependencies, and edges reaching
+/// context projects are dotted.
+pub fn to_mermaid(workspace: &Workspace, view: &GraphView, options: &DotOptions) -> String {
+    let mut mermaid = String::new();
+
+    let _ = writeln!(mermaid, "flowchart {}", options.rank_direction);
+
+    for (id, role) in view.nodes() {
+        let Some(project) = workspace.get_project(id) else {
+            continue;
+        };
+
+        let class = match role {
+            NodeRole::Context => ":::context",
+            NodeRole::Focus if project.affected && options.affected_color.is_some() => {
+                ":::affected"
+            }
+            NodeRole::Focus => "",
+        };
+
+        let _ = writeln!(mermaid, "  p{id}[\"{}\"]{class}", escape(&project.name));
+    }
+
+    for (from, to) in view.edges(workspace) {
+        let dimmed =
+            view.role(from) == Some(NodeRole::Context) || view.role(to) == Some(NodeRole::Context);
+        let arrow = if dimmed { "-.->" } else { "-->" };
+
+        let _ = writeln!(mermaid, "  p{from} {arrow} p{to}");
+    }
+
+    if let Some(color) = &options.affected_color {
+        let _ = writeln!(mermaid, "  classDef affected fill:{color}");
+    }
+
+    let _ = writeln!(
+        mermaid,
+        "  classDef context stroke-dasharray:5 5,stroke:{0},color:{0}",
+        options.context_color
+    );
+
+    mermaid
+}
+
+/// Escapes the characters that would end a quoted Mermaid label.
+fn escape(value: &str) -> String {
+    value.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::{DotOptions, GraphView};
+
+    use super::to_mermaid;
+
+    #[test]
+    pub fn when_exporting_mermaid_should_class_affected_and_context() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("core", "core", None);
+        declaration.add_project("web", "web", Some(vec!["core".into()]));
+
+        let mut workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_path(&"core").unwrap();
+        let web = workspace.get_id_by_path(&"web").unwrap();
+
+        workspace.mark_project_as_affected(web).unwrap();
+
+        let view = GraphView::affected_with_context(&workspace);
+        let mermaid = to_mermaid(&workspace, &view, &DotOptions::default());
+
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains(&format!("  p{core}[\"core\"]:::context\n")));
+        assert!(mermaid.contains(&format!("  p{web}[\"web\"]:::affected\n")));
+        assert!(mermaid.contains(&format!("  p{web} -.-> p{core}\n")));
+        assert!(mermaid.contains("  classDef affected fill:#f4a261\n"));
+    }
+}
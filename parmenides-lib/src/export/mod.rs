@@ -0,0 +1,247 @@
+//! # Export
+//!
+//! Exports the project graph to formats meant for people and other tools. A [`GraphView`]
+//! selects the part of the workspace to export, so that large workspaces can be narrowed down to
+//! what is relevant, e.g. the affected projects and their immediate context.
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::ProjectId;
+use crate::workspace::{Direction, Workspace};
+
+mod backstage;
+mod catalog;
+mod dot;
+mod features;
+mod json;
+mod mermaid;
+#[cfg(feature = "svg")]
+mod svg;
+
+pub use backstage::{backstage_relations, to_backstage, BackstageOptions, CatalogDrift};
+pub use catalog::{readme_summary, Catalog, CatalogEntry, CATALOG_FORMAT_VERSION};
+pub use dot::{to_dot, DotOptions};
+#[cfg(feature = "git")]
+pub use features::churn;
+pub use features::{to_features_csv, FeatureHistory};
+pub use json::{to_json, GraphEdge, GraphProject, WorkspaceGraph, GRAPH_FORMAT_VERSION};
+pub use mermaid::to_mermaid;
+#[cfg(feature = "svg")]
+pub use svg::to_svg;
+
+/// The role of a project within a [`GraphView`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    /// A project the view is about.
+    Focus,
+    /// A project included only to give context to the focused ones, usually rendered dimmed.
+    Context,
+}
+
+/// A subgraph of a workspace.
+///
+/// The edges of the view are the dependencies between its projects that touch at least one
+/// focused project, so the context doesn't add edges of its own.
+#[derive(Debug, PartialEq, Default)]
+pub struct GraphView {
+    nodes: BTreeMap<ProjectId, NodeRole>,
+}
+
+impl GraphView {
+    /// A view focusing on every project of the workspace.
+    pub fn full(workspace: &Workspace) -> Self {
+        Self {
+            nodes: workspace
+                .iter_with_ids()
+                .map(|(id, _)| (id, NodeRole::Focus))
+                .collect(),
+        }
+    }
+
+    /// A view focusing on the affected projects, with their direct dependencies and dependents
+    /// as context.
+    pub fn affected_with_context(workspace: &Workspace) -> Self {
+        let mut view = Self::default();
+
+        for id in workspace.affected_projects() {
+            view.nodes.insert(id, NodeRole::Focus);
+        }
+
+        view.add_context(workspace);
+        view
+    }
+
+    /// A view focusing on a project and the projects up to `depth` levels away from it, in
+    /// either direction, with their direct dependencies and dependents as context.
+    ///
+    /// Dependencies of dependents, and dependents of dependencies, aren't walked, so siblings
+    /// of the project only appear as context when they are adjacent to the neighborhood.
+    pub fn neighborhood(workspace: &Workspace, id: ProjectId, depth: usize) -> Self {
+        let mut view = Self::default();
+
+        if workspace.get_project(id).is_none() {
+            return view;
+        }
+
+        view.nodes.insert(id, NodeRole::Focus);
+
+        for direction in [Direction::Dependencies, Direction::Dependents] {
+            let mut queue = VecDeque::from([(id, 0)]);
+
+            while let Some((current_id, current_depth)) = queue.pop_front() {
+                if current_depth == depth {
+                    continue;
+                }
+
+                let Some(project) = workspace.get_project(current_id) else {
+                    continue;
+                };
+
+                let next = match direction {
+                    Direction::Dependencies => project.dependencies.as_deref().unwrap_or_default(),
+                    Direction::Dependents => &project.dependents,
+                };
+
+                for next_id in next {
+                    if view.nodes.insert(*next_id, NodeRole::Focus).is_none() {
+                        queue.push_back((*next_id, current_depth + 1));
+                    }
+                }
+            }
+        }
+
+        view.add_context(workspace);
+        view
+    }
+
+    /// Adds the direct dependencies and dependents of the focused projects as context.
+    fn add_context(&mut self, workspace: &Workspace) {
+        let focused: Vec<ProjectId> = self.focused().collect();
+
+        for id in focused {
+            let Some(project) = workspace.get_project(id) else {
+                continue;
+            };
+
+            for neighbor in project
+                .dependencies
+                .iter()
+                .flatten()
+                .chain(&project.dependents)
+            {
+                self.nodes.entry(*neighbor).or_insert(NodeRole::Context);
+            }
+        }
+    }
+
+    /// Returns the role of a project, or `None` if it is not part of the view.
+    pub fn role(&self, id: ProjectId) -> Option<NodeRole> {
+        self.nodes.get(&id).copied()
+    }
+
+    /// Returns the projects of the view with their roles, ordered by id.
+    pub fn nodes(&self) -> impl Iterator<Item = (ProjectId, NodeRole)> + '_ {
+        self.nodes.iter().map(|(id, role)| (*id, *role))
+    }
+
+    /// Returns the focused projects of the view.
+    pub fn focused(&self) -> impl Iterator<Item = ProjectId> + '_ {
+        self.nodes()
+            .filter(|(_, role)| *role == NodeRole::Focus)
+            .map(|(id, _)| id)
+    }
+
+    /// Returns the edges `(project, dependency)` of the view.
+    pub fn edges<'a>(
+        &'a self,
+        workspace: &'a Workspace,
+    ) -> impl Iterator<Item = (ProjectId, ProjectId)> + 'a {
+        self.nodes().flat_map(move |(id, role)| {
+            workspace
+                .get_project(id)
+                .and_then(|project| project.dependencies.as_ref())
+                .into_iter()
+                .flatten()
+                .filter(move |dependency| match self.role(**dependency) {
+                    Some(dependency_role) => {
+                        role == NodeRole::Focus || dependency_role == NodeRole::Focus
+                    }
+                    None => false,
+                })
+                .map(move |dependency| (id, *dependency))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+
+    use super::{GraphView, NodeRole};
+
+    #[test]
+    pub fn when_viewing_affected_should_include_first_degree_context() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("base", "base", None);
+        declaration.add_project("core", "core", Some(vec!["base".into()]));
+        declaration.add_project("ui", "ui", Some(vec!["core".into()]));
+        declaration.add_project("web", "web", Some(vec!["ui".into()]));
+        declaration.add_project("docs", "docs", None);
+
+        let mut workspace = declaration.build_workspace().unwrap();
+
+        let base = workspace.get_id_by_path(&"base").unwrap();
+        let core = workspace.get_id_by_path(&"core").unwrap();
+        let ui = workspace.get_id_by_path(&"ui").unwrap();
+        let web = workspace.get_id_by_path(&"web").unwrap();
+        let docs = workspace.get_id_by_path(&"docs").unwrap();
+
+        workspace.mark_project_as_affected(ui).unwrap();
+
+        let view = GraphView::affected_with_context(&workspace);
+
+        assert_eq!(view.role(ui), Some(NodeRole::Focus));
+        assert_eq!(view.role(core), Some(NodeRole::Context));
+        assert_eq!(view.role(web), Some(NodeRole::Focus));
+        assert_eq!(view.role(base), None);
+        assert_eq!(view.role(docs), None);
+
+        let mut edges: Vec<_> = view.edges(&workspace).collect();
+        edges.sort();
+
+        let mut expected = vec![(ui, core), (web, ui)];
+        expected.sort();
+
+        assert_eq!(edges, expected);
+    }
+
+    #[test]
+    pub fn when_viewing_neighborhood_should_walk_both_directions_up_to_depth() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("base", "base", None);
+        declaration.add_project("core", "core", Some(vec!["base".into()]));
+        declaration.add_project("ui", "ui", Some(vec!["core".into()]));
+        declaration.add_project("web", "web", Some(vec!["ui".into()]));
+        declaration.add_project("docs", "docs", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let base = workspace.get_id_by_path(&"base").unwrap();
+        let core = workspace.get_id_by_path(&"core").unwrap();
+        let ui = workspace.get_id_by_path(&"ui").unwrap();
+        let web = workspace.get_id_by_path(&"web").unwrap();
+        let docs = workspace.get_id_by_path(&"docs").unwrap();
+
+        let view = GraphView::neighborhood(&workspace, core, 1);
+
+        assert_eq!(view.role(core), Some(NodeRole::Focus));
+        assert_eq!(view.role(base), Some(NodeRole::Focus));
+        assert_eq!(view.role(ui), Some(NodeRole::Focus));
+        assert_eq!(view.role(web), Some(NodeRole::Context));
+        assert_eq!(view.role(docs), None);
+    }
+}
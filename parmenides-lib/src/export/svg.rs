@@ -0,0 +1,74 @@
+use layout::backends::svg::SVGWriter;
+use layout::gv::{DotParser, GraphBuilder};
+
+use crate::errors::RenderError;
+use crate::workspace::Workspace;
+
+use super::{to_dot, DotOptions, GraphView};
+
+/// Renders a view of the workspace as an SVG image, without needing Graphviz.
+///
+/// The graph is laid out in layers by the built-in engine, with the same styling as
+/// [`to_dot`]. Only available with the `svg` feature.
+pub fn to_svg(
+    workspace: &Workspace,
+    view: &GraphView,
+    options: &DotOptions,
+) -> Result<String, RenderError> {
+    let dot = to_dot(workspace, view, options);
+
+    let mut parser = DotParser::new(&dot);
+    let graph = parser.process().map_err(RenderError::Layout)?;
+
+    let mut builder = GraphBuilder::new();
+    builder.visit_graph(&graph);
+
+    let mut visual_graph = builder.get();
+
+    if visual_graph.num_nodes() == 0 {
+        return Ok(SVGWriter::new().finalize());
+    }
+
+    let mut writer = SVGWriter::new();
+    visual_graph.do_it(false, false, false, &mut writer);
+
+    Ok(writer.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::{DotOptions, GraphView};
+
+    use super::to_svg;
+
+    #[test]
+    pub fn when_rendering_svg_should_draw_every_project() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("libs/core", "core", None);
+        declaration.add_project("apps/web", "web", Some(vec!["libs/core".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let svg = to_svg(
+            &workspace,
+            &GraphView::full(&workspace),
+            &DotOptions::default(),
+        )
+        .unwrap();
+
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains(">core<"));
+        assert!(svg.contains(">web<"));
+    }
+
+    #[test]
+    pub fn when_rendering_empty_view_should_return_empty_image() {
+        let workspace = WorkspaceDeclaration::new().build_workspace().unwrap();
+
+        let svg = to_svg(&workspace, &GraphView::default(), &DotOptions::default()).unwrap();
+
+        assert!(svg.contains("<svg"));
+    }
+}
@@ -0,0 +1,212 @@
+//! # File system
+//!
+//! The [`FileSystem`] trait abstracts the reads made by discovery and declaration loading, so
+//! they can run against the disk ([`OsFileSystem`]), an in-memory tree in tests
+//! ([`MemoryFileSystem`]), or other sources such as a git tree or an archive.
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Read access to a tree of files.
+pub trait FileSystem {
+    /// Reads the whole content of a file.
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+
+    /// Reads the whole content of a UTF-8 file.
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    /// Returns the paths of the entries of a directory, sorted.
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>>;
+
+    /// Returns `true` if the path is an existing file.
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Returns `true` if the path is an existing directory.
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Returns the target of the path if it is a symbolic link, without following it. Sources
+    /// without links, the default, return `None`.
+    fn read_link(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// The [`FileSystem`] of the operating system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        entries.sort();
+
+        Ok(entries)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_link(&self, path: &Path) -> Option<PathBuf> {
+        std::fs::symlink_metadata(path)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+            .then(|| std::fs::read_link(path).ok())
+            .flatten()
+    }
+}
+
+/// An in-memory [`FileSystem`]. Directories exist implicitly as the ancestors of its files.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFileSystem {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    links: BTreeMap<PathBuf, PathBuf>,
+    unreadable: BTreeSet<PathBuf>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any file with the same path.
+    pub fn insert<P, C>(&mut self, path: P, content: C)
+    where
+        P: Into<PathBuf>,
+        C: Into<Vec<u8>>,
+    {
+        self.files.insert(path.into(), content.into());
+    }
+
+    /// Adds a file, returning the file system to allow chaining.
+    pub fn with_file<P, C>(mut self, path: P, content: C) -> Self
+    where
+        P: Into<PathBuf>,
+        C: Into<Vec<u8>>,
+    {
+        self.insert(path, content);
+        self
+    }
+
+    /// Adds a symbolic link to `target`, returning the file system to allow chaining. Links
+    /// are listed in their directory but never followed.
+    pub fn with_symlink<P, T>(mut self, path: P, target: T) -> Self
+    where
+        P: Into<PathBuf>,
+        T: Into<PathBuf>,
+    {
+        self.links.insert(path.into(), target.into());
+        self
+    }
+
+    /// Makes reading a file, or listing a directory, fail as if permission was denied, e.g. to
+    /// test how readers skip it.
+    pub fn with_unreadable<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.unreadable.insert(path.into());
+        self
+    }
+
+    fn check_readable(&self, path: &Path) -> std::io::Result<()> {
+        if self.unreadable.contains(path) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                path.display().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.check_readable(path)?;
+
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, path.display().to_string()))
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        if !self.is_dir(path) {
+            return Err(Error::new(ErrorKind::NotFound, path.display().to_string()));
+        }
+
+        self.check_readable(path)?;
+
+        let mut entries: Vec<PathBuf> = self
+            .files
+            .keys()
+            .chain(self.links.keys())
+            .filter_map(|file| {
+                let child = file.strip_prefix(path).ok()?.components().next()?;
+
+                Some(path.join(child))
+            })
+            .collect();
+
+        entries.sort();
+        entries.dedup();
+
+        Ok(entries)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .keys()
+            .chain(self.links.keys())
+            .any(|file| file != path && file.starts_with(path))
+    }
+
+    fn read_link(&self, path: &Path) -> Option<PathBuf> {
+        self.links.get(path).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{FileSystem, MemoryFileSystem};
+
+    #[test]
+    pub fn when_listing_memory_directory_should_include_implicit_directories() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/repo/Cargo.toml", "")
+            .with_file("/repo/crates/a/Cargo.toml", "")
+            .with_file("/repo/crates/b/Cargo.toml", "");
+
+        assert_eq!(
+            fs.read_dir(Path::new("/repo")).unwrap(),
+            vec![
+                PathBuf::from("/repo/Cargo.toml"),
+                PathBuf::from("/repo/crates")
+            ]
+        );
+        assert!(fs.is_dir(Path::new("/repo/crates/a")));
+        assert!(!fs.is_dir(Path::new("/repo/Cargo.toml")));
+        assert!(fs.is_file(Path::new("/repo/Cargo.toml")));
+        assert!(fs.read_dir(Path::new("/missing")).is_err());
+    }
+}
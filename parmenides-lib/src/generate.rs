@@ -0,0 +1,190 @@
+//! Generators create new projects from templates declared in the workspace.
+//!
+//! A template is a directory of files whose names and contents may contain
+//! [`Parameters`] placeholders. Besides the workspace constants and any overrides, the
+//! `name` and `path` parameters hold the name and path of the new project.
+use std::path::Path;
+
+use crate::declarations::WorkspaceDeclaration;
+use crate::edit::DeclarationEdit;
+use crate::errors::GenerateError;
+use crate::parameters::Parameters;
+
+/// Instantiates a generator, creating the project files at `path`.
+///
+/// The project is not declared yet: the returned edit should be applied to the declaration,
+/// e.g. with [`crate::edit::edit_declaration_file`], so the new project joins the workspace.
+///
+/// # Parameters
+/// - `declaration`: The workspace declaration holding the generators.
+/// - `generator`: The name of the generator.
+/// - `path`: Where to create the project. It must not exist or be an empty directory.
+/// - `name`: The name of the new project.
+/// - `parameters`: The values available to the template placeholders.
+///
+/// # Returns
+/// - `Ok(DeclarationEdit)`: The [`DeclarationEdit::AddProject`] declaring the new project.
+/// - `Err(GenerateError)`: If the generator is unknown or the files could not be created.
+pub fn generate_project(
+    declaration: &WorkspaceDeclaration,
+    generator: &str,
+    path: &Path,
+    name: &str,
+    parameters: &Parameters,
+) -> Result<DeclarationEdit, GenerateError> {
+    let generator_declaration = declaration
+        .generators
+        .get(generator)
+        .ok_or_else(|| GenerateError::UnknownGenerator(generator.to_owned()))?;
+
+    let is_empty = match std::fs::read_dir(path) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => true,
+        Err(err) => return Err(GenerateError::Io(path.to_path_buf(), err)),
+    };
+
+    if !is_empty {
+        return Err(GenerateError::DestinationNotEmpty(path.to_path_buf()));
+    }
+
+    let mut parameters = parameters.clone();
+    parameters.set("name", name);
+    parameters.set("path", path.to_string_lossy());
+
+    copy_template(&generator_declaration.template, path, &parameters)?;
+
+    Ok(DeclarationEdit::AddProject {
+        path: path.to_path_buf(),
+        name: name.to_owned(),
+        dependencies: generator_declaration.dependencies.clone(),
+        tags: generator_declaration.tags.clone(),
+    })
+}
+
+fn copy_template(
+    source: &Path,
+    destination: &Path,
+    parameters: &Parameters,
+) -> Result<(), GenerateError> {
+    std::fs::create_dir_all(destination)
+        .map_err(|err| GenerateError::Io(destination.to_path_buf(), err))?;
+
+    let entries =
+        std::fs::read_dir(source).map_err(|err| GenerateError::Io(source.to_path_buf(), err))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|err| GenerateError::Io(source.to_path_buf(), err))?;
+        let source_path = entry.path();
+
+        let file_name = parameters
+            .interpolate(&entry.file_name().to_string_lossy())
+            .map_err(|err| GenerateError::Interpolate(source_path.clone(), err))?;
+        let destination_path = destination.join(file_name);
+
+        let file_type = entry
+            .file_type()
+            .map_err(|err| GenerateError::Io(source_path.clone(), err))?;
+
+        if file_type.is_dir() {
+            copy_template(&source_path, &destination_path, parameters)?;
+            continue;
+        }
+
+        let content = std::fs::read(&source_path)
+            .map_err(|err| GenerateError::Io(source_path.clone(), err))?;
+
+        // Binary files are copied as they are.
+        let content = match String::from_utf8(content) {
+            Ok(text) => parameters
+                .interpolate(&text)
+                .map_err(|err| GenerateError::Interpolate(source_path.clone(), err))?
+                .into_bytes(),
+            Err(err) => err.into_bytes(),
+        };
+
+        std::fs::write(&destination_path, content)
+            .map_err(|err| GenerateError::Io(destination_path.clone(), err))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use crate::declarations::{GeneratorDeclaration, WorkspaceDeclaration};
+    use crate::edit::DeclarationEdit;
+    use crate::errors::GenerateError;
+    use crate::parameters::Parameters;
+
+    use super::generate_project;
+
+    #[test]
+    pub fn when_generating_should_copy_and_interpolate_template() {
+        let root = std::env::temp_dir().join(format!("parmenides-generate-{}", std::process::id()));
+        let template = root.join("templates/lib");
+
+        std::fs::create_dir_all(template.join("src")).unwrap();
+        std::fs::write(
+            template.join("src/{{ name }}.txt"),
+            "{{ name }} published to {{ registry }}",
+        )
+        .unwrap();
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.generators.insert(
+            "lib".to_owned(),
+            GeneratorDeclaration {
+                template,
+                tags: vec!["lib".to_owned()],
+                dependencies: vec![PathBuf::from("libs/core")],
+            },
+        );
+
+        let parameters = Parameters::new(HashMap::from([(
+            "registry".to_owned(),
+            "registry.example.com".to_owned(),
+        )]));
+
+        let destination = root.join("libs/widgets");
+
+        let edit =
+            generate_project(&declaration, "lib", &destination, "widgets", &parameters).unwrap();
+
+        let content = std::fs::read_to_string(destination.join("src/widgets.txt")).unwrap();
+
+        let second = generate_project(&declaration, "lib", &destination, "widgets", &parameters);
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(content, "widgets published to registry.example.com");
+        assert_eq!(
+            edit,
+            DeclarationEdit::AddProject {
+                path: destination.clone(),
+                name: "widgets".to_owned(),
+                dependencies: vec![PathBuf::from("libs/core")],
+                tags: vec!["lib".to_owned()],
+            }
+        );
+        assert!(matches!(
+            second,
+            Err(GenerateError::DestinationNotEmpty(path)) if path == destination
+        ));
+    }
+
+    #[test]
+    pub fn when_generator_is_unknown_should_return_error() {
+        let result = generate_project(
+            &WorkspaceDeclaration::new(),
+            "missing",
+            &std::env::temp_dir().join("parmenides-never-created"),
+            "never",
+            &Parameters::default(),
+        );
+
+        assert!(matches!(result, Err(GenerateError::UnknownGenerator(name)) if name == "missing"));
+    }
+}
@@ -0,0 +1,351 @@
+//! # Health
+//!
+//! A single score summarizing the health of a workspace, from 0 to 100, with a breakdown per
+//! category: lint violations, cycles between tag layers, the average blast radius of a change,
+//! the cache hit rate and the share of stale projects. Each [`HealthReport`] can be appended
+//! to [`HEALTH_FILE`], e.g. on every merge to the main branch, so platform teams can follow
+//! the trend instead of a single measurement.
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::HealthError;
+use crate::lint::rules::NoCircularTagLayers;
+use crate::lint::LintRule;
+use crate::project::ProjectId;
+use crate::stats::{civil_date, millis};
+use crate::workspace::Workspace;
+
+/// The health file of a workspace, relative to its root.
+pub const HEALTH_FILE: &str = ".parmenides/health.jsonl";
+
+/// The signals of a workspace that don't come from its graph. The categories of the missing
+/// ones aren't scored, rather than scored as perfect.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct HealthSignals {
+    /// How many diagnostics a [`crate::lint::Linter`] run reported.
+    pub lint_violations: Option<usize>,
+    /// The share of the task runs replayed from the cache, see
+    /// [`crate::shard::Durations::cache_hit_rate`].
+    pub cache_hit_rate: Option<f64>,
+    /// How many of the recent commits changed each project, see [`crate::export::churn`].
+    /// The projects without a count are stale.
+    pub churn: Option<HashMap<ProjectId, usize>>,
+}
+
+/// A category of the health score.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCategory {
+    /// The lint diagnostics per project. Scores 0 from one diagnostic per project.
+    LintViolations,
+    /// The edges closing a cycle between tag layers, see [`NoCircularTagLayers`]. Each one
+    /// halves the score of the previous.
+    TagLayerCycles,
+    /// The average number of transitive dependents of a project, scored by its share of the
+    /// other projects.
+    BlastRadius,
+    /// The share of the task runs replayed from the cache.
+    CacheHitRate,
+    /// The number of projects no recent commit changed, scored by their share.
+    StaleProjects,
+}
+
+impl HealthCategory {
+    /// The name of the category, as serialized.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LintViolations => "lint_violations",
+            Self::TagLayerCycles => "tag_layer_cycles",
+            Self::BlastRadius => "blast_radius",
+            Self::CacheHitRate => "cache_hit_rate",
+            Self::StaleProjects => "stale_projects",
+        }
+    }
+}
+
+/// The measurement and score of a category.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CategoryScore {
+    pub category: HealthCategory,
+    /// The measurement, in the unit of the category.
+    pub value: f64,
+    /// The score, from 0 to 100.
+    pub score: f64,
+}
+
+/// The health of a workspace at a point in time.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct HealthReport {
+    /// When the report was computed, in milliseconds since the Unix epoch.
+    pub computed_ms: u64,
+    /// The number of projects in the workspace.
+    pub projects: usize,
+    /// The mean of the category scores, from 0 to 100.
+    pub score: f64,
+    /// The scored categories, in the order of [`HealthCategory`].
+    pub categories: Vec<CategoryScore>,
+}
+
+impl HealthReport {
+    /// Computes the health of `workspace` from its graph and `signals`, at `computed`.
+    ///
+    /// Values are rounded to two decimals and scores to one, so recorded reports stay
+    /// readable.
+    pub fn compute(workspace: &Workspace, signals: &HealthSignals, computed: SystemTime) -> Self {
+        let projects = workspace.len();
+        let share = |count: f64, of: usize| if of == 0 { 0.0 } else { count / of as f64 };
+
+        let mut categories = Vec::new();
+        let mut push = |category, value: f64, score: f64| {
+            categories.push(CategoryScore {
+                category,
+                value: (value * 100.0).round() / 100.0,
+                score: (score.clamp(0.0, 100.0) * 10.0).round() / 10.0,
+            });
+        };
+
+        if let Some(violations) = signals.lint_violations {
+            let ratio = share(violations as f64, projects);
+            push(
+                HealthCategory::LintViolations,
+                violations as f64,
+                100.0 * (1.0 - ratio.min(1.0)),
+            );
+        }
+
+        let cycles = NoCircularTagLayers.check(workspace).len();
+        push(
+            HealthCategory::TagLayerCycles,
+            cycles as f64,
+            100.0 / 2f64.powi(i32::try_from(cycles).unwrap_or(i32::MAX)),
+        );
+
+        let dependents: usize = workspace
+            .iter_with_ids()
+            .filter_map(|(id, _)| workspace.transitive_dependents(id))
+            .map(|dependents| dependents.len())
+            .sum();
+        let average = share(dependents as f64, projects);
+        push(
+            HealthCategory::BlastRadius,
+            average,
+            100.0 * (1.0 - share(average, projects.saturating_sub(1))),
+        );
+
+        if let Some(rate) = signals.cache_hit_rate {
+            push(HealthCategory::CacheHitRate, rate, 100.0 * rate);
+        }
+
+        if let Some(churn) = &signals.churn {
+            let stale = workspace
+                .iter_with_ids()
+                .filter(|(id, _)| churn.get(id).is_none_or(|count| *count == 0))
+                .count();
+            push(
+                HealthCategory::StaleProjects,
+                stale as f64,
+                100.0 * (1.0 - share(stale as f64, projects)),
+            );
+        }
+
+        let score = categories
+            .iter()
+            .map(|category| category.score)
+            .sum::<f64>()
+            / categories.len() as f64;
+
+        Self {
+            computed_ms: millis(computed.duration_since(UNIX_EPOCH).unwrap_or_default()),
+            projects,
+            score: (score * 10.0).round() / 10.0,
+            categories,
+        }
+    }
+
+    /// Returns the score of `category`, if it was scored.
+    pub fn category(&self, category: HealthCategory) -> Option<&CategoryScore> {
+        self.categories
+            .iter()
+            .find(|score| score.category == category)
+    }
+
+    /// Returns the day the report was computed, as `YYYY-MM-DD`, in UTC.
+    pub fn date(&self) -> String {
+        let (year, month, day) = civil_date(self.computed_ms / 86_400_000);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+
+    /// Renders the score, then a line per category.
+    pub fn to_text(&self) -> String {
+        let mut output = format!("health {:.1}\n", self.score);
+
+        for category in &self.categories {
+            let _ = writeln!(
+                output,
+                "  {:<16}  {:>8}  {:>5.1}",
+                category.category.name(),
+                category.value,
+                category.score
+            );
+        }
+
+        output
+    }
+}
+
+/// Renders the score of each report, oldest first, with its change since the previous one.
+pub fn trend_to_text(reports: &[HealthReport]) -> String {
+    let mut output = String::from("date        score  change\n");
+    let mut previous: Option<f64> = None;
+
+    for report in reports {
+        let change = previous
+            .map(|previous| format!("{:+.1}", report.score - previous))
+            .unwrap_or_default();
+
+        let _ = writeln!(
+            output,
+            "{}  {:>5.1}  {:>6}",
+            report.date(),
+            report.score,
+            change
+        );
+        previous = Some(report.score);
+    }
+
+    output
+}
+
+/// Appends `report` to the health file at `path`, creating it if needed.
+pub fn record(path: &Path, report: &HealthReport) -> Result<(), HealthError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| HealthError::Io(parent.to_path_buf(), err))?;
+    }
+
+    let mut line = serde_json::to_string(report).expect("reports serialize");
+    line.push('\n');
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|err| HealthError::Io(path.to_path_buf(), err))
+}
+
+/// Reads every report of the health file at `path`. A missing file has no reports.
+///
+/// # Returns
+/// - `Ok(Vec<HealthReport>)`: The reports, in the order they were recorded.
+/// - `Err(HealthError)`: If the file could not be read, or a line is not a report.
+pub fn read(path: &Path) -> Result<Vec<HealthReport>, HealthError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(HealthError::Io(path.to_path_buf(), err)),
+    };
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| HealthError::Parse(path.to_path_buf(), index + 1, err.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::declarations::WorkspaceDeclaration;
+
+    use super::{read, record, trend_to_text, HealthCategory, HealthReport, HealthSignals};
+
+    /// Computes the health of a workspace where `core` has 2 transitive dependents, `ui` has 1
+    /// and `docs` none, on 2024-01-31 in UTC. Churn only counts `core` and `web`.
+    fn report(measured: bool) -> HealthReport {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("libs/core", "core", None);
+        declaration.add_project("libs/ui", "ui", Some(vec!["libs/core".into()]));
+        declaration.add_project("apps/web", "web", Some(vec!["libs/ui".into()]));
+        declaration.add_project("apps/docs", "docs", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_identifier("core").unwrap();
+        let web = workspace.get_id_by_identifier("web").unwrap();
+
+        let signals = if measured {
+            HealthSignals {
+                lint_violations: Some(1),
+                cache_hit_rate: Some(0.5),
+                churn: Some(HashMap::from([(core, 3), (web, 1)])),
+            }
+        } else {
+            HealthSignals::default()
+        };
+
+        let computed = UNIX_EPOCH + Duration::from_secs(1_706_659_200);
+
+        HealthReport::compute(&workspace, &signals, computed)
+    }
+
+    #[test]
+    pub fn when_computing_health_should_score_each_category() {
+        let report = report(true);
+
+        let score = |category| {
+            let score = report.category(category).unwrap();
+            (score.value, score.score)
+        };
+
+        assert_eq!(score(HealthCategory::LintViolations), (1.0, 75.0));
+        assert_eq!(score(HealthCategory::TagLayerCycles), (0.0, 100.0));
+        // core has 2 transitive dependents and ui 1, of the 3 other projects.
+        assert_eq!(score(HealthCategory::BlastRadius), (0.75, 75.0));
+        assert_eq!(score(HealthCategory::CacheHitRate), (0.5, 50.0));
+        assert_eq!(score(HealthCategory::StaleProjects), (2.0, 50.0));
+        assert_eq!(report.score, 70.0);
+        assert_eq!(report.date(), "2024-01-31");
+    }
+
+    #[test]
+    pub fn when_signals_are_missing_should_score_only_the_graph_categories() {
+        let unmeasured = report(false);
+
+        assert_eq!(unmeasured.categories.len(), 2);
+        assert!(unmeasured.category(HealthCategory::CacheHitRate).is_none());
+        assert_eq!(unmeasured.score, 87.5);
+    }
+
+    #[test]
+    pub fn when_reading_recorded_reports_should_show_the_trend() {
+        let path = std::env::temp_dir()
+            .join(format!("parmenides-health-{}", std::process::id()))
+            .join("health.jsonl");
+
+        record(&path, &report(true)).unwrap();
+        record(&path, &report(false)).unwrap();
+
+        let reports = read(&path);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(
+            trend_to_text(&reports.unwrap()),
+            "date        score  change\n\
+             2024-01-31   70.0        \n\
+             2024-01-31   87.5   +17.5\n"
+        );
+    }
+}
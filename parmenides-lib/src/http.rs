@@ -0,0 +1,349 @@
+//! # HTTP
+//!
+//! The HTTP client shared by the features talking to remotes, such as the
+//! [`crate::cache::HttpRemoteCache`]. An [`HttpClient`] retries failed calls with exponential
+//! backoff, waits as long as a rate limited remote asks with `Retry-After`, goes through a
+//! proxy, and reports failures as [`HttpError`]s, so each remote only interprets statuses.
+//!
+//! Proxies are read from the `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables
+//! unless one is set with [`HttpClient::with_proxy`].
+use std::time::Duration;
+
+use ureq::http::Response;
+use ureq::{Agent, Body, Proxy};
+
+use crate::clock::{Clock, SystemClock};
+use crate::errors::HttpError;
+
+/// How failed calls are retried: on connection failures, `429 Too Many Requests` and `5xx`
+/// statuses. Other statuses are answers, returned right away.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times a call is made at most, including the first one.
+    pub attempts: u32,
+    /// The wait before the first retry, doubled before each of the next ones.
+    pub base_delay: Duration,
+    /// The longest wait between attempts. A remote asking to wait longer with `Retry-After`
+    /// fails the call with [`HttpError::RateLimited`] instead.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Makes every call once.
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the backoff before the retry following the attempt numbered `attempt`, from 0.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+/// The answer of a remote.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Returns `true` if the status is `2xx`.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// A blocking HTTP client with retries, rate limit handling and proxy support.
+pub struct HttpClient<C = SystemClock> {
+    agent: Agent,
+    timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    token: Option<String>,
+    retry: RetryPolicy,
+    clock: C,
+}
+
+impl HttpClient {
+    /// A client with the default [`RetryPolicy`], the proxy of the environment and no timeout.
+    pub fn new() -> Self {
+        let proxy = Proxy::try_from_env();
+
+        Self {
+            agent: agent(None, proxy.clone()),
+            timeout: None,
+            proxy,
+            token: None,
+            retry: RetryPolicy::default(),
+            clock: SystemClock,
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> HttpClient<C> {
+    /// Waits between retries with the given clock.
+    pub fn with_clock<D: Clock>(self, clock: D) -> HttpClient<D> {
+        HttpClient {
+            agent: self.agent,
+            timeout: self.timeout,
+            proxy: self.proxy,
+            token: self.token,
+            retry: self.retry,
+            clock,
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the timeout of each attempt, after which it counts as a connection failure.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.agent = agent(self.timeout, self.proxy.clone());
+        self
+    }
+
+    /// Sends every call through the proxy at `url`, e.g. `http://proxy.internal:3128`,
+    /// instead of the one of the environment.
+    pub fn with_proxy(mut self, url: &str) -> Result<Self, HttpError> {
+        let proxy = Proxy::new(url)
+            .map_err(|err| HttpError::InvalidProxy(url.to_owned(), err.to_string()))?;
+
+        self.proxy = Some(proxy);
+        self.agent = agent(self.timeout, self.proxy.clone());
+        Ok(self)
+    }
+
+    /// Authenticates every call with a bearer token.
+    pub fn with_bearer_token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Reads the resource at `url`.
+    pub fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+        self.call(url, || {
+            let mut request = self.agent.get(url);
+
+            if let Some(token) = &self.token {
+                request = request.header("Authorization", format!("Bearer {token}"));
+            }
+
+            request.call()
+        })
+    }
+
+    /// Stores `body` as the resource at `url`.
+    pub fn put(&self, url: &str, body: &[u8]) -> Result<HttpResponse, HttpError> {
+        self.call(url, || {
+            let mut request = self.agent.put(url);
+
+            if let Some(token) = &self.token {
+                request = request.header("Authorization", format!("Bearer {token}"));
+            }
+
+            request.send(body)
+        })
+    }
+
+    /// Makes the call until it gets an answer or runs out of attempts.
+    fn call<F>(&self, url: &str, send: F) -> Result<HttpResponse, HttpError>
+    where
+        F: Fn() -> Result<Response<Body>, ureq::Error>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let last = attempt + 1 >= self.retry.attempts;
+
+            let wait = match send() {
+                Err(err) if last => {
+                    return Err(HttpError::Unreachable(url.to_owned(), err.to_string()))
+                }
+                Err(_) => self.retry.delay(attempt),
+                Ok(mut response) => {
+                    let status = response.status().as_u16();
+                    let retry_after = response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.trim().parse().ok())
+                        .map(Duration::from_secs);
+
+                    let rate_limited = status == 429;
+                    let too_long = retry_after.is_some_and(|wait| wait > self.retry.max_delay);
+
+                    if rate_limited && (last || too_long) {
+                        return Err(HttpError::RateLimited(url.to_owned(), retry_after));
+                    }
+
+                    if !(rate_limited || status >= 500) || last || too_long {
+                        let body = response
+                            .body_mut()
+                            .with_config()
+                            .limit(u64::MAX)
+                            .read_to_vec()
+                            .map_err(|err| {
+                                HttpError::Unreachable(url.to_owned(), err.to_string())
+                            })?;
+
+                        return Ok(HttpResponse { status, body });
+                    }
+
+                    retry_after.unwrap_or_else(|| self.retry.delay(attempt))
+                }
+            };
+
+            self.clock.sleep(wait);
+            attempt += 1;
+        }
+    }
+}
+
+fn agent(timeout: Option<Duration>, proxy: Option<Proxy>) -> Agent {
+    Agent::config_builder()
+        .timeout_global(timeout)
+        .proxy(proxy)
+        // Statuses are answers, interpreted by the callers.
+        .http_status_as_error(false)
+        .build()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use crate::clock::ManualClock;
+    use crate::errors::HttpError;
+
+    use super::{HttpClient, HttpResponse, RetryPolicy};
+
+    /// Answers one request per response, in order, closing each connection.
+    fn serve(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    pub fn when_remote_fails_or_rate_limits_should_back_off_and_retry() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 2\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        ]);
+
+        let clock = ManualClock::new();
+        let client = HttpClient::new()
+            .with_clock(&clock)
+            .with_retry(RetryPolicy::default());
+
+        let retried = client.get(&format!("{url}/a"));
+        server.join().unwrap();
+
+        assert_eq!(
+            retried,
+            Ok(HttpResponse {
+                status: 200,
+                body: b"ok".to_vec()
+            })
+        );
+        // The backoff after the first attempt, then the wait the remote asked for.
+        assert_eq!(clock.elapsed(), Duration::from_millis(2_200));
+    }
+
+    #[test]
+    pub fn when_remote_asks_to_wait_too_long_should_return_rate_limited() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 60\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+
+        let clock = ManualClock::new();
+        let client = HttpClient::new()
+            .with_clock(&clock)
+            .with_retry(RetryPolicy::default());
+
+        let limited = client.get(&format!("{url}/b"));
+        server.join().unwrap();
+
+        assert_eq!(
+            limited,
+            Err(HttpError::RateLimited(
+                format!("{url}/b"),
+                Some(Duration::from_secs(60))
+            ))
+        );
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    pub fn when_remote_answers_client_error_should_return_it_without_retrying() {
+        let (url, server) = serve(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+
+        let clock = ManualClock::new();
+        let client = HttpClient::new()
+            .with_clock(&clock)
+            .with_retry(RetryPolicy::default());
+
+        let missing = client.get(&format!("{url}/c"));
+        server.join().unwrap();
+
+        assert_eq!(missing.unwrap().status, 404);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    pub fn when_proxy_is_invalid_should_return_error() {
+        assert!(matches!(
+            HttpClient::new().with_proxy("not a proxy"),
+            Err(HttpError::InvalidProxy(..))
+        ));
+    }
+}
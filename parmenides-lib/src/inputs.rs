@@ -0,0 +1,178 @@
+//! # Inputs
+//!
+//! By default every file inside the directory of a project is one of its inputs, so changing
+//! it affects the project. [`Inputs`] narrows that down with globs, e.g. `["src/**",
+//! "!**/*.md"]`, so changes to documentation or fixtures don't mark the project affected.
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::errors::InputsError;
+
+/// The input globs of a project, matched against paths relative to its directory.
+///
+/// The globs are evaluated in order, and the last one matching a path decides: a glob starting
+/// with `!` excludes the path, any other includes it. Paths no glob matches are included only
+/// if every glob is an exclusion, so `["!docs"]` excludes the docs and nothing else.
+///
+/// In a glob, `*` matches within a path component, `?` matches one character, `[...]` matches
+/// a character class, and `**` matches any number of components. A glob matching a directory
+/// also matches everything inside it.
+#[derive(Debug, Clone)]
+pub struct Inputs {
+    globs: Vec<String>,
+    patterns: Vec<(bool, Regex)>,
+    include_unmatched: bool,
+}
+
+impl Inputs {
+    /// Compiles the input globs of a project.
+    ///
+    /// # Returns
+    /// - `Ok(Inputs)`: The compiled globs.
+    /// - `Err(InputsError)`: If a glob is invalid, e.g. with a character class like `[z-a]`.
+    pub fn new<I, S>(globs: I) -> Result<Self, InputsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let globs: Vec<String> = globs.into_iter().map(Into::into).collect();
+        let mut patterns = Vec::with_capacity(globs.len());
+
+        for glob in &globs {
+            let (excluded, pattern) = match glob.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, glob.as_str()),
+            };
+
+            let regex = Regex::new(&to_regex(pattern))
+                .map_err(|err| InputsError::InvalidGlob(glob.clone(), err))?;
+
+            patterns.push((excluded, regex));
+        }
+
+        let include_unmatched = patterns.iter().all(|(excluded, _)| *excluded);
+
+        Ok(Self {
+            globs,
+            patterns,
+            include_unmatched,
+        })
+    }
+
+    /// Returns the globs, as declared.
+    pub fn globs(&self) -> &[String] {
+        &self.globs
+    }
+
+    /// Returns `true` if the path, relative to the directory of the project, is an input.
+    pub fn includes<P>(&self, relative: &P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        // Globs always use `/`, whatever the platform separator.
+        let relative: Vec<_> = relative
+            .as_ref()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let relative = relative.join("/");
+
+        self.patterns
+            .iter()
+            .rev()
+            .find(|(_, regex)| regex.is_match(&relative))
+            .map_or(self.include_unmatched, |(excluded, _)| !excluded)
+    }
+}
+
+/// Translates a glob into an anchored regular expression.
+fn to_regex(glob: &str) -> String {
+    let glob = glob.trim_start_matches('/').trim_end_matches('/');
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from("^");
+    let mut index = 0;
+
+    while index < chars.len() {
+        match chars[index] {
+            '*' if chars.get(index + 1) == Some(&'*') => {
+                index += 1;
+
+                if chars.get(index + 1) == Some(&'/') {
+                    // `**/` matches no component too, so `**/*.md` matches `README.md`.
+                    index += 1;
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[index + 1..].iter().position(|char| *char == ']') {
+                Some(length) => {
+                    let mut class = &chars[index + 1..index + 1 + length];
+
+                    regex.push('[');
+
+                    if let Some(('!', negated)) = class.split_first() {
+                        regex.push('^');
+                        class = negated;
+                    }
+
+                    for char in class {
+                        // Only ranges keep their meaning, as in a glob.
+                        if matches!(char, '\\' | '[' | '&' | '~' | '^') {
+                            regex.push('\\');
+                        }
+
+                        regex.push(*char);
+                    }
+
+                    regex.push(']');
+                    index += length + 1;
+                }
+                // An unclosed bracket is just a character.
+                None => regex.push_str("\\["),
+            },
+            char => regex.push_str(&regex::escape(&char.to_string())),
+        }
+
+        index += 1;
+    }
+
+    // A matching directory includes everything inside it.
+    regex.push_str("(?:/.*)?$");
+
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::InputsError;
+
+    use super::Inputs;
+
+    #[test]
+    pub fn when_matching_inputs_should_let_last_matching_glob_decide() {
+        let inputs = Inputs::new(["src/**", "Cargo.toml", "!**/*.md", "src/keep/*.md"]).unwrap();
+        let exclusions = Inputs::new(["!docs", "!fixtures/*.json"]).unwrap();
+
+        assert!(inputs.includes(&"src/lib.rs"));
+        assert!(inputs.includes(&"src/nested/mod.rs"));
+        assert!(inputs.includes(&"Cargo.toml"));
+        assert!(inputs.includes(&"src/keep/notes.md"));
+        assert!(!inputs.includes(&"src/README.md"));
+        assert!(!inputs.includes(&"README.md"));
+        assert!(!inputs.includes(&"tests/fixture.rs"));
+
+        assert!(exclusions.includes(&"src/lib.rs"));
+        assert!(exclusions.includes(&"fixtures/nested/data.json"));
+        assert!(!exclusions.includes(&"docs/guide/intro.md"));
+        assert!(!exclusions.includes(&"fixtures/data.json"));
+
+        assert!(matches!(
+            Inputs::new(["[z-a].rs"]),
+            Err(InputsError::InvalidGlob(..))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+//! # Last green
+//!
+//! Records the commit of the last run whose tasks all passed, so the next runs diff from it
+//! instead of a fixed branch, like `nx affected --base=last-release`. When `main` moves
+//! forward between pipeline runs, only what changed since the last green run is tested
+//! again, and a failure keeps being tested until it is fixed.
+//!
+//! Commits are kept in a [`CacheStore`] under [`NAMESPACE`], one per pipeline, by default
+//! in the [`crate::cache::LocalCacheStore`] of the workspace. Any other store can be
+//! plugged in, e.g. one shared between the machines of the CI.
+use crate::cache::CacheStore;
+use crate::errors::CacheError;
+
+/// The namespace of the cache store the commits are kept under.
+pub const NAMESPACE: &str = "last-green";
+
+/// The pipeline recorded when none is named.
+pub const DEFAULT_PIPELINE: &str = "default";
+
+/// Reads and records the commits of the last green runs of each pipeline, e.g. `main` or
+/// `nightly`, in a [`CacheStore`].
+pub struct LastGreen<'s> {
+    store: &'s dyn CacheStore,
+}
+
+impl<'s> LastGreen<'s> {
+    pub fn new(store: &'s dyn CacheStore) -> Self {
+        Self { store }
+    }
+
+    /// Returns the commit of the last green run of `pipeline`.
+    ///
+    /// # Returns
+    /// - `Ok(Some(String))`: The id of the commit.
+    /// - `Ok(None)`: If no green run of the pipeline was recorded.
+    /// - `Err(CacheError)`: If the store failed, or the entry isn't the id of a commit.
+    pub fn get(&self, pipeline: &str) -> Result<Option<String>, CacheError> {
+        let key = key(pipeline);
+
+        let Some(content) = self.store.get(&key)? else {
+            return Ok(None);
+        };
+
+        let commit = String::from_utf8_lossy(&content).trim().to_owned();
+
+        if !is_commit_id(&commit) {
+            return Err(CacheError::Corrupt(
+                key,
+                format!("{commit} isn't a commit id"),
+            ));
+        }
+
+        Ok(Some(commit))
+    }
+
+    /// Records `commit` as the last green run of `pipeline`, replacing the previous one.
+    pub fn record(&self, pipeline: &str, commit: &str) -> Result<(), CacheError> {
+        self.store.put(&key(pipeline), commit.as_bytes())
+    }
+}
+
+fn key(pipeline: &str) -> String {
+    format!("{NAMESPACE}/{pipeline}")
+}
+
+/// Returns `true` for a full SHA-1 or SHA-256 commit id.
+fn is_commit_id(commit: &str) -> bool {
+    matches!(commit.len(), 40 | 64) && commit.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::{CacheStore, MemoryCacheStore};
+    use crate::errors::CacheError;
+
+    use super::LastGreen;
+
+    #[test]
+    pub fn when_recording_green_runs_should_keep_the_last_commit_of_each_pipeline() {
+        let store = MemoryCacheStore::new();
+        let last_green = LastGreen::new(&store);
+
+        let first = "a".repeat(40);
+        let second = "b".repeat(40);
+
+        let missing = last_green.get("main");
+
+        last_green.record("main", &first).unwrap();
+        last_green.record("main", &second).unwrap();
+        last_green.record("nightly", &first).unwrap();
+
+        store.put("last-green/broken", b"main").unwrap();
+
+        assert!(matches!(missing, Ok(None)));
+        assert_eq!(last_green.get("main").unwrap(), Some(second));
+        assert_eq!(last_green.get("nightly").unwrap(), Some(first));
+        assert!(matches!(
+            last_green.get("broken"),
+            Err(CacheError::Corrupt(key, _)) if key == "last-green/broken"
+        ));
+        assert_eq!(
+            store.keys(),
+            ["last-green/broken", "last-green/main", "last-green/nightly"]
+        );
+    }
+}
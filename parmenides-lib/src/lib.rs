@@ -1,5 +1,55 @@
+pub mod affected;
+#[cfg(feature = "git")]
+pub mod bisect;
+pub mod cache;
+pub mod ci;
+pub mod clock;
+pub mod constraints;
+pub mod context;
+pub mod credentials;
 pub mod declarations;
+pub mod deprecation;
+#[cfg(feature = "runner")]
+pub mod determinism;
 pub mod diff_engine;
+#[cfg(feature = "discovery")]
+pub mod discovery;
+pub mod drift;
+pub mod edit;
+pub mod encryption;
 pub mod errors;
+pub mod export;
+pub mod file_system;
+pub mod generate;
+pub mod health;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod inputs;
+pub mod last_green;
+pub mod lint;
+pub mod parameters;
+pub mod path_roots;
+pub mod preset;
+pub mod process;
+pub mod progress;
 pub mod project;
+pub mod redaction;
+pub mod refactor;
+pub mod schema;
+pub mod selector;
+pub mod shard;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod sort;
+pub mod stats;
+pub mod tasks;
+#[cfg(target_os = "linux")]
+pub mod trace;
+pub mod unknown_keys;
+pub mod warnings;
+pub mod watch;
 pub mod workspace;
+
+pub use affected::{compute_affected, mark_changed_paths, AffectedState, AffectedStrategy};
+#[cfg(feature = "git")]
+pub use affected::{compute_merge_affected, MergeAffected};
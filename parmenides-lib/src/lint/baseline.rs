@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::BaselineError;
+use crate::workspace::Workspace;
+
+use super::{Diagnostic, LintReport};
+
+/// A known violation recorded in a [`Baseline`].
+///
+/// Entries reference projects by path rather than by ID, so they stay valid when the workspace
+/// is rebuilt.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    /// The name of the rule that reported the violation.
+    pub rule: String,
+    /// The path of the project the violation is attributed to.
+    pub project: PathBuf,
+    /// The message of the violation.
+    pub message: String,
+}
+
+/// The set of existing violations that are tolerated.
+///
+/// A baseline lets legacy monorepos adopt rules incrementally: the current violations are
+/// recorded into a committed file, and only violations that are not in it fail the check.
+#[derive(Debug, PartialEq, Default, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The recorded violations, sorted so the file has stable diffs.
+    pub entries: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    /// Records every diagnostic of the report.
+    pub fn from_report(report: &LintReport, workspace: &Workspace) -> Self {
+        let mut entries: Vec<_> = report
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| entry_for(diagnostic, workspace))
+            .collect();
+
+        entries.sort();
+
+        Self { entries }
+    }
+
+    /// Reads a baseline from a JSON file.
+    pub fn load<P>(path: P) -> Result<Self, BaselineError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| BaselineError::Io(path.to_path_buf(), err))?;
+
+        serde_json::from_str(&content).map_err(|err| BaselineError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Writes the baseline to a JSON file.
+    pub fn save<P>(&self, path: P) -> Result<(), BaselineError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| BaselineError::Parse(path.to_path_buf(), err))?;
+
+        std::fs::write(path, content + "\n")
+            .map_err(|err| BaselineError::Io(path.to_path_buf(), err))
+    }
+
+    /// Returns a report with only the diagnostics that are not in the baseline.
+    ///
+    /// Each baseline entry tolerates a single diagnostic, so a violation that appears more often
+    /// than it was recorded is still reported.
+    pub fn filter_new(&self, report: &LintReport, workspace: &Workspace) -> LintReport {
+        let mut remaining: HashMap<&BaselineEntry, usize> = HashMap::new();
+
+        for entry in &self.entries {
+            *remaining.entry(entry).or_default() += 1;
+        }
+
+        let diagnostics = report
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                let Some(entry) = entry_for(diagnostic, workspace) else {
+                    return true;
+                };
+
+                match remaining.get_mut(&entry) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        false
+                    }
+                    _ => true,
+                }
+            })
+            .cloned()
+            .collect();
+
+        LintReport::new(diagnostics)
+    }
+}
+
+fn entry_for(diagnostic: &Diagnostic, workspace: &Workspace) -> Option<BaselineEntry> {
+    let project = workspace.get_project(diagnostic.project)?;
+
+    Some(BaselineEntry {
+        rule: diagnostic.rule.clone(),
+        project: project.path.clone(),
+        message: diagnostic.message.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::{LintDeclaration, WorkspaceDeclaration};
+    use crate::lint::Linter;
+
+    use super::Baseline;
+
+    #[test]
+    pub fn when_filtering_with_baseline_should_only_report_new_violations() {
+        let linter = Linter::from_declaration(&LintDeclaration::default()).unwrap();
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/legacy", "legacy", None);
+
+        let before = declaration.build_workspace().unwrap();
+        let baseline = Baseline::from_report(&linter.run(&before), &before);
+
+        assert_eq!(baseline.entries.len(), 1);
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/legacy", "legacy", None);
+        declaration.add_project("/repo/new", "new", None);
+
+        let after = declaration.build_workspace().unwrap();
+        let report = baseline.filter_new(&linter.run(&after), &after);
+
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(
+            after
+                .get_project(report.diagnostics[0].project)
+                .unwrap()
+                .name,
+            "new"
+        );
+    }
+
+    #[test]
+    pub fn when_saving_and_loading_should_round_trip() {
+        let linter = Linter::from_declaration(&LintDeclaration::default()).unwrap();
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/legacy", "legacy", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+        let baseline = Baseline::from_report(&linter.run(&workspace), &workspace);
+
+        let path =
+            std::env::temp_dir().join(format!("parmenides-baseline-{}.json", std::process::id()));
+
+        baseline.save(&path).unwrap();
+        let loaded = Baseline::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, baseline);
+    }
+}
@@ -0,0 +1,253 @@
+//! # Lint
+//!
+//! The lint framework checks the workspace graph against a set of rules, such as a maximum
+//! dependency depth or forbidden edges between applications. Each rule reports violations, and
+//! the [`Linter`] turns them into [`Diagnostic`]s using the severity configured for the rule.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::declarations::LintDeclaration;
+use crate::errors::LintConfigError;
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+mod baseline;
+mod report;
+pub mod rules;
+
+pub use baseline::{Baseline, BaselineEntry};
+pub use report::LintReport;
+
+/// How seriously a rule violation is taken.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule is disabled.
+    Off,
+    /// Violations are reported but don't fail the check.
+    Warning,
+    /// Violations fail the check.
+    Error,
+}
+
+/// A single violation reported by a [`LintRule`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Violation {
+    /// The project the violation is attributed to.
+    pub project: ProjectId,
+    /// A human-readable explanation of the violation.
+    pub message: String,
+}
+
+/// A rule that checks the workspace graph.
+pub trait LintRule {
+    /// The unique name of the rule, used in configuration and reports.
+    fn name(&self) -> &str;
+
+    /// A short description of what the rule checks.
+    fn description(&self) -> &str;
+
+    /// The severity used when the configuration doesn't override it.
+    fn default_severity(&self) -> Severity;
+
+    /// Checks the workspace, returning every violation found.
+    fn check(&self, workspace: &Workspace) -> Vec<Violation>;
+}
+
+/// A violation together with the rule that reported it and its effective severity.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    /// The name of the rule that reported the violation.
+    pub rule: String,
+    /// The effective severity of the violation.
+    pub severity: Severity,
+    /// The project the violation is attributed to.
+    pub project: ProjectId,
+    /// A human-readable explanation of the violation.
+    pub message: String,
+}
+
+/// Runs a set of [`LintRule`]s against a workspace.
+#[derive(Default)]
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+    severities: HashMap<String, Severity>,
+}
+
+impl Linter {
+    /// Creates a linter without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a linter with the built-in rules and the custom rules of the declaration.
+    ///
+    /// # Returns
+    /// - `Ok(Linter)`: The configured linter.
+    /// - `Err(LintConfigError)`: If a custom rule is invalid.
+    pub fn from_declaration(declaration: &LintDeclaration) -> Result<Self, LintConfigError> {
+        let mut linter = Self::new();
+
+        linter.add_rule(rules::MaxDependencyDepth::new(
+            declaration.max_dependency_depth,
+        ));
+        linter.add_rule(rules::NoAppToApp::new(declaration.app_tag.clone()));
+        linter.add_rule(rules::NoCircularTagLayers);
+        linter.add_rule(rules::OrphanProject);
+        linter.add_rule(rules::DeprecatedDependency);
+
+        for custom in &declaration.custom {
+            linter.add_rule(rules::CustomRule::from_declaration(custom)?);
+        }
+
+        for (rule, severity) in &declaration.rules {
+            linter.set_severity(rule.clone(), *severity);
+        }
+
+        Ok(linter)
+    }
+
+    /// Adds a rule to the linter.
+    pub fn add_rule<R>(&mut self, rule: R)
+    where
+        R: LintRule + 'static,
+    {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Overrides the severity of a rule.
+    pub fn set_severity<S>(&mut self, rule: S, severity: Severity)
+    where
+        S: Into<String>,
+    {
+        self.severities.insert(rule.into(), severity);
+    }
+
+    /// Returns the rules of the linter.
+    pub fn rules(&self) -> impl Iterator<Item = &dyn LintRule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Runs every enabled rule against the workspace.
+    pub fn run(&self, workspace: &Workspace) -> LintReport {
+        let mut diagnostics = Vec::new();
+
+        for rule in &self.rules {
+            let severity = self
+                .severities
+                .get(rule.name())
+                .copied()
+                .unwrap_or_else(|| rule.default_severity());
+
+            if severity == Severity::Off {
+                continue;
+            }
+
+            diagnostics.extend(
+                rule.check(workspace)
+                    .into_iter()
+                    .map(|violation| Diagnostic {
+                        rule: rule.name().to_owned(),
+                        severity,
+                        project: violation.project,
+                        message: violation.message,
+                    }),
+            );
+        }
+
+        LintReport::new(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::{CustomRuleDeclaration, LintDeclaration, WorkspaceDeclaration};
+    use crate::errors::LintConfigError;
+
+    use super::{Linter, Severity};
+
+    #[test]
+    pub fn when_linting_should_apply_configured_severities() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration
+            .add_project("/repo/apps/admin", "admin", None)
+            .tags = vec!["app".to_owned()];
+        declaration
+            .add_project(
+                "/repo/apps/web",
+                "web",
+                Some(vec!["/repo/apps/admin".into()]),
+            )
+            .tags = vec!["app".to_owned()];
+        declaration.add_project("/repo/tools", "tools", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let mut configuration = LintDeclaration::default();
+        configuration
+            .rules
+            .insert("orphan-project".to_owned(), Severity::Off);
+
+        let report = Linter::from_declaration(&configuration)
+            .unwrap()
+            .run(&workspace);
+
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].rule, "no-app-to-app");
+        assert_eq!(report.diagnostics[0].severity, Severity::Error);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    pub fn when_custom_rule_is_declared_should_report_forbidden_edges() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/db", "db", None).tags = vec!["infra".to_owned()];
+        declaration
+            .add_project(
+                "/repo/libs/button",
+                "button",
+                Some(vec!["/repo/libs/db".into()]),
+            )
+            .tags = vec!["ui".to_owned()];
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let mut configuration = LintDeclaration::default();
+        configuration.custom.push(CustomRuleDeclaration {
+            name: "ui-not-infra".to_owned(),
+            source: "tag:ui".to_owned(),
+            forbidden: "tag:infra".to_owned(),
+            message: "{source} must not use {target}".to_owned(),
+            severity: Some(Severity::Warning),
+        });
+
+        let report = Linter::from_declaration(&configuration)
+            .unwrap()
+            .run(&workspace);
+
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].rule, "ui-not-infra");
+        assert_eq!(report.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(report.diagnostics[0].message, "button must not use db");
+    }
+
+    #[test]
+    pub fn when_custom_rule_has_invalid_selector_should_return_error() {
+        let mut configuration = LintDeclaration::default();
+        configuration.custom.push(CustomRuleDeclaration {
+            name: "broken".to_owned(),
+            source: "owner:me".to_owned(),
+            forbidden: "*".to_owned(),
+            message: String::new(),
+            severity: None,
+        });
+
+        assert!(matches!(
+            Linter::from_declaration(&configuration),
+            Err(LintConfigError::InvalidSelector(name, _)) if name == "broken"
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt::Write;
+
+use serde_json::{json, Value};
+
+use crate::sort::{natural_cmp, sort_key, SortKey};
+use crate::workspace::Workspace;
+
+use super::{Diagnostic, Linter, Severity};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The diagnostics produced by a [`Linter`] run.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LintReport {
+    /// Every diagnostic, in the order the rules reported them.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    pub(crate) fn new(diagnostics: Vec<Diagnostic>) -> Self {
+        Self { diagnostics }
+    }
+
+    /// Returns `true` if any diagnostic has the [`Severity::Error`] severity.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Renders the report as human-readable text, one diagnostic per line, sorted by project
+    /// path and then by rule, see [`crate::sort`].
+    pub fn to_text(&self, workspace: &Workspace) -> String {
+        let mut lines: Vec<(SortKey, &Diagnostic, String)> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let path = workspace
+                    .get_project(diagnostic.project)
+                    .map(|project| project.path.display().to_string())
+                    .unwrap_or_default();
+
+                (sort_key(&path), diagnostic, path)
+            })
+            .collect();
+
+        // Stable, so the diagnostics of a rule for a project keep the order they were reported in.
+        lines.sort_by(|(a_key, a, _), (b_key, b, _)| {
+            a_key.cmp(b_key).then_with(|| natural_cmp(&a.rule, &b.rule))
+        });
+
+        let mut output = String::new();
+
+        for (_, diagnostic, path) in lines {
+            let _ = writeln!(
+                output,
+                "{}[{}] {}: {}",
+                severity_name(diagnostic.severity),
+                diagnostic.rule,
+                path,
+                diagnostic.message
+            );
+        }
+
+        output
+    }
+
+    /// Renders the report in the SARIF 2.1.0 format, as understood by code scanning tools.
+    pub fn to_sarif(&self, linter: &Linter, workspace: &Workspace) -> Value {
+        let rules: Vec<Value> = linter
+            .rules()
+            .map(|rule| {
+                json!({
+                    "id": rule.name(),
+                    "shortDescription": { "text": rule.description() },
+                })
+            })
+            .collect();
+
+        let results: Vec<Value> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let uri = workspace
+                    .get_project(diagnostic.project)
+                    .map(|project| project.path.display().to_string())
+                    .unwrap_or_default();
+
+                json!({
+                    "ruleId": diagnostic.rule,
+                    "level": severity_name(diagnostic.severity),
+                    "message": { "text": diagnostic.message },
+                    "locations": [{
+                        "physicalLocation": { "artifactLocation": { "uri": uri } }
+                    }],
+                })
+            })
+            .collect();
+
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": { "name": "parmenides", "rules": rules } },
+                "results": results,
+            }],
+        })
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Off => "none",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::{LintDeclaration, WorkspaceDeclaration};
+    use crate::lint::Linter;
+
+    #[test]
+    pub fn when_rendering_should_include_rule_and_path() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/lonely", "lonely", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let linter = Linter::from_declaration(&LintDeclaration::default()).unwrap();
+        let report = linter.run(&workspace);
+
+        assert_eq!(
+            report.to_text(&workspace),
+            "warning[orphan-project] /repo/lonely: lonely has no dependencies and no dependents\n"
+        );
+
+        let sarif = report.to_sarif(&linter, &workspace);
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(sarif["runs"][0]["results"][0]["ruleId"], "orphan-project");
+        assert_eq!(sarif["runs"][0]["results"][0]["level"], "warning");
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"]
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    pub fn when_rendering_text_should_sort_projects_naturally() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for path in ["/repo/lib10", "/repo/Lib9", "/repo/lib1"] {
+            declaration.add_project(path, path.trim_start_matches("/repo/"), None);
+        }
+
+        let workspace = declaration.build_workspace().unwrap();
+        let report = Linter::from_declaration(&LintDeclaration::default())
+            .unwrap()
+            .run(&workspace);
+
+        let text = report.to_text(&workspace);
+        let paths: Vec<&str> = text
+            .lines()
+            .filter_map(|line| line.split(' ').nth(1))
+            .collect();
+
+        assert_eq!(paths, vec!["/repo/lib1:", "/repo/Lib9:", "/repo/lib10:"]);
+    }
+}
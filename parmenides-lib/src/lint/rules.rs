@@ -0,0 +1,492 @@
+//! The built-in [`LintRule`]s.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::declarations::CustomRuleDeclaration;
+use crate::errors::LintConfigError;
+use crate::selector::Selector;
+use crate::workspace::Workspace;
+
+use super::{LintRule, Severity, Violation};
+
+/// Reports projects whose longest dependency chain is deeper than the allowed maximum.
+pub struct MaxDependencyDepth {
+    max_depth: usize,
+}
+
+impl MaxDependencyDepth {
+    pub fn new(max_depth: usize) -> Self {
+        Self { max_depth }
+    }
+}
+
+impl LintRule for MaxDependencyDepth {
+    fn name(&self) -> &str {
+        "max-dependency-depth"
+    }
+
+    fn description(&self) -> &str {
+        "Limits the length of the longest dependency chain of a project"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, workspace: &Workspace) -> Vec<Violation> {
+        // Dependencies are always added before their dependents, so the arena is already in
+        // dependency order and each depth only needs the depths computed before it.
+        let mut depths = vec![0; workspace.id_bound()];
+        let mut violations = Vec::new();
+
+        for (id, project) in workspace.iter_with_ids() {
+            let depth = project
+                .dependencies
+                .iter()
+                .flatten()
+                .map(|dependency| depths[dependency.into_inner()] + 1)
+                .max()
+                .unwrap_or(0);
+
+            depths[id.into_inner()] = depth;
+
+            if depth > self.max_depth {
+                violations.push(Violation {
+                    project: id,
+                    message: format!(
+                        "{} has a dependency depth of {depth}, the maximum is {}",
+                        project.name, self.max_depth
+                    ),
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+/// Reports applications that depend on other applications.
+pub struct NoAppToApp {
+    app_tag: String,
+}
+
+impl NoAppToApp {
+    pub fn new<S>(app_tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            app_tag: app_tag.into(),
+        }
+    }
+}
+
+impl LintRule for NoAppToApp {
+    fn name(&self) -> &str {
+        "no-app-to-app"
+    }
+
+    fn description(&self) -> &str {
+        "Forbids applications from depending on other applications"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, workspace: &Workspace) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        for (id, project) in workspace.iter_with_ids() {
+            if !project.has_tag(&self.app_tag) {
+                continue;
+            }
+
+            for dependency in project.dependencies.iter().flatten() {
+                let Some(dependency) = workspace.get_project(*dependency) else {
+                    continue;
+                };
+
+                if dependency.has_tag(&self.app_tag) {
+                    violations.push(Violation {
+                        project: id,
+                        message: format!(
+                            "The application {} depends on the application {}",
+                            project.name, dependency.name
+                        ),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Reports dependency edges that create a cycle between tag layers, e.g. a `ui` project
+/// depending on a `data` project while some `data` project depends on a `ui` project.
+pub struct NoCircularTagLayers;
+
+impl LintRule for NoCircularTagLayers {
+    fn name(&self) -> &str {
+        "no-circular-tag-layers"
+    }
+
+    fn description(&self) -> &str {
+        "Forbids dependency cycles between the layers defined by tags"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, workspace: &Workspace) -> Vec<Violation> {
+        let mut layers: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+
+        for project in workspace.iter() {
+            for dependency in project.dependencies.iter().flatten() {
+                let Some(dependency) = workspace.get_project(*dependency) else {
+                    continue;
+                };
+
+                for from in &project.tags {
+                    for to in &dependency.tags {
+                        if from != to {
+                            layers.entry(from).or_default().insert(to);
+                        }
+                    }
+                }
+            }
+        }
+
+        let reaches = |from: &str, target: &str| {
+            let mut visited = BTreeSet::new();
+            let mut stack = vec![from];
+
+            while let Some(current) = stack.pop() {
+                if current == target {
+                    return true;
+                }
+
+                if visited.insert(current) {
+                    stack.extend(layers.get(current).into_iter().flatten());
+                }
+            }
+
+            false
+        };
+
+        let mut violations = Vec::new();
+
+        for (id, project) in workspace.iter_with_ids() {
+            for dependency in project.dependencies.iter().flatten() {
+                let Some(dependency) = workspace.get_project(*dependency) else {
+                    continue;
+                };
+
+                for from in &project.tags {
+                    for to in &dependency.tags {
+                        if from != to && reaches(to, from) {
+                            violations.push(Violation {
+                                project: id,
+                                message: format!(
+                                    "{} ({from}) depends on {} ({to}), but the layer {to} also depends on {from}",
+                                    project.name, dependency.name
+                                ),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Reports projects that have neither dependencies nor dependents.
+pub struct OrphanProject;
+
+impl LintRule for OrphanProject {
+    fn name(&self) -> &str {
+        "orphan-project"
+    }
+
+    fn description(&self) -> &str {
+        "Reports projects that are not connected to the rest of the graph"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, workspace: &Workspace) -> Vec<Violation> {
+        workspace
+            .iter_with_ids()
+            .filter(|(_, project)| {
+                project.dependents.is_empty()
+                    && project.dependencies.as_ref().is_none_or(Vec::is_empty)
+            })
+            .map(|(id, project)| Violation {
+                project: id,
+                message: format!("{} has no dependencies and no dependents", project.name),
+            })
+            .collect()
+    }
+}
+
+/// Reports dependencies on deprecated projects, see [`crate::project::Deprecation`].
+///
+/// Every such edge is reported, so a [`crate::lint::Baseline`] of the existing ones only warns
+/// about the edges added since.
+pub struct DeprecatedDependency;
+
+impl LintRule for DeprecatedDependency {
+    fn name(&self) -> &str {
+        "deprecated-dependency"
+    }
+
+    fn description(&self) -> &str {
+        "Reports projects depending on deprecated projects"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, workspace: &Workspace) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        for (id, project) in workspace.iter_with_ids() {
+            for dependency in project.dependencies() {
+                let Some(dependency) = workspace.get_project(*dependency) else {
+                    continue;
+                };
+                let Some(deprecation) = dependency.deprecation() else {
+                    continue;
+                };
+
+                let mut message = format!(
+                    "{} depends on {}, which is deprecated",
+                    project.name, dependency.name
+                );
+
+                if let Some(sunset) = deprecation.sunset() {
+                    message.push_str(&format!(" until {sunset}"));
+                }
+
+                if let Some(replacement) = deprecation
+                    .replacement()
+                    .and_then(|replacement| workspace.get_project(replacement))
+                {
+                    message.push_str(&format!(", use {} instead", replacement.name));
+                }
+
+                violations.push(Violation {
+                    project: id,
+                    message,
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+/// A user-defined rule that forbids projects matching one selector from depending on projects
+/// matching another.
+pub struct CustomRule {
+    name: String,
+    source: Selector,
+    forbidden: Selector,
+    message: String,
+    severity: Severity,
+}
+
+impl CustomRule {
+    pub fn new<N, M>(
+        name: N,
+        source: Selector,
+        forbidden: Selector,
+        message: M,
+        severity: Severity,
+    ) -> Self
+    where
+        N: Into<String>,
+        M: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            source,
+            forbidden,
+            message: message.into(),
+            severity,
+        }
+    }
+
+    /// Creates the rule from its declaration, parsing both selectors.
+    pub fn from_declaration(declaration: &CustomRuleDeclaration) -> Result<Self, LintConfigError> {
+        let parse = |expression: &str| {
+            Selector::parse(expression)
+                .map_err(|err| LintConfigError::InvalidSelector(declaration.name.clone(), err))
+        };
+
+        Ok(Self::new(
+            declaration.name.clone(),
+            parse(&declaration.source)?,
+            parse(&declaration.forbidden)?,
+            declaration.message.clone(),
+            declaration.severity.unwrap_or(Severity::Error),
+        ))
+    }
+}
+
+impl LintRule for CustomRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.message
+    }
+
+    fn default_severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, workspace: &Workspace) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        for (id, project) in workspace.iter_with_ids() {
+            if !self.source.matches(project) {
+                continue;
+            }
+
+            for dependency in project.dependencies.iter().flatten() {
+                let Some(dependency) = workspace.get_project(*dependency) else {
+                    continue;
+                };
+
+                if self.forbidden.matches(dependency) {
+                    violations.push(Violation {
+                        project: id,
+                        message: self
+                            .message
+                            .replace("{source}", &project.name)
+                            .replace("{target}", &dependency.name),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::lint::LintRule;
+
+    use crate::declarations::DeprecationDeclaration;
+
+    use super::{DeprecatedDependency, MaxDependencyDepth, NoCircularTagLayers, OrphanProject};
+
+    #[test]
+    pub fn when_chain_is_too_deep_should_report_max_depth() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/a", "a", None);
+        declaration.add_project("/repo/b", "b", Some(vec!["/repo/a".into()]));
+        declaration.add_project("/repo/c", "c", Some(vec!["/repo/b".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let violations = MaxDependencyDepth::new(1).check(&workspace);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].project,
+            workspace.get_id_by_path(&"/repo/c").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn when_tag_layers_form_a_cycle_should_report_both_edges() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/data", "data", None).tags = vec!["data".to_owned()];
+        declaration
+            .add_project("/repo/widgets", "widgets", None)
+            .tags = vec!["ui".to_owned()];
+        declaration
+            .add_project("/repo/page", "page", Some(vec!["/repo/data".into()]))
+            .tags = vec!["ui".to_owned()];
+        declaration
+            .add_project("/repo/store", "store", Some(vec!["/repo/widgets".into()]))
+            .tags = vec!["data".to_owned()];
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let mut projects: Vec<_> = NoCircularTagLayers
+            .check(&workspace)
+            .into_iter()
+            .map(|violation| {
+                workspace
+                    .get_project(violation.project)
+                    .unwrap()
+                    .name
+                    .clone()
+            })
+            .collect();
+        projects.sort();
+
+        assert_eq!(projects, vec!["page", "store"]);
+    }
+
+    #[test]
+    pub fn when_project_is_disconnected_should_report_orphan() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/a", "a", None);
+        declaration.add_project("/repo/b", "b", Some(vec!["/repo/a".into()]));
+        declaration.add_project("/repo/lonely", "lonely", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let violations = OrphanProject.check(&workspace);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].project,
+            workspace.get_id_by_path(&"/repo/lonely").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn when_depending_on_deprecated_project_should_report_edge() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/new", "new", None);
+        declaration.add_project("/repo/old", "old", None).deprecated =
+            Some(DeprecationDeclaration {
+                sunset: Some("2027-01-31".to_owned()),
+                replacement: Some("/repo/new".into()),
+            });
+        declaration.add_project("/repo/web", "web", Some(vec!["/repo/old".into()]));
+        declaration.add_project("/repo/api", "api", Some(vec!["/repo/new".into()]));
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let violations = DeprecatedDependency.check(&workspace);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].project,
+            workspace.get_id_by_path(&"/repo/web").unwrap()
+        );
+        assert_eq!(
+            violations[0].message,
+            "web depends on old, which is deprecated until 2027-01-31, use new instead"
+        );
+    }
+}
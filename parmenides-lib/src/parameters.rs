@@ -0,0 +1,213 @@
+//! Parameters are the values that can be referenced from task commands, cache namespaces and
+//! generator templates.
+//!
+//! They are made of the workspace-level constants declared in the
+//! [`crate::declarations::WorkspaceDeclaration`], optionally overridden by `key=value`
+//! arguments (e.g. the CLI's `--arg` flag). Templates reference them with `{{ key }}`, where
+//! the key is made of ASCII letters, digits, `_` and `-`, and starts with a letter or `_`.
+//!
+//! Braces around anything else, such as the Go template of `docker inspect --format '{{.Id}}'`
+//! or a `${{ github.sha }}` expression, are left as they are. A placeholder that would be read
+//! as a key is escaped with a backslash: `\{{ range }}` is written as `{{ range }}`.
+use std::collections::HashMap;
+
+use crate::errors::{InterpolateError, ParseArgumentError};
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+const ESCAPE: char = '\\';
+
+/// The set of named values available when interpolating templates.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Parameters {
+    values: HashMap<String, String>,
+}
+
+impl Parameters {
+    /// Creates the parameters from the workspace constants.
+    pub fn new(constants: HashMap<String, String>) -> Self {
+        Self { values: constants }
+    }
+
+    /// Sets a parameter, overriding any constant with the same key.
+    pub fn set<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.values.insert(key.into(), value.into());
+    }
+
+    /// Gets the value of a parameter.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Applies a list of `key=value` arguments as overrides.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If every argument was applied.
+    /// - `Err(ParseArgumentError)`: If an argument is malformed. Arguments before it are kept.
+    pub fn apply_arguments<I, S>(&mut self, arguments: I) -> Result<(), ParseArgumentError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for argument in arguments {
+            let (key, value) = parse_argument(argument.as_ref())?;
+
+            self.set(key, value);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces every `{{ key }}` placeholder in the template with the value of the parameter.
+    /// Braces around something other than a key are kept, and `\{{` is written as `{{`.
+    ///
+    /// # Returns
+    /// - `Ok(String)`: The interpolated template.
+    /// - `Err(InterpolateError)`: If a placeholder is not closed or references an unknown
+    ///   parameter.
+    pub fn interpolate(&self, template: &str) -> Result<String, InterpolateError> {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find(OPEN) {
+            let after_open = &rest[start + OPEN.len()..];
+
+            if let Some(before) = rest[..start].strip_suffix(ESCAPE) {
+                output.push_str(before);
+                output.push_str(OPEN);
+                rest = after_open;
+                continue;
+            }
+
+            output.push_str(&rest[..start]);
+
+            let end = after_open.find(CLOSE).ok_or_else(|| {
+                InterpolateError::UnclosedPlaceholder(template.len() - rest.len() + start)
+            })?;
+
+            let key = after_open[..end].trim();
+
+            if !is_parameter_key(key) {
+                output.push_str(&rest[start..start + OPEN.len() + end + CLOSE.len()]);
+                rest = &after_open[end + CLOSE.len()..];
+                continue;
+            }
+
+            let value = self
+                .get(key)
+                .ok_or_else(|| InterpolateError::UnknownParameter(key.to_owned()))?;
+
+            output.push_str(value);
+            rest = &after_open[end + CLOSE.len()..];
+        }
+
+        output.push_str(rest);
+
+        Ok(output)
+    }
+}
+
+/// Returns `true` if `key` can be referenced as a parameter: it is made of ASCII letters,
+/// digits, `_` and `-`, and starts with a letter or `_`.
+fn is_parameter_key(key: &str) -> bool {
+    key.starts_with(|char: char| char.is_ascii_alphabetic() || char == '_')
+        && key
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | '-'))
+}
+
+/// Parses a single `key=value` argument.
+pub fn parse_argument(argument: &str) -> Result<(&str, &str), ParseArgumentError> {
+    let (key, value) = argument
+        .split_once('=')
+        .ok_or_else(|| ParseArgumentError::MissingSeparator(argument.to_owned()))?;
+
+    let key = key.trim();
+
+    if key.is_empty() {
+        return Err(ParseArgumentError::EmptyKey(argument.to_owned()));
+    }
+
+    Ok((key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::errors::{InterpolateError, ParseArgumentError};
+
+    use super::Parameters;
+
+    fn parameters() -> Parameters {
+        Parameters::new(HashMap::from([
+            ("registry".to_owned(), "registry.example.com".to_owned()),
+            ("tag".to_owned(), "latest".to_owned()),
+        ]))
+    }
+
+    #[test]
+    pub fn when_interpolating_should_replace_placeholders() {
+        let result = parameters()
+            .interpolate("docker push {{ registry }}/app:{{tag}}")
+            .unwrap();
+
+        assert_eq!(result, "docker push registry.example.com/app:latest");
+    }
+
+    #[test]
+    pub fn when_braces_are_not_placeholders_should_keep_them() {
+        let result = parameters()
+            .interpolate(
+                r"docker inspect --format '{{.Id}}' {{ tag }} && echo ${{ github.sha }} \{{ range }}",
+            )
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "docker inspect --format '{{.Id}}' latest && echo ${{ github.sha }} {{ range }}"
+        );
+    }
+
+    #[test]
+    pub fn when_applying_arguments_should_override_constants() {
+        let mut parameters = parameters();
+
+        parameters.apply_arguments(["tag=v1.2.3"]).unwrap();
+
+        assert_eq!(parameters.get("tag"), Some("v1.2.3"));
+        assert_eq!(parameters.get("registry"), Some("registry.example.com"));
+    }
+
+    #[test]
+    pub fn when_argument_is_malformed_should_return_error() {
+        let mut parameters = parameters();
+
+        assert_eq!(
+            parameters.apply_arguments(["tag"]).unwrap_err(),
+            ParseArgumentError::MissingSeparator("tag".to_owned())
+        );
+        assert_eq!(
+            parameters.apply_arguments(["=value"]).unwrap_err(),
+            ParseArgumentError::EmptyKey("=value".to_owned())
+        );
+    }
+
+    #[test]
+    pub fn when_interpolating_unknown_or_unclosed_placeholder_should_return_error() {
+        let parameters = parameters();
+
+        assert_eq!(
+            parameters.interpolate("echo {{ missing }}").unwrap_err(),
+            InterpolateError::UnknownParameter("missing".to_owned())
+        );
+        assert_eq!(
+            parameters.interpolate("echo {{ tag").unwrap_err(),
+            InterpolateError::UnclosedPlaceholder(5)
+        );
+    }
+}
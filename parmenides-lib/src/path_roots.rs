@@ -0,0 +1,207 @@
+//! Path roots remap the root a repository is checked out at in one environment to the root it
+//! has in another, e.g. `/workspace` in a CI container to the checkout of the host runner, so
+//! diff results, cache entries, and logs stay portable between environments.
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use crate::context::Context;
+use crate::declarations::PathRootDeclaration;
+use crate::diff_engine::{ChangedFile, DiffEngine};
+use crate::errors::DiffEngineError;
+
+/// Rewrites paths under a set of roots.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PathRoots {
+    roots: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathRoots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the remappings that apply to the current environment. A declared root guarded by
+    /// `when_env` only applies when that environment variable is set in the current process.
+    pub fn from_declaration(declarations: &[PathRootDeclaration]) -> Self {
+        let mut roots = Self::new();
+
+        for declaration in declarations {
+            let applies = declaration
+                .when_env
+                .as_ref()
+                .is_none_or(|name| std::env::var_os(name).is_some());
+
+            if applies {
+                roots.add(&declaration.from, &declaration.to);
+            }
+        }
+
+        roots
+    }
+
+    /// Remaps paths under `from` to the same paths under `to`.
+    pub fn add<F, T>(&mut self, from: F, to: T)
+    where
+        F: AsRef<Path>,
+        T: AsRef<Path>,
+    {
+        self.roots
+            .push((from.as_ref().to_path_buf(), to.as_ref().to_path_buf()));
+        // Deeper roots first, so a nested root wins over its parent.
+        self.roots
+            .sort_by_key(|(from, _)| std::cmp::Reverse(from.components().count()));
+    }
+
+    /// Returns `true` if there is nothing to remap.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Remaps a path, or returns it unchanged if it is outside every root.
+    pub fn remap<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        self.roots
+            .iter()
+            .find_map(|(from, to)| path.strip_prefix(from).ok().map(|rest| to.join(rest)))
+            .map_or(Cow::Borrowed(path), Cow::Owned)
+    }
+
+    /// Remaps the roots appearing in free text, such as a log line.
+    pub fn remap_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+
+        for (from, to) in &self.roots {
+            let from = from.to_string_lossy();
+
+            if !from.is_empty() && text.contains(from.as_ref()) {
+                text = Cow::Owned(text.replace(from.as_ref(), &to.to_string_lossy()));
+            }
+        }
+
+        text
+    }
+
+    /// Remaps the roots appearing in `bytes`, read as UTF-8, such as the output of a command.
+    /// Invalid sequences are replaced, unless there is nothing to remap.
+    pub fn remap_bytes(&self, bytes: Vec<u8>) -> Vec<u8> {
+        if self.is_empty() {
+            return bytes;
+        }
+
+        match self.remap_text(&String::from_utf8_lossy(&bytes)) {
+            Cow::Borrowed(_) => bytes,
+            Cow::Owned(remapped) => remapped.into_bytes(),
+        }
+    }
+
+    /// Remaps the paths of a changed file.
+    pub fn remap_changed_file(&self, changed_file: ChangedFile) -> ChangedFile {
+        ChangedFile {
+            path: self.remap(&changed_file.path).into_owned(),
+            old_path: changed_file
+                .old_path
+                .map(|old_path| self.remap(&old_path).into_owned()),
+            kind: changed_file.kind,
+        }
+    }
+}
+
+/// A [`DiffEngine`] remapping the paths reported by another engine, e.g. a git repository
+/// mounted at a different root than the one the declaration uses.
+pub struct RemappedDiffEngine<E> {
+    engine: E,
+    roots: PathRoots,
+}
+
+impl<E> RemappedDiffEngine<E> {
+    pub fn new(engine: E, roots: PathRoots) -> Self {
+        Self { engine, roots }
+    }
+}
+
+impl<E: DiffEngine> DiffEngine for RemappedDiffEngine<E> {
+    fn get_changed_files(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context: &Context,
+    ) -> Result<Vec<ChangedFile>, DiffEngineError> {
+        let mut changed_files: Vec<ChangedFile> = self
+            .engine
+            .get_changed_files(from, to, context)?
+            .into_iter()
+            .map(|changed_file| self.roots.remap_changed_file(changed_file))
+            .collect();
+
+        changed_files.sort();
+
+        Ok(changed_files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::declarations::PathRootDeclaration;
+    use crate::diff_engine::ChangedFile;
+
+    use super::PathRoots;
+
+    #[test]
+    pub fn when_remapping_should_prefer_deepest_root_and_keep_outside_paths() {
+        let mut roots = PathRoots::new();
+        roots.add("/workspace", "/home/runner/repo");
+        roots.add("/workspace/vendor", "/opt/vendor");
+
+        assert_eq!(
+            roots.remap(Path::new("/workspace/libs/core/lib.rs")),
+            Path::new("/home/runner/repo/libs/core/lib.rs")
+        );
+        assert_eq!(
+            roots.remap(Path::new("/workspace/vendor/x.rs")),
+            Path::new("/opt/vendor/x.rs")
+        );
+        assert_eq!(roots.remap(Path::new("/tmp/x")), Path::new("/tmp/x"));
+        assert_eq!(
+            roots.remap_text("error in /workspace/libs/core/lib.rs:3"),
+            "error in /home/runner/repo/libs/core/lib.rs:3"
+        );
+        assert_eq!(
+            roots.remap_bytes(b"at /workspace/a.rs".to_vec()),
+            b"at /home/runner/repo/a.rs"
+        );
+
+        let renamed = roots.remap_changed_file(ChangedFile::renamed(
+            "/workspace/new.rs",
+            "/workspace/old.rs",
+        ));
+
+        assert_eq!(renamed.path, Path::new("/home/runner/repo/new.rs"));
+        assert_eq!(
+            renamed.old_path.as_deref(),
+            Some(Path::new("/home/runner/repo/old.rs"))
+        );
+    }
+
+    #[test]
+    pub fn when_environment_variable_is_missing_should_skip_guarded_root() {
+        let roots = PathRoots::from_declaration(&[
+            PathRootDeclaration {
+                from: "/workspace".into(),
+                to: "/repo".into(),
+                when_env: Some("PARMENIDES_TEST_MISSING_ENVIRONMENT".to_owned()),
+            },
+            PathRootDeclaration {
+                from: "/mnt".into(),
+                to: "/repo".into(),
+                when_env: None,
+            },
+        ]);
+
+        assert_eq!(
+            roots.remap(Path::new("/workspace/a")),
+            Path::new("/workspace/a")
+        );
+        assert_eq!(roots.remap(Path::new("/mnt/a")), Path::new("/repo/a"));
+    }
+}
@@ -0,0 +1,325 @@
+//! # Preset
+//!
+//! Presets bundle the setup of a common kind of repository, so a declaration can be as short
+//! as `preset = "cargo-workspace"`. A preset discovers the projects with a
+//! [`Discovery`] backend, gives each of them default targets and input globs leaving out
+//! build output and documentation, and adds the files whose changes affect every project,
+//! such as the lockfile, to the triggers.
+//!
+//! The rest of the declaration overrides the preset, see [`Preset::apply`].
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::Context;
+use crate::declarations::{ProjectDeclaration, WorkspaceDeclaration};
+#[cfg(feature = "discovery")]
+use crate::discovery::{CargoDiscovery, Discovery, NodeDiscovery};
+use crate::errors::DiscoveryError;
+use crate::file_system::FileSystem;
+use crate::tasks::Target;
+
+/// A built-in setup, named in the `preset` key of the declaration.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// A Cargo workspace: its members, built, tested and linked with Cargo.
+    CargoWorkspace,
+    /// A pnpm workspace whose scripts are run through Turborepo.
+    PnpmTurbo,
+}
+
+impl Preset {
+    /// Returns the backend discovering the projects.
+    #[cfg(feature = "discovery")]
+    pub fn discovery(&self) -> &'static dyn Discovery {
+        match self {
+            Self::CargoWorkspace => &CargoDiscovery,
+            Self::PnpmTurbo => &NodeDiscovery,
+        }
+    }
+
+    /// Returns the targets given to every discovered project, by name.
+    pub fn targets(&self) -> BTreeMap<String, Target> {
+        let commands: &[(&str, &str)] = match self {
+            // In the directory of a member, Cargo only builds that member.
+            Self::CargoWorkspace => &[
+                ("build", "cargo build"),
+                ("test", "cargo test"),
+                ("lint", "cargo clippy --all-targets -- -D warnings"),
+            ],
+            // In the directory of a package, Turborepo only runs the task of that package.
+            Self::PnpmTurbo => &[
+                ("build", "pnpm exec turbo run build"),
+                ("test", "pnpm exec turbo run test"),
+                ("lint", "pnpm exec turbo run lint"),
+            ],
+        };
+
+        commands
+            .iter()
+            .map(|(name, command)| {
+                let command = (*command).to_owned();
+                ((*name).to_owned(), Target { command })
+            })
+            .collect()
+    }
+
+    /// Returns the input globs given to every discovered project, see [`crate::inputs`].
+    pub fn inputs(&self) -> Vec<String> {
+        let globs: &[&str] = match self {
+            Self::CargoWorkspace => &["!target", "!**/*.md"],
+            Self::PnpmTurbo => &["!node_modules", "!dist", "!.turbo", "!**/*.md"],
+        };
+
+        globs.iter().map(|glob| (*glob).to_owned()).collect()
+    }
+
+    /// Returns the globs of the files whose changes affect every project.
+    pub fn triggers(&self) -> Vec<String> {
+        let globs: &[&str] = match self {
+            Self::CargoWorkspace => &[
+                "Cargo.toml",
+                "Cargo.lock",
+                "rust-toolchain.toml",
+                ".cargo/config.toml",
+            ],
+            Self::PnpmTurbo => &[
+                "package.json",
+                "pnpm-lock.yaml",
+                "pnpm-workspace.yaml",
+                "turbo.json",
+            ],
+        };
+
+        globs.iter().map(|glob| (*glob).to_owned()).collect()
+    }
+
+    /// Adds the projects the preset discovers under `root` to `declaration`, with the defaults
+    /// of the preset.
+    ///
+    /// A declared project overrides the discovered project at its path field by field: the
+    /// fields it sets replace the discovered ones, except its targets, dependency kinds and
+    /// links, which are added to them. Projects the preset doesn't discover are kept as they
+    /// are, and the declared triggers are kept after the ones of the preset.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the projects were discovered.
+    /// - `Err(DiscoveryError)`: If a manifest could not be read or is invalid, or if the crate
+    ///   is built without the `discovery` feature.
+    pub fn apply(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        declaration: &mut WorkspaceDeclaration,
+        context: &Context,
+    ) -> Result<(), DiscoveryError> {
+        let discovered = self.discover(fs, root, context)?;
+        self.apply_discovered(discovered, declaration);
+
+        Ok(())
+    }
+
+    /// Adds the projects of `discovered`, as the preset discovered them, to `declaration`, like
+    /// [`Self::apply`] without discovering them again.
+    pub fn apply_discovered(
+        &self,
+        discovered: WorkspaceDeclaration,
+        declaration: &mut WorkspaceDeclaration,
+    ) {
+        declaration.warnings.extend(discovered.warnings);
+
+        let mut projects = discovered.projects;
+
+        for project in projects.values_mut() {
+            project.targets = self.targets();
+            project.inputs = self.inputs();
+        }
+
+        for (path, declared) in std::mem::take(&mut declaration.projects) {
+            match projects.get_mut(&path) {
+                Some(discovered) => overlay(discovered, declared),
+                None => {
+                    projects.insert(path, declared);
+                }
+            }
+        }
+
+        declaration.projects = projects;
+
+        let mut triggers = self.triggers();
+        triggers.retain(|trigger| !declaration.triggers.contains(trigger));
+        triggers.append(&mut declaration.triggers);
+        declaration.triggers = triggers;
+    }
+
+    #[cfg(feature = "discovery")]
+    fn discover(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        context: &Context,
+    ) -> Result<WorkspaceDeclaration, DiscoveryError> {
+        self.discovery().discover(fs, root, context)
+    }
+
+    #[cfg(not(feature = "discovery"))]
+    fn discover(
+        &self,
+        _fs: &dyn FileSystem,
+        _root: &Path,
+        _context: &Context,
+    ) -> Result<WorkspaceDeclaration, DiscoveryError> {
+        Err(DiscoveryError::Disabled)
+    }
+}
+
+/// Overrides the fields of `discovered` that `declared` sets.
+fn overlay(discovered: &mut ProjectDeclaration, declared: ProjectDeclaration) {
+    // Destructured, so a new field can't be forgotten here.
+    let ProjectDeclaration {
+        id,
+        name,
+        dependencies,
+        soft_dependencies,
+        implicit_dependencies,
+        encapsulates,
+        dependency_kinds,
+        contract,
+        inputs,
+        tags,
+        targets,
+        description,
+        owners,
+        links,
+        deprecated,
+    } = declared;
+
+    fn replace<T>(field: &mut Vec<T>, declared: Vec<T>) {
+        if !declared.is_empty() {
+            *field = declared;
+        }
+    }
+
+    discovered.id = id.or(discovered.id.take());
+    discovered.name = name;
+    discovered.dependencies = dependencies.or(discovered.dependencies.take());
+    replace(&mut discovered.soft_dependencies, soft_dependencies);
+    replace(&mut discovered.implicit_dependencies, implicit_dependencies);
+    replace(&mut discovered.encapsulates, encapsulates);
+    discovered.dependency_kinds.extend(dependency_kinds);
+    discovered.contract = contract.or(discovered.contract.take());
+    replace(&mut discovered.inputs, inputs);
+    replace(&mut discovered.tags, tags);
+    discovered.targets.extend(targets);
+    discovered.description = description.or(discovered.description.take());
+    replace(&mut discovered.owners, owners);
+    discovered.links.extend(links);
+    discovered.deprecated = deprecated.or(discovered.deprecated.take());
+}
+
+#[cfg(all(test, feature = "discovery"))]
+mod tests {
+    use std::path::Path;
+
+    use crate::context::Context;
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::file_system::MemoryFileSystem;
+
+    use super::Preset;
+
+    /// Applies the `cargo-workspace` preset to a declaration overriding `crates/api` and
+    /// declaring `tools/scripts`, which no manifest covers.
+    fn applied(root: &Path) -> WorkspaceDeclaration {
+        let fs = MemoryFileSystem::new()
+            .with_file(
+                root.join("Cargo.toml"),
+                "[workspace]\nmembers = [\"crates/*\"]\n",
+            )
+            .with_file(
+                root.join("crates/core/Cargo.toml"),
+                "[package]\nname = \"core\"\n",
+            )
+            .with_file(
+                root.join("crates/api/Cargo.toml"),
+                "[package]\nname = \"api\"\n\n[dependencies]\ncore = { path = \"../core\" }\n",
+            );
+
+        let mut declaration = WorkspaceDeclaration::from_toml_str(
+            r#"
+            preset = "cargo-workspace"
+            triggers = [".github/**", "Cargo.lock"]
+
+            [projects."crates/api"]
+            name = "API"
+            tags = ["service"]
+            targets = { test = { command = "cargo nextest run" }, deploy = { command = "./deploy" } }
+
+            [projects."tools/scripts"]
+            name = "scripts"
+            "#,
+        )
+        .unwrap();
+        declaration.resolve_paths(root);
+
+        declaration.apply_preset(&fs, &Context::new()).unwrap();
+
+        declaration
+    }
+
+    #[test]
+    pub fn when_applying_preset_should_discover_projects_with_its_defaults() {
+        let root = Path::new("/repo");
+        let declaration = applied(root);
+        let preset = declaration.preset.unwrap();
+
+        let core = &declaration.projects[&root.join("crates/core")];
+        let api = &declaration.projects[&root.join("crates/api")];
+
+        assert_eq!(preset, Preset::CargoWorkspace);
+        assert_eq!(core.name, "core");
+        assert_eq!(core.targets, preset.targets());
+        assert_eq!(core.inputs, ["!target", "!**/*.md"]);
+        assert_eq!(api.dependencies, Some(vec![root.join("crates/core")]));
+        assert_eq!(api.inputs, core.inputs);
+        assert!(declaration.build_workspace().is_ok());
+    }
+
+    #[test]
+    pub fn when_applying_preset_should_keep_the_overrides() {
+        let root = Path::new("/repo");
+        let declaration = applied(root);
+
+        let api = &declaration.projects[&root.join("crates/api")];
+
+        assert_eq!(declaration.projects.len(), 3);
+        assert!(declaration
+            .projects
+            .contains_key(&root.join("tools/scripts")));
+
+        assert_eq!(api.name, "API");
+        assert_eq!(api.tags, ["service"]);
+        assert_eq!(
+            api.targets.keys().collect::<Vec<_>>(),
+            ["build", "deploy", "lint", "test"]
+        );
+        assert_eq!(api.targets["test"].command, "cargo nextest run");
+    }
+
+    #[test]
+    pub fn when_applying_preset_should_add_its_triggers_before_the_declared_ones() {
+        let declaration = applied(Path::new("/repo"));
+
+        assert_eq!(
+            declaration.triggers,
+            [
+                "Cargo.toml",
+                "rust-toolchain.toml",
+                ".cargo/config.toml",
+                ".github/**",
+                "Cargo.lock"
+            ]
+        );
+    }
+}
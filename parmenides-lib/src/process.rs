@@ -0,0 +1,298 @@
+//! # Process
+//!
+//! Processes are spawned through the [`ProcessRunner`] trait, so code that shells out can be
+//! tested with a [`ScriptedProcessRunner`] that records the commands and returns canned output.
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The result of a finished process.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ProcessOutput {
+    /// The exit code, or `None` if the process was terminated by a signal.
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl ProcessOutput {
+    /// A successful output with the given standard output.
+    pub fn success<S: Into<Vec<u8>>>(stdout: S) -> Self {
+        Self {
+            code: Some(0),
+            stdout: stdout.into(),
+            stderr: vec![],
+        }
+    }
+
+    /// Returns `true` if the process exited with code 0.
+    pub fn is_success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Returns a command running `command` through the platform shell: `sh -c` on Unix and
+/// `cmd /C` on Windows.
+pub fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    shell.arg(command);
+    shell
+}
+
+/// Spawns processes.
+pub trait ProcessRunner: Send + Sync {
+    /// Runs the command to completion, capturing its output.
+    fn run(&self, command: &mut Command) -> std::io::Result<ProcessOutput>;
+
+    /// Runs the command to completion with `input` as its standard input, capturing its
+    /// output, e.g. to hand it a secret without writing it to a file.
+    ///
+    /// Runners that can't feed the standard input, the default, fail with
+    /// [`std::io::ErrorKind::Unsupported`].
+    fn run_with_input(
+        &self,
+        command: &mut Command,
+        _input: &[u8],
+    ) -> std::io::Result<ProcessOutput> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "{} can't be given a standard input",
+                command.get_program().to_string_lossy()
+            ),
+        ))
+    }
+}
+
+/// The [`ProcessRunner`] that spawns real processes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemProcessRunner {
+    timeout: Option<Duration>,
+}
+
+impl SystemProcessRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills processes still running after `timeout`, failing the run with
+    /// [`std::io::ErrorKind::TimedOut`] instead of hanging.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl SystemProcessRunner {
+    fn spawn(&self, command: &mut Command, input: Option<&[u8]>) -> std::io::Result<ProcessOutput> {
+        let stdin = if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        };
+
+        let mut child = command
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // The input is written, and the pipes drained, while waiting, so a process that doesn't
+        // read all of its input or is chatty can't block on a full pipe.
+        let writer = child.stdin.take().zip(input).map(|(mut stdin, input)| {
+            let input = input.to_vec();
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            })
+        });
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+
+        let status = match self.timeout {
+            None => child.wait()?,
+            Some(timeout) => {
+                let started = Instant::now();
+
+                loop {
+                    if let Some(status) = child.try_wait()? {
+                        break status;
+                    }
+
+                    if started.elapsed() >= timeout {
+                        child.kill()?;
+                        child.wait()?;
+
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            format!("timed out after {timeout:?}"),
+                        ));
+                    }
+
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+        };
+
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+
+        Ok(ProcessOutput {
+            code: status.code(),
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+}
+
+impl ProcessRunner for SystemProcessRunner {
+    fn run(&self, command: &mut Command) -> std::io::Result<ProcessOutput> {
+        self.spawn(command, None)
+    }
+
+    fn run_with_input(
+        &self,
+        command: &mut Command,
+        input: &[u8],
+    ) -> std::io::Result<ProcessOutput> {
+        self.spawn(command, Some(input))
+    }
+}
+
+fn drain<R>(pipe: Option<R>) -> JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut content = Vec::new();
+
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut content);
+        }
+
+        content
+    })
+}
+
+/// A [`ProcessRunner`] returning canned outputs by program name.
+///
+/// Programs without a scripted output fail to spawn with [`std::io::ErrorKind::NotFound`], as
+/// if they were not installed. Every command run is recorded, as the program followed by its
+/// arguments, and so is the standard input given to it.
+#[derive(Debug, Default)]
+pub struct ScriptedProcessRunner {
+    outputs: HashMap<String, ProcessOutput>,
+    commands: Mutex<Vec<Vec<String>>>,
+    inputs: Mutex<Vec<Vec<u8>>>,
+}
+
+impl ScriptedProcessRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scripts the output of every run of `program`.
+    pub fn with_output<S: Into<String>>(mut self, program: S, output: ProcessOutput) -> Self {
+        self.outputs.insert(program.into(), output);
+        self
+    }
+
+    /// Returns the commands run so far.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    /// Returns the standard inputs given to the commands run with
+    /// [`ProcessRunner::run_with_input`] so far.
+    pub fn inputs(&self) -> Vec<Vec<u8>> {
+        self.inputs.lock().unwrap().clone()
+    }
+}
+
+impl ProcessRunner for ScriptedProcessRunner {
+    fn run(&self, command: &mut Command) -> std::io::Result<ProcessOutput> {
+        let lossy = |value: &OsStr| value.to_string_lossy().into_owned();
+        let program = lossy(command.get_program());
+
+        self.commands.lock().unwrap().push(
+            std::iter::once(program.clone())
+                .chain(command.get_args().map(lossy))
+                .collect(),
+        );
+
+        self.outputs.get(&program).cloned().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, format!("{program} not found"))
+        })
+    }
+
+    fn run_with_input(
+        &self,
+        command: &mut Command,
+        input: &[u8],
+    ) -> std::io::Result<ProcessOutput> {
+        self.inputs.lock().unwrap().push(input.to_vec());
+        self.run(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::time::Duration;
+
+    use super::{
+        shell_command, ProcessOutput, ProcessRunner, ScriptedProcessRunner, SystemProcessRunner,
+    };
+
+    #[test]
+    pub fn when_running_scripted_command_should_record_and_return_output() {
+        let runner = ScriptedProcessRunner::new().with_output("git", ProcessOutput::success("ok"));
+
+        let output = runner.run(Command::new("git").args(["status", "-s"]));
+        let missing = runner.run(&mut Command::new("hg"));
+
+        assert_eq!(output.unwrap().stdout, b"ok");
+        assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(
+            runner.commands(),
+            vec![vec!["git", "status", "-s"], vec!["hg"]]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_process_outlives_timeout_should_kill_it() {
+        let runner = SystemProcessRunner::new().with_timeout(Duration::from_millis(50));
+
+        let slow = runner.run(&mut shell_command("sleep 5"));
+        let fast = runner.run(&mut shell_command("echo ok"));
+
+        assert_eq!(slow.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(fast.unwrap().stdout, b"ok\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn when_running_with_input_should_feed_standard_input() {
+        let runner = SystemProcessRunner::new();
+
+        let echoed = runner.run_with_input(&mut shell_command("tr a-z A-Z"), b"secret");
+        let ignored = runner.run_with_input(&mut shell_command("echo ok"), &[b'a'; 1 << 20]);
+
+        assert_eq!(echoed.unwrap().stdout, b"SECRET");
+        assert_eq!(ignored.unwrap().stdout, b"ok\n");
+    }
+}
@@ -0,0 +1,86 @@
+//! # Progress
+//!
+//! Long operations report what they are doing through a [`ProgressSink`], so the CLI can draw
+//! progress bars while library users plug their own reporting, or silence it with
+//! [`NoProgress`], without anything being written to stdout.
+use std::sync::Mutex;
+
+/// The long operations that report progress.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum Stage {
+    /// Reading the manifests of the discovered projects.
+    Discovery,
+    /// Running commands twice and hashing their outputs.
+    Determinism,
+    /// Running a target across projects.
+    Tasks,
+}
+
+/// Receives the progress of long operations.
+pub trait ProgressSink: Send + Sync {
+    /// A stage started, with the number of steps when it is known.
+    fn start(&self, stage: Stage, total: Option<usize>);
+
+    /// A step of the stage completed. `item` names it, e.g. the path of a project.
+    fn advance(&self, stage: Stage, item: &str);
+
+    /// The stage completed.
+    fn finish(&self, stage: Stage);
+}
+
+/// A [`ProgressSink`] that ignores everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn start(&self, _stage: Stage, _total: Option<usize>) {}
+
+    fn advance(&self, _stage: Stage, _item: &str) {}
+
+    fn finish(&self, _stage: Stage) {}
+}
+
+/// A single call made to a [`RecordingProgress`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    Started(Stage, Option<usize>),
+    Advanced(Stage, String),
+    Finished(Stage),
+}
+
+/// A [`ProgressSink`] that records every call, e.g. to assert on them in tests.
+#[derive(Debug, Default)]
+pub struct RecordingProgress {
+    events: Mutex<Vec<ProgressEvent>>,
+}
+
+impl RecordingProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the recorded calls, in order.
+    pub fn events(&self) -> Vec<ProgressEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn record(&self, event: ProgressEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl ProgressSink for RecordingProgress {
+    fn start(&self, stage: Stage, total: Option<usize>) {
+        self.record(ProgressEvent::Started(stage, total));
+    }
+
+    fn advance(&self, stage: Stage, item: &str) {
+        self.record(ProgressEvent::Advanced(stage, item.to_owned()));
+    }
+
+    fn finish(&self, stage: Stage) {
+        self.record(ProgressEvent::Finished(stage));
+    }
+}
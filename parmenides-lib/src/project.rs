@@ -1,11 +1,17 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::inputs::Inputs;
+use crate::tasks::Target;
 
 /// The unique identifier for a project within a workspace.
 ///
 /// Each project added to a workspace is assigned a `ProjectId`. It is used to track
 /// dependencies, dependents, and for efficient project lookup.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct ProjectId(usize);
 
 impl ProjectId {
@@ -24,50 +30,433 @@ impl Display for ProjectId {
     }
 }
 
+/// An identifier of a project that stays the same across rebuilds of the workspace.
+///
+/// A [`ProjectId`] is the position of the project in the workspace, which changes with the
+/// order projects are declared or discovered in. A `StableId` is instead derived from the
+/// declared identifier of the project or, without one, from its normalized path relative to the
+/// workspace root, so it can be persisted, e.g. with affected results or cache entries, and
+/// looked up in a later run with [`crate::workspace::Workspace::get_id_by_stable_id`], even from
+/// another clone or CI worker checked out elsewhere.
+///
+/// Path-derived IDs change when the project moves within the workspace. Declare an `id` to
+/// keep them.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct StableId(u64);
+
+impl StableId {
+    /// Derives the ID of a project with a declared identifier.
+    pub fn from_identifier(identifier: &str) -> Self {
+        Self::hash(b"id", identifier.as_bytes())
+    }
+
+    /// Derives the ID of a project without a declared identifier from its path, relative to the
+    /// workspace root. The path is normalized first, so `a/./b` and `a/c/../b` have the same ID
+    /// as `a/b`.
+    pub fn from_path(path: &Path) -> Self {
+        let mut components: Vec<String> = Vec::new();
+
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    components.pop();
+                }
+                component => components.push(component.as_os_str().to_string_lossy().into_owned()),
+            }
+        }
+
+        Self::hash(b"path", components.join("/").as_bytes())
+    }
+
+    /// Parses the hexadecimal form the ID is displayed in.
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.len() != 16 {
+            return None;
+        }
+
+        u64::from_str_radix(value, 16).ok().map(Self)
+    }
+
+    pub fn into_inner(&self) -> u64 {
+        self.0
+    }
+
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    fn hash(kind: &[u8], value: &[u8]) -> Self {
+        let digest = Sha256::new()
+            .chain_update(kind)
+            .chain_update(b"\0")
+            .chain_update(value)
+            .finalize();
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+
+        Self(u64::from_be_bytes(bytes))
+    }
+}
+
+impl Display for StableId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The lifecycle of a deprecated project: the date it stops being maintained, and the project
+/// its dependents should migrate to.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Deprecation {
+    pub(crate) sunset: Option<String>,
+    pub(crate) replacement: Option<ProjectId>,
+}
+
+impl Deprecation {
+    /// The date the project stops being maintained, as `YYYY-MM-DD`, if one was announced.
+    pub fn sunset(&self) -> Option<&str> {
+        self.sunset.as_deref()
+    }
+
+    /// The ID of the project replacing this one, if any.
+    pub fn replacement(&self) -> Option<ProjectId> {
+        self.replacement
+    }
+}
+
+/// Returns `true` if `value` is a calendar date formatted as `YYYY-MM-DD`, so dates compare
+/// in order as strings.
+pub fn is_valid_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return false;
+    }
+
+    let number = |range: std::ops::Range<usize>| {
+        bytes[range.clone()]
+            .iter()
+            .all(u8::is_ascii_digit)
+            .then(|| value[range].parse::<u32>().ok())
+            .flatten()
+    };
+
+    let (Some(year), Some(month), Some(day)) = (number(0..4), number(5..7), number(8..10)) else {
+        return false;
+    };
+
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    (1..=days).contains(&day)
+}
+
 /// Represents an individual project in a workspace.
 ///
 /// A `Project` encapsulates the project's metadata, such as its path, name, dependencies,
 /// and dependents. It also tracks whether the project is affected by a change.
+///
+/// The fields are read through accessors, so new metadata can be added without breaking
+/// embedders.
 #[derive(Debug)]
 pub struct Project {
     /// The file path of the project.
-    pub path: PathBuf,
+    pub(crate) path: PathBuf,
 
-    /// The human-readable name of the project.
-    pub name: String,
+    /// The machine identifier of the project, unique in the workspace and used in selectors
+    /// and cache keys.
+    pub(crate) identifier: String,
+
+    /// The human-readable name of the project. It may contain spaces or emoji.
+    pub(crate) name: String,
+
+    /// The identifier of the project across rebuilds of the workspace.
+    pub(crate) stable_id: StableId,
 
     /// The dependencies of this project, represented as a list of `ProjectId`s.
     ///
     /// `None` indicates that the project has no dependencies.
-    pub dependencies: Option<Vec<ProjectId>>,
+    pub(crate) dependencies: Option<Vec<ProjectId>>,
 
     /// The dependents of this project, represented as a list of `ProjectId`s.
     ///
     /// This list is automatically populated when other projects declare this project as a dependency.
-    pub dependents: Vec<ProjectId>,
+    pub(crate) dependents: Vec<ProjectId>,
+
+    /// Free-form labels used to group projects, e.g. by type (`app`, `lib`) or layer.
+    pub(crate) tags: Vec<String>,
+
+    /// The targets the project can run, by name.
+    pub(crate) targets: BTreeMap<String, Target>,
+
+    /// The target running the contract tests of the project, which gate the changes stopping
+    /// at it. See [`crate::tasks::TaskRunner::gate_contracts`].
+    pub(crate) contract: Option<String>,
+
+    /// The files of the project whose changes affect it, or `None` for every file.
+    pub(crate) inputs: Option<Inputs>,
+
+    /// The lifecycle of the project if it is deprecated, or `None` if it is maintained.
+    pub(crate) deprecation: Option<Deprecation>,
+
+    /// A short description of what the project is for.
+    pub(crate) description: Option<String>,
+
+    /// The people or teams owning the project, e.g. `@web-team`.
+    pub(crate) owners: Vec<String>,
+
+    /// Links about the project, e.g. to its documentation or dashboards, by label.
+    pub(crate) links: BTreeMap<String, String>,
 
     /// Indicates whether this project is affected by a change.
     ///
     /// This field is useful for tracking which projects need to be rebuilt or tested after a change.
-    pub affected: bool,
+    pub(crate) affected: bool,
 }
 
 impl Project {
     pub(crate) fn new(path: PathBuf, name: String, dependencies: Option<Vec<ProjectId>>) -> Self {
         Self {
+            identifier: identifier_from_path(&path),
+            stable_id: StableId::from_path(&path),
             path,
             name,
             dependencies,
             dependents: vec![],
+            tags: vec![],
+            targets: BTreeMap::new(),
+            contract: None,
+            inputs: None,
+            deprecation: None,
+            description: None,
+            owners: vec![],
+            links: BTreeMap::new(),
             affected: false,
         }
     }
 
+    pub(crate) fn with_identifier(mut self, identifier: String) -> Self {
+        self.identifier = identifier;
+        self
+    }
+
+    pub(crate) fn with_stable_id(mut self, stable_id: StableId) -> Self {
+        self.stable_id = stable_id;
+        self
+    }
+
+    pub(crate) fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// The file path of the project.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The machine identifier of the project, unique in the workspace.
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// The human-readable name of the project.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The identifier of the project across rebuilds of the workspace.
+    pub fn stable_id(&self) -> StableId {
+        self.stable_id
+    }
+
+    /// The IDs of the projects this project depends on.
+    pub fn dependencies(&self) -> &[ProjectId] {
+        self.dependencies.as_deref().unwrap_or_default()
+    }
+
+    /// The IDs of the projects depending on this project.
+    pub fn dependents(&self) -> &[ProjectId] {
+        &self.dependents
+    }
+
+    /// The tags of the project.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns `true` if the project is affected by a change.
+    pub fn is_affected(&self) -> bool {
+        self.affected
+    }
+
+    /// Returns the target with the given name, if the project defines it.
+    pub fn target(&self, name: &str) -> Option<&Target> {
+        self.targets.get(name)
+    }
+
+    /// The targets of the project, by name.
+    pub fn targets(&self) -> &BTreeMap<String, Target> {
+        &self.targets
+    }
+
+    /// The name of the target running the contract tests of the project, if any.
+    pub fn contract(&self) -> Option<&str> {
+        self.contract.as_deref()
+    }
+
+    /// Returns the input globs of the project, empty if every file of the project is an input.
+    /// See [`Inputs`].
+    pub fn inputs(&self) -> &[String] {
+        self.inputs.as_ref().map_or(&[], Inputs::globs)
+    }
+
+    /// The lifecycle of the project if it is deprecated, see [`Deprecation`].
+    pub fn deprecation(&self) -> Option<&Deprecation> {
+        self.deprecation.as_ref()
+    }
+
+    /// Returns `true` if the project is deprecated.
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecation.is_some()
+    }
+
+    /// The declared description of the project, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The owners of the project.
+    pub fn owners(&self) -> &[String] {
+        &self.owners
+    }
+
+    /// The links about the project, by label.
+    pub fn links(&self) -> &BTreeMap<String, String> {
+        &self.links
+    }
+
+    /// Returns `true` if changes to the file affect the project.
+    ///
+    /// # Parameters
+    /// - `path`: The file path, relative to the same root as the project path. Files outside
+    ///   the project are never inputs.
+    pub fn is_input<P>(&self, path: &P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        match path.as_ref().strip_prefix(&self.path) {
+            Ok(relative) => self
+                .inputs
+                .as_ref()
+                .is_none_or(|inputs| inputs.includes(&relative)),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns `true` if the project has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing == tag)
+    }
+
+    pub(crate) fn with_targets(mut self, targets: BTreeMap<String, Target>) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    pub(crate) fn with_contract(mut self, contract: Option<String>) -> Self {
+        self.contract = contract;
+        self
+    }
+
+    pub(crate) fn with_inputs(mut self, inputs: Option<Inputs>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    pub(crate) fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub(crate) fn with_owners(mut self, owners: Vec<String>) -> Self {
+        self.owners = owners;
+        self
+    }
+
+    pub(crate) fn with_links(mut self, links: BTreeMap<String, String>) -> Self {
+        self.links = links;
+        self
+    }
+
     pub(crate) fn add_dependent(&mut self, id: ProjectId) {
         self.dependents.push(id);
     }
 }
 
+/// Returns `true` if `value` can identify a project: it is made of ASCII letters, digits, `-`,
+/// `_` and `.`, and isn't empty.
+pub fn is_valid_identifier(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || matches!(char, '-' | '_' | '.'))
+}
+
+/// Derives the identifier of a project without a declared one from the last component of its
+/// path, lowercased, with the characters not allowed in identifiers replaced by `-`.
+pub fn identifier_from_path(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    sanitize_identifier(&name)
+}
+
+/// Derives the identifier of a project from its path relative to the workspace root, like
+/// [`identifier_from_path`], e.g. `apps-ui` for `apps/ui`. Used instead when the last
+/// component of the path clashes with another project.
+pub fn identifier_from_relative_path(path: &Path) -> String {
+    let components: Vec<_> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+
+    sanitize_identifier(&components.join("/"))
+}
+
+fn sanitize_identifier(name: &str) -> String {
+    let mut identifier = String::with_capacity(name.len());
+
+    for char in name.to_lowercase().chars() {
+        if char.is_ascii_alphanumeric() || matches!(char, '_' | '.') {
+            identifier.push(char);
+        } else if !identifier.ends_with('-') {
+            identifier.push('-');
+        }
+    }
+
+    let identifier = identifier.trim_matches('-');
+
+    if identifier.is_empty() {
+        "root".to_owned()
+    } else {
+        identifier.to_owned()
+    }
+}
+
 impl Display for Project {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Project {} {:?}", self.name, self.path)
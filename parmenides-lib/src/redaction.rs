@@ -0,0 +1,356 @@
+//! Redaction masks secrets in task output, logs, and reports before they are written anywhere,
+//! so cached logs and CI artifacts don't leak tokens.
+//!
+//! The command line builds a [`Redactor`] from the declaration when loading it, and masks the
+//! outputs of tasks with [`crate::tasks::TaskRunner::with_redactor`] before they are printed,
+//! reported or cached.
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use regex::Regex;
+
+use crate::declarations::RedactionDeclaration;
+use crate::errors::RedactionError;
+
+/// The text that replaces every redacted secret.
+pub const MASK: &str = "[REDACTED]";
+
+/// How much of an unterminated line a [`RedactingWriter`] holds back before writing it anyway.
+const MAX_BUFFERED: usize = 8 * 1024;
+
+/// Masks secret values and pattern matches in text.
+#[derive(Debug, Default, Clone)]
+pub struct Redactor {
+    secrets: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a redactor from the workspace redaction settings, reading the values of the
+    /// environment variables marked as secret from the current process.
+    pub fn from_declaration(declaration: &RedactionDeclaration) -> Result<Self, RedactionError> {
+        let mut redactor = Self::new();
+
+        for pattern in &declaration.patterns {
+            redactor.add_pattern(pattern)?;
+        }
+
+        redactor.add_secret_env_vars(&declaration.secret_env);
+
+        Ok(redactor)
+    }
+
+    /// Adds a literal secret value. Empty values are ignored, as they would match everywhere.
+    pub fn add_secret<S>(&mut self, secret: S)
+    where
+        S: Into<String>,
+    {
+        let secret = secret.into();
+
+        if !secret.is_empty() && !self.secrets.contains(&secret) {
+            self.secrets.push(secret);
+            // Longer secrets first, so a secret containing another one is fully masked.
+            self.secrets
+                .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        }
+    }
+
+    /// Adds the values of the given environment variables as secrets. Variables that are not
+    /// set are ignored.
+    pub fn add_secret_env_vars<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for name in names {
+            if let Ok(value) = std::env::var(name.as_ref()) {
+                self.add_secret(value);
+            }
+        }
+    }
+
+    /// Adds a regular expression whose matches are masked.
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<(), RedactionError> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| RedactionError::InvalidPattern(pattern.to_owned(), err))?;
+
+        self.patterns.push(regex);
+
+        Ok(())
+    }
+
+    /// Returns `true` if the redactor has nothing to mask.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Returns the length of the longest secret, in bytes.
+    fn longest_secret(&self) -> usize {
+        self.secrets.first().map_or(0, String::len)
+    }
+
+    /// Masks every secret and pattern match in the text.
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut output = Cow::Borrowed(text);
+
+        for secret in &self.secrets {
+            if output.contains(secret.as_str()) {
+                output = Cow::Owned(output.replace(secret.as_str(), MASK));
+            }
+        }
+
+        for pattern in &self.patterns {
+            if let Cow::Owned(replaced) = pattern.replace_all(&output, MASK) {
+                output = Cow::Owned(replaced);
+            }
+        }
+
+        output
+    }
+
+    /// Masks every secret and pattern match in `bytes`, read as UTF-8. Invalid sequences are
+    /// replaced, unless there is nothing to mask.
+    pub fn redact_bytes(&self, bytes: Vec<u8>) -> Vec<u8> {
+        if self.is_empty() {
+            return bytes;
+        }
+
+        match self.redact(&String::from_utf8_lossy(&bytes)) {
+            Cow::Borrowed(_) => bytes,
+            Cow::Owned(redacted) => redacted.into_bytes(),
+        }
+    }
+}
+
+/// A writer that masks secrets in everything written through it.
+///
+/// Output is redacted line by line, so a secret split across two `write` calls is still masked.
+/// An unterminated line is held back, even by `flush`, until a newline arrives or the writer
+/// is dropped or unwrapped with [`Self::into_inner`].
+///
+/// A line longer than 8 KiB is written without waiting for its end, holding back only a tail
+/// as long as the longest secret. Pattern matches split at that point aren't masked.
+pub struct RedactingWriter<W: Write> {
+    /// Only `None` once taken by [`Self::into_inner`].
+    inner: Option<W>,
+    redactor: Redactor,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> RedactingWriter<W> {
+    pub fn new(inner: W, redactor: Redactor) -> Self {
+        Self {
+            inner: Some(inner),
+            redactor,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes the pending output, flushes it, and returns the inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.write_pending()?;
+
+        let mut inner = self
+            .inner
+            .take()
+            .expect("the inner writer is only taken once");
+        inner.flush()?;
+
+        Ok(inner)
+    }
+
+    /// Writes the held back tail, once no more output can complete it.
+    fn write_pending(&mut self) -> io::Result<()> {
+        let pending = std::mem::take(&mut self.buffer);
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        self.write_redacted(&pending)
+    }
+
+    /// Writes the buffered unterminated line, holding back a tail the longest secret may start
+    /// in, and a character split across writes.
+    fn write_capped(&mut self) -> io::Result<()> {
+        let complete = match std::str::from_utf8(&self.buffer) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.buffer.len(),
+        };
+
+        let incomplete = self.buffer.split_off(complete);
+        let text = String::from_utf8_lossy(&self.buffer);
+        let redacted = self.redactor.redact(&text).into_owned();
+
+        let mut split = redacted
+            .len()
+            .saturating_sub(self.redactor.longest_secret());
+
+        while !redacted.is_char_boundary(split) {
+            split -= 1;
+        }
+
+        self.buffer = [&redacted.as_bytes()[split..], &incomplete].concat();
+
+        match &mut self.inner {
+            Some(inner) => inner.write_all(&redacted.as_bytes()[..split]),
+            None => Ok(()),
+        }
+    }
+
+    fn write_redacted(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let text = String::from_utf8_lossy(bytes);
+        let redacted = self.redactor.redact(&text);
+
+        match &mut self.inner {
+            Some(inner) => inner.write_all(redacted.as_bytes()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        if let Some(last_newline) = self.buffer.iter().rposition(|byte| *byte == b'\n') {
+            let rest = self.buffer.split_off(last_newline + 1);
+            let lines = std::mem::replace(&mut self.buffer, rest);
+
+            self.write_redacted(&lines)?;
+        }
+
+        if self.buffer.len() > MAX_BUFFERED {
+            self.write_capped()?;
+        }
+
+        Ok(buf.len())
+    }
+
+    /// Flushes the complete lines. The unterminated tail stays held back, as the rest of a
+    /// secret may still be written.
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        // Errors can't be reported from a drop, use `into_inner` to handle them.
+        let _ = self.write_pending().and_then(|()| self.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{RedactingWriter, Redactor, MASK, MAX_BUFFERED};
+
+    #[test]
+    pub fn when_redacting_should_mask_secrets_and_patterns() {
+        let mut redactor = Redactor::new();
+
+        redactor.add_secret("hunter2");
+        redactor.add_pattern(r"ghp_[A-Za-z0-9]+").unwrap();
+
+        let output = redactor.redact("password=hunter2 token=ghp_abc123 user=me");
+
+        assert_eq!(output, format!("password={MASK} token={MASK} user=me"));
+    }
+
+    #[test]
+    pub fn when_nothing_matches_should_borrow_input() {
+        let mut redactor = Redactor::new();
+
+        redactor.add_secret("hunter2");
+
+        assert!(matches!(
+            redactor.redact("nothing to see"),
+            std::borrow::Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    pub fn when_pattern_is_invalid_should_return_error() {
+        let mut redactor = Redactor::new();
+
+        assert!(redactor.add_pattern("(unclosed").is_err());
+    }
+
+    #[test]
+    pub fn when_secret_is_split_across_writes_should_still_mask() {
+        let mut redactor = Redactor::new();
+
+        redactor.add_secret("hunter2");
+
+        let mut writer = RedactingWriter::new(Vec::new(), redactor);
+
+        writer.write_all(b"the password is hun").unwrap();
+        writer.write_all(b"ter2\nand the tail hunter2").unwrap();
+
+        let output = writer.into_inner().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("the password is {MASK}\nand the tail {MASK}")
+        );
+    }
+
+    #[test]
+    pub fn when_flushing_mid_secret_should_hold_back_the_tail() {
+        let mut redactor = Redactor::new();
+
+        redactor.add_secret("hunter2");
+
+        let mut output = Vec::new();
+
+        {
+            let mut writer = RedactingWriter::new(&mut output, redactor.clone());
+
+            writer.write_all(b"done\ntoken hun").unwrap();
+            writer.flush().unwrap();
+            writer.write_all(b"ter2").unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("done\ntoken {MASK}")
+        );
+        assert_eq!(
+            redactor.redact_bytes(b"token=hunter2".to_vec()),
+            format!("token={MASK}").into_bytes()
+        );
+    }
+
+    #[test]
+    pub fn when_line_exceeds_buffer_cap_should_write_it_and_mask_split_secret() {
+        let mut redactor = Redactor::new();
+
+        redactor.add_secret("hunter2");
+
+        let mut writer = RedactingWriter::new(Vec::new(), redactor);
+        let line = "x".repeat(MAX_BUFFERED);
+
+        writer.write_all(line.as_bytes()).unwrap();
+        writer.write_all(b" hunter2 hun").unwrap();
+
+        assert!(writer.buffer.len() <= "hunter2".len());
+
+        writer.write_all(b"ter2").unwrap();
+
+        let output = writer.into_inner().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{line} {MASK} {MASK}")
+        );
+    }
+}
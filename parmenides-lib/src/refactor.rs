@@ -0,0 +1,535 @@
+//! Refactorings are workspace-wide changes that touch both the file system and the
+//! declarations, made safe to automate by the knowledge of the project graph.
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::declarations::WorkspaceDeclaration;
+use crate::edit::{edited_declaration_file, DeclarationEdit};
+use crate::errors::{EditDeclarationError, ExtractProjectError, MoveProjectError};
+
+/// Moves a project to a new path, updating the declaration file.
+///
+/// The project directory is moved, the project's own declaration is renamed, and every
+/// declaration referencing it (or a project nested under it) by path is rewritten. The
+/// declaration is validated before anything is moved, so an unknown project leaves the file
+/// system untouched.
+///
+/// # Parameters
+/// - `declaration_path`: The declaration file. Relative `from` and `to` paths are resolved
+///   against its directory.
+/// - `from`: The current path of the project.
+/// - `to`: The new path of the project. It must not exist.
+pub fn move_project<P, F, T>(declaration_path: P, from: F, to: T) -> Result<(), MoveProjectError>
+where
+    P: AsRef<Path>,
+    F: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    let declaration_path = declaration_path.as_ref();
+    let root = declaration_path.parent().unwrap_or(Path::new(""));
+
+    let from = root.join(from);
+    let to = root.join(to);
+
+    if to.exists() {
+        return Err(MoveProjectError::DestinationExists(to));
+    }
+
+    let edited = edited_declaration_file(
+        declaration_path,
+        &[DeclarationEdit::MoveProject {
+            from: from.clone(),
+            to: to.clone(),
+        }],
+    )?;
+
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| MoveProjectError::Io(parent.to_path_buf(), err))?;
+    }
+
+    if from.exists() {
+        std::fs::rename(&from, &to).map_err(|err| MoveProjectError::Io(from.clone(), err))?;
+    }
+
+    std::fs::write(declaration_path, edited).map_err(|err| {
+        MoveProjectError::Edit(EditDeclarationError::Io(
+            declaration_path.to_path_buf(),
+            err,
+        ))
+    })
+}
+
+/// Renames a tag across declaration files, in every project and generator, and in the
+/// selectors of the constraints and lint rules. See [`DeclarationEdit::RenameTag`].
+///
+/// # Returns
+/// - `Ok(Vec<PathBuf>)`: The files that changed, in the order given.
+/// - `Err(EditDeclarationError)`: If a file could not be read, parsed or written.
+pub fn rename_tag<I, P>(
+    declaration_paths: I,
+    from: &str,
+    to: &str,
+) -> Result<Vec<PathBuf>, EditDeclarationError>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    rename(
+        declaration_paths,
+        DeclarationEdit::RenameTag {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        },
+    )
+}
+
+/// Renames a target across declaration files, in every project and in the contracts naming
+/// it. See [`DeclarationEdit::RenameTarget`].
+///
+/// # Returns
+/// - `Ok(Vec<PathBuf>)`: The files that changed, in the order given.
+/// - `Err(EditDeclarationError)`: If a file could not be read, parsed or written, or a project
+///   already has a target with the new name.
+pub fn rename_target<I, P>(
+    declaration_paths: I,
+    from: &str,
+    to: &str,
+) -> Result<Vec<PathBuf>, EditDeclarationError>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    rename(
+        declaration_paths,
+        DeclarationEdit::RenameTarget {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        },
+    )
+}
+
+/// Applies a rename to every file. All of them are edited in memory before any is written, so
+/// an invalid file or a conflicting target leaves every file untouched.
+fn rename<I, P>(
+    declaration_paths: I,
+    edit: DeclarationEdit,
+) -> Result<Vec<PathBuf>, EditDeclarationError>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut changed = Vec::new();
+
+    for path in declaration_paths {
+        let path = path.as_ref();
+        let edited = edited_declaration_file(path, std::slice::from_ref(&edit))?;
+
+        // Compared with the file as the editor writes it, as YAML files are reformatted.
+        if edited != edited_declaration_file(path, &[])? {
+            changed.push((path.to_path_buf(), edited));
+        }
+    }
+
+    for (path, edited) in &changed {
+        std::fs::write(path, edited).map_err(|err| EditDeclarationError::Io(path.clone(), err))?;
+    }
+
+    Ok(changed.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Extracts a project and the subtree of its transitive dependencies into a standalone
+/// directory, e.g. to split it out of the monorepo or to produce a minimal reproduction.
+///
+/// The projects keep their location relative to the workspace root, so relative references
+/// between them stay valid. A declaration file with the same name is written at the root of
+/// `destination`, holding only the extracted projects with their paths relative to the new
+/// root, along with the constants, redaction and lint settings. Generators are left out, as
+/// their templates live outside the subtree.
+///
+/// # Parameters
+/// - `declaration_path`: The declaration file. A relative `project` is resolved against its
+///   directory.
+/// - `project`: The path of the project to extract.
+/// - `destination`: Where to extract the subtree. It must not exist or be an empty directory.
+///
+/// # Returns
+/// - `Ok(Vec<PathBuf>)`: The paths of the extracted projects, relative to `destination`.
+/// - `Err(ExtractProjectError)`: If the project is unknown or the subtree could not be copied.
+pub fn extract_project<P, Q, D>(
+    declaration_path: P,
+    project: Q,
+    destination: D,
+) -> Result<Vec<PathBuf>, ExtractProjectError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    let declaration_path = declaration_path.as_ref();
+    let destination = destination.as_ref();
+    let root = declaration_path.parent().unwrap_or(Path::new(""));
+
+    let mut declaration = WorkspaceDeclaration::from_path(declaration_path)?;
+    let projects = std::mem::take(&mut declaration.projects);
+    let project = root.join(project);
+
+    if !projects.contains_key(&project) {
+        return Err(ExtractProjectError::ProjectNotFound(project));
+    }
+
+    let is_empty = match std::fs::read_dir(destination) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => true,
+        Err(err) => return Err(ExtractProjectError::Io(destination.to_path_buf(), err)),
+    };
+
+    if !is_empty {
+        return Err(ExtractProjectError::DestinationNotEmpty(
+            destination.to_path_buf(),
+        ));
+    }
+
+    let mut subtree = BTreeSet::new();
+    let mut stack = vec![project];
+
+    // The declaration isn't built into a workspace, so it may have cycles.
+    while let Some(path) = stack.pop() {
+        let Some(project_declaration) = projects.get(&path) else {
+            return Err(ExtractProjectError::ProjectNotFound(path));
+        };
+
+        if !subtree.insert(path.clone()) {
+            continue;
+        }
+
+        stack.extend(project_declaration.dependencies.iter().flatten().cloned());
+        stack.extend(project_declaration.soft_dependencies.iter().cloned());
+        stack.extend(project_declaration.implicit_dependencies.iter().cloned());
+    }
+
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+    declaration.generators.clear();
+
+    for path in &subtree {
+        let mut project_declaration = projects[path].clone();
+
+        if let Some(dependencies) = &mut project_declaration.dependencies {
+            for dependency in dependencies {
+                *dependency = relative(dependency);
+            }
+        }
+
+        for dependency in project_declaration
+            .soft_dependencies
+            .iter_mut()
+            .chain(&mut project_declaration.implicit_dependencies)
+            .chain(&mut project_declaration.encapsulates)
+        {
+            *dependency = relative(dependency);
+        }
+
+        // A replacement outside the subtree isn't part of the new workspace.
+        if let Some(deprecated) = &mut project_declaration.deprecated {
+            deprecated.replacement = deprecated
+                .replacement
+                .take()
+                .filter(|replacement| subtree.contains(replacement))
+                .map(|replacement| relative(&replacement));
+        }
+
+        project_declaration.dependency_kinds =
+            std::mem::take(&mut project_declaration.dependency_kinds)
+                .into_iter()
+                .map(|(dependency, kind)| (relative(&dependency), kind))
+                .collect();
+
+        declaration
+            .projects
+            .insert(relative(path), project_declaration);
+
+        copy_directory(path, &destination.join(relative(path)))?;
+    }
+
+    let content = match declaration_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("toml") => toml::to_string(&declaration).map_err(|err| err.to_string()),
+        _ => serde_yaml::to_string(&declaration).map_err(|err| err.to_string()),
+    }
+    .map_err(ExtractProjectError::Serialize)?;
+
+    let extracted_declaration_path =
+        destination.join(declaration_path.file_name().unwrap_or_default());
+
+    std::fs::create_dir_all(destination)
+        .map_err(|err| ExtractProjectError::Io(destination.to_path_buf(), err))?;
+    std::fs::write(&extracted_declaration_path, content)
+        .map_err(|err| ExtractProjectError::Io(extracted_declaration_path, err))?;
+
+    Ok(subtree.iter().map(|path| relative(path)).collect())
+}
+
+fn copy_directory(source: &Path, destination: &Path) -> Result<(), ExtractProjectError> {
+    std::fs::create_dir_all(destination)
+        .map_err(|err| ExtractProjectError::Io(destination.to_path_buf(), err))?;
+
+    let entries = match std::fs::read_dir(source) {
+        Ok(entries) => entries,
+        // A declared project without files has nothing to copy.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(ExtractProjectError::Io(source.to_path_buf(), err)),
+    };
+
+    for entry in entries {
+        let entry = entry.map_err(|err| ExtractProjectError::Io(source.to_path_buf(), err))?;
+        let source_path = entry.path();
+        let destination_path = destination.join(entry.file_name());
+
+        let file_type = entry
+            .file_type()
+            .map_err(|err| ExtractProjectError::Io(source_path.clone(), err))?;
+
+        if file_type.is_dir() {
+            copy_directory(&source_path, &destination_path)?;
+        } else {
+            std::fs::copy(&source_path, &destination_path)
+                .map_err(|err| ExtractProjectError::Io(source_path.clone(), err))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::{EditDeclarationError, ExtractProjectError, MoveProjectError};
+
+    use super::{extract_project, move_project, rename_tag, rename_target};
+
+    #[test]
+    pub fn when_moving_project_should_move_directory_and_update_declaration() {
+        let root = std::env::temp_dir().join(format!("parmenides-move-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("libs/a/src")).unwrap();
+        std::fs::write(root.join("libs/a/src/lib.rs"), "").unwrap();
+
+        let declaration_path = root.join("parmenides.toml");
+        std::fs::write(
+            &declaration_path,
+            "[projects.\"libs/a\"]\nname = \"a\"\n\n[projects.web]\nname = \"web\"\ndependencies = [\"libs/a\"]\n",
+        )
+        .unwrap();
+
+        move_project(&declaration_path, "libs/a", "libs/core").unwrap();
+
+        let moved = root.join("libs/core/src/lib.rs").exists();
+        let declaration = WorkspaceDeclaration::from_toml_path(&declaration_path).unwrap();
+
+        let unknown = move_project(&declaration_path, "libs/missing", "libs/other");
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(moved);
+        assert_eq!(
+            declaration.projects[&root.join("web")].dependencies,
+            Some(vec![root.join("libs/core")])
+        );
+        assert!(matches!(
+            unknown,
+            Err(MoveProjectError::Edit(
+                EditDeclarationError::ProjectNotFound(_)
+            ))
+        ));
+    }
+
+    #[test]
+    pub fn when_renaming_across_files_should_write_all_or_none() {
+        let root = std::env::temp_dir().join(format!("parmenides-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        let apps = root.join("apps.toml");
+        let libs = root.join("libs.yaml");
+        let docs = root.join("docs.toml");
+        let docs_content = "[projects.docs]\nname = \"docs\"\n";
+
+        std::fs::write(
+            &apps,
+            "[projects.web]\nname = \"web\"\ntags = [\"ui\"]\n\n[projects.web.targets.test]\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &libs,
+            r#"{"projects": {"kit": {"name": "kit", "tags": ["ui"], "targets": {"test": {"command": "true"}, "check": {"command": "true"}}}}}"#,
+        )
+        .unwrap();
+        std::fs::write(&docs, docs_content).unwrap();
+
+        let conflict = rename_target([&apps, &libs], "test", "check");
+        let untouched = std::fs::read_to_string(&apps).unwrap();
+        let renamed = rename_tag([&apps, &libs, &docs], "ui", "frontend");
+        let apps_declaration = WorkspaceDeclaration::from_toml_path(&apps).unwrap();
+        let docs_after = std::fs::read_to_string(&docs).unwrap();
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(
+            conflict,
+            Err(EditDeclarationError::TargetExists(_, target)) if target == "check"
+        ));
+        assert!(untouched.contains("[projects.web.targets.test]"));
+        assert_eq!(renamed.unwrap(), vec![apps.clone(), libs]);
+        assert_eq!(
+            apps_declaration.projects[&root.join("web")].tags,
+            ["frontend"]
+        );
+        assert_eq!(docs_after, docs_content);
+    }
+
+    #[test]
+    pub fn when_extracting_project_should_copy_dependency_subtree() {
+        let root = std::env::temp_dir().join(format!("parmenides-extract-{}", std::process::id()));
+        let destination = root.join("out");
+
+        for project in ["libs/core", "libs/ui", "apps/web", "apps/admin"] {
+            std::fs::create_dir_all(root.join(project)).unwrap();
+            std::fs::write(root.join(project).join("README.md"), project).unwrap();
+        }
+
+        let declaration_path = root.join("parmenides.toml");
+        std::fs::write(
+            &declaration_path,
+            r#"[constants]
+registry = "internal"
+
+[projects."libs/core"]
+name = "core"
+
+[projects."libs/ui"]
+name = "ui"
+dependencies = ["libs/core"]
+
+[projects."apps/web"]
+name = "web"
+dependencies = ["libs/ui"]
+
+[projects."apps/admin"]
+name = "admin"
+dependencies = ["libs/core"]
+"#,
+        )
+        .unwrap();
+
+        let extracted = extract_project(&declaration_path, "apps/web", &destination).unwrap();
+
+        let copied = destination.join("libs/core/README.md").exists();
+        let skipped = destination.join("apps/admin").exists();
+        let declaration =
+            WorkspaceDeclaration::from_toml_path(destination.join("parmenides.toml")).unwrap();
+
+        let not_empty = extract_project(&declaration_path, "apps/web", &destination);
+        let unknown = extract_project(&declaration_path, "apps/missing", root.join("other"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            extracted,
+            vec![
+                PathBuf::from("apps/web"),
+                PathBuf::from("libs/core"),
+                PathBuf::from("libs/ui")
+            ]
+        );
+        assert!(copied);
+        assert!(!skipped);
+        assert_eq!(declaration.projects.len(), 3);
+        assert_eq!(declaration.constants["registry"], "internal");
+        assert_eq!(
+            declaration.projects[&destination.join("libs/ui")].dependencies,
+            Some(vec![destination.join("libs/core")])
+        );
+        assert!(matches!(
+            not_empty,
+            Err(ExtractProjectError::DestinationNotEmpty(_))
+        ));
+        assert!(matches!(
+            unknown,
+            Err(ExtractProjectError::ProjectNotFound(_))
+        ));
+    }
+
+    #[test]
+    pub fn when_extracting_project_in_cycle_should_copy_each_project_once() {
+        let root =
+            std::env::temp_dir().join(format!("parmenides-extract-cycle-{}", std::process::id()));
+        let destination = root.join("out");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let declaration_path = root.join("parmenides.toml");
+        std::fs::write(
+            &declaration_path,
+            r#"[projects."libs/a"]
+name = "a"
+dependencies = ["libs/b"]
+
+[projects."libs/b"]
+name = "b"
+soft_dependencies = ["libs/a"]
+"#,
+        )
+        .unwrap();
+
+        let extracted = extract_project(&declaration_path, "libs/a", &destination);
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            extracted.unwrap(),
+            vec![PathBuf::from("libs/a"), PathBuf::from("libs/b")]
+        );
+    }
+
+    #[test]
+    pub fn when_extracting_project_with_diamonds_should_visit_each_project_once() {
+        let root =
+            std::env::temp_dir().join(format!("parmenides-extract-diamond-{}", std::process::id()));
+        let destination = root.join("out");
+        std::fs::create_dir_all(&root).unwrap();
+
+        // Every level depends on both projects of the next one, so walking each path through the
+        // graph would take 2^40 steps.
+        let levels = 40;
+        let mut content = String::new();
+
+        for level in 0..levels {
+            for side in ["left", "right"] {
+                content.push_str(&format!(
+                    "[projects.\"libs/{side}-{level}\"]\nname = \"{side}-{level}\"\n"
+                ));
+
+                if level + 1 < levels {
+                    let next = level + 1;
+                    content.push_str(&format!(
+                        "dependencies = [\"libs/left-{next}\", \"libs/right-{next}\"]\n"
+                    ));
+                }
+
+                content.push('\n');
+            }
+        }
+
+        let declaration_path = root.join("parmenides.toml");
+        std::fs::write(&declaration_path, content).unwrap();
+
+        let extracted = extract_project(&declaration_path, "libs/left-0", &destination);
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // Only the right project of the first level isn't reachable.
+        assert_eq!(extracted.unwrap().len(), levels * 2 - 1);
+    }
+}
@@ -0,0 +1,248 @@
+//! # Schema
+//!
+//! The JSON Schemas of the declaration format and of the JSON outputs, so editors can
+//! validate and complete declarations, e.g. with the YAML language server or Taplo, and
+//! external tools can parse the outputs. Each schema is versioned in its `$id`, e.g.
+//! `urn:parmenides:schema:graph:1`, along with the format it describes.
+use std::fmt::Display;
+
+/// The version of the declaration format, bumped on incompatible changes.
+pub const DECLARATION_FORMAT_VERSION: u32 = 1;
+
+/// The schema of a workspace declaration, see
+/// [`crate::declarations::WorkspaceDeclaration`].
+pub const DECLARATION_SCHEMA: &str = include_str!("../schemas/declaration.schema.json");
+
+/// The schema of the graph printed as JSON, see [`crate::export::WorkspaceGraph`].
+pub const GRAPH_SCHEMA: &str = include_str!("../schemas/graph.schema.json");
+
+/// The schema of the report of a run, see [`crate::tasks::JsonTaskReport`].
+pub const TASK_REPORT_SCHEMA: &str = include_str!("../schemas/task-report.schema.json");
+
+/// A published schema.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Schema {
+    Declaration,
+    Graph,
+    TaskReport,
+}
+
+impl Schema {
+    /// Every schema, in the order they are listed.
+    pub const ALL: [Schema; 3] = [Schema::Declaration, Schema::Graph, Schema::TaskReport];
+
+    /// Returns the JSON Schema document.
+    pub fn content(&self) -> &'static str {
+        match self {
+            Self::Declaration => DECLARATION_SCHEMA,
+            Self::Graph => GRAPH_SCHEMA,
+            Self::TaskReport => TASK_REPORT_SCHEMA,
+        }
+    }
+
+    /// Returns the version of the format the schema describes.
+    pub fn version(&self) -> u32 {
+        match self {
+            Self::Declaration => DECLARATION_FORMAT_VERSION,
+            Self::Graph => crate::export::GRAPH_FORMAT_VERSION,
+            Self::TaskReport => crate::tasks::TASK_REPORT_FORMAT_VERSION,
+        }
+    }
+}
+
+impl Display for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Declaration => "declaration",
+            Self::Graph => "graph",
+            Self::TaskReport => "task-report",
+        };
+
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Value;
+
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::export::{GraphView, WorkspaceGraph};
+    use crate::process::ProcessOutput;
+    use crate::tasks::{JsonTaskReport, TaskReport, TaskResult, TaskStatus};
+
+    use super::Schema;
+
+    /// Returns where `value` doesn't follow `schema`, checking the keywords the schemas use.
+    fn violations(root: &Value, schema: &Value, value: &Value, path: &str) -> Vec<String> {
+        let schema = match schema["$ref"].as_str() {
+            Some(reference) => {
+                let name = reference.trim_start_matches("#/definitions/");
+                &root["definitions"][name]
+            }
+            None => schema,
+        };
+
+        let type_name = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(name) => vec![name],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+
+        if !types.is_empty() && !types.contains(&type_name) {
+            return vec![format!("{path}: {type_name} isn't one of {types:?}")];
+        }
+
+        if let Some(allowed) = schema["enum"].as_array() {
+            if !allowed.contains(value) {
+                return vec![format!("{path}: {value} isn't one of {allowed:?}")];
+            }
+        }
+
+        let mut found = vec![];
+
+        match value {
+            Value::Object(entries) => {
+                for required in schema["required"].as_array().into_iter().flatten() {
+                    if !entries.contains_key(required.as_str().unwrap()) {
+                        found.push(format!("{path}: {required} is missing"));
+                    }
+                }
+
+                for (key, entry) in entries {
+                    let path = format!("{path}.{key}");
+
+                    match (&schema["properties"][key], &schema["additionalProperties"]) {
+                        (Value::Null, Value::Bool(false)) => {
+                            found.push(format!("{path} isn't in the schema"))
+                        }
+                        (Value::Null, Value::Null) => {}
+                        (Value::Null, additional) => {
+                            found.extend(violations(root, additional, entry, &path))
+                        }
+                        (property, _) => found.extend(violations(root, property, entry, &path)),
+                    }
+                }
+            }
+            Value::Array(items) if !schema["items"].is_null() => {
+                for (index, item) in items.iter().enumerate() {
+                    let path = format!("{path}[{index}]");
+                    found.extend(violations(root, &schema["items"], item, &path));
+                }
+            }
+            _ => {}
+        }
+
+        found
+    }
+
+    fn check(schema: Schema, value: &Value) -> Vec<String> {
+        let schema: Value = serde_json::from_str(schema.content()).unwrap();
+
+        violations(&schema, &schema, value, "")
+    }
+
+    #[test]
+    pub fn when_serializing_outputs_should_follow_their_schema() {
+        for schema in Schema::ALL {
+            let document: Value = serde_json::from_str(schema.content()).unwrap();
+
+            assert_eq!(
+                document["$id"],
+                format!("urn:parmenides:schema:{schema}:{}", schema.version())
+            );
+        }
+
+        let declaration = WorkspaceDeclaration::from_toml_str(
+            r#"
+            preset = "cargo-workspace"
+            triggers = ["Cargo.lock"]
+            affected_strategy = "changed_only"
+
+            [constants]
+            registry = "registry.example.com"
+
+            [projects."libs/core"]
+            name = "core"
+            dependencies = ["libs/util"]
+            tags = ["lib"]
+            inputs = ["src/**"]
+            owners = ["@core"]
+            contract = "test"
+            description = "The core."
+            dependency_kinds = { "libs/util" = "dev" }
+            deprecated = { sunset = "2027-01-31" }
+            targets = { test = { command = "cargo test" } }
+
+            [projects."libs/util"]
+            name = "util"
+
+            [lint]
+            rules = { no-cycles = "warning" }
+
+            [cache.remote]
+            url = "https://cache.example.com"
+            token = "ENC[token]"
+
+            [[path_roots]]
+            from = "/workspace"
+            to = "/repo"
+            "#,
+        )
+        .unwrap();
+
+        let serialized = serde_json::to_value(&declaration).unwrap();
+        assert_eq!(
+            check(Schema::Declaration, &serialized),
+            Vec::<String>::new()
+        );
+
+        let mut typo = serialized.clone();
+        typo["projects"]["libs/core"]["dependecies"] = Value::Null;
+        typo["watch"]["backend"] = "inotify".into();
+        assert_eq!(check(Schema::Declaration, &typo).len(), 2);
+
+        let workspace = declaration.build_workspace().unwrap();
+        let graph = WorkspaceGraph::new(&workspace, &GraphView::full(&workspace));
+        let graph = serde_json::to_value(graph).unwrap();
+        assert_eq!(check(Schema::Graph, &graph), Vec::<String>::new());
+
+        let core = workspace.get_id_by_path(&"libs/core").unwrap();
+        let report = TaskReport {
+            results: vec![
+                TaskResult {
+                    project: core,
+                    status: TaskStatus::Failed(ProcessOutput {
+                        code: Some(2),
+                        ..ProcessOutput::default()
+                    }),
+                    duration: Duration::from_millis(1_500),
+                },
+                TaskResult {
+                    project: core,
+                    status: TaskStatus::TimedOut,
+                    duration: Duration::ZERO,
+                },
+            ],
+            warnings: vec![],
+        };
+        let report = serde_json::to_value(JsonTaskReport::new(&workspace, &report)).unwrap();
+
+        assert_eq!(check(Schema::TaskReport, &report), Vec::<String>::new());
+        assert_eq!(report["results"][0]["status"], "failed");
+        assert_eq!(report["results"][0]["duration_ms"], 1_500);
+        assert_eq!(report["results"][1]["status"], "timed_out");
+    }
+}
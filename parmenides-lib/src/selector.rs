@@ -0,0 +1,276 @@
+//! Selectors are small expressions that pick a set of projects from a workspace.
+//!
+//! A selector is made of atoms combined with `&` (intersection) and `|` (union), where `&` binds
+//! tighter than `|`. An atom is either `*`, which matches every project, or `kind:pattern`,
+//! optionally negated with `!`:
+//!
+//! - `tag:ui` matches projects tagged `ui`.
+//! - `id:web-*` matches projects whose identifier starts with `web-`.
+//! - `name:web-*` matches projects whose display name starts with `web-`.
+//! - `path:libs/*` matches projects whose path matches the pattern.
+//!
+//! Patterns support `*` as a wildcard for any sequence of characters.
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::errors::SelectorError;
+use crate::project::{Project, ProjectId};
+use crate::workspace::Workspace;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Atom {
+    All,
+    Tag(String),
+    Identifier(String),
+    Name(String),
+    Path(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Term {
+    negated: bool,
+    atom: Atom,
+}
+
+/// A parsed selector expression.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Selector {
+    source: String,
+    /// A union of intersections of terms.
+    alternatives: Vec<Vec<Term>>,
+}
+
+impl Selector {
+    /// Parses a selector expression.
+    pub fn parse(expression: &str) -> Result<Self, SelectorError> {
+        let mut alternatives = Vec::new();
+
+        for alternative in expression.split('|') {
+            let mut terms = Vec::new();
+
+            for term in alternative.split('&') {
+                terms.push(parse_term(term.trim(), expression)?);
+            }
+
+            alternatives.push(terms);
+        }
+
+        Ok(Self {
+            source: expression.to_owned(),
+            alternatives,
+        })
+    }
+
+    /// Returns `true` if the project matches the selector.
+    pub fn matches(&self, project: &Project) -> bool {
+        self.alternatives
+            .iter()
+            .any(|terms| terms.iter().all(|term| term_matches(term, project)))
+    }
+
+    /// Returns the IDs of the projects in the workspace that match the selector, in ID order.
+    pub fn select(&self, workspace: &Workspace) -> Vec<ProjectId> {
+        workspace
+            .iter_with_ids()
+            .filter(|(_, project)| self.matches(project))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Returns the expression the selector was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Returns the expression with every `tag:from` term, negated or not, renamed to `tag:to`,
+/// keeping its spacing. Patterns with wildcards are left as they are, as they may match other
+/// tags too.
+pub fn rename_tag(expression: &str, from: &str, to: &str) -> String {
+    let mut renamed = String::with_capacity(expression.len());
+
+    for piece in expression.split_inclusive(['&', '|']) {
+        let term = piece.trim_end_matches(['&', '|']);
+
+        match term.split_once(':') {
+            Some((kind, pattern))
+                if kind.trim().trim_start_matches('!').trim() == "tag"
+                    && pattern.trim() == from =>
+            {
+                let start = pattern.len() - pattern.trim_start().len();
+                let end = pattern.trim_end().len();
+
+                renamed.push_str(kind);
+                renamed.push(':');
+                renamed.push_str(&pattern[..start]);
+                renamed.push_str(to);
+                renamed.push_str(&pattern[end..]);
+            }
+            _ => renamed.push_str(term),
+        }
+
+        renamed.push_str(&piece[term.len()..]);
+    }
+
+    renamed
+}
+
+fn parse_term(term: &str, expression: &str) -> Result<Term, SelectorError> {
+    let (negated, atom) = match term.strip_prefix('!') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, term),
+    };
+
+    if atom.is_empty() {
+        return Err(SelectorError::EmptyTerm(expression.to_owned()));
+    }
+
+    if atom == "*" {
+        return Ok(Term {
+            negated,
+            atom: Atom::All,
+        });
+    }
+
+    let (kind, pattern) = atom
+        .split_once(':')
+        .ok_or_else(|| SelectorError::MissingKind(atom.to_owned()))?;
+
+    let pattern = pattern.trim().to_owned();
+
+    let atom = match kind.trim() {
+        "tag" => Atom::Tag(pattern),
+        "id" => Atom::Identifier(pattern),
+        "name" => Atom::Name(pattern),
+        "path" => Atom::Path(pattern),
+        other => return Err(SelectorError::UnknownKind(other.to_owned())),
+    };
+
+    Ok(Term { negated, atom })
+}
+
+fn term_matches(term: &Term, project: &Project) -> bool {
+    let matches = match &term.atom {
+        Atom::All => true,
+        Atom::Tag(pattern) => project.tags.iter().any(|tag| wildcard_match(pattern, tag)),
+        Atom::Identifier(pattern) => wildcard_match(pattern, &project.identifier),
+        Atom::Name(pattern) => wildcard_match(pattern, &project.name),
+        Atom::Path(pattern) => wildcard_match(pattern, &project.path.to_string_lossy()),
+    };
+
+    matches != term.negated
+}
+
+/// Matches `text` against a pattern where `*` stands for any sequence of characters.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    // There is always at least one part, even for an empty pattern.
+    let first = parts.next().unwrap_or_default();
+
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::SelectorError;
+
+    use super::{rename_tag, wildcard_match, Selector};
+
+    #[test]
+    pub fn when_renaming_tag_should_only_rewrite_matching_terms() {
+        assert_eq!(
+            rename_tag("tag:ui & !tag: ui |tag:ui-*|id:ui", "ui", "frontend"),
+            "tag:frontend & !tag: frontend |tag:ui-*|id:ui"
+        );
+        assert_eq!(rename_tag("*", "ui", "frontend"), "*");
+    }
+
+    #[test]
+    pub fn when_matching_wildcards_should_handle_prefixes_and_suffixes() {
+        assert!(wildcard_match("web-*", "web-admin"));
+        assert!(wildcard_match("*-admin", "web-admin"));
+        assert!(wildcard_match("w*d*n", "web-admin"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("web", "web-admin"));
+        assert!(!wildcard_match("a*a", "a"));
+    }
+
+    #[test]
+    pub fn when_selecting_should_combine_terms() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/apps/web", "web", None).tags =
+            vec!["app".to_owned(), "ui".to_owned()];
+        declaration.add_project("/repo/apps/cli", "cli", None).tags = vec!["app".to_owned()];
+        declaration
+            .add_project("/repo/libs/ui-kit", "ui-kit", None)
+            .tags = vec!["ui".to_owned()];
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let names = |expression: &str| {
+            let mut names: Vec<_> = Selector::parse(expression)
+                .unwrap()
+                .select(&workspace)
+                .into_iter()
+                .map(|id| workspace.get_project(id).unwrap().name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names("tag:app & !tag:ui"), vec!["cli"]);
+        assert_eq!(names("name:cli | path:/repo/libs/*"), vec!["cli", "ui-kit"]);
+        assert_eq!(names("*"), vec!["cli", "ui-kit", "web"]);
+        assert_eq!(names("id:ui-*"), vec!["ui-kit"]);
+    }
+
+    #[test]
+    pub fn when_parsing_invalid_selector_should_return_error() {
+        assert_eq!(
+            Selector::parse("owner:me").unwrap_err(),
+            SelectorError::UnknownKind("owner".to_owned())
+        );
+        assert_eq!(
+            Selector::parse("ui").unwrap_err(),
+            SelectorError::MissingKind("ui".to_owned())
+        );
+        assert_eq!(
+            Selector::parse("tag:ui &").unwrap_err(),
+            SelectorError::EmptyTerm("tag:ui &".to_owned())
+        );
+    }
+}
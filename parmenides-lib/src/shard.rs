@@ -0,0 +1,563 @@
+//! # Shard
+//!
+//! CI pipelines run the affected projects across parallel jobs, e.g. the entries of a build
+//! matrix. [`shard`] splits the projects into balanced shards, weighing each project by how
+//! long its target took in earlier runs, as recorded in [`Durations`], and keeping dependencies
+//! together or in order, see [`ShardConstraint`]. [`stable_shard`] assigns the projects by
+//! their stable IDs instead, so retried jobs run the same projects whatever was recorded since.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{DurationsError, TopologicalOrderError};
+use crate::project::{ProjectId, StableId};
+use crate::tasks::{TaskReport, TaskStatus};
+use crate::workspace::Workspace;
+
+/// The durations file of a workspace, relative to its root.
+pub const DURATIONS_FILE: &str = ".parmenides/durations.json";
+
+/// The weight of projects without a recorded duration, when none is recorded at all.
+const DEFAULT_DURATION: Duration = Duration::from_secs(1);
+
+/// How long each target last ran in each project, by target name and project identifier.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Durations {
+    /// The durations in milliseconds.
+    targets: BTreeMap<String, BTreeMap<String, u64>>,
+    /// How often each target ran and failed in each project, see [`Durations::failure_rate`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    outcomes: BTreeMap<String, BTreeMap<String, Outcomes>>,
+}
+
+/// How often a target ran in a project, how often it failed, and how often it was replayed
+/// from the cache instead.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Outcomes {
+    pub runs: u64,
+    pub failures: u64,
+    #[serde(default)]
+    pub cached: u64,
+}
+
+impl Durations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the durations from a JSON file. A missing file has no durations.
+    pub fn load<P>(path: P) -> Result<Self, DurationsError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(err) => return Err(DurationsError::Io(path.to_path_buf(), err)),
+        };
+
+        serde_json::from_str(&content).map_err(|err| DurationsError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Writes the durations to a JSON file, creating its directory.
+    pub fn save<P>(&self, path: P) -> Result<(), DurationsError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| DurationsError::Parse(path.to_path_buf(), err))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| DurationsError::Io(parent.to_path_buf(), err))?;
+        }
+
+        std::fs::write(path, content + "\n")
+            .map_err(|err| DurationsError::Io(path.to_path_buf(), err))
+    }
+
+    /// Sets how long `target` ran in the project with `identifier`.
+    pub fn insert<T, I>(&mut self, target: T, identifier: I, duration: Duration)
+    where
+        T: Into<String>,
+        I: Into<String>,
+    {
+        let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+
+        self.targets
+            .entry(target.into())
+            .or_default()
+            .insert(identifier.into(), millis);
+    }
+
+    /// Records how long `target` ran in each project of `report`, and whether it failed.
+    /// Projects it was skipped in keep their earlier duration and outcomes, as do the ones it
+    /// was replayed from the cache for, but for counting the replay.
+    pub fn record(&mut self, target: &str, workspace: &Workspace, report: &TaskReport) {
+        for result in &report.results {
+            if result.status == TaskStatus::Skipped {
+                continue;
+            }
+
+            if let Some(project) = workspace.get_project(result.project) {
+                let outcomes = self
+                    .outcomes
+                    .entry(target.to_owned())
+                    .or_default()
+                    .entry(project.identifier().to_owned())
+                    .or_default();
+
+                if let TaskStatus::Cached(_) = result.status {
+                    outcomes.cached += 1;
+                    continue;
+                }
+
+                outcomes.runs += 1;
+
+                if !result.status.is_success() {
+                    outcomes.failures += 1;
+                }
+
+                self.insert(target, project.identifier(), result.duration);
+            }
+        }
+    }
+
+    /// Returns how often `target` ran and failed in the project with `identifier`.
+    pub fn outcomes(&self, target: &str, identifier: &str) -> Option<Outcomes> {
+        self.outcomes.get(target)?.get(identifier).copied()
+    }
+
+    /// Returns the share of the recorded runs of `target` in the project with `identifier`
+    /// that failed, from 0 to 1, or `None` if none is recorded.
+    pub fn failure_rate(&self, target: &str, identifier: &str) -> Option<f64> {
+        self.outcomes(target, identifier)
+            .filter(|outcomes| outcomes.runs > 0)
+            .map(|outcomes| outcomes.failures as f64 / outcomes.runs as f64)
+    }
+
+    /// Returns the share of the recorded runs of every target in every project that were
+    /// replayed from the cache, from 0 to 1, or `None` if none is recorded.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let (cached, total) = self.outcomes.values().flat_map(BTreeMap::values).fold(
+            (0, 0),
+            |(cached, total), outcomes| {
+                (
+                    cached + outcomes.cached,
+                    total + outcomes.runs + outcomes.cached,
+                )
+            },
+        );
+
+        (total > 0).then(|| cached as f64 / total as f64)
+    }
+
+    /// Returns how long `target` last ran in the project with `identifier`.
+    pub fn get(&self, target: &str, identifier: &str) -> Option<Duration> {
+        self.targets
+            .get(target)?
+            .get(identifier)
+            .map(|millis| Duration::from_millis(*millis))
+    }
+
+    /// Estimates how long `target` runs in each of `projects`.
+    ///
+    /// Projects without a recorded duration, e.g. new ones, are estimated at the mean of the
+    /// recorded ones, or at one second if none is.
+    pub fn estimates<I>(
+        &self,
+        workspace: &Workspace,
+        target: &str,
+        projects: I,
+    ) -> HashMap<ProjectId, Duration>
+    where
+        I: IntoIterator<Item = ProjectId>,
+    {
+        let recorded: Vec<Duration> = self
+            .targets
+            .get(target)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+            .map(|millis| Duration::from_millis(*millis))
+            .collect();
+
+        let fallback = match u32::try_from(recorded.len()) {
+            Ok(count) if count > 0 => recorded.iter().sum::<Duration>() / count,
+            _ => DEFAULT_DURATION,
+        };
+
+        projects
+            .into_iter()
+            .filter_map(|id| {
+                let project = workspace.get_project(id)?;
+                let duration = self.get(target, project.identifier()).unwrap_or(fallback);
+
+                Some((id, duration))
+            })
+            .collect()
+    }
+}
+
+/// How [`shard`] places projects that depend on each other.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ShardConstraint {
+    /// A project is in the same shard as the sharded projects it depends on, so each job
+    /// builds its dependencies itself.
+    #[default]
+    CoLocate,
+    /// A project is in the same shard as the sharded projects it depends on, or a later one,
+    /// for pipelines running the shards in order and sharing their outputs.
+    Ordered,
+}
+
+/// A set of projects to run in one job.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+pub struct Shard {
+    /// The projects, in dependency order.
+    pub projects: Vec<ProjectId>,
+    /// The sum of the estimated durations of the projects.
+    pub duration: Duration,
+}
+
+/// Splits the projects of `estimates` into `count` shards of similar total duration.
+///
+/// Dependencies count through other projects too, so a project depending on a sharded project
+/// through one that isn't sharded is still placed by `constraint`. The split is greedy, so it
+/// isn't always optimal, but it is deterministic: the same estimates give the same shards.
+///
+/// # Parameters
+/// - `workspace`: The workspace the projects belong to.
+/// - `estimates`: The projects to shard, with their estimated duration, see
+///   [`Durations::estimates`].
+/// - `count`: How many shards to split into. Some may stay empty, e.g. with fewer projects.
+/// - `constraint`: How projects depending on each other are placed.
+///
+/// # Returns
+/// - `Ok(Vec<Shard>)`: The `count` shards, or a single one if `count` is zero.
+/// - `Err(TopologicalOrderError)`: If the workspace contains a dependency cycle.
+pub fn shard(
+    workspace: &Workspace,
+    estimates: &HashMap<ProjectId, Duration>,
+    count: usize,
+    constraint: ShardConstraint,
+) -> Result<Vec<Shard>, TopologicalOrderError> {
+    let mut shards = vec![Shard::default(); count.max(1)];
+
+    let order: Vec<ProjectId> = workspace
+        .topological_order()?
+        .into_iter()
+        .filter(|id| estimates.contains_key(id))
+        .collect();
+
+    let dependencies = |id: ProjectId| -> Vec<ProjectId> {
+        workspace
+            .transitive_dependencies(id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|dependency| estimates.contains_key(dependency))
+            .collect()
+    };
+
+    match constraint {
+        ShardConstraint::CoLocate => {
+            // Each group is a connected set of projects, heaviest groups placed first.
+            let mut groups = groups(&order, dependencies);
+
+            groups.sort_by_key(|group| {
+                let duration: Duration = group.iter().map(|id| estimates[id]).sum();
+
+                (std::cmp::Reverse(duration), group.iter().min().copied())
+            });
+
+            for group in groups {
+                let index = lightest(&shards, 0);
+
+                for id in group {
+                    shards[index].projects.push(id);
+                    shards[index].duration += estimates[&id];
+                }
+            }
+
+            let position: HashMap<ProjectId, usize> = order
+                .iter()
+                .enumerate()
+                .map(|(position, id)| (*id, position))
+                .collect();
+
+            for shard in &mut shards {
+                shard.projects.sort_by_key(|id| position[id]);
+            }
+        }
+        ShardConstraint::Ordered => {
+            let mut placed: HashMap<ProjectId, usize> = HashMap::new();
+
+            for id in order {
+                let earliest = dependencies(id)
+                    .iter()
+                    .filter_map(|dependency| placed.get(dependency))
+                    .max()
+                    .copied()
+                    .unwrap_or(0);
+
+                let index = lightest(&shards, earliest);
+
+                shards[index].projects.push(id);
+                shards[index].duration += estimates[&id];
+                placed.insert(id, index);
+            }
+        }
+    }
+
+    Ok(shards)
+}
+
+/// Splits the projects of `estimates` into `count` shards by their stable IDs.
+///
+/// Each project is placed by rendezvous hashing of its [`StableId`] and the shard indices, so
+/// its shard only depends on its ID and `count`: it doesn't change with the estimates, the
+/// other projects, or between machines checked out in different directories, as the ID is
+/// derived from the path relative to the workspace root. Adding a shard only moves the
+/// projects that go to it. A retried CI job thus runs exactly the same projects. Dependencies aren't kept
+/// together, and the shards are only balanced by project count, on average.
+///
+/// # Parameters
+/// - `workspace`: The workspace the projects belong to.
+/// - `estimates`: The projects to shard, with their estimated duration, only summed into
+///   [`Shard::duration`].
+/// - `count`: How many shards to split into.
+///
+/// # Returns
+/// - `Ok(Vec<Shard>)`: The `count` shards, or a single one if `count` is zero.
+/// - `Err(TopologicalOrderError)`: If the workspace contains a dependency cycle.
+pub fn stable_shard(
+    workspace: &Workspace,
+    estimates: &HashMap<ProjectId, Duration>,
+    count: usize,
+) -> Result<Vec<Shard>, TopologicalOrderError> {
+    let mut shards = vec![Shard::default(); count.max(1)];
+
+    for id in workspace.topological_order()? {
+        let (Some(project), Some(duration)) = (workspace.get_project(id), estimates.get(&id))
+        else {
+            continue;
+        };
+
+        let index = (0..shards.len())
+            .max_by_key(|index| {
+                (
+                    weight(project.stable_id(), *index),
+                    std::cmp::Reverse(*index),
+                )
+            })
+            .unwrap_or(0);
+
+        shards[index].projects.push(id);
+        shards[index].duration += *duration;
+    }
+
+    Ok(shards)
+}
+
+/// The rendezvous weight of a project for a shard, the same on every platform and version.
+fn weight(stable_id: StableId, index: usize) -> u64 {
+    let digest = Sha256::new()
+        .chain_update(stable_id.into_inner().to_be_bytes())
+        .chain_update((index as u64).to_be_bytes())
+        .finalize();
+
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+
+    u64::from_be_bytes(bytes)
+}
+
+/// Splits `order` into the sets of projects connected through `dependencies`.
+fn groups<F>(order: &[ProjectId], dependencies: F) -> Vec<Vec<ProjectId>>
+where
+    F: Fn(ProjectId) -> Vec<ProjectId>,
+{
+    let mut group_of: HashMap<ProjectId, usize> = HashMap::new();
+    let mut groups: Vec<Vec<ProjectId>> = Vec::new();
+
+    for id in order {
+        // Dependencies come first in `order`, so they already have a group, to merge into.
+        let merged: HashSet<usize> = dependencies(*id)
+            .iter()
+            .filter_map(|dependency| group_of.get(dependency).copied())
+            .collect();
+
+        let target = merged.iter().min().copied().unwrap_or(groups.len());
+
+        if target == groups.len() {
+            groups.push(Vec::new());
+        }
+
+        for index in merged.iter().filter(|index| **index != target) {
+            for member in std::mem::take(&mut groups[*index]) {
+                group_of.insert(member, target);
+                groups[target].push(member);
+            }
+        }
+
+        group_of.insert(*id, target);
+        groups[target].push(*id);
+    }
+
+    groups.retain(|group| !group.is_empty());
+
+    groups
+}
+
+/// Returns the index of the shard with the least duration from `from` on, the first on ties.
+fn lightest(shards: &[Shard], from: usize) -> usize {
+    let from = from.min(shards.len() - 1);
+
+    (from..shards.len())
+        .min_by_key(|index| (shards[*index].duration, *index))
+        .unwrap_or(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::project::ProjectId;
+
+    use super::{shard, stable_shard, Durations, Shard, ShardConstraint};
+
+    #[test]
+    pub fn when_sharding_should_balance_durations_and_respect_dependencies() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("core", "core", None);
+        declaration.add_project("web", "web", Some(vec!["core".into()]));
+        declaration.add_project("api", "api", None);
+        declaration.add_project("docs", "docs", None);
+        declaration.add_project("cli", "cli", None);
+
+        let workspace = declaration.build_workspace().unwrap();
+        let id = |identifier: &str| workspace.get_id_by_identifier(identifier).unwrap();
+
+        let mut durations = Durations::new();
+        durations.insert("test", "core", Duration::from_secs(4));
+        durations.insert("test", "web", Duration::from_secs(2));
+        durations.insert("test", "api", Duration::from_secs(5));
+        durations.insert("test", "docs", Duration::from_secs(1));
+
+        let projects: Vec<ProjectId> = workspace.iter_with_ids().map(|(id, _)| id).collect();
+        let estimates = durations.estimates(&workspace, "test", projects);
+
+        let co_located = shard(&workspace, &estimates, 2, ShardConstraint::CoLocate).unwrap();
+        let ordered = shard(&workspace, &estimates, 2, ShardConstraint::Ordered).unwrap();
+        let single = shard(&workspace, &estimates, 0, ShardConstraint::CoLocate).unwrap();
+
+        let identifiers = |projects: &[ProjectId]| -> Vec<String> {
+            projects
+                .iter()
+                .map(|id| workspace.get_project(*id).unwrap().identifier().to_owned())
+                .collect()
+        };
+
+        // The cli has no recorded duration, so it weighs the mean of 3s.
+        assert_eq!(estimates[&id("cli")], Duration::from_secs(3));
+        assert_eq!(
+            identifiers(&co_located[0].projects),
+            ["core", "docs", "web"]
+        );
+        assert_eq!(identifiers(&co_located[1].projects), ["api", "cli"]);
+        assert_eq!(co_located[0].duration, Duration::from_secs(7));
+        assert_eq!(co_located[1].duration, Duration::from_secs(8));
+
+        let shard_of: HashMap<ProjectId, usize> = ordered
+            .iter()
+            .enumerate()
+            .flat_map(|(index, shard)| shard.projects.iter().map(move |id| (*id, index)))
+            .collect();
+
+        assert!(shard_of[&id("core")] <= shard_of[&id("web")]);
+        assert_eq!(shard_of.len(), 5);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].projects.len(), 5);
+    }
+
+    #[test]
+    pub fn when_sharding_stably_should_keep_projects_in_their_shard() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for index in 0..40 {
+            declaration.add_project(format!("libs/{index}"), format!("{index}"), None);
+        }
+
+        let workspace = declaration.build_workspace().unwrap();
+        let ids: Vec<ProjectId> = workspace.iter_with_ids().map(|(id, _)| id).collect();
+
+        let all: HashMap<ProjectId, Duration> =
+            ids.iter().map(|id| (*id, Duration::ZERO)).collect();
+        let mut some = all.clone();
+        some.retain(|id, _| id.into_inner() % 3 == 0);
+        some.insert(ids[0], Duration::from_secs(60));
+
+        let shard_of = |shards: Vec<Shard>| -> HashMap<ProjectId, usize> {
+            shards
+                .into_iter()
+                .enumerate()
+                .flat_map(|(index, shard)| shard.projects.into_iter().map(move |id| (id, index)))
+                .collect()
+        };
+
+        let four = shard_of(stable_shard(&workspace, &all, 4).unwrap());
+        let retried = shard_of(stable_shard(&workspace, &some, 4).unwrap());
+        let five = shard_of(stable_shard(&workspace, &all, 5).unwrap());
+
+        // The shard of a project depends neither on the estimates nor on the other projects.
+        assert!(retried.iter().all(|(id, index)| four[id] == *index));
+        // Adding a shard only moves projects to the new one.
+        assert!(five
+            .iter()
+            .all(|(id, index)| *index == 4 || four[id] == *index));
+        assert!(five.values().any(|index| *index == 4));
+        assert!((0..4).all(|index| four.values().any(|shard| *shard == index)));
+    }
+
+    #[test]
+    pub fn when_sharding_stably_under_other_root_should_split_the_same_way() {
+        let shards = |root: &str| -> Vec<Vec<String>> {
+            let mut declaration = WorkspaceDeclaration::new();
+
+            for index in 0..20 {
+                declaration.add_project(format!("libs/{index}"), format!("{index}"), None);
+            }
+
+            declaration.resolve_paths(root);
+
+            let workspace = declaration.build_workspace().unwrap();
+            let estimates: HashMap<ProjectId, Duration> = workspace
+                .iter_with_ids()
+                .map(|(id, _)| (id, Duration::ZERO))
+                .collect();
+
+            stable_shard(&workspace, &estimates, 3)
+                .unwrap()
+                .into_iter()
+                .map(|shard| {
+                    shard
+                        .projects
+                        .iter()
+                        .map(|id| workspace.get_project(*id).unwrap().name().to_owned())
+                        .collect()
+                })
+                .collect()
+        };
+
+        assert_eq!(shards("/builds/a"), shards("/builds/b@2"));
+    }
+}
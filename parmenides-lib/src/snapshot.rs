@@ -0,0 +1,373 @@
+//! # Snapshot
+//!
+//! Building a [`Workspace`] resolves every declared project and dependency, which adds up in
+//! workspaces with thousands of projects. A snapshot stores the built graph in a compact binary
+//! file, so later runs load it instead of building it again.
+//!
+//! Each snapshot records the [`content_hash`] of the declaration it was built from. Loading
+//! checks it against the current declaration, and a snapshot of another declaration, or of
+//! another version of parmenides, is ignored rather than trusted.
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::affected::builtin_strategy;
+use crate::cache::encode_hex;
+use crate::errors::SnapshotError;
+use crate::inputs::Inputs;
+use crate::project::{Deprecation, Project, ProjectId, StableId};
+use crate::tasks::Target;
+use crate::workspace::{Edge, Workspace};
+
+/// The snapshot file of a workspace, relative to its root.
+pub const SNAPSHOT_FILE: &str = ".parmenides/workspace.snapshot";
+
+/// Changes whenever the layout of a snapshot changes, so older snapshots are rebuilt.
+const FORMAT: u32 = 11;
+
+/// Returns the hex-encoded hash of the content of a declaration file.
+pub fn content_hash(content: &[u8]) -> String {
+    encode_hex(&Sha256::digest(content))
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    format: u32,
+    version: String,
+    content_hash: String,
+    /// The projects, in the order they were added, so dependencies come first.
+    projects: Vec<SnapshotProject>,
+    constants: BTreeMap<String, String>,
+    /// The root and globs of the triggers, see [`Workspace::triggers`].
+    triggers: Option<(PathBuf, Vec<String>)>,
+    /// The name of the affected strategy, see [`Workspace::affected_strategy`].
+    strategy: String,
+    /// The edges with non-default attributes, as dependent and dependency indices.
+    edges: Vec<(usize, usize, Edge)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotProject {
+    path: PathBuf,
+    identifier: String,
+    stable_id: u64,
+    name: String,
+    dependencies: Option<Vec<usize>>,
+    tags: Vec<String>,
+    targets: BTreeMap<String, Target>,
+    contract: Option<String>,
+    inputs: Vec<String>,
+    /// The sunset date and replacement index of a deprecated project.
+    deprecation: Option<(Option<String>, Option<usize>)>,
+    description: Option<String>,
+    owners: Vec<String>,
+    links: BTreeMap<String, String>,
+}
+
+/// Writes a snapshot of `workspace`, built from a declaration with `content_hash`, to `path`.
+///
+/// Whether projects are affected isn't part of the snapshot. Projects are stored by their
+/// position, so the IDs left by removed projects, see [`Workspace::remove_project`], are
+/// compacted on load.
+pub fn save(path: &Path, workspace: &Workspace, content_hash: &str) -> Result<(), SnapshotError> {
+    let positions: HashMap<ProjectId, usize> = workspace
+        .iter_with_ids()
+        .enumerate()
+        .map(|(position, (id, _))| (id, position))
+        .collect();
+
+    let projects = workspace
+        .iter()
+        .map(|project| SnapshotProject {
+            path: project.path.clone(),
+            identifier: project.identifier.clone(),
+            stable_id: project.stable_id.into_inner(),
+            name: project.name.clone(),
+            dependencies: project
+                .dependencies
+                .as_ref()
+                .map(|dependencies| dependencies.iter().map(|id| positions[id]).collect()),
+            tags: project.tags.clone(),
+            targets: project.targets.clone(),
+            contract: project.contract.clone(),
+            inputs: project.inputs().to_vec(),
+            deprecation: project.deprecation.as_ref().map(|deprecation| {
+                (
+                    deprecation.sunset.clone(),
+                    deprecation.replacement.map(|id| positions[&id]),
+                )
+            }),
+            description: project.description.clone(),
+            owners: project.owners.clone(),
+            links: project.links.clone(),
+        })
+        .collect();
+
+    let mut edges: Vec<(usize, usize, Edge)> = workspace
+        .edges()
+        .map(|(dependent, dependency, edge)| (positions[&dependent], positions[&dependency], edge))
+        .collect();
+    // Sorted, so the same workspace always writes the same bytes.
+    edges.sort_by_key(|(dependent, dependency, _)| (*dependent, *dependency));
+
+    let snapshot = Snapshot {
+        format: FORMAT,
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        content_hash: content_hash.to_owned(),
+        projects,
+        constants: workspace.constants().clone().into_iter().collect(),
+        triggers: workspace
+            .triggers()
+            .map(|(root, globs)| (root.to_path_buf(), globs.to_vec())),
+        strategy: workspace.affected_strategy().name().to_owned(),
+        edges,
+    };
+
+    let bytes = bincode::serde::encode_to_vec(&snapshot, bincode::config::standard())
+        .expect("snapshots serialize");
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| SnapshotError::Io(parent.to_path_buf(), err))?;
+    }
+
+    std::fs::write(path, bytes).map_err(|err| SnapshotError::Io(path.to_path_buf(), err))
+}
+
+/// Loads the snapshot at `path`, if it was built from a declaration with `content_hash`.
+///
+/// # Returns
+/// - `Ok(Some(Workspace))`: The workspace of the snapshot.
+/// - `Ok(None)`: If there is no snapshot, or it is stale: built from another declaration, or
+///   by another version of parmenides.
+/// - `Err(SnapshotError)`: If the snapshot could not be read, or is corrupted.
+pub fn load(path: &Path, content_hash: &str) -> Result<Option<Workspace>, SnapshotError> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(SnapshotError::Io(path.to_path_buf(), err)),
+    };
+
+    let invalid = |reason: String| SnapshotError::Invalid(path.to_path_buf(), reason);
+
+    // The format comes first, so it can be checked before decoding the rest.
+    let (format, _): (u32, usize) =
+        bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+            .map_err(|err| invalid(err.to_string()))?;
+
+    if format != FORMAT {
+        return Ok(None);
+    }
+
+    let (snapshot, _): (Snapshot, usize) =
+        bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+            .map_err(|err| invalid(err.to_string()))?;
+
+    if snapshot.version != env!("CARGO_PKG_VERSION") || snapshot.content_hash != content_hash {
+        return Ok(None);
+    }
+
+    let mut workspace = Workspace::new();
+    let mut deprecations = Vec::new();
+
+    for (position, project) in snapshot.projects.into_iter().enumerate() {
+        if let Some(deprecation) = project.deprecation {
+            deprecations.push((ProjectId::new(position), deprecation));
+        }
+
+        let dependencies = project
+            .dependencies
+            .map(|dependencies| dependencies.into_iter().map(ProjectId::new).collect());
+
+        let path = project.path.clone();
+        // The globs compiled when the snapshot was saved, so they still compile.
+        let inputs = if project.inputs.is_empty() {
+            None
+        } else {
+            let inputs = Inputs::new(project.inputs)
+                .map_err(|err| invalid(format!("{}: {err}", path.display())))?;
+
+            Some(inputs)
+        };
+        let project = Project::new(project.path, project.name, dependencies)
+            .with_identifier(project.identifier)
+            .with_stable_id(StableId::new(project.stable_id))
+            .with_tags(project.tags)
+            .with_targets(project.targets)
+            .with_contract(project.contract)
+            .with_inputs(inputs)
+            .with_description(project.description)
+            .with_owners(project.owners)
+            .with_links(project.links);
+
+        workspace
+            .add_project(project)
+            .map_err(|err| invalid(format!("{}: {err}", path.display())))?;
+    }
+
+    // After every project, as replacements may come later in the order.
+    for (id, (sunset, replacement)) in deprecations {
+        let replacement = replacement.map(ProjectId::new);
+
+        if replacement.is_some_and(|replacement| workspace.get_project(replacement).is_none()) {
+            return Err(invalid(format!("replacement of {id} is not a project")));
+        }
+
+        workspace.set_deprecation(
+            id,
+            Deprecation {
+                sunset,
+                replacement,
+            },
+        );
+    }
+
+    for (dependent, dependency, edge) in snapshot.edges {
+        let (dependent, dependency) = (ProjectId::new(dependent), ProjectId::new(dependency));
+
+        if workspace.edge(dependent, dependency).is_none() {
+            return Err(invalid(format!(
+                "edge from {dependent} to {dependency} is not a dependency"
+            )));
+        }
+
+        workspace.set_edge(dependent, dependency, edge);
+    }
+
+    if let Some((root, globs)) = snapshot.triggers {
+        let triggers = Inputs::new(globs).map_err(|err| invalid(err.to_string()))?;
+
+        workspace.set_triggers(root, triggers);
+    }
+
+    let strategy = builtin_strategy(&snapshot.strategy)
+        .ok_or_else(|| invalid(format!("unknown affected strategy {}", snapshot.strategy)))?;
+
+    workspace.set_affected_strategy(strategy);
+    workspace.set_constants(snapshot.constants.into_iter().collect());
+
+    Ok(Some(workspace))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::declarations::{DeprecationDeclaration, WorkspaceDeclaration};
+    use crate::errors::SnapshotError;
+    use crate::workspace::EdgeStrength;
+
+    use super::{content_hash, load, save, FORMAT};
+
+    /// Saves a snapshot of a workspace in a fresh directory named after `name`, returning the
+    /// directory and the snapshot, saved with the hash of `[projects]`.
+    fn saved(name: &str) -> (PathBuf, PathBuf) {
+        let root =
+            std::env::temp_dir().join(format!("parmenides-snapshot-{name}-{}", std::process::id()));
+        let path = root.join("workspace.snapshot");
+
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration
+            .add_project("/repo/libs/core", "core", None)
+            .deprecated = Some(DeprecationDeclaration {
+            sunset: Some("2027-01-31".to_owned()),
+            replacement: Some("apps/web".into()),
+        });
+        declaration
+            .add_project("/repo/tools/lint", "lint", None)
+            .owners = vec!["@tooling".to_owned()];
+        declaration
+            .add_project(
+                "/repo/apps/web",
+                "web",
+                Some(vec!["/repo/libs/core".into()]),
+            )
+            .soft_dependencies = vec!["/repo/tools/lint".into()];
+        declaration.add_constant("registry", "registry.example.com");
+        declaration.triggers = vec!["Cargo.lock".to_owned()];
+        declaration.affected_strategy = Some("changed_only".to_owned());
+        declaration.resolve_paths("/repo");
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        save(&path, &workspace, &content_hash(b"[projects]")).unwrap();
+
+        (root, path)
+    }
+
+    #[test]
+    pub fn when_loading_snapshot_should_restore_the_workspace() {
+        let (root, path) = saved("restore");
+
+        let loaded = load(&path, &content_hash(b"[projects]"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let loaded = loaded.unwrap().unwrap();
+        let web = loaded.get_id_by_path(&Path::new("/repo/apps/web")).unwrap();
+        let core = loaded.get_id_by_identifier("core").unwrap();
+        let lint = loaded.get_id_by_identifier("lint").unwrap();
+
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(
+            loaded.get_project(web).unwrap().dependencies(),
+            [core, lint]
+        );
+        assert_eq!(loaded.get_project(core).unwrap().dependents(), [web]);
+        assert_eq!(
+            loaded
+                .get_project(core)
+                .unwrap()
+                .deprecation()
+                .map(|deprecation| (deprecation.sunset(), deprecation.replacement())),
+            Some((Some("2027-01-31"), Some(web)))
+        );
+        assert_eq!(loaded.get_project(lint).unwrap().owners(), ["@tooling"]);
+        assert_eq!(
+            loaded.edge(web, lint).map(|edge| edge.strength),
+            Some(EdgeStrength::Soft)
+        );
+        assert_eq!(
+            loaded.constants().get("registry").map(String::as_str),
+            Some("registry.example.com")
+        );
+        assert!(loaded.is_trigger(&"/repo/Cargo.lock"));
+        assert_eq!(loaded.affected_strategy().name(), "changed_only");
+    }
+
+    #[test]
+    pub fn when_declaration_changed_should_not_load_the_stale_snapshot() {
+        let (root, path) = saved("stale");
+
+        let stale = load(&path, &content_hash(b"[projects.other]"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(stale.unwrap().is_none());
+    }
+
+    #[test]
+    pub fn when_snapshot_is_missing_should_return_none() {
+        let missing = load(
+            Path::new("/nonexistent/parmenides/workspace.snapshot"),
+            &content_hash(b"[projects]"),
+        );
+
+        assert!(missing.unwrap().is_none());
+    }
+
+    #[test]
+    pub fn when_snapshot_is_corrupted_should_return_invalid_error() {
+        let (root, path) = saved("corrupted");
+
+        // The current format, followed by a version longer than the file.
+        std::fs::write(&path, [FORMAT as u8, 200]).unwrap();
+        let corrupted = load(&path, &content_hash(b"[projects]"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(corrupted, Err(SnapshotError::Invalid(..))));
+    }
+}
@@ -0,0 +1,77 @@
+//! # Sorting
+//!
+//! Human-facing lists sort naturally: numbers inside names compare by value, so `lib2` comes
+//! before `lib10`, and letters compare case-insensitively. Every formatter sorts through
+//! [`sort_key`], so the same names always come out in the same order.
+use std::cmp::Ordering;
+
+/// A part of a [`SortKey`]. Numbers sort before text.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+enum Segment {
+    /// A run of digits, without its leading zeros. Longer runs are larger numbers.
+    Number(usize, String),
+    /// A run of other characters, lowercased.
+    Text(String),
+}
+
+/// The natural sort key of a string, see [`sort_key`].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct SortKey {
+    segments: Vec<Segment>,
+    /// Breaks the ties between strings only differing by case or leading zeros.
+    original: String,
+}
+
+/// Returns the natural sort key of `text`: numeric-aware and case-insensitive, with the
+/// original string breaking ties so the order is total.
+pub fn sort_key(text: &str) -> SortKey {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while let Some(first) = rest.chars().next() {
+        let is_digit = first.is_ascii_digit();
+        let end = rest
+            .find(|char: char| char.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+
+        let (run, remaining) = rest.split_at(end);
+
+        segments.push(if is_digit {
+            let digits = run.trim_start_matches('0');
+            Segment::Number(digits.len(), digits.to_owned())
+        } else {
+            Segment::Text(run.to_lowercase())
+        });
+
+        rest = remaining;
+    }
+
+    SortKey {
+        segments,
+        original: text.to_owned(),
+    }
+}
+
+/// Compares two strings by their [`sort_key`].
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    sort_key(a).cmp(&sort_key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::natural_cmp;
+
+    #[test]
+    pub fn when_sorting_should_compare_numbers_by_value_and_ignore_case() {
+        let mut names = vec![
+            "lib10", "Lib2", "lib2", "app", "lib02", "Zeta", "alpha", "lib1b",
+        ];
+
+        names.sort_by(|a, b| natural_cmp(a, b));
+
+        assert_eq!(
+            names,
+            vec!["alpha", "app", "lib1b", "Lib2", "lib02", "lib2", "lib10", "Zeta"]
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! # Stats
+//!
+//! Opt-in usage statistics, kept on the machine: each command run appends a [`StatsRecord`]
+//! with its duration and the size of the workspace to [`STATS_FILE`]. Nothing is ever sent
+//! anywhere. A [`StatsReport`] then shows how the analysis time evolves as the workspace grows,
+//! month by month.
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::StatsError;
+
+/// The statistics file of a workspace, relative to its root.
+pub const STATS_FILE: &str = ".parmenides/stats.jsonl";
+
+/// Represents the statistics settings of a workspace.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy)]
+pub struct StatsDeclaration {
+    /// Records every command run to [`STATS_FILE`]. Disabled by default.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// A single command run.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct StatsRecord {
+    /// When the command started, in milliseconds since the Unix epoch.
+    pub started_ms: u64,
+    /// The command, e.g. `affected`.
+    pub command: String,
+    /// How long the command ran, in milliseconds.
+    pub duration_ms: u64,
+    /// The number of projects in the workspace.
+    pub projects: usize,
+    /// The number of dependencies between them.
+    pub dependencies: usize,
+    /// Whether the command succeeded.
+    pub success: bool,
+}
+
+impl StatsRecord {
+    /// A record of `command`, started at `started` and run for `duration`.
+    pub fn new<S: Into<String>>(
+        command: S,
+        started: SystemTime,
+        duration: Duration,
+        projects: usize,
+        dependencies: usize,
+        success: bool,
+    ) -> Self {
+        Self {
+            started_ms: millis(started.duration_since(UNIX_EPOCH).unwrap_or_default()),
+            command: command.into(),
+            duration_ms: millis(duration),
+            projects,
+            dependencies,
+            success,
+        }
+    }
+
+    /// Returns the month the command started in, as `YYYY-MM`, in UTC.
+    pub fn month(&self) -> String {
+        let (year, month) = year_month(self.started_ms / 86_400_000);
+        format!("{year:04}-{month:02}")
+    }
+}
+
+pub(crate) fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Converts days since the Unix epoch to a civil year and month.
+fn year_month(days: u64) -> (u64, u64) {
+    let (year, month, _) = civil_date(days);
+
+    (year, month)
+}
+
+/// Converts days since the Unix epoch to a civil year, month and day.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+pub(crate) fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Appends `record` to the statistics file at `path`, creating it if needed.
+pub fn record(path: &Path, record: &StatsRecord) -> Result<(), StatsError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| StatsError::Io(parent.to_path_buf(), err))?;
+    }
+
+    let mut line = serde_json::to_string(record).expect("records serialize");
+    line.push('\n');
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|err| StatsError::Io(path.to_path_buf(), err))
+}
+
+/// Reads every record of the statistics file at `path`. A missing file has no records.
+///
+/// # Returns
+/// - `Ok(Vec<StatsRecord>)`: The records, in the order they were recorded.
+/// - `Err(StatsError)`: If the file could not be read, or a line is not a record.
+pub fn read(path: &Path) -> Result<Vec<StatsRecord>, StatsError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(StatsError::Io(path.to_path_buf(), err)),
+    };
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| StatsError::Parse(path.to_path_buf(), index + 1, err.to_string()))
+        })
+        .collect()
+}
+
+/// The runs of a command during a month.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct StatsPeriod {
+    /// The month, as `YYYY-MM`.
+    pub month: String,
+    pub runs: usize,
+    pub failures: usize,
+    /// The median duration of the runs, in milliseconds.
+    pub median_ms: u64,
+    /// The largest workspace the command ran on, in projects and dependencies.
+    pub projects: usize,
+    pub dependencies: usize,
+}
+
+/// The statistics of each command, month by month.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatsReport {
+    /// The periods of each command, by command, oldest first.
+    pub commands: BTreeMap<String, Vec<StatsPeriod>>,
+}
+
+impl StatsReport {
+    /// Summarizes `records` by command and month.
+    pub fn new(records: &[StatsRecord]) -> Self {
+        let mut grouped: BTreeMap<&str, BTreeMap<String, Vec<&StatsRecord>>> = BTreeMap::new();
+
+        for record in records {
+            grouped
+                .entry(&record.command)
+                .or_default()
+                .entry(record.month())
+                .or_default()
+                .push(record);
+        }
+
+        let commands = grouped
+            .into_iter()
+            .map(|(command, months)| {
+                let periods = months
+                    .into_iter()
+                    .map(|(month, records)| period(month, &records))
+                    .collect();
+
+                (command.to_owned(), periods)
+            })
+            .collect();
+
+        Self { commands }
+    }
+
+    /// Renders the report as a table per command.
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+
+        for (command, periods) in &self.commands {
+            output.push_str(&format!("{command}\n"));
+            output.push_str("  month    runs  failures  median ms  projects  dependencies\n");
+
+            for period in periods {
+                output.push_str(&format!(
+                    "  {:<7}  {:>4}  {:>8}  {:>9}  {:>8}  {:>12}\n",
+                    period.month,
+                    period.runs,
+                    period.failures,
+                    period.median_ms,
+                    period.projects,
+                    period.dependencies
+                ));
+            }
+        }
+
+        output
+    }
+}
+
+fn period(month: String, records: &[&StatsRecord]) -> StatsPeriod {
+    let mut durations: Vec<u64> = records.iter().map(|record| record.duration_ms).collect();
+    durations.sort_unstable();
+
+    StatsPeriod {
+        month,
+        runs: records.len(),
+        failures: records.iter().filter(|record| !record.success).count(),
+        median_ms: durations[durations.len() / 2],
+        projects: records
+            .iter()
+            .map(|record| record.projects)
+            .max()
+            .unwrap_or(0),
+        dependencies: records
+            .iter()
+            .map(|record| record.dependencies)
+            .max()
+            .unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{read, record, StatsRecord, StatsReport};
+
+    /// Runs of `command` on 2024-01-31, 2024-01-31, 2024-01-31 and 2024-02-01, in UTC.
+    fn runs(command: &str) -> Vec<StatsRecord> {
+        let january = UNIX_EPOCH + Duration::from_secs(1_706_659_200);
+        let february = UNIX_EPOCH + Duration::from_secs(1_706_745_600);
+
+        [
+            (january, 30, 10, true),
+            (january, 10, 12, false),
+            (january, 20, 11, true),
+            (february, 50, 40, true),
+        ]
+        .into_iter()
+        .map(|(started, duration, projects, success)| {
+            StatsRecord::new(
+                command,
+                started,
+                Duration::from_millis(duration),
+                projects,
+                projects * 2,
+                success,
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    pub fn when_reading_recorded_runs_should_return_them_in_order() {
+        let path = std::env::temp_dir()
+            .join(format!("parmenides-stats-{}", std::process::id()))
+            .join("stats.jsonl");
+
+        let runs = runs("affected");
+        for run in &runs {
+            record(&path, run).unwrap();
+        }
+
+        let records = read(&path);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(records.unwrap(), runs);
+    }
+
+    #[test]
+    pub fn when_stats_file_is_missing_should_read_no_runs() {
+        let records = read(Path::new("/nonexistent/parmenides/stats.jsonl"));
+
+        assert!(records.unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn when_reporting_should_group_runs_by_month() {
+        let report = StatsReport::new(&runs("affected"));
+        let periods = &report.commands["affected"];
+
+        assert_eq!(periods.len(), 2);
+        assert_eq!(
+            (
+                periods[0].month.as_str(),
+                periods[0].runs,
+                periods[0].failures
+            ),
+            ("2024-01", 3, 1)
+        );
+        assert_eq!((periods[0].median_ms, periods[0].projects), (20, 12));
+        assert_eq!(
+            (periods[1].month.as_str(), periods[1].median_ms),
+            ("2024-02", 50)
+        );
+    }
+
+    #[test]
+    pub fn when_reporting_should_summarize_each_command_apart() {
+        let mut records = runs("affected");
+        records.extend(runs("run").into_iter().take(1));
+
+        let report = StatsReport::new(&records);
+
+        assert_eq!(report.commands["affected"][0].runs, 3);
+        assert_eq!(report.commands["run"].len(), 1);
+        assert_eq!(report.commands["run"][0].runs, 1);
+        assert!(report.to_text().starts_with("affected\n"));
+    }
+}
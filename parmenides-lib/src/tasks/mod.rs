@@ -0,0 +1,164 @@
+//! # Tasks
+//!
+//! Each project can declare named targets, e.g. `build`, `test` or `lint`, with the command
+//! running them. The [`TaskRunner`], behind the `runner` feature, runs a target across a set of
+//! projects, typically the affected ones, dependencies first, and skips the dependents of the
+//! projects it failed in.
+//!
+//! With a cache, successful runs are stored under a key hashing the inputs of the project and
+//! the command, and replayed instead of running again while neither changes.
+//!
+//! With [`TaskRunner::with_parameters`], commands are interpolated before they run, e.g.
+//! `docker push {{ registry }}`, see [`crate::parameters`]. With
+//! [`TaskRunner::with_redactor`], secrets are masked in the outputs before anything reads or
+//! caches them, see [`crate::redaction`], and with [`TaskRunner::with_path_roots`] the roots of
+//! other environments are remapped, see [`crate::path_roots`].
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::process::ProcessOutput;
+use crate::project::ProjectId;
+use crate::warnings::PathWarning;
+use crate::workspace::Workspace;
+
+#[cfg(feature = "runner")]
+mod runner;
+
+#[cfg(feature = "runner")]
+pub use runner::{execution_waves, CacheMismatch, TaskRunner};
+
+/// Represents a declaration of a target, keyed by its name in the project declaration.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Target {
+    /// The shell command running the target, in the project directory.
+    pub command: String,
+}
+
+/// How running a target in a project ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum TaskStatus {
+    /// The command exited with code 0.
+    Succeeded(ProcessOutput),
+    /// The command succeeded in an earlier run with the same inputs, and its output was
+    /// replayed from the cache instead of running it again.
+    Cached(ProcessOutput),
+    /// The command exited with another code, or was terminated by a signal.
+    Failed(ProcessOutput),
+    /// The command was killed after running longer than the runner's timeout.
+    TimedOut,
+    /// The command wasn't run, as a dependency failed.
+    Skipped,
+}
+
+impl TaskStatus {
+    /// Returns `true` if the command succeeded, now or in the cached run.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Succeeded(_) | Self::Cached(_))
+    }
+}
+
+/// The result of running a target in a single project.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct TaskResult {
+    pub project: ProjectId,
+    pub status: TaskStatus,
+    /// How long the command ran. Zero if it didn't run, or its output was replayed.
+    pub duration: Duration,
+}
+
+/// The results of running a target across a set of projects.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct TaskReport {
+    /// The results of the projects defining the target, in the order they ran.
+    pub results: Vec<TaskResult>,
+    /// The inputs skipped while computing the cache keys, as they could not be read. The
+    /// projects they belong to ran without the cache.
+    pub warnings: Vec<PathWarning>,
+}
+
+impl TaskReport {
+    /// Returns `true` if the target succeeded in every project.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|result| result.status.is_success())
+    }
+
+    /// Returns the projects the target failed, timed out, or was skipped in.
+    pub fn unsuccessful(&self) -> impl Iterator<Item = ProjectId> + '_ {
+        self.results
+            .iter()
+            .filter(|result| !result.status.is_success())
+            .map(|result| result.project)
+    }
+}
+
+/// The version of the [`JsonTaskReport`] format, bumped on incompatible changes.
+pub const TASK_REPORT_FORMAT_VERSION: u32 = 1;
+
+/// A serializable [`TaskReport`], meant for CI systems. Outputs are left out, they are
+/// printed as the tasks run. See [`crate::schema::TASK_REPORT_SCHEMA`].
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonTaskReport {
+    pub version: u32,
+    pub results: Vec<JsonTaskResult>,
+}
+
+/// A result of a [`JsonTaskReport`].
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonTaskResult {
+    /// The identifier of the project.
+    pub project: String,
+    pub status: TaskOutcome,
+    /// The exit code, or `None` if the command didn't run or was terminated by a signal.
+    pub code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+/// How running a target ended, without the output, see [`TaskStatus`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOutcome {
+    Succeeded,
+    Cached,
+    Failed,
+    TimedOut,
+    Skipped,
+}
+
+impl JsonTaskReport {
+    /// Summarizes the results of `report`, naming the projects with their identifier in
+    /// `workspace`.
+    pub fn new(workspace: &Workspace, report: &TaskReport) -> Self {
+        let results = report
+            .results
+            .iter()
+            .map(|result| {
+                let (status, code) = match &result.status {
+                    TaskStatus::Succeeded(output) => (TaskOutcome::Succeeded, output.code),
+                    TaskStatus::Cached(output) => (TaskOutcome::Cached, output.code),
+                    TaskStatus::Failed(output) => (TaskOutcome::Failed, output.code),
+                    TaskStatus::TimedOut => (TaskOutcome::TimedOut, None),
+                    TaskStatus::Skipped => (TaskOutcome::Skipped, None),
+                };
+
+                JsonTaskResult {
+                    project: workspace
+                        .get_project(result.project)
+                        .map(|project| project.identifier.clone())
+                        .unwrap_or_default(),
+                    status,
+                    code,
+                    duration_ms: u64::try_from(result.duration.as_millis()).unwrap_or(u64::MAX),
+                }
+            })
+            .collect();
+
+        Self {
+            version: TASK_REPORT_FORMAT_VERSION,
+            results,
+        }
+    }
+}
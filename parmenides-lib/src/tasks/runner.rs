@@ -0,0 +1,1207 @@
+//! The [`TaskRunner`], running the targets of the projects.
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{task_key, CacheScope, CacheStore, InputHasher};
+use crate::clock::{Clock, SystemClock};
+use crate::context::Context;
+use crate::errors::{CacheError, TaskError, TopologicalOrderError};
+use crate::file_system::FileSystem;
+use crate::parameters::Parameters;
+use crate::path_roots::PathRoots;
+use crate::process::{shell_command, ProcessOutput, ProcessRunner};
+use crate::progress::Stage;
+use crate::project::{Project, ProjectId};
+use crate::redaction::Redactor;
+use crate::warnings::PathWarning;
+use crate::workspace::Workspace;
+
+use super::{TaskReport, TaskResult, TaskStatus};
+
+/// Splits `projects` into waves, each depending only on projects of earlier waves, so the
+/// projects of a wave can run concurrently.
+///
+/// The depth of a project is measured over the whole workspace, so a dependency through a
+/// project outside `projects` still orders the waves. Each wave is in ID order.
+pub fn execution_waves(
+    workspace: &Workspace,
+    projects: &HashSet<ProjectId>,
+) -> Result<Vec<Vec<ProjectId>>, TopologicalOrderError> {
+    let mut depths = vec![0; workspace.id_bound()];
+    let mut waves: Vec<Vec<ProjectId>> = Vec::new();
+
+    for id in workspace.topological_order()? {
+        let depth = workspace
+            .get_project(id)
+            .into_iter()
+            .flat_map(|project| project.dependencies())
+            .map(|dependency| depths[dependency.into_inner()] + 1)
+            .max()
+            .unwrap_or(0);
+
+        depths[id.into_inner()] = depth;
+
+        if projects.contains(&id) {
+            if waves.len() <= depth {
+                waves.resize_with(depth + 1, Vec::new);
+            }
+
+            waves[depth].push(id);
+        }
+    }
+
+    waves.retain(|wave| !wave.is_empty());
+
+    for wave in &mut waves {
+        wave.sort();
+    }
+
+    Ok(waves)
+}
+
+/// What a single cached run stores, see [`TaskStatus::Cached`].
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    code: Option<i32>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+/// A cached run that running its command again doesn't reproduce, see
+/// [`TaskRunner::verify_cached`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CacheMismatch {
+    /// The project the command ran in.
+    pub project: ProjectId,
+    /// The key the run is cached under.
+    pub key: String,
+}
+
+/// The cache keys of the projects, and the inputs that could not be read.
+type TaskKeys = (HashMap<ProjectId, String>, Vec<PathWarning>);
+
+/// The command each project runs, interpolated.
+type Commands = HashMap<ProjectId, String>;
+
+struct TaskCache<'a> {
+    store: &'a dyn CacheStore,
+    scope: CacheScope,
+    fs: &'a (dyn FileSystem + Sync),
+    /// The keys of the entries that could not be decoded, with the error.
+    undecodable: Mutex<Vec<(String, String)>>,
+}
+
+/// Runs targets across projects, in dependency order.
+pub struct TaskRunner<'a, C = SystemClock> {
+    runner: &'a dyn ProcessRunner,
+    jobs: usize,
+    cache: Option<TaskCache<'a>>,
+    parameters: Option<Parameters>,
+    redactor: Redactor,
+    roots: PathRoots,
+    clock: C,
+}
+
+impl<'a> TaskRunner<'a> {
+    /// Creates a task runner spawning the commands through `runner`, one at a time. Its
+    /// timeout, if any, applies to each command.
+    pub fn new(runner: &'a dyn ProcessRunner) -> Self {
+        Self {
+            runner,
+            jobs: 1,
+            cache: None,
+            parameters: None,
+            redactor: Redactor::new(),
+            roots: PathRoots::new(),
+            clock: SystemClock,
+        }
+    }
+}
+
+impl<'a, C: Clock> TaskRunner<'a, C> {
+    /// Measures the duration of the runs with the given clock.
+    pub fn with_clock<D: Clock>(self, clock: D) -> TaskRunner<'a, D> {
+        TaskRunner {
+            runner: self.runner,
+            jobs: self.jobs,
+            cache: self.cache,
+            parameters: self.parameters,
+            redactor: self.redactor,
+            roots: self.roots,
+            clock,
+        }
+    }
+
+    /// Runs up to `jobs` commands at once. A value of 0 is treated as 1.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Replaces the `{{ key }}` placeholders of every command with `parameters` before it runs,
+    /// and before it is hashed into the cache key, so runs with different values don't share
+    /// an entry. Without parameters, commands run as they are written.
+    pub fn with_parameters(mut self, parameters: Parameters) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+
+    /// Masks the secrets of `redactor` in the outputs of the commands, before they are
+    /// reported or cached. Outputs replayed from the cache are masked too, in case they were
+    /// stored before a secret was declared.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Remaps the paths under `roots` in the outputs of the commands, before they are reported
+    /// or cached, and in the input hashes, so entries and logs are portable between the
+    /// environments the workspace is mounted in.
+    pub fn with_path_roots(mut self, roots: PathRoots) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    /// Caches successful runs in `store`, reading and writing the namespaces of `scope`. The
+    /// inputs of the projects are read from `fs`, see [`InputHasher`].
+    pub fn with_cache(
+        mut self,
+        store: &'a dyn CacheStore,
+        scope: CacheScope,
+        fs: &'a (dyn FileSystem + Sync),
+    ) -> Self {
+        self.cache = Some(TaskCache {
+            store,
+            scope,
+            fs,
+            undecodable: Mutex::new(vec![]),
+        });
+        self
+    }
+
+    /// Returns the cache entries that could not be decoded, e.g. as an older version wrote
+    /// them, in the order they were read. They were treated as misses, so their tasks ran.
+    pub fn undecodable(&self) -> Vec<CacheError> {
+        self.cache
+            .iter()
+            .flat_map(|cache| cache.undecodable.lock().unwrap().clone())
+            .map(|(key, message)| CacheError::Corrupt(key, message))
+            .collect()
+    }
+
+    /// Runs `target` in each of `projects` defining it, after their dependencies.
+    ///
+    /// The projects are split into [`execution_waves`], and the projects of a wave run
+    /// concurrently, up to the number of jobs. When the target doesn't succeed in a project, it
+    /// is skipped in the projects depending on it, directly or not, while the others keep
+    /// running.
+    ///
+    /// # Parameters
+    /// - `workspace`: The workspace the projects belong to.
+    /// - `target`: The name of the target to run.
+    /// - `projects`: The projects to run it in, e.g. the affected ones.
+    /// - `context`: Receives each project run under [`Stage::Tasks`] and stops the run when
+    ///   cancelled.
+    ///
+    /// # Returns
+    /// - `Ok(TaskReport)`: The result of every project defining the target, wave by wave.
+    /// - `Err(TaskError)`: If a command could not be interpolated or spawned, the cache could
+    ///   not be accessed, or the run was cancelled.
+    pub fn run<I>(
+        &self,
+        workspace: &Workspace,
+        target: &str,
+        projects: I,
+        context: &Context,
+    ) -> Result<TaskReport, TaskError>
+    where
+        I: IntoIterator<Item = ProjectId>,
+    {
+        self.run_with(workspace, projects, context, |project| {
+            project
+                .target(target)
+                .map(|definition| definition.command.as_str())
+        })
+    }
+
+    /// Runs the same shell `command` in each of `projects`, like [`Self::run`].
+    pub fn run_command<I>(
+        &self,
+        workspace: &Workspace,
+        command: &str,
+        projects: I,
+        context: &Context,
+    ) -> Result<TaskReport, TaskError>
+    where
+        I: IntoIterator<Item = ProjectId>,
+    {
+        self.run_with(workspace, projects, context, |_| Some(command))
+    }
+
+    /// Runs the contract target of every project a change stopped at, see
+    /// [`Workspace::stopped_projects`], and resumes the propagation past those whose contract
+    /// doesn't pass, so their dependents are affected after all. Projects without a contract
+    /// keep stopping the change.
+    ///
+    /// Resuming may stop the change at further projects, whose contracts then run too.
+    ///
+    /// # Returns
+    /// - `Ok(TaskReport)`: The result of every contract, in the order they ran.
+    /// - `Err(TaskError)`: If a command could not be spawned, the cache could not be accessed,
+    ///   or the run was cancelled.
+    pub fn gate_contracts(
+        &self,
+        workspace: &mut Workspace,
+        context: &Context,
+    ) -> Result<TaskReport, TaskError> {
+        let mut report = TaskReport::default();
+        let mut checked = HashSet::new();
+
+        loop {
+            let pending: Vec<ProjectId> = workspace
+                .stopped_projects()
+                .filter(|id| !checked.contains(id))
+                .filter(|id| {
+                    workspace
+                        .get_project(*id)
+                        .is_some_and(|project| project.contract().is_some())
+                })
+                .collect();
+
+            if pending.is_empty() {
+                return Ok(report);
+            }
+
+            checked.extend(pending.iter().copied());
+
+            let contracts = self.run_with(workspace, pending, context, |project| {
+                project
+                    .target(project.contract()?)
+                    .map(|definition| definition.command.as_str())
+            })?;
+
+            workspace
+                .resume_propagation(contracts.unsuccessful())
+                .expect("contracts only run in projects of the workspace");
+
+            report.results.extend(contracts.results);
+            report.warnings.extend(contracts.warnings);
+        }
+    }
+
+    /// Runs `target` again in each of `projects` with a cached run, dependencies first, and
+    /// returns those whose fresh run exits with another code or prints another output, on stdout
+    /// or stderr, than the cached one, e.g. as the command embeds a timestamp. The cache would
+    /// replay a run the command doesn't reproduce for them, and it replays both outputs, so a
+    /// warning that changes between runs is a mismatch too.
+    ///
+    /// Fresh runs aren't cached. Projects without a cached run, with one that can't be decoded,
+    /// or with inputs that could not be read, aren't run, and nothing runs without
+    /// [`Self::with_cache`].
+    ///
+    /// # Returns
+    /// - `Ok(Vec<CacheMismatch>)`: The projects whose fresh run differs, in the order they ran.
+    /// - `Err(TaskError)`: If a command could not be interpolated or spawned, the cache could
+    ///   not be read, or the check was cancelled.
+    pub fn verify_cached<I>(
+        &self,
+        workspace: &Workspace,
+        target: &str,
+        projects: I,
+        context: &Context,
+    ) -> Result<Vec<CacheMismatch>, TaskError>
+    where
+        I: IntoIterator<Item = ProjectId>,
+    {
+        let Some(cache) = &self.cache else {
+            return Ok(vec![]);
+        };
+
+        let mut commands = Commands::new();
+
+        for id in projects {
+            let Some(project) = workspace.get_project(id) else {
+                continue;
+            };
+
+            if let Some(definition) = project.target(target) {
+                commands.insert(id, self.interpolate(project, &definition.command)?);
+            }
+        }
+
+        let (keys, _) = self.task_keys(workspace, &commands, context)?;
+        let mut entries = HashMap::new();
+
+        for (id, key) in keys {
+            if let Some(entry) = self.cached_entry(cache, &key)? {
+                entries.insert(id, entry);
+            }
+        }
+
+        let selected: HashSet<ProjectId> = entries.keys().copied().collect();
+        let mut mismatches = Vec::new();
+
+        context.progress().start(Stage::Tasks, Some(selected.len()));
+
+        for id in execution_waves(workspace, &selected)?.into_iter().flatten() {
+            let (Some(project), Some(command), Some((key, entry))) = (
+                workspace.get_project(id),
+                commands.get(&id),
+                entries.remove(&id),
+            ) else {
+                continue;
+            };
+
+            if context.is_cancelled() {
+                return Err(TaskError::Cancelled);
+            }
+
+            context.progress().advance(Stage::Tasks, &project.name);
+
+            let reproduced = match self.run_project(project, command)? {
+                TaskStatus::Succeeded(output) | TaskStatus::Failed(output) => {
+                    output.code == entry.code
+                        && output.stdout == entry.stdout
+                        && output.stderr == entry.stderr
+                }
+                _ => false,
+            };
+
+            if !reproduced {
+                mismatches.push(CacheMismatch { project: id, key });
+            }
+        }
+
+        context.progress().finish(Stage::Tasks);
+
+        Ok(mismatches)
+    }
+
+    fn run_with<'w, I, F>(
+        &self,
+        workspace: &'w Workspace,
+        projects: I,
+        context: &Context,
+        command: F,
+    ) -> Result<TaskReport, TaskError>
+    where
+        I: IntoIterator<Item = ProjectId>,
+        F: Fn(&'w Project) -> Option<&'w str>,
+    {
+        let mut commands = Commands::new();
+
+        for id in projects {
+            let Some(project) = workspace.get_project(id) else {
+                continue;
+            };
+
+            if let Some(template) = command(project) {
+                commands.insert(id, self.interpolate(project, template)?);
+            }
+        }
+
+        let selected: HashSet<ProjectId> = commands.keys().copied().collect();
+        let (keys, warnings) = self.task_keys(workspace, &commands, context)?;
+
+        let mut report = TaskReport {
+            warnings,
+            ..TaskReport::default()
+        };
+        let mut unsuccessful = HashSet::new();
+
+        let dependencies: HashMap<ProjectId, _> = selected
+            .iter()
+            .map(|id| {
+                (
+                    *id,
+                    workspace.transitive_dependencies(*id).unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        context.progress().start(Stage::Tasks, Some(selected.len()));
+
+        for wave in execution_waves(workspace, &selected)? {
+            let blocked = |id: ProjectId| {
+                dependencies[&id]
+                    .iter()
+                    .any(|dependency| unsuccessful.contains(dependency))
+            };
+
+            let runnable: Vec<ProjectId> =
+                wave.iter().copied().filter(|id| !blocked(*id)).collect();
+            let mut statuses = self.run_wave(workspace, &runnable, &keys, context, &commands)?;
+
+            for id in wave {
+                let (status, duration) = statuses
+                    .remove(&id)
+                    .unwrap_or((TaskStatus::Skipped, Duration::ZERO));
+
+                if status == TaskStatus::Skipped {
+                    if let Some(project) = workspace.get_project(id) {
+                        context.progress().advance(Stage::Tasks, &project.name);
+                    }
+                }
+
+                if !status.is_success() {
+                    unsuccessful.insert(id);
+                }
+
+                report.results.push(TaskResult {
+                    project: id,
+                    status,
+                    duration,
+                });
+            }
+        }
+
+        context.progress().finish(Stage::Tasks);
+
+        Ok(report)
+    }
+
+    /// Returns `template` with the parameters of the runner, see [`Self::with_parameters`].
+    fn interpolate(&self, project: &Project, template: &str) -> Result<String, TaskError> {
+        match &self.parameters {
+            Some(parameters) => parameters
+                .interpolate(template)
+                .map_err(|err| TaskError::Interpolate(project.path.clone(), err)),
+            None => Ok(template.to_owned()),
+        }
+    }
+
+    /// Runs the commands of a wave on up to `jobs` threads.
+    fn run_wave(
+        &self,
+        workspace: &Workspace,
+        wave: &[ProjectId],
+        keys: &HashMap<ProjectId, String>,
+        context: &Context,
+        commands: &Commands,
+    ) -> Result<HashMap<ProjectId, (TaskStatus, Duration)>, TaskError> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(wave.len()));
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.min(wave.len()) {
+                scope.spawn(|| {
+                    while let Some(id) = wave.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if context.is_cancelled() {
+                            return;
+                        }
+
+                        let (Some(project), Some(command)) =
+                            (workspace.get_project(*id), commands.get(id))
+                        else {
+                            continue;
+                        };
+
+                        let started = self.clock.now();
+                        let result =
+                            self.run_cached(project, command, keys.get(id).map(String::as_str));
+                        let duration = match result {
+                            Ok(TaskStatus::Cached(_)) => Duration::ZERO,
+                            _ => self.clock.now().duration_since(started),
+                        };
+
+                        context.progress().advance(Stage::Tasks, &project.name);
+                        results.lock().unwrap().push((*id, result, duration));
+                    }
+                });
+            }
+        });
+
+        if context.is_cancelled() {
+            return Err(TaskError::Cancelled);
+        }
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(id, ..)| *id);
+
+        results
+            .into_iter()
+            .map(|(id, result, duration)| result.map(|status| (id, (status, duration))))
+            .collect()
+    }
+
+    /// Returns the cache key of each project, or nothing without a cache, and the inputs that
+    /// could not be read. Projects with such inputs get no key, so they aren't cached.
+    fn task_keys(
+        &self,
+        workspace: &Workspace,
+        commands: &Commands,
+        context: &Context,
+    ) -> Result<TaskKeys, TaskError> {
+        let Some(cache) = &self.cache else {
+            return Ok((HashMap::new(), vec![]));
+        };
+
+        let mut hasher = InputHasher::new(cache.fs, workspace)
+            .with_io_policy(context.io_policy())
+            .with_path_roots(self.roots.clone());
+        let mut keys = HashMap::with_capacity(commands.len());
+
+        for (id, command) in commands {
+            let input_hash = hasher.input_hash(*id, context).map_err(|err| match err {
+                CacheError::Cancelled => TaskError::Cancelled,
+                err => err.into(),
+            })?;
+
+            if hasher.is_complete(*id) {
+                keys.insert(*id, task_key(&input_hash, command));
+            }
+        }
+
+        Ok((keys, hasher.into_warnings()))
+    }
+
+    fn run_cached(
+        &self,
+        project: &Project,
+        command: &str,
+        key: Option<&str>,
+    ) -> Result<TaskStatus, TaskError> {
+        let (Some(cache), Some(key)) = (&self.cache, key) else {
+            return self.run_project(project, command);
+        };
+
+        if let Some((_, entry)) = self.cached_entry(cache, key)? {
+            return Ok(TaskStatus::Cached(self.clean(ProcessOutput {
+                code: entry.code,
+                stdout: entry.stdout,
+                stderr: entry.stderr,
+            })));
+        }
+
+        let status = self.run_project(project, command)?;
+
+        if let (TaskStatus::Succeeded(output), false) = (&status, cache.scope.is_read_only()) {
+            let entry = CacheEntry {
+                code: output.code,
+                stdout: output.stdout.clone(),
+                stderr: output.stderr.clone(),
+            };
+
+            let content = serde_json::to_vec(&entry)
+                .map_err(|err| CacheError::Corrupt(key.to_owned(), err.to_string()))?;
+
+            cache.store.put(&cache.scope.write_key(key)?, &content)?;
+        }
+
+        Ok(status)
+    }
+
+    /// Returns the first entry cached under the keys `cache` reads for `key`, with the key it
+    /// was read from. Entries that can't be decoded are skipped, see [`Self::undecodable`].
+    fn cached_entry(
+        &self,
+        cache: &TaskCache,
+        key: &str,
+    ) -> Result<Option<(String, CacheEntry)>, TaskError> {
+        for read_key in cache.scope.read_keys(key) {
+            let Some(content) = cache.store.get(&read_key)? else {
+                continue;
+            };
+
+            // A miss, like an entry failing its integrity check, so the task runs again.
+            match serde_json::from_slice(&content) {
+                Ok(entry) => return Ok(Some((read_key, entry))),
+                Err(err) => cache
+                    .undecodable
+                    .lock()
+                    .unwrap()
+                    .push((read_key, err.to_string())),
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn run_project(&self, project: &Project, command: &str) -> Result<TaskStatus, TaskError> {
+        let mut shell = shell_command(command);
+        shell.current_dir(&project.path);
+
+        match self.runner.run(&mut shell).map(|output| self.clean(output)) {
+            Ok(output) if output.is_success() => Ok(TaskStatus::Succeeded(output)),
+            Ok(output) => Ok(TaskStatus::Failed(output)),
+            Err(err) if err.kind() == std::io::ErrorKind::TimedOut => Ok(TaskStatus::TimedOut),
+            Err(err) => Err(TaskError::Run(project.path.clone(), err)),
+        }
+    }
+
+    /// Remaps the roots in the output, then masks its secrets, so a secret in a remapped path
+    /// is masked too.
+    fn clean(&self, output: ProcessOutput) -> ProcessOutput {
+        let clean = |bytes| self.redactor.redact_bytes(self.roots.remap_bytes(bytes));
+
+        ProcessOutput {
+            code: output.code,
+            stdout: clean(output.stdout),
+            stderr: clean(output.stderr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::path::Path;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::time::Duration;
+
+    use crate::cache::{CacheScope, CacheStore, MemoryCacheStore};
+    use crate::clock::ManualClock;
+    use crate::context::Context;
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::{CacheError, InterpolateError, TaskError};
+    use crate::file_system::MemoryFileSystem;
+    use crate::parameters::Parameters;
+    use crate::path_roots::PathRoots;
+    use crate::process::{ProcessOutput, ProcessRunner, ScriptedProcessRunner};
+    use crate::project::ProjectId;
+    use crate::workspace::Workspace;
+
+    use super::{execution_waves, TaskRunner};
+    use crate::tasks::{Target, TaskStatus};
+
+    /// Records how many commands run at the same time. Each command waits for `jobs` commands
+    /// to be running, so a runner running fewer at once never finishes.
+    struct ConcurrencyRunner {
+        barrier: Barrier,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl ConcurrencyRunner {
+        fn new(jobs: usize) -> Self {
+            Self {
+                barrier: Barrier::new(jobs),
+                running: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl ProcessRunner for ConcurrencyRunner {
+        fn run(&self, _command: &mut Command) -> std::io::Result<ProcessOutput> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+
+            self.barrier.wait();
+
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(ProcessOutput::success(""))
+        }
+    }
+
+    /// Takes a second of `clock` to run each command.
+    struct SlowRunner<'a> {
+        clock: &'a ManualClock,
+    }
+
+    impl ProcessRunner for SlowRunner<'_> {
+        fn run(&self, _command: &mut Command) -> std::io::Result<ProcessOutput> {
+            self.clock.advance(Duration::from_secs(1));
+            Ok(ProcessOutput::success(""))
+        }
+    }
+
+    fn workspace() -> Workspace {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for (path, dependencies, command) in [
+            ("/repo/core", vec![], Some("exit 1")),
+            ("/repo/ui", vec!["/repo/core"], Some("true")),
+            ("/repo/docs", vec![], Some("true")),
+            ("/repo/web", vec!["/repo/ui"], None),
+        ] {
+            let project = declaration.add_project(
+                path,
+                path.trim_start_matches("/repo/"),
+                Some(dependencies.into_iter().map(Into::into).collect()),
+            );
+
+            if let Some(command) = command {
+                project.targets = BTreeMap::from([(
+                    "test".to_owned(),
+                    Target {
+                        command: command.to_owned(),
+                    },
+                )]);
+            }
+        }
+
+        declaration.build_workspace().unwrap()
+    }
+
+    #[test]
+    pub fn when_target_fails_should_skip_dependents_and_keep_running_others() {
+        let workspace = workspace();
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let runner = ScriptedProcessRunner::new().with_output(
+            "sh",
+            ProcessOutput {
+                code: Some(1),
+                ..ProcessOutput::default()
+            },
+        );
+
+        let all: Vec<ProjectId> = workspace.iter_with_ids().map(|(id, _)| id).collect();
+        let report = TaskRunner::new(&runner)
+            .run(&workspace, "test", all, &Context::new())
+            .unwrap();
+
+        let ran = runner.commands().len();
+
+        let status = |path: &str| {
+            report
+                .results
+                .iter()
+                .find(|result| result.project == id(path))
+                .map(|result| &result.status)
+        };
+
+        assert!(matches!(status("/repo/core"), Some(TaskStatus::Failed(_))));
+        assert_eq!(status("/repo/ui"), Some(&TaskStatus::Skipped));
+        assert!(matches!(status("/repo/docs"), Some(TaskStatus::Failed(_))));
+        assert_eq!(status("/repo/web"), None);
+        assert_eq!(ran, 2);
+        assert!(!report.is_success());
+    }
+
+    /// Runs nothing, failing the commands that contain `exit 1`.
+    struct ExitCodeRunner;
+
+    impl ProcessRunner for ExitCodeRunner {
+        fn run(&self, command: &mut Command) -> std::io::Result<ProcessOutput> {
+            let failing = command
+                .get_args()
+                .any(|arg| arg.to_string_lossy().contains("exit 1"));
+
+            Ok(ProcessOutput {
+                code: Some(if failing { 1 } else { 0 }),
+                ..ProcessOutput::default()
+            })
+        }
+    }
+
+    #[test]
+    pub fn when_contract_fails_should_propagate_past_its_project() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/core", "core", None);
+
+        for (facade, app, contract) in [
+            ("/repo/passing", "/repo/passing-app", "true"),
+            ("/repo/failing", "/repo/failing-app", "exit 1"),
+        ] {
+            let project = declaration.add_project(facade, facade, Some(vec!["/repo/core".into()]));
+            project.encapsulates = vec!["/repo/core".into()];
+            project.contract = Some("contract".to_owned());
+            project.targets = BTreeMap::from([(
+                "contract".to_owned(),
+                Target {
+                    command: contract.to_owned(),
+                },
+            )]);
+
+            declaration.add_project(app, app, Some(vec![facade.into()]));
+        }
+
+        let mut workspace = declaration.build_workspace().unwrap();
+        let id = |workspace: &Workspace, path: &str| workspace.get_id_by_path(&path).unwrap();
+        let core = id(&workspace, "/repo/core");
+
+        workspace.mark_project_as_affected(core).unwrap();
+        let stopped: Vec<ProjectId> = workspace.stopped_projects().collect();
+
+        let report = TaskRunner::new(&ExitCodeRunner)
+            .gate_contracts(&mut workspace, &Context::new())
+            .unwrap();
+
+        let affected = |path: &str| {
+            workspace
+                .get_project(id(&workspace, path))
+                .unwrap()
+                .is_affected()
+        };
+
+        assert_eq!(stopped.len(), 2);
+        assert_eq!(report.results.len(), 2);
+        assert!(affected("/repo/failing-app"));
+        assert!(!affected("/repo/passing-app"));
+        assert_eq!(
+            workspace.stopped_projects().collect::<Vec<_>>(),
+            vec![id(&workspace, "/repo/passing")]
+        );
+    }
+
+    #[test]
+    pub fn when_running_subset_should_follow_dependency_order() {
+        let workspace = workspace();
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let runner = ScriptedProcessRunner::new().with_output("sh", ProcessOutput::success(""));
+        let report = TaskRunner::new(&runner)
+            .run(
+                &workspace,
+                "test",
+                [id("/repo/ui"), id("/repo/core")],
+                &Context::new(),
+            )
+            .unwrap();
+
+        let order: Vec<ProjectId> = report.results.iter().map(|result| result.project).collect();
+
+        assert_eq!(order, vec![id("/repo/core"), id("/repo/ui")]);
+        assert!(report.is_success());
+    }
+
+    #[test]
+    pub fn when_splitting_into_waves_should_order_through_unselected_projects() {
+        let workspace = workspace();
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let selected = HashSet::from([id("/repo/core"), id("/repo/web"), id("/repo/docs")]);
+
+        let mut first = vec![id("/repo/core"), id("/repo/docs")];
+        first.sort();
+
+        assert_eq!(
+            execution_waves(&workspace, &selected).unwrap(),
+            vec![first, vec![id("/repo/web")]]
+        );
+    }
+
+    #[test]
+    pub fn when_running_with_jobs_should_run_wave_concurrently_within_limit() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for index in 0..6 {
+            let path = format!("/repo/lib{index}");
+
+            declaration
+                .add_project(path.as_str(), path.as_str(), None)
+                .targets
+                .insert(
+                    "build".to_owned(),
+                    Target {
+                        command: "true".to_owned(),
+                    },
+                );
+        }
+
+        let workspace = declaration.build_workspace().unwrap();
+        let all: Vec<ProjectId> = workspace.iter_with_ids().map(|(id, _)| id).collect();
+
+        let runner = ConcurrencyRunner::new(3);
+        let report = TaskRunner::new(&runner)
+            .with_jobs(3)
+            .run(&workspace, "build", all, &Context::new())
+            .unwrap();
+
+        assert_eq!(report.results.len(), 6);
+        assert!(report.is_success());
+        assert_eq!(runner.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    pub fn when_running_with_clock_should_measure_durations_with_it() {
+        let workspace = workspace();
+        let clock = ManualClock::new();
+        let runner = SlowRunner { clock: &clock };
+
+        let all: Vec<ProjectId> = workspace.iter_with_ids().map(|(id, _)| id).collect();
+        let report = TaskRunner::new(&runner)
+            .with_clock(&clock)
+            .run(&workspace, "test", all, &Context::new())
+            .unwrap();
+
+        let durations: Vec<Duration> = report
+            .results
+            .iter()
+            .map(|result| result.duration)
+            .collect();
+
+        assert_eq!(durations, [Duration::from_secs(1); 3]);
+    }
+
+    #[test]
+    pub fn when_running_with_parameters_should_interpolate_commands_and_keys() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration
+            .add_project("/repo/app", "app", None)
+            .targets
+            .insert(
+                "push".to_owned(),
+                Target {
+                    command: "docker push {{ registry }}/app".to_owned(),
+                },
+            );
+
+        let workspace = declaration.build_workspace().unwrap();
+        let app = workspace.get_id_by_path(&"/repo/app").unwrap();
+
+        let fs = MemoryFileSystem::new().with_file("/repo/app/Dockerfile", "FROM scratch");
+        let store = MemoryCacheStore::new();
+        let runner = ScriptedProcessRunner::new().with_output("sh", ProcessOutput::success(""));
+
+        let run = |registry: &str| {
+            let parameters = Parameters::new(HashMap::from([(
+                "registry".to_owned(),
+                registry.to_owned(),
+            )]));
+
+            TaskRunner::new(&runner)
+                .with_parameters(parameters)
+                .with_cache(&store, CacheScope::default(), &fs)
+                .run(&workspace, "push", [app], &Context::new())
+                .unwrap()
+                .results
+                .remove(0)
+                .status
+        };
+
+        let first = run("registry.example.com");
+        let other = run("mirror.example.com");
+
+        let missing = TaskRunner::new(&runner)
+            .with_parameters(Parameters::default())
+            .run(&workspace, "push", [app], &Context::new());
+
+        let commands: Vec<String> = runner
+            .commands()
+            .into_iter()
+            .map(|command| command[2].clone())
+            .collect();
+
+        assert!(first.is_success() && other.is_success());
+        assert!(!matches!(other, TaskStatus::Cached(_)));
+        assert_eq!(
+            commands,
+            [
+                "docker push registry.example.com/app",
+                "docker push mirror.example.com/app"
+            ]
+        );
+        assert_eq!(store.keys().len(), 2);
+        assert!(matches!(
+            missing,
+            Err(TaskError::Interpolate(path, InterpolateError::UnknownParameter(key)))
+                if path == Path::new("/repo/app") && key == "registry"
+        ));
+    }
+
+    #[test]
+    pub fn when_inputs_are_unchanged_should_replay_cached_output() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration
+            .add_project("/repo/core", "core", None)
+            .targets
+            .insert(
+                "test".to_owned(),
+                Target {
+                    command: "cargo test".to_owned(),
+                },
+            );
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_path(&"/repo/core").unwrap();
+
+        let fs = MemoryFileSystem::new().with_file("/repo/core/src/lib.rs", "fn core() {}");
+        let changed = fs
+            .clone()
+            .with_file("/repo/core/src/lib.rs", "fn changed() {}");
+        let store = MemoryCacheStore::new();
+        let runner = ScriptedProcessRunner::new().with_output("sh", ProcessOutput::success("ok"));
+
+        let run = |fs: &MemoryFileSystem| {
+            TaskRunner::new(&runner)
+                .with_cache(&store, CacheScope::default(), fs)
+                .run(&workspace, "test", [core], &Context::new())
+                .unwrap()
+                .results
+                .remove(0)
+                .status
+        };
+
+        let first = run(&fs);
+        let second = run(&fs);
+        let third = run(&changed);
+
+        assert_eq!(first, TaskStatus::Succeeded(ProcessOutput::success("ok")));
+        assert_eq!(second, TaskStatus::Cached(ProcessOutput::success("ok")));
+        assert_eq!(third, TaskStatus::Succeeded(ProcessOutput::success("ok")));
+        assert_eq!(runner.commands().len(), 2);
+        assert_eq!(store.keys().len(), 2);
+    }
+
+    #[test]
+    pub fn when_cached_entry_cannot_be_decoded_should_run_the_task_again() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration
+            .add_project("/repo/core", "core", None)
+            .targets
+            .insert(
+                "test".to_owned(),
+                Target {
+                    command: "cargo test".to_owned(),
+                },
+            );
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_path(&"/repo/core").unwrap();
+
+        let fs = MemoryFileSystem::new();
+        let store = MemoryCacheStore::new();
+        let runner = ScriptedProcessRunner::new().with_output("sh", ProcessOutput::success("ok"));
+        let tasks = TaskRunner::new(&runner).with_cache(&store, CacheScope::default(), &fs);
+
+        tasks
+            .run(&workspace, "test", [core], &Context::new())
+            .unwrap();
+
+        // As written by a version with another entry format.
+        for key in store.keys() {
+            store.put(&key, br#"{"format": 2}"#).unwrap();
+        }
+
+        let status = tasks
+            .run(&workspace, "test", [core], &Context::new())
+            .unwrap()
+            .results
+            .remove(0)
+            .status;
+
+        assert_eq!(status, TaskStatus::Succeeded(ProcessOutput::success("ok")));
+        assert_eq!(runner.commands().len(), 2);
+        assert!(matches!(
+            tasks.undecodable().as_slice(),
+            [CacheError::Corrupt(key, _)] if store.keys() == [key.clone()]
+        ));
+    }
+
+    /// Prints the number of the run in `/repo/docs`, like a build embedding a timestamp, and to
+    /// stderr in `/repo/lint`, like a tool warning with one.
+    #[derive(Default)]
+    struct StampingRunner {
+        runs: AtomicUsize,
+    }
+
+    impl ProcessRunner for StampingRunner {
+        fn run(&self, command: &mut Command) -> std::io::Result<ProcessOutput> {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst);
+
+            if command.get_current_dir() == Some(Path::new("/repo/docs")) {
+                Ok(ProcessOutput::success(format!("built at {run}")))
+            } else if command.get_current_dir() == Some(Path::new("/repo/lint")) {
+                Ok(ProcessOutput {
+                    stderr: format!("warning: checked at {run}").into_bytes(),
+                    ..ProcessOutput::success("ok")
+                })
+            } else {
+                Ok(ProcessOutput::success("ok"))
+            }
+        }
+    }
+
+    #[test]
+    pub fn when_verifying_cached_runs_should_return_the_ones_not_reproduced() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        for path in ["/repo/core", "/repo/docs", "/repo/lint", "/repo/ui"] {
+            declaration
+                .add_project(path, path.trim_start_matches("/repo/"), None)
+                .targets
+                .insert(
+                    "build".to_owned(),
+                    Target {
+                        command: format!("make {path}"),
+                    },
+                );
+        }
+
+        let workspace = declaration.build_workspace().unwrap();
+        let id = |path: &str| workspace.get_id_by_path(&path).unwrap();
+
+        let fs = MemoryFileSystem::new();
+        let store = MemoryCacheStore::new();
+        let runner = StampingRunner::default();
+        let tasks = TaskRunner::new(&runner).with_cache(&store, CacheScope::default(), &fs);
+
+        tasks
+            .run(
+                &workspace,
+                "build",
+                [id("/repo/core"), id("/repo/docs"), id("/repo/lint")],
+                &Context::new(),
+            )
+            .unwrap();
+
+        let mismatches = tasks
+            .verify_cached(
+                &workspace,
+                "build",
+                workspace.iter_with_ids().map(|(id, _)| id),
+                &Context::new(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            mismatches
+                .iter()
+                .map(|mismatch| mismatch.project)
+                .collect::<Vec<_>>(),
+            [id("/repo/docs"), id("/repo/lint")]
+        );
+        // Only the cached runs run again, so `ui` is never built.
+        assert_eq!(runner.runs.load(Ordering::SeqCst), 6);
+        assert_eq!(store.keys().len(), 3);
+    }
+
+    #[test]
+    pub fn when_running_with_path_roots_should_remap_outputs() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration
+            .add_project("/workspace/core", "core", None)
+            .targets
+            .insert(
+                "test".to_owned(),
+                Target {
+                    command: "cargo test".to_owned(),
+                },
+            );
+
+        let workspace = declaration.build_workspace().unwrap();
+        let core = workspace.get_id_by_path(&"/workspace/core").unwrap();
+
+        let runner = ScriptedProcessRunner::new().with_output(
+            "sh",
+            ProcessOutput::success("error in /workspace/core/src/lib.rs:3"),
+        );
+
+        let mut roots = PathRoots::new();
+        roots.add("/workspace", "/home/runner/repo");
+
+        let status = TaskRunner::new(&runner)
+            .with_path_roots(roots)
+            .run(&workspace, "test", [core], &Context::new())
+            .unwrap()
+            .results
+            .remove(0)
+            .status;
+
+        assert_eq!(
+            status,
+            TaskStatus::Succeeded(ProcessOutput::success(
+                "error in /home/runner/repo/core/src/lib.rs:3"
+            ))
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Tracing records which files a command actually accessed, so the files read from other
+//! projects can be compared against the declared dependencies.
+//!
+//! The tracer runs the command under `strace`, which must be installed. It is only available on
+//! Linux.
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use crate::errors::TraceError;
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+/// The end of the first half of a syscall interrupted by another process.
+const UNFINISHED: &str = " <unfinished ...>";
+
+/// The marker before the rest of an interrupted syscall, e.g. `<... openat resumed>`.
+const RESUMED: &str = " resumed>";
+
+/// The result of running a command under the tracer.
+#[derive(Debug)]
+pub struct TraceOutput {
+    /// The exit status of the traced command.
+    pub status: ExitStatus,
+    /// Every path the command successfully accessed, made absolute.
+    pub accessed_paths: BTreeSet<PathBuf>,
+}
+
+/// A project whose files were accessed without being declared as a dependency.
+#[derive(Debug, PartialEq)]
+pub struct UndeclaredDependency {
+    /// The project that owns the accessed files.
+    pub project: ProjectId,
+    /// The accessed files owned by that project.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Runs the command under `strace` and collects the paths it accessed.
+///
+/// The command's program, arguments, working directory, and environment are preserved. Its
+/// standard streams are inherited.
+pub fn trace_command(command: &Command) -> Result<TraceOutput, TraceError> {
+    let log_path = std::env::temp_dir().join(format!(
+        "parmenides-trace-{}-{}.log",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default()
+    ));
+
+    let mut strace = Command::new("strace");
+    strace
+        .args(["-f", "-qq", "-e", "trace=%file", "-o"])
+        .arg(&log_path)
+        .arg("--")
+        .arg(command.get_program())
+        .args(command.get_args());
+
+    if let Some(dir) = command.get_current_dir() {
+        strace.current_dir(dir);
+    }
+
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => strace.env(key, value),
+            None => strace.env_remove(key),
+        };
+    }
+
+    let status = strace.status().map_err(TraceError::TracerNotAvailable)?;
+
+    let log = std::fs::read_to_string(&log_path)
+        .map_err(|err| TraceError::ReadLog(log_path.clone(), err))?;
+    let _ = std::fs::remove_file(&log_path);
+
+    let cwd = match command.get_current_dir() {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir().unwrap_or_default(),
+    };
+
+    Ok(TraceOutput {
+        status,
+        accessed_paths: parse_strace_log(&log, &cwd),
+    })
+}
+
+/// Parses an `strace` log, returning the paths of every successful file syscall.
+///
+/// Relative paths are resolved against `cwd`. A syscall interrupted by another process is
+/// logged as an `<unfinished ...>` line and a later `resumed>` line of the same PID, which are
+/// joined before the result is checked.
+pub fn parse_strace_log(log: &str, cwd: &Path) -> BTreeSet<PathBuf> {
+    let mut unfinished: HashMap<&str, &str> = HashMap::new();
+    let mut calls: Vec<Cow<'_, str>> = Vec::new();
+
+    for line in log.lines() {
+        let pid = line.split_whitespace().next().unwrap_or_default();
+
+        if let Some(head) = line.strip_suffix(UNFINISHED) {
+            unfinished.insert(pid, head);
+            continue;
+        }
+
+        match line.find(RESUMED).zip(unfinished.remove(pid)) {
+            Some((index, head)) => calls.push(Cow::Owned(format!(
+                "{head}{}",
+                &line[index + RESUMED.len()..]
+            ))),
+            None => calls.push(Cow::Borrowed(line)),
+        }
+    }
+
+    calls
+        .iter()
+        .filter(|call| !call.contains("= -1 "))
+        .filter_map(|call| first_quoted_argument(call))
+        .map(|path| cwd.join(path))
+        .collect()
+}
+
+fn first_quoted_argument(line: &str) -> Option<&str> {
+    let start = line.find("(\"").map(|index| index + 2).or_else(|| {
+        // Syscalls such as `openat(AT_FDCWD, "...")` take the path as the second argument.
+        line.find(", \"").map(|index| index + 3)
+    })?;
+
+    let length = line[start..].find('"')?;
+
+    Some(&line[start..start + length])
+}
+
+/// Compares the paths accessed by a project's task against its declared dependencies.
+///
+/// A path counts as declared if it belongs to the project itself or to any of its transitive
+/// dependencies. Paths outside the workspace are ignored.
+///
+/// # Returns
+/// The undeclared dependencies, sorted by project ID. Empty if `project` is not in the
+/// workspace.
+pub fn find_undeclared_dependencies<'a, I>(
+    workspace: &Workspace,
+    project: ProjectId,
+    accessed_paths: I,
+) -> Vec<UndeclaredDependency>
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    let Some(declared) = declared_closure(workspace, project) else {
+        return vec![];
+    };
+
+    let mut undeclared: Vec<UndeclaredDependency> = Vec::new();
+
+    for path in accessed_paths {
+        let Some(owner) = workspace.resolve_owner(path) else {
+            continue;
+        };
+
+        if declared.contains(&owner) {
+            continue;
+        }
+
+        match undeclared.iter_mut().find(|entry| entry.project == owner) {
+            Some(entry) => entry.paths.push(path.clone()),
+            None => undeclared.push(UndeclaredDependency {
+                project: owner,
+                paths: vec![path.clone()],
+            }),
+        }
+    }
+
+    undeclared.sort_by_key(|entry| entry.project);
+
+    undeclared
+}
+
+/// Infers the direct dependencies of a project from the paths accessed by its task, for
+/// [`crate::drift::DependencyDrift::detect`].
+///
+/// A declared dependency is inferred if the files of it or of its own dependencies were
+/// accessed, so a dependency only reached through another one isn't reported as missing. The
+/// projects from [`find_undeclared_dependencies`] are inferred too. Paths of the project itself
+/// and outside the workspace are ignored.
+pub fn infer_dependencies<'a, I>(
+    workspace: &Workspace,
+    project: ProjectId,
+    accessed_paths: I,
+) -> BTreeSet<ProjectId>
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    let accessed_paths: Vec<&PathBuf> = accessed_paths.into_iter().collect();
+    let owners: HashSet<ProjectId> = accessed_paths
+        .iter()
+        .filter_map(|path| workspace.resolve_owner(*path))
+        .collect();
+
+    let declared = workspace
+        .get_project(project)
+        .and_then(|project| project.dependencies.as_ref())
+        .into_iter()
+        .flatten()
+        .copied()
+        .filter(|dependency| {
+            declared_closure(workspace, *dependency)
+                .is_some_and(|closure| !closure.is_disjoint(&owners))
+        });
+
+    let undeclared = find_undeclared_dependencies(workspace, project, accessed_paths)
+        .into_iter()
+        .map(|entry| entry.project);
+
+    declared.chain(undeclared).collect()
+}
+
+fn declared_closure(workspace: &Workspace, project: ProjectId) -> Option<HashSet<ProjectId>> {
+    workspace.get_project(project)?;
+
+    let mut declared = HashSet::new();
+    let mut stack = vec![project];
+
+    while let Some(current_id) = stack.pop() {
+        if declared.insert(current_id) {
+            if let Some(dependencies) = workspace
+                .get_project(current_id)
+                .and_then(|project| project.dependencies.as_ref())
+            {
+                stack.extend(dependencies);
+            }
+        }
+    }
+
+    Some(declared)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::declarations::WorkspaceDeclaration;
+
+    use super::{
+        find_undeclared_dependencies, infer_dependencies, parse_strace_log, UndeclaredDependency,
+    };
+
+    #[test]
+    pub fn when_parsing_strace_log_should_keep_successful_accesses() {
+        let log = r#"1201 openat(AT_FDCWD, "/repo/libs/core/src/lib.rs", O_RDONLY|O_CLOEXEC) = 3
+1201 openat(AT_FDCWD, "/repo/missing.rs", O_RDONLY) = -1 ENOENT (No such file or directory)
+1202 stat("Cargo.toml", {st_mode=S_IFREG|0644, st_size=120, ...}) = 0
+1202 +++ exited with 0 +++"#;
+
+        let paths = parse_strace_log(log, Path::new("/repo/apps/web"));
+
+        assert_eq!(
+            paths.into_iter().collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("/repo/apps/web/Cargo.toml"),
+                PathBuf::from("/repo/libs/core/src/lib.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn when_syscall_is_split_should_join_it_with_its_result() {
+        let log = r#"1201 openat(AT_FDCWD, "/repo/missing.rs", O_RDONLY <unfinished ...>
+1202 openat(AT_FDCWD, "/repo/libs/core/src/lib.rs", O_RDONLY <unfinished ...>
+1201 <... openat resumed>) = -1 ENOENT (No such file or directory)
+1202 <... openat resumed>) = 4"#;
+
+        let paths = parse_strace_log(log, Path::new("/repo"));
+
+        assert_eq!(
+            paths.into_iter().collect::<Vec<_>>(),
+            vec![PathBuf::from("/repo/libs/core/src/lib.rs")]
+        );
+    }
+
+    #[test]
+    pub fn when_reading_undeclared_project_should_report_it() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration.add_project(
+            "/repo/libs/utils",
+            "utils",
+            Some(vec!["/repo/libs/core".into()]),
+        );
+        declaration.add_project("/repo/libs/other", "other", None);
+        declaration.add_project(
+            "/repo/apps/web",
+            "web",
+            Some(vec!["/repo/libs/utils".into()]),
+        );
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let web = workspace.get_id_by_path(&"/repo/apps/web").unwrap();
+        let other = workspace.get_id_by_path(&"/repo/libs/other").unwrap();
+
+        let accessed = [
+            PathBuf::from("/repo/apps/web/src/main.rs"),
+            PathBuf::from("/repo/libs/core/src/lib.rs"),
+            PathBuf::from("/repo/libs/other/src/lib.rs"),
+            PathBuf::from("/usr/lib/libc.so.6"),
+        ];
+
+        let undeclared = find_undeclared_dependencies(&workspace, web, &accessed);
+
+        assert_eq!(
+            undeclared,
+            vec![UndeclaredDependency {
+                project: other,
+                paths: vec![PathBuf::from("/repo/libs/other/src/lib.rs")],
+            }]
+        );
+    }
+
+    #[test]
+    pub fn when_inferring_dependencies_should_keep_read_declared_and_undeclared_projects() {
+        let mut declaration = WorkspaceDeclaration::new();
+
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration.add_project(
+            "/repo/libs/utils",
+            "utils",
+            Some(vec!["/repo/libs/core".into()]),
+        );
+        declaration.add_project("/repo/libs/unused", "unused", None);
+        declaration.add_project("/repo/libs/other", "other", None);
+        declaration.add_project(
+            "/repo/apps/web",
+            "web",
+            Some(vec!["/repo/libs/utils".into(), "/repo/libs/unused".into()]),
+        );
+
+        let workspace = declaration.build_workspace().unwrap();
+
+        let web = workspace.get_id_by_path(&"/repo/apps/web").unwrap();
+        let utils = workspace.get_id_by_path(&"/repo/libs/utils").unwrap();
+        let other = workspace.get_id_by_path(&"/repo/libs/other").unwrap();
+
+        // Only the files of core are read through utils, which still counts as using utils.
+        let accessed = [
+            PathBuf::from("/repo/apps/web/src/main.rs"),
+            PathBuf::from("/repo/libs/core/src/lib.rs"),
+            PathBuf::from("/repo/libs/other/src/lib.rs"),
+        ];
+
+        let inferred = infer_dependencies(&workspace, web, &accessed);
+
+        assert_eq!(inferred.into_iter().collect::<Vec<_>>(), vec![utils, other]);
+    }
+}
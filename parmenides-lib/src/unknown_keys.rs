@@ -0,0 +1,464 @@
+//! # Unknown keys
+//!
+//! Serde ignores the keys a declaration doesn't know, so a typo such as `dependecies` silently
+//! drops the setting. Declarations are deserialized with [`serde_ignored`] before they are
+//! parsed, and every key serde ignores is reported with its location and, when a field of the
+//! same type is close enough, a "did you mean" suggestion.
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_reflection::{ContainerFormat, Format, Registry, Tracer, TracerConfig};
+
+use crate::declarations::WorkspaceDeclaration;
+use crate::errors::SourceLocation;
+
+/// What to do with the unknown keys of a declaration.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownKeyPolicy {
+    /// Ignore them.
+    Allow,
+    /// Report them, but load the declaration anyway.
+    #[default]
+    Warn,
+    /// Refuse to load the declaration.
+    Deny,
+}
+
+/// A key that isn't part of the declaration schema.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct UnknownKey {
+    /// The dotted path of the key, e.g. `projects.web.dependecies`.
+    pub path: String,
+    /// The closest known key at the same level, if any is close enough.
+    pub suggestion: Option<String>,
+    /// Where the key is in the declaration, when it could be found.
+    pub location: Option<SourceLocation>,
+}
+
+impl Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown key {}", self.path)?;
+
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean {suggestion}?")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A format-independent tree of the keys of a document.
+enum Node {
+    Map(Vec<Entry>),
+    List(Vec<Node>),
+    Leaf,
+}
+
+struct Entry {
+    key: String,
+    /// The byte offset of the key in the source, when known.
+    offset: Option<usize>,
+    value: Node,
+}
+
+/// A step of the path to a key.
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Returns the unknown keys of a TOML declaration. Invalid TOML has no unknown keys, as
+/// parsing reports it anyway.
+pub fn find_unknown_toml_keys(source: &str) -> Vec<UnknownKey> {
+    let (Ok(document), Ok(deserializer)) = (
+        toml_edit::Document::parse(source),
+        toml::Deserializer::parse(source),
+    ) else {
+        return vec![];
+    };
+
+    check(source, deserializer, &toml_table(document.as_table()))
+}
+
+/// Returns the unknown keys of a YAML declaration, like [`find_unknown_toml_keys`].
+///
+/// YAML values don't carry their span, so a key is located at the first line defining it.
+pub fn find_unknown_yaml_keys(source: &str) -> Vec<UnknownKey> {
+    let Ok(value) = serde_yaml::from_str::<serde_yaml::Value>(source) else {
+        return vec![];
+    };
+
+    check(
+        source,
+        serde_yaml::Deserializer::from_str(source),
+        &yaml_value(source, &value),
+    )
+}
+
+/// Deserializes the declaration, collecting the keys serde ignores in the order of the source.
+///
+/// The keys ignored before a deserialization error are reported too, as a misspelled required
+/// field is both unknown and missing.
+fn check<'de, D>(source: &str, deserializer: D, root: &Node) -> Vec<UnknownKey>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut ignored = Vec::new();
+
+    let _: Result<WorkspaceDeclaration, _> =
+        serde_ignored::deserialize(deserializer, |path| ignored.push(segments(&path)));
+
+    let schema = schema();
+
+    let mut unknown: Vec<_> = ignored
+        .into_iter()
+        .filter_map(|path| {
+            let (Segment::Key(key), parent) = path.split_last()? else {
+                return None;
+            };
+
+            let suggestion = schema
+                .as_ref()
+                .and_then(|(root, registry)| suggest(key, fields(registry, root, parent)));
+            let offset = offset(root, &path);
+
+            Some((
+                offset,
+                UnknownKey {
+                    path: display(&path),
+                    suggestion,
+                    location: offset.map(|offset| SourceLocation::from_offset(source, offset)),
+                },
+            ))
+        })
+        .collect();
+
+    unknown.sort_by_key(|(offset, _)| offset.unwrap_or(usize::MAX));
+
+    unknown.into_iter().map(|(_, key)| key).collect()
+}
+
+/// Returns the steps of a path reported by serde_ignored, skipping the ones that don't
+/// appear in the document, such as options.
+fn segments(path: &serde_ignored::Path) -> Vec<Segment> {
+    use serde_ignored::Path;
+
+    let mut segments = match path {
+        Path::Root => return vec![],
+        Path::Seq { parent, .. }
+        | Path::Map { parent, .. }
+        | Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => segments(parent),
+    };
+
+    match path {
+        Path::Seq { index, .. } => segments.push(Segment::Index(*index)),
+        Path::Map { key, .. } => segments.push(Segment::Key(key.clone())),
+        _ => {}
+    }
+
+    segments
+}
+
+/// Returns the dotted form of a path, e.g. `projects."apps/web".tags` or `path_roots[0].to`.
+fn display(path: &[Segment]) -> String {
+    let mut displayed = String::new();
+
+    for segment in path {
+        match segment {
+            Segment::Index(index) => displayed.push_str(&format!("[{index}]")),
+            Segment::Key(key) => {
+                if !displayed.is_empty() {
+                    displayed.push('.');
+                }
+
+                if !displayed.is_empty() && key.contains(['.', '/', ' ']) {
+                    displayed.push_str(&format!("\"{key}\""));
+                } else {
+                    displayed.push_str(key);
+                }
+            }
+        }
+    }
+
+    displayed
+}
+
+/// Returns the offset of the key at `path` in the source, when known.
+fn offset(root: &Node, path: &[Segment]) -> Option<usize> {
+    let mut node = root;
+    let mut offset = None;
+
+    for segment in path {
+        (node, offset) = match (node, segment) {
+            (Node::Map(entries), Segment::Key(key)) => {
+                let entry = entries.iter().find(|entry| entry.key == *key)?;
+                (&entry.value, entry.offset)
+            }
+            (Node::List(items), Segment::Index(index)) => (items.get(*index)?, offset),
+            _ => return None,
+        };
+    }
+
+    offset
+}
+
+/// Traces the formats of the declaration types, returning the format of the declaration and
+/// the registry of the types it refers to.
+///
+/// The registry is taken unchecked, as nested enums only have their first variant traced, which
+/// doesn't matter for the fields of structs.
+fn schema() -> Option<(Format, Registry)> {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    let (root, _) = tracer.trace_simple_type::<WorkspaceDeclaration>().ok()?;
+
+    Some((root, tracer.registry_unchecked()))
+}
+
+/// Returns the fields of the struct at `path`, or none if it isn't a struct.
+fn fields<'a>(registry: &'a Registry, root: &'a Format, path: &[Segment]) -> Vec<&'a str> {
+    let mut format = root;
+
+    for segment in path {
+        format = match (resolve(registry, format), segment) {
+            (Some(ContainerFormat::Struct(fields)), Segment::Key(key)) => {
+                match fields.iter().find(|field| field.name == *key) {
+                    Some(field) => &field.value,
+                    None => return vec![],
+                }
+            }
+            (None, _) => match (unwrap(format), segment) {
+                (Format::Map { value, .. }, Segment::Key(_)) => value,
+                (Format::Seq(item), Segment::Index(_)) => item,
+                _ => return vec![],
+            },
+            _ => return vec![],
+        };
+    }
+
+    match resolve(registry, format) {
+        Some(ContainerFormat::Struct(fields)) => {
+            fields.iter().map(|field| field.name.as_str()).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// Returns the format inside options.
+fn unwrap(format: &Format) -> &Format {
+    match format {
+        Format::Option(inner) => unwrap(inner),
+        format => format,
+    }
+}
+
+/// Returns the container a format names, looking through options and newtypes.
+fn resolve<'a>(registry: &'a Registry, format: &'a Format) -> Option<&'a ContainerFormat> {
+    let Format::TypeName(name) = unwrap(format) else {
+        return None;
+    };
+
+    match registry.get(name)? {
+        ContainerFormat::NewTypeStruct(inner) => resolve(registry, inner),
+        container => Some(container),
+    }
+}
+
+/// Returns the candidate closest to `key`, if it is within a third of its length.
+fn suggest<'a, I>(key: &str, candidates: I) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let max_distance = (key.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+fn toml_table(table: &toml_edit::Table) -> Node {
+    Node::Map(
+        table
+            .iter()
+            .map(|(key, item)| Entry {
+                key: key.to_owned(),
+                offset: table
+                    .key(key)
+                    .and_then(|key| key.span())
+                    .map(|span| span.start),
+                value: toml_item(item),
+            })
+            .collect(),
+    )
+}
+
+fn toml_item(item: &toml_edit::Item) -> Node {
+    match item {
+        toml_edit::Item::Table(table) => toml_table(table),
+        toml_edit::Item::ArrayOfTables(tables) => {
+            Node::List(tables.iter().map(toml_table).collect())
+        }
+        toml_edit::Item::Value(value) => toml_value(value),
+        toml_edit::Item::None => Node::Leaf,
+    }
+}
+
+fn toml_value(value: &toml_edit::Value) -> Node {
+    match value {
+        toml_edit::Value::InlineTable(table) => Node::Map(
+            table
+                .iter()
+                .map(|(key, value)| Entry {
+                    key: key.to_owned(),
+                    offset: table
+                        .key(key)
+                        .and_then(|key| key.span())
+                        .map(|span| span.start),
+                    value: toml_value(value),
+                })
+                .collect(),
+        ),
+        toml_edit::Value::Array(array) => Node::List(array.iter().map(toml_value).collect()),
+        _ => Node::Leaf,
+    }
+}
+
+fn yaml_value(source: &str, value: &serde_yaml::Value) -> Node {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => Node::Map(
+            mapping
+                .iter()
+                .filter_map(|(key, value)| {
+                    let key = key.as_str()?.to_owned();
+
+                    Some(Entry {
+                        offset: yaml_key_offset(source, &key),
+                        value: yaml_value(source, value),
+                        key,
+                    })
+                })
+                .collect(),
+        ),
+        serde_yaml::Value::Sequence(items) => {
+            Node::List(items.iter().map(|item| yaml_value(source, item)).collect())
+        }
+        serde_yaml::Value::Tagged(tagged) => yaml_value(source, &tagged.value),
+        _ => Node::Leaf,
+    }
+}
+
+/// Finds the first line defining `key`, plain or quoted, possibly as the first key of a list
+/// item.
+fn yaml_key_offset(source: &str, key: &str) -> Option<usize> {
+    let mut offset = 0;
+
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let trimmed = trimmed.strip_prefix("- ").unwrap_or(trimmed).trim_start();
+        let indentation = line.len() - trimmed.len();
+
+        let defines = [key.to_owned(), format!("\"{key}\""), format!("'{key}'")]
+            .iter()
+            .any(|candidate| {
+                trimmed
+                    .strip_prefix(candidate.as_str())
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+            });
+
+        if defines {
+            return Some(offset + indentation);
+        }
+
+        offset += line.len();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, find_unknown_toml_keys, find_unknown_yaml_keys};
+
+    #[test]
+    pub fn when_toml_has_typo_should_suggest_known_key_with_location() {
+        let unknown = find_unknown_toml_keys(
+            "[projects.\"apps/web\"]\nname = \"web\"\ndependecies = [\"libs/core\"]\n\n[lint]\nmax_depth = 2\n[watch]\nflavor = \"x\"\n",
+        );
+
+        assert_eq!(unknown.len(), 3);
+        assert_eq!(unknown[0].path, "projects.\"apps/web\".dependecies");
+        assert_eq!(unknown[0].suggestion.as_deref(), Some("dependencies"));
+        assert_eq!(
+            unknown[0].location.as_ref().map(|location| location.line),
+            Some(3)
+        );
+        assert_eq!(unknown[1].path, "lint.max_depth");
+        assert_eq!(unknown[2].path, "watch.flavor");
+        assert_eq!(unknown[2].suggestion, None);
+    }
+
+    #[test]
+    pub fn when_yaml_has_typo_should_report_it() {
+        let unknown = find_unknown_yaml_keys(
+            "projects:\n  core:\n    name: core\n    tag: [lib]\npath_roots:\n  - from: /a\n    too: /b\n",
+        );
+
+        assert_eq!(unknown.len(), 2);
+        assert_eq!(unknown[0].path, "projects.core.tag");
+        assert_eq!(unknown[0].suggestion.as_deref(), Some("tags"));
+        assert_eq!(
+            unknown[0].location.as_ref().map(|location| location.line),
+            Some(4)
+        );
+        assert_eq!(unknown[1].path, "path_roots[0].too");
+        assert_eq!(unknown[1].suggestion.as_deref(), Some("to"));
+    }
+
+    #[test]
+    pub fn when_typo_is_in_nested_table_should_suggest_field_of_its_type() {
+        let unknown = find_unknown_toml_keys(
+            "[projects.core]\nname = \"core\"\n\n[projects.core.deprecated]\nsunsett = \"2027-01-31\"\n\n[generators.lib]\ntemplate = \"templates/lib\"\ntagz = [\"lib\"]\n",
+        );
+
+        assert_eq!(unknown.len(), 2);
+        assert_eq!(unknown[0].path, "projects.core.deprecated.sunsett");
+        assert_eq!(unknown[0].suggestion.as_deref(), Some("sunset"));
+        assert_eq!(
+            unknown[0].location.as_ref().map(|location| location.line),
+            Some(5)
+        );
+        assert_eq!(unknown[1].path, "generators.lib.tagz");
+        assert_eq!(unknown[1].suggestion.as_deref(), Some("tags"));
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
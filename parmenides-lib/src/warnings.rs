@@ -0,0 +1,178 @@
+//! # Warnings
+//!
+//! Discovery and input hashing walk the whole workspace, where a single path that can't be
+//! read, e.g. a directory without read permission or a broken symlink, shouldn't abort the
+//! analysis. Such errors are collected as [`PathWarning`]s on the result instead, and the
+//! path is skipped. With [`IoPolicy::Strict`], set with
+//! [`crate::context::Context::with_io_policy`], they fail like any other error.
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// How errors reading a single path are handled.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum IoPolicy {
+    /// Skip the path and record a [`PathWarning`].
+    #[default]
+    Warn,
+    /// Fail with the error.
+    Strict,
+}
+
+/// Why a path could not be read.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum PathProblem {
+    PermissionDenied,
+    /// The path is a symlink whose target doesn't exist.
+    BrokenSymlink,
+    /// The path, or one of its components, is longer than the platform allows.
+    TooLong,
+    Other,
+}
+
+impl Display for PathProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::PermissionDenied => "permission denied",
+            Self::BrokenSymlink => "broken symlink",
+            Self::TooLong => "path too long",
+            Self::Other => "unreadable",
+        };
+
+        f.write_str(description)
+    }
+}
+
+/// A path that was skipped, as it could not be read.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PathWarning {
+    pub path: PathBuf,
+    pub problem: PathProblem,
+    /// The message of the underlying error.
+    pub message: String,
+}
+
+impl PathWarning {
+    /// Describes the error reading `path`.
+    pub fn new(path: &Path, err: &std::io::Error) -> Self {
+        let problem = match err.kind() {
+            ErrorKind::PermissionDenied => PathProblem::PermissionDenied,
+            ErrorKind::InvalidFilename => PathProblem::TooLong,
+            // Reading through a dangling symlink fails as if nothing was there.
+            ErrorKind::NotFound
+                if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.is_symlink()) =>
+            {
+                PathProblem::BrokenSymlink
+            }
+            _ => PathProblem::Other,
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            problem,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl Display for PathWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The message of an unclassified error tells more than the problem.
+        match self.problem {
+            PathProblem::Other => write!(f, "Skipped {}: {}", self.path.display(), self.message),
+            problem => write!(f, "Skipped {}: {problem}", self.path.display()),
+        }
+    }
+}
+
+/// Collects the [`PathWarning`]s of an operation, following its [`IoPolicy`].
+#[derive(Debug, Default)]
+pub struct PathWarnings {
+    policy: IoPolicy,
+    warnings: Vec<PathWarning>,
+}
+
+impl PathWarnings {
+    pub fn new(policy: IoPolicy) -> Self {
+        Self {
+            policy,
+            warnings: vec![],
+        }
+    }
+
+    /// Records the error reading `path`, so the caller skips the path.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the path should be skipped.
+    /// - `Err(std::io::Error)`: The error itself, with [`IoPolicy::Strict`].
+    pub fn recover(&mut self, path: &Path, err: std::io::Error) -> Result<(), std::io::Error> {
+        if self.policy == IoPolicy::Strict {
+            return Err(err);
+        }
+
+        self.warnings.push(PathWarning::new(path, &err));
+
+        Ok(())
+    }
+
+    pub fn into_vec(self) -> Vec<PathWarning> {
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error, ErrorKind};
+    use std::path::Path;
+
+    use super::{IoPolicy, PathProblem, PathWarnings};
+
+    #[test]
+    pub fn when_reading_a_path_fails_should_warn_unless_strict() {
+        let mut warnings = PathWarnings::new(IoPolicy::Warn);
+
+        let denied = warnings.recover(
+            Path::new("/repo/secrets"),
+            Error::from(ErrorKind::PermissionDenied),
+        );
+        let long = warnings.recover(
+            Path::new("/repo/long"),
+            Error::from(ErrorKind::InvalidFilename),
+        );
+
+        let strict = PathWarnings::new(IoPolicy::Strict).recover(
+            Path::new("/repo/secrets"),
+            Error::from(ErrorKind::PermissionDenied),
+        );
+
+        assert!(denied.is_ok() && long.is_ok());
+        assert!(strict.is_err());
+
+        let warnings = warnings.into_vec();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].problem, PathProblem::PermissionDenied);
+        assert_eq!(warnings[1].problem, PathProblem::TooLong);
+        assert_eq!(
+            warnings[0].to_string(),
+            "Skipped /repo/secrets: permission denied"
+        );
+
+        #[cfg(unix)]
+        {
+            let root =
+                std::env::temp_dir().join(format!("parmenides-warnings-{}", std::process::id()));
+            std::fs::create_dir_all(&root).unwrap();
+            let link = root.join("dangling");
+            std::os::unix::fs::symlink(root.join("missing"), &link).unwrap();
+
+            let mut warnings = PathWarnings::new(IoPolicy::Warn);
+            let read = std::fs::read(&link).unwrap_err();
+            warnings.recover(&link, read).unwrap();
+
+            std::fs::remove_dir_all(&root).unwrap();
+
+            assert_eq!(warnings.into_vec()[0].problem, PathProblem::BrokenSymlink);
+        }
+    }
+}
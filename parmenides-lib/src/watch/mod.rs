@@ -0,0 +1,282 @@
+//! # Watch
+//!
+//! Watchers report the files changing in the workspace as they change, so the affected
+//! projects can be kept up to date incrementally with [`crate::mark_changed_paths`].
+//!
+//! Three backends are available: [`PollingWatcher`], which periodically scans the workspace and
+//! works everywhere, `NotifyWatcher` (feature `notify`), which uses the notifications of the
+//! operating system, and [`WatchmanWatcher`], which subscribes to
+//! [Watchman](https://facebook.github.io/watchman/) and scales to very large repositories.
+//!
+//! [`watch_affected`] ties a watcher to a workspace, reporting the projects affected by each
+//! batch of changes, e.g. to rerun their tests.
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::affected::AffectedState;
+use crate::context::Context;
+use crate::declarations::WatchDeclaration;
+use crate::errors::WatchError;
+use crate::process::{ProcessRunner, SystemProcessRunner};
+use crate::project::ProjectId;
+use crate::workspace::Workspace;
+
+#[cfg(feature = "notify")]
+mod native;
+mod polling;
+mod watchman;
+
+#[cfg(feature = "notify")]
+pub use native::NotifyWatcher;
+pub use polling::PollingWatcher;
+pub use watchman::WatchmanWatcher;
+
+/// Directories whose changes are never reported, as they hold tool state rather than sources,
+/// e.g. the cache written while rerunning tasks.
+pub(crate) const IGNORED_DIRECTORIES: [&str; 4] = [".git", ".parmenides", "node_modules", "target"];
+
+/// Returns `true` if the path, relative to the watched root, is in an ignored directory.
+pub(crate) fn is_ignored(relative: &Path) -> bool {
+    relative.components().any(|component| match component {
+        Component::Normal(name) => IGNORED_DIRECTORIES.iter().any(|ignored| name == *ignored),
+        _ => false,
+    })
+}
+
+/// The watcher backend to use.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchBackend {
+    /// Watchman when it is installed, polling otherwise.
+    #[default]
+    Auto,
+    /// Periodically scans the workspace.
+    Polling,
+    /// Subscribes to Watchman, failing if it is not installed.
+    Watchman,
+    /// Uses the notifications of the operating system, failing if built without the `notify`
+    /// feature.
+    Notify,
+}
+
+impl Display for WatchBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            WatchBackend::Auto => "auto",
+            WatchBackend::Polling => "polling",
+            WatchBackend::Watchman => "watchman",
+            WatchBackend::Notify => "notify",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// A source of file change notifications.
+pub trait Watcher {
+    /// Blocks until files change, returning the absolute paths of the changed files.
+    fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError>;
+}
+
+/// Opens a watcher for the workspace at `root`, using the configured backend.
+///
+/// With [`WatchBackend::Auto`], Watchman is used when the `watchman` executable is available,
+/// then notifications when built with the `notify` feature, then polling.
+pub fn open_watcher(
+    root: &Path,
+    declaration: &WatchDeclaration,
+) -> Result<Box<dyn Watcher>, WatchError> {
+    let polling = || -> Result<Box<dyn Watcher>, WatchError> {
+        Ok(Box::new(PollingWatcher::new(
+            root,
+            Duration::from_millis(declaration.poll_interval_ms),
+        )?))
+    };
+
+    match resolve_backend(declaration.backend, &SystemProcessRunner::new()) {
+        WatchBackend::Watchman => Ok(Box::new(WatchmanWatcher::subscribe(root)?)),
+        #[cfg(feature = "notify")]
+        WatchBackend::Notify => Ok(Box::new(NotifyWatcher::new(
+            root,
+            Duration::from_millis(declaration.debounce_ms),
+        )?)),
+        #[cfg(not(feature = "notify"))]
+        WatchBackend::Notify => Err(WatchError::Notify(
+            "this build has no notify support".to_owned(),
+        )),
+        _ => polling(),
+    }
+}
+
+/// Resolves [`WatchBackend::Auto`] to the backend [`open_watcher`] will use, looking for the
+/// `watchman` executable through `runner`.
+pub fn resolve_backend(backend: WatchBackend, runner: &dyn ProcessRunner) -> WatchBackend {
+    match backend {
+        WatchBackend::Auto if watchman::is_available(runner) => WatchBackend::Watchman,
+        WatchBackend::Auto if cfg!(feature = "notify") => WatchBackend::Notify,
+        WatchBackend::Auto => WatchBackend::Polling,
+        backend => backend,
+    }
+}
+
+/// A batch of changes reported by [`watch_affected`].
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+pub struct WatchBatch {
+    /// The changed files, as absolute paths.
+    pub changed: BTreeSet<PathBuf>,
+    /// The projects owning a changed file, and their dependents, in ID order.
+    pub affected: Vec<ProjectId>,
+    /// Every project affected since watching started, in ID order.
+    pub affected_since_start: Vec<ProjectId>,
+}
+
+/// Waits for changes with `watcher` and calls `on_batch` with the projects each batch affects,
+/// until `on_batch` breaks or `context` is cancelled. Batches affecting no project, e.g.
+/// changes outside of every project, are skipped.
+///
+/// # Returns
+/// - `Ok(())`: If `on_batch` broke or the context was cancelled.
+/// - `Err(WatchError)`: If the watcher failed.
+pub fn watch_affected<F>(
+    workspace: &Workspace,
+    watcher: &mut dyn Watcher,
+    context: &Context,
+    mut on_batch: F,
+) -> Result<(), WatchError>
+where
+    F: FnMut(&WatchBatch) -> ControlFlow<()>,
+{
+    let mut batch = AffectedState::new(workspace);
+    let mut since_start = AffectedState::new(workspace);
+
+    while !context.is_cancelled() {
+        let changed = watcher.wait()?;
+
+        if context.is_cancelled() {
+            break;
+        }
+
+        batch.reset();
+        batch.mark_changed_paths(&changed)?;
+        since_start.mark_changed_paths(&changed)?;
+
+        let affected: Vec<ProjectId> = batch.affected_projects().collect();
+
+        if affected.is_empty() {
+            continue;
+        }
+
+        let report = WatchBatch {
+            changed,
+            affected,
+            affected_since_start: since_start.affected_projects().collect(),
+        };
+
+        if on_batch(&report).is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, VecDeque};
+    use std::ops::ControlFlow;
+    use std::path::PathBuf;
+
+    use crate::context::Context;
+    use crate::declarations::WorkspaceDeclaration;
+    use crate::errors::WatchError;
+    use crate::process::{ProcessOutput, ScriptedProcessRunner};
+
+    use super::{resolve_backend, watch_affected, WatchBackend, Watcher};
+
+    struct ScriptedWatcher(VecDeque<BTreeSet<PathBuf>>);
+
+    impl Watcher for ScriptedWatcher {
+        fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError> {
+            self.0
+                .pop_front()
+                .ok_or_else(|| WatchError::Notify("no more changes".to_owned()))
+        }
+    }
+
+    #[test]
+    pub fn when_backend_is_auto_should_prefer_watchman_when_installed() {
+        let installed = ScriptedProcessRunner::new().with_output(
+            "watchman",
+            ProcessOutput::success("{\"version\":\"2024.01.01\"}"),
+        );
+        let missing = ScriptedProcessRunner::new();
+
+        assert_eq!(
+            resolve_backend(WatchBackend::Auto, &installed),
+            WatchBackend::Watchman
+        );
+        assert_eq!(
+            resolve_backend(WatchBackend::Auto, &missing),
+            if cfg!(feature = "notify") {
+                WatchBackend::Notify
+            } else {
+                WatchBackend::Polling
+            }
+        );
+        assert_eq!(
+            resolve_backend(WatchBackend::Watchman, &missing),
+            WatchBackend::Watchman
+        );
+        assert_eq!(installed.commands(), vec![vec!["watchman", "version"]]);
+    }
+
+    #[test]
+    pub fn when_watching_should_report_affected_projects_per_batch() {
+        let mut declaration = WorkspaceDeclaration::new();
+        declaration.add_project("/repo/libs/core", "core", None);
+        declaration.add_project("/repo/libs/other", "other", None);
+        declaration.add_project(
+            "/repo/apps/web",
+            "web",
+            Some(vec!["/repo/libs/core".into()]),
+        );
+
+        let workspace = declaration.build_workspace().unwrap();
+        let id = |identifier| workspace.get_id_by_identifier(identifier).unwrap();
+
+        let mut watcher = ScriptedWatcher(VecDeque::from([
+            BTreeSet::from([PathBuf::from("/repo/libs/core/lib.rs")]),
+            BTreeSet::from([PathBuf::from("/repo/README.md")]),
+            BTreeSet::from([PathBuf::from("/repo/libs/other/lib.rs")]),
+        ]));
+
+        let mut batches = vec![];
+
+        watch_affected(&workspace, &mut watcher, &Context::new(), |batch| {
+            batches.push(batch.clone());
+
+            if batches.len() == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+
+        let mut core_and_web = vec![id("core"), id("web")];
+        core_and_web.sort();
+        let mut all = vec![id("core"), id("other"), id("web")];
+        all.sort();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].affected, core_and_web);
+        assert_eq!(batches[1].affected, vec![id("other")]);
+        assert_eq!(batches[1].affected_since_start, all);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::errors::WatchError;
+
+use super::{is_ignored, Watcher};
+
+/// A [`Watcher`] backed by the file system notifications of the operating system, through the
+/// [`notify`] crate: inotify on Linux, FSEvents on macOS, and `ReadDirectoryChangesW` on
+/// Windows.
+///
+/// Events are debounced: after one arrives, the watcher keeps collecting until none arrived for
+/// the debounce duration, so saving many files at once is reported as a single change.
+pub struct NotifyWatcher {
+    root: PathBuf,
+    debounce: Duration,
+    events: Receiver<notify::Result<Event>>,
+    // Dropping the watcher stops the notifications, so it is kept alive.
+    _watcher: RecommendedWatcher,
+}
+
+impl NotifyWatcher {
+    /// Starts watching `root` recursively.
+    pub fn new(root: &Path, debounce: Duration) -> Result<Self, WatchError> {
+        let (sender, events) = channel();
+
+        let mut watcher = notify::recommended_watcher(sender)
+            .map_err(|err| WatchError::Notify(err.to_string()))?;
+
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|err| WatchError::Notify(err.to_string()))?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            debounce,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    fn collect(
+        &self,
+        event: notify::Result<Event>,
+        changed: &mut BTreeSet<PathBuf>,
+    ) -> Result<(), WatchError> {
+        let event = event.map_err(|err| WatchError::Notify(err.to_string()))?;
+
+        // Reading a file isn't a change.
+        if matches!(event.kind, EventKind::Access(_)) {
+            return Ok(());
+        }
+
+        changed.extend(event.paths.into_iter().filter(|path| {
+            path.strip_prefix(&self.root)
+                .is_ok_and(|relative| !is_ignored(relative))
+        }));
+
+        Ok(())
+    }
+}
+
+impl Watcher for NotifyWatcher {
+    fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError> {
+        let stopped = || WatchError::Notify("the watcher stopped".to_owned());
+
+        loop {
+            let mut changed = BTreeSet::new();
+
+            let first = self.events.recv().map_err(|_| stopped())?;
+            self.collect(first, &mut changed)?;
+
+            loop {
+                match self.events.recv_timeout(self.debounce) {
+                    Ok(event) => self.collect(event, &mut changed)?,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return Err(stopped()),
+                }
+            }
+
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::watch::Watcher;
+
+    use super::NotifyWatcher;
+
+    #[test]
+    pub fn when_files_change_should_report_them_once_debounced() {
+        let root = std::env::temp_dir().join(format!("parmenides-notify-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("libs/core")).unwrap();
+        std::fs::create_dir_all(root.join(".parmenides")).unwrap();
+        // Canonicalized, as notifications report resolved paths, e.g. on macOS.
+        let root = root.canonicalize().unwrap();
+
+        let mut watcher = NotifyWatcher::new(&root, Duration::from_millis(200)).unwrap();
+
+        std::fs::write(root.join(".parmenides/stats.jsonl"), "").unwrap();
+        std::fs::write(root.join("libs/core/a.rs"), "").unwrap();
+        std::fs::write(root.join("libs/core/b.rs"), "").unwrap();
+
+        let changed = watcher.wait();
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let changed = changed.unwrap();
+        assert!(changed.contains(&root.join("libs/core/a.rs")));
+        assert!(changed.contains(&root.join("libs/core/b.rs")));
+        assert!(changed
+            .iter()
+            .all(|path| !path.starts_with(root.join(".parmenides"))));
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::clock::{Clock, SystemClock};
+use crate::errors::WatchError;
+
+use super::{Watcher, IGNORED_DIRECTORIES};
+
+/// A [`Watcher`] that scans the workspace at a fixed interval, comparing modification times.
+pub struct PollingWatcher<C = SystemClock> {
+    root: PathBuf,
+    interval: Duration,
+    clock: C,
+    snapshot: HashMap<PathBuf, SystemTime>,
+}
+
+impl PollingWatcher {
+    /// Creates a watcher for `root`, taking the initial snapshot right away.
+    pub fn new(root: &Path, interval: Duration) -> Result<Self, WatchError> {
+        Self::with_clock(root, interval, SystemClock)
+    }
+}
+
+impl<C: Clock> PollingWatcher<C> {
+    /// Creates a watcher that waits between scans using the given clock.
+    pub fn with_clock(root: &Path, interval: Duration, clock: C) -> Result<Self, WatchError> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            interval,
+            clock,
+            snapshot: scan(root)?,
+        })
+    }
+}
+
+impl<C: Clock> Watcher for PollingWatcher<C> {
+    fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError> {
+        loop {
+            self.clock.sleep(self.interval);
+
+            let snapshot = scan(&self.root)?;
+
+            let mut changed: BTreeSet<PathBuf> = snapshot
+                .iter()
+                .filter(|(path, modified)| self.snapshot.get(*path) != Some(modified))
+                .map(|(path, _)| path.clone())
+                .collect();
+
+            changed.extend(
+                self.snapshot
+                    .keys()
+                    .filter(|path| !snapshot.contains_key(*path))
+                    .cloned(),
+            );
+
+            self.snapshot = snapshot;
+
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+}
+
+fn scan(root: &Path) -> Result<HashMap<PathBuf, SystemTime>, WatchError> {
+    let mut snapshot = HashMap::new();
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(directory) = stack.pop() {
+        let entries = match std::fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            // The directory was removed while scanning.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(WatchError::Io(directory, err)),
+        };
+
+        for entry in entries {
+            let entry = entry.map_err(|err| WatchError::Io(directory.clone(), err))?;
+            let path = entry.path();
+
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                if !IGNORED_DIRECTORIES.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                    stack.push(path);
+                }
+            } else if let Ok(modified) = metadata.modified() {
+                snapshot.insert(path, modified);
+            }
+        }
+    }
+
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    use crate::clock::ManualClock;
+    use crate::watch::Watcher;
+
+    use super::PollingWatcher;
+
+    #[test]
+    pub fn when_files_change_should_report_created_and_removed_files() {
+        let root = std::env::temp_dir().join(format!("parmenides-polling-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("libs/core")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("libs/core/old.rs"), "").unwrap();
+
+        let mut watcher =
+            PollingWatcher::with_clock(&root, Duration::from_secs(1), ManualClock::new()).unwrap();
+
+        std::fs::write(root.join("libs/core/new.rs"), "").unwrap();
+        std::fs::write(root.join(".git/index"), "").unwrap();
+        std::fs::remove_file(root.join("libs/core/old.rs")).unwrap();
+
+        let changed = watcher.wait();
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            changed.unwrap(),
+            BTreeSet::from([root.join("libs/core/new.rs"), root.join("libs/core/old.rs")])
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+use crate::errors::WatchError;
+use crate::process::ProcessRunner;
+
+use super::{is_ignored, Watcher};
+
+const SUBSCRIPTION: &str = "parmenides";
+
+/// A [`Watcher`] backed by a Watchman subscription.
+///
+/// Watchman keeps watching the repository between runs, so subscribing is cheap even for very
+/// large repositories. The subscription lasts as long as the watcher.
+pub struct WatchmanWatcher {
+    root: PathBuf,
+    child: Child,
+    // Closing the input ends the persistent session, so it is kept open.
+    _stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+/// Returns `true` if the `watchman` executable can be run.
+pub(crate) fn is_available(runner: &dyn ProcessRunner) -> bool {
+    runner
+        .run(Command::new("watchman").arg("version"))
+        .is_ok_and(|output| output.is_success())
+}
+
+impl WatchmanWatcher {
+    /// Asks Watchman to watch `root` and subscribes to its changes.
+    pub fn subscribe(root: &Path) -> Result<Self, WatchError> {
+        let output = Command::new("watchman")
+            .args(["--output-encoding=json", "--no-pretty", "watch-project"])
+            .arg(root)
+            .output()
+            .map_err(WatchError::WatchmanUnavailable)?;
+
+        let response = parse_response(&String::from_utf8_lossy(&output.stdout))?;
+
+        let watch = response
+            .get("watch")
+            .and_then(Value::as_str)
+            .ok_or_else(|| WatchError::Watchman("missing watch root".to_owned()))?;
+
+        let mut query = json!({ "fields": ["name"] });
+
+        // Watchman may watch an ancestor of the workspace, e.g. the repository root.
+        if let Some(relative_path) = response.get("relative_path").and_then(Value::as_str) {
+            query["relative_root"] = json!(relative_path);
+        }
+
+        let mut child = Command::new("watchman")
+            .args([
+                "--persistent",
+                "--server-encoding=json",
+                "--output-encoding=json",
+                "--no-pretty",
+                "--json-command",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(WatchError::WatchmanUnavailable)?;
+
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        writeln!(
+            stdin,
+            "{}",
+            json!(["subscribe", watch, SUBSCRIPTION, query])
+        )
+        .map_err(|err| WatchError::Io(root.to_path_buf(), err))?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            child,
+            _stdin: stdin,
+            stdout,
+        })
+    }
+}
+
+impl Watcher for WatchmanWatcher {
+    fn wait(&mut self) -> Result<BTreeSet<PathBuf>, WatchError> {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            let read = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|err| WatchError::Io(self.root.clone(), err))?;
+
+            if read == 0 {
+                return Err(WatchError::Watchman("the session ended".to_owned()));
+            }
+
+            if let Some(changed) = parse_subscription(&line, &self.root)? {
+                return Ok(changed);
+            }
+        }
+    }
+}
+
+impl Drop for WatchmanWatcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn parse_response(line: &str) -> Result<Value, WatchError> {
+    let response: Value = serde_json::from_str(line.trim())
+        .map_err(|err| WatchError::Watchman(format!("invalid response: {err}")))?;
+
+    if let Some(error) = response.get("error").and_then(Value::as_str) {
+        return Err(WatchError::Watchman(error.to_owned()));
+    }
+
+    Ok(response)
+}
+
+/// Parses a message of the subscription session.
+///
+/// Returns `None` for messages that carry no changes: the subscription acknowledgement and the
+/// initial fresh-instance listing of every file.
+fn parse_subscription(line: &str, root: &Path) -> Result<Option<BTreeSet<PathBuf>>, WatchError> {
+    let response = parse_response(line)?;
+
+    if response.get("subscription").and_then(Value::as_str) != Some(SUBSCRIPTION)
+        || response.get("is_fresh_instance").and_then(Value::as_bool) == Some(true)
+    {
+        return Ok(None);
+    }
+
+    let changed: BTreeSet<PathBuf> = response
+        .get("files")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|file| file.as_str().or_else(|| file.get("name")?.as_str()))
+        .filter(|name| !is_ignored(Path::new(name)))
+        .map(|name| root.join(name))
+        .collect();
+
+    Ok((!changed.is_empty()).then_some(changed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::Path;
+
+    use crate::errors::WatchError;
+
+    use super::parse_subscription;
+
+    #[test]
+    pub fn when_parsing_subscription_should_skip_acknowledgement_and_fresh_instance() {
+        let root = Path::new("/repo");
+
+        assert_eq!(
+            parse_subscription(r#"{"version":"2024.01.01","subscribe":"parmenides"}"#, root)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            parse_subscription(
+                r#"{"subscription":"parmenides","is_fresh_instance":true,"files":["a.rs"]}"#,
+                root
+            )
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            parse_subscription(
+                r#"{"subscription":"parmenides","is_fresh_instance":false,"files":["libs/core/lib.rs",".parmenides/cache/abc"]}"#,
+                root
+            )
+            .unwrap(),
+            Some(BTreeSet::from([root.join("libs/core/lib.rs")]))
+        );
+    }
+
+    #[test]
+    pub fn when_watchman_reports_error_should_return_error() {
+        let result = parse_subscription(r#"{"error":"unable to resolve root"}"#, Path::new("/"));
+
+        assert!(
+            matches!(result, Err(WatchError::Watchman(message)) if message == "unable to resolve root")
+        );
+    }
+}
@@ -1,17 +1,172 @@
 use std::path::PathBuf;
-use std::{collections::HashMap, path::Path};
+use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    errors::{AddProjectError, MarkProjectAsAffectedError},
-    project::{Project, ProjectId},
+    affected::{AffectedStrategy, Marking, TransitiveDependents},
+    errors::{
+        AddProjectError, MarkProjectAsAffectedError, RemoveProjectError, TopologicalOrderError,
+    },
+    inputs::Inputs,
+    project::{Deprecation, Project, ProjectId, StableId},
+    sort::natural_cmp,
 };
 
+/// The direction in which the graph is walked, starting from a project.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    /// Follow the projects that the starting project depends on.
+    Dependencies,
+    /// Follow the projects that depend on the starting project.
+    Dependents,
+}
+
+/// Explains why a project is affected.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct AffectedReason {
+    /// The chain of projects the change propagated through. It starts at the directly changed
+    /// project and ends at the explained one, so it holds a single ID for a direct change.
+    pub chain: Vec<ProjectId>,
+    /// The changed files of the first project of the chain. It is empty when the project was
+    /// marked without files, e.g. through [`Workspace::mark_project_as_affected`].
+    pub files: Vec<PathBuf>,
+    /// Whether the change stopped at the explained project, as it encapsulates the dependency
+    /// the change came from, so its dependents weren't marked for it. See
+    /// [`Edge::stop_propagation`].
+    pub stopped: bool,
+}
+
+/// How strongly changes propagate through a dependency edge.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum EdgeStrength {
+    /// Changes always propagate, e.g. through an API dependency.
+    #[default]
+    Hard,
+    /// Changes only propagate with soft propagation enabled, e.g. through dev tooling or
+    /// examples. See [`Workspace::set_soft_propagation`].
+    Soft,
+}
+
+/// What a dependency is needed for, e.g. to only propagate changes of dev dependencies to test
+/// targets. See [`Workspace::set_propagated_kinds`].
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    /// Needed to run the dependent, the kind of undeclared dependencies.
+    #[default]
+    Runtime,
+    /// Only needed to develop the dependent, e.g. by its tests.
+    Dev,
+    /// Only needed to build the dependent, e.g. code generators.
+    Build,
+    /// Only needed by optional features of the dependent.
+    Optional,
+}
+
+impl DependencyKind {
+    /// Every kind, so changes propagate through every edge by default.
+    pub const ALL: [DependencyKind; 4] = [
+        DependencyKind::Runtime,
+        DependencyKind::Dev,
+        DependencyKind::Build,
+        DependencyKind::Optional,
+    ];
+}
+
+/// The attributes of a dependency edge, from a dependent to one of its dependencies.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Edge {
+    pub strength: EdgeStrength,
+    /// Changes coming through the edge affect the dependent, but stop there, e.g. for a facade
+    /// whose own contract tests cover the dependency it wraps. Its dependents are only affected
+    /// by its own changes, or by its other dependencies.
+    #[serde(default)]
+    pub stop_propagation: bool,
+    #[serde(default)]
+    pub kind: DependencyKind,
+    /// The dependency isn't expressed in the files of the dependent, e.g. a service generated
+    /// from a schema project. It propagates like any other, and is only told apart in reports.
+    #[serde(default)]
+    pub implicit: bool,
+}
+
+impl Edge {
+    /// A soft edge, see [`EdgeStrength::Soft`].
+    pub fn soft() -> Self {
+        Self {
+            strength: EdgeStrength::Soft,
+            ..Self::default()
+        }
+    }
+
+    /// Sets whether changes stop at the dependent, see [`Edge::stop_propagation`].
+    pub fn with_stop_propagation(mut self, stop: bool) -> Self {
+        self.stop_propagation = stop;
+        self
+    }
+
+    /// Sets what the dependency is needed for, see [`DependencyKind`].
+    pub fn with_kind(mut self, kind: DependencyKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets whether the dependency is implicit, see [`Edge::implicit`].
+    pub fn with_implicit(mut self, implicit: bool) -> Self {
+        self.implicit = implicit;
+        self
+    }
+}
+
+/// What happens to the dependents of a project being removed, see
+/// [`Workspace::remove_project`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum RemovePolicy {
+    /// Fail if other projects still depend on it.
+    #[default]
+    Restrict,
+    /// Remove its dependents too, transitively.
+    Cascade,
+}
+
+/// What marked a single project as affected: its own files, or one of its dependencies.
+#[derive(Debug, Clone)]
+pub(crate) enum Cause {
+    Changed(Vec<PathBuf>),
+    Dependency(ProjectId),
+    /// Through a dependency whose changes stop at the project, so its dependents weren't
+    /// marked.
+    Stopped(ProjectId),
+}
+
 /// Represents a workspace, which holds a collection of projects and manages their
 /// relationships, such as dependencies and dependents.
 #[derive(Debug)]
 pub struct Workspace {
-    arena: Vec<Project>,
+    /// The projects by ID. A removed project leaves an empty slot, so the IDs of the others
+    /// stay valid.
+    arena: Vec<Option<Project>>,
     hash: HashMap<PathBuf, ProjectId>,
+    identifiers: HashMap<String, ProjectId>,
+    stable_ids: HashMap<StableId, ProjectId>,
+    constants: HashMap<String, String>,
+    causes: HashMap<ProjectId, Cause>,
+    /// The edges with non-default attributes, by dependent and dependency.
+    edges: HashMap<(ProjectId, ProjectId), Edge>,
+    soft_propagation: bool,
+    propagated_kinds: Vec<DependencyKind>,
+    strategy: Arc<dyn AffectedStrategy>,
+    /// The files affecting every project, relative to the root they are paired with.
+    triggers: Option<(PathBuf, Inputs)>,
 }
 
 impl Workspace {
@@ -19,32 +174,250 @@ impl Workspace {
         Self {
             arena: vec![],
             hash: HashMap::new(),
+            identifiers: HashMap::new(),
+            stable_ids: HashMap::new(),
+            constants: HashMap::new(),
+            causes: HashMap::new(),
+            edges: HashMap::new(),
+            soft_propagation: false,
+            propagated_kinds: DependencyKind::ALL.to_vec(),
+            strategy: Arc::new(TransitiveDependents),
+            triggers: None,
+        }
+    }
+
+    pub(crate) fn set_constants(&mut self, constants: HashMap<String, String>) {
+        self.constants = constants;
+    }
+
+    /// Returns the workspace-level constants declared in the workspace declaration.
+    pub fn constants(&self) -> &HashMap<String, String> {
+        &self.constants
+    }
+
+    pub(crate) fn set_edge(&mut self, dependent: ProjectId, dependency: ProjectId, edge: Edge) {
+        if edge == Edge::default() {
+            self.edges.remove(&(dependent, dependency));
+        } else {
+            self.edges.insert((dependent, dependency), edge);
+        }
+    }
+
+    /// Gets the attributes of the edge from `dependent` to `dependency`.
+    ///
+    /// # Returns
+    /// - `Some(Edge)`: The attributes, if `dependent` depends directly on `dependency`.
+    /// - `None`: If there is no such edge.
+    pub fn edge(&self, dependent: ProjectId, dependency: ProjectId) -> Option<Edge> {
+        self.get_project(dependent)?
+            .dependencies()
+            .contains(&dependency)
+            .then(|| {
+                self.edges
+                    .get(&(dependent, dependency))
+                    .copied()
+                    .unwrap_or_default()
+            })
+    }
+
+    /// Returns the edges with non-default attributes, as dependent, dependency, and edge.
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn edges(&self) -> impl Iterator<Item = (ProjectId, ProjectId, Edge)> + '_ {
+        self.edges
+            .iter()
+            .map(|((dependent, dependency), edge)| (*dependent, *dependency, *edge))
+    }
+
+    /// Enables propagating changes through [`EdgeStrength::Soft`] edges when marking projects as
+    /// affected. Disabled by default, so only hard edges propagate.
+    pub fn set_soft_propagation(&mut self, enabled: bool) {
+        self.soft_propagation = enabled;
+    }
+
+    /// Returns `true` if changes propagate through soft edges.
+    pub fn soft_propagation(&self) -> bool {
+        self.soft_propagation
+    }
+
+    /// Restricts propagating changes, when marking projects as affected, to the edges of the
+    /// given kinds. Every kind propagates by default.
+    ///
+    /// Changed projects are marked either way, e.g. a test run can propagate through runtime
+    /// and dev dependencies while a deployment only follows runtime ones.
+    pub fn set_propagated_kinds<I>(&mut self, kinds: I)
+    where
+        I: IntoIterator<Item = DependencyKind>,
+    {
+        self.propagated_kinds = kinds.into_iter().collect();
+    }
+
+    /// Returns the kinds of the edges changes propagate through.
+    pub fn propagated_kinds(&self) -> &[DependencyKind] {
+        &self.propagated_kinds
+    }
+
+    /// Sets the strategy deciding which projects a change marks as affected. Defaults to
+    /// [`TransitiveDependents`].
+    pub fn set_affected_strategy(&mut self, strategy: Arc<dyn AffectedStrategy>) {
+        self.strategy = strategy;
+    }
+
+    /// Returns the strategy deciding which projects a change marks as affected.
+    pub fn affected_strategy(&self) -> Arc<dyn AffectedStrategy> {
+        Arc::clone(&self.strategy)
+    }
+
+    pub(crate) fn set_deprecation(&mut self, id: ProjectId, deprecation: Deprecation) {
+        if let Some(Some(project)) = self.arena.get_mut(id.into_inner()) {
+            project.deprecation = Some(deprecation);
         }
     }
 
+    pub(crate) fn set_triggers(&mut self, root: PathBuf, triggers: Inputs) {
+        self.triggers = Some((root, triggers));
+    }
+
+    /// Returns the root and globs of the files whose changes affect every project, if any.
+    pub fn triggers(&self) -> Option<(&Path, &[String])> {
+        self.triggers
+            .as_ref()
+            .map(|(root, triggers)| (root.as_path(), triggers.globs()))
+    }
+
+    /// Returns `true` if changes to the file affect every project, e.g. a lock file or the CI
+    /// configuration, which no project owns but all of them depend on.
+    ///
+    /// # Parameters
+    /// - `path`: The file path, relative to the same root as the project paths.
+    pub fn is_trigger<P>(&self, path: &P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        self.triggers.as_ref().is_some_and(|(root, triggers)| {
+            path.as_ref()
+                .strip_prefix(root)
+                .is_ok_and(|relative| triggers.includes(&relative))
+        })
+    }
+
     pub(crate) fn add_project(&mut self, project: Project) -> Result<ProjectId, AddProjectError> {
         let id = ProjectId::new(self.arena.len());
 
-        if let Some(existing_id) = self.hash.insert(project.path.clone(), id) {
-            return Err(AddProjectError::PathAlreadyAdded(existing_id));
+        if let Some(existing_id) = self.hash.get(&project.path) {
+            return Err(AddProjectError::PathAlreadyAdded(*existing_id));
+        }
+
+        if let Some(existing_id) = self.identifiers.get(&project.identifier) {
+            return Err(AddProjectError::IdentifierAlreadyAdded(
+                project.identifier.clone(),
+                *existing_id,
+            ));
+        }
+
+        if let Some(existing_id) = self.stable_ids.get(&project.stable_id) {
+            return Err(AddProjectError::StableIdAlreadyAdded(
+                project.stable_id,
+                *existing_id,
+            ));
         }
 
+        self.hash.insert(project.path.clone(), id);
+
         if let Some(dependencies) = &project.dependencies {
             for dependency in dependencies {
                 let project = self
                     .arena
                     .get_mut(dependency.into_inner())
+                    .and_then(Option::as_mut)
                     .ok_or(AddProjectError::DepedencyNotFound(*dependency))?;
 
                 project.add_dependent(id)
             }
         }
 
-        self.arena.push(project);
+        self.identifiers.insert(project.identifier.clone(), id);
+        self.stable_ids.insert(project.stable_id, id);
+        self.arena.push(Some(project));
 
         Ok(id)
     }
 
+    /// Removes a project, detaching it from the dependents of its dependencies.
+    ///
+    /// The IDs of the remaining projects stay valid, and the ID of a removed project is never
+    /// handed out again, so incremental tooling can drop deleted packages without rebuilding
+    /// the workspace.
+    ///
+    /// # Parameters
+    /// - `id`: The `ProjectId` of the project to remove.
+    /// - `policy`: Whether to fail or to remove the dependents too when other projects still
+    ///   depend on it.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ProjectId>)`: The IDs of the removed projects, in ID order.
+    /// - `Err(RemoveProjectError)`: If the project doesn't exist, or still has dependents
+    ///   with [`RemovePolicy::Restrict`].
+    pub fn remove_project(
+        &mut self,
+        id: ProjectId,
+        policy: RemovePolicy,
+    ) -> Result<Vec<ProjectId>, RemoveProjectError> {
+        let dependents = self
+            .transitive_dependents(id)
+            .ok_or(RemoveProjectError::ProjectNotFound(id))?;
+
+        if policy == RemovePolicy::Restrict && !dependents.is_empty() {
+            return Err(RemoveProjectError::HasDependents(
+                id,
+                self.linked(id).dependents.clone(),
+            ));
+        }
+
+        let mut removed = dependents;
+        removed.push(id);
+        removed.sort();
+        removed.dedup();
+
+        for removed_id in &removed {
+            let Some(project) = self.arena[removed_id.into_inner()].take() else {
+                continue;
+            };
+
+            for dependency in project.dependencies.iter().flatten() {
+                if let Some(dependency) = self.arena[dependency.into_inner()].as_mut() {
+                    dependency
+                        .dependents
+                        .retain(|dependent| dependent != removed_id);
+                }
+            }
+
+            self.hash.remove(&project.path);
+            self.identifiers.remove(&project.identifier);
+            self.stable_ids.remove(&project.stable_id);
+            self.causes.remove(removed_id);
+        }
+
+        self.edges.retain(|(dependent, dependency), _| {
+            !removed.contains(dependent) && !removed.contains(dependency)
+        });
+
+        for deprecation in self
+            .arena
+            .iter_mut()
+            .flatten()
+            .filter_map(|project| project.deprecation.as_mut())
+        {
+            if deprecation
+                .replacement
+                .is_some_and(|replacement| removed.contains(&replacement))
+            {
+                deprecation.replacement = None;
+            }
+        }
+
+        Ok(removed)
+    }
+
     /// Gets the ID of a project by its path.
     ///
     /// This method provides a quick way to find a project's ID using its file system path.
@@ -62,14 +435,87 @@ impl Workspace {
         self.hash.get(path.as_ref()).copied()
     }
 
+    /// Gets the ID of a project by its identifier.
+    ///
+    /// # Parameters
+    /// - `identifier`: The identifier of the project, see [`Project::identifier`].
+    ///
+    /// # Returns
+    /// - `Some(ProjectId)`: The ID of the project if found.
+    /// - `None`: If no project has the given identifier.
+    pub fn get_id_by_identifier(&self, identifier: &str) -> Option<ProjectId> {
+        self.identifiers.get(identifier).copied()
+    }
+
+    /// Gets the ID of a project by its stable ID, e.g. one persisted by an earlier run.
+    ///
+    /// # Parameters
+    /// - `stable_id`: The stable ID of the project, see [`Project::stable_id`].
+    ///
+    /// # Returns
+    /// - `Some(ProjectId)`: The ID of the project in this workspace if found.
+    /// - `None`: If no project has the given stable ID.
+    pub fn get_id_by_stable_id(&self, stable_id: StableId) -> Option<ProjectId> {
+        self.stable_ids.get(&stable_id).copied()
+    }
+
+    /// Finds the project that owns a file.
+    ///
+    /// The owner is the project whose path is the deepest prefix of the file's path, so nested
+    /// project roots (e.g. `libs/a` and `libs/a/sub`) are handled correctly. Prefixes are
+    /// matched by path components, so `libs/ab` is never owned by `libs/a`.
+    ///
+    /// # Parameters
+    /// - `path`: The path of the file. It should be on the same basis (absolute or relative to
+    ///   the same root) as the project paths.
+    ///
+    /// # Returns
+    /// - `Some(ProjectId)`: The ID of the owning project.
+    /// - `None`: If the file is outside every project.
+    pub fn resolve_owner<P>(&self, path: &P) -> Option<ProjectId>
+    where
+        P: AsRef<Path>,
+    {
+        path.as_ref()
+            .ancestors()
+            .find_map(|ancestor| self.hash.get(ancestor).copied())
+    }
+
     /// Returns the number of projects in the workspace.
     pub fn len(&self) -> usize {
-        self.arena.len()
+        self.hash.len()
     }
 
     /// Returns `true` if the workspace contains no projects.
     pub fn is_empty(&self) -> bool {
-        self.arena.is_empty()
+        self.hash.is_empty()
+    }
+
+    /// Returns the number of IDs handed out, including those of removed projects, i.e. the
+    /// length of a vector indexed by ID.
+    pub(crate) fn id_bound(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns an iterator over all projects in the workspace, in ID order.
+    pub fn iter(&self) -> impl Iterator<Item = &Project> {
+        self.arena.iter().flatten()
+    }
+
+    /// Returns an iterator over all projects in the workspace paired with their IDs, in ID
+    /// order.
+    pub fn iter_with_ids(&self) -> impl Iterator<Item = (ProjectId, &Project)> {
+        self.arena
+            .iter()
+            .enumerate()
+            .filter_map(|(index, project)| Some((ProjectId::new(index), project.as_ref()?)))
+    }
+
+    /// Returns a project the graph links to, which is never a removed one.
+    fn linked(&self, id: ProjectId) -> &Project {
+        self.arena[id.into_inner()]
+            .as_ref()
+            .expect("the graph only links projects that weren't removed")
     }
 
     /// Gets a project by its ID.
@@ -83,7 +529,7 @@ impl Workspace {
     /// - `Some(&Project)`: The project if it exists.
     /// - `None`: If no project with the given ID exists.
     pub fn get_project(&self, id: ProjectId) -> Option<&Project> {
-        self.arena.get(id.into_inner())
+        self.arena.get(id.into_inner())?.as_ref()
     }
 
     /// Gets a project by its path.
@@ -104,6 +550,130 @@ impl Workspace {
             .and_then(|id| self.get_project(id))
     }
 
+    /// Gets the names of the projects reachable from a project within a given depth.
+    ///
+    /// This method is meant for interactive consumers, such as autocompletion in a TUI or an
+    /// editor, so it only walks the graph up to `depth` levels away from the starting project
+    /// and never visits the same project twice. The starting project itself is not included.
+    ///
+    /// The names are sorted by relevance, that is, projects with more dependents come first.
+    /// Projects with the same number of dependents are sorted by name.
+    ///
+    /// # Parameters
+    /// - `id`: The `ProjectId` of the starting project.
+    /// - `direction`: Whether to follow dependencies or dependents.
+    /// - `depth`: How many levels to walk. Use `1` for the direct neighbors only.
+    ///
+    /// # Returns
+    /// - `Some(Vec<&str>)`: The names of the neighbors, sorted by relevance.
+    /// - `None`: If no project with the given ID exists.
+    pub fn neighbors(
+        &self,
+        id: ProjectId,
+        direction: Direction,
+        depth: usize,
+    ) -> Option<Vec<&str>> {
+        self.get_project(id)?;
+
+        // A set rather than a flag per project, as the walk is usually short and shouldn't
+        // cost an allocation the size of the workspace on every keystroke.
+        let mut visited = HashSet::from([id]);
+
+        let mut found = Vec::new();
+        let mut queue = VecDeque::from([(id, 0)]);
+
+        while let Some((current_id, current_depth)) = queue.pop_front() {
+            if current_depth == depth {
+                continue;
+            }
+
+            let project = self.linked(current_id);
+
+            let next = match direction {
+                Direction::Dependencies => project.dependencies.as_deref().unwrap_or_default(),
+                Direction::Dependents => &project.dependents,
+            };
+
+            for next_id in next {
+                if visited.insert(*next_id) {
+                    found.push(self.linked(*next_id));
+                    queue.push_back((*next_id, current_depth + 1));
+                }
+            }
+        }
+
+        found.sort_by(|a, b| {
+            b.dependents
+                .len()
+                .cmp(&a.dependents.len())
+                .then_with(|| natural_cmp(&a.name, &b.name))
+        });
+
+        Some(
+            found
+                .into_iter()
+                .map(|project| project.name.as_str())
+                .collect(),
+        )
+    }
+
+    /// Returns the IDs of every project the given project depends on, directly or transitively.
+    ///
+    /// The workspace is not modified, and each project is visited once, so the walk terminates
+    /// even if the graph contains a cycle. The starting project is only included when it
+    /// depends on itself through a cycle.
+    ///
+    /// # Returns
+    /// - `Some(Vec<ProjectId>)`: The IDs of the dependencies, in ID order.
+    /// - `None`: If no project with the given ID exists.
+    pub fn transitive_dependencies(&self, id: ProjectId) -> Option<Vec<ProjectId>> {
+        self.transitive(id, Direction::Dependencies)
+    }
+
+    /// Returns the IDs of every project that depends on the given project, directly or
+    /// transitively, i.e. the projects that could break if it changes.
+    ///
+    /// Like [`Self::transitive_dependencies`], this is read-only and cycle-safe.
+    ///
+    /// # Returns
+    /// - `Some(Vec<ProjectId>)`: The IDs of the dependents, in ID order.
+    /// - `None`: If no project with the given ID exists.
+    pub fn transitive_dependents(&self, id: ProjectId) -> Option<Vec<ProjectId>> {
+        self.transitive(id, Direction::Dependents)
+    }
+
+    fn transitive(&self, id: ProjectId, direction: Direction) -> Option<Vec<ProjectId>> {
+        self.get_project(id)?;
+
+        let mut visited = vec![false; self.arena.len()];
+        let mut stack = vec![id];
+
+        while let Some(current_id) = stack.pop() {
+            let project = self.linked(current_id);
+
+            let next = match direction {
+                Direction::Dependencies => project.dependencies.as_deref().unwrap_or_default(),
+                Direction::Dependents => &project.dependents,
+            };
+
+            for next_id in next {
+                if !visited[next_id.into_inner()] {
+                    visited[next_id.into_inner()] = true;
+                    stack.push(*next_id);
+                }
+            }
+        }
+
+        Some(
+            visited
+                .into_iter()
+                .enumerate()
+                .filter(|(_, visited)| *visited)
+                .map(|(index, _)| ProjectId::new(index))
+                .collect(),
+        )
+    }
+
     /// Marks a project and all its dependents as "affected".
     ///
     /// This method traverses the dependency tree of a project and marks it and all projects
@@ -119,87 +689,447 @@ impl Workspace {
         &mut self,
         id: ProjectId,
     ) -> Result<(), MarkProjectAsAffectedError> {
-        let mut stack = vec![id];
-
-        while let Some(current_id) = stack.pop() {
-            let project = self
-                .arena
-                .get_mut(current_id.into_inner())
-                .ok_or(MarkProjectAsAffectedError::ProjectNotFound(current_id))?;
-
-            if !project.affected {
-                project.affected = true;
-                stack.extend(&project.dependents);
-            }
-        }
-
-        Ok(())
+        self.mark_projects_as_affected([id])
     }
-}
 
-impl Default for Workspace {
-    fn default() -> Self {
-        Self::new()
+    /// Marks many projects and all their dependents as "affected" in a single traversal.
+    ///
+    /// The walks from every root share the visited set, so dependents reachable from several
+    /// roots are only visited once. All the IDs are checked before anything is marked.
+    ///
+    /// # Parameters
+    /// - `ids`: The `ProjectId`s of the projects to mark as affected.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the operation was successful.
+    /// - `Err(MarkProjectAsAffectedError)`: If one of the projects could not be found.
+    pub fn mark_projects_as_affected<I>(&mut self, ids: I) -> Result<(), MarkProjectAsAffectedError>
+    where
+        I: IntoIterator<Item = ProjectId>,
+    {
+        self.mark_projects_as_changed(ids.into_iter().map(|id| (id, vec![])))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::Workspace;
-    use crate::{
-        errors::AddProjectError,
-        project::{Project, ProjectId},
-    };
-    use std::path::Path;
+    /// Marks the projects owning changed files, and all their dependents, as "affected",
+    /// recording the files so [`Workspace::affected_reason`] can explain the result.
+    ///
+    /// The dependents are walked breadth-first, so each one is explained by a shortest chain
+    /// from a changed project. Projects already affected keep their reason, unless they are
+    /// changed directly, in which case the files are added to their own. Another strategy
+    /// set with [`Workspace::set_affected_strategy`] may mark other projects.
+    ///
+    /// # Parameters
+    /// - `changes`: The `ProjectId`s of the changed projects, with the files that changed in
+    ///   each.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the operation was successful.
+    /// - `Err(MarkProjectAsAffectedError)`: If one of the projects could not be found.
+    pub fn mark_projects_as_changed<I>(
+        &mut self,
+        changes: I,
+    ) -> Result<(), MarkProjectAsAffectedError>
+    where
+        I: IntoIterator<Item = (ProjectId, Vec<PathBuf>)>,
+    {
+        let mut affected = self.affected_marks();
+        let mut causes = std::mem::take(&mut self.causes);
 
-    #[test]
-    pub fn when_adding_project_should_add() {
-        let path = Path::new("/home/test/project");
-        let name = "test";
+        let result = self.propagate(
+            changes,
+            self.strategy.as_ref(),
+            self.soft_propagation,
+            &self.propagated_kinds,
+            &mut affected,
+            &mut causes,
+        );
 
-        let mut workspace = Workspace::new();
+        self.causes = causes;
 
-        let project_id = workspace
-            .add_project(Project::new(path.to_owned(), name.to_owned(), None))
-            .unwrap();
+        self.set_affected_marks(affected);
 
-        let project = workspace.get_project(project_id).unwrap();
+        result
+    }
 
-        assert_eq!(project.path, path);
-        assert_eq!(project.name, name);
+    /// Returns whether each project is affected, by ID.
+    fn affected_marks(&self) -> Vec<bool> {
+        self.arena
+            .iter()
+            .map(|project| project.as_ref().is_some_and(|project| project.affected))
+            .collect()
     }
 
-    #[test]
-    pub fn when_adding_the_same_project_twice_should_return_error() {
-        let path = Path::new("/home/test/project");
-        let name = "test";
+    fn set_affected_marks(&mut self, affected: Vec<bool>) {
+        for (project, affected) in self.arena.iter_mut().zip(affected) {
+            if let Some(project) = project {
+                project.affected = affected;
+            }
+        }
+    }
 
-        let mut workspace = Workspace::new();
+    /// Marks the changed projects in `affected` with `strategy`, propagating through soft edges
+    /// only when `soft` is set and through edges of `kinds` only, and recording the causes.
+    /// Shared by the in-place marking and [`crate::affected::AffectedState`].
+    pub(crate) fn propagate<I>(
+        &self,
+        changes: I,
+        strategy: &dyn AffectedStrategy,
+        soft: bool,
+        kinds: &[DependencyKind],
+        affected: &mut [bool],
+        causes: &mut HashMap<ProjectId, Cause>,
+    ) -> Result<(), MarkProjectAsAffectedError>
+    where
+        I: IntoIterator<Item = (ProjectId, Vec<PathBuf>)>,
+    {
+        let changes: Vec<(ProjectId, Vec<PathBuf>)> = changes.into_iter().collect();
 
-        let id = workspace
-            .add_project(Project::new(path.to_owned(), name.to_owned(), None))
-            .unwrap();
+        if let Some((missing, _)) = changes
+            .iter()
+            .find(|(id, _)| self.get_project(*id).is_none())
+        {
+            return Err(MarkProjectAsAffectedError::ProjectNotFound(*missing));
+        }
 
-        let error = workspace
-            .add_project(Project::new(path.to_owned(), name.to_owned(), None))
-            .unwrap_err();
+        let mut marking = Marking {
+            affected,
+            causes,
+            soft,
+            kinds,
+        };
 
-        assert_eq!(AddProjectError::PathAlreadyAdded(id), error);
+        strategy.mark(self, changes, &mut marking);
+
+        Ok(())
     }
 
-    #[test]
-    pub fn when_adding_project_dependent_should_update_dependents() {
-        let mut workspace = Workspace::new();
+    /// Marks the changed projects and walks their dependents, see [`TransitiveDependents`].
+    ///
+    /// A project reached through a stopping edge is marked but not walked past. It is walked
+    /// later if the change reaches it another way, or it changes itself.
+    pub(crate) fn propagate_to_dependents(
+        &self,
+        changes: Vec<(ProjectId, Vec<PathBuf>)>,
+        marking: &mut Marking,
+    ) {
+        let mut queue = VecDeque::new();
 
-        let core_id = workspace
-            .add_project(Project::new(
-                Path::new("/home/test/core").to_owned(),
-                "core".to_owned(),
-                None,
-            ))
-            .unwrap();
+        for (id, files) in changes {
+            if marking.mark_changed(id, files) {
+                queue.push_back(id);
+            }
+        }
 
-        let dependent_id = workspace
+        self.walk(queue, marking);
+    }
+
+    /// Walks the dependents of the projects in `queue`, breadth-first, marking them.
+    fn walk(&self, mut queue: VecDeque<ProjectId>, marking: &mut Marking) {
+        let Marking {
+            affected,
+            causes,
+            soft,
+            kinds,
+        } = marking;
+
+        while let Some(current_id) = queue.pop_front() {
+            for dependent_id in &self.linked(current_id).dependents {
+                let edge = self
+                    .edges
+                    .get(&(*dependent_id, current_id))
+                    .copied()
+                    .unwrap_or_default();
+
+                if (edge.strength == EdgeStrength::Soft && !*soft) || !kinds.contains(&edge.kind) {
+                    continue;
+                }
+
+                let index = dependent_id.into_inner();
+                let stopped = matches!(causes.get(dependent_id), Some(Cause::Stopped(_)));
+
+                if edge.stop_propagation {
+                    if !affected[index] {
+                        affected[index] = true;
+                        causes.insert(*dependent_id, Cause::Stopped(current_id));
+                    }
+                } else if !affected[index] || stopped {
+                    affected[index] = true;
+                    causes.insert(*dependent_id, Cause::Dependency(current_id));
+                    queue.push_back(*dependent_id);
+                }
+            }
+        }
+    }
+
+    /// Returns the IDs of the affected projects a change stopped at, see
+    /// [`Edge::stop_propagation`], in ID order.
+    pub fn stopped_projects(&self) -> impl Iterator<Item = ProjectId> + '_ {
+        self.affected_projects()
+            .filter(|id| matches!(self.causes.get(id), Some(Cause::Stopped(_))))
+    }
+
+    /// Resumes the propagation of the changes that stopped at the given projects, marking
+    /// their dependents as affected after all, e.g. when the contract tests of a facade fail.
+    /// Projects the change didn't stop at are left as they are.
+    ///
+    /// # Returns
+    /// - `Ok(())`: If the operation was successful.
+    /// - `Err(MarkProjectAsAffectedError)`: If one of the projects could not be found.
+    pub fn resume_propagation<I>(&mut self, ids: I) -> Result<(), MarkProjectAsAffectedError>
+    where
+        I: IntoIterator<Item = ProjectId>,
+    {
+        let ids: Vec<ProjectId> = ids.into_iter().collect();
+
+        if let Some(missing) = ids.iter().find(|id| self.get_project(**id).is_none()) {
+            return Err(MarkProjectAsAffectedError::ProjectNotFound(*missing));
+        }
+
+        let mut causes = std::mem::take(&mut self.causes);
+        let mut queue = VecDeque::new();
+
+        for id in ids {
+            if let Some(Cause::Stopped(dependency)) = causes.get(&id) {
+                causes.insert(id, Cause::Dependency(*dependency));
+                queue.push_back(id);
+            }
+        }
+
+        let mut affected = self.affected_marks();
+
+        self.walk(
+            queue,
+            &mut Marking {
+                affected: &mut affected,
+                causes: &mut causes,
+                soft: self.soft_propagation,
+                kinds: &self.propagated_kinds,
+            },
+        );
+        self.causes = causes;
+
+        self.set_affected_marks(affected);
+
+        Ok(())
+    }
+
+    /// Explains why a project is affected, e.g. for CI logs or to debug a surprising rebuild.
+    ///
+    /// # Parameters
+    /// - `id`: The `ProjectId` of the project to explain.
+    ///
+    /// # Returns
+    /// - `Some(AffectedReason)`: The chain from the changed project and its changed files.
+    /// - `None`: If the project doesn't exist or wasn't marked as affected.
+    pub fn affected_reason(&self, id: ProjectId) -> Option<AffectedReason> {
+        explain(&self.causes, id)
+    }
+
+    /// Clears every affected mark and reason, so the workspace can be reused for another
+    /// change set without being rebuilt.
+    pub fn reset_affected(&mut self) {
+        for project in self.arena.iter_mut().flatten() {
+            project.affected = false;
+        }
+
+        self.causes.clear();
+    }
+
+    /// Returns the IDs of all projects in dependency order.
+    ///
+    /// Every project comes after all of its dependencies, so building or testing the projects in
+    /// this order is always correct. Projects that don't depend on each other are kept in ID
+    /// order, which makes the result deterministic.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ProjectId>)`: The IDs in dependency order.
+    /// - `Err(TopologicalOrderError)`: If the workspace contains a dependency cycle.
+    pub fn topological_order(&self) -> Result<Vec<ProjectId>, TopologicalOrderError> {
+        let mut pending: Vec<usize> = self
+            .arena
+            .iter()
+            .map(|project| {
+                project
+                    .as_ref()
+                    .and_then(|project| project.dependencies.as_ref())
+                    .map_or(0, Vec::len)
+            })
+            .collect();
+
+        let mut ready: VecDeque<usize> = pending
+            .iter()
+            .enumerate()
+            .filter(|(index, count)| **count == 0 && self.arena[*index].is_some())
+            .map(|(index, _)| index)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(index) = ready.pop_front() {
+            order.push(ProjectId::new(index));
+
+            for dependent in &self.linked(ProjectId::new(index)).dependents {
+                let count = &mut pending[dependent.into_inner()];
+                *count -= 1;
+
+                if *count == 0 {
+                    ready.push_back(dependent.into_inner());
+                }
+            }
+        }
+
+        if order.len() == self.len() {
+            return Ok(order);
+        }
+
+        Err(TopologicalOrderError::CyclicDependencyFound(
+            self.find_cycle(&pending),
+        ))
+    }
+
+    /// Finds a cycle among the projects that still have pending dependencies after sorting.
+    fn find_cycle(&self, pending: &[usize]) -> Vec<PathBuf> {
+        let Some(start) = pending.iter().position(|count| *count > 0) else {
+            return vec![];
+        };
+
+        let mut stack = vec![start];
+        let mut current = start;
+
+        loop {
+            let next = self
+                .linked(ProjectId::new(current))
+                .dependencies
+                .iter()
+                .flatten()
+                .map(|id| id.into_inner())
+                .find(|index| pending[*index] > 0);
+
+            let Some(next) = next else {
+                break;
+            };
+
+            if let Some(position) = stack.iter().position(|index| *index == next) {
+                stack.drain(..position);
+                stack.push(next);
+                break;
+            }
+
+            stack.push(next);
+            current = next;
+        }
+
+        stack
+            .into_iter()
+            .map(|index| self.linked(ProjectId::new(index)).path.clone())
+            .collect()
+    }
+
+    /// Returns the IDs of the projects marked as affected, in ID order.
+    pub fn affected_projects(&self) -> impl Iterator<Item = ProjectId> + '_ {
+        self.iter_with_ids()
+            .filter(|(_, project)| project.affected)
+            .map(|(id, _)| id)
+    }
+
+    /// Returns the IDs of the projects not marked as affected, in ID order.
+    pub fn not_affected(&self) -> impl Iterator<Item = ProjectId> + '_ {
+        self.iter_with_ids()
+            .filter(|(_, project)| !project.affected)
+            .map(|(id, _)| id)
+    }
+}
+
+/// Walks the causes back from `id` to the changed project that explains it.
+pub(crate) fn explain(causes: &HashMap<ProjectId, Cause>, id: ProjectId) -> Option<AffectedReason> {
+    let mut chain = vec![id];
+    let mut current = id;
+    let stopped = matches!(causes.get(&id), Some(Cause::Stopped(_)));
+
+    loop {
+        match causes.get(&current)? {
+            Cause::Changed(files) => {
+                chain.reverse();
+
+                return Some(AffectedReason {
+                    chain,
+                    files: files.clone(),
+                    stopped,
+                });
+            }
+            Cause::Dependency(dependency) | Cause::Stopped(dependency) => {
+                current = *dependency;
+                chain.push(current);
+            }
+        }
+    }
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AffectedReason, Direction, Edge, EdgeStrength, RemovePolicy, Workspace};
+    use crate::{
+        errors::{
+            AddProjectError, MarkProjectAsAffectedError, RemoveProjectError, TopologicalOrderError,
+        },
+        project::{Project, ProjectId},
+    };
+    use std::path::Path;
+
+    #[test]
+    pub fn when_adding_project_should_add() {
+        let path = Path::new("/home/test/project");
+        let name = "test";
+
+        let mut workspace = Workspace::new();
+
+        let project_id = workspace
+            .add_project(Project::new(path.to_owned(), name.to_owned(), None))
+            .unwrap();
+
+        let project = workspace.get_project(project_id).unwrap();
+
+        assert_eq!(project.path, path);
+        assert_eq!(project.name, name);
+    }
+
+    #[test]
+    pub fn when_adding_the_same_project_twice_should_return_error() {
+        let path = Path::new("/home/test/project");
+        let name = "test";
+
+        let mut workspace = Workspace::new();
+
+        let id = workspace
+            .add_project(Project::new(path.to_owned(), name.to_owned(), None))
+            .unwrap();
+
+        let error = workspace
+            .add_project(Project::new(path.to_owned(), name.to_owned(), None))
+            .unwrap_err();
+
+        assert_eq!(AddProjectError::PathAlreadyAdded(id), error);
+    }
+
+    #[test]
+    pub fn when_adding_project_dependent_should_update_dependents() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let dependent_id = workspace
             .add_project(Project::new(
                 Path::new("/home/test/dependent").to_owned(),
                 "dependent".to_owned(),
@@ -239,6 +1169,81 @@ mod tests {
         assert_eq!(AddProjectError::DepedencyNotFound(dependency_id), error);
     }
 
+    #[test]
+    pub fn when_removing_project_should_detach_it_and_keep_other_ids_valid() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let ui_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/ui").to_owned(),
+                "ui".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![ui_id]),
+            ))
+            .unwrap();
+
+        let other_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/other").to_owned(),
+                "other".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            workspace.remove_project(ui_id, RemovePolicy::Restrict),
+            Err(RemoveProjectError::HasDependents(ui_id, vec![app_id]))
+        );
+        assert_eq!(
+            workspace.remove_project(ui_id, RemovePolicy::Cascade),
+            Ok(vec![ui_id, app_id])
+        );
+
+        assert_eq!(workspace.len(), 2);
+        assert!(workspace.get_project(app_id).is_none());
+        assert_eq!(workspace.get_id_by_identifier("ui"), None);
+        assert_eq!(
+            workspace.get_project(core_id).unwrap().dependents(),
+            [other_id]
+        );
+        assert_eq!(workspace.topological_order(), Ok(vec![core_id, other_id]));
+
+        assert_eq!(
+            workspace.remove_project(other_id, RemovePolicy::Restrict),
+            Ok(vec![other_id])
+        );
+        assert_eq!(
+            workspace.remove_project(other_id, RemovePolicy::Restrict),
+            Err(RemoveProjectError::ProjectNotFound(other_id))
+        );
+
+        let readded_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/ui").to_owned(),
+                "ui".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        assert_ne!(readded_id, ui_id);
+        assert_eq!(workspace.iter().count(), 2);
+    }
+
     #[test]
     pub fn when_marking_project_as_affected_should_mark_dependents_too() {
         let mut workspace = Workspace::new();
@@ -267,4 +1272,482 @@ mod tests {
         assert!(core.affected);
         assert!(dependent.affected);
     }
+
+    #[test]
+    pub fn when_edge_is_soft_should_propagate_only_with_soft_propagation() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let examples_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/examples").to_owned(),
+                "examples".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        workspace.set_edge(examples_id, core_id, Edge::soft());
+
+        workspace.mark_project_as_affected(core_id).unwrap();
+        let hard_only = workspace.get_project(examples_id).unwrap().affected;
+
+        workspace.reset_affected();
+        workspace.set_soft_propagation(true);
+        workspace.mark_project_as_affected(core_id).unwrap();
+        let with_soft = workspace.get_project(examples_id).unwrap().affected;
+
+        assert_eq!(
+            workspace
+                .edge(examples_id, core_id)
+                .map(|edge| edge.strength),
+            Some(EdgeStrength::Soft)
+        );
+        assert_eq!(workspace.edge(core_id, examples_id), None);
+        assert!(!hard_only);
+        assert!(with_soft);
+    }
+
+    #[test]
+    pub fn when_edge_stops_propagation_should_mark_dependent_but_not_past_it() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let facade_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/facade").to_owned(),
+                "facade".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![facade_id]),
+            ))
+            .unwrap();
+
+        workspace.set_edge(
+            facade_id,
+            core_id,
+            Edge::default().with_stop_propagation(true),
+        );
+
+        workspace.mark_project_as_affected(core_id).unwrap();
+
+        assert!(workspace.get_project(facade_id).unwrap().affected);
+        assert!(!workspace.get_project(app_id).unwrap().affected);
+        assert_eq!(
+            workspace.affected_reason(facade_id),
+            Some(AffectedReason {
+                chain: vec![core_id, facade_id],
+                files: vec![],
+                stopped: true,
+            })
+        );
+
+        // A change of the facade itself still propagates.
+        workspace.mark_project_as_affected(facade_id).unwrap();
+
+        assert!(workspace.get_project(app_id).unwrap().affected);
+        assert!(!workspace.affected_reason(facade_id).unwrap().stopped);
+    }
+
+    #[test]
+    pub fn when_marking_many_projects_should_mark_all_dependents_or_none() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let utils_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/utils").to_owned(),
+                "utils".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![core_id, utils_id]),
+            ))
+            .unwrap();
+
+        let missing_id = ProjectId::new(42);
+
+        assert_eq!(
+            workspace.mark_projects_as_affected([core_id, missing_id]),
+            Err(MarkProjectAsAffectedError::ProjectNotFound(missing_id))
+        );
+        assert!(workspace.affected_projects().next().is_none());
+
+        workspace
+            .mark_projects_as_affected([core_id, utils_id])
+            .unwrap();
+
+        assert_eq!(
+            workspace.affected_projects().collect::<Vec<_>>(),
+            vec![core_id, utils_id, app_id]
+        );
+    }
+
+    #[test]
+    pub fn when_explaining_affected_should_return_chain_from_changed_files() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let ui_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/ui").to_owned(),
+                "ui".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![ui_id]),
+            ))
+            .unwrap();
+
+        let other_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/other").to_owned(),
+                "other".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let file = Path::new("/home/test/core/src/lib.rs").to_owned();
+
+        workspace
+            .mark_projects_as_changed([(core_id, vec![file.clone()])])
+            .unwrap();
+
+        assert_eq!(
+            workspace.affected_reason(app_id),
+            Some(AffectedReason {
+                chain: vec![core_id, ui_id, app_id],
+                files: vec![file.clone()],
+                stopped: false,
+            })
+        );
+        assert_eq!(
+            workspace.affected_reason(core_id),
+            Some(AffectedReason {
+                chain: vec![core_id],
+                files: vec![file],
+                stopped: false,
+            })
+        );
+        assert_eq!(workspace.affected_reason(other_id), None);
+    }
+
+    #[test]
+    pub fn when_querying_affected_should_split_projects() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let dependent_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/dependent").to_owned(),
+                "dependent".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let other_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/other").to_owned(),
+                "other".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        workspace.mark_project_as_affected(core_id).unwrap();
+
+        assert_eq!(
+            workspace.affected_projects().collect::<Vec<_>>(),
+            vec![core_id, dependent_id]
+        );
+        assert_eq!(workspace.not_affected().collect::<Vec<_>>(), vec![other_id]);
+    }
+
+    #[test]
+    pub fn when_querying_neighbors_should_respect_depth_and_sort_by_relevance() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let utils_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/utils").to_owned(),
+                "utils".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let api_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/api").to_owned(),
+                "api".to_owned(),
+                Some(vec![core_id, utils_id]),
+            ))
+            .unwrap();
+
+        workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![api_id]),
+            ))
+            .unwrap();
+
+        let direct = workspace
+            .neighbors(core_id, Direction::Dependents, 1)
+            .unwrap();
+        assert_eq!(direct, vec!["api", "utils"]);
+
+        let transitive = workspace
+            .neighbors(core_id, Direction::Dependents, 2)
+            .unwrap();
+        assert_eq!(transitive, vec!["api", "utils", "app"]);
+
+        let dependencies = workspace
+            .neighbors(api_id, Direction::Dependencies, 1)
+            .unwrap();
+        assert_eq!(dependencies, vec!["core", "utils"]);
+
+        assert!(workspace
+            .neighbors(ProjectId::new(42), Direction::Dependents, 1)
+            .is_none());
+    }
+
+    #[test]
+    pub fn when_iterating_should_yield_every_project_with_its_id() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let dependent_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/dependent").to_owned(),
+                "dependent".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let names: Vec<_> = workspace
+            .iter()
+            .map(|project| project.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["core", "dependent"]);
+
+        let ids: Vec<_> = workspace
+            .iter_with_ids()
+            .map(|(id, project)| (id, project.name.as_str()))
+            .collect();
+        assert_eq!(ids, vec![(core_id, "core"), (dependent_id, "dependent")]);
+    }
+
+    #[test]
+    pub fn when_sorting_topologically_should_put_dependencies_first() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let order = workspace.topological_order().unwrap();
+
+        assert_eq!(order, vec![core_id, app_id]);
+    }
+
+    #[test]
+    pub fn when_arena_contains_cycle_should_return_error() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        workspace.arena[core_id.into_inner()]
+            .as_mut()
+            .unwrap()
+            .dependencies = Some(vec![app_id]);
+        workspace.arena[app_id.into_inner()]
+            .as_mut()
+            .unwrap()
+            .dependents = vec![core_id];
+
+        let error = workspace.topological_order().unwrap_err();
+
+        assert_eq!(
+            error,
+            TopologicalOrderError::CyclicDependencyFound(vec![
+                Path::new("/home/test/core").to_owned(),
+                Path::new("/home/test/app").to_owned(),
+                Path::new("/home/test/core").to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn when_querying_transitive_closures_should_walk_both_directions() {
+        let mut workspace = Workspace::new();
+
+        let core_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/core").to_owned(),
+                "core".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let ui_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/ui").to_owned(),
+                "ui".to_owned(),
+                Some(vec![core_id]),
+            ))
+            .unwrap();
+
+        let app_id = workspace
+            .add_project(Project::new(
+                Path::new("/home/test/app").to_owned(),
+                "app".to_owned(),
+                Some(vec![ui_id]),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            workspace.transitive_dependencies(app_id),
+            Some(vec![core_id, ui_id])
+        );
+        assert_eq!(
+            workspace.transitive_dependents(core_id),
+            Some(vec![ui_id, app_id])
+        );
+        assert_eq!(workspace.transitive_dependents(app_id), Some(vec![]));
+        assert_eq!(workspace.transitive_dependencies(ProjectId::new(42)), None);
+        assert!(workspace.affected_projects().next().is_none());
+
+        workspace.arena[core_id.into_inner()]
+            .as_mut()
+            .unwrap()
+            .dependencies = Some(vec![app_id]);
+
+        assert_eq!(
+            workspace.transitive_dependencies(app_id),
+            Some(vec![core_id, ui_id, app_id])
+        );
+    }
+
+    #[test]
+    pub fn when_resolving_owner_should_choose_deepest_project() {
+        let mut workspace = Workspace::new();
+
+        let outer_id = workspace
+            .add_project(Project::new(
+                Path::new("/repo/libs/a").to_owned(),
+                "a".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        let inner_id = workspace
+            .add_project(Project::new(
+                Path::new("/repo/libs/a/sub").to_owned(),
+                "sub".to_owned(),
+                None,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            workspace.resolve_owner(&"/repo/libs/a/src/lib.rs"),
+            Some(outer_id)
+        );
+        assert_eq!(
+            workspace.resolve_owner(&"/repo/libs/a/sub/src/lib.rs"),
+            Some(inner_id)
+        );
+        assert_eq!(workspace.resolve_owner(&"/repo/libs/a"), Some(outer_id));
+        assert_eq!(workspace.resolve_owner(&"/repo/libs/ab/src/lib.rs"), None);
+        assert_eq!(workspace.resolve_owner(&"/repo/README.md"), None);
+    }
 }